@@ -97,6 +97,20 @@ where
         Ok(())
     }
 
+    /// Read the chip id register. The controller only answers while it's awake so this is only
+    /// reliable shortly after [`Self::setup`] resets it.
+    pub fn read_chip_id(&mut self) -> Result<u8, Error<CommE, PinE>> {
+        let mut chip_id = [0u8; 1];
+        self.i2c
+            .write_read(
+                Self::DEFAULT_I2C_ADDRESS,
+                &[Self::REG_CHIP_ID],
+                &mut chip_id,
+            )
+            .map_err(Error::Comm)?;
+        Ok(chip_id[0])
+    }
+
     pub fn read_truncated_registers(&mut self) -> Result<(), Error<CommE, PinE>> {
         let read_reg = [Self::REG_FIRST; 1];
         self.i2c
@@ -184,6 +198,7 @@ where
 
     /// The first register on the device
     const REG_FIRST: u8 = 0x00;
+    const REG_CHIP_ID: u8 = 0xA7;
 
     /// Header bytes (first three of every register block read)
     // const RESERVED_0_OFF: usize = 0;
@@ -0,0 +1,144 @@
+//! Crash reports that survive a power cycle.
+//!
+//! The panic handler can only paint the panic on screen, and that's gone as soon as the device
+//! is unplugged. So it also appends a compact record to a small region of NVS set aside at boot.
+//! The device loop reports whatever is in there after it next announces itself and clears it
+//! once the coordinator has acked.
+//!
+//! The panic handler doesn't get [`crate::resources::Resources`], and may have interrupted
+//! something holding the flash, so it works from the region's location alone and opens its own
+//! handle to the flash. It avoids the heap too since the panic might be that it ran out.
+use crate::partitions::EspFlashPartition;
+use crate::Instant;
+use alloc::vec::Vec;
+use core::cell::{Cell, RefCell};
+use core::fmt::Write as _;
+use critical_section::Mutex;
+use esp_storage::FlashStorage;
+use frostsnap_comms::{CrashReport, Sha256Digest};
+use frostsnap_embedded::{FlashPartition, NorFlashLog};
+use frostsnap_widgets::string_ext::StringFixed;
+
+/// Long enough for any panic message we write ourselves. Longer ones are cut short.
+const MAX_MESSAGE_LEN: usize = 256;
+const MAX_LOCATION_LEN: usize = 64;
+
+/// `(offset_sector, n_sectors)` of the crash report region and the firmware we're running.
+static REGION: Mutex<Cell<Option<(u32, u32, Sha256Digest)>>> = Mutex::new(Cell::new(None));
+static CONTEXT: Mutex<Cell<Context>> = Mutex::new(Cell::new(Context {
+    uptime_ms: 0,
+    last_workflow: "Startup",
+}));
+
+#[derive(Clone, Copy)]
+struct Context {
+    uptime_ms: u64,
+    last_workflow: &'static str,
+}
+
+/// Encodes exactly as [`CrashReport`] does, but borrows so that nothing is allocated while
+/// panicking.
+#[derive(bincode::Encode)]
+struct CrashRecord<'a> {
+    message: &'a str,
+    location: Option<&'a str>,
+    firmware_digest: Sha256Digest,
+    uptime_ms: u64,
+    last_workflow: &'a str,
+}
+
+/// The crash reports the device has yet to hand over.
+pub struct CrashReports<'a> {
+    partition: EspFlashPartition<'a>,
+}
+
+impl<'a> CrashReports<'a> {
+    /// Start keeping crash reports in `partition` for the firmware with `firmware_digest`.
+    pub fn init(partition: EspFlashPartition<'a>, firmware_digest: Sha256Digest) -> Self {
+        critical_section::with(|cs| {
+            REGION.borrow(cs).set(Some((
+                partition.offset_sector(),
+                partition.n_sectors(),
+                firmware_digest,
+            )))
+        });
+        Self { partition }
+    }
+
+    pub fn pending(&self) -> Vec<CrashReport> {
+        // A record cut short by losing power mid-write won't decode. Everything before it is
+        // still worth reporting.
+        NorFlashLog::new(self.partition)
+            .seek_iter::<CrashReport>()
+            .map_while(Result::ok)
+            .collect()
+    }
+
+    pub fn clear(&self) {
+        self.partition
+            .erase_all()
+            .expect("failed to erase crash reports");
+    }
+}
+
+/// Remember what the device is showing, to go in the report if it crashes.
+pub fn set_last_workflow(last_workflow: &'static str) {
+    critical_section::with(|cs| {
+        let context = CONTEXT.borrow(cs);
+        context.set(Context {
+            last_workflow,
+            ..context.get()
+        })
+    });
+}
+
+/// Remember how long the device has been up, to go in the report if it crashes.
+pub fn set_uptime(now: Instant) {
+    critical_section::with(|cs| {
+        let context = CONTEXT.borrow(cs);
+        context.set(Context {
+            uptime_ms: now.duration_since_epoch().to_millis(),
+            ..context.get()
+        })
+    });
+}
+
+/// Append a record of the panic to the crash report region. Does nothing if the region was never
+/// set up or is full: the first crashes are the interesting ones.
+pub fn record(info: &core::panic::PanicInfo) {
+    // Taking the region means a panic while recording doesn't try to record again.
+    let (region, context) =
+        critical_section::with(|cs| (REGION.borrow(cs).take(), CONTEXT.borrow(cs).get()));
+    let Some((offset_sector, n_sectors, firmware_digest)) = region else {
+        return;
+    };
+
+    let mut message = StringFixed::<MAX_MESSAGE_LEN>::new();
+    let _ = write!(&mut message, "{}", info.message());
+    let mut location = StringFixed::<MAX_LOCATION_LEN>::new();
+    if let Some(panic_location) = info.location() {
+        let _ = write!(
+            &mut location,
+            "{}:{}",
+            panic_location.file().split('/').next_back().unwrap_or(""),
+            panic_location.line()
+        );
+    }
+
+    let flash = RefCell::new(FlashStorage::new());
+    let mut log = NorFlashLog::new(FlashPartition::new(
+        &flash,
+        offset_sector,
+        n_sectors,
+        "crash_reports",
+    ));
+    // seek to the end so we append. Decoding as `()` skips over each entry without allocating.
+    for _ in log.seek_iter::<()>() {}
+    let _ = log.push(CrashRecord {
+        message: message.as_str(),
+        location: info.location().map(|_| location.as_str()),
+        firmware_digest,
+        uptime_ms: context.uptime_ms,
+        last_workflow: context.last_workflow,
+    });
+}
@@ -0,0 +1,42 @@
+//! The checks behind [`CoordinatorSendBody::RunDiagnostics`]. None of them write anything.
+//!
+//! [`CoordinatorSendBody::RunDiagnostics`]: frostsnap_comms::CoordinatorSendBody::RunDiagnostics
+use crate::efuse::EfuseController;
+use crate::partitions::EspFlashPartition;
+use alloc::string::ToString;
+use frostsnap_comms::diagnostics::{EfuseKeyStatus, PartitionCheck};
+
+const CRC: crc::Crc<u32> = crc::Crc::<u32>::new(&crc::CRC_32_ISO_HDLC);
+
+/// Read every sector of `partition`, taking a CRC of what's read if `with_crc`. Don't ask for the
+/// CRC of a partition holding secrets.
+pub fn check_partition(partition: &EspFlashPartition<'_>, with_crc: bool) -> PartitionCheck {
+    let mut digest = CRC.digest();
+    let mut unreadable_sectors = 0;
+    for i in 0..partition.n_sectors() {
+        match partition.read_sector(i) {
+            Ok(sector) => {
+                if with_crc {
+                    digest.update(&sector[..]);
+                }
+            }
+            Err(_) => unreadable_sectors += 1,
+        }
+    }
+
+    PartitionCheck {
+        name: partition.tag.to_string(),
+        size: partition.size(),
+        crc32: with_crc.then(|| digest.finalize()),
+        unreadable_sectors,
+    }
+}
+
+pub fn efuse_key_status(efuse: &EfuseController<'_>) -> EfuseKeyStatus {
+    let discovered = efuse.discover_efuses();
+    EfuseKeyStatus {
+        share_encryption: discovered.share_encryption.map(|key_id| key_id as u8),
+        fixed_entropy: discovered.fixed_entropy.map(|key_id| key_id as u8),
+        ds: discovered.ds.map(|key_id| key_id as u8),
+    }
+}
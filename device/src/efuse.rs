@@ -12,6 +12,9 @@ use reed_solomon;
 const KEY_BLOCKS_OFFSET: u8 = 4;
 const WR_DIS_KEY_OFFSET: u8 = 23;
 const WR_DIS_KP_OFFSET: u8 = 8;
+// SECURE_BOOT_KEY_REVOKE0-2 are bits 85-87 of Block 0, bits 5-7 of byte 10 of the write buffer
+const SECURE_BOOT_KEY_REVOKE_BYTE: usize = 10;
+const SECURE_BOOT_KEY_REVOKE_OFFSET: u8 = 5;
 
 const READ_COMMAND: u16 = 0x5AA5;
 const WRITE_COMMAND: u16 = 0x5A5A;
@@ -127,6 +130,31 @@ impl<'a> EfuseController<'a> {
         self.write_block(&buff, 0)
     }
 
+    /// Burn the revoke bit for Secure Boot digest `digest_index` (0-2). From then on neither the ROM
+    /// nor [`crate::secure_boot`] accept anything signed only by that key. This can't be undone.
+    ///
+    /// This is a second write to Block 0 (see [`Self::write_key_purposes`]) so the buffer holds
+    /// nothing but the one bit.
+    pub fn revoke_secure_boot_digest(&self, digest_index: u8) -> Result<(), EfuseError> {
+        let revoke_field = match digest_index {
+            0 => hal_efuse::SECURE_BOOT_KEY_REVOKE0,
+            1 => hal_efuse::SECURE_BOOT_KEY_REVOKE1,
+            2 => hal_efuse::SECURE_BOOT_KEY_REVOKE2,
+            _ => return Err(EfuseError::EfuseError),
+        };
+        let mut buff = [0x00u8; 32];
+        buff[SECURE_BOOT_KEY_REVOKE_BYTE] = 0x01 << (SECURE_BOOT_KEY_REVOKE_OFFSET + digest_index);
+        unsafe {
+            self.write_block(&buff, 0)?;
+        }
+
+        if Efuse::read_bit(revoke_field) {
+            Ok(())
+        } else {
+            Err(EfuseError::EfuseWriteError(0))
+        }
+    }
+
     /// There should only be one series of calls to set_efuse_key accompanied by write_key_purposes
     unsafe fn set_efuse_key(
         &self,
@@ -301,7 +329,8 @@ impl<'a> EfuseKeyWriter<'a> {
         }
     }
 
-    /// Set whether to read-protect the keys
+    /// Set whether to read-protect the keys. Secure Boot digests never are: the ROM has to be able
+    /// to read them.
     pub fn read_protect(mut self, protect: bool) -> Self {
         self.read_protect = protect;
         self
@@ -345,6 +374,10 @@ impl<'a> EfuseKeyWriter<'a> {
         self
     }
 
+    fn read_protects(&self, purpose: KeyPurpose) -> bool {
+        self.read_protect && !purpose.is_secure_boot_digest()
+    }
+
     /// Write all configured keys and their purposes to efuses
     pub fn write_efuses(self) -> Result<(), EfuseError> {
         // First write all the key values
@@ -358,7 +391,7 @@ impl<'a> EfuseKeyWriter<'a> {
         let configs: Vec<(u8, KeyPurpose, bool)> = self
             .keys
             .iter()
-            .map(|&(key_id, _, purpose)| (key_id as u8, purpose, self.read_protect))
+            .map(|&(key_id, _, purpose)| (key_id as u8, purpose, self.read_protects(purpose)))
             .collect();
 
         unsafe {
@@ -371,7 +404,10 @@ impl<'a> EfuseKeyWriter<'a> {
         // ships. We've seen devices in the field where RD_DIS ended up as 0
         // despite a successful write_key_purposes call.
         if self.read_protect {
-            for &(key_id, _, _) in &self.keys {
+            for &(key_id, _, purpose) in &self.keys {
+                if !self.read_protects(purpose) {
+                    continue;
+                }
                 let readback = self.efuse.read_efuse(key_id)?;
                 if readback != [0u8; 32] {
                     return Err(EfuseError::ReadProtectNotLatched(key_id));
@@ -429,6 +465,27 @@ pub enum KeyPurpose {
     SecureBootDigest2 = 11,
 }
 
+impl KeyPurpose {
+    /// The purpose for Secure Boot digest `digest_index` (0-2).
+    pub fn secure_boot_digest(digest_index: u8) -> Option<Self> {
+        match digest_index {
+            0 => Some(KeyPurpose::SecureBootDigest0),
+            1 => Some(KeyPurpose::SecureBootDigest1),
+            2 => Some(KeyPurpose::SecureBootDigest2),
+            _ => None,
+        }
+    }
+
+    pub fn is_secure_boot_digest(self) -> bool {
+        matches!(
+            self,
+            KeyPurpose::SecureBootDigest0
+                | KeyPurpose::SecureBootDigest1
+                | KeyPurpose::SecureBootDigest2
+        )
+    }
+}
+
 impl TryFrom<u8> for KeyPurpose {
     type Error = ();
 
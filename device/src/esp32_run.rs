@@ -12,6 +12,7 @@ use crate::{
 };
 use alloc::{boxed::Box, collections::VecDeque, string::ToString, vec::Vec};
 use frostsnap_comms::{
+    diagnostics::{DiagnosticReport, NonceSlotUsage},
     CommsMisc, CoordinatorSendBody, CoordinatorUpgradeMessage, DeviceName, DeviceSendBody,
     ReceiveSerial, Sha256Digest, Upstream, MAGIC_BYTES_PERIOD,
 };
@@ -26,8 +27,10 @@ use frostsnap_core::{Gist, Kind};
 use frostsnap_embedded::NonceAbSlot;
 use rand_core::RngCore;
 
+use crate::crash_report::CrashReports;
+use crate::diagnostics;
 use crate::ds::HardwareDs;
-use crate::efuse::EfuseHmacKeys;
+use crate::efuse::{EfuseController, EfuseHmacKeys};
 use crate::frosty_ui::FrostyUi;
 use crate::ota::OtaPartitions;
 use crate::partitions::EspFlashPartition;
@@ -69,6 +72,8 @@ struct DeviceLoop<'a> {
     downstream_serial: &'a mut EspSerial<'a, Downstream>,
     downstream_detect: &'a mut Input<'a, AnyPin>,
     rsa: &'a mut Rsa<'a, Blocking>,
+    efuse: &'a EfuseController<'a>,
+    touch_chip_id: Option<u8>,
 
     // Owned values created during init
     full_nvs: EspFlashPartition<'a>,
@@ -78,6 +83,7 @@ struct DeviceLoop<'a> {
     device_id: DeviceId,
     active_firmware_digest: Sha256Digest,
     upstream_connection: UpstreamConnection,
+    crash_reports: CrashReports<'a>,
 
     // Mutable loop state
     soft_reset: bool,
@@ -90,6 +96,8 @@ struct DeviceLoop<'a> {
     upgrade: Option<ota::FirmwareUpgradeMode<'a>>,
     erase_state: Option<erase::Erase>,
     pending_device_name: Option<DeviceName>,
+    /// Crash reports went out on this connection and can be cleared once their receipt arrives.
+    crash_reports_sent: bool,
 }
 
 impl<'a> DeviceLoop<'a> {
@@ -100,6 +108,8 @@ impl<'a> DeviceLoop<'a> {
         let Resources {
             ref mut rng,
             ref mut hmac_keys,
+            ref efuse,
+            touch_chip_id,
             ds: ref mut hardware_rsa,
             ref certificate,
             ref mut nvs,
@@ -132,8 +142,10 @@ impl<'a> DeviceLoop<'a> {
 
         let share_partition = nvs.split_off_front(2);
 
-        // Keep some space reserved for other potential uses in the future, 8 AB slots
-        let _reserved = nvs.split_off_front(8 * 2);
+        // Keep some space reserved for other potential uses in the future, 8 AB slots. The first
+        // two sectors now hold crash reports.
+        let mut reserved = nvs.split_off_front(8 * 2);
+        let crash_report_partition = reserved.split_off_front(2);
 
         let nonce_slots = {
             let mut n_nonce_sectors = nvs.n_sectors().div_ceil(2);
@@ -166,6 +178,7 @@ impl<'a> DeviceLoop<'a> {
         let (firmware_size, _firmware_and_signature_block_size) =
             active_partition.firmware_size().unwrap();
         let active_firmware_digest = active_partition.sha256_digest(sha256, Some(firmware_size));
+        let crash_reports = CrashReports::init(crash_report_partition, active_firmware_digest);
 
         let device_id = signer.device_id();
 
@@ -196,6 +209,8 @@ impl<'a> DeviceLoop<'a> {
             downstream_serial,
             downstream_detect,
             rsa,
+            efuse,
+            touch_chip_id: *touch_chip_id,
             full_nvs,
             mutation_log,
             signer,
@@ -203,6 +218,7 @@ impl<'a> DeviceLoop<'a> {
             device_id,
             active_firmware_digest,
             upstream_connection,
+            crash_reports,
             soft_reset: true,
             downstream_connection_state: DownstreamConnectionState::Disconnected,
             outbox: VecDeque::new(),
@@ -213,6 +229,7 @@ impl<'a> DeviceLoop<'a> {
             upgrade: None,
             erase_state: None,
             pending_device_name: None,
+            crash_reports_sent: false,
         })
     }
 
@@ -227,6 +244,28 @@ impl<'a> DeviceLoop<'a> {
         self.ui.set_default_workflow(workflow);
     }
 
+    fn run_diagnostics(&mut self) -> DiagnosticReport {
+        let nonce_slots = self.signer.nonce_slots();
+        let nonce_slots = NonceSlotUsage {
+            total: nonce_slots.total_slots() as u32,
+            in_use: nonce_slots.all_stream_ids().count() as u32,
+        };
+        DiagnosticReport {
+            firmware_digest: self.active_firmware_digest,
+            partitions: vec![
+                diagnostics::check_partition(&self.ota_partitions.otadata, true),
+                diagnostics::check_partition(&self.ota_partitions.ota_0, true),
+                diagnostics::check_partition(&self.ota_partitions.ota_1, true),
+                diagnostics::check_partition(&self.full_nvs, false),
+            ],
+            mutation_log: self.mutation_log.fill(),
+            nonce_slots,
+            efuse_keys: diagnostics::efuse_key_status(self.efuse),
+            touch_controller: self.touch_chip_id,
+            secure_boot_enabled: crate::secure_boot::is_secure_boot_enabled(),
+        }
+    }
+
     fn save_pending_device_name(&mut self) -> bool {
         let Some(new_name) = self.pending_device_name.take() else {
             return false;
@@ -256,7 +295,9 @@ impl<'a> DeviceLoop<'a> {
             self.pending_device_name = None;
             self.outbox.clear();
             self.nonce_task_batch = None;
+            self.crash_reports_sent = false;
         }
+        crate::crash_report::set_uptime(self.timer.now());
 
         let is_usb_connected_downstream = !self.downstream_detect.is_high();
 
@@ -547,6 +588,7 @@ impl<'a> DeviceLoop<'a> {
                     } => {
                         let upgrade_ = self.ota_partitions.start_upgrade(
                             *size,
+                            ota::UpgradeEncoding::Raw,
                             *firmware_digest,
                             self.active_firmware_digest,
                         );
@@ -558,6 +600,41 @@ impl<'a> DeviceLoop<'a> {
                     } => {
                         let upgrade_ = self.ota_partitions.start_upgrade(
                             *size,
+                            ota::UpgradeEncoding::Raw,
+                            *firmware_digest,
+                            self.active_firmware_digest,
+                        );
+                        self.upgrade = Some(upgrade_);
+                    }
+                    CoordinatorUpgradeMessage::PrepareUpgradeCompressed {
+                        size,
+                        compressed_size,
+                        firmware_digest,
+                    } => {
+                        let upgrade_ = self.ota_partitions.start_upgrade(
+                            *size,
+                            ota::UpgradeEncoding::Deflate {
+                                compressed_size: *compressed_size,
+                            },
+                            *firmware_digest,
+                            self.active_firmware_digest,
+                        );
+                        self.upgrade = Some(upgrade_);
+                    }
+                    CoordinatorUpgradeMessage::PrepareUpgradeDelta {
+                        size,
+                        base_digest,
+                        patch_size,
+                        compressed_size,
+                        firmware_digest,
+                    } => {
+                        let upgrade_ = self.ota_partitions.start_upgrade(
+                            *size,
+                            ota::UpgradeEncoding::Delta {
+                                base_digest: *base_digest,
+                                patch_size: *patch_size,
+                                compressed_size: *compressed_size,
+                            },
                             *firmware_digest,
                             self.active_firmware_digest,
                         );
@@ -568,6 +645,50 @@ impl<'a> DeviceLoop<'a> {
                 CoordinatorSendBody::DataErase => self
                     .ui
                     .set_workflow(ui::Workflow::prompt(ui::Prompt::EraseDevice)),
+                CoordinatorSendBody::RunDiagnostics => {
+                    self.ui.set_busy_task(ui::BusyTask::Loading);
+                    let report = self.run_diagnostics();
+                    self.ui.clear_busy_task();
+                    self.upstream_connection
+                        .send_to_coordinator([DeviceSendBody::Misc(CommsMisc::DiagnosticReport(
+                            Box::new(report),
+                        ))]);
+                }
+                CoordinatorSendBody::RequestCrashReports => {
+                    let crash_reports = self.crash_reports.pending();
+                    if !crash_reports.is_empty() {
+                        self.upstream_connection
+                            .send_to_coordinator([DeviceSendBody::Misc(CommsMisc::CrashReports(
+                                crash_reports,
+                            ))]);
+                        self.crash_reports_sent = true;
+                    }
+                }
+                CoordinatorSendBody::CrashReportsReceived => {
+                    if core::mem::take(&mut self.crash_reports_sent) {
+                        self.crash_reports.clear();
+                    }
+                }
+                CoordinatorSendBody::RevokeSecureBootKey(revocation) => {
+                    let firmware = self.ota_partitions.active_partition();
+                    let bootloader = crate::partitions::bootloader_partition(firmware);
+                    self.ui.set_busy_task(ui::BusyTask::Loading);
+                    let result = crate::secure_boot::revoke_secure_boot_key(
+                        self.efuse,
+                        revocation,
+                        &[bootloader, firmware],
+                        self.rsa,
+                        self.sha256,
+                    );
+                    self.ui.clear_busy_task();
+                    self.upstream_connection
+                        .send_to_coordinator([DeviceSendBody::Misc(
+                            CommsMisc::SecureBootKeyRevocation {
+                                key_digest: revocation.key_digest,
+                                result,
+                            },
+                        )]);
+                }
                 CoordinatorSendBody::Challenge(challenge) => {
                     if let (Some(hw_rsa), Some(cert)) =
                         (self.hardware_rsa.as_mut(), self.certificate.as_ref())
@@ -648,6 +769,17 @@ impl<'a> DeviceLoop<'a> {
                                 rand_seed,
                             })
                         }
+                        DeviceToUserMessage::VerifyAccount {
+                            key_name,
+                            account,
+                            master_fingerprint,
+                            account_xpub,
+                        } => self.ui.set_workflow(ui::Workflow::DisplayAccount {
+                            key_name,
+                            account,
+                            master_fingerprint,
+                            account_xpub,
+                        }),
                         DeviceToUserMessage::SignatureRequest { phase } => {
                             let rand_seed = self.rng.next_u32();
                             self.ui
@@ -656,6 +788,17 @@ impl<'a> DeviceLoop<'a> {
                                     rand_seed,
                                 }));
                         }
+                        DeviceToUserMessage::SilentPaymentEcdh { phase } => {
+                            self.ui.set_workflow(ui::Workflow::prompt(
+                                ui::Prompt::SilentPaymentEcdh { phase },
+                            ));
+                        }
+                        DeviceToUserMessage::AddressBook { phase } => {
+                            self.ui
+                                .set_workflow(ui::Workflow::prompt(ui::Prompt::AddressBook {
+                                    phase,
+                                }));
+                        }
                         DeviceToUserMessage::Restoration(to_user_restoration) => {
                             use frostsnap_core::device::restoration::ToUserRestoration::*;
                             match *to_user_restoration {
@@ -700,6 +843,30 @@ impl<'a> DeviceLoop<'a> {
                                         rand_seed,
                                     });
                                 }
+                                ExportShare {
+                                    key_name,
+                                    to: _,
+                                    phase,
+                                } => {
+                                    self.ui.set_workflow(ui::Workflow::prompt(
+                                        ui::Prompt::ExportShare {
+                                            key_name,
+                                            phase: Box::new(phase),
+                                        },
+                                    ));
+                                }
+                                ImportShare {
+                                    key_name,
+                                    from: _,
+                                    phase,
+                                } => {
+                                    self.ui.set_workflow(ui::Workflow::prompt(
+                                        ui::Prompt::ImportShare {
+                                            key_name,
+                                            phase: Box::new(phase),
+                                        },
+                                    ));
+                                }
                                 ConsolidateBackup(phase) => {
                                     // Auto-confirm: the user can't meaningfully verify this
                                     self.outbox.extend(self.signer.finish_consolidation(
@@ -741,6 +908,30 @@ impl<'a> DeviceLoop<'a> {
                             .expect("state changed while acking sign"),
                     );
                 }
+                UiEvent::SilentPaymentEcdhConfirm { phase } => {
+                    self.outbox.extend(
+                        self.signer
+                            .silent_payment_ecdh_ack(*phase, &mut self.hmac_keys.share_encryption)
+                            .expect("state changed while confirming silent payment"),
+                    );
+                }
+                UiEvent::AddressBookConfirm { phase } => {
+                    self.outbox.extend(self.signer.address_book_ack(*phase));
+                }
+                UiEvent::ShareExportConfirm { phase } => {
+                    self.outbox.extend(
+                        self.signer
+                            .export_share_ack(
+                                *phase,
+                                &mut self.hmac_keys.share_encryption,
+                                self.rng,
+                            )
+                            .expect("state changed while confirming share export"),
+                    );
+                }
+                UiEvent::ShareImportConfirm { phase } => {
+                    self.outbox.extend(self.signer.import_share_ack(*phase));
+                }
                 UiEvent::BackupRecorded => {
                     self.upstream_connection
                         .send_to_coordinator([DeviceSendBody::Misc(CommsMisc::BackupRecorded)]);
@@ -12,7 +12,11 @@ use frostsnap_comms::{factory::*, ReceiveSerial};
 use frostsnap_embedded::ABWRITE_BINCODE_CONFIG;
 use rand_core::{RngCore, SeedableRng};
 
-use crate::{efuse::EfuseKeyWriter, io::SerialInterface};
+use crate::{
+    efuse::{EfuseKeyWriter, KeyPurpose},
+    io::SerialInterface,
+};
+use esp_hal::hmac::KeyId;
 
 /// Configuration for device provisioning
 pub struct ProvisioningConfig {
@@ -175,6 +179,12 @@ pub fn run_factory_provisioning(
     upstream.send(DeviceFactorySend::ReceivedDsKey).unwrap();
     text_display!(&mut display, "Received DS key");
 
+    // Receive backup secure boot key digests
+    let backup_secure_boot_digests = read_message!(upstream, FactorySend::SetSecureBootDigests);
+    upstream
+        .send(DeviceFactorySend::ReceivedSecureBootDigests)
+        .unwrap();
+
     // Receive certificate
     let certificate = read_message!(upstream, FactorySend::SetGenuineCertificate);
 
@@ -203,18 +213,38 @@ pub fn run_factory_provisioning(
     factory_rng.fill_bytes(&mut user_key);
 
     // Burn EFUSES with configurable read protection
-    EfuseKeyWriter::new(&efuse)
+    let mut efuse_writer = EfuseKeyWriter::new(&efuse)
         .read_protect(config.read_protect)
         .add_encryption_key(share_encryption_key)
         .add_entropy_key(factory_entropy)
-        .add_ds_key(ds_hmac_key)
-        .add_key(
-            esp_hal::hmac::KeyId::Key5,
-            user_key,
-            crate::efuse::KeyPurpose::HmacUpstream,
-        )
-        .write_efuses()
-        .unwrap();
+        .add_ds_key(ds_hmac_key);
+
+    // The bootloader burned the digest of the key it was signed with on first boot (into Key0).
+    // The backup digests go in the slots we have spare.
+    let burned = crate::secure_boot::secure_boot_key_slots();
+    let mut spare_digest_indexes =
+        (0..3u8).filter(|index| !burned.iter().any(|slot| slot.digest_index == *index));
+    let mut spare_key_ids = [KeyId::Key1, KeyId::Key5]
+        .into_iter()
+        .filter(|key_id| !efuse.is_key_written(*key_id));
+    for digest in backup_secure_boot_digests {
+        if burned.iter().any(|slot| slot.digest == digest) {
+            continue;
+        }
+        let (key_id, digest_index) = spare_key_ids
+            .next()
+            .zip(spare_digest_indexes.next())
+            .expect("no eFuse key slot left for a backup secure boot digest");
+        efuse_writer = efuse_writer.add_key(
+            key_id,
+            digest,
+            KeyPurpose::secure_boot_digest(digest_index).unwrap(),
+        );
+    }
+    if spare_key_ids.any(|key_id| key_id == KeyId::Key5) {
+        efuse_writer = efuse_writer.add_key(KeyId::Key5, user_key, KeyPurpose::HmacUpstream);
+    }
+    efuse_writer.write_efuses().unwrap();
 
     text_display!(
         &mut display,
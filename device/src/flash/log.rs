@@ -100,6 +100,14 @@ impl<'a, S: NorFlash> MutationLog<'a, S> {
         )
     }
 
+    /// How much of the log is taken. The share slot is left out since it's always the same size.
+    pub fn fill(&self) -> frostsnap_comms::diagnostics::StorageFill {
+        frostsnap_comms::diagnostics::StorageFill {
+            used_bytes: self.log.used_bytes(),
+            capacity_bytes: self.log.capacity_bytes(),
+        }
+    }
+
     pub fn append(
         &mut self,
         iter: impl IntoIterator<Item = Mutation>,
@@ -2,6 +2,7 @@ use crate::DISPLAY_REFRESH_MS;
 use alloc::{boxed::Box, string::ToString};
 use embedded_graphics::prelude::*;
 use esp_hal::prelude::*;
+use frostsnap_core::Kind;
 use frostsnap_cst816s::interrupt::TouchReceiver;
 use frostsnap_widgets::palette::PALETTE;
 use frostsnap_widgets::{
@@ -114,6 +115,7 @@ impl<'a> UserInteraction for FrostyUi<'a> {
     }
 
     fn set_workflow(&mut self, workflow: Workflow) {
+        crate::crash_report::set_last_workflow(workflow.kind());
         // Check if we can update the current widget instead of switching
         let current_widget = self.widget.inner_mut().current_mut();
 
@@ -206,6 +208,14 @@ impl<'a> UserInteraction for FrostyUi<'a> {
                 Prompt::Signing { phase, rand_seed } => {
                     WidgetTree::build_signing_prompt(phase, rand_seed)
                 }
+                Prompt::SilentPaymentEcdh { phase } => WidgetTree::build_silent_payment_ecdh(phase),
+                Prompt::AddressBook { phase } => WidgetTree::build_address_book(phase),
+                Prompt::ExportShare { key_name, phase } => {
+                    WidgetTree::build_share_export(key_name, phase)
+                }
+                Prompt::ImportShare { key_name, phase } => {
+                    WidgetTree::build_share_import(key_name, phase)
+                }
                 Prompt::ConfirmFirmwareUpgrade {
                     firmware_digest,
                     size,
@@ -239,6 +249,18 @@ impl<'a> UserInteraction for FrostyUi<'a> {
                 rand_seed,
             } => WidgetTree::build_display_address(address, bip32_path, rand_seed),
 
+            Workflow::DisplayAccount {
+                key_name,
+                account,
+                master_fingerprint,
+                account_xpub,
+            } => WidgetTree::build_display_account(
+                &key_name,
+                account,
+                master_fingerprint,
+                &account_xpub,
+            ),
+
             Workflow::FirmwareUpgrade(status) => {
                 use crate::ui::FirmwareUpgradeStatus;
 
@@ -316,7 +338,9 @@ impl<'a> UserInteraction for FrostyUi<'a> {
                     self.go_to_default();
                 }
             }
-            WidgetTree::SignTestPrompt { widget, phase } => {
+            WidgetTree::SignTestPrompt { widget, phase }
+            | WidgetTree::SignProofOfReservesPrompt { widget, phase }
+            | WidgetTree::SignAddressBookEntryPrompt { widget, phase } => {
                 if widget.is_confirmed() {
                     if let Some(phase_data) = phase.take() {
                         return Some(UiEvent::SigningConfirm { phase: phase_data });
@@ -326,6 +350,45 @@ impl<'a> UserInteraction for FrostyUi<'a> {
                     self.go_to_default();
                 }
             }
+            WidgetTree::SilentPaymentEcdhPrompt { widget, phase } => {
+                if widget.is_confirmed() {
+                    if let Some(phase_data) = phase.take() {
+                        return Some(UiEvent::SilentPaymentEcdhConfirm { phase: phase_data });
+                    }
+                }
+                if phase.is_none() && widget.is_finished() {
+                    self.go_to_default();
+                }
+            }
+            WidgetTree::AddressBookPrompt { widget, phase } => {
+                if widget.is_confirmed() {
+                    if let Some(phase_data) = phase.take() {
+                        return Some(UiEvent::AddressBookConfirm { phase: phase_data });
+                    }
+                }
+                if phase.is_none() && widget.is_finished() {
+                    self.go_to_default();
+                }
+            }
+            WidgetTree::ShareExportPrompt { widget, phase } => {
+                if widget.is_confirmed() {
+                    if let Some(phase_data) = phase.take() {
+                        return Some(UiEvent::ShareExportConfirm { phase: phase_data });
+                    }
+                }
+                if phase.is_none() && widget.is_finished() {
+                    self.go_to_default();
+                }
+            }
+            // Stays on the code after confirming since the sending device only shows its own once
+            // we have.
+            WidgetTree::ShareImportPrompt { widget, phase } => {
+                if widget.is_confirmed() {
+                    if let Some(phase_data) = phase.take() {
+                        return Some(UiEvent::ShareImportConfirm { phase: phase_data });
+                    }
+                }
+            }
             WidgetTree::FirmwareUpgradeConfirm {
                 widget, confirmed, ..
             } if widget.is_confirmed() && !*confirmed => {
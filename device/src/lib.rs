@@ -54,7 +54,9 @@ macro_rules! log_and_redraw {
     }};
 }
 
+pub mod crash_report;
 pub mod device_config;
+pub mod diagnostics;
 pub mod ds;
 pub mod efuse;
 pub mod erase;
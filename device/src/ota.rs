@@ -12,6 +12,8 @@ use esp_hal::time::Duration;
 use esp_hal::timer;
 use esp_hal::Blocking;
 use frostsnap_comms::{
+    firmware_compression::{Inflater, SectorWriter},
+    firmware_delta::PatchApplier,
     firmware_version, CommsMisc, DeviceSendBody, Sha256Digest, BAUDRATE,
     FIRMWARE_NEXT_CHUNK_READY_SIGNAL, FIRMWARE_UPGRADE_CHUNK_LEN,
 };
@@ -85,6 +87,141 @@ fn decide_upgrade(
     UpgradeVerdict::Commit
 }
 
+/// How the coordinator is going to send the image.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UpgradeEncoding {
+    Raw,
+    /// Raw DEFLATE, `compressed_size` bytes of it.
+    Deflate {
+        compressed_size: u32,
+    },
+    /// A patch against the `base_digest` firmware, `patch_size` bytes of it, sent as raw DEFLATE
+    /// like [`UpgradeEncoding::Deflate`].
+    Delta {
+        base_digest: Sha256Digest,
+        patch_size: u32,
+        compressed_size: u32,
+    },
+}
+
+impl UpgradeEncoding {
+    /// The number of bytes that will come over the wire for an image of `size`.
+    fn stream_size(self, size: u32) -> u32 {
+        match self {
+            UpgradeEncoding::Raw => size,
+            UpgradeEncoding::Deflate { compressed_size }
+            | UpgradeEncoding::Delta {
+                compressed_size, ..
+            } => compressed_size,
+        }
+    }
+}
+
+/// Turns the stream into the image in the partition, whatever its encoding.
+enum ImageWriter<'a> {
+    Raw {
+        sector: u32,
+    },
+    Deflate {
+        inflater: Inflater,
+        sectors: SectorWriter,
+    },
+    Delta {
+        inflater: Inflater,
+        applier: PatchApplier,
+        /// The running image the patch is against.
+        base: EspFlashPartition<'a>,
+    },
+    /// The stream can't make the image, so the rest of it is ignored.
+    Broken,
+}
+
+impl<'a> ImageWriter<'a> {
+    fn new(
+        size: u32,
+        encoding: UpgradeEncoding,
+        active_digest: Sha256Digest,
+        active_partition: EspFlashPartition<'a>,
+    ) -> Self {
+        match encoding {
+            UpgradeEncoding::Raw => ImageWriter::Raw { sector: 0 },
+            UpgradeEncoding::Deflate { .. } => ImageWriter::Deflate {
+                inflater: Inflater::new(size),
+                sectors: SectorWriter::new(),
+            },
+            UpgradeEncoding::Delta {
+                base_digest,
+                patch_size,
+                ..
+            } => {
+                if base_digest == active_digest {
+                    ImageWriter::Delta {
+                        inflater: Inflater::new(patch_size),
+                        applier: PatchApplier::new(size),
+                        base: active_partition,
+                    }
+                } else {
+                    // a patch for firmware we're not running can only make garbage
+                    ImageWriter::Broken
+                }
+            }
+        }
+    }
+
+    /// Write the next `len` bytes of the stream. `chunk` is padded with `0xff` past `len`.
+    fn write(
+        &mut self,
+        chunk: &[u8; SECTOR_SIZE as usize],
+        len: usize,
+        partition: &EspFlashPartition<'_>,
+    ) {
+        let mut write_sector = |sector, bytes: &_| partition.nor_write_sector(sector, bytes);
+        let result = match self {
+            ImageWriter::Raw { sector } => {
+                partition.nor_write_sector(*sector, chunk).unwrap();
+                *sector += 1;
+                Ok(())
+            }
+            ImageWriter::Deflate { inflater, sectors } => inflater
+                .push(&chunk[..len], |bytes| {
+                    sectors.write(bytes, &mut write_sector)
+                })
+                .map_err(|_| ()),
+            ImageWriter::Delta {
+                inflater,
+                applier,
+                base,
+            } => {
+                let mut read_base = |offset, buf: &mut [u8]| base.read(offset, buf);
+                inflater
+                    .push(&chunk[..len], |bytes| {
+                        applier.push(bytes, &mut read_base, &mut write_sector)
+                    })
+                    .map_err(|_| ())
+            }
+            ImageWriter::Broken => Ok(()),
+        };
+        if result.is_err() {
+            *self = ImageWriter::Broken;
+        }
+    }
+
+    /// Call after the last of the stream. Returns whether the whole image was written.
+    fn finish(self, partition: &EspFlashPartition<'_>) -> bool {
+        let mut write_sector = |sector, bytes: &_| partition.nor_write_sector(sector, bytes);
+        match self {
+            ImageWriter::Raw { .. } => true,
+            ImageWriter::Deflate { inflater, sectors } => {
+                inflater.finish::<()>().is_ok() && sectors.finish(&mut write_sector).is_ok()
+            }
+            ImageWriter::Delta {
+                inflater, applier, ..
+            } => inflater.finish::<()>().is_ok() && applier.finish(&mut write_sector).is_ok(),
+            ImageWriter::Broken => false,
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct OtaPartitions<'a> {
     pub otadata: EspFlashPartition<'a>,
@@ -220,6 +357,7 @@ impl<'a> OtaPartitions<'a> {
     pub fn start_upgrade(
         &self,
         size: u32,
+        encoding: UpgradeEncoding,
         expected_digest: Sha256Digest,
         active_partition_digest: Sha256Digest,
     ) -> FirmwareUpgradeMode<'a> {
@@ -236,7 +374,7 @@ impl<'a> OtaPartitions<'a> {
 
         if expected_digest == active_partition_digest {
             FirmwareUpgradeMode::Passive {
-                size,
+                stream_size: encoding.stream_size(size),
                 sent_ack: false,
             }
         } else {
@@ -244,7 +382,9 @@ impl<'a> OtaPartitions<'a> {
                 ota: self.clone(),
                 ota_slot: slot,
                 expected_digest,
+                active_digest: active_partition_digest,
                 size,
+                encoding,
                 state: State::WaitingForConfirm { sent_prompt: false },
             }
         }
@@ -257,11 +397,15 @@ pub enum FirmwareUpgradeMode<'a> {
         ota: OtaPartitions<'a>,
         ota_slot: usize,
         expected_digest: Sha256Digest,
+        /// What we're running, which a delta has to be against.
+        active_digest: Sha256Digest,
         size: u32,
+        encoding: UpgradeEncoding,
         state: State,
     },
     Passive {
-        size: u32,
+        /// What we have to forward, however it's encoded.
+        stream_size: u32,
         sent_ack: bool,
     },
 }
@@ -292,6 +436,7 @@ impl FirmwareUpgradeMode<'_> {
                 expected_digest,
                 size,
                 state,
+                ..
             } => {
                 let partition = ota.ota_partitions()[*ota_slot];
                 match state {
@@ -386,9 +531,26 @@ impl FirmwareUpgradeMode<'_> {
             FirmwareUpgradeMode::Passive { .. } => { /* always ready to enter upgrade mode */ }
         }
 
-        let upgrade_size = match *self {
-            FirmwareUpgradeMode::Upgrading { size, .. } => size,
-            FirmwareUpgradeMode::Passive { size, .. } => size,
+        let stream_size = match *self {
+            FirmwareUpgradeMode::Upgrading { size, encoding, .. } => encoding.stream_size(size),
+            FirmwareUpgradeMode::Passive { stream_size, .. } => stream_size,
+        };
+        // We keep reading and forwarding a bad stream to the end so the rest of the chain isn't
+        // left waiting. It's refused then.
+        let mut writer = match *self {
+            FirmwareUpgradeMode::Upgrading {
+                size,
+                encoding,
+                active_digest,
+                ref ota,
+                ..
+            } => Some(ImageWriter::new(
+                size,
+                encoding,
+                active_digest,
+                ota.active_partition(),
+            )),
+            FirmwareUpgradeMode::Passive { .. } => None,
         };
 
         upstream_io.change_baud(OTA_UPDATE_BAUD);
@@ -405,7 +567,6 @@ impl FirmwareUpgradeMode<'_> {
         let mut in_buf = Box::new([0xffu8; SECTOR_SIZE as usize]);
         let mut i = 0;
         let mut byte_count = 0;
-        let mut sector = 0;
 
         let mut finished_writing = false;
         let mut downstream_ready = downstream_io.is_none();
@@ -417,7 +578,7 @@ impl FirmwareUpgradeMode<'_> {
                     in_buf[i] = byte;
                     i += 1;
                     byte_count += 1;
-                    finished_writing = byte_count == upgrade_size;
+                    finished_writing = byte_count == stream_size;
                     if let Some(downstream_io) = &mut downstream_io {
                         block!(downstream_io.write_byte_nb(byte)).unwrap();
                     }
@@ -428,26 +589,22 @@ impl FirmwareUpgradeMode<'_> {
                         downstream_ready = downstream_io.is_none();
                         // likewise the upstream device assumes we're not ready
                         told_upstream_im_ready = false;
+                        let chunk_len = i;
                         i = 0;
                         // only write to the partition if we're actually upgrading
-                        if let FirmwareUpgradeMode::Upgrading {
-                            ota_slot,
-                            ota,
-                            size,
-                            ..
-                        } = &self
-                        {
+                        if let FirmwareUpgradeMode::Upgrading { ota_slot, ota, .. } = &self {
                             let partition = ota.ota_partitions()[*ota_slot];
-                            partition.nor_write_sector(sector, &in_buf).unwrap();
+                            if let Some(writer) = &mut writer {
+                                writer.write(&in_buf, chunk_len, &partition);
+                            }
                             ui.set_workflow(ui::Workflow::FirmwareUpgrade(
                                 ui::FirmwareUpgradeStatus::Download {
-                                    progress: byte_count as f32 / *size as f32,
+                                    progress: byte_count as f32 / stream_size as f32,
                                 },
                             ));
                             ui.poll();
                         }
                         in_buf.fill(0xff);
-                        sector += 1;
                     }
                 }
             }
@@ -493,11 +650,20 @@ impl FirmwareUpgradeMode<'_> {
         {
             let partition = &ota.ota_partitions()[*ota_slot];
 
+            let stream_corrupt = !writer.is_some_and(|writer| writer.finish(partition));
+
             // The coordinator driving this is untrusted, so acceptance is enforced
             // here, against the bytes actually written. Any refusal leaves the active
             // slot unchanged and returns Rejected; the running firmware survives and
             // boots again on the power cycle the refusal screen asks for.
-            match decide_upgrade(partition, expected_digest, sha, rsa) {
+            let verdict = if stream_corrupt {
+                // Whatever made it into the partition isn't the image, so there's nothing
+                // to check.
+                UpgradeVerdict::Refuse(RefuseReason::DigestMismatch)
+            } else {
+                decide_upgrade(partition, expected_digest, sha, rsa)
+            };
+            match verdict {
                 UpgradeVerdict::Commit => ota.switch_partition(*ota_slot, OtaMetadata {}),
                 UpgradeVerdict::Refuse(reason) => {
                     ui.set_workflow(ui::Workflow::FirmwareUpgrade(
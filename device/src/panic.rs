@@ -23,6 +23,9 @@ pub fn handle_panic(info: &core::panic::PanicInfo) -> ! {
         critical_section::acquire();
     }
 
+    // Before touching the display so the record survives even if drawing panics again.
+    crate::crash_report::record(info);
+
     let mut peripherals = unsafe { Peripherals::steal() };
 
     let mut bl = Output::new(&mut peripherals.GPIO1, Level::Low);
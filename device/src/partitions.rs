@@ -9,6 +9,17 @@ use frostsnap_embedded::FlashPartition;
 
 pub type EspFlashPartition<'a> = FlashPartition<'a, FlashStorage>;
 
+const PARTITION_TABLE_OFFSET: u32 = 0xd000;
+
+/// The bootloader runs from the start of flash up to the partition table. `on_flash` is any
+/// partition of the same flash.
+pub fn bootloader_partition(on_flash: EspFlashPartition<'_>) -> EspFlashPartition<'_> {
+    let mut bootloader = on_flash;
+    bootloader.tag = "bootloader";
+    bootloader.set_offset_and_size(0, PARTITION_TABLE_OFFSET);
+    bootloader
+}
+
 #[derive(Clone)]
 pub struct Partitions<'a> {
     pub factory_cert: EspFlashPartition<'a>,
@@ -30,7 +41,7 @@ impl<'a> Partitions<'a> {
     }
 
     pub fn load(flash: &'a RefCell<FlashStorage>) -> Self {
-        let table = esp_partition_table::PartitionTable::new(0xd000, 10 * 32);
+        let table = esp_partition_table::PartitionTable::new(PARTITION_TABLE_OFFSET, 10 * 32);
 
         let mut self_ = Self::new(flash);
         for row in table.iter_storage(&mut *flash.borrow_mut(), false) {
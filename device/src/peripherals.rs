@@ -114,6 +114,9 @@ pub struct DevicePeripherals<'a> {
     /// Touch receiver for interrupt-based touch handling
     pub touch_receiver: frostsnap_cst816s::interrupt::TouchReceiver,
 
+    /// What the touch controller answered when asked for its chip id at boot
+    pub touch_chip_id: Option<u8>,
+
     /// Display backlight
     pub backlight: channel::Channel<'a, LowSpeed>,
 
@@ -258,6 +261,9 @@ impl<'a> DevicePeripherals<'a> {
 
         let mut capsense = CST816S::new_esp32(i2c, &mut peripherals.GPIO2, &mut peripherals.GPIO3);
         capsense.setup(&mut delay).unwrap();
+        // Ask now while it's just been reset and is still awake. It goes to sleep and stops
+        // answering until it's touched.
+        let touch_chip_id = capsense.read_chip_id().ok();
 
         // Register the capsense instance with the interrupt handler
         let touch_receiver = frostsnap_cst816s::interrupt::register(capsense, &mut io);
@@ -300,6 +306,7 @@ impl<'a> DevicePeripherals<'a> {
             ui_timer,
             display,
             touch_receiver,
+            touch_chip_id,
             backlight,
             uart_upstream,
             uart_downstream,
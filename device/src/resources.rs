@@ -8,7 +8,7 @@ use rand_chacha::ChaCha20Rng;
 
 use crate::{
     ds::HardwareDs,
-    efuse::EfuseHmacKeys,
+    efuse::{EfuseController, EfuseHmacKeys},
     flash::VersionedFactoryData,
     frosty_ui::FrostyUi,
     io::SerialInterface,
@@ -38,6 +38,12 @@ pub struct Resources<'a> {
     /// HMAC keys from efuses
     pub hmac_keys: EfuseHmacKeys<'a>,
 
+    /// eFuse controller, kept for diagnostics
+    pub efuse: EfuseController<'a>,
+
+    /// What the touch controller answered when asked for its chip id at boot
+    pub touch_chip_id: Option<u8>,
+
     /// Hardware Ds for attestation (None for dev devices)
     pub ds: Option<HardwareDs<'a>>,
 
@@ -113,6 +119,7 @@ impl<'a> Resources<'a> {
             ui_timer,
             display,
             touch_receiver,
+            touch_chip_id,
             sha256,
             ds,
             rsa,
@@ -158,6 +165,8 @@ impl<'a> Resources<'a> {
         Box::new(Self {
             rng,
             hmac_keys,
+            efuse,
+            touch_chip_id,
             ds,
             rsa,
             certificate,
@@ -191,6 +200,7 @@ impl<'a> Resources<'a> {
             ui_timer,
             display,
             touch_receiver,
+            touch_chip_id,
             sha256,
             ds,
             rsa,
@@ -239,6 +249,8 @@ impl<'a> Resources<'a> {
         Box::new(Self {
             rng,
             hmac_keys,
+            efuse,
+            touch_chip_id,
             ds,
             certificate,
             rsa,
@@ -1,4 +1,5 @@
 extern crate alloc;
+use crate::efuse::EfuseController;
 use crate::partitions::EspFlashPartition;
 use alloc::boxed::Box;
 use alloc::{vec, vec::Vec};
@@ -9,6 +10,8 @@ use esp_hal::rsa::{operand_sizes::Op3072, Rsa, RsaModularExponentiation};
 use esp_hal::sha::{Sha, Sha256};
 use esp_hal::Blocking;
 use frostsnap_comms::firmware_reader::SECTOR_SIZE;
+use frostsnap_comms::secure_boot::{revocation_digest, RevocationRefused, SecureBootKeyRevocation};
+use frostsnap_comms::{MAX_SIGNATURE_BLOCKS, SIGNATURE_BLOCK_LEN, SIGNATURE_BLOCK_MAGIC};
 
 use nb::block;
 
@@ -35,8 +38,22 @@ struct SignatureBlock {
     crc32: [u8; 4],
 }
 
+const CRC: Crc<u32> = Crc::<u32>::new(&crc::CRC_32_ISO_HDLC);
+
 impl SignatureBlock {
-    fn from_bytes(data: &[u8; SECTOR_SIZE]) -> Self {
+    /// Parse a block, checking its CRC32 (calculated over the first 1196 bytes).
+    fn parse(data: &[u8; SIGNATURE_BLOCK_LEN]) -> Result<Self, SecureBootError<'static>> {
+        if data[0..4] != SIGNATURE_BLOCK_MAGIC {
+            return Err(SecureBootError::MissingSignature);
+        }
+        let block = Self::from_bytes(data);
+        if CRC.checksum(&data[0..1196]) != u32::from_le_bytes(block.crc32) {
+            return Err(SecureBootError::ChecksumInvalid);
+        }
+        Ok(block)
+    }
+
+    fn from_bytes(data: &[u8; SIGNATURE_BLOCK_LEN]) -> Self {
         let mut block = SignatureBlock {
             image_digest: [0; 32],
             rsa_public_modulus: [0; 384],
@@ -229,8 +246,18 @@ fn compute_sha256_hardware(sha: &mut Sha, data: &[u8]) -> [u8; 32] {
     result
 }
 
-// Find secure boot key digest from eFuse by checking KEY_PURPOSE fields
-fn find_secure_boot_key() -> Option<[u8; 32]> {
+/// A Secure Boot key digest burned into eFuse.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SecureBootKeySlot {
+    /// Which of `SECURE_BOOT_DIGEST0-2` the digest was burned as, which is also which revoke bit
+    /// applies to it.
+    pub digest_index: u8,
+    pub digest: [u8; 32],
+    pub revoked: bool,
+}
+
+/// Read the secure boot key digests from eFuse by checking KEY_PURPOSE fields
+pub fn secure_boot_key_slots() -> Vec<SecureBootKeySlot> {
     use esp_hal::efuse::{
         KEY0, KEY1, KEY2, KEY3, KEY4, KEY5, KEY_PURPOSE_0, KEY_PURPOSE_1, KEY_PURPOSE_2,
         KEY_PURPOSE_3, KEY_PURPOSE_4, KEY_PURPOSE_5, SECURE_BOOT_KEY_REVOKE0,
@@ -253,30 +280,39 @@ fn find_secure_boot_key() -> Option<[u8; 32]> {
     ];
     let key_data_fields = [KEY0, KEY1, KEY2, KEY3, KEY4, KEY5];
 
+    let mut slots = Vec::new();
     // Search through all key blocks
     for (i, &purpose_field) in key_purpose_fields.iter().enumerate() {
         let purpose: u8 = Efuse::read_field_le(purpose_field);
 
         // Find matching secure boot digest revoke field
-        if let Some((_, revoke_field)) = secure_boot_digests
+        if let Some((digest_index, (_, revoke_field))) = secure_boot_digests
             .iter()
-            .find(|(purpose_val, _)| *purpose_val == purpose)
+            .enumerate()
+            .find(|(_, (purpose_val, _))| *purpose_val == purpose)
         {
-            // Check if this key is revoked
-            let is_revoked = Efuse::read_bit(*revoke_field);
-            if !is_revoked {
+            slots.push(SecureBootKeySlot {
+                digest_index: digest_index as u8,
                 // Read the key data (32 bytes)
-                let key_data: [u8; 32] = Efuse::read_field_le(key_data_fields[i]);
-                return Some(key_data);
-            }
+                digest: Efuse::read_field_le(key_data_fields[i]),
+                revoked: Efuse::read_bit(*revoke_field),
+            });
         }
     }
-    None
+    slots
 }
 
-/// Check if secure boot is enabled by looking for secure boot key digests in eFuse
+fn trusted_key_digests(slots: &[SecureBootKeySlot]) -> Vec<[u8; 32]> {
+    slots
+        .iter()
+        .filter(|slot| !slot.revoked)
+        .map(|slot| slot.digest)
+        .collect()
+}
+
+/// Check if secure boot is enabled by looking for unrevoked secure boot key digests in eFuse
 pub fn is_secure_boot_enabled() -> bool {
-    find_secure_boot_key().is_some()
+    secure_boot_key_slots().iter().any(|slot| !slot.revoked)
 }
 
 fn read_signature_sector(partition: &EspFlashPartition) -> Option<(u32, Box<[u8; SECTOR_SIZE]>)> {
@@ -285,48 +321,83 @@ fn read_signature_sector(partition: &EspFlashPartition) -> Option<(u32, Box<[u8;
     Some((sector_idx, sector_data))
 }
 
+/// Check the image in `app_partition` is signed by one of the Secure Boot keys burned into eFuse
+/// that hasn't been revoked.
 pub fn verify_secure_boot<'a>(
     app_partition: &EspFlashPartition,
     rsa: &mut Rsa<'_, Blocking>,
     sha: &mut Sha,
 ) -> Result<(), SecureBootError<'a>> {
-    let (signature_sector_index, signature_block) =
-        read_signature_sector(app_partition).ok_or(SecureBootError::MissingSignature)?;
-
-    // Parse signature block structure
-    let parsed_block = SignatureBlock::from_bytes(&signature_block);
-
-    // Verify CRC32 checksum
-    const CRC: Crc<u32> = Crc::<u32>::new(&crc::CRC_32_ISO_HDLC);
-    // CRC32 is calculated over first 1196 bytes
-    let calculated_crc = CRC.checksum(&signature_block[0..1196]);
-    let stored_crc = u32::from_le_bytes(parsed_block.crc32);
-    if calculated_crc != stored_crc {
-        return Err(SecureBootError::ChecksumInvalid);
+    let trusted = trusted_key_digests(&secure_boot_key_slots());
+    verify_signed_by_any(app_partition, rsa, sha, &trusted)
+}
+
+/// Like the ROM, accept the image if any one of its signature blocks is by a key in `trusted` and
+/// checks out.
+fn verify_signed_by_any<'a>(
+    partition: &EspFlashPartition,
+    rsa: &mut Rsa<'_, Blocking>,
+    sha: &mut Sha,
+    trusted: &[[u8; 32]],
+) -> Result<(), SecureBootError<'a>> {
+    if trusted.is_empty() {
+        return Err(SecureBootError::EfuseError);
     }
 
-    // Verify public key digest against eFuse FIRST
-    // Find the secure boot key digest from eFuse by checking KEY_PURPOSE fields
-    let efuse_key_digest = match find_secure_boot_key() {
-        Some(key_digest) => key_digest,
-        None => return Err(SecureBootError::EfuseError),
-    };
+    let (signature_sector_index, signature_sector) =
+        read_signature_sector(partition).ok_or(SecureBootError::MissingSignature)?;
+
+    let mut image_digest = None;
+    let mut result = Err(SecureBootError::PublicKeyInvalid);
+    for block in signature_sector
+        .chunks_exact(SIGNATURE_BLOCK_LEN)
+        .take(MAX_SIGNATURE_BLOCKS)
+    {
+        if block[0..4] != SIGNATURE_BLOCK_MAGIC {
+            break;
+        }
+        let block: &[u8; SIGNATURE_BLOCK_LEN] = block.try_into().unwrap();
+        let parsed_block = match SignatureBlock::parse(block) {
+            Ok(parsed_block) => parsed_block,
+            Err(e) => {
+                result = Err(e);
+                continue;
+            }
+        };
 
-    // Calculate SHA-256 of public key material from signature block (bytes 36-812)
-    // This includes: RSA modulus (36-420) + exponent (420-424) + pre-calculated R (424-812)
-    let public_key_data = &signature_block[36..812]; // 776 bytes total
+        // Check the public key against eFuse before anything expensive. The digest is SHA-256 of
+        // the public key material from the signature block (bytes 36-812): RSA modulus (36-420) +
+        // exponent (420-424) + pre-calculated R (424-808) + M' (808-812)
+        let calculated_key_digest = compute_sha256_hardware(sha, &block[36..812]);
+        if !trusted.contains(&calculated_key_digest) {
+            continue;
+        }
 
-    let calculated_key_digest = compute_sha256_hardware(sha, public_key_data);
+        // Every block signs the same image so only hash it once
+        let image_digest = match image_digest {
+            Some(image_digest) => image_digest,
+            None => *image_digest.insert(image_sha256(partition, signature_sector_index, sha)?),
+        };
 
-    if calculated_key_digest != efuse_key_digest {
-        return Err(SecureBootError::PublicKeyInvalid);
+        match verify_signature_block(rsa, &parsed_block, &image_digest, sha) {
+            // If we reach here, ALL security checks have passed
+            Ok(()) => return Ok(()),
+            Err(e) => result = Err(e),
+        }
     }
 
-    // Verify image digest (SHA-256 of application data before signature block)
-    // Calculate how many sectors contain application data (before signature block)
+    result
+}
+
+/// SHA-256 of the application data before the signature sector
+fn image_sha256<'a>(
+    partition: &EspFlashPartition,
+    signature_sector_index: u32,
+    sha: &mut Sha,
+) -> Result<[u8; 32], SecureBootError<'a>> {
     let mut hasher = sha.start::<Sha256>();
     for sector in 0..signature_sector_index {
-        match app_partition.read_sector(sector) {
+        match partition.read_sector(sector) {
             Ok(sector_data) => {
                 let mut remaining = sector_data.as_slice();
                 while !remaining.is_empty() {
@@ -342,18 +413,72 @@ pub fn verify_secure_boot<'a>(
 
     let mut calculated_digest = [0u8; 32];
     block!(hasher.finish(&mut calculated_digest)).unwrap();
+    Ok(calculated_digest)
+}
 
-    if calculated_digest != parsed_block.image_digest {
+/// Check `parsed_block` signs `digest`
+fn verify_signature_block<'a>(
+    rsa: &mut Rsa<'_, Blocking>,
+    parsed_block: &SignatureBlock,
+    digest: &[u8; 32],
+    sha: &mut Sha,
+) -> Result<(), SecureBootError<'a>> {
+    if *digest != parsed_block.image_digest {
         return Err(SecureBootError::ImageHashInvalid);
     }
 
     // Verify RSA-PSS signature using hardware RSA peripheral
-    match verify_rsa_pss_signature(rsa, &parsed_block, &parsed_block.image_digest, sha) {
-        Ok(true) => { /* Firmware signature verified successfully */ }
-        Ok(false) => return Err(SecureBootError::SignatureInvalid),
-        Err(e) => return Err(SecureBootError::SignatureError(e)),
+    match verify_rsa_pss_signature(rsa, parsed_block, digest, sha) {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(SecureBootError::SignatureInvalid),
+        Err(e) => Err(SecureBootError::SignatureError(e)),
+    }
+}
+
+/// Carry out a [`SecureBootKeyRevocation`].
+///
+/// `boot_images` are what has to keep booting afterwards (the bootloader and the running
+/// firmware); the revocation is refused unless each is signed by a key that stays trusted.
+pub fn revoke_secure_boot_key(
+    efuse: &EfuseController,
+    revocation: &SecureBootKeyRevocation,
+    boot_images: &[EspFlashPartition],
+    rsa: &mut Rsa<'_, Blocking>,
+    sha: &mut Sha,
+) -> Result<(), RevocationRefused> {
+    let slots = secure_boot_key_slots();
+    let target = slots
+        .iter()
+        .find(|slot| !slot.revoked && slot.digest == revocation.key_digest)
+        .ok_or(RevocationRefused::NotTrusted)?;
+    let remaining = trusted_key_digests(&slots)
+        .into_iter()
+        .filter(|digest| *digest != target.digest)
+        .collect::<Vec<_>>();
+
+    let block: &[u8; SIGNATURE_BLOCK_LEN] = revocation
+        .signature_block
+        .as_slice()
+        .try_into()
+        .map_err(|_| RevocationRefused::BadSignature)?;
+    let parsed_block = SignatureBlock::parse(block).map_err(|_| RevocationRefused::BadSignature)?;
+    if !remaining.contains(&compute_sha256_hardware(sha, &block[36..812])) {
+        return Err(RevocationRefused::UntrustedSigner);
+    }
+    verify_signature_block(
+        rsa,
+        &parsed_block,
+        &revocation_digest(&revocation.key_digest),
+        sha,
+    )
+    .map_err(|_| RevocationRefused::BadSignature)?;
+
+    for image in boot_images {
+        verify_signed_by_any(image, rsa, sha, &remaining)
+            .map_err(|_| RevocationRefused::WouldNotBoot)?;
     }
 
-    // If we reach here, ALL security checks have passed
-    Ok(())
+    efuse
+        .revoke_secure_boot_digest(target.digest_index)
+        .map_err(|_| RevocationRefused::EfuseWriteFailed)
 }
@@ -3,12 +3,17 @@ use alloc::{boxed::Box, string::String};
 use frost_backup::ShareBackup;
 use frostsnap_comms::{DeviceName, Sha256Digest};
 use frostsnap_core::{
-    device::{restoration::EnterBackupPhase, KeyGenPhase3, SignPhase1},
+    device::{
+        address_book::AddressBookPhase,
+        restoration::{EnterBackupPhase, ShareExportPhase, ShareImportPhase},
+        KeyGenPhase3, SignPhase1, SilentPaymentEcdhPhase,
+    },
     message::HeldShare2,
     schnorr_fun::frost::ShareIndex,
-    tweak::BitcoinBip32Path,
-    AccessStructureRef,
+    tweak::{BitcoinAccount, BitcoinBip32Path},
+    AccessStructureRef, Kind,
 };
+use frostsnap_macros::Kind as KindDerive;
 use frostsnap_widgets::Frac;
 
 pub trait UserInteraction {
@@ -62,7 +67,7 @@ impl<T: UserInteraction + ?Sized> UserInteraction for Box<T> {
     }
 }
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, KindDerive)]
 pub enum Workflow {
     #[default]
     Startup,
@@ -71,6 +76,7 @@ pub enum Workflow {
         device_name: DeviceName,
         held_share: HeldShare2,
     },
+    #[delegate_kind]
     UserPrompt(Prompt),
     NamingDevice {
         new_name: DeviceName,
@@ -86,6 +92,12 @@ pub enum Workflow {
         bip32_path: BitcoinBip32Path,
         rand_seed: u32,
     },
+    DisplayAccount {
+        key_name: String,
+        account: BitcoinAccount,
+        master_fingerprint: bitcoin::bip32::Fingerprint,
+        account_xpub: bitcoin::bip32::Xpub,
+    },
     FirmwareUpgrade(FirmwareUpgradeStatus),
     EraseProgress {
         progress: Frac,
@@ -105,7 +117,7 @@ impl Workflow {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, KindDerive)]
 pub enum Prompt {
     KeyGen {
         phase: Box<KeyGenPhase3>,
@@ -114,6 +126,20 @@ pub enum Prompt {
         phase: Box<SignPhase1>,
         rand_seed: u32,
     },
+    SilentPaymentEcdh {
+        phase: Box<SilentPaymentEcdhPhase>,
+    },
+    AddressBook {
+        phase: Box<AddressBookPhase>,
+    },
+    ExportShare {
+        key_name: String,
+        phase: Box<ShareExportPhase>,
+    },
+    ImportShare {
+        key_name: String,
+        phase: Box<ShareImportPhase>,
+    },
     ConfirmFirmwareUpgrade {
         firmware_digest: Sha256Digest,
         size: u32,
@@ -146,6 +172,18 @@ pub enum UiEvent {
     SigningConfirm {
         phase: Box<SignPhase1>,
     },
+    SilentPaymentEcdhConfirm {
+        phase: Box<SilentPaymentEcdhPhase>,
+    },
+    AddressBookConfirm {
+        phase: Box<AddressBookPhase>,
+    },
+    ShareExportConfirm {
+        phase: Box<ShareExportPhase>,
+    },
+    ShareImportConfirm {
+        phase: Box<ShareImportPhase>,
+    },
     EnteredShareBackup {
         phase: EnterBackupPhase,
         share_backup: ShareBackup,
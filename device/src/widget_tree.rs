@@ -1,11 +1,15 @@
-use alloc::boxed::Box;
-use bitcoin::Address;
+use alloc::{boxed::Box, string::String};
+use bitcoin::{bip32, Address};
 use frost_backup::ShareBackup;
 use frostsnap_comms::Sha256Digest;
 use frostsnap_core::{
-    device::{restoration::EnterBackupPhase, KeyGenPhase3, SignPhase1},
+    device::{
+        address_book::AddressBookPhase,
+        restoration::{EnterBackupPhase, ShareExportPhase, ShareImportPhase},
+        KeyGenPhase3, SignPhase1, SilentPaymentEcdhPhase,
+    },
     schnorr_fun::frost::ShareIndex,
-    tweak::BitcoinBip32Path,
+    tweak::{BitcoinAccount, BitcoinBip32Path},
     AccessStructureRef, SignTask,
 };
 use frostsnap_widgets::{
@@ -13,8 +17,9 @@ use frostsnap_widgets::{
     keygen_check::KeygenCheck,
     layout::*,
     sign_prompt::SignTxPrompt,
-    AddressWithIndex, DeviceNameScreen, EraseDevice, EraseProgress, FirmwareUpgradeConfirm,
-    FirmwareUpgradeProgress, SignMessageConfirm, Standby,
+    AccountXpubDisplay, AddressBookConfirm, AddressWithIndex, DeviceNameScreen, EraseDevice,
+    EraseProgress, FirmwareUpgradeConfirm, FirmwareUpgradeProgress, SignMessageConfirm,
+    SilentPaymentEcdhConfirm, Standby,
 };
 
 use crate::ui::FirmwareUpgradeStatus;
@@ -47,6 +52,42 @@ pub enum WidgetTree {
         phase: Option<Box<SignPhase1>>,
     },
 
+    /// Sign proof of reserves prompt screen
+    SignProofOfReservesPrompt {
+        widget: Box<SignMessageConfirm>,
+        phase: Option<Box<SignPhase1>>,
+    },
+
+    /// Sign address book entry prompt screen
+    SignAddressBookEntryPrompt {
+        widget: Box<SignMessageConfirm>,
+        phase: Option<Box<SignPhase1>>,
+    },
+
+    /// Silent payment ECDH confirmation screen
+    SilentPaymentEcdhPrompt {
+        widget: Box<SilentPaymentEcdhConfirm>,
+        phase: Option<Box<SilentPaymentEcdhPhase>>,
+    },
+
+    /// Confirm a change to a wallet's address book
+    AddressBookPrompt {
+        widget: Box<AddressBookConfirm>,
+        phase: Option<Box<AddressBookPhase>>,
+    },
+
+    /// Confirm handing this device's share over to another device
+    ShareExportPrompt {
+        widget: Box<SignMessageConfirm>,
+        phase: Option<Box<ShareExportPhase>>,
+    },
+
+    /// Confirm taking over another device's share
+    ShareImportPrompt {
+        widget: Box<SignMessageConfirm>,
+        phase: Option<Box<ShareImportPhase>>,
+    },
+
     /// Firmware upgrade confirmation screen
     FirmwareUpgradeConfirm {
         widget: Box<FirmwareUpgradeConfirm>,
@@ -76,6 +117,9 @@ pub enum WidgetTree {
     /// Display Bitcoin address screen with derivation path
     AddressDisplay(Box<Center<frostsnap_widgets::AddressWithIndex>>),
 
+    /// Display an account's master fingerprint and xpub
+    AccountDisplay(Box<AccountXpubDisplay>),
+
     /// Enter backup screen
     EnterBackup {
         widget: Box<EnterShareScreen>,
@@ -133,12 +177,15 @@ impl WidgetTree {
         let sign_task = phase.sign_task();
 
         match &sign_task.inner {
-            SignTask::BitcoinTransaction {
-                tx_template,
-                network,
-            } => {
-                let prompt = tx_template.user_prompt(*network);
-                let widget = Box::new(SignTxPrompt::new_with_seed(prompt, rand_seed));
+            SignTask::BitcoinTransaction { .. } => {
+                let (prompt, details) = phase
+                    .bitcoin_prompt()
+                    .expect("the task is a bitcoin transaction");
+                let widget = Box::new(SignTxPrompt::new_with_details(
+                    prompt,
+                    Some(details),
+                    rand_seed,
+                ));
                 Self::SignTxPrompt {
                     widget,
                     phase: Some(phase),
@@ -151,6 +198,43 @@ impl WidgetTree {
                     phase: Some(phase),
                 }
             }
+            SignTask::ProofOfReserves {
+                message,
+                tx_template,
+                ..
+            } => {
+                let proven = tx_template
+                    .iter_our_inputs()
+                    .map(|(_, input, _)| input.txout().value)
+                    .sum::<bitcoin::Amount>();
+                let widget = Box::new(SignMessageConfirm::with_title(
+                    "Proof of reserves,\nnot a spend",
+                    format!(
+                        "{proven} in {} coins\n\n{message}",
+                        tx_template.iter_our_inputs().count()
+                    ),
+                ));
+                Self::SignProofOfReservesPrompt {
+                    widget,
+                    phase: Some(phase),
+                }
+            }
+            SignTask::AddressBookEntry {
+                label,
+                spk,
+                network,
+            } => {
+                let address =
+                    Address::from_script(spk, *network).expect("checked the entry has an address");
+                let widget = Box::new(SignMessageConfirm::with_title(
+                    "Approve address\nbook entry",
+                    format!("{label}\n\n{address}"),
+                ));
+                Self::SignAddressBookEntryPrompt {
+                    widget,
+                    phase: Some(phase),
+                }
+            }
             SignTask::Nostr { .. } => {
                 let mut standby = Standby::new(crate::FIRMWARE_VERSION);
                 standby.set_welcome();
@@ -159,6 +243,60 @@ impl WidgetTree {
         }
     }
 
+    #[inline(never)]
+    pub(crate) fn build_silent_payment_ecdh(phase: Box<SilentPaymentEcdhPhase>) -> Self {
+        let widget = Box::new(SilentPaymentEcdhConfirm::new(phase.address()));
+        Self::SilentPaymentEcdhPrompt {
+            widget,
+            phase: Some(phase),
+        }
+    }
+
+    #[inline(never)]
+    pub(crate) fn build_address_book(phase: Box<AddressBookPhase>) -> Self {
+        let widget = Box::new(AddressBookConfirm::new(
+            phase.address(),
+            phase.label(),
+            phase.old_label(),
+        ));
+        Self::AddressBookPrompt {
+            widget,
+            phase: Some(phase),
+        }
+    }
+
+    #[inline(never)]
+    pub(crate) fn build_share_export(key_name: String, phase: Box<ShareExportPhase>) -> Self {
+        let widget = Box::new(SignMessageConfirm::with_title(
+            "Move share to\nanother device",
+            format!(
+                "{key_name} #{}\n\nOnly confirm if the other device shows\n\n{}",
+                share_index_to_u16(phase.share_index()),
+                phase.code
+            ),
+        ));
+        Self::ShareExportPrompt {
+            widget,
+            phase: Some(phase),
+        }
+    }
+
+    #[inline(never)]
+    pub(crate) fn build_share_import(key_name: String, phase: Box<ShareImportPhase>) -> Self {
+        let widget = Box::new(SignMessageConfirm::with_title(
+            "Take over share\nfrom another device",
+            format!(
+                "{key_name} #{}\n\nAfter you confirm the other device must show\n\n{}",
+                share_index_to_u16(phase.share_index()),
+                phase.code
+            ),
+        ));
+        Self::ShareImportPrompt {
+            widget,
+            phase: Some(phase),
+        }
+    }
+
     #[inline(never)]
     pub(crate) fn build_display_backup(backup: ShareBackup) -> Self {
         let word_indices = backup.to_word_indices();
@@ -215,6 +353,21 @@ impl WidgetTree {
             AddressWithIndex::new_with_seed(address, bip32_path.index.to_u32() as usize, rand_seed);
         Self::AddressDisplay(Box::new(Center::new(address_display)))
     }
+
+    #[inline(never)]
+    pub(crate) fn build_display_account(
+        key_name: &str,
+        account: BitcoinAccount,
+        master_fingerprint: bip32::Fingerprint,
+        account_xpub: &bip32::Xpub,
+    ) -> Self {
+        Self::AccountDisplay(Box::new(AccountXpubDisplay::new(
+            key_name,
+            account,
+            master_fingerprint,
+            account_xpub,
+        )))
+    }
 }
 
 impl Default for WidgetTree {
@@ -222,3 +375,9 @@ impl Default for WidgetTree {
         WidgetTree::Standby(Box::new(Standby::new(crate::FIRMWARE_VERSION)))
     }
 }
+
+fn share_index_to_u16(share_index: ShareIndex) -> u16 {
+    share_index
+        .try_into()
+        .expect("Share index should fit in u16")
+}
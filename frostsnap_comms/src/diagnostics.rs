@@ -0,0 +1,154 @@
+//! What a device finds when asked to check itself over with
+//! [`CoordinatorSendBody::RunDiagnostics`](crate::CoordinatorSendBody::RunDiagnostics).
+//!
+//! Everything here is read-only on the device: nothing is written, erased or burned to produce it,
+//! so it's safe to ask a device holding a share for one at any time.
+use crate::Sha256Digest;
+use alloc::{string::String, vec::Vec};
+use bincode::{Decode, Encode};
+
+#[derive(Encode, Decode, Debug, Clone, PartialEq)]
+pub struct DiagnosticReport {
+    /// The firmware the device is running.
+    pub firmware_digest: Sha256Digest,
+    pub partitions: Vec<PartitionCheck>,
+    pub mutation_log: StorageFill,
+    pub nonce_slots: NonceSlotUsage,
+    pub efuse_keys: EfuseKeyStatus,
+    /// The chip id the touch controller answered with at boot. `None` if it didn't answer.
+    pub touch_controller: Option<u8>,
+    pub secure_boot_enabled: bool,
+}
+
+/// The result of reading every sector of a flash partition.
+#[derive(Encode, Decode, Debug, Clone, PartialEq)]
+pub struct PartitionCheck {
+    pub name: String,
+    pub size: u32,
+    /// CRC-32 (ISO-HDLC) of the whole partition. Not given for partitions that hold the device's
+    /// secrets.
+    pub crc32: Option<u32>,
+    pub unreadable_sectors: u32,
+}
+
+#[derive(Encode, Decode, Debug, Clone, Copy, PartialEq)]
+pub struct StorageFill {
+    pub used_bytes: u32,
+    pub capacity_bytes: u32,
+}
+
+impl StorageFill {
+    pub fn percent_used(&self) -> u32 {
+        if self.capacity_bytes == 0 {
+            return 100;
+        }
+        (self.used_bytes as u64 * 100 / self.capacity_bytes as u64) as u32
+    }
+}
+
+#[derive(Encode, Decode, Debug, Clone, Copy, PartialEq)]
+pub struct NonceSlotUsage {
+    pub total: u32,
+    pub in_use: u32,
+}
+
+/// Which eFuse key slot (`KEY0` to `KEY5`) each of the device's keys was found in.
+#[derive(Encode, Decode, Debug, Clone, Copy, PartialEq)]
+pub struct EfuseKeyStatus {
+    pub share_encryption: Option<u8>,
+    pub fixed_entropy: Option<u8>,
+    pub ds: Option<u8>,
+}
+
+impl DiagnosticReport {
+    /// The mutation log is nearly full past this. A full log stops the device saving anything.
+    pub const LOG_FILL_WARNING_PERCENT: u32 = 90;
+
+    /// Anything in the report that needs someone to look at the device. Empty if it's healthy.
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        for partition in &self.partitions {
+            if partition.unreadable_sectors > 0 {
+                problems.push(format!(
+                    "{} unreadable sectors in the {} partition",
+                    partition.unreadable_sectors, partition.name
+                ));
+            }
+        }
+        if self.mutation_log.percent_used() >= Self::LOG_FILL_WARNING_PERCENT {
+            problems.push(format!(
+                "mutation log is {}% full",
+                self.mutation_log.percent_used()
+            ));
+        }
+        if self.nonce_slots.total == 0 {
+            problems.push("no nonce slots".into());
+        }
+        if self.efuse_keys.share_encryption.is_none() {
+            problems.push("share encryption key missing from eFuses".into());
+        }
+        if self.efuse_keys.fixed_entropy.is_none() {
+            problems.push("fixed entropy key missing from eFuses".into());
+        }
+        if self.touch_controller.is_none() {
+            problems.push("touch controller didn't respond".into());
+        }
+        problems
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use alloc::string::ToString;
+
+    fn healthy() -> DiagnosticReport {
+        DiagnosticReport {
+            firmware_digest: Sha256Digest([0; 32]),
+            partitions: vec![PartitionCheck {
+                name: "ota_0".into(),
+                size: 4096 * 4,
+                crc32: Some(0xdeadbeef),
+                unreadable_sectors: 0,
+            }],
+            mutation_log: StorageFill {
+                used_bytes: 100,
+                capacity_bytes: 4096,
+            },
+            nonce_slots: NonceSlotUsage {
+                total: 8,
+                in_use: 2,
+            },
+            efuse_keys: EfuseKeyStatus {
+                share_encryption: Some(2),
+                fixed_entropy: Some(3),
+                ds: None,
+            },
+            touch_controller: Some(0xb5),
+            secure_boot_enabled: false,
+        }
+    }
+
+    #[test]
+    fn healthy_report_has_no_problems() {
+        assert!(healthy().problems().is_empty());
+    }
+
+    #[test]
+    fn problems_are_listed() {
+        let mut report = healthy();
+        report.partitions[0].unreadable_sectors = 1;
+        report.mutation_log.used_bytes = 4000;
+        report.efuse_keys.fixed_entropy = None;
+        report.touch_controller = None;
+        assert_eq!(
+            report.problems(),
+            vec![
+                "1 unreadable sectors in the ota_0 partition".to_string(),
+                "mutation log is 97% full".to_string(),
+                "fixed entropy key missing from eFuses".to_string(),
+                "touch controller didn't respond".to_string(),
+            ]
+        );
+    }
+}
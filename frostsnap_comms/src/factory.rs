@@ -54,6 +54,7 @@ pub struct FactoryDownstream;
 pub enum DeviceFactorySend {
     InitEntropyOk,
     ReceivedDsKey,
+    ReceivedSecureBootDigests,
 }
 
 #[derive(bincode::Encode, bincode::Decode, Debug, Clone)]
@@ -63,6 +64,10 @@ pub enum FactorySend {
     InitEntropy([u8; 32]),
     SetEsp32DsKey(Esp32DsKey),
     SetGenuineCertificate(Certificate),
+    /// Digests of backup Secure Boot keys to burn alongside the one the bootloader burned, so
+    /// devices keep booting firmware signed by them once that key is revoked. See
+    /// [`crate::secure_boot`].
+    SetSecureBootDigests(Vec<[u8; 32]>),
 }
 
 #[derive(bincode::Encode, bincode::Decode, Debug, Clone)]
@@ -76,6 +81,7 @@ impl Gist for DeviceFactorySend {
         match self {
             DeviceFactorySend::InitEntropyOk => "InitEntropyOk",
             DeviceFactorySend::ReceivedDsKey => "SetDs",
+            DeviceFactorySend::ReceivedSecureBootDigests => "ReceivedSecureBootDigests",
         }
         .into()
     }
@@ -88,6 +94,7 @@ impl Gist for FactorySend {
             FactorySend::SetEsp32DsKey { .. } => "SetEsp32DsKey",
             FactorySend::InitEntropy(_) => "InitEntropy",
             FactorySend::SetGenuineCertificate(_) => "GenuineCertificate",
+            FactorySend::SetSecureBootDigests(_) => "SetSecureBootDigests",
         }
         .into()
     }
@@ -0,0 +1,284 @@
+//! # Compressed Firmware Upgrades
+//!
+//! With [`CoordinatorUpgradeMessage::PrepareUpgradeCompressed`] the coordinator streams the
+//! firmware image as raw DEFLATE (RFC 1951, no zlib header) rather than as-is. The stream is sent
+//! in the same [`FIRMWARE_UPGRADE_CHUNK_LEN`] chunks as a raw image and devices that aren't
+//! upgrading forward it without looking inside. The device being upgraded feeds each chunk to an
+//! [`Inflater`] and collects what comes out into sectors with a [`SectorWriter`].
+//!
+//! Nothing about the image's acceptance changes: the device still checks the digest of what ended
+//! up in flash afterwards.
+//!
+//! [`CoordinatorUpgradeMessage::PrepareUpgradeCompressed`]: crate::CoordinatorUpgradeMessage::PrepareUpgradeCompressed
+//! [`FIRMWARE_UPGRADE_CHUNK_LEN`]: crate::FIRMWARE_UPGRADE_CHUNK_LEN
+
+use crate::firmware_reader::SECTOR_SIZE;
+use alloc::boxed::Box;
+use miniz_oxide::inflate::core::{decompress, inflate_flags, DecompressorOxide};
+use miniz_oxide::inflate::TINFLStatus;
+
+/// DEFLATE refers back up to 32KiB into what it has already output, so the inflater has to keep
+/// that much around.
+const WINDOW_SIZE: usize = 32 * 1024;
+
+/// Compress a firmware image (or a patch) for [`Inflater`].
+#[cfg(feature = "coordinator")]
+pub fn compress(image: &[u8]) -> alloc::vec::Vec<u8> {
+    use miniz_oxide::deflate::{compress_to_vec, CompressionLevel};
+    compress_to_vec(image, CompressionLevel::BestCompression as u8)
+}
+
+/// Decompresses a DEFLATE stream pushed to it in pieces.
+///
+/// The stream comes from the coordinator so it's untrusted. Anything wrong with it is an error,
+/// including producing more than the length the coordinator announced.
+pub struct Inflater {
+    decompressor: Box<DecompressorOxide>,
+    window: Box<[u8]>,
+    window_pos: usize,
+    expected_len: u32,
+    total_len: u32,
+    done: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InflateError<E> {
+    /// The stream isn't valid DEFLATE.
+    Corrupt,
+    /// The stream ended before the end of the DEFLATE data.
+    Incomplete,
+    /// The stream decompresses to a different length than announced.
+    WrongLength { expected: u32 },
+    /// Handing on the output failed.
+    Output(E),
+}
+
+impl<E: core::fmt::Display> core::fmt::Display for InflateError<E> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            InflateError::Corrupt => write!(f, "Compressed firmware is corrupt"),
+            InflateError::Incomplete => write!(f, "Compressed firmware ended early"),
+            InflateError::WrongLength { expected } => {
+                write!(
+                    f,
+                    "Compressed firmware doesn't decompress to {} bytes",
+                    expected
+                )
+            }
+            InflateError::Output(e) => write!(f, "Failed to write firmware: {}", e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: core::fmt::Debug + core::fmt::Display> std::error::Error for InflateError<E> {}
+
+impl Inflater {
+    /// Expect the stream to decompress to exactly `expected_len` bytes.
+    pub fn new(expected_len: u32) -> Self {
+        Self {
+            decompressor: Box::default(),
+            // via a Vec so it never passes through the stack
+            window: vec![0u8; WINDOW_SIZE].into_boxed_slice(),
+            window_pos: 0,
+            expected_len,
+            total_len: 0,
+            done: false,
+        }
+    }
+
+    /// Decompress the next piece of the stream, passing what comes out to `output` as it goes.
+    pub fn push<E>(
+        &mut self,
+        mut input: &[u8],
+        mut output: impl FnMut(&[u8]) -> Result<(), E>,
+    ) -> Result<(), InflateError<E>> {
+        while !self.done {
+            let (status, consumed, written) = decompress(
+                &mut self.decompressor,
+                input,
+                &mut self.window[..],
+                self.window_pos,
+                inflate_flags::TINFL_FLAG_HAS_MORE_INPUT,
+            );
+            input = &input[consumed..];
+
+            // output never wraps within one call; it stops at the end of the window instead
+            let out = &self.window[self.window_pos..self.window_pos + written];
+            self.window_pos = (self.window_pos + written) & (WINDOW_SIZE - 1);
+            self.total_len = self.total_len.saturating_add(written as u32);
+            if self.total_len > self.expected_len {
+                return Err(InflateError::WrongLength {
+                    expected: self.expected_len,
+                });
+            }
+            if !out.is_empty() {
+                output(out).map_err(InflateError::Output)?;
+            }
+
+            match status {
+                TINFLStatus::Done => self.done = true,
+                TINFLStatus::NeedsMoreInput => return Ok(()),
+                TINFLStatus::HasMoreOutput => { /* window is full, go around again */ }
+                _ => return Err(InflateError::Corrupt),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Call once the whole stream has been pushed.
+    pub fn finish<E>(self) -> Result<(), InflateError<E>> {
+        if !self.done {
+            return Err(InflateError::Incomplete);
+        }
+        if self.total_len != self.expected_len {
+            return Err(InflateError::WrongLength {
+                expected: self.expected_len,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Collects an image written a piece at a time into whole sectors, handing each on with its index
+/// as soon as it's complete.
+pub struct SectorWriter {
+    sector: Box<[u8; SECTOR_SIZE]>,
+    len: usize,
+    next_sector: u32,
+}
+
+impl Default for SectorWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SectorWriter {
+    pub fn new() -> Self {
+        Self {
+            sector: Box::new([0xff; SECTOR_SIZE]),
+            len: 0,
+            next_sector: 0,
+        }
+    }
+
+    pub fn write<E>(
+        &mut self,
+        mut bytes: &[u8],
+        write_sector: &mut impl FnMut(u32, &[u8; SECTOR_SIZE]) -> Result<(), E>,
+    ) -> Result<(), E> {
+        while !bytes.is_empty() {
+            let n = self.fill(
+                bytes.len(),
+                |buf| {
+                    buf.copy_from_slice(&bytes[..buf.len()]);
+                    Ok(())
+                },
+                write_sector,
+            )?;
+            bytes = &bytes[n..];
+        }
+        Ok(())
+    }
+
+    /// Have `fill` write up to `max` bytes straight into the current sector. Returns how many
+    /// bytes it was given room for, which is fewer than `max` when the sector fills up.
+    pub fn fill<E>(
+        &mut self,
+        max: usize,
+        fill: impl FnOnce(&mut [u8]) -> Result<(), E>,
+        write_sector: &mut impl FnMut(u32, &[u8; SECTOR_SIZE]) -> Result<(), E>,
+    ) -> Result<usize, E> {
+        let n = (SECTOR_SIZE - self.len).min(max);
+        fill(&mut self.sector[self.len..self.len + n])?;
+        self.len += n;
+        if self.len == SECTOR_SIZE {
+            write_sector(self.next_sector, &self.sector)?;
+            self.next_sector += 1;
+            self.len = 0;
+        }
+        Ok(n)
+    }
+
+    /// Hand on the last partial sector padded with `0xff`.
+    pub fn finish<E>(
+        mut self,
+        write_sector: &mut impl FnMut(u32, &[u8; SECTOR_SIZE]) -> Result<(), E>,
+    ) -> Result<(), E> {
+        if self.len > 0 {
+            self.sector[self.len..].fill(0xff);
+            write_sector(self.next_sector, &self.sector)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::FIRMWARE_UPGRADE_CHUNK_LEN;
+    use alloc::vec::Vec;
+    use core::convert::Infallible;
+
+    fn image(len: usize) -> Vec<u8> {
+        // compressible but not trivially so, and long enough to use back-references further
+        // than one window
+        (0..len)
+            .map(|i| ((i / 7) as u8) ^ ((i % 251) as u8))
+            .collect()
+    }
+
+    fn inflate(stream: &[u8], expected_len: u32) -> Result<Vec<u8>, InflateError<Infallible>> {
+        let mut out = Vec::new();
+        let mut write_sector = |i: u32, sector: &[u8; SECTOR_SIZE]| -> Result<(), Infallible> {
+            assert_eq!(i as usize, out.len() / SECTOR_SIZE);
+            out.extend_from_slice(sector);
+            Ok(())
+        };
+        let mut inflater = Inflater::new(expected_len);
+        let mut sectors = SectorWriter::new();
+        for chunk in stream.chunks(FIRMWARE_UPGRADE_CHUNK_LEN as usize) {
+            inflater.push(chunk, |bytes| sectors.write(bytes, &mut write_sector))?;
+        }
+        inflater.finish()?;
+        sectors
+            .finish(&mut write_sector)
+            .map_err(InflateError::Output)?;
+        Ok(out)
+    }
+
+    #[test]
+    fn round_trip_pads_the_last_sector() {
+        let image = image(200_000 + 123);
+        let stream = compress(&image);
+        assert!(stream.len() < image.len());
+        let out = inflate(&stream, image.len() as u32).unwrap();
+        assert_eq!(out.len(), image.len().div_ceil(SECTOR_SIZE) * SECTOR_SIZE);
+        assert_eq!(&out[..image.len()], &image[..]);
+        assert!(out[image.len()..].iter().all(|byte| *byte == 0xff));
+    }
+
+    #[test]
+    fn rejects_bad_streams() {
+        let image = image(50_000);
+        let stream = compress(&image);
+        let len = image.len() as u32;
+
+        assert_eq!(
+            inflate(&stream[..stream.len() / 2], len),
+            Err(InflateError::Incomplete)
+        );
+        assert_eq!(
+            inflate(&stream, len - 1),
+            Err(InflateError::WrongLength { expected: len - 1 })
+        );
+        assert_eq!(
+            inflate(&stream, len + 1),
+            Err(InflateError::WrongLength { expected: len + 1 })
+        );
+        // reserved block type
+        assert_eq!(inflate(&[0xff; 64], len), Err(InflateError::Corrupt));
+    }
+}
@@ -0,0 +1,329 @@
+//! # Delta Firmware Upgrades
+//!
+//! Most releases only change a small part of the image, so rather than the whole thing the
+//! coordinator can send a patch against the firmware the device is already running. See
+//! [`CoordinatorUpgradeMessage::PrepareUpgradeDelta`].
+//!
+//! A patch is a list of operations that build the new image front to back, each either copying a
+//! run of bytes out of the running image or inserting bytes carried in the patch:
+//!
+//! ```text
+//! copy:   0x00 | offset: u32 LE | len: u32 LE
+//! insert: 0x01 | len: u32 LE | len bytes
+//! ```
+//!
+//! It goes over the wire compressed like [`firmware_compression`](crate::firmware_compression)
+//! and the device feeds what the [`Inflater`](crate::firmware_compression::Inflater) produces
+//! straight into a [`PatchApplier`]. Working out a patch is up to the coordinator.
+//!
+//! [`CoordinatorUpgradeMessage::PrepareUpgradeDelta`]: crate::CoordinatorUpgradeMessage::PrepareUpgradeDelta
+
+use crate::firmware_compression::SectorWriter;
+use crate::firmware_reader::SECTOR_SIZE;
+
+const OP_COPY: u8 = 0x00;
+const OP_INSERT: u8 = 0x01;
+/// The longest op header: a copy.
+const MAX_HEADER_LEN: usize = 9;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatchOp<'a> {
+    Copy { offset: u32, len: u32 },
+    Insert(&'a [u8]),
+}
+
+impl PatchOp<'_> {
+    pub fn encode(&self, out: &mut alloc::vec::Vec<u8>) {
+        match self {
+            PatchOp::Copy { offset, len } => {
+                out.push(OP_COPY);
+                out.extend_from_slice(&offset.to_le_bytes());
+                out.extend_from_slice(&len.to_le_bytes());
+            }
+            PatchOp::Insert(bytes) => {
+                out.push(OP_INSERT);
+                out.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+                out.extend_from_slice(bytes);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatchError<E> {
+    /// An op we don't know.
+    Corrupt,
+    /// The patch ended in the middle of an op.
+    Incomplete,
+    /// The patch builds an image of a different length than announced.
+    WrongLength { expected: u32 },
+    /// Reading the running image or writing the new one failed.
+    Io(E),
+}
+
+impl<E: core::fmt::Display> core::fmt::Display for PatchError<E> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            PatchError::Corrupt => write!(f, "Firmware patch is corrupt"),
+            PatchError::Incomplete => write!(f, "Firmware patch ended early"),
+            PatchError::WrongLength { expected } => {
+                write!(f, "Firmware patch doesn't make a {} byte image", expected)
+            }
+            PatchError::Io(e) => write!(f, "Failed to apply firmware patch: {}", e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: core::fmt::Debug + core::fmt::Display> std::error::Error for PatchError<E> {}
+
+#[derive(Debug, Clone, Copy)]
+enum State {
+    Header {
+        buf: [u8; MAX_HEADER_LEN],
+        len: usize,
+    },
+    Copy {
+        offset: u32,
+        remaining: u32,
+    },
+    Insert {
+        remaining: u32,
+    },
+}
+
+impl State {
+    const fn header() -> Self {
+        State::Header {
+            buf: [0u8; MAX_HEADER_LEN],
+            len: 0,
+        }
+    }
+}
+
+/// Applies a patch pushed to it in pieces, writing the new image out a sector at a time.
+///
+/// Like the rest of an upgrade the patch is untrusted: an op that doesn't make sense or that would
+/// build more than the announced length is an error.
+pub struct PatchApplier {
+    state: State,
+    sectors: SectorWriter,
+    expected_len: u32,
+    total_len: u32,
+}
+
+impl PatchApplier {
+    /// Expect the patch to build an image of exactly `expected_len` bytes.
+    pub fn new(expected_len: u32) -> Self {
+        Self {
+            state: State::header(),
+            sectors: SectorWriter::new(),
+            expected_len,
+            total_len: 0,
+        }
+    }
+
+    /// Apply the next piece of the patch. `read_base` reads from the running image at an offset;
+    /// `write_sector` is called with each sector of the new image as soon as it's complete.
+    pub fn push<E>(
+        &mut self,
+        mut input: &[u8],
+        read_base: &mut impl FnMut(u32, &mut [u8]) -> Result<(), E>,
+        write_sector: &mut impl FnMut(u32, &[u8; SECTOR_SIZE]) -> Result<(), E>,
+    ) -> Result<(), PatchError<E>> {
+        loop {
+            match &mut self.state {
+                State::Header { buf, len } => {
+                    if input.is_empty() {
+                        return Ok(());
+                    }
+                    if *len == 0 {
+                        buf[0] = input[0];
+                        *len = 1;
+                        input = &input[1..];
+                    }
+                    let header_len = match buf[0] {
+                        OP_COPY => 9,
+                        OP_INSERT => 5,
+                        _ => return Err(PatchError::Corrupt),
+                    };
+                    let n = (header_len - *len).min(input.len());
+                    buf[*len..*len + n].copy_from_slice(&input[..n]);
+                    *len += n;
+                    input = &input[n..];
+                    if *len < header_len {
+                        return Ok(());
+                    }
+
+                    let field = |at: usize| u32::from_le_bytes(buf[at..at + 4].try_into().unwrap());
+                    let (state, op_len) = match buf[0] {
+                        OP_COPY => (
+                            State::Copy {
+                                offset: field(1),
+                                remaining: field(5),
+                            },
+                            field(5),
+                        ),
+                        _ => (State::Insert { remaining: field(1) }, field(1)),
+                    };
+                    self.total_len = self.total_len.saturating_add(op_len);
+                    if self.total_len > self.expected_len {
+                        return Err(PatchError::WrongLength {
+                            expected: self.expected_len,
+                        });
+                    }
+                    self.state = state;
+                }
+                State::Copy { offset, remaining } => {
+                    while *remaining > 0 {
+                        let at = *offset;
+                        let n = self
+                            .sectors
+                            .fill(
+                                *remaining as usize,
+                                |buf| read_base(at, buf),
+                                write_sector,
+                            )
+                            .map_err(PatchError::Io)? as u32;
+                        *offset = offset.saturating_add(n);
+                        *remaining -= n;
+                    }
+                    self.state = State::header();
+                }
+                State::Insert { remaining } => {
+                    if *remaining > 0 && input.is_empty() {
+                        return Ok(());
+                    }
+                    let n = (*remaining as usize).min(input.len());
+                    self.sectors
+                        .write(&input[..n], write_sector)
+                        .map_err(PatchError::Io)?;
+                    input = &input[n..];
+                    *remaining -= n as u32;
+                    if *remaining == 0 {
+                        self.state = State::header();
+                    }
+                }
+            }
+        }
+    }
+
+    /// Call once the whole patch has been pushed. Hands on the last partial sector padded with
+    /// `0xff`.
+    pub fn finish<E>(
+        self,
+        write_sector: &mut impl FnMut(u32, &[u8; SECTOR_SIZE]) -> Result<(), E>,
+    ) -> Result<(), PatchError<E>> {
+        if !matches!(self.state, State::Header { len: 0, .. }) {
+            return Err(PatchError::Incomplete);
+        }
+        if self.total_len != self.expected_len {
+            return Err(PatchError::WrongLength {
+                expected: self.expected_len,
+            });
+        }
+        self.sectors.finish(write_sector).map_err(PatchError::Io)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use alloc::vec::Vec;
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    struct OutOfBounds;
+
+    fn apply(
+        base: &[u8],
+        patch: &[u8],
+        expected_len: u32,
+        piece_len: usize,
+    ) -> Result<Vec<u8>, PatchError<OutOfBounds>> {
+        let mut out = Vec::new();
+        let mut read_base = |offset: u32, buf: &mut [u8]| -> Result<(), OutOfBounds> {
+            let start = offset as usize;
+            let bytes = base.get(start..start + buf.len()).ok_or(OutOfBounds)?;
+            buf.copy_from_slice(bytes);
+            Ok(())
+        };
+        let mut write_sector = |i: u32, sector: &[u8; SECTOR_SIZE]| -> Result<(), OutOfBounds> {
+            assert_eq!(i as usize, out.len() / SECTOR_SIZE);
+            out.extend_from_slice(sector);
+            Ok(())
+        };
+        let mut applier = PatchApplier::new(expected_len);
+        for piece in patch.chunks(piece_len) {
+            applier.push(piece, &mut read_base, &mut write_sector)?;
+        }
+        applier.finish(&mut write_sector)?;
+        out.truncate(expected_len as usize);
+        Ok(out)
+    }
+
+    fn encode(ops: &[PatchOp<'_>]) -> Vec<u8> {
+        let mut patch = Vec::new();
+        for op in ops {
+            op.encode(&mut patch);
+        }
+        patch
+    }
+
+    #[test]
+    fn ops_build_the_image_whatever_the_piece_size() {
+        let base: Vec<u8> = (0..10_000u32).map(|i| (i % 253) as u8).collect();
+        let ops = [
+            PatchOp::Copy {
+                offset: 100,
+                len: 5_000,
+            },
+            PatchOp::Insert(&[7; 3_000]),
+            PatchOp::Copy {
+                offset: 9_000,
+                len: 1_000,
+            },
+        ];
+        let mut image = base[100..5_100].to_vec();
+        image.extend_from_slice(&[7; 3_000]);
+        image.extend_from_slice(&base[9_000..]);
+
+        let patch = encode(&ops);
+        for piece_len in [1, 4, 9, 4096, patch.len()] {
+            assert_eq!(
+                apply(&base, &patch, image.len() as u32, piece_len).unwrap(),
+                image
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_bad_patches() {
+        let base = [1u8; 100];
+        let patch = encode(&[PatchOp::Copy {
+            offset: 0,
+            len: 50,
+        }]);
+
+        assert_eq!(
+            apply(&base, &patch[..5], 50, 4096),
+            Err(PatchError::Incomplete)
+        );
+        assert_eq!(
+            apply(&base, &patch, 49, 4096),
+            Err(PatchError::WrongLength { expected: 49 })
+        );
+        assert_eq!(
+            apply(&base, &patch, 51, 4096),
+            Err(PatchError::WrongLength { expected: 51 })
+        );
+        assert_eq!(apply(&base, &[0x02], 50, 4096), Err(PatchError::Corrupt));
+        let past_the_end = encode(&[PatchOp::Copy {
+            offset: 80,
+            len: 50,
+        }]);
+        assert_eq!(
+            apply(&base, &past_the_end, 50, 4096),
+            Err(PatchError::Io(OutOfBounds))
+        );
+    }
+}
@@ -10,7 +10,7 @@
 use crate::Sha256Digest;
 use frostsnap_macros::hex;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(bincode::Encode, bincode::Decode, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct VersionNumber {
     pub major: u8,
     pub minor: u8,
@@ -55,10 +55,14 @@ impl VersionNumber {
     pub fn features(&self) -> FirmwareFeatures {
         const V0_0_1: VersionNumber = VersionNumber::new(0, 0, 1);
         const V0_3_0: VersionNumber = VersionNumber::new(0, 3, 0);
+        const V0_4_0: VersionNumber = VersionNumber::new(0, 4, 0);
 
         FirmwareFeatures {
             upgrade_digest_no_sig: *self > V0_0_1,
             check_backup: *self >= V0_3_0,
+            compressed_upgrade: *self >= V0_4_0,
+            delta_upgrade: *self >= V0_4_0,
+            nonce_partitions: *self >= V0_4_0,
         }
     }
 }
@@ -69,6 +73,12 @@ pub struct FirmwareFeatures {
     pub upgrade_digest_no_sig: bool,
     /// Device supports the check backup quiz workflow
     pub check_backup: bool,
+    /// Device accepts a DEFLATE compressed firmware upgrade stream
+    pub compressed_upgrade: bool,
+    /// Device accepts a firmware upgrade as a patch against the firmware it's running
+    pub delta_upgrade: bool,
+    /// Device can set aside a partition of its nonce slots for each coordinator
+    pub nonce_partitions: bool,
 }
 
 impl FirmwareFeatures {
@@ -76,6 +86,9 @@ impl FirmwareFeatures {
         Self {
             upgrade_digest_no_sig: true,
             check_backup: true,
+            compressed_upgrade: true,
+            delta_upgrade: true,
+            nonce_partitions: true,
         }
     }
 }
@@ -5,11 +5,15 @@ extern crate std;
 
 #[macro_use]
 extern crate alloc;
+pub mod diagnostics;
 pub mod factory;
+pub mod firmware_compression;
+pub mod firmware_delta;
 pub mod firmware_reader;
 pub mod firmware_version;
 pub mod fixed_string;
 pub mod genuine_certificate;
+pub mod secure_boot;
 use alloc::boxed::Box;
 use alloc::string::ToString;
 use alloc::vec::Vec;
@@ -47,6 +51,10 @@ pub const FIRMWARE_UPGRADE_CHUNK_LEN: u32 = 4096;
 // Secure Boot v2 signature block constants
 pub const SIGNATURE_BLOCK_SIZE: usize = firmware_reader::SECTOR_SIZE;
 pub const SIGNATURE_BLOCK_MAGIC: [u8; 4] = [0xE7, 0x02, 0x00, 0x00];
+/// The signature sector holds one block of this length per signing key, back to back.
+pub const SIGNATURE_BLOCK_LEN: usize = 1216;
+/// One per eFuse key digest slot.
+pub const MAX_SIGNATURE_BLOCKS: usize = 3;
 
 pub const FIRMWARE_NEXT_CHUNK_READY_SIGNAL: u8 = 0x11;
 
@@ -227,6 +235,18 @@ pub enum CoordinatorSendBody {
     Upgrade(CoordinatorUpgradeMessage),
     DataErase,
     Challenge(Box<GenuineChallenge>),
+    /// Check the device's flash, storage and hardware without changing anything and send back a
+    /// [`CommsMisc::DiagnosticReport`].
+    RunDiagnostics,
+    /// Burn the revoke bit for one of the device's Secure Boot keys. Answered with
+    /// [`CommsMisc::SecureBootKeyRevocation`].
+    RevokeSecureBootKey(Box<secure_boot::SecureBootKeyRevocation>),
+    /// Send any crashes recorded since they were last reported, as [`CommsMisc::CrashReports`].
+    /// Devices only report crashes when asked so coordinators that don't know the message never
+    /// get one.
+    RequestCrashReports,
+    /// The [`CommsMisc::CrashReports`] the device sent arrived, so it can forget them.
+    CrashReportsReceived,
 }
 
 impl From<CoordinatorSendBody> for WireCoordinatorSendBody {
@@ -290,6 +310,33 @@ pub enum CoordinatorUpgradeMessage {
         size: u32,
         firmware_digest: Sha256Digest,
     },
+    /// Like [`PrepareUpgrade2`] but the image will be streamed as `compressed_size` bytes of raw
+    /// DEFLATE that decompress to the `size` byte image. Only sent when every device on the chain
+    /// has [`FirmwareFeatures::compressed_upgrade`] since they all have to know how many bytes to
+    /// forward. See [`firmware_compression`].
+    ///
+    /// [`PrepareUpgrade2`]: CoordinatorUpgradeMessage::PrepareUpgrade2
+    /// [`FirmwareFeatures::compressed_upgrade`]: firmware_version::FirmwareFeatures::compressed_upgrade
+    PrepareUpgradeCompressed {
+        size: u32,
+        compressed_size: u32,
+        firmware_digest: Sha256Digest,
+    },
+    /// The image will be streamed as a patch against the firmware with `base_digest`, compressed
+    /// as for [`PrepareUpgradeCompressed`]: `compressed_size` bytes on the wire decompressing to
+    /// the `patch_size` byte patch which builds the `size` byte image. Only sent when every device
+    /// on the chain has [`FirmwareFeatures::delta_upgrade`] and every device being upgraded is
+    /// running `base_digest`. See [`firmware_delta`].
+    ///
+    /// [`PrepareUpgradeCompressed`]: CoordinatorUpgradeMessage::PrepareUpgradeCompressed
+    /// [`FirmwareFeatures::delta_upgrade`]: firmware_version::FirmwareFeatures::delta_upgrade
+    PrepareUpgradeDelta {
+        size: u32,
+        base_digest: Sha256Digest,
+        patch_size: u32,
+        compressed_size: u32,
+        firmware_digest: Sha256Digest,
+    },
 }
 
 #[derive(Encode, Decode, Debug, Clone)]
@@ -473,6 +520,45 @@ pub enum CommsMisc {
         access_structure_ref: frostsnap_core::AccessStructureRef,
         share_index: frostsnap_core::schnorr_fun::frost::ShareIndex,
     },
+    /// The answer to [`CoordinatorSendBody::RequestCrashReports`] when the device has recorded
+    /// crashes. It keeps them until the coordinator sends
+    /// [`CoordinatorSendBody::CrashReportsReceived`].
+    CrashReports(Vec<CrashReport>),
+    /// The answer to [`CoordinatorSendBody::RunDiagnostics`].
+    DiagnosticReport(Box<diagnostics::DiagnosticReport>),
+    /// The answer to [`CoordinatorSendBody::RevokeSecureBootKey`].
+    SecureBootKeyRevocation {
+        key_digest: [u8; 32],
+        result: Result<(), secure_boot::RevocationRefused>,
+    },
+}
+
+/// What a device remembers about a panic.
+#[derive(Encode, Decode, Debug, Clone, PartialEq)]
+pub struct CrashReport {
+    pub message: String,
+    /// `file:line` of the panic, with the file stripped of its directories.
+    pub location: Option<String>,
+    /// The firmware that crashed. Not necessarily the firmware that's reporting it.
+    pub firmware_digest: Sha256Digest,
+    /// Milliseconds since boot, as of the last time the main loop ran.
+    pub uptime_ms: u64,
+    /// The kind of workflow the screen was showing.
+    pub last_workflow: String,
+}
+
+impl core::fmt::Display for CrashReport {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "panicked at {} after {}s in {} on firmware {}: {}",
+            self.location.as_deref().unwrap_or("<unknown>"),
+            self.uptime_ms / 1000,
+            self.last_workflow,
+            self.firmware_digest,
+            self.message
+        )
+    }
 }
 
 impl Gist for CommsMisc {
@@ -0,0 +1,70 @@
+//! # Secure Boot Key Revocation
+//!
+//! A device boots firmware signed by any of the (up to three) Secure Boot keys whose digests are
+//! burned into its eFuses and not revoked. If one of those keys is compromised the coordinator can
+//! tell the device to revoke it with [`CoordinatorSendBody::RevokeSecureBootKey`].
+//!
+//! Revoking can't be undone, so the instruction has to be signed by one of the *other* keys the
+//! device trusts. The signature is a Secure Boot v2 signature block (the same format appended to
+//! firmware) over [`revocation_digest`], which lets the device check it with the same code it
+//! checks firmware with. The device also refuses if its bootloader or the firmware it's running
+//! isn't signed by a key that stays trusted, since it would no longer boot.
+//!
+//! [`CoordinatorSendBody::RevokeSecureBootKey`]: crate::CoordinatorSendBody::RevokeSecureBootKey
+use alloc::vec::Vec;
+use bincode::{Decode, Encode};
+use frostsnap_core::sha2::{Digest, Sha256};
+
+const REVOCATION_DOMAIN: &[u8; 32] = b"frostsnap-revoke-secure-boot-key";
+
+#[derive(Encode, Decode, Debug, Clone, PartialEq)]
+pub struct SecureBootKeyRevocation {
+    /// The eFuse key digest of the key to revoke.
+    pub key_digest: [u8; 32],
+    /// A [`SIGNATURE_BLOCK_LEN`](crate::SIGNATURE_BLOCK_LEN) byte signature block over
+    /// [`revocation_digest`] of `key_digest`.
+    pub signature_block: Vec<u8>,
+}
+
+/// What the signature block of a [`SecureBootKeyRevocation`] for `key_digest` has to sign.
+pub fn revocation_digest(key_digest: &[u8; 32]) -> [u8; 32] {
+    let mut hash = Sha256::new();
+    hash.update(REVOCATION_DOMAIN);
+    hash.update(key_digest);
+    hash.finalize().into()
+}
+
+/// Why a device didn't revoke a key.
+#[derive(Encode, Decode, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RevocationRefused {
+    /// The key isn't one the device trusts (it may already be revoked).
+    NotTrusted,
+    /// The signature block is malformed or doesn't sign this revocation.
+    BadSignature,
+    /// The signature is by a key the device doesn't trust, or by the key being revoked.
+    UntrustedSigner,
+    /// The bootloader or the running firmware isn't signed by any key that would stay trusted.
+    WouldNotBoot,
+    /// Burning the revoke bit failed.
+    EfuseWriteFailed,
+}
+
+impl core::fmt::Display for RevocationRefused {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            RevocationRefused::NotTrusted => write!(f, "the device doesn't trust this key"),
+            RevocationRefused::BadSignature => write!(f, "the revocation isn't validly signed"),
+            RevocationRefused::UntrustedSigner => {
+                write!(f, "the revocation is signed by a key the device won't accept")
+            }
+            RevocationRefused::WouldNotBoot => write!(
+                f,
+                "the device would no longer boot without the key; upgrade its firmware first"
+            ),
+            RevocationRefused::EfuseWriteFailed => write!(f, "burning the revoke bit failed"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for RevocationRefused {}
@@ -0,0 +1,146 @@
+use std::{
+    borrow::BorrowMut,
+    collections::{BTreeSet, HashSet},
+};
+
+use frostsnap_comms::{CoordinatorSendBody, CoordinatorSendMessage, Destination};
+use frostsnap_core::{
+    coordinator::{CoordinatorToUserMessage, UpdateAddressBook},
+    message::{address_book::CoordinatorAddressBook, CoordinatorToDeviceMessage},
+    DeviceId,
+};
+
+use crate::{Completion, DeviceMode, Sink, UiProtocol};
+
+#[derive(Clone, Debug, Default)]
+pub struct AddressBookUpdateState {
+    pub target_devices: Vec<DeviceId>, // not a set for frb compat
+    pub connected_devices: HashSet<DeviceId>,
+    /// Devices whose user confirmed the change.
+    pub confirmed_by: Vec<DeviceId>,
+    pub aborted: Option<String>,
+}
+
+/// Takes an address book change to the devices of an access structure. Each device asks its user
+/// to confirm it, and the change is done once all of them have. A device that misses out only
+/// flags the address as unlisted, so the user can stop early and catch it up later.
+pub struct UpdateAddressBookProtocol {
+    state: AddressBookUpdateState,
+    message: CoordinatorAddressBook,
+    need_to_send_to: BTreeSet<DeviceId>,
+    sink: Box<dyn Sink<AddressBookUpdateState>>,
+}
+
+impl UpdateAddressBookProtocol {
+    pub fn new(
+        update: UpdateAddressBook,
+        sink: impl Sink<AddressBookUpdateState> + 'static,
+    ) -> Self {
+        Self {
+            state: AddressBookUpdateState {
+                target_devices: update.target_devices.into_iter().collect(),
+                connected_devices: Default::default(),
+                confirmed_by: Default::default(),
+                aborted: None,
+            },
+            message: update.message,
+            need_to_send_to: Default::default(),
+            sink: Box::new(sink),
+        }
+    }
+
+    pub fn emit_state(&self) {
+        self.sink.send(self.state.clone());
+    }
+
+    fn is_done(&self) -> bool {
+        self.state.confirmed_by.len() >= self.state.target_devices.len()
+    }
+}
+
+impl UiProtocol for UpdateAddressBookProtocol {
+    fn cancel(&mut self) {
+        self.state.aborted = Some("Address book update canceled".into());
+        self.emit_state();
+    }
+
+    fn is_complete(&self) -> Option<Completion> {
+        if self.is_done() {
+            Some(Completion::Success)
+        } else if self.state.aborted.is_some() {
+            Some(Completion::Abort {
+                send_cancel_to_all_devices: true,
+            })
+        } else {
+            None
+        }
+    }
+
+    fn connected(&mut self, id: DeviceId, state: DeviceMode) {
+        if self.state.target_devices.contains(&id) {
+            if state == DeviceMode::Ready && !self.state.confirmed_by.contains(&id) {
+                self.need_to_send_to.insert(id);
+            }
+            if self.state.connected_devices.insert(id) {
+                self.emit_state();
+            }
+        }
+    }
+
+    fn disconnected(&mut self, device_id: DeviceId) {
+        self.need_to_send_to.remove(&device_id);
+        if self.state.connected_devices.remove(&device_id) {
+            self.emit_state();
+        }
+    }
+
+    fn process_to_user_message(&mut self, message: CoordinatorToUserMessage) -> bool {
+        let (key_id, spk) = match &self.message {
+            CoordinatorAddressBook::AddEntry {
+                master_appkey, spk, ..
+            } => (master_appkey.key_id(), spk),
+            CoordinatorAddressBook::RemoveEntry { key_id, spk } => (*key_id, spk),
+        };
+        match message {
+            CoordinatorToUserMessage::AddressBookUpdated {
+                device_id,
+                key_id: updated_key_id,
+                spk: updated_spk,
+                ..
+            } if updated_key_id == key_id && &updated_spk == spk => {
+                if self.state.target_devices.contains(&device_id)
+                    && !self.state.confirmed_by.contains(&device_id)
+                {
+                    self.state.confirmed_by.push(device_id);
+                    self.emit_state();
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn poll(&mut self) -> Vec<CoordinatorSendMessage> {
+        let mut messages = vec![];
+        if !self.need_to_send_to.is_empty() {
+            messages.push(CoordinatorSendMessage {
+                target_destinations: Destination::Particular(core::mem::take(
+                    &mut self.need_to_send_to,
+                )),
+                message_body: CoordinatorSendBody::Core(CoordinatorToDeviceMessage::AddressBook(
+                    self.message.clone(),
+                )),
+            });
+        }
+
+        messages
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_mut_any(&mut self) -> &mut dyn std::any::Any {
+        self.borrow_mut()
+    }
+}
@@ -0,0 +1,462 @@
+//! An append-only record of the security-relevant things the coordinator has done: keys made and
+//! deleted, who signed what and when, address verifications, backup checks, firmware upgrades
+//! and device misbehaviour.
+//!
+//! The coordinator's mutations can't serve as this. They're the state store: deleting a key
+//! deletes every mutation tied to it, and they say nothing about what happened outside the core.
+//! Here entries are only ever added, and each commits to the hash of the one before it, so
+//! editing, removing or reordering an entry breaks the chain from that point on. [`verify`]
+//! checks an exported log. Someone with the database could rebuild every later hash, so an
+//! auditor should keep the [`AuditEntry::hash`] of the last entry they checked and pass it to
+//! [`verify_from`] next time.
+use crate::persist::BincodeWrapper;
+use anyhow::Context;
+use bdk_chain::rusqlite_impl::migrate_schema;
+use frostsnap_comms::Sha256Digest;
+use frostsnap_core::{
+    coordinator::{self, FrostCoordinator},
+    AccessStructureId, AccessStructureRef, DeviceId, KeyId, MasterAppkey, SignSessionId,
+    WireSignTask,
+};
+use rusqlite::params;
+use sha2::{Digest, Sha256};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(
+    Clone, Debug, PartialEq, bincode::Encode, bincode::Decode, serde::Serialize, serde::Deserialize,
+)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum AuditEvent {
+    KeyCreated {
+        key_id: KeyId,
+        access_structure_id: AccessStructureId,
+        key_name: String,
+        threshold: u16,
+        devices: Vec<DeviceId>,
+    },
+    KeyDeleted {
+        key_id: KeyId,
+    },
+    SignSessionStarted {
+        session_id: SignSessionId,
+        key_id: KeyId,
+        task: String,
+        devices: Vec<DeviceId>,
+    },
+    SignatureShareReceived {
+        session_id: SignSessionId,
+        device_id: DeviceId,
+    },
+    SignSessionCompleted {
+        session_id: SignSessionId,
+    },
+    SignSessionCancelled {
+        session_id: SignSessionId,
+    },
+    AddressShown {
+        key_id: KeyId,
+        address_index: u32,
+        devices: Vec<DeviceId>,
+    },
+    AccountXpubShown {
+        key_id: KeyId,
+        account_index: u32,
+        devices: Vec<DeviceId>,
+    },
+    BackupChecked {
+        key_id: KeyId,
+        access_structure_id: AccessStructureId,
+        device_id: DeviceId,
+        valid: bool,
+    },
+    FirmwareUpgrade {
+        firmware_digest: Sha256Digest,
+        error: Option<String>,
+    },
+    DeviceFault {
+        device_id: DeviceId,
+        fault: String,
+    },
+    DeviceQuarantined {
+        device_id: DeviceId,
+        quarantined: bool,
+    },
+    AddressBookUpdateRequested {
+        key_id: KeyId,
+        address: String,
+        /// `None` if the address is being removed.
+        label: Option<String>,
+        devices: Vec<DeviceId>,
+    },
+}
+
+impl AuditEvent {
+    /// The event a coordinator mutation records, if any. `coordinator` must already have the
+    /// mutation applied.
+    pub fn from_mutation(
+        mutation: &coordinator::Mutation,
+        coordinator: &FrostCoordinator,
+    ) -> Option<Self> {
+        use coordinator::{faults::FaultMutation, keys::KeyMutation, signing::SigningMutation};
+        Some(match mutation {
+            coordinator::Mutation::Keygen(KeyMutation::NewAccessStructure {
+                shared_key, ..
+            }) => {
+                let access_structure_ref = AccessStructureRef {
+                    key_id: MasterAppkey::from_xpub_unchecked(shared_key).key_id(),
+                    access_structure_id: AccessStructureId::from_app_poly(
+                        shared_key.key().point_polynomial(),
+                    ),
+                };
+                let key = coordinator.get_frost_key(access_structure_ref.key_id)?;
+                let access_structure = coordinator.get_access_structure(access_structure_ref)?;
+                AuditEvent::KeyCreated {
+                    key_id: access_structure_ref.key_id,
+                    access_structure_id: access_structure_ref.access_structure_id,
+                    key_name: key.key_name.clone(),
+                    threshold: access_structure.threshold(),
+                    devices: access_structure.devices().collect(),
+                }
+            }
+            coordinator::Mutation::Keygen(KeyMutation::DeleteKey(key_id)) => {
+                AuditEvent::KeyDeleted { key_id: *key_id }
+            }
+            coordinator::Mutation::Signing(SigningMutation::NewSigningSession(session)) => {
+                AuditEvent::SignSessionStarted {
+                    session_id: session.session_id(),
+                    key_id: session.key_id,
+                    task: task_summary(&session.init.group_request.sign_task),
+                    devices: session.init.nonces.keys().copied().collect(),
+                }
+            }
+            coordinator::Mutation::Signing(SigningMutation::GotSignatureSharesFromDevice {
+                session_id,
+                device_id,
+                ..
+            }) => AuditEvent::SignatureShareReceived {
+                session_id: *session_id,
+                device_id: *device_id,
+            },
+            coordinator::Mutation::Signing(SigningMutation::CloseSignSession {
+                session_id,
+                finished,
+            }) => match finished {
+                Some(_) => AuditEvent::SignSessionCompleted {
+                    session_id: *session_id,
+                },
+                None => AuditEvent::SignSessionCancelled {
+                    session_id: *session_id,
+                },
+            },
+            coordinator::Mutation::Fault(FaultMutation::RecordFault { device_id, fault }) => {
+                AuditEvent::DeviceFault {
+                    device_id: *device_id,
+                    fault: fault.to_string(),
+                }
+            }
+            coordinator::Mutation::Fault(FaultMutation::SetQuarantined {
+                device_id,
+                quarantined,
+            }) => AuditEvent::DeviceQuarantined {
+                device_id: *device_id,
+                quarantined: *quarantined,
+            },
+            _ => return None,
+        })
+    }
+}
+
+/// What a sign task asked for, in a line.
+pub fn task_summary(task: &WireSignTask) -> String {
+    match task {
+        WireSignTask::Test { message } => format!("test message {message:?}"),
+        WireSignTask::Nostr { event } => format!("nostr event {}", event.id),
+        WireSignTask::BitcoinTransaction(tx_template) => {
+            let fee = match tx_template.fee() {
+                Some(fee) => format!("{fee} sat"),
+                None => "invalid".into(),
+            };
+            format!(
+                "bitcoin transaction {} with {} inputs, {} outputs and fee {fee}",
+                tx_template.txid(),
+                tx_template.inputs().len(),
+                tx_template.outputs().len(),
+            )
+        }
+        WireSignTask::ProofOfReserves {
+            message,
+            tx_template,
+        } => format!(
+            "proof of reserves for {message:?} over {} coins",
+            tx_template.inputs().len().saturating_sub(1),
+        ),
+        WireSignTask::AddressBookEntry { label, spk } => {
+            format!("address book entry {label:?} for {spk}")
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AuditHash(pub [u8; 32]);
+
+frostsnap_core::impl_display_debug_serialize! {
+    fn to_bytes(hash: &AuditHash) -> [u8;32] {
+        hash.0
+    }
+}
+
+frostsnap_core::impl_fromstr_deserialize! {
+    name => "audit log hash",
+    fn from_bytes(bytes: [u8;32]) -> AuditHash {
+        AuditHash(bytes)
+    }
+}
+
+impl AuditHash {
+    /// What the first entry chains from.
+    pub const GENESIS: Self = AuditHash([0u8; 32]);
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct AuditEntry {
+    pub seq: u64,
+    /// Seconds since the unix epoch, by the coordinator's clock.
+    pub timestamp: u64,
+    #[serde(flatten)]
+    pub event: AuditEvent,
+    pub prev_hash: AuditHash,
+    pub hash: AuditHash,
+}
+
+impl AuditEntry {
+    fn compute_hash(
+        seq: u64,
+        timestamp: u64,
+        event: &AuditEvent,
+        prev_hash: AuditHash,
+    ) -> AuditHash {
+        let event = bincode::encode_to_vec(event, bincode::config::standard()).unwrap();
+        AuditHash(
+            Sha256::new()
+                .chain_update(b"frostsnap/audit-log")
+                .chain_update(seq.to_be_bytes())
+                .chain_update(timestamp.to_be_bytes())
+                .chain_update(prev_hash.0)
+                .chain_update(event)
+                .finalize()
+                .into(),
+        )
+    }
+
+    pub fn expected_hash(&self) -> AuditHash {
+        Self::compute_hash(self.seq, self.timestamp, &self.event, self.prev_hash)
+    }
+}
+
+pub fn migrate(conn: &mut rusqlite::Connection) -> anyhow::Result<()> {
+    const SCHEMA_NAME: &str = "frostsnap_audit_log";
+    const MIGRATIONS: &[&str] = &[
+        // Version 0
+        "CREATE TABLE IF NOT EXISTS fs_audit_log ( \
+            seq INTEGER PRIMARY KEY, \
+            timestamp INTEGER NOT NULL, \
+            event BLOB NOT NULL, \
+            prev_hash BLOB NOT NULL, \
+            hash BLOB NOT NULL \
+        )",
+    ];
+
+    let db_tx = conn.transaction()?;
+    migrate_schema(&db_tx, SCHEMA_NAME, MIGRATIONS)?;
+    db_tx.commit()?;
+    Ok(())
+}
+
+/// Add `event` to the end of the log, timestamped now.
+pub fn append(conn: &rusqlite::Connection, event: AuditEvent) -> anyhow::Result<AuditEntry> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|since| since.as_secs())
+        .unwrap_or(0);
+    append_at(conn, event, timestamp)
+}
+
+fn append_at(
+    conn: &rusqlite::Connection,
+    event: AuditEvent,
+    timestamp: u64,
+) -> anyhow::Result<AuditEntry> {
+    let last = conn.query_row(
+        "SELECT seq, hash FROM fs_audit_log ORDER BY seq DESC LIMIT 1",
+        [],
+        |row| Ok((row.get::<_, u64>(0)?, row.get::<_, [u8; 32]>(1)?)),
+    );
+    let (seq, prev_hash) = match last {
+        Ok((seq, hash)) => (seq + 1, AuditHash(hash)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => (0, AuditHash::GENESIS),
+        Err(e) => return Err(e.into()),
+    };
+    let hash = AuditEntry::compute_hash(seq, timestamp, &event, prev_hash);
+    let entry = AuditEntry {
+        seq,
+        timestamp,
+        event,
+        prev_hash,
+        hash,
+    };
+    conn.execute(
+        "INSERT INTO fs_audit_log (seq, timestamp, event, prev_hash, hash) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            entry.seq,
+            entry.timestamp,
+            BincodeWrapper(&entry.event),
+            entry.prev_hash.0,
+            entry.hash.0
+        ],
+    )?;
+    Ok(entry)
+}
+
+/// Every entry, oldest first.
+pub fn export(conn: &rusqlite::Connection) -> anyhow::Result<Vec<AuditEntry>> {
+    let mut stmt = conn
+        .prepare("SELECT seq, timestamp, event, prev_hash, hash FROM fs_audit_log ORDER BY seq")?;
+    let rows = stmt.query_map([], |row| {
+        Ok(AuditEntry {
+            seq: row.get(0)?,
+            timestamp: row.get(1)?,
+            event: row.get::<_, BincodeWrapper<AuditEvent>>(2)?.0,
+            prev_hash: AuditHash(row.get(3)?),
+            hash: AuditHash(row.get(4)?),
+        })
+    })?;
+    rows.map(|row| row.context("failed to decode an audit log entry"))
+        .collect()
+}
+
+/// The log as a JSON array, for handing to an auditor.
+pub fn export_json(conn: &rusqlite::Connection) -> anyhow::Result<String> {
+    Ok(serde_json::to_string_pretty(&export(conn)?)?)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum AuditLogError {
+    /// The entry's hash doesn't match its contents.
+    TamperedEntry { seq: u64 },
+    /// The entry doesn't chain from the one before it.
+    BrokenChain { seq: u64 },
+    /// An entry is missing or out of order.
+    MissingEntry { expected_seq: u64 },
+}
+
+impl core::fmt::Display for AuditLogError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            AuditLogError::TamperedEntry { seq } => {
+                write!(f, "audit log entry {seq} has been modified")
+            }
+            AuditLogError::BrokenChain { seq } => {
+                write!(f, "audit log entry {seq} does not follow the one before it")
+            }
+            AuditLogError::MissingEntry { expected_seq } => {
+                write!(f, "audit log entry {expected_seq} is missing")
+            }
+        }
+    }
+}
+
+impl std::error::Error for AuditLogError {}
+
+/// Check that `entries` is a whole log, unmodified.
+pub fn verify(entries: &[AuditEntry]) -> Result<(), AuditLogError> {
+    verify_from(entries, 0, AuditHash::GENESIS)
+}
+
+/// Check that `entries` carries on unmodified from an entry `seq - 1` whose hash was
+/// `prev_hash`, e.g. the end of the log as it was last audited.
+pub fn verify_from(
+    entries: &[AuditEntry],
+    seq: u64,
+    prev_hash: AuditHash,
+) -> Result<(), AuditLogError> {
+    let (mut expected_seq, mut prev_hash) = (seq, prev_hash);
+    for entry in entries {
+        if entry.seq != expected_seq {
+            return Err(AuditLogError::MissingEntry { expected_seq });
+        }
+        if entry.prev_hash != prev_hash {
+            return Err(AuditLogError::BrokenChain { seq: entry.seq });
+        }
+        if entry.expected_hash() != entry.hash {
+            return Err(AuditLogError::TamperedEntry { seq: entry.seq });
+        }
+        expected_seq += 1;
+        prev_hash = entry.hash;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn log_with_entries(n: u8) -> rusqlite::Connection {
+        let mut conn = rusqlite::Connection::open_in_memory().unwrap();
+        migrate(&mut conn).unwrap();
+        for i in 0..n {
+            append_at(
+                &conn,
+                AuditEvent::SignSessionCompleted {
+                    session_id: SignSessionId([i; 32]),
+                },
+                1_700_000_000 + i as u64,
+            )
+            .unwrap();
+        }
+        conn
+    }
+
+    #[test]
+    fn exported_log_verifies_and_survives_json() {
+        let conn = log_with_entries(3);
+        let entries = export(&conn).unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(verify(&entries), Ok(()));
+
+        let from_json: Vec<AuditEntry> =
+            serde_json::from_str(&export_json(&conn).unwrap()).unwrap();
+        assert_eq!(from_json, entries);
+        assert_eq!(verify_from(&entries[2..], 2, entries[1].hash), Ok(()));
+    }
+
+    #[test]
+    fn tampering_is_detected() {
+        let conn = log_with_entries(3);
+        let entries = export(&conn).unwrap();
+
+        let mut edited = entries.clone();
+        edited[1].event = AuditEvent::SignSessionCancelled {
+            session_id: SignSessionId([1; 32]),
+        };
+        assert_eq!(
+            verify(&edited),
+            Err(AuditLogError::TamperedEntry { seq: 1 })
+        );
+
+        let mut removed = entries.clone();
+        removed.remove(1);
+        assert_eq!(
+            verify(&removed),
+            Err(AuditLogError::MissingEntry { expected_seq: 1 })
+        );
+
+        // rewriting the entry's hash too only moves the problem to the next entry
+        let mut rehashed = entries.clone();
+        rehashed[1].timestamp += 1;
+        rehashed[1].hash = rehashed[1].expected_hash();
+        assert_eq!(
+            verify(&rehashed),
+            Err(AuditLogError::BrokenChain { seq: 2 })
+        );
+    }
+}
@@ -1,10 +1,18 @@
+pub mod animated_qr;
 pub mod chain_sync;
+pub mod export;
 mod handler_state;
 pub mod outgoing;
+pub mod payjoin;
+pub mod proof_of_reserves;
 pub mod psbt;
 pub mod send;
+pub mod spending_policy;
 pub mod status_tracker;
+pub mod sweep_vault;
 pub mod tofu;
+#[cfg(test)]
+mod test_fixture;
 pub mod wallet;
 mod wallet_persist;
 
@@ -0,0 +1,824 @@
+//! Animated QR codes for moving PSBTs, transactions and the wallet descriptor to and from
+//! air-gapped wallets.
+//!
+//! Two framings are in common use and tools generally speak only one of them, so both are here:
+//!
+//! - **UR** (BCR-2020-005): a fountain code over CBOR. PSBTs travel as `ur:crypto-psbt`, raw
+//!   transactions as `ur:bytes` and the descriptor as `ur:crypto-output` (BCR-2020-010).
+//! - **BBQr**: the file split into numbered frames behind an 8 character header saying how it's
+//!   encoded, what kind of file it is and how many frames there are.
+//!
+//! Everything here is plain data in and strings out, so what a scanner reads back can be tested
+//! without a camera or the app.
+
+use anyhow::{anyhow, Result};
+use bdk_chain::{
+    bitcoin::{
+        bip32::{ChainCode, ChildNumber, DerivationPath, Fingerprint, Xpub},
+        consensus,
+        hex::{DisplayHex, FromHex},
+        secp256k1, NetworkKind, Psbt, Transaction,
+    },
+    miniscript::{
+        descriptor::{DerivPaths, DescriptorMultiXKey, DescriptorXKey, Wildcard},
+        Descriptor, DescriptorPublicKey,
+    },
+};
+use data_encoding::BASE32_NOPAD;
+use minicbor::{
+    data::{Tag, Type},
+    Decoder, Encoder,
+};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+/// Bytes of message per UR fragment. The same as the app's PSBT encoder, which scanners already
+/// cope with.
+pub const UR_FRAGMENT_LEN: usize = 400;
+
+/// Characters of data per BBQr frame. A multiple of 8 so every frame but the last is whole base32
+/// groups, as the format requires.
+pub const BBQR_FRAME_CHARS: usize = 400;
+
+/// BBQr numbers frames with two base36 digits.
+const BBQR_MAX_FRAMES: usize = 36 * 36 - 1;
+
+// CBOR tags from BCR-2020-006 and BCR-2020-010.
+const TAG_HDKEY: u64 = 303;
+const TAG_KEYPATH: u64 = 304;
+const TAG_COIN_INFO: u64 = 305;
+const TAG_TAPROOT: u64 = 409;
+
+/// What can be moved by QR.
+#[derive(Clone, Debug, PartialEq)]
+pub enum QrPayload {
+    Psbt(Box<Psbt>),
+    Transaction(Transaction),
+    Descriptor(Descriptor<DescriptorPublicKey>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QrFormat {
+    Ur,
+    Bbqr,
+}
+
+/// Produces the frames of an animated QR code, one per call, forever. A UR code never runs out
+/// (later frames are fountain-coded mixes of earlier ones) and a BBQr code loops.
+pub struct AnimatedQrEncoder {
+    inner: EncoderInner,
+}
+
+enum EncoderInner {
+    Ur(ur::Encoder<'static>),
+    Bbqr { frames: Vec<String>, next: usize },
+}
+
+impl AnimatedQrEncoder {
+    pub fn new(payload: &QrPayload, format: QrFormat) -> Result<Self> {
+        let inner = match format {
+            QrFormat::Ur => {
+                let (ur_type, message) = ur_message(payload)?;
+                EncoderInner::Ur(
+                    ur::Encoder::new(&message, UR_FRAGMENT_LEN, ur_type)
+                        .map_err(|e| anyhow!("UR encoding failed: {e}"))?,
+                )
+            }
+            QrFormat::Bbqr => EncoderInner::Bbqr {
+                frames: bbqr_frames(payload)?,
+                next: 0,
+            },
+        };
+        Ok(Self { inner })
+    }
+
+    /// The next frame, uppercased so it fits QR's denser alphanumeric mode.
+    pub fn next_frame(&mut self) -> String {
+        match &mut self.inner {
+            EncoderInner::Ur(encoder) => encoder
+                .next_part()
+                .expect("encoder was built from a non-empty message")
+                .to_uppercase(),
+            EncoderInner::Bbqr { frames, next } => {
+                let frame = frames[*next].clone();
+                *next = (*next + 1) % frames.len();
+                frame
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum QrDecodeStatus {
+    /// Still collecting. `total` is the number of distinct frames needed, once a frame has said.
+    Progress {
+        received: usize,
+        total: Option<usize>,
+    },
+    Decoded(QrPayload),
+}
+
+/// Reassembles what [`AnimatedQrEncoder`] (or another tool speaking either framing) produces.
+/// Frames can arrive in any order and repeat; the first frame decides which framing the rest
+/// must be in.
+#[derive(Default)]
+pub struct AnimatedQrDecoder {
+    ur: Option<UrCollector>,
+    bbqr: Option<BbqrCollector>,
+}
+
+impl AnimatedQrDecoder {
+    pub fn receive(&mut self, frame: &str) -> Result<QrDecodeStatus> {
+        let frame = frame.trim();
+        if frame
+            .get(..3)
+            .is_some_and(|prefix| prefix.eq_ignore_ascii_case("ur:"))
+        {
+            self.receive_ur(&frame.to_lowercase())
+        } else if frame.starts_with("B$") {
+            self.receive_bbqr(frame)
+        } else {
+            Err(anyhow!("not a UR or BBQr frame"))
+        }
+    }
+
+    fn receive_ur(&mut self, frame: &str) -> Result<QrDecodeStatus> {
+        if self.bbqr.is_some() {
+            return Err(anyhow!("got a UR frame while reading a BBQr code"));
+        }
+        let ur_type = frame[3..]
+            .split('/')
+            .next()
+            .filter(|ur_type| !ur_type.is_empty())
+            .ok_or_else(|| anyhow!("UR frame has no type"))?
+            .to_string();
+
+        if let (ur::ur::Kind::SinglePart, message) =
+            ur::decode(frame).map_err(|e| anyhow!("invalid UR: {e}"))?
+        {
+            return Ok(QrDecodeStatus::Decoded(ur_payload(&ur_type, &message)?));
+        }
+
+        let collector = self.ur.get_or_insert_with(|| UrCollector {
+            ur_type: ur_type.clone(),
+            decoder: ur::Decoder::default(),
+            received: 0,
+        });
+        if collector.ur_type != ur_type {
+            return Err(anyhow!(
+                "got a ur:{ur_type} frame while reading a ur:{}",
+                collector.ur_type
+            ));
+        }
+        collector
+            .decoder
+            .receive(frame)
+            .map_err(|e| anyhow!("invalid UR frame: {e}"))?;
+        collector.received += 1;
+        if collector.decoder.complete() {
+            let message = collector
+                .decoder
+                .message()
+                .map_err(|e| anyhow!("UR frames are inconsistent: {e}"))?
+                .expect("complete");
+            return Ok(QrDecodeStatus::Decoded(ur_payload(&ur_type, &message)?));
+        }
+        // The fountain code doesn't need any particular frames, so "received" is only a count of
+        // the ones that arrived; it can pass `total` before the message is complete.
+        Ok(QrDecodeStatus::Progress {
+            received: collector.received,
+            total: Some(collector.decoder.sequence_count()),
+        })
+    }
+
+    fn receive_bbqr(&mut self, frame: &str) -> Result<QrDecodeStatus> {
+        if self.ur.is_some() {
+            return Err(anyhow!("got a BBQr frame while reading a UR code"));
+        }
+        let header = BbqrHeader::parse(frame)?;
+        let collector = self.bbqr.get_or_insert_with(|| BbqrCollector {
+            header,
+            frames: BTreeMap::new(),
+        });
+        if collector.header.encoding != header.encoding
+            || collector.header.file_type != header.file_type
+            || collector.header.total != header.total
+        {
+            return Err(anyhow!("BBQr frame belongs to a different code"));
+        }
+        collector
+            .frames
+            .insert(header.index, frame[BbqrHeader::LEN..].to_string());
+
+        if collector.frames.len() < collector.header.total {
+            return Ok(QrDecodeStatus::Progress {
+                received: collector.frames.len(),
+                total: Some(collector.header.total),
+            });
+        }
+        let data = collector.frames.values().cloned().collect::<String>();
+        let bytes = match collector.header.encoding {
+            '2' => BASE32_NOPAD
+                .decode(data.as_bytes())
+                .map_err(|e| anyhow!("invalid BBQr base32: {e}"))?,
+            'H' => Vec::<u8>::from_hex(&data).map_err(|e| anyhow!("invalid BBQr hex: {e}"))?,
+            'Z' => return Err(anyhow!("compressed BBQr codes aren't supported")),
+            other => return Err(anyhow!("unknown BBQr encoding {other:?}")),
+        };
+        let payload = match collector.header.file_type {
+            'P' => QrPayload::Psbt(Box::new(
+                Psbt::deserialize(&bytes).map_err(|e| anyhow!("invalid PSBT: {e}"))?,
+            )),
+            'T' => QrPayload::Transaction(
+                consensus::deserialize(&bytes).map_err(|e| anyhow!("invalid transaction: {e}"))?,
+            ),
+            'U' => {
+                let text =
+                    core::str::from_utf8(&bytes).map_err(|_| anyhow!("BBQr text isn't UTF-8"))?;
+                QrPayload::Descriptor(
+                    Descriptor::from_str(text.trim())
+                        .map_err(|e| anyhow!("invalid descriptor: {e}"))?,
+                )
+            }
+            other => return Err(anyhow!("unsupported BBQr file type {other:?}")),
+        };
+        Ok(QrDecodeStatus::Decoded(payload))
+    }
+}
+
+struct UrCollector {
+    ur_type: String,
+    decoder: ur::Decoder,
+    received: usize,
+}
+
+struct BbqrCollector {
+    header: BbqrHeader,
+    frames: BTreeMap<usize, String>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct BbqrHeader {
+    encoding: char,
+    file_type: char,
+    total: usize,
+    index: usize,
+}
+
+impl BbqrHeader {
+    const LEN: usize = 8;
+
+    fn parse(frame: &str) -> Result<Self> {
+        let header = frame
+            .get(..Self::LEN)
+            .filter(|header| header.is_ascii())
+            .ok_or_else(|| anyhow!("BBQr frame doesn't start with a header"))?;
+        let mut chars = header[2..].chars();
+        let encoding = chars.next().expect("length checked");
+        let file_type = chars.next().expect("length checked");
+        let base36 = |digits: &str| {
+            usize::from_str_radix(digits, 36).map_err(|_| anyhow!("invalid BBQr frame number"))
+        };
+        let total = base36(&header[4..6])?;
+        let index = base36(&header[6..8])?;
+        if index >= total {
+            return Err(anyhow!("BBQr frame {index} of {total} is out of range"));
+        }
+        Ok(Self {
+            encoding,
+            file_type,
+            total,
+            index,
+        })
+    }
+}
+
+fn ur_message(payload: &QrPayload) -> Result<(&'static str, Vec<u8>)> {
+    Ok(match payload {
+        QrPayload::Psbt(psbt) => ("crypto-psbt", cbor_bytes(&psbt.serialize())),
+        QrPayload::Transaction(tx) => ("bytes", cbor_bytes(&consensus::serialize(tx))),
+        QrPayload::Descriptor(descriptor) => ("crypto-output", crypto_output(descriptor)?),
+    })
+}
+
+fn ur_payload(ur_type: &str, message: &[u8]) -> Result<QrPayload> {
+    let byte_string = || {
+        let mut decoder = Decoder::new(message);
+        let bytes = decoder
+            .bytes()
+            .map_err(|e| anyhow!("ur:{ur_type} should be a CBOR byte string: {e}"))?;
+        finished(&decoder, message)?;
+        Ok::<_, anyhow::Error>(bytes)
+    };
+    Ok(match ur_type {
+        "crypto-psbt" | "psbt" => QrPayload::Psbt(Box::new(
+            Psbt::deserialize(byte_string()?).map_err(|e| anyhow!("invalid PSBT: {e}"))?,
+        )),
+        "bytes" => QrPayload::Transaction(
+            consensus::deserialize(byte_string()?)
+                .map_err(|e| anyhow!("ur:bytes isn't a transaction: {e}"))?,
+        ),
+        "crypto-output" | "output-descriptor" => {
+            QrPayload::Descriptor(from_crypto_output(message)?)
+        }
+        other => return Err(anyhow!("unsupported UR type ur:{other}")),
+    })
+}
+
+fn bbqr_frames(payload: &QrPayload) -> Result<Vec<String>> {
+    let (file_type, encoding, data) = match payload {
+        QrPayload::Psbt(psbt) => ('P', '2', BASE32_NOPAD.encode(&psbt.serialize())),
+        QrPayload::Transaction(tx) => ('T', '2', BASE32_NOPAD.encode(&consensus::serialize(tx))),
+        // Descriptors are text that hex keeps readable to anything that skips the header.
+        QrPayload::Descriptor(descriptor) => (
+            'U',
+            'H',
+            descriptor.to_string().as_bytes().to_upper_hex_string(),
+        ),
+    };
+    let chunks = data
+        .as_bytes()
+        .chunks(BBQR_FRAME_CHARS)
+        .map(|chunk| core::str::from_utf8(chunk).expect("ascii"))
+        .collect::<Vec<_>>();
+    let total = chunks.len();
+    if total > BBQR_MAX_FRAMES {
+        return Err(anyhow!("too large for a BBQr code: {total} frames"));
+    }
+    Ok(chunks
+        .into_iter()
+        .enumerate()
+        .map(|(index, chunk)| {
+            format!(
+                "B${encoding}{file_type}{}{}{chunk}",
+                base36_pair(total),
+                base36_pair(index)
+            )
+        })
+        .collect())
+}
+
+fn base36_pair(n: usize) -> String {
+    let digit = |d: usize| {
+        char::from_digit(d as u32, 36)
+            .expect("< 36")
+            .to_ascii_uppercase()
+    };
+    [digit(n / 36), digit(n % 36)].into_iter().collect()
+}
+
+fn cbor_bytes(bytes: &[u8]) -> Vec<u8> {
+    let mut encoder = Encoder::new(vec![]);
+    encoder.bytes(bytes).expect("writing to a Vec");
+    encoder.into_writer()
+}
+
+/// The `tr(...)` descriptors this wallet exports as a crypto-output. The `<0;1>/*` multipath
+/// the wallet uses is written without children, which importers read as the usual receive and
+/// change keychains; a single keychain is written out in full.
+fn crypto_output(descriptor: &Descriptor<DescriptorPublicKey>) -> Result<Vec<u8>> {
+    let Descriptor::Tr(tr) = descriptor else {
+        return Err(anyhow!(
+            "only tr() descriptors are exported as crypto-output"
+        ));
+    };
+    if tr.tap_tree().is_some() {
+        return Err(anyhow!(
+            "tr() descriptors with script paths can't be exported"
+        ));
+    }
+    let (origin, xkey, children) = match tr.internal_key() {
+        DescriptorPublicKey::MultiXPub(key) => {
+            let receive_and_change = [0, 1].map(|keychain| {
+                DerivationPath::from(vec![ChildNumber::from_normal_idx(keychain).unwrap()])
+            });
+            if key.derivation_paths.paths()[..] != receive_and_change
+                || key.wildcard != Wildcard::Unhardened
+            {
+                return Err(anyhow!("only <0;1>/* multipath keys can be exported"));
+            }
+            (&key.origin, &key.xkey, None)
+        }
+        DescriptorPublicKey::XPub(key) => (
+            &key.origin,
+            &key.xkey,
+            Some((&key.derivation_path, key.wildcard)),
+        ),
+        DescriptorPublicKey::Single(_) => {
+            return Err(anyhow!("a descriptor without an xpub can't be exported"))
+        }
+    };
+
+    let mut encoder = Encoder::new(vec![]);
+    let fields = 3 + origin.is_some() as u64 + children.is_some() as u64;
+    encoder
+        .tag(Tag::Unassigned(TAG_TAPROOT))?
+        .tag(Tag::Unassigned(TAG_HDKEY))?
+        .map(fields)?
+        .u8(3)?
+        .bytes(&xkey.public_key.serialize())?
+        .u8(4)?
+        .bytes(&xkey.chain_code.to_bytes())?
+        .u8(5)?
+        .tag(Tag::Unassigned(TAG_COIN_INFO))?
+        .map(1)?
+        .u8(2)?
+        .u8(match xkey.network {
+            NetworkKind::Main => 0,
+            NetworkKind::Test => 1,
+        })?;
+    if let Some((fingerprint, path)) = origin {
+        encoder.u8(6)?;
+        keypath(&mut encoder, path, None, Some(*fingerprint))?;
+    }
+    if let Some((path, wildcard)) = children {
+        encoder.u8(7)?;
+        keypath(&mut encoder, path, Some(wildcard), None)?;
+    }
+    Ok(encoder.into_writer())
+}
+
+fn keypath(
+    encoder: &mut Encoder<Vec<u8>>,
+    path: &DerivationPath,
+    wildcard: Option<Wildcard>,
+    source_fingerprint: Option<Fingerprint>,
+) -> Result<()> {
+    let wildcard = wildcard.filter(|wildcard| *wildcard != Wildcard::None);
+    let components = 2 * (path.len() + wildcard.is_some() as usize) as u64;
+    encoder
+        .tag(Tag::Unassigned(TAG_KEYPATH))?
+        .map(if source_fingerprint.is_some() { 3 } else { 1 })?
+        .u8(1)?
+        .array(components)?;
+    for child in path {
+        let (index, hardened) = match *child {
+            ChildNumber::Normal { index } => (index, false),
+            ChildNumber::Hardened { index } => (index, true),
+        };
+        encoder.u32(index)?.bool(hardened)?;
+    }
+    if let Some(wildcard) = wildcard {
+        encoder.array(0)?.bool(wildcard == Wildcard::Hardened)?;
+    }
+    if let Some(fingerprint) = source_fingerprint {
+        encoder
+            .u8(2)?
+            .u32(u32::from_be_bytes(fingerprint.to_bytes()))?
+            .u8(3)?
+            .u64(path.len() as u64)?;
+    }
+    Ok(())
+}
+
+fn from_crypto_output(message: &[u8]) -> Result<Descriptor<DescriptorPublicKey>> {
+    let mut decoder = Decoder::new(message);
+    if decoder.tag()? != Tag::Unassigned(TAG_TAPROOT) {
+        return Err(anyhow!("only tr() crypto-outputs are supported"));
+    }
+    if decoder.tag()? != Tag::Unassigned(TAG_HDKEY) {
+        return Err(anyhow!("crypto-output key must be a crypto-hdkey"));
+    }
+
+    let mut public_key = None;
+    let mut chain_code = None;
+    let mut network = NetworkKind::Main;
+    let mut origin = None;
+    let mut children = None;
+    let mut parent_fingerprint = Fingerprint::default();
+    for _ in 0..definite(decoder.map()?)? {
+        match decoder.u64()? {
+            3 => {
+                public_key = Some(
+                    secp256k1::PublicKey::from_slice(decoder.bytes()?)
+                        .map_err(|e| anyhow!("invalid key: {e}"))?,
+                )
+            }
+            4 => {
+                chain_code = Some(
+                    <[u8; 32]>::try_from(decoder.bytes()?)
+                        .map_err(|_| anyhow!("chain code isn't 32 bytes"))?,
+                )
+            }
+            5 => network = coin_info(&mut decoder)?,
+            6 => {
+                let (path, _, fingerprint) = parse_keypath(&mut decoder)?;
+                origin = Some((fingerprint.unwrap_or_default(), path));
+            }
+            7 => children = Some(parse_keypath(&mut decoder)?),
+            8 => parent_fingerprint = Fingerprint::from(decoder.u32()?.to_be_bytes()),
+            _ => decoder.skip()?,
+        }
+    }
+    finished(&decoder, message)?;
+
+    let xkey = Xpub {
+        network,
+        depth: origin.as_ref().map_or(0, |(_, path)| path.len() as u8),
+        parent_fingerprint,
+        child_number: origin
+            .as_ref()
+            .and_then(|(_, path)| path.into_iter().last().copied())
+            .unwrap_or(ChildNumber::from_normal_idx(0).unwrap()),
+        public_key: public_key.ok_or_else(|| anyhow!("crypto-hdkey has no key"))?,
+        chain_code: ChainCode::from(
+            chain_code.ok_or_else(|| anyhow!("crypto-hdkey has no chain code"))?,
+        ),
+    };
+
+    let key = match children {
+        None => DescriptorPublicKey::MultiXPub(DescriptorMultiXKey {
+            origin,
+            xkey,
+            derivation_paths: DerivPaths::new(
+                [0, 1]
+                    .into_iter()
+                    .map(|keychain| {
+                        DerivationPath::from(vec![ChildNumber::from_normal_idx(keychain).unwrap()])
+                    })
+                    .collect(),
+            )
+            .expect("two paths"),
+            wildcard: Wildcard::Unhardened,
+        }),
+        Some((derivation_path, wildcard, _)) => DescriptorPublicKey::XPub(DescriptorXKey {
+            origin,
+            xkey,
+            derivation_path,
+            wildcard,
+        }),
+    };
+    Descriptor::new_tr(key, None).map_err(|e| anyhow!("invalid descriptor: {e}"))
+}
+
+fn coin_info(decoder: &mut Decoder<'_>) -> Result<NetworkKind> {
+    if decoder.tag()? != Tag::Unassigned(TAG_COIN_INFO) {
+        return Err(anyhow!("invalid coin info"));
+    }
+    let mut network = NetworkKind::Main;
+    for _ in 0..definite(decoder.map()?)? {
+        match decoder.u64()? {
+            2 => {
+                network = match decoder.u64()? {
+                    0 => NetworkKind::Main,
+                    _ => NetworkKind::Test,
+                }
+            }
+            _ => decoder.skip()?,
+        }
+    }
+    Ok(network)
+}
+
+fn parse_keypath(
+    decoder: &mut Decoder<'_>,
+) -> Result<(DerivationPath, Wildcard, Option<Fingerprint>)> {
+    if decoder.tag()? != Tag::Unassigned(TAG_KEYPATH) {
+        return Err(anyhow!("expected a crypto-keypath"));
+    }
+    let mut components = None;
+    let mut fingerprint = None;
+    for _ in 0..definite(decoder.map()?)? {
+        match decoder.u64()? {
+            1 => components = Some(parse_components(decoder)?),
+            2 => fingerprint = Some(Fingerprint::from(decoder.u32()?.to_be_bytes())),
+            _ => decoder.skip()?,
+        }
+    }
+    let (path, wildcard) = components.ok_or_else(|| anyhow!("crypto-keypath has no components"))?;
+    Ok((path, wildcard, fingerprint))
+}
+
+fn parse_components(decoder: &mut Decoder<'_>) -> Result<(DerivationPath, Wildcard)> {
+    let len = definite(decoder.array()?)?;
+    if len % 2 != 0 {
+        return Err(anyhow!("unsupported crypto-keypath component"));
+    }
+    let mut path = vec![];
+    let mut wildcard = Wildcard::None;
+    for _ in 0..len / 2 {
+        if wildcard != Wildcard::None {
+            return Err(anyhow!("a wildcard must be the last component"));
+        }
+        if decoder.datatype()? == Type::Array {
+            if decoder.array()? != Some(0) {
+                return Err(anyhow!("unsupported crypto-keypath component"));
+            }
+            wildcard = if decoder.bool()? {
+                Wildcard::Hardened
+            } else {
+                Wildcard::Unhardened
+            };
+        } else {
+            let index = decoder.u32()?;
+            path.push(
+                if decoder.bool()? {
+                    ChildNumber::from_hardened_idx(index)
+                } else {
+                    ChildNumber::from_normal_idx(index)
+                }
+                .map_err(|e| anyhow!("invalid path component: {e}"))?,
+            );
+        }
+    }
+    Ok((DerivationPath::from(path), wildcard))
+}
+
+/// The UR types here never use indefinite lengths, so a code that does isn't one of them.
+fn definite(len: Option<u64>) -> Result<u64> {
+    len.ok_or_else(|| anyhow!("indefinite length CBOR isn't supported"))
+}
+
+fn finished(decoder: &Decoder<'_>, message: &[u8]) -> Result<()> {
+    if decoder.position() != message.len() {
+        return Err(anyhow!("trailing bytes after CBOR value"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bitcoin::multi_x_descriptor_for_account;
+    use bdk_chain::bitcoin::{absolute, transaction, Amount, OutPoint, ScriptBuf, TxIn, TxOut};
+    use frostsnap_core::{schnorr_fun::fun::Point, tweak::BitcoinAccount, MasterAppkey};
+
+    fn tx_with_outputs(n_outputs: usize) -> Transaction {
+        Transaction {
+            version: transaction::Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: OutPoint::null(),
+                ..Default::default()
+            }],
+            output: (0..n_outputs)
+                .map(|i| TxOut {
+                    value: Amount::from_sat(1_000 + i as u64),
+                    script_pubkey: ScriptBuf::from_bytes(vec![0x51, 0x20, i as u8]),
+                })
+                .collect(),
+        }
+    }
+
+    fn descriptor() -> Descriptor<DescriptorPublicKey> {
+        let master_appkey =
+            MasterAppkey::derive_from_rootkey(Point::random(&mut rand::thread_rng()));
+        multi_x_descriptor_for_account(master_appkey, BitcoinAccount::default(), NetworkKind::Main)
+    }
+
+    /// Feed frames from the encoder until the decoder has the whole thing. A fountain code can
+    /// need more frames than it was split into, so allow a few rounds.
+    fn scan(encoder: &mut AnimatedQrEncoder) -> (QrPayload, usize) {
+        let mut decoder = AnimatedQrDecoder::default();
+        for scanned in 1..=1_000 {
+            if let QrDecodeStatus::Decoded(payload) =
+                decoder.receive(&encoder.next_frame()).unwrap()
+            {
+                return (payload, scanned);
+            }
+        }
+        panic!("never finished decoding");
+    }
+
+    fn spks(descriptor: &Descriptor<DescriptorPublicKey>) -> Vec<ScriptBuf> {
+        descriptor
+            .clone()
+            .into_single_descriptors()
+            .unwrap()
+            .into_iter()
+            .flat_map(|descriptor| {
+                (0..3).map(move |i| descriptor.at_derivation_index(i).unwrap().script_pubkey())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn psbt_round_trips_in_both_formats() {
+        let psbt = QrPayload::Psbt(Box::new(
+            Psbt::from_unsigned_tx(tx_with_outputs(40)).unwrap(),
+        ));
+        for format in [QrFormat::Ur, QrFormat::Bbqr] {
+            let mut encoder = AnimatedQrEncoder::new(&psbt, format).unwrap();
+            let (decoded, scanned) = scan(&mut encoder);
+            assert_eq!(decoded, psbt, "{format:?}");
+            assert!(scanned > 1, "{format:?} should need several frames");
+        }
+    }
+
+    #[test]
+    fn transaction_round_trips_in_both_formats() {
+        let tx = QrPayload::Transaction(tx_with_outputs(3));
+        for format in [QrFormat::Ur, QrFormat::Bbqr] {
+            let mut encoder = AnimatedQrEncoder::new(&tx, format).unwrap();
+            assert_eq!(scan(&mut encoder).0, tx, "{format:?}");
+        }
+    }
+
+    #[test]
+    fn frames_use_the_expected_framing() {
+        let psbt = QrPayload::Psbt(Box::new(
+            Psbt::from_unsigned_tx(tx_with_outputs(40)).unwrap(),
+        ));
+        let ur_frame = AnimatedQrEncoder::new(&psbt, QrFormat::Ur)
+            .unwrap()
+            .next_frame();
+        assert!(ur_frame.starts_with("UR:CRYPTO-PSBT/1-"), "{ur_frame}");
+
+        let mut bbqr = AnimatedQrEncoder::new(&psbt, QrFormat::Bbqr).unwrap();
+        let first = bbqr.next_frame();
+        let total = usize::from_str_radix(&first[4..6], 36).unwrap();
+        assert!(first.starts_with("B$2P"), "{first}");
+        assert_eq!(&first[6..8], "00");
+        for _ in 1..total {
+            bbqr.next_frame();
+        }
+        assert_eq!(bbqr.next_frame(), first, "BBQr frames loop");
+    }
+
+    /// The wallet's own descriptor, exported and read back, must derive the same addresses: the
+    /// point of exporting it is a watch-only wallet that sees the same coins.
+    #[test]
+    fn descriptor_round_trips_as_crypto_output() {
+        let descriptor = descriptor();
+        let mut encoder =
+            AnimatedQrEncoder::new(&QrPayload::Descriptor(descriptor.clone()), QrFormat::Ur)
+                .unwrap();
+        assert!(encoder.next_frame().starts_with("UR:CRYPTO-OUTPUT/"));
+        let QrPayload::Descriptor(decoded) = scan(&mut encoder).0 else {
+            panic!("expected a descriptor");
+        };
+        assert_eq!(spks(&decoded), spks(&descriptor));
+        let origin = |descriptor: &Descriptor<DescriptorPublicKey>| match descriptor {
+            Descriptor::Tr(tr) => match tr.internal_key() {
+                DescriptorPublicKey::MultiXPub(key) => key.origin.clone(),
+                _ => panic!("expected a multipath key"),
+            },
+            _ => panic!("expected tr()"),
+        };
+        assert_eq!(origin(&decoded), origin(&descriptor));
+    }
+
+    #[test]
+    fn a_single_keychain_descriptor_keeps_its_children() {
+        let external = descriptor().into_single_descriptors().unwrap().remove(0);
+        let message = crypto_output(&external).unwrap();
+        let decoded = from_crypto_output(&message).unwrap();
+        assert_eq!(spks(&decoded), spks(&external));
+        match decoded {
+            Descriptor::Tr(tr) => match tr.internal_key() {
+                DescriptorPublicKey::XPub(key) => {
+                    assert_eq!(key.derivation_path.to_string(), "0");
+                    assert_eq!(key.wildcard, Wildcard::Unhardened);
+                }
+                _ => panic!("expected a single path key"),
+            },
+            _ => panic!("expected tr()"),
+        }
+    }
+
+    #[test]
+    fn descriptor_round_trips_through_bbqr() {
+        let descriptor = QrPayload::Descriptor(descriptor());
+        let mut encoder = AnimatedQrEncoder::new(&descriptor, QrFormat::Bbqr).unwrap();
+        assert_eq!(scan(&mut encoder).0, descriptor);
+    }
+
+    /// A byte string's length goes in the CBOR head in as few bytes as it fits. Get the width
+    /// wrong and a scanner reads the length from the first bytes of the PSBT.
+    #[test]
+    fn byte_string_lengths_use_the_right_head() {
+        for (len, head) in [
+            (10, vec![0x4a]),
+            (200, vec![0x58, 200]),
+            (1_000, vec![0x59, 0x03, 0xe8]),
+            (70_000, vec![0x5a, 0x00, 0x01, 0x11, 0x70]),
+        ] {
+            let encoded = cbor_bytes(&vec![0u8; len]);
+            assert_eq!(encoded[..head.len()], head[..], "length {len}");
+            assert_eq!(encoded.len(), head.len() + len);
+            assert_eq!(Decoder::new(&encoded).bytes().unwrap(), &vec![0u8; len][..]);
+        }
+    }
+
+    #[test]
+    fn frames_from_another_code_are_refused() {
+        let mut decoder = AnimatedQrDecoder::default();
+        let mut psbt = AnimatedQrEncoder::new(
+            &QrPayload::Psbt(Box::new(
+                Psbt::from_unsigned_tx(tx_with_outputs(40)).unwrap(),
+            )),
+            QrFormat::Bbqr,
+        )
+        .unwrap();
+        let mut tx =
+            AnimatedQrEncoder::new(&QrPayload::Transaction(tx_with_outputs(40)), QrFormat::Bbqr)
+                .unwrap();
+        decoder.receive(&psbt.next_frame()).unwrap();
+        assert!(decoder.receive(&tx.next_frame()).is_err());
+
+        let mut ur =
+            AnimatedQrEncoder::new(&QrPayload::Transaction(tx_with_outputs(40)), QrFormat::Ur)
+                .unwrap();
+        assert!(decoder.receive(&ur.next_frame()).is_err());
+    }
+}
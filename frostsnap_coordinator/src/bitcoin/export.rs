@@ -0,0 +1,315 @@
+//! Exporting a key's history and coins for bookkeeping.
+//!
+//! Everything here is denominated in sats and carries no prices: what a transaction was worth in
+//! some currency on the day is the accountant's question, and answering it here would bake one
+//! price source into a file meant to outlive it. The records are plain data so the CSV and JSON
+//! forms are two renderings of one answer rather than two answers.
+
+use super::wallet::{CoordSuperWallet, Transaction};
+use anyhow::{anyhow, Result};
+use bdk_chain::{
+    bitcoin::{self, BlockHash, OutPoint, Txid},
+    CanonicalizationParams, ChainPosition, ConfirmationBlockTime,
+};
+use frostsnap_core::{
+    tweak::{BitcoinBip32Path, NormalIndex},
+    MasterAppkey,
+};
+use std::{collections::HashMap, fmt::Write};
+
+/// One transaction as it touched a key.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize)]
+pub struct HistoryRecord {
+    pub txid: Txid,
+    /// Block time when confirmed, otherwise when we last saw it unconfirmed.
+    pub time: Option<u64>,
+    /// `None` while unconfirmed.
+    pub height: Option<u32>,
+    /// Change to the key's balance. `None` when a prevout is missing, see
+    /// [`Transaction::net_value`].
+    pub net_sats: Option<i64>,
+    pub fee: Option<u64>,
+    pub label: Option<String>,
+}
+
+impl HistoryRecord {
+    fn from_transaction(tx: &Transaction, labels: &HashMap<Txid, String>) -> Self {
+        Self {
+            txid: tx.txid,
+            time: tx
+                .confirmation_time
+                .as_ref()
+                .map(|c| c.time)
+                .or(tx.last_seen),
+            height: tx.confirmation_time.as_ref().map(|c| c.height),
+            net_sats: tx.net_value(),
+            fee: tx.fee(),
+            label: labels.get(&tx.txid).cloned(),
+        }
+    }
+}
+
+/// A coin the key held at the end of a given block.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize)]
+pub struct UtxoRecord {
+    pub outpoint: OutPoint,
+    pub value: u64,
+    pub address: String,
+    /// "Receive #3", "Change #2" — see [`BitcoinBip32Path::label`].
+    pub path: String,
+    pub height: u32,
+}
+
+/// Every coin a key held at the end of block `height`.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize)]
+pub struct UtxoSnapshot {
+    pub height: u32,
+    /// The block the snapshot was taken against, so a reader can check it against their own node.
+    pub block_hash: BlockHash,
+    pub total: u64,
+    pub utxos: Vec<UtxoRecord>,
+}
+
+impl CoordSuperWallet {
+    /// The key's history oldest-first, which is the order a ledger is read in.
+    ///
+    /// `labels` are the caller's notes on transactions; the wallet keeps none itself.
+    pub fn export_history(
+        &mut self,
+        master_appkey: MasterAppkey,
+        labels: &HashMap<Txid, String>,
+    ) -> Vec<HistoryRecord> {
+        let mut records = self
+            .list_transactions(master_appkey)
+            .iter()
+            .map(|tx| HistoryRecord::from_transaction(tx, labels))
+            .collect::<Vec<_>>();
+        // `list_transactions` is newest-first with pending at the front
+        records.reverse();
+        records
+    }
+
+    /// The key's coins as they stood at the end of block `height`.
+    ///
+    /// Canonicalised against the chain cut at that block, so a coin confirmed later isn't held
+    /// yet and a spend confirmed later hasn't happened. Unconfirmed activity never counts: an
+    /// auditor asks what was on chain at a height, not what was in our mempool.
+    pub fn utxo_snapshot(
+        &mut self,
+        master_appkey: MasterAppkey,
+        height: u32,
+    ) -> Result<UtxoSnapshot> {
+        self.lazily_initialize_key(master_appkey);
+        let tip = self.chain.tip();
+        if height > tip.height() {
+            return Err(anyhow!(
+                "can't take a snapshot at {height}, we are only synced to {}",
+                tip.height()
+            ));
+        }
+        let at = tip
+            .iter()
+            .find(|cp| cp.height() <= height)
+            .expect("genesis is always in the chain");
+        let confirmed_by_height = |position: &ChainPosition<ConfirmationBlockTime>| match position {
+            ChainPosition::Confirmed { anchor, .. } => anchor.block_id.height <= height,
+            ChainPosition::Unconfirmed { .. } => false,
+        };
+
+        let mut utxos = self
+            .tx_graph
+            .graph()
+            .filter_chain_txouts(
+                self.chain.as_ref(),
+                at.block_id(),
+                CanonicalizationParams::default(),
+                self.tx_graph
+                    .index
+                    .keychain_outpoints_in_range(Self::key_index_range(master_appkey)),
+            )
+            .filter(|(_, txout)| confirmed_by_height(&txout.chain_position))
+            .filter(|(_, txout)| match &txout.spent_by {
+                Some((position, _)) => !confirmed_by_height(position),
+                None => true,
+            })
+            .map(|(((_, account_keychain), index), txout)| {
+                let path = BitcoinBip32Path {
+                    account_keychain,
+                    index: NormalIndex::new(index)
+                        .expect("bdk derived this spk, so its index is a normal child"),
+                };
+                let height = match txout.chain_position {
+                    ChainPosition::Confirmed { anchor, .. } => anchor.block_id.height,
+                    ChainPosition::Unconfirmed { .. } => unreachable!("filtered above"),
+                };
+                UtxoRecord {
+                    outpoint: txout.outpoint,
+                    value: txout.txout.value.to_sat(),
+                    address: bitcoin::Address::from_script(&txout.txout.script_pubkey, self.network)
+                        .map(|address| address.to_string())
+                        .unwrap_or_default(),
+                    path: path.label(),
+                    height,
+                }
+            })
+            .collect::<Vec<_>>();
+        utxos.sort_by_key(|utxo| (utxo.height, utxo.outpoint));
+
+        Ok(UtxoSnapshot {
+            height,
+            block_hash: at.hash(),
+            total: utxos.iter().map(|utxo| utxo.value).sum(),
+            utxos,
+        })
+    }
+}
+
+pub fn history_to_csv(records: &[HistoryRecord]) -> String {
+    let mut out = String::from("txid,time,height,net_sats,fee,label\n");
+    for record in records {
+        writeln!(
+            out,
+            "{},{},{},{},{},{}",
+            record.txid,
+            optional(record.time),
+            optional(record.height),
+            optional(record.net_sats),
+            optional(record.fee),
+            csv_field(record.label.as_deref().unwrap_or_default()),
+        )
+        .expect("writing to a string");
+    }
+    out
+}
+
+pub fn history_to_json(records: &[HistoryRecord]) -> Result<String> {
+    Ok(serde_json::to_string_pretty(records)?)
+}
+
+pub fn utxo_snapshot_to_csv(snapshot: &UtxoSnapshot) -> String {
+    let mut out = String::from("txid,vout,value,address,path,height\n");
+    for utxo in &snapshot.utxos {
+        writeln!(
+            out,
+            "{},{},{},{},{},{}",
+            utxo.outpoint.txid,
+            utxo.outpoint.vout,
+            utxo.value,
+            csv_field(&utxo.address),
+            csv_field(&utxo.path),
+            utxo.height,
+        )
+        .expect("writing to a string");
+    }
+    out
+}
+
+pub fn utxo_snapshot_to_json(snapshot: &UtxoSnapshot) -> Result<String> {
+    Ok(serde_json::to_string_pretty(snapshot)?)
+}
+
+fn optional<T: std::fmt::Display>(value: Option<T>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+/// RFC 4180 quoting, only when the field needs it. Labels are free text so they can hold anything.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bitcoin::test_fixture::{Fixture, EPOCH};
+    use bdk_chain::bitcoin::{Amount, TxOut};
+
+    #[test]
+    fn history_is_oldest_first_with_net_value_and_fee() {
+        let mut fx = Fixture::new();
+        let coin = fx.fund(0, 100_000, 1);
+        let spend = fx.spend(
+            [coin],
+            vec![
+                TxOut {
+                    value: Amount::from_sat(30_000),
+                    script_pubkey: Fixture::stranger_spk(),
+                },
+                TxOut {
+                    value: Amount::from_sat(69_000),
+                    script_pubkey: fx.receive_spk(1),
+                },
+            ],
+            Some(2),
+        );
+        let labels = HashMap::from([(spend, "rent, \"march\"".to_string())]);
+
+        let records = fx.wallet.export_history(fx.master_appkey, &labels);
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].txid, coin.txid);
+        assert_eq!(records[0].net_sats, Some(100_000));
+        assert_eq!(records[0].height, Some(1));
+        assert_eq!(records[0].label, None);
+        assert_eq!(records[1].txid, spend);
+        assert_eq!(records[1].net_sats, Some(-31_000));
+        assert_eq!(records[1].fee, Some(1_000));
+        assert_eq!(records[1].time, Some(EPOCH + 2));
+
+        let csv = history_to_csv(&records);
+        let lines = csv.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "txid,time,height,net_sats,fee,label");
+        assert_eq!(
+            lines[2],
+            format!("{spend},{},2,-31000,1000,\"rent, \"\"march\"\"\"", EPOCH + 2)
+        );
+
+        let json: serde_json::Value =
+            serde_json::from_str(&history_to_json(&records).unwrap()).unwrap();
+        assert_eq!(json[1]["net_sats"], -31_000);
+        assert_eq!(json[1]["label"], "rent, \"march\"");
+    }
+
+    #[test]
+    fn utxo_snapshot_only_counts_what_was_confirmed_by_the_height() {
+        let mut fx = Fixture::new();
+        let first = fx.fund(0, 50_000, 1);
+        let second = fx.fund(1, 20_000, 2);
+        fx.spend(
+            [first],
+            vec![TxOut {
+                value: Amount::from_sat(49_000),
+                script_pubkey: Fixture::stranger_spk(),
+            }],
+            Some(3),
+        );
+        // a later deposit that is still in the mempool
+        fx.spend(
+            [second],
+            vec![TxOut {
+                value: Amount::from_sat(19_000),
+                script_pubkey: fx.receive_spk(2),
+            }],
+            None,
+        );
+
+        let at_1 = fx.wallet.utxo_snapshot(fx.master_appkey, 1).unwrap();
+        assert_eq!(at_1.total, 50_000);
+        assert_eq!(at_1.utxos[0].path, "Receive #0");
+
+        let at_2 = fx.wallet.utxo_snapshot(fx.master_appkey, 2).unwrap();
+        assert_eq!(at_2.total, 70_000);
+
+        let at_3 = fx.wallet.utxo_snapshot(fx.master_appkey, 3).unwrap();
+        assert_eq!(
+            at_3.utxos.iter().map(|u| u.outpoint).collect::<Vec<_>>(),
+            vec![second],
+            "the spend confirmed at 3 removes the first coin and the mempool spend doesn't count"
+        );
+        assert!(utxo_snapshot_to_csv(&at_3).contains(&second.txid.to_string()));
+
+        assert!(fx.wallet.utxo_snapshot(fx.master_appkey, 4).is_err());
+    }
+}
@@ -0,0 +1,731 @@
+//! Sending with payjoin (BIP-78).
+//!
+//! The receiver answers our transaction with one of its own that also spends a coin of theirs,
+//! which breaks the assumption that every input belongs to the sender. What comes back is
+//! written by a stranger, so nothing in it is taken on trust: the proposal is checked against
+//! the original before it ever becomes a sign task, and a proposal that moves our coins anywhere
+//! other than where the user already agreed to send them is refused.
+//!
+//! BIP-78 wants the original transaction finalized, because it is the receiver's fallback if the
+//! sender walks away after the exchange. So the devices sign twice: once for the original, and
+//! once for the proposal. Both are ordinary [`TransactionTemplate`]s and both go through the
+//! normal signing flow; the second differs from the first only in having the receiver's input
+//! in it, which the device shows as not ours.
+//!
+//! Output substitution is always disabled. The user checked the payee's address on the device
+//! when signing the original, and letting the receiver swap it afterwards would mean the second
+//! signature approves something the first check never saw.
+//!
+//! BIP-77 only changes how the bytes travel, so it is a [`PayjoinTransport`] away; nothing here
+//! speaks it yet.
+
+use super::wallet::CoordSuperWallet;
+use anyhow::{anyhow, Result};
+use base64::Engine as _;
+use bdk_chain::bitcoin::{self, address::NetworkUnchecked, Amount, OutPoint, Psbt};
+use frostsnap_core::{
+    bitcoin_transaction::{signature_witness, TransactionTemplate},
+    message::EncodedSignature,
+    tweak::BitcoinAccountKeychain,
+    MasterAppkey,
+};
+use std::borrow::Cow;
+use tracing::{event, Level};
+
+/// A BIP-21 URI that offers payjoin.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PayjoinUri {
+    pub address: bitcoin::Address,
+    pub amount: Option<u64>,
+    /// Where the original is posted, already percent-decoded.
+    pub endpoint: String,
+}
+
+impl PayjoinUri {
+    pub fn parse(uri: &str, network: bitcoin::Network) -> Result<Self> {
+        // BIP-21 refuses `req-` parameters we don't claim, since a required parameter we don't
+        // understand means we can't pay this URI.
+        let uri = bip21::Uri::<'_, NetworkUnchecked, PayjoinParams>::try_from(uri.trim())
+            .map_err(|e| anyhow!("invalid bitcoin: URI: {e}"))?;
+        let address = uri
+            .address
+            .require_network(network)
+            .map_err(|_| anyhow!("address is for a different network"))?;
+
+        let endpoint = uri
+            .extras
+            .endpoint
+            .ok_or_else(|| anyhow!("this URI doesn't offer payjoin"))?;
+        // The original is a signed transaction paying the receiver; on plain http anyone on the
+        // path can read it or answer in the receiver's place. Onion services are their own
+        // encryption.
+        let onion = endpoint
+            .strip_prefix("http://")
+            .and_then(|rest| rest.split(['/', ':', '?']).next())
+            .is_some_and(|host| host.ends_with(".onion"));
+        if !(endpoint.starts_with("https://") || onion) {
+            return Err(anyhow!(
+                "payjoin endpoint must be https or an onion service, got {endpoint}"
+            ));
+        }
+
+        Ok(Self {
+            address,
+            amount: uri.amount.map(Amount::to_sat),
+            endpoint,
+        })
+    }
+}
+
+/// The BIP-21 parameters payjoin adds. Only `pj` matters to us: `pjos` asks us to disable output
+/// substitution, which we always do.
+#[derive(Clone, Debug, Default)]
+struct PayjoinParams {
+    endpoint: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum PayjoinParamError {
+    NotUtf8,
+    DuplicateEndpoint,
+}
+
+impl core::fmt::Display for PayjoinParamError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            PayjoinParamError::NotUtf8 => write!(f, "payjoin endpoint is not UTF-8"),
+            PayjoinParamError::DuplicateEndpoint => write!(f, "more than one payjoin endpoint"),
+        }
+    }
+}
+
+impl std::error::Error for PayjoinParamError {}
+
+impl bip21::DeserializationError for PayjoinParams {
+    type Error = PayjoinParamError;
+}
+
+impl<'de> bip21::de::DeserializeParams<'de> for PayjoinParams {
+    type DeserializationState = PayjoinParams;
+}
+
+impl<'de> bip21::de::DeserializationState<'de> for PayjoinParams {
+    type Value = PayjoinParams;
+
+    fn is_param_known(&self, key: &str) -> bool {
+        matches!(key, "pj" | "pjos")
+    }
+
+    fn deserialize_temp(
+        &mut self,
+        key: &str,
+        value: bip21::Param<'de>,
+    ) -> Result<bip21::de::ParamKind, PayjoinParamError> {
+        match key {
+            "pj" if self.endpoint.is_some() => Err(PayjoinParamError::DuplicateEndpoint),
+            "pj" => {
+                let endpoint =
+                    Cow::<str>::try_from(value).map_err(|_| PayjoinParamError::NotUtf8)?;
+                self.endpoint = Some(endpoint.into_owned());
+                Ok(bip21::de::ParamKind::Known)
+            }
+            "pjos" => Ok(bip21::de::ParamKind::Known),
+            _ => Ok(bip21::de::ParamKind::Unknown),
+        }
+    }
+
+    fn finalize(self) -> Result<PayjoinParams, PayjoinParamError> {
+        Ok(self)
+    }
+}
+
+/// How the original reaches the receiver and the proposal comes back.
+///
+/// Kept out of this module so it doesn't pick an HTTP client (or a proxy for onion endpoints)
+/// on the app's behalf. BIP-78 posts `body` as `text/plain` and the response is the proposal as
+/// base64, also as text.
+pub trait PayjoinTransport {
+    fn post(&mut self, url: &str, body: &str) -> Result<String>;
+}
+
+/// What gets posted to the receiver.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PayjoinRequest {
+    pub url: String,
+    pub body: String,
+}
+
+/// A payjoin in progress: the original we will sign first and the bounds we hold the receiver's
+/// proposal to.
+#[derive(Clone, Debug)]
+pub struct PayjoinSender {
+    endpoint: String,
+    master_appkey: MasterAppkey,
+    original: TransactionTemplate,
+    /// Our change, which is where the receiver may take the extra fee from.
+    fee_output: Option<usize>,
+    max_additional_fee_contribution: u64,
+    min_feerate: f64,
+}
+
+impl PayjoinSender {
+    /// `original` is the transaction as committed from the [`SendPlan`], which must pay the
+    /// URI's address.
+    ///
+    /// We offer to pay for the receiver's input out of our change: one taproot input's worth at
+    /// the original's feerate, rounded up to whole vbytes so the receiver can keep the feerate
+    /// without paying from the payment. With no change there is nothing to take it from, so the
+    /// receiver pays it.
+    ///
+    /// [`SendPlan`]: super::send::SendPlan
+    pub fn new(
+        uri: &PayjoinUri,
+        master_appkey: MasterAppkey,
+        original: TransactionTemplate,
+    ) -> Result<Self> {
+        let scoped = original.as_seen_by(master_appkey);
+        if !scoped.owns_every_input() {
+            return Err(anyhow!(
+                "can only payjoin a transaction whose inputs are all ours"
+            ));
+        }
+        let payee_spk = uri.address.script_pubkey();
+        let payee_outputs = original
+            .outputs()
+            .iter()
+            .filter(|output| output.local_owner().is_none() && output.owner().spk() == payee_spk)
+            .count();
+        if payee_outputs != 1 {
+            return Err(anyhow!(
+                "the transaction must pay the payjoin address exactly once, it pays it {payee_outputs} times"
+            ));
+        }
+
+        let fee_output = scoped
+            .iter_our_outputs()
+            .find(|(_, _, owner)| {
+                owner.bip32_path.account_keychain == BitcoinAccountKeychain::internal()
+            })
+            .map(|(i, _, _)| i);
+        let feerate = scoped
+            .feerate()
+            .expect("every input is ours so the fee is known");
+        let max_additional_fee_contribution = match fee_output {
+            Some(_) => (feerate * TR_KEYSPEND_INPUT_VBYTES).ceil() as u64,
+            None => 0,
+        };
+
+        Ok(Self {
+            endpoint: uri.endpoint.clone(),
+            master_appkey,
+            original,
+            fee_output,
+            max_additional_fee_contribution,
+            // floored to what we put in the query, so the receiver and we agree on the bar
+            min_feerate: (feerate * 1000.0).floor() / 1000.0,
+        })
+    }
+
+    /// The transaction the devices sign first.
+    pub fn original(&self) -> &TransactionTemplate {
+        &self.original
+    }
+
+    /// The original, signed, for broadcasting ourselves if the receiver never answers or
+    /// answers with something we refuse.
+    pub fn original_transaction(
+        &self,
+        signatures: &[EncodedSignature],
+    ) -> Result<bitcoin::Transaction> {
+        Ok(self
+            .original
+            .as_seen_by(self.master_appkey)
+            .to_signed_rust_bitcoin_tx(signatures)?)
+    }
+
+    /// The signed original, addressed to the receiver.
+    ///
+    /// Key origins are stripped from the PSBT: they name our key's fingerprint and our paths,
+    /// which the receiver needs for nothing and could use to link our coins.
+    pub fn request(&self, signatures: &[EncodedSignature]) -> Result<PayjoinRequest> {
+        let scoped = self.original.as_seen_by(self.master_appkey);
+        let mut psbt = self.original.to_psbt();
+        for (i, signature) in scoped.signatures_by_input_index(signatures)? {
+            psbt.inputs[i].final_script_witness = Some(signature_witness(signature));
+        }
+        for input in &mut psbt.inputs {
+            input.tap_internal_key = None;
+            input.tap_key_origins.clear();
+        }
+        for output in &mut psbt.outputs {
+            output.tap_internal_key = None;
+            output.tap_key_origins.clear();
+        }
+
+        let mut url = self.endpoint.clone();
+        url.push(if url.contains('?') { '&' } else { '?' });
+        url.push_str(&format!(
+            "v=1&disableoutputsubstitution=true&minfeerate={}",
+            self.min_feerate
+        ));
+        if let Some(fee_output) = self.fee_output {
+            url.push_str(&format!(
+                "&additionalfeeoutputindex={fee_output}&maxadditionalfeecontribution={}",
+                self.max_additional_fee_contribution
+            ));
+        }
+
+        Ok(PayjoinRequest {
+            url,
+            body: base64::engine::general_purpose::STANDARD.encode(psbt.serialize()),
+        })
+    }
+
+    /// Posts the signed original and checks what comes back.
+    pub fn negotiate(
+        &self,
+        wallet: &CoordSuperWallet,
+        transport: &mut impl PayjoinTransport,
+        signatures: &[EncodedSignature],
+    ) -> Result<PayjoinProposal> {
+        let request = self.request(signatures)?;
+        let response = transport.post(&request.url, &request.body)?;
+        self.process_response(wallet, &response)
+    }
+
+    /// Checks the receiver's proposal against the original and, if it holds up, returns it as a
+    /// template to sign.
+    ///
+    /// The checks are BIP-78's sender checks, tightened where we can afford to be strict: the
+    /// receiver may add inputs and raise the payee's output, and may take at most the offered
+    /// contribution from our change toward the extra fee. Everything else about our side of the
+    /// transaction has to be exactly as the user approved it.
+    ///
+    /// The receiver's inputs are described by the receiver, and we can't check their values
+    /// without a chain lookup. We don't need to: the taproot sighash commits to every input's
+    /// amount and script, so a lie there only makes our own signatures invalid.
+    pub fn process_response(
+        &self,
+        wallet: &CoordSuperWallet,
+        response: &str,
+    ) -> Result<PayjoinProposal> {
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(response.trim())
+            .map_err(|e| anyhow!("payjoin response isn't base64: {e}"))?;
+        let proposal =
+            Psbt::deserialize(&bytes).map_err(|e| anyhow!("payjoin response isn't a PSBT: {e}"))?;
+        let original_psbt = self.original.to_psbt();
+        let original_tx = &original_psbt.unsigned_tx;
+        let proposal_tx = &proposal.unsigned_tx;
+
+        if proposal_tx.version != original_tx.version {
+            return Err(anyhow!("proposal changed the transaction version"));
+        }
+        if proposal_tx.lock_time != original_tx.lock_time {
+            return Err(anyhow!("proposal changed the locktime"));
+        }
+        let sequence = original_tx.input[0].sequence;
+
+        // Inputs: every one of ours exactly once and unsigned, everything else finalized by the
+        // receiver and not spending from our wallet.
+        let mut annotated = proposal.clone();
+        let mut ours_seen = 0;
+        let mut seen = std::collections::BTreeSet::<OutPoint>::new();
+        let mut receiver_inputs = vec![];
+        let mut input_total = 0u64;
+        for (i, (txin, input)) in proposal_tx.input.iter().zip(&proposal.inputs).enumerate() {
+            if !seen.insert(txin.previous_output) {
+                return Err(anyhow!("proposal spends {} twice", txin.previous_output));
+            }
+            if txin.sequence != sequence {
+                return Err(anyhow!("proposal input {i} has a different sequence"));
+            }
+            let original_index = original_tx
+                .input
+                .iter()
+                .position(|orig| orig.previous_output == txin.previous_output);
+            match original_index {
+                Some(j) => {
+                    if input.final_script_witness.is_some()
+                        || input.tap_key_sig.is_some()
+                        || !input.partial_sigs.is_empty()
+                    {
+                        return Err(anyhow!("proposal left a signature on our input {i}"));
+                    }
+                    // BIP-78 lets the receiver drop our utxo information, so ours is restored
+                    // from the original rather than read from the proposal.
+                    annotated.inputs[i] = original_psbt.inputs[j].clone();
+                    input_total += original_psbt.inputs[j]
+                        .witness_utxo
+                        .as_ref()
+                        .expect("to_psbt sets witness_utxo")
+                        .value
+                        .to_sat();
+                    ours_seen += 1;
+                }
+                None => {
+                    if input.final_script_witness.is_none() {
+                        return Err(anyhow!("receiver's input {i} isn't finalized"));
+                    }
+                    let txout = input.witness_utxo.as_ref().ok_or_else(|| {
+                        anyhow!("receiver's input {i} doesn't say what it spends")
+                    })?;
+                    if !txout.script_pubkey.is_p2tr() {
+                        return Err(anyhow!(
+                            "receiver's input {i} isn't taproot, which would mark it as theirs"
+                        ));
+                    }
+                    if wallet
+                        .spk_path(self.master_appkey, txout.script_pubkey.clone())
+                        .is_some()
+                    {
+                        return Err(anyhow!("receiver's input {i} spends one of our coins"));
+                    }
+                    input_total += txout.value.to_sat();
+                    receiver_inputs.push(i);
+                }
+            }
+        }
+        if ours_seen != original_tx.input.len() {
+            return Err(anyhow!("proposal dropped one of our inputs"));
+        }
+
+        // Outputs: the same ones, matched by script since the receiver may shuffle them.
+        if proposal_tx.output.len() != original_tx.output.len() {
+            return Err(anyhow!(
+                "proposal has {} outputs, the original had {}",
+                proposal_tx.output.len(),
+                original_tx.output.len()
+            ));
+        }
+        let mut claimed = vec![false; proposal_tx.output.len()];
+        let mut fee_contribution = 0;
+        for (j, original_out) in original_tx.output.iter().enumerate() {
+            let i = (0..proposal_tx.output.len())
+                .find(|&i| {
+                    !claimed[i] && proposal_tx.output[i].script_pubkey == original_out.script_pubkey
+                })
+                .ok_or_else(|| anyhow!("proposal removed or changed original output {j}"))?;
+            claimed[i] = true;
+            annotated.outputs[i] = original_psbt.outputs[j].clone();
+            let (before, after) = (
+                original_out.value.to_sat(),
+                proposal_tx.output[i].value.to_sat(),
+            );
+
+            if Some(j) == self.fee_output {
+                if after > before {
+                    return Err(anyhow!("proposal raised our change"));
+                }
+                fee_contribution = before - after;
+            } else if original_psbt.outputs[j].tap_internal_key.is_some() {
+                if after != before {
+                    return Err(anyhow!("proposal changed one of our outputs"));
+                }
+            } else if after < before {
+                return Err(anyhow!("proposal lowered a payment"));
+            }
+        }
+
+        if fee_contribution > self.max_additional_fee_contribution {
+            return Err(anyhow!(
+                "proposal takes {fee_contribution} sats from our change, we offered at most {}",
+                self.max_additional_fee_contribution
+            ));
+        }
+        let output_total = proposal_tx
+            .output
+            .iter()
+            .map(|txout| txout.value.to_sat())
+            .sum::<u64>();
+        let fee = input_total
+            .checked_sub(output_total)
+            .ok_or_else(|| anyhow!("proposal spends more than its inputs"))?;
+        let original_fee = self.original.fee().expect("every input is ours");
+        if fee < original_fee + fee_contribution {
+            return Err(anyhow!(
+                "proposal takes {fee_contribution} sats from our change but only raises the fee by {}",
+                fee.saturating_sub(original_fee)
+            ));
+        }
+
+        let mut weighed = proposal_tx.clone();
+        for (txin, input) in weighed.input.iter_mut().zip(&proposal.inputs) {
+            txin.witness = match &input.final_script_witness {
+                Some(witness) => witness.clone(),
+                None => bitcoin::Witness::from_slice(&[[0u8; 64]]),
+            };
+        }
+        let feerate = fee as f64 / weighed.weight().to_vbytes_ceil() as f64;
+        if feerate < self.min_feerate {
+            return Err(anyhow!(
+                "proposal's feerate {feerate:.2} is below the original's {}",
+                self.min_feerate
+            ));
+        }
+
+        let template = TransactionTemplate::from_psbt(&annotated, &[self.master_appkey])
+            .map_err(|e| anyhow!("proposal isn't signable: {e}"))?;
+        let loss_before = -self.original.as_seen_by(self.master_appkey).our_net_value();
+        let loss_after = -template.as_seen_by(self.master_appkey).our_net_value();
+        if loss_after - loss_before > self.max_additional_fee_contribution as i64 {
+            return Err(anyhow!(
+                "proposal costs us {} more than the original",
+                loss_after - loss_before
+            ));
+        }
+
+        event!(
+            Level::INFO,
+            receiver_inputs = receiver_inputs.len(),
+            fee_contribution,
+            "accepted payjoin proposal"
+        );
+
+        Ok(PayjoinProposal {
+            psbt: annotated,
+            template,
+            master_appkey: self.master_appkey,
+            additional_fee: fee_contribution,
+        })
+    }
+}
+
+/// One taproot key-spend input, rounded up to whole vbytes.
+const TR_KEYSPEND_INPUT_VBYTES: f64 = 58.0;
+
+/// A receiver's proposal that passed every check, ready for the devices to sign.
+#[derive(Clone, Debug)]
+pub struct PayjoinProposal {
+    psbt: Psbt,
+    template: TransactionTemplate,
+    master_appkey: MasterAppkey,
+    additional_fee: u64,
+}
+
+impl PayjoinProposal {
+    /// What the devices sign. The receiver's inputs are foreign to it.
+    pub fn template(&self) -> &TransactionTemplate {
+        &self.template
+    }
+
+    /// The proposal with our inputs' utxo information and key origins restored.
+    pub fn psbt(&self) -> &Psbt {
+        &self.psbt
+    }
+
+    /// How much of our change went to the fee for the receiver's input.
+    pub fn additional_fee(&self) -> u64 {
+        self.additional_fee
+    }
+
+    /// The payjoin, with our signatures alongside the receiver's.
+    pub fn to_signed_transaction(
+        &self,
+        signatures: &[EncodedSignature],
+    ) -> Result<bitcoin::Transaction> {
+        let mut tx = self
+            .template
+            .as_seen_by(self.master_appkey)
+            .to_signed_rust_bitcoin_tx(signatures)?;
+        for (txin, input) in tx.input.iter_mut().zip(&self.psbt.inputs) {
+            if let Some(witness) = &input.final_script_witness {
+                txin.witness = witness.clone();
+            }
+            if let Some(script_sig) = &input.final_script_sig {
+                txin.script_sig = script_sig.clone();
+            }
+        }
+        Ok(tx)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bitcoin::test_fixture::{Fixture, NETWORK};
+    use bdk_chain::bitcoin::{hashes::Hash, ScriptBuf, TxIn, TxOut, Txid, Witness};
+    use std::collections::HashMap;
+
+    /// Behaves like an honest BIP-78 receiver, then lets a test make it dishonest.
+    struct StandInReceiver {
+        tamper: fn(&mut Psbt),
+    }
+
+    const RECEIVER_COIN: u64 = 30_000;
+
+    impl PayjoinTransport for StandInReceiver {
+        fn post(&mut self, url: &str, body: &str) -> Result<String> {
+            let params = url
+                .split_once('?')
+                .unwrap()
+                .1
+                .split('&')
+                .filter_map(|param| param.split_once('='))
+                .collect::<HashMap<_, _>>();
+            assert_eq!(params["disableoutputsubstitution"], "true");
+
+            let bytes = base64::engine::general_purpose::STANDARD.decode(body)?;
+            let mut psbt = Psbt::deserialize(&bytes)?;
+            assert!(psbt
+                .inputs
+                .iter()
+                .all(|input| input.final_script_witness.is_some()));
+            assert!(psbt
+                .inputs
+                .iter()
+                .all(|input| input.tap_key_origins.is_empty()));
+
+            let sequence = psbt.unsigned_tx.input[0].sequence;
+            for input in &mut psbt.inputs {
+                input.final_script_witness = None;
+                input.witness_utxo = None;
+            }
+            psbt.unsigned_tx.input.push(TxIn {
+                previous_output: OutPoint::new(Txid::from_byte_array([9; 32]), 0),
+                sequence,
+                ..Default::default()
+            });
+            psbt.inputs.push(bitcoin::psbt::Input {
+                witness_utxo: Some(TxOut {
+                    value: Amount::from_sat(RECEIVER_COIN),
+                    script_pubkey: ScriptBuf::from_bytes([&[0x51, 0x20][..], &[0x42; 32]].concat()),
+                }),
+                final_script_witness: Some(Witness::from_slice(&[[7u8; 64]])),
+                ..Default::default()
+            });
+
+            let payee = Fixture::stranger_spk();
+            for txout in &mut psbt.unsigned_tx.output {
+                if txout.script_pubkey == payee {
+                    txout.value += Amount::from_sat(RECEIVER_COIN);
+                }
+            }
+            if let Some(index) = params.get("additionalfeeoutputindex") {
+                let contribution: u64 = params["maxadditionalfeecontribution"].parse()?;
+                psbt.unsigned_tx.output[index.parse::<usize>()?].value -=
+                    Amount::from_sat(contribution);
+            }
+
+            (self.tamper)(&mut psbt);
+            Ok(base64::engine::general_purpose::STANDARD.encode(psbt.serialize()))
+        }
+    }
+
+    fn sender(fx: &mut Fixture) -> PayjoinSender {
+        fx.fund(0, 100_000, 1);
+        let payee = bitcoin::Address::from_script(&Fixture::stranger_spk(), NETWORK).unwrap();
+        let plan = fx
+            .wallet
+            .plan_send(fx.master_appkey, [(payee.clone(), Some(50_000))], 2.0)
+            .unwrap();
+        let original = fx.wallet.commit_send(&plan, []).unwrap();
+        let uri = PayjoinUri::parse(
+            &format!("bitcoin:{payee}?amount=0.0005&pj=https%3A%2F%2Fexample.com%2Fpj%3Fid%3D1"),
+            NETWORK,
+        )
+        .unwrap();
+        assert_eq!(uri.endpoint, "https://example.com/pj?id=1");
+        PayjoinSender::new(&uri, fx.master_appkey, original).unwrap()
+    }
+
+    fn signatures(template: &TransactionTemplate, key: MasterAppkey) -> Vec<EncodedSignature> {
+        template
+            .as_seen_by(key)
+            .iter_our_inputs()
+            .map(|_| EncodedSignature([1; 64]))
+            .collect()
+    }
+
+    #[test]
+    fn an_honest_proposal_is_accepted_and_signed_alongside_the_receiver() {
+        let mut fx = Fixture::new();
+        let sender = sender(&mut fx);
+        let sigs = signatures(sender.original(), fx.master_appkey);
+
+        let proposal = sender
+            .negotiate(&fx.wallet, &mut StandInReceiver { tamper: |_| {} }, &sigs)
+            .unwrap();
+        let scoped = proposal.template().as_seen_by(fx.master_appkey);
+        assert_eq!(proposal.template().inputs().len(), 2);
+        assert!(!scoped.owns_every_input());
+        let offered = sender.max_additional_fee_contribution;
+        assert!(offered > 0);
+        assert_eq!(proposal.additional_fee(), offered);
+        assert_eq!(
+            scoped.our_net_value(),
+            sender
+                .original()
+                .as_seen_by(fx.master_appkey)
+                .our_net_value()
+                - offered as i64
+        );
+
+        let tx = proposal
+            .to_signed_transaction(&signatures(proposal.template(), fx.master_appkey))
+            .unwrap();
+        assert!(tx.input.iter().all(|txin| !txin.witness.is_empty()));
+        assert_eq!(tx.input[1].witness.to_vec(), vec![vec![7u8; 64]]);
+    }
+
+    #[test]
+    fn a_proposal_that_skims_our_change_is_refused() {
+        let mut fx = Fixture::new();
+        let sender = sender(&mut fx);
+        let sigs = signatures(sender.original(), fx.master_appkey);
+
+        let mut skims = StandInReceiver {
+            tamper: |psbt| {
+                let payee = Fixture::stranger_spk();
+                for txout in &mut psbt.unsigned_tx.output {
+                    if txout.script_pubkey == payee {
+                        txout.value += Amount::from_sat(1_000);
+                    } else {
+                        txout.value -= Amount::from_sat(1_000);
+                    }
+                }
+            },
+        };
+        assert!(sender.negotiate(&fx.wallet, &mut skims, &sigs).is_err());
+    }
+
+    #[test]
+    fn a_proposal_that_pockets_the_fee_contribution_is_refused() {
+        let mut fx = Fixture::new();
+        let sender = sender(&mut fx);
+        let sigs = signatures(sender.original(), fx.master_appkey);
+
+        // takes all of our contribution but passes some of it to the payee instead of the fee
+        let mut pockets = StandInReceiver {
+            tamper: |psbt| {
+                let payee = Fixture::stranger_spk();
+                for txout in &mut psbt.unsigned_tx.output {
+                    if txout.script_pubkey == payee {
+                        txout.value += Amount::from_sat(50);
+                    }
+                }
+            },
+        };
+        assert!(sender.negotiate(&fx.wallet, &mut pockets, &sigs).is_err());
+    }
+
+    #[test]
+    fn only_private_endpoints_are_accepted() {
+        let address = "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4";
+        assert!(
+            PayjoinUri::parse(&format!("bitcoin:{address}?pj=http://example.com"), NETWORK)
+                .is_err()
+        );
+        assert!(PayjoinUri::parse(
+            &format!("bitcoin:{address}?pj=http://abcdef.onion/pj"),
+            NETWORK
+        )
+        .is_ok());
+        assert!(PayjoinUri::parse(&format!("bitcoin:{address}"), NETWORK).is_err());
+        assert!(PayjoinUri::parse(
+            &format!("bitcoin:{address}?pj=https://example.com&req-unknown=1"),
+            NETWORK
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,328 @@
+//! Building and checking [BIP-127] proofs of reserves. The proof itself, and what makes it safe
+//! to sign, is described in [`frostsnap_core::proof_of_reserves`].
+//!
+//! A proof is built from every coin the wallet holds for a key and signed like any other task,
+//! as [`WireSignTask::ProofOfReserves`]. What an auditor gets is the signed transaction, or the
+//! PSBT of it, and the message; [`verify`] is what they run against their own view of the UTXO
+//! set.
+//!
+//! [BIP-127]: https://github.com/bitcoin/bips/blob/master/bip-0127.mediawiki
+use super::wallet::CoordSuperWallet;
+use anyhow::{anyhow, Result};
+use bdk_chain::bitcoin::{
+    self,
+    hashes::Hash as _,
+    secp256k1::{Message, Secp256k1, XOnlyPublicKey},
+    sighash::{Prevouts, SighashCache},
+    Amount, OutPoint, TapSighashType, TxOut,
+};
+use frostsnap_core::{
+    bitcoin_transaction::{LocalSpk, PushInput, TransactionTemplate},
+    proof_of_reserves as por, MasterAppkey, WireSignTask,
+};
+use std::collections::BTreeSet;
+
+impl CoordSuperWallet {
+    /// A proof that `master_appkey` holds every coin the wallet knows it has, committing to
+    /// `message`. Sign it with [`proof_of_reserves_sign_task`].
+    pub fn build_proof_of_reserves(
+        &mut self,
+        master_appkey: MasterAppkey,
+        message: &str,
+    ) -> Result<TransactionTemplate> {
+        let coins = self.all_unspent(master_appkey);
+        if coins.is_empty() {
+            return Err(anyhow!("there are no coins to prove"));
+        }
+        let mut template = por::proof_template(message);
+        for (bip32_path, outpoint, _) in coins {
+            let prev_tx = self
+                .tx_graph
+                .graph()
+                .get_tx(outpoint.txid)
+                .expect("unspent output implies its tx is in the graph");
+            template
+                .push_owned_input(
+                    PushInput::spend_tx_output(prev_tx.as_ref(), outpoint.vout),
+                    LocalSpk {
+                        master_appkey,
+                        bip32_path,
+                    },
+                )
+                .expect("must be able to add input");
+        }
+        por::push_proof_output(&mut template);
+        Ok(template)
+    }
+}
+
+pub fn proof_of_reserves_sign_task(message: &str, template: TransactionTemplate) -> WireSignTask {
+    WireSignTask::ProofOfReserves {
+        message: message.into(),
+        tx_template: template,
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ProofOfReservesError {
+    /// The first input isn't the commitment to the message, so this either proves nothing about
+    /// the message or is a transaction that can be mined.
+    NoCommitment,
+    /// BIP-127 proofs have exactly one output.
+    WrongOutputCount(usize),
+    /// No coins are spent apart from the commitment.
+    Empty,
+    DuplicateInput(OutPoint),
+    /// The coin isn't in the UTXO set, so it's spent or never existed.
+    NotInUtxoSet(OutPoint),
+    /// Only taproot key-path spends can be checked here, which is all a FROST key makes.
+    UnsupportedInput {
+        index: usize,
+    },
+    /// The signature doesn't commit to the whole proof, so it could be reused elsewhere.
+    WrongSighashType {
+        index: usize,
+    },
+    InvalidSignature {
+        index: usize,
+    },
+}
+
+impl core::fmt::Display for ProofOfReservesError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ProofOfReservesError::NoCommitment => {
+                write!(f, "the proof doesn't commit to the message")
+            }
+            ProofOfReservesError::WrongOutputCount(n) => {
+                write!(f, "the proof has {n} outputs but must have exactly one")
+            }
+            ProofOfReservesError::Empty => write!(f, "the proof doesn't spend any coins"),
+            ProofOfReservesError::DuplicateInput(outpoint) => {
+                write!(f, "the proof spends {outpoint} more than once")
+            }
+            ProofOfReservesError::NotInUtxoSet(outpoint) => {
+                write!(f, "{outpoint} is not in the UTXO set")
+            }
+            ProofOfReservesError::UnsupportedInput { index } => write!(
+                f,
+                "input {index} is not a taproot key-path spend, which is all this can verify"
+            ),
+            ProofOfReservesError::WrongSighashType { index } => {
+                write!(f, "input {index} isn't signed with SIGHASH_ALL")
+            }
+            ProofOfReservesError::InvalidSignature { index } => {
+                write!(f, "input {index} has an invalid signature")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ProofOfReservesError {}
+
+/// Check that `proof` proves ownership of its coins as of the UTXO set `utxo` looks up, while
+/// committing to `message`. Returns how much it proves.
+pub fn verify(
+    proof: &bitcoin::Transaction,
+    message: &str,
+    utxo: impl Fn(OutPoint) -> Option<TxOut>,
+) -> Result<Amount, ProofOfReservesError> {
+    let (commitment, coins) = proof
+        .input
+        .split_first()
+        .ok_or(ProofOfReservesError::NoCommitment)?;
+    if commitment.previous_output != por::commitment_outpoint(message) {
+        return Err(ProofOfReservesError::NoCommitment);
+    }
+    if proof.output.len() != 1 {
+        return Err(ProofOfReservesError::WrongOutputCount(proof.output.len()));
+    }
+    if coins.is_empty() {
+        return Err(ProofOfReservesError::Empty);
+    }
+
+    let mut seen = BTreeSet::new();
+    let mut prevouts = vec![por::commitment_txout()];
+    for txin in coins {
+        let outpoint = txin.previous_output;
+        if !seen.insert(outpoint) {
+            return Err(ProofOfReservesError::DuplicateInput(outpoint));
+        }
+        prevouts.push(utxo(outpoint).ok_or(ProofOfReservesError::NotInUtxoSet(outpoint))?);
+    }
+
+    let secp = Secp256k1::verification_only();
+    let mut sighash_cache = SighashCache::new(proof);
+    for (index, prevout) in prevouts.iter().enumerate().skip(1) {
+        let spk = &prevout.script_pubkey;
+        let witness = &proof.input[index].witness;
+        if !spk.is_p2tr() || witness.len() != 1 {
+            return Err(ProofOfReservesError::UnsupportedInput { index });
+        }
+        let signature = bitcoin::taproot::Signature::from_slice(&witness[0])
+            .map_err(|_| ProofOfReservesError::InvalidSignature { index })?;
+        if !matches!(
+            signature.sighash_type,
+            TapSighashType::Default | TapSighashType::All
+        ) {
+            return Err(ProofOfReservesError::WrongSighashType { index });
+        }
+        let output_key = XOnlyPublicKey::from_slice(&spk.as_bytes()[2..])
+            .map_err(|_| ProofOfReservesError::UnsupportedInput { index })?;
+        let sighash = sighash_cache
+            .taproot_key_spend_signature_hash(
+                index,
+                &Prevouts::All(&prevouts),
+                signature.sighash_type,
+            )
+            .map_err(|_| ProofOfReservesError::InvalidSignature { index })?;
+        secp.verify_schnorr(
+            &signature.signature,
+            &Message::from_digest(sighash.to_byte_array()),
+            &output_key,
+        )
+        .map_err(|_| ProofOfReservesError::InvalidSignature { index })?;
+    }
+
+    Ok(prevouts.iter().map(|prevout| prevout.value).sum())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bitcoin::test_fixture::Fixture;
+    use bdk_chain::bitcoin::{
+        hashes::Hash as _,
+        key::{Keypair, TapTweak as _},
+        Sequence, TxIn, Witness,
+    };
+    use std::collections::BTreeMap;
+
+    const MESSAGE: &str = "reserves as of block 900000";
+
+    /// A proof signed with plain keys, over coins of our own making.
+    struct Signed {
+        proof: bitcoin::Transaction,
+        utxos: BTreeMap<OutPoint, TxOut>,
+    }
+
+    fn signed_proof(values: &[u64], message: &str) -> Signed {
+        let secp = Secp256k1::new();
+        let mut utxos = BTreeMap::new();
+        let mut keypairs = vec![];
+        for (i, value) in values.iter().enumerate() {
+            let keypair = Keypair::from_seckey_slice(&secp, &[i as u8 + 1; 32]).unwrap();
+            let (internal_key, _) = keypair.x_only_public_key();
+            let outpoint = OutPoint::new(bitcoin::Txid::from_byte_array([i as u8; 32]), 0);
+            utxos.insert(
+                outpoint,
+                TxOut {
+                    value: Amount::from_sat(*value),
+                    script_pubkey: bitcoin::ScriptBuf::new_p2tr(&secp, internal_key, None),
+                },
+            );
+            keypairs.push((outpoint, keypair.tap_tweak(&secp, None).to_inner()));
+        }
+
+        let mut input = vec![TxIn {
+            previous_output: por::commitment_outpoint(message),
+            sequence: Sequence::MAX,
+            ..Default::default()
+        }];
+        input.extend(keypairs.iter().map(|(outpoint, _)| TxIn {
+            previous_output: *outpoint,
+            sequence: Sequence::MAX,
+            ..Default::default()
+        }));
+        let mut proof = bitcoin::Transaction {
+            version: bitcoin::transaction::Version::TWO,
+            lock_time: bitcoin::absolute::LockTime::ZERO,
+            input,
+            output: vec![TxOut {
+                value: Amount::from_sat(values.iter().sum()),
+                script_pubkey: por::proof_output_script(),
+            }],
+        };
+
+        let mut prevouts = vec![por::commitment_txout()];
+        prevouts.extend(keypairs.iter().map(|(outpoint, _)| utxos[outpoint].clone()));
+        let sighashes = (1..proof.input.len())
+            .map(|index| {
+                SighashCache::new(&proof)
+                    .taproot_key_spend_signature_hash(
+                        index,
+                        &Prevouts::All(&prevouts),
+                        TapSighashType::Default,
+                    )
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+        for (i, ((_, keypair), sighash)) in keypairs.iter().zip(sighashes).enumerate() {
+            let signature = secp
+                .sign_schnorr_no_aux_rand(&Message::from_digest(sighash.to_byte_array()), keypair);
+            proof.input[i + 1].witness = Witness::from_slice(&[signature.serialize()]);
+        }
+        Signed { proof, utxos }
+    }
+
+    #[test]
+    fn a_signed_proof_verifies_against_the_utxo_set() {
+        let Signed { proof, mut utxos } = signed_proof(&[50_000, 25_000], MESSAGE);
+        let lookup = |utxos: &BTreeMap<OutPoint, TxOut>| {
+            let utxos = utxos.clone();
+            move |outpoint: OutPoint| utxos.get(&outpoint).cloned()
+        };
+        assert_eq!(
+            verify(&proof, MESSAGE, lookup(&utxos)),
+            Ok(Amount::from_sat(75_000))
+        );
+        assert_eq!(
+            verify(&proof, "some other message", lookup(&utxos)),
+            Err(ProofOfReservesError::NoCommitment)
+        );
+
+        let spent = *utxos.keys().next().unwrap();
+        utxos.remove(&spent);
+        assert_eq!(
+            verify(&proof, MESSAGE, lookup(&utxos)),
+            Err(ProofOfReservesError::NotInUtxoSet(spent))
+        );
+    }
+
+    #[test]
+    fn a_tampered_proof_does_not_verify() {
+        let Signed { mut proof, utxos } = signed_proof(&[50_000, 25_000], MESSAGE);
+        proof.input.swap(1, 2);
+        assert_eq!(
+            verify(&proof, MESSAGE, |outpoint| utxos.get(&outpoint).cloned()),
+            Err(ProofOfReservesError::InvalidSignature { index: 1 })
+        );
+    }
+
+    #[test]
+    fn the_wallet_builds_a_proof_of_every_coin() {
+        let mut f = Fixture::new();
+        let coins = [f.fund(0, 100_000, 100), f.fund(3, 40_000, 101)];
+        let template = f
+            .wallet
+            .build_proof_of_reserves(f.master_appkey, MESSAGE)
+            .unwrap();
+
+        assert!(por::commits_to(&template, MESSAGE));
+        assert_eq!(
+            template.inputs()[1..]
+                .iter()
+                .map(|input| input.outpoint())
+                .collect::<BTreeSet<_>>(),
+            BTreeSet::from(coins)
+        );
+        assert_eq!(template.outputs().len(), 1);
+        assert_eq!(template.fee(), Some(0));
+        assert!(proof_of_reserves_sign_task(MESSAGE, template)
+            .check(
+                f.master_appkey,
+                frostsnap_core::device::KeyPurpose::Bitcoin(crate::bitcoin::test_fixture::NETWORK),
+            )
+            .is_ok());
+    }
+}
@@ -17,6 +17,7 @@ use bdk_coin_select::{
 };
 use frostsnap_core::{
     bitcoin_transaction::{LocalSpk, PushInput, TransactionTemplate},
+    silent_payments::{SilentPaymentAddress, SilentPaymentEcdh},
     tweak::{BitcoinAccountKeychain, BitcoinBip32Path, NormalIndex},
     MasterAppkey,
 };
@@ -31,6 +32,42 @@ use tracing::{event, Level};
 /// gap never enters the UTXO set, so there is nothing to force.
 const RISKY_GAP: u32 = 20;
 
+/// Who a send pays. A silent payment address has no script until the inputs are chosen, so it
+/// is planned with a stand-in of the same weight and only gets its real one at commit.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SendRecipient {
+    Address(bitcoin::Address),
+    SilentPayment(SilentPaymentAddress),
+}
+
+impl SendRecipient {
+    /// A script the output can be weighed with. For a silent payment this is the spend key as a
+    /// P2TR output: never what gets paid, but exactly as heavy.
+    pub(super) fn planning_spk(&self) -> bitcoin::ScriptBuf {
+        match self {
+            SendRecipient::Address(address) => address.script_pubkey(),
+            SendRecipient::SilentPayment(address) => {
+                let (spend, _) = address.spend.into_point_with_even_y();
+                bitcoin::ScriptBuf::new_p2tr_tweaked(
+                    bitcoin::key::TweakedPublicKey::dangerous_assume_tweaked(spend.into()),
+                )
+            }
+        }
+    }
+}
+
+impl From<bitcoin::Address> for SendRecipient {
+    fn from(address: bitcoin::Address) -> Self {
+        SendRecipient::Address(address)
+    }
+}
+
+impl From<SilentPaymentAddress> for SendRecipient {
+    fn from(address: SilentPaymentAddress) -> Self {
+        SendRecipient::SilentPayment(address)
+    }
+}
+
 /// A finished coin selection that has reserved nothing.
 ///
 /// Each selected input is pinned as the keychain outpoint it came from — derivation path and
@@ -45,6 +82,8 @@ pub struct SendPlan {
     master_appkey: MasterAppkey,
     selected: Vec<(BitcoinBip32Path, OutPoint, u64)>,
     recipients: Vec<TxOut>,
+    /// Recipients (by position in `recipients`) whose script there is only a stand-in.
+    silent_payments: BTreeMap<usize, SilentPaymentAddress>,
     change_value: Option<u64>,
     fee: u64,
 }
@@ -81,6 +120,7 @@ impl SendPlan {
             master_appkey,
             selected,
             recipients,
+            silent_payments: BTreeMap::new(),
             change_value,
             fee,
         })
@@ -122,6 +162,13 @@ impl SendPlan {
     pub fn recipient_value(&self, index: usize) -> Option<u64> {
         self.recipients.get(index).map(|txo| txo.value.to_sat())
     }
+
+    /// The silent payment addresses this plan pays. Each needs a threshold of devices to
+    /// contribute an ECDH share before the plan can be committed, see
+    /// [`commit_send_with_silent_payments`](CoordSuperWallet::commit_send_with_silent_payments).
+    pub fn silent_payment_addresses(&self) -> impl Iterator<Item = &SilentPaymentAddress> + '_ {
+        self.silent_payments.values()
+    }
 }
 
 impl CoordSuperWallet {
@@ -129,25 +176,28 @@ impl CoordSuperWallet {
     /// this freely; only [`commit_send`](Self::commit_send) consumes the result.
     ///
     /// A recipient with a `None` amount receives everything left over (send max).
-    pub fn plan_send(
+    pub fn plan_send<R: Into<SendRecipient>>(
         &mut self,
         master_appkey: MasterAppkey,
-        recipients: impl IntoIterator<Item = (bitcoin::Address, Option<u64>)>,
+        recipients: impl IntoIterator<Item = (R, Option<u64>)>,
         feerate: f32,
     ) -> Result<SendPlan> {
         self.lazily_initialize_key(master_appkey);
 
-        let recipients = recipients.into_iter().collect::<Vec<_>>();
+        let recipients = recipients
+            .into_iter()
+            .map(|(recipient, amount)| (recipient.into(), amount))
+            .collect::<Vec<(SendRecipient, _)>>();
 
         let target_outputs = {
             let mut target_outputs = Vec::<TxOut>::with_capacity(recipients.len());
             let mut available_amount = self.calculate_avaliable_value(
                 master_appkey,
-                recipients.iter().map(|(addr, _)| addr.clone()),
+                recipients.iter().map(|(recipient, _)| recipient.clone()),
                 feerate,
                 true,
             );
-            for (i, (addr, amount_opt)) in recipients.iter().enumerate() {
+            for (i, (recipient, amount_opt)) in recipients.iter().enumerate() {
                 let amount: u64 = match amount_opt {
                     Some(amount) => *amount,
                     None => available_amount
@@ -164,7 +214,7 @@ impl CoordSuperWallet {
                 }
                 target_outputs.push(TxOut {
                     value: Amount::from_sat(amount),
-                    script_pubkey: addr.script_pubkey(),
+                    script_pubkey: recipient.planning_spk(),
                 });
             }
             target_outputs
@@ -283,7 +333,16 @@ impl CoordSuperWallet {
             .selected_value()
             .checked_sub(recipient_value + change_value.unwrap_or(0))
             .ok_or_else(|| anyhow!("selection does not cover its outputs"))?;
-        SendPlan::new(master_appkey, selected, target_outputs, change_value, fee)
+        let mut plan = SendPlan::new(master_appkey, selected, target_outputs, change_value, fee)?;
+        plan.silent_payments = recipients
+            .into_iter()
+            .enumerate()
+            .filter_map(|(i, (recipient, _))| match recipient {
+                SendRecipient::SilentPayment(address) => Some((i, address)),
+                SendRecipient::Address(_) => None,
+            })
+            .collect();
+        Ok(plan)
     }
 
     /// Positions in `coins` of ones a [`RISKY_GAP`]-window restore cannot discover, found by
@@ -379,7 +438,7 @@ impl CoordSuperWallet {
     }
 
     /// Every unspent coin, as consolidation identities.
-    fn all_unspent(
+    pub(super) fn all_unspent(
         &mut self,
         master_appkey: MasterAppkey,
     ) -> Vec<(BitcoinBip32Path, OutPoint, u64)> {
@@ -528,6 +587,70 @@ impl CoordSuperWallet {
         SendPlan::new(master_appkey, coins, vec![], Some(change_value), fee)
     }
 
+    /// Plan a sweep: every unspent coin of the key goes in and a single output to `destination`
+    /// comes out, with no change. This is what a pre-signed emergency sweep signs (see
+    /// [`super::sweep_vault`]), so unlike a send it doesn't skip coins that cost more than they
+    /// carry: anything left behind would be left for whoever the sweep is running from.
+    pub fn plan_sweep(
+        &mut self,
+        master_appkey: MasterAppkey,
+        destination: &bitcoin::Address,
+        feerate: f32,
+    ) -> Result<SendPlan> {
+        let coins = self.all_unspent(master_appkey);
+        if coins.is_empty() {
+            return Err(anyhow!("there are no coins to sweep"));
+        }
+
+        let candidates = coins
+            .iter()
+            .map(|&(_, _, value)| Candidate {
+                input_count: 1,
+                value,
+                weight: TR_KEYSPEND_TXIN_WEIGHT,
+                is_segwit: true,
+            })
+            .collect::<Vec<_>>();
+        let mut cs = CoinSelector::new(&candidates);
+        for position in 0..candidates.len() {
+            cs.select(position);
+        }
+        let output = TxOut {
+            value: Amount::ZERO,
+            script_pubkey: destination.script_pubkey(),
+        };
+        let target = Target {
+            fee: TargetFee::from_feerate(FeeRate::from_sat_per_vb(feerate)),
+            outputs: TargetOutputs::fund_outputs([(output.weight().to_wu(), 0)]),
+        };
+        let fee = cs.implied_fee(
+            target,
+            DrainWeights {
+                output_weight: 0,
+                spend_weight: 0,
+                n_outputs: 0,
+            },
+        );
+        let sum = cs.selected_value();
+        let value = sum
+            .checked_sub(fee)
+            .filter(|value| *value >= TR_DUST_RELAY_MIN_VALUE)
+            .ok_or_else(|| {
+                anyhow!("the wallet holds {sum} sats — not enough to pay the {fee} sat fee")
+            })?;
+
+        SendPlan::new(
+            master_appkey,
+            coins,
+            vec![TxOut {
+                value: Amount::from_sat(value),
+                ..output
+            }],
+            None,
+            fee,
+        )
+    }
+
     /// Turn a [`SendPlan`] into a signable template. This is the wallet's single change-address
     /// allocation point: the lowest revealed-unused index not in `reserved_change`, revealing
     /// fresh only when nothing passes. `reserved_change` is the caller's view of in-flight
@@ -539,13 +662,43 @@ impl CoordSuperWallet {
     /// the plan's own outpoints to re-check the one mutable fact: each must still be unspent —
     /// a plan can outlive a sync that spends one of its coins. The plan is dead then; the
     /// caller builds a new one.
+    ///
+    /// A plan paying a silent payment address can't be committed here; it needs the devices' ECDH
+    /// shares and goes through [`commit_send_with_silent_payments`](Self::commit_send_with_silent_payments).
     pub fn commit_send(
         &mut self,
         plan: &SendPlan,
         reserved_change: impl IntoIterator<Item = u32>,
+    ) -> Result<TransactionTemplate> {
+        self.commit_send_with_silent_payments(plan, reserved_change, [])
+    }
+
+    /// [`commit_send`](Self::commit_send) for a plan that pays silent payment addresses, with the
+    /// ECDH the devices contributed for each one's scan key. The outputs are derived from the
+    /// inputs the plan pinned, so the shares must have been gathered for this key but needn't be
+    /// gathered again if the plan is re-made.
+    pub fn commit_send_with_silent_payments(
+        &mut self,
+        plan: &SendPlan,
+        reserved_change: impl IntoIterator<Item = u32>,
+        ecdh: impl IntoIterator<Item = SilentPaymentEcdh>,
     ) -> Result<TransactionTemplate> {
         self.lazily_initialize_key(plan.master_appkey);
 
+        let ecdh = ecdh.into_iter().collect::<Vec<_>>();
+        let ecdh_for = |address: &SilentPaymentAddress| {
+            ecdh.iter()
+                .find(|ecdh| ecdh.scan == address.scan)
+                .cloned()
+                .ok_or_else(|| {
+                    anyhow!("no ECDH from the devices for the silent payment address {address}")
+                })
+        };
+        // Checked before anything is allocated, so a missing share doesn't reveal a change address.
+        for address in plan.silent_payment_addresses() {
+            ecdh_for(address)?;
+        }
+
         self.owned_unspent(plan.master_appkey, plan.selected_outpoints())
             .map_err(|err| anyhow!("a planned input is no longer spendable: {err}"))?;
 
@@ -603,8 +756,13 @@ impl CoordSuperWallet {
             );
         }
 
-        for txo in &plan.recipients {
-            template.push_foreign_output(txo.clone());
+        for (i, txo) in plan.recipients.iter().enumerate() {
+            match plan.silent_payments.get(&i) {
+                Some(address) => template
+                    .push_silent_payment_output(txo.value, address.clone(), ecdh_for(address)?)
+                    .map_err(|err| anyhow!("paying {address}: {err}"))?,
+                None => template.push_foreign_output(txo.clone()),
+            }
         }
 
         Ok(template)
@@ -613,10 +771,8 @@ impl CoordSuperWallet {
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::bitcoin::chain_sync::{ChainClient, ConnectionHandler, ElectrumConfig};
+    use crate::bitcoin::test_fixture::{chain_client, Fixture, NETWORK};
     use crate::bitcoin::wallet::CoordSuperWallet;
-    use crate::persist::Persisted;
-    use crate::settings::ElectrumEnabled;
     use bdk_chain::{
         bitcoin::{hashes::Hash, BlockHash, TxIn},
         BlockId, CheckPoint, ConfirmationBlockTime, TxUpdate,
@@ -627,125 +783,12 @@ mod test {
     use std::str::FromStr;
     use std::sync::{Arc, Mutex};
 
-    const NETWORK: bitcoin::Network = bitcoin::Network::Bitcoin;
-
     /// The rate the app raises the nudge at: worth mentioning a coin whose rescue pays for itself
     /// at an ordinary feerate. It is the app's number, not the wallet's — these tests pass it
     /// explicitly for the same reason the Dart caller does.
     const NUDGE_BAR: f32 = 10.0;
 
-    /// The handler owns the receiving ends of the client's channels, so it must outlive every
-    /// `ChainClient` call or `monitor_keychain`'s send panics.
-    fn chain_client(db: &Arc<Mutex<rusqlite::Connection>>) -> (ChainClient, ConnectionHandler) {
-        let trusted = {
-            let mut conn = db.lock().unwrap();
-            Persisted::new(&mut *conn, NETWORK).unwrap()
-        };
-        ChainClient::new(
-            bitcoin::constants::genesis_block(NETWORK).block_hash(),
-            ElectrumConfig {
-                enabled: ElectrumEnabled::None,
-                primary: String::new(),
-                backup: String::new(),
-            },
-            trusted,
-            db.clone(),
-        )
-    }
-
-    struct Fixture {
-        wallet: CoordSuperWallet,
-        handler: ConnectionHandler,
-        master_appkey: MasterAppkey,
-        recipient: bitcoin::Address,
-        blocks: Vec<BlockId>,
-    }
-
     impl Fixture {
-        fn new() -> Self {
-            let db = Arc::new(Mutex::new(rusqlite::Connection::open_in_memory().unwrap()));
-            let (client, handler) = chain_client(&db);
-            let master_appkey =
-                MasterAppkey::derive_from_rootkey(Point::random(&mut rand::thread_rng()));
-            let mut wallet = CoordSuperWallet::load_or_init(db, NETWORK, client).unwrap();
-            wallet.list_addresses(master_appkey);
-            let recipient =
-                bitcoin::Address::from_str("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4")
-                    .unwrap()
-                    .require_network(NETWORK)
-                    .unwrap();
-            Self {
-                wallet,
-                handler,
-                master_appkey,
-                recipient,
-                blocks: vec![BlockId {
-                    height: 0,
-                    hash: bitcoin::constants::genesis_block(NETWORK).block_hash(),
-                }],
-            }
-        }
-
-        /// Deliver a confirmed external payment the way a sync would.
-        fn fund(&mut self, index: u32, value: u64, height: u32) -> OutPoint {
-            self.fund_keychain(BitcoinAccountKeychain::external(), index, value, height)
-        }
-
-        /// Deliver a confirmed payment to either keychain. Change lands on the internal one,
-        /// which is where the burned indices are.
-        fn fund_keychain(
-            &mut self,
-            account_keychain: BitcoinAccountKeychain,
-            index: u32,
-            value: u64,
-            height: u32,
-        ) -> OutPoint {
-            let spk = crate::bitcoin::peek_spk(
-                self.master_appkey,
-                BitcoinBip32Path {
-                    account_keychain,
-                    index: NormalIndex::new(index).expect("fixture index is a literal below 2^31"),
-                },
-            );
-            let tx = bitcoin::Transaction {
-                version: bitcoin::transaction::Version::TWO,
-                lock_time: bitcoin::absolute::LockTime::ZERO,
-                input: vec![TxIn::default()],
-                output: vec![TxOut {
-                    value: Amount::from_sat(value),
-                    script_pubkey: spk,
-                }],
-            };
-            let block = BlockId {
-                height,
-                hash: BlockHash::from_byte_array([height as u8; 32]),
-            };
-            self.blocks.push(block);
-            let mut tx_update = TxUpdate::default();
-            tx_update.txs = vec![Arc::new(tx.clone())];
-            tx_update.anchors = [(
-                ConfirmationBlockTime {
-                    block_id: block,
-                    confirmation_time: 1_700_000_000,
-                },
-                tx.compute_txid(),
-            )]
-            .into();
-            self.wallet
-                .apply_update(bdk_electrum_streaming::Update {
-                    tx_update,
-                    last_active_indices: [((self.master_appkey, account_keychain), index)].into(),
-                    chain_update: Some(
-                        CheckPoint::from_block_ids(self.blocks.iter().copied()).unwrap(),
-                    ),
-                })
-                .unwrap();
-            OutPoint {
-                txid: tx.compute_txid(),
-                vout: 0,
-            }
-        }
-
         /// Deliver a spend of one of our coins that pays change to `change_index`, with the server
         /// naming nothing.
         ///
@@ -760,55 +803,20 @@ mod test {
             change_value: u64,
             height: u32,
         ) {
-            let change_spk = crate::bitcoin::peek_spk(
-                self.master_appkey,
-                BitcoinBip32Path {
-                    account_keychain: BitcoinAccountKeychain::internal(),
-                    index: NormalIndex::new(change_index).expect("fixture index below 2^31"),
+            let change_spk = self.spk(BitcoinBip32Path::internal(
+                NormalIndex::new(change_index).expect("fixture index below 2^31"),
+            ));
+            let outputs = vec![
+                TxOut {
+                    value: Amount::from_sat(10_000),
+                    script_pubkey: self.recipient.script_pubkey(),
                 },
-            );
-            let tx = bitcoin::Transaction {
-                version: bitcoin::transaction::Version::TWO,
-                lock_time: bitcoin::absolute::LockTime::ZERO,
-                input: vec![TxIn {
-                    previous_output: spend,
-                    ..Default::default()
-                }],
-                output: vec![
-                    TxOut {
-                        value: Amount::from_sat(10_000),
-                        script_pubkey: self.recipient.script_pubkey(),
-                    },
-                    TxOut {
-                        value: Amount::from_sat(change_value),
-                        script_pubkey: change_spk,
-                    },
-                ],
-            };
-            let block = BlockId {
-                height,
-                hash: BlockHash::from_byte_array([height as u8; 32]),
-            };
-            self.blocks.push(block);
-            let mut tx_update = TxUpdate::default();
-            tx_update.txs = vec![Arc::new(tx.clone())];
-            tx_update.anchors = [(
-                ConfirmationBlockTime {
-                    block_id: block,
-                    confirmation_time: 1_700_000_000,
+                TxOut {
+                    value: Amount::from_sat(change_value),
+                    script_pubkey: change_spk,
                 },
-                tx.compute_txid(),
-            )]
-            .into();
-            self.wallet
-                .apply_update(bdk_electrum_streaming::Update {
-                    tx_update,
-                    last_active_indices: Default::default(),
-                    chain_update: Some(
-                        CheckPoint::from_block_ids(self.blocks.iter().copied()).unwrap(),
-                    ),
-                })
-                .unwrap();
+            ];
+            self.spend([spend], outputs, Some(height));
         }
 
         fn last_revealed_internal(&self) -> Option<u32> {
@@ -1518,40 +1526,14 @@ mod test {
 
         // A sync spends the planned coin out from under the plan.
         let coin = f.wallet.get_tx(plan.selected[0].1.txid).unwrap();
-        let spend = bitcoin::Transaction {
-            version: bitcoin::transaction::Version::TWO,
-            lock_time: bitcoin::absolute::LockTime::ZERO,
-            input: vec![TxIn {
-                previous_output: plan.selected[0].1,
-                ..Default::default()
-            }],
-            output: vec![TxOut {
+        f.spend(
+            [plan.selected[0].1],
+            vec![TxOut {
                 value: coin.output[0].value - Amount::from_sat(500),
                 script_pubkey: bitcoin::ScriptBuf::new_op_return([]),
             }],
-        };
-        let block = BlockId {
-            height: 101,
-            hash: BlockHash::from_byte_array([101u8; 32]),
-        };
-        let mut tx_update = TxUpdate::default();
-        tx_update.txs = vec![Arc::new(spend.clone())];
-        tx_update.anchors = [(
-            ConfirmationBlockTime {
-                block_id: block,
-                confirmation_time: 1_700_000_100,
-            },
-            spend.compute_txid(),
-        )]
-        .into();
-        f.blocks.push(block);
-        f.wallet
-            .apply_update(bdk_electrum_streaming::Update {
-                tx_update,
-                last_active_indices: Default::default(),
-                chain_update: Some(CheckPoint::from_block_ids(f.blocks.iter().copied()).unwrap()),
-            })
-            .unwrap();
+            Some(101),
+        );
 
         let err = f.wallet.commit_send(&plan, []).unwrap_err();
         assert!(
@@ -1605,4 +1587,86 @@ mod test {
             "the keychain that revealed locally is the one the server must be told about: {asked:?}"
         );
     }
+
+    /// A 1-of-1 key and its one device's ECDH with `address`'s scan key, which is all a threshold
+    /// of one needs to commit a silent payment.
+    fn single_device_ecdh(address: &SilentPaymentAddress) -> (MasterAppkey, SilentPaymentEcdh) {
+        use frostsnap_core::schnorr_fun::frost::{PairedSecretShare, SecretShare};
+        use frostsnap_core::schnorr_fun::fun::{g, s, Scalar, G};
+        use frostsnap_core::silent_payments::EcdhShare;
+        use frostsnap_core::tweak::Xpub;
+
+        let secret = Scalar::random(&mut rand::thread_rng());
+        let rootkey = g!(secret * G).normalize();
+        let app_share = Xpub::from_rootkey(PairedSecretShare::new_unchecked(
+            SecretShare {
+                index: s!(1).public(),
+                share: secret.mark_zero(),
+            },
+            rootkey,
+        ))
+        .rootkey_to_master_appkey()
+        .key
+        .secret_share();
+        let share = EcdhShare::new(app_share.index, &app_share.share, address.scan).unwrap();
+        (
+            MasterAppkey::derive_from_rootkey(rootkey),
+            SilentPaymentEcdh {
+                scan: address.scan,
+                shares: vec![share],
+            },
+        )
+    }
+
+    /// The script a silent payment is selected with is a stand-in, so the one it's paid to must
+    /// weigh the same or the fee shown is not the fee paid.
+    #[test]
+    fn a_silent_payment_is_planned_at_its_weight_and_derived_at_commit() {
+        use frostsnap_core::bitcoin_transaction::SpkOwner;
+
+        let mut f = Fixture::new();
+        let address = SilentPaymentAddress {
+            network: NETWORK,
+            scan: Point::random(&mut rand::thread_rng()),
+            spend: Point::random(&mut rand::thread_rng()),
+        };
+        let (master_appkey, ecdh) = single_device_ecdh(&address);
+        f.master_appkey = master_appkey;
+        f.wallet.list_addresses(master_appkey);
+        f.fund(0, 1_000_000, 100);
+
+        let plan = f
+            .wallet
+            .plan_send(master_appkey, [(address.clone(), Some(20_000))], 1.0)
+            .unwrap();
+        assert_eq!(
+            plan.silent_payment_addresses().collect::<Vec<_>>(),
+            vec![&address]
+        );
+
+        let err = f.wallet.commit_send(&plan, []).unwrap_err();
+        assert!(err.to_string().contains("no ECDH"), "got: {err}");
+        assert_eq!(
+            f.last_revealed_internal(),
+            None,
+            "a refused commit allocates no change"
+        );
+
+        let template = f
+            .wallet
+            .commit_send_with_silent_payments(&plan, [], [ecdh])
+            .unwrap();
+        assert_eq!(template.fee(), Some(plan.fee()), "fee shown is fee paid");
+        let paid = template
+            .outputs()
+            .iter()
+            .find(|output| matches!(output.owner, SpkOwner::SilentPayment(_)))
+            .expect("the recipient is paid as a silent payment");
+        assert_eq!(paid.value, 20_000);
+        assert_ne!(
+            paid.txout().script_pubkey,
+            SendRecipient::from(address).planning_spk(),
+            "the stand-in is never what gets paid"
+        );
+    }
 }
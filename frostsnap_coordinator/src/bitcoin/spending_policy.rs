@@ -0,0 +1,471 @@
+//! Rules a key's owner can put on what the coordinator will start signing.
+//!
+//! Devices show what they're signing, but someone who has the coordinator and enough devices
+//! can still drain a wallet one confirmation at a time. A [`SpendingPolicy`] is checked before a
+//! signing session is created, so a transaction that breaks it never reaches a device.
+//! Everything broken is reported at once in a [`PolicyRejection`] so the user can see what to
+//! change.
+//!
+//! Policies are stored per key in [`Settings`](crate::settings::Settings).
+use super::wallet::Transaction;
+use bdk_chain::bitcoin::{self, Address, ScriptBuf, Txid};
+use frostsnap_core::{
+    bitcoin_transaction::TransactionTemplate, coordinator::FrostCoordinator, KeyId, MasterAppkey,
+    WireSignTask,
+};
+use std::{collections::BTreeSet, str::FromStr};
+
+const DAY: u64 = 24 * 60 * 60;
+const WEEK: u64 = 7 * DAY;
+
+#[derive(Clone, Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct SpendingPolicy {
+    /// Most (in sats) that may leave the wallet in any 24 hours.
+    pub daily_limit: Option<u64>,
+    /// Most (in sats) that may leave the wallet in any 7 days.
+    pub weekly_limit: Option<u64>,
+    /// When set, every output that isn't back to the wallet must pay one of these addresses.
+    pub allowed_destinations: Option<Vec<String>>,
+    /// Highest feerate in sat/vB.
+    pub max_feerate: Option<f64>,
+    pub large_spend: Option<LargeSpendRule>,
+}
+
+/// Spends over `above` sats need `min_signers` devices, usually more than the threshold.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct LargeSpendRule {
+    pub above: u64,
+    pub min_signers: u16,
+}
+
+/// A transaction the coordinator has been asked to sign, as far as a policy cares.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpendProposal {
+    /// What the wallet loses, fee included.
+    pub amount: u64,
+    /// `None` when there are inputs we can't size.
+    pub feerate: Option<f64>,
+    pub destinations: Vec<ScriptBuf>,
+    pub signers: usize,
+}
+
+impl SpendProposal {
+    pub fn new(
+        template: &TransactionTemplate,
+        master_appkey: MasterAppkey,
+        signers: usize,
+    ) -> Self {
+        let scoped = template.as_seen_by(master_appkey);
+        Self {
+            amount: spent_by_template(template, master_appkey),
+            feerate: scoped.feerate(),
+            destinations: scoped
+                .foreign_recipients()
+                .map(|(spk, _)| spk.to_owned())
+                .collect(),
+            signers,
+        }
+    }
+}
+
+/// Value that left the wallet at a point in time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PastSpend {
+    /// Seconds since the unix epoch.
+    pub timestamp: u64,
+    pub amount: u64,
+}
+
+/// What the wallet's transactions took from it. A transaction is dated by its block, or by
+/// when we last saw it if it's unconfirmed. Ones whose net value we can't work out are left out.
+pub fn past_spends(transactions: &[Transaction], now: u64) -> Vec<PastSpend> {
+    transactions
+        .iter()
+        .filter_map(|tx| {
+            let net_value = tx.net_value()?;
+            if net_value >= 0 {
+                return None;
+            }
+            let timestamp = tx
+                .confirmation_time
+                .as_ref()
+                .map(|confirmation_time| confirmation_time.time)
+                .or(tx.last_seen)
+                .unwrap_or(now);
+            Some(PastSpend {
+                timestamp,
+                amount: net_value.unsigned_abs(),
+            })
+        })
+        .collect()
+}
+
+fn spent_by_template(template: &TransactionTemplate, master_appkey: MasterAppkey) -> u64 {
+    let net_value = template.as_seen_by(master_appkey).our_net_value();
+    if net_value < 0 {
+        net_value.unsigned_abs()
+    } else {
+        0
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LimitPeriod {
+    Day,
+    Week,
+}
+
+impl core::fmt::Display for LimitPeriod {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            LimitPeriod::Day => write!(f, "daily"),
+            LimitPeriod::Week => write!(f, "weekly"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PolicyViolation {
+    SpendingLimit {
+        period: LimitPeriod,
+        limit: u64,
+        already_spent: u64,
+        amount: u64,
+    },
+    /// `destination` is the address, or the script in hex if it doesn't have one.
+    DestinationNotAllowed {
+        destination: String,
+    },
+    FeerateTooHigh {
+        feerate: f64,
+        max_feerate: f64,
+    },
+    TooFewSigners {
+        amount: u64,
+        min_signers: u16,
+        signers: usize,
+    },
+}
+
+impl core::fmt::Display for PolicyViolation {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            PolicyViolation::SpendingLimit {
+                period,
+                limit,
+                already_spent,
+                amount,
+            } => write!(
+                f,
+                "spending {amount} sats on top of {already_spent} goes over the {period} limit of {limit}"
+            ),
+            PolicyViolation::DestinationNotAllowed { destination } => {
+                write!(f, "{destination} is not an allowed destination")
+            }
+            PolicyViolation::FeerateTooHigh {
+                feerate,
+                max_feerate,
+            } => write!(
+                f,
+                "feerate of {feerate:.1} sat/vB is over the maximum of {max_feerate:.1}"
+            ),
+            PolicyViolation::TooFewSigners {
+                amount,
+                min_signers,
+                signers,
+            } => write!(
+                f,
+                "spending {amount} sats needs {min_signers} signers but only {signers} were chosen"
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PolicyRejection {
+    pub violations: Vec<PolicyViolation>,
+}
+
+impl core::fmt::Display for PolicyRejection {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "the spending policy doesn't allow this transaction: ")?;
+        for (i, violation) in self.violations.iter().enumerate() {
+            if i > 0 {
+                write!(f, "; ")?;
+            }
+            write!(f, "{violation}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for PolicyRejection {}
+
+impl SpendingPolicy {
+    /// Check `proposal` against the policy given what has already been spent.
+    pub fn check(
+        &self,
+        proposal: &SpendProposal,
+        history: &[PastSpend],
+        now: u64,
+        network: bitcoin::Network,
+    ) -> Result<(), PolicyRejection> {
+        let mut violations = vec![];
+
+        for (period, window, limit) in [
+            (LimitPeriod::Day, DAY, self.daily_limit),
+            (LimitPeriod::Week, WEEK, self.weekly_limit),
+        ] {
+            let Some(limit) = limit else { continue };
+            let already_spent = history
+                .iter()
+                .filter(|spend| spend.timestamp.saturating_add(window) > now)
+                .map(|spend| spend.amount)
+                .sum::<u64>();
+            if already_spent.saturating_add(proposal.amount) > limit {
+                violations.push(PolicyViolation::SpendingLimit {
+                    period,
+                    limit,
+                    already_spent,
+                    amount: proposal.amount,
+                });
+            }
+        }
+
+        if let Some(allowed_destinations) = &self.allowed_destinations {
+            let allowed = allowed_destinations
+                .iter()
+                .filter_map(|address| {
+                    Some(
+                        Address::from_str(address)
+                            .ok()?
+                            .require_network(network)
+                            .ok()?
+                            .script_pubkey(),
+                    )
+                })
+                .collect::<BTreeSet<_>>();
+            for spk in &proposal.destinations {
+                if !allowed.contains(spk) {
+                    let destination = match Address::from_script(spk, network) {
+                        Ok(address) => address.to_string(),
+                        Err(_) => spk.to_hex_string(),
+                    };
+                    violations.push(PolicyViolation::DestinationNotAllowed { destination });
+                }
+            }
+        }
+
+        if let (Some(max_feerate), Some(feerate)) = (self.max_feerate, proposal.feerate) {
+            if feerate > max_feerate {
+                violations.push(PolicyViolation::FeerateTooHigh {
+                    feerate,
+                    max_feerate,
+                });
+            }
+        }
+
+        if let Some(rule) = self.large_spend {
+            if proposal.amount > rule.above && proposal.signers < rule.min_signers as usize {
+                violations.push(PolicyViolation::TooFewSigners {
+                    amount: proposal.amount,
+                    min_signers: rule.min_signers,
+                    signers: proposal.signers,
+                });
+            }
+        }
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(PolicyRejection { violations })
+        }
+    }
+}
+
+/// A key's policy with the history to check it against, gathered before signing starts.
+#[derive(Clone, Debug)]
+pub struct SpendingPolicyCheck {
+    pub policy: SpendingPolicy,
+    pub network: bitcoin::Network,
+    pub history: Vec<PastSpend>,
+    /// The transactions `history` came from, so a signed one that has since been broadcast isn't
+    /// counted twice.
+    pub history_txids: BTreeSet<Txid>,
+    pub now: u64,
+}
+
+impl SpendingPolicyCheck {
+    /// Call with the coordinator locked until the session is started, so that spends in sessions
+    /// still being signed, or signed but not yet broadcast, are counted against the limits and two
+    /// can't slip in side by side. Anything other than a bitcoin transaction is allowed.
+    pub fn check(
+        &self,
+        coordinator: &FrostCoordinator,
+        key_id: KeyId,
+        task: &WireSignTask,
+        signers: usize,
+    ) -> Result<(), PolicyRejection> {
+        let WireSignTask::BitcoinTransaction(template) = task else {
+            return Ok(());
+        };
+        let Some(frost_key) = coordinator.get_frost_key(key_id) else {
+            return Ok(());
+        };
+        let master_appkey = frost_key.complete_key.master_appkey;
+
+        let signing = coordinator
+            .active_signing_sessions()
+            .filter(|session| session.key_id == key_id)
+            .map(|session| session.init.group_request.sign_task);
+        let unbroadcast = coordinator
+            .finished_signing_sessions()
+            .values()
+            .filter(|session| session.key_id == key_id)
+            .map(|session| session.init.group_request.sign_task.clone())
+            .filter(|task| match task {
+                WireSignTask::BitcoinTransaction(template) => {
+                    !self.history_txids.contains(&template.txid())
+                }
+                _ => false,
+            });
+        let in_flight = signing.chain(unbroadcast).filter_map(|task| match &task {
+            WireSignTask::BitcoinTransaction(template) => Some(PastSpend {
+                timestamp: self.now,
+                amount: spent_by_template(template, master_appkey),
+            }),
+            _ => None,
+        });
+        let history = self
+            .history
+            .iter()
+            .copied()
+            .chain(in_flight)
+            .collect::<Vec<_>>();
+
+        self.policy.check(
+            &SpendProposal::new(template, master_appkey, signers),
+            &history,
+            self.now,
+            self.network,
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const NOW: u64 = 1_700_000_000;
+
+    fn proposal(amount: u64) -> SpendProposal {
+        SpendProposal {
+            amount,
+            feerate: Some(2.0),
+            destinations: vec![],
+            signers: 2,
+        }
+    }
+
+    #[test]
+    fn limits_count_spends_inside_their_window() {
+        let policy = SpendingPolicy {
+            daily_limit: Some(100_000),
+            weekly_limit: Some(200_000),
+            ..Default::default()
+        };
+        let history = [
+            PastSpend {
+                timestamp: NOW - 2 * DAY,
+                amount: 120_000,
+            },
+            PastSpend {
+                timestamp: NOW - 60,
+                amount: 50_000,
+            },
+        ];
+        let network = bitcoin::Network::Bitcoin;
+
+        assert_eq!(
+            policy.check(&proposal(30_000), &history, NOW, network),
+            Ok(())
+        );
+        assert_eq!(
+            policy.check(&proposal(60_000), &history, NOW, network),
+            Err(PolicyRejection {
+                violations: vec![
+                    PolicyViolation::SpendingLimit {
+                        period: LimitPeriod::Day,
+                        limit: 100_000,
+                        already_spent: 50_000,
+                        amount: 60_000,
+                    },
+                    PolicyViolation::SpendingLimit {
+                        period: LimitPeriod::Week,
+                        limit: 200_000,
+                        already_spent: 170_000,
+                        amount: 60_000,
+                    },
+                ]
+            })
+        );
+        // a week later it has all aged out
+        assert_eq!(
+            policy.check(&proposal(100_000), &history, NOW + WEEK, network),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn destinations_feerate_and_signers_are_checked() {
+        let network = bitcoin::Network::Bitcoin;
+        let allowed = Address::from_str("bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq")
+            .unwrap()
+            .assume_checked();
+        let stranger =
+            Address::from_str("bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr")
+                .unwrap()
+                .assume_checked();
+        let policy = SpendingPolicy {
+            allowed_destinations: Some(vec![allowed.to_string()]),
+            max_feerate: Some(50.0),
+            large_spend: Some(LargeSpendRule {
+                above: 1_000_000,
+                min_signers: 3,
+            }),
+            ..Default::default()
+        };
+
+        let ok = SpendProposal {
+            destinations: vec![allowed.script_pubkey()],
+            ..proposal(500_000)
+        };
+        assert_eq!(policy.check(&ok, &[], NOW, network), Ok(()));
+
+        let bad = SpendProposal {
+            amount: 2_000_000,
+            feerate: Some(80.0),
+            destinations: vec![allowed.script_pubkey(), stranger.script_pubkey()],
+            signers: 2,
+        };
+        assert_eq!(
+            policy.check(&bad, &[], NOW, network),
+            Err(PolicyRejection {
+                violations: vec![
+                    PolicyViolation::DestinationNotAllowed {
+                        destination: stranger.to_string(),
+                    },
+                    PolicyViolation::FeerateTooHigh {
+                        feerate: 80.0,
+                        max_feerate: 50.0,
+                    },
+                    PolicyViolation::TooFewSigners {
+                        amount: 2_000_000,
+                        min_signers: 3,
+                        signers: 2,
+                    },
+                ]
+            })
+        );
+    }
+}
@@ -0,0 +1,583 @@
+//! Transactions signed ahead of time that move every coin of a key to one address, so in an
+//! emergency getting the funds out is a broadcast rather than a signing session.
+//!
+//! A sweep is planned with [`CoordSuperWallet::plan_sweep`], usually at a few feerates, and each
+//! is signed through the normal signing flow. The signed transactions are kept encrypted under the
+//! app's encryption key, with only what's needed to list them in the clear.
+//!
+//! A sweep spends the exact coins the wallet had when it was planned, so once the coins change it
+//! may leave new ones behind or no longer be broadcastable at all. Nothing is thrown away because
+//! of that: each sweep is listed with a [`SweepStatus`] worked out from the wallet as it is now, and
+//! only the user discards sweeps. Sweeps of the same coins at other feerates stay after one is
+//! broadcast, since broadcasting a higher paying one is how a stuck sweep gets bumped.
+use super::wallet::CoordSuperWallet;
+use crate::persist::{Persist, SqlTxid};
+use anyhow::{anyhow, Context as _, Result};
+use bdk_chain::{
+    bitcoin::{self, consensus, hashes::Hash as _, OutPoint, ScriptBuf, Txid},
+    rusqlite_impl::migrate_schema,
+};
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    ChaCha20Poly1305,
+};
+use frostsnap_core::{
+    bitcoin_transaction::TransactionTemplate, message::EncodedSignature, MasterAppkey, SymmetricKey,
+};
+use rusqlite::params;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    str::FromStr,
+};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PresignedSweep {
+    pub txid: Txid,
+    pub master_appkey: MasterAppkey,
+    /// sats per vbyte
+    pub feerate: f64,
+    pub destination: ScriptBuf,
+    pub spends: BTreeSet<OutPoint>,
+    /// The nonce followed by the encrypted consensus encoding of the signed transaction.
+    sealed_tx: Vec<u8>,
+}
+
+impl PresignedSweep {
+    fn seal(
+        master_appkey: MasterAppkey,
+        feerate: f64,
+        tx: &bitcoin::Transaction,
+        encryption_key: SymmetricKey,
+        rng: &mut impl rand_core::RngCore,
+    ) -> Self {
+        let txid = tx.compute_txid();
+        let mut nonce = [0u8; 12];
+        rng.fill_bytes(&mut nonce);
+        let ciphertext = ChaCha20Poly1305::new(&encryption_key.0.into())
+            .encrypt(
+                &nonce.into(),
+                Payload {
+                    msg: &consensus::serialize(tx),
+                    aad: txid.as_byte_array(),
+                },
+            )
+            .expect("encryption doesn't fail");
+        Self {
+            txid,
+            master_appkey,
+            feerate,
+            destination: tx.output[0].script_pubkey.clone(),
+            spends: tx.input.iter().map(|txin| txin.previous_output).collect(),
+            sealed_tx: [nonce.as_slice(), &ciphertext].concat(),
+        }
+    }
+
+    /// The signed transaction, ready to broadcast.
+    pub fn open(&self, encryption_key: SymmetricKey) -> Result<bitcoin::Transaction> {
+        if self.sealed_tx.len() < 12 {
+            return Err(anyhow!("stored sweep {} is truncated", self.txid));
+        }
+        let (nonce, ciphertext) = self.sealed_tx.split_at(12);
+        let nonce: [u8; 12] = nonce.try_into().expect("split at 12");
+        let plaintext = ChaCha20Poly1305::new(&encryption_key.0.into())
+            .decrypt(
+                &nonce.into(),
+                Payload {
+                    msg: ciphertext,
+                    aad: self.txid.as_byte_array(),
+                },
+            )
+            .map_err(|_| anyhow!("couldn't decrypt the sweep {} with this key", self.txid))?;
+        let tx: bitcoin::Transaction =
+            consensus::deserialize(&plaintext).context("decrypted sweep isn't a transaction")?;
+        if tx.compute_txid() != self.txid {
+            return Err(anyhow!("decrypted sweep isn't {}", self.txid));
+        }
+        Ok(tx)
+    }
+}
+
+/// Where a stored sweep stands against the wallet's coins right now.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SweepStatus {
+    /// It spends every coin of the key.
+    Ready,
+    /// Its coins are all unspent, but coins worth `value` sats have arrived since it was planned
+    /// and broadcasting it would leave them behind.
+    LeavesBehind { value: u64 },
+    /// It's been broadcast and hasn't confirmed yet.
+    Broadcast,
+    /// It's been broadcast and confirmed.
+    Confirmed,
+    /// Unconfirmed transactions spend some of its coins, e.g. a sweep of the same coins at a lower
+    /// feerate. Broadcasting it replaces them if it pays more.
+    Replaces { txids: Vec<Txid> },
+    /// Some of its coins have been spent for good, so it can never be broadcast.
+    Stale,
+}
+
+impl SweepStatus {
+    fn of(
+        sweep: &PresignedSweep,
+        unspent: &BTreeMap<OutPoint, u64>,
+        spenders: &BTreeMap<OutPoint, (Txid, bool)>,
+    ) -> Self {
+        let mut replaces = BTreeSet::new();
+        for outpoint in &sweep.spends {
+            match spenders.get(outpoint) {
+                Some(&(txid, confirmed)) if txid == sweep.txid => {
+                    return if confirmed {
+                        SweepStatus::Confirmed
+                    } else {
+                        SweepStatus::Broadcast
+                    };
+                }
+                Some(&(_, true)) => return SweepStatus::Stale,
+                Some(&(txid, false)) => {
+                    replaces.insert(txid);
+                }
+                // the coin itself is gone, e.g. the transaction that made it was replaced
+                None if !unspent.contains_key(outpoint) => return SweepStatus::Stale,
+                None => {}
+            }
+        }
+        if !replaces.is_empty() {
+            return SweepStatus::Replaces {
+                txids: replaces.into_iter().collect(),
+            };
+        }
+        let value = unspent
+            .iter()
+            .filter(|(outpoint, _)| !sweep.spends.contains(outpoint))
+            .map(|(_, value)| value)
+            .sum::<u64>();
+        if value == 0 {
+            SweepStatus::Ready
+        } else {
+            SweepStatus::LeavesBehind { value }
+        }
+    }
+}
+
+#[derive(Default, Debug)]
+pub struct SweepVault {
+    sweeps: BTreeMap<Txid, PresignedSweep>,
+}
+
+#[derive(Clone, Debug)]
+pub enum SweepVaultMutation {
+    Store(PresignedSweep),
+    Discard { txid: Txid },
+}
+
+impl SweepVault {
+    fn apply(&mut self, mutation: &SweepVaultMutation) {
+        match mutation {
+            SweepVaultMutation::Store(sweep) => {
+                self.sweeps.insert(sweep.txid, sweep.clone());
+            }
+            SweepVaultMutation::Discard { txid } => {
+                self.sweeps.remove(txid);
+            }
+        }
+    }
+
+    pub fn get(&self, txid: Txid) -> Option<&PresignedSweep> {
+        self.sweeps.get(&txid)
+    }
+
+    pub fn sweeps(&self, master_appkey: MasterAppkey) -> impl Iterator<Item = &PresignedSweep> {
+        self.sweeps
+            .values()
+            .filter(move |sweep| sweep.master_appkey == master_appkey)
+    }
+}
+
+impl Persist<rusqlite::Connection> for SweepVault {
+    type Update = Vec<SweepVaultMutation>;
+    type LoadParams = ();
+
+    fn migrate(conn: &mut rusqlite::Connection) -> Result<()> {
+        const SCHEMA_NAME: &str = "frostsnap_sweep_vault";
+        const MIGRATIONS: &[&str] = &[
+            // Version 0
+            "CREATE TABLE IF NOT EXISTS fs_sweep_vault ( \
+                txid TEXT PRIMARY KEY NOT NULL, \
+                master_appkey TEXT NOT NULL, \
+                feerate REAL NOT NULL, \
+                destination BLOB NOT NULL, \
+                spends TEXT NOT NULL, \
+                sealed_tx BLOB NOT NULL \
+            ) WITHOUT ROWID, STRICT",
+        ];
+
+        let db_tx = conn.transaction()?;
+        migrate_schema(&db_tx, SCHEMA_NAME, MIGRATIONS)?;
+        db_tx.commit()?;
+        Ok(())
+    }
+
+    fn load(conn: &mut rusqlite::Connection, _: Self::LoadParams) -> Result<Self> {
+        let mut vault = SweepVault::default();
+        let mut stmt = conn.prepare(
+            "SELECT txid, master_appkey, feerate, destination, spends, sealed_tx FROM fs_sweep_vault",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, SqlTxid>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, f64>(2)?,
+                row.get::<_, Vec<u8>>(3)?,
+                row.get::<_, String>(4)?,
+                row.get::<_, Vec<u8>>(5)?,
+            ))
+        })?;
+        for row in rows {
+            let (SqlTxid(txid), master_appkey, feerate, destination, spends, sealed_tx) = row?;
+            let spends = serde_json::from_str::<Vec<String>>(&spends)?
+                .iter()
+                .map(|outpoint| OutPoint::from_str(outpoint))
+                .collect::<Result<_, _>>()?;
+            vault.apply(&SweepVaultMutation::Store(PresignedSweep {
+                txid,
+                master_appkey: MasterAppkey::from_str(&master_appkey)?,
+                feerate,
+                destination: ScriptBuf::from_bytes(destination),
+                spends,
+                sealed_tx,
+            }));
+        }
+        drop(stmt);
+        Ok(vault)
+    }
+
+    fn persist_update(&self, conn: &mut rusqlite::Connection, update: Self::Update) -> Result<()> {
+        let db_tx = conn.transaction()?;
+        for mutation in update {
+            match mutation {
+                SweepVaultMutation::Store(sweep) => {
+                    let spends = serde_json::to_string(
+                        &sweep
+                            .spends
+                            .iter()
+                            .map(ToString::to_string)
+                            .collect::<Vec<_>>(),
+                    )?;
+                    db_tx.execute(
+                        "INSERT OR REPLACE INTO fs_sweep_vault \
+                         (txid, master_appkey, feerate, destination, spends, sealed_tx) \
+                         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                        params![
+                            SqlTxid(sweep.txid),
+                            sweep.master_appkey.to_string(),
+                            sweep.feerate,
+                            sweep.destination.as_bytes(),
+                            spends,
+                            sweep.sealed_tx,
+                        ],
+                    )?;
+                }
+                SweepVaultMutation::Discard { txid } => {
+                    db_tx.execute(
+                        "DELETE FROM fs_sweep_vault WHERE txid = ?1",
+                        params![SqlTxid(txid)],
+                    )?;
+                }
+            }
+        }
+        db_tx.commit()?;
+        Ok(())
+    }
+}
+
+impl CoordSuperWallet {
+    /// Keep a signed sweep of `master_appkey`'s coins, from a template made by
+    /// [`Self::plan_sweep`] and the signatures its signing session produced. Refused if the
+    /// wallet's coins have changed since the sweep was planned.
+    pub fn store_presigned_sweep(
+        &mut self,
+        master_appkey: MasterAppkey,
+        template: &TransactionTemplate,
+        signatures: &[EncodedSignature],
+        encryption_key: SymmetricKey,
+        rng: &mut impl rand_core::RngCore,
+    ) -> Result<Txid> {
+        let scoped = template.as_seen_by(master_appkey);
+        let tx = scoped.to_signed_rust_bitcoin_tx(signatures)?;
+        if tx.output.len() != 1 {
+            return Err(anyhow!("a sweep pays a single output"));
+        }
+        let feerate = scoped
+            .feerate()
+            .ok_or_else(|| anyhow!("the sweep's inputs aren't all known"))?;
+        let spends = tx
+            .input
+            .iter()
+            .map(|txin| txin.previous_output)
+            .collect::<BTreeSet<_>>();
+        if spends != self.unspent_outpoints(master_appkey) {
+            return Err(anyhow!(
+                "the wallet's coins have changed since this sweep was planned"
+            ));
+        }
+
+        let sweep = PresignedSweep::seal(master_appkey, feerate, &tx, encryption_key, rng);
+        let txid = sweep.txid;
+        let mut db = self.db.lock().unwrap();
+        self.sweep_vault.mutate(&mut *db, |vault| {
+            let mutation = SweepVaultMutation::Store(sweep);
+            vault.apply(&mutation);
+            Ok(((), vec![mutation]))
+        })?;
+        Ok(txid)
+    }
+
+    /// Every stored sweep of `master_appkey` with where it stands, cheapest first.
+    pub fn presigned_sweeps(
+        &mut self,
+        master_appkey: MasterAppkey,
+    ) -> Vec<(PresignedSweep, SweepStatus)> {
+        let unspent = self
+            .all_unspent(master_appkey)
+            .into_iter()
+            .map(|(_, outpoint, value)| (outpoint, value))
+            .collect::<BTreeMap<_, _>>();
+        let spenders = self
+            .list_transactions(master_appkey)
+            .into_iter()
+            .flat_map(|tx| {
+                let spender = (tx.txid, tx.confirmation_time.is_some());
+                tx.inner
+                    .input
+                    .iter()
+                    .map(|txin| (txin.previous_output, spender))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<BTreeMap<_, _>>();
+        let mut sweeps = self
+            .sweep_vault
+            .sweeps(master_appkey)
+            .map(|sweep| {
+                let status = SweepStatus::of(sweep, &unspent, &spenders);
+                (sweep.clone(), status)
+            })
+            .collect::<Vec<_>>();
+        sweeps.sort_by(|(a, _), (b, _)| a.feerate.total_cmp(&b.feerate));
+        sweeps
+    }
+
+    /// Decrypt a stored sweep so it can be broadcast.
+    pub fn presigned_sweep_tx(
+        &self,
+        txid: Txid,
+        encryption_key: SymmetricKey,
+    ) -> Result<bitcoin::Transaction> {
+        self.sweep_vault
+            .get(txid)
+            .ok_or_else(|| anyhow!("there is no stored sweep {txid}"))?
+            .open(encryption_key)
+    }
+
+    pub fn discard_presigned_sweeps(&mut self, master_appkey: MasterAppkey) -> Result<()> {
+        let txids = self
+            .sweep_vault
+            .sweeps(master_appkey)
+            .map(|sweep| sweep.txid)
+            .collect::<Vec<_>>();
+        self.discard_sweeps(txids)
+    }
+
+    pub fn discard_presigned_sweep(&mut self, txid: Txid) -> Result<()> {
+        if self.sweep_vault.get(txid).is_none() {
+            return Err(anyhow!("there is no stored sweep {txid}"));
+        }
+        self.discard_sweeps(vec![txid])
+    }
+
+    fn discard_sweeps(&mut self, txids: Vec<Txid>) -> Result<()> {
+        if txids.is_empty() {
+            return Ok(());
+        }
+        let mut db = self.db.lock().unwrap();
+        self.sweep_vault.mutate(&mut *db, |vault| {
+            let mutations = txids
+                .into_iter()
+                .map(|txid| SweepVaultMutation::Discard { txid })
+                .collect::<Vec<_>>();
+            for mutation in &mutations {
+                vault.apply(mutation);
+            }
+            Ok(((), mutations))
+        })
+    }
+
+    fn unspent_outpoints(&mut self, master_appkey: MasterAppkey) -> BTreeSet<OutPoint> {
+        self.all_unspent(master_appkey)
+            .into_iter()
+            .map(|(_, outpoint, _)| outpoint)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bitcoin::test_fixture::{Fixture, NETWORK};
+
+    fn sweep(f: &mut Fixture, feerate: f32) -> TransactionTemplate {
+        let destination = bitcoin::Address::from_script(&Fixture::stranger_spk(), NETWORK).unwrap();
+        let plan = f
+            .wallet
+            .plan_sweep(f.master_appkey, &destination, feerate)
+            .unwrap();
+        f.wallet.commit_send(&plan, []).unwrap()
+    }
+
+    fn signatures(template: &TransactionTemplate) -> Vec<EncodedSignature> {
+        vec![EncodedSignature([7; 64]); template.inputs().len()]
+    }
+
+    #[test]
+    fn a_sweep_is_stored_encrypted() {
+        let mut rng = rand::thread_rng();
+        let key = SymmetricKey([3; 32]);
+        let mut f = Fixture::new();
+        let coins = [f.fund(0, 100_000, 100), f.fund(1, 50_000, 101)];
+
+        let mut stored = vec![];
+        for feerate in [20.0, 2.0] {
+            let template = sweep(&mut f, feerate);
+            assert_eq!(template.inputs().len(), 2);
+            let txid = f
+                .wallet
+                .store_presigned_sweep(
+                    f.master_appkey,
+                    &template,
+                    &signatures(&template),
+                    key,
+                    &mut rng,
+                )
+                .unwrap();
+            stored.push(txid);
+        }
+        let sweeps = f.wallet.presigned_sweeps(f.master_appkey);
+        assert_eq!(
+            sweeps
+                .iter()
+                .map(|(sweep, status)| (sweep.txid, status.clone()))
+                .collect::<Vec<_>>(),
+            vec![
+                (stored[1], SweepStatus::Ready),
+                (stored[0], SweepStatus::Ready)
+            ]
+        );
+        assert_eq!(sweeps[0].0.spends, BTreeSet::from(coins));
+        assert_eq!(sweeps[0].0.destination, Fixture::stranger_spk());
+
+        let tx = f.wallet.presigned_sweep_tx(stored[0], key).unwrap();
+        assert_eq!(tx.compute_txid(), stored[0]);
+        assert!(tx.input.iter().all(|txin| !txin.witness.is_empty()));
+        assert!(f
+            .wallet
+            .presigned_sweep_tx(stored[0], SymmetricKey([4; 32]))
+            .is_err());
+
+        // it survives a reload
+        let reloaded = {
+            let mut db = f.wallet.db.lock().unwrap();
+            SweepVault::load(&mut *db, ()).unwrap()
+        };
+        assert_eq!(reloaded.sweeps, f.wallet.sweep_vault.sweeps);
+    }
+
+    fn store(f: &mut Fixture, feerate: f32) -> Txid {
+        let template = sweep(f, feerate);
+        f.wallet
+            .store_presigned_sweep(
+                f.master_appkey,
+                &template,
+                &signatures(&template),
+                SymmetricKey([3; 32]),
+                &mut rand::thread_rng(),
+            )
+            .unwrap()
+    }
+
+    fn statuses(f: &mut Fixture) -> Vec<SweepStatus> {
+        f.wallet
+            .presigned_sweeps(f.master_appkey)
+            .into_iter()
+            .map(|(_, status)| status)
+            .collect()
+    }
+
+    #[test]
+    fn new_coins_leave_sweeps_in_place_until_the_user_discards_them() {
+        let mut f = Fixture::new();
+        f.fund(0, 100_000, 100);
+        let cheap = store(&mut f, 2.0);
+        let dear = store(&mut f, 20.0);
+
+        f.fund(1, 546, 101);
+        assert_eq!(
+            statuses(&mut f),
+            vec![SweepStatus::LeavesBehind { value: 546 }; 2]
+        );
+
+        f.wallet.discard_presigned_sweep(cheap).unwrap();
+        assert_eq!(
+            f.wallet
+                .presigned_sweeps(f.master_appkey)
+                .into_iter()
+                .map(|(sweep, _)| sweep.txid)
+                .collect::<Vec<_>>(),
+            vec![dear]
+        );
+        assert!(f.wallet.discard_presigned_sweep(cheap).is_err());
+    }
+
+    #[test]
+    fn a_broadcast_sweep_keeps_its_siblings_for_bumping() {
+        let key = SymmetricKey([3; 32]);
+        let mut f = Fixture::new();
+        f.fund(0, 100_000, 100);
+        let cheap = store(&mut f, 2.0);
+        let dear = store(&mut f, 20.0);
+
+        let tx = f.wallet.presigned_sweep_tx(cheap, key).unwrap();
+        f.deliver(tx, None);
+        assert_eq!(
+            statuses(&mut f),
+            vec![
+                SweepStatus::Broadcast,
+                SweepStatus::Replaces { txids: vec![cheap] }
+            ]
+        );
+
+        let tx = f.wallet.presigned_sweep_tx(dear, key).unwrap();
+        f.deliver(tx, Some(102));
+        assert_eq!(
+            statuses(&mut f),
+            vec![SweepStatus::Stale, SweepStatus::Confirmed]
+        );
+    }
+
+    #[test]
+    fn a_sweep_planned_before_the_coins_changed_is_refused() {
+        let mut f = Fixture::new();
+        f.fund(0, 100_000, 100);
+        let template = sweep(&mut f, 5.0);
+        f.fund(1, 50_000, 101);
+
+        assert!(f
+            .wallet
+            .store_presigned_sweep(
+                f.master_appkey,
+                &template,
+                &signatures(&template),
+                SymmetricKey([3; 32]),
+                &mut rand::thread_rng(),
+            )
+            .is_err());
+    }
+}
@@ -0,0 +1,218 @@
+//! A wallet fed by hand instead of by a chain source, for tests that need history in it.
+
+use super::{
+    chain_sync::{ChainClient, ConnectionHandler, ElectrumConfig},
+    wallet::CoordSuperWallet,
+};
+use crate::{persist::Persisted, settings::ElectrumEnabled};
+use bdk_chain::{
+    bitcoin::{self, hashes::Hash, Amount, BlockHash, OutPoint, ScriptBuf, TxIn, TxOut, Txid},
+    BlockId, CheckPoint, ConfirmationBlockTime, TxUpdate,
+};
+use frostsnap_core::{
+    schnorr_fun::fun::Point,
+    tweak::{BitcoinAccountKeychain, BitcoinBip32Path, NormalIndex},
+    MasterAppkey,
+};
+use std::{
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+pub const NETWORK: bitcoin::Network = bitcoin::Network::Bitcoin;
+
+/// Fixture block times are the height in seconds past this, so a record's time says which block
+/// it came from.
+pub const EPOCH: u64 = 1_700_000_000;
+
+/// The handler owns the receiving ends of the client's channels, so it must outlive every
+/// `ChainClient` call or `monitor_keychain`'s send panics.
+pub fn chain_client(db: &Arc<Mutex<rusqlite::Connection>>) -> (ChainClient, ConnectionHandler) {
+    let trusted = {
+        let mut conn = db.lock().unwrap();
+        Persisted::new(&mut *conn, NETWORK).unwrap()
+    };
+    ChainClient::new(
+        bitcoin::constants::genesis_block(NETWORK).block_hash(),
+        ElectrumConfig {
+            enabled: ElectrumEnabled::None,
+            primary: String::new(),
+            backup: String::new(),
+        },
+        trusted,
+        db.clone(),
+    )
+}
+
+pub struct Fixture {
+    pub wallet: CoordSuperWallet,
+    pub handler: ConnectionHandler,
+    pub master_appkey: MasterAppkey,
+    /// A payee nobody in the fixture owns.
+    pub recipient: bitcoin::Address,
+    blocks: Vec<BlockId>,
+    funded: u32,
+}
+
+impl Fixture {
+    pub fn new() -> Self {
+        let db = Arc::new(Mutex::new(rusqlite::Connection::open_in_memory().unwrap()));
+        let (client, handler) = chain_client(&db);
+        let master_appkey =
+            MasterAppkey::derive_from_rootkey(Point::random(&mut rand::thread_rng()));
+        let mut wallet = CoordSuperWallet::load_or_init(db, NETWORK, client).unwrap();
+        wallet.list_addresses(master_appkey);
+        Self {
+            wallet,
+            handler,
+            master_appkey,
+            recipient: Self::stranger(),
+            blocks: vec![BlockId {
+                height: 0,
+                hash: bitcoin::constants::genesis_block(NETWORK).block_hash(),
+            }],
+            funded: 0,
+        }
+    }
+
+    pub fn spk(&self, path: BitcoinBip32Path) -> ScriptBuf {
+        super::peek_spk(self.master_appkey, path)
+    }
+
+    pub fn receive_spk(&self, index: u32) -> ScriptBuf {
+        self.spk(BitcoinBip32Path::external(
+            NormalIndex::new(index).expect("fixture index below 2^31"),
+        ))
+    }
+
+    fn stranger() -> bitcoin::Address {
+        bitcoin::Address::from_str("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4")
+            .unwrap()
+            .require_network(NETWORK)
+            .unwrap()
+    }
+
+    /// The script of [`Fixture::recipient`].
+    pub fn stranger_spk() -> ScriptBuf {
+        Self::stranger().script_pubkey()
+    }
+
+    /// An external payment of `value` to receive address `index`, confirmed at `height`.
+    pub fn fund(&mut self, index: u32, value: u64, height: u32) -> OutPoint {
+        self.fund_keychain(BitcoinAccountKeychain::external(), index, value, height)
+    }
+
+    /// A confirmed payment to either keychain, reported the way a sync reports activity: with the
+    /// index it landed on as the keychain's last active one.
+    pub fn fund_keychain(
+        &mut self,
+        account_keychain: BitcoinAccountKeychain,
+        index: u32,
+        value: u64,
+        height: u32,
+    ) -> OutPoint {
+        let tx = bitcoin::Transaction {
+            version: bitcoin::transaction::Version::TWO,
+            lock_time: bitcoin::absolute::LockTime::ZERO,
+            // distinct prevouts, or every funding would conflict with every other
+            input: vec![TxIn {
+                previous_output: OutPoint::new(Txid::all_zeros(), self.funded),
+                ..Default::default()
+            }],
+            output: vec![TxOut {
+                value: Amount::from_sat(value),
+                script_pubkey: self.spk(BitcoinBip32Path {
+                    account_keychain,
+                    index: NormalIndex::new(index).expect("fixture index below 2^31"),
+                }),
+            }],
+        };
+        self.funded += 1;
+        let txid = self.apply(
+            tx,
+            Some(height),
+            [((self.master_appkey, account_keychain), index)].into(),
+        );
+        OutPoint { txid, vout: 0 }
+    }
+
+    /// Spend `inputs` into `outputs`, confirmed at `height` or left in the mempool.
+    pub fn spend(
+        &mut self,
+        inputs: impl IntoIterator<Item = OutPoint>,
+        outputs: Vec<TxOut>,
+        height: Option<u32>,
+    ) -> Txid {
+        let tx = bitcoin::Transaction {
+            version: bitcoin::transaction::Version::TWO,
+            lock_time: bitcoin::absolute::LockTime::ZERO,
+            input: inputs
+                .into_iter()
+                .map(|previous_output| TxIn {
+                    previous_output,
+                    ..Default::default()
+                })
+                .collect(),
+            output: outputs,
+        };
+        self.deliver(tx, height)
+    }
+
+    /// Hand the wallet `tx` the way a sync would, naming no active indices.
+    pub fn deliver(&mut self, tx: bitcoin::Transaction, height: Option<u32>) -> Txid {
+        self.apply(tx, height, Default::default())
+    }
+
+    fn apply(
+        &mut self,
+        tx: bitcoin::Transaction,
+        height: Option<u32>,
+        last_active_indices: std::collections::BTreeMap<
+            (MasterAppkey, BitcoinAccountKeychain),
+            u32,
+        >,
+    ) -> Txid {
+        let txid = tx.compute_txid();
+        let mut tx_update = TxUpdate::default();
+        tx_update.txs = vec![Arc::new(tx)];
+        match height {
+            Some(height) => {
+                let block = self.block_at(height);
+                tx_update.anchors = [(
+                    ConfirmationBlockTime {
+                        block_id: block,
+                        confirmation_time: EPOCH + height as u64,
+                    },
+                    txid,
+                )]
+                .into();
+            }
+            None => {
+                tx_update.seen_ats = [(txid, EPOCH + 1_000_000)].into();
+            }
+        }
+        self.wallet
+            .apply_update(bdk_electrum_streaming::Update {
+                tx_update,
+                last_active_indices,
+                chain_update: Some(
+                    CheckPoint::from_block_ids(self.blocks.iter().copied()).unwrap(),
+                ),
+            })
+            .unwrap();
+        txid
+    }
+
+    fn block_at(&mut self, height: u32) -> BlockId {
+        if let Some(block) = self.blocks.iter().find(|block| block.height == height) {
+            return *block;
+        }
+        let block = BlockId {
+            height,
+            hash: BlockHash::from_byte_array([height as u8; 32]),
+        };
+        self.blocks.push(block);
+        self.blocks.sort_by_key(|block| block.height);
+        block
+    }
+}
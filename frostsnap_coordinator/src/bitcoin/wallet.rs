@@ -49,6 +49,7 @@ pub type WalletIndexedTxGraphChangeSet =
 pub struct CoordSuperWallet {
     pub(super) tx_graph: Persisted<WalletIndexedTxGraph>,
     pub(super) chain: Persisted<local_chain::LocalChain>,
+    pub(super) sweep_vault: Persisted<super::sweep_vault::SweepVault>,
     pub(super) chain_client: ChainClient,
     pub network: bitcoin::Network,
     pub(super) db: Arc<Mutex<rusqlite::Connection>>,
@@ -88,11 +89,14 @@ impl CoordSuperWallet {
             bitcoin::constants::genesis_block(network).block_hash(),
         )
         .context("loading chain from database")?;
+        let sweep_vault =
+            Persisted::new(&mut *db_, ()).context("loading pre-signed sweeps from database")?;
         drop(db_);
 
         Ok(Self {
             tx_graph,
             chain,
+            sweep_vault,
             chain_client,
             db,
             network,
@@ -481,7 +485,7 @@ impl CoordSuperWallet {
     pub fn calculate_avaliable_value(
         &mut self,
         master_appkey: MasterAppkey,
-        target_addresses: impl IntoIterator<Item = bitcoin::Address>,
+        targets: impl IntoIterator<Item = impl Into<super::send::SendRecipient>>,
         feerate: f32,
         effective_only: bool,
     ) -> i64 {
@@ -494,9 +498,9 @@ impl CoordSuperWallet {
         let feerate = FeeRate::from_sat_per_vb(feerate);
         let target = Target {
             fee: TargetFee::from_feerate(feerate),
-            outputs: TargetOutputs::fund_outputs(target_addresses.into_iter().map(|addr| {
+            outputs: TargetOutputs::fund_outputs(targets.into_iter().map(|target| {
                 let txo = bitcoin::TxOut {
-                    script_pubkey: addr.script_pubkey(),
+                    script_pubkey: target.into().planning_spk(),
                     value: Amount::ZERO,
                 };
                 (txo.weight().to_wu(), 0)
@@ -593,6 +597,63 @@ pub struct Transaction {
     pub is_mine: HashMap<ScriptBuf, u32>,
 }
 
+impl Transaction {
+    /// See [`net_value`].
+    pub fn net_value(&self) -> Option<i64> {
+        net_value(&self.inner, &self.prevouts, &self.is_mine)
+    }
+
+    /// See [`fee`].
+    pub fn fee(&self) -> Option<u64> {
+        fee(&self.inner, &self.prevouts)
+    }
+}
+
+/// Owned outputs minus owned inputs: what `tx` did to the key's balance.
+///
+/// `None` when any input's prevout is missing: `is_mine` is built from the prevouts we hold, so a
+/// missing one could be ours and the amount it took is unknown.
+pub fn net_value(
+    tx: &bitcoin::Transaction,
+    prevouts: &HashMap<OutPoint, TxOut>,
+    is_mine: &HashMap<ScriptBuf, u32>,
+) -> Option<i64> {
+    let spent: u64 = tx
+        .input
+        .iter()
+        .map(|txin| prevouts.get(&txin.previous_output))
+        .collect::<Option<Vec<_>>>()?
+        .into_iter()
+        .filter(|prevout| is_mine.contains_key(&prevout.script_pubkey))
+        .map(|prevout| prevout.value.to_sat())
+        .sum();
+    let received: u64 = tx
+        .output
+        .iter()
+        .filter(|txout| is_mine.contains_key(&txout.script_pubkey))
+        .map(|txout| txout.value.to_sat())
+        .sum();
+    Some(received as i64 - spent as i64)
+}
+
+/// Inputs minus outputs. `None` when any input's prevout is missing.
+///
+/// Takes the parts rather than a [`Transaction`] so a fee can be had before the transaction is
+/// built or broadcast.
+pub fn fee(tx: &bitcoin::Transaction, prevouts: &HashMap<OutPoint, TxOut>) -> Option<u64> {
+    let inputs = tx
+        .input
+        .iter()
+        .map(|txin| {
+            prevouts
+                .get(&txin.previous_output)
+                .map(|txout| txout.value.to_sat())
+        })
+        .sum::<Option<u64>>()?;
+    let outputs: u64 = tx.output.iter().map(|o| o.value.to_sat()).sum();
+    Some(inputs.saturating_sub(outputs))
+}
+
 #[derive(Clone, Debug)]
 pub struct ConfirmationTime {
     pub height: u32,
@@ -0,0 +1,862 @@
+//! Keeping several coordinators of the same keys in step.
+//!
+//! Every mutation a coordinator makes is also written to a sync log as `(origin, seq)`, where
+//! `origin` is the coordinator's [`CoordinatorId`] and `seq` counts up from zero. Mutations made
+//! before the log existed are logged as the coordinator's own when it's created. Two
+//! coordinators sync by telling each other how far into every origin's entries they are, then
+//! sending what the other is missing in the order they logged it. Since a coordinator logs an
+//! entry only after everything it had already applied, that order never puts a mutation before
+//! one it depends on, and entries pass through a coordinator to ones it syncs with later.
+//!
+//! Conflict rules:
+//!
+//! - **Nonces never leave the coordinator that got them.** Stream ids are chosen at random by the
+//!   coordinator that opens the stream, and neither `NewNonces` nor the coordinator's nonce
+//!   partitions on its devices are shared, so every stream has exactly one coordinator that can
+//!   allocate from it. A session from elsewhere arrives with its nonces
+//!   already chosen from that coordinator's streams. If one claims a stream we hold it's
+//!   refused, so a stream is never allocated by two coordinators.
+//! - **Restorations stay local.** They're a flow the user is going through on that coordinator.
+//! - **Deletion wins.** Shares, access structures and sessions for a key we no longer have are
+//!   dropped, and anything for a session that has been closed is ignored. Once a key is deleted
+//!   here its entries are cut down to tombstones, so what it was doesn't outlive it in the log.
+//! - **Keys change only with the user.** Anyone who can reach a coordinator can sync with it, so
+//!   a key, access structure or share added or deleted elsewhere is [`held`] until the user
+//!   accepts or rejects it here. Sessions aren't held: one only asks the devices to sign, and each
+//!   shows its user what that is.
+//!
+//! Entries that are dropped are still logged and passed on, so every coordinator ends up with
+//! the same log even when it doesn't apply all of it.
+use crate::frostsnap_persist::store_mutation;
+use crate::persist::BincodeWrapper;
+use anyhow::{anyhow, Context as _};
+use bdk_chain::rusqlite_impl::migrate_schema;
+use frostsnap_core::{
+    coordinator::{keys::KeyMutation, signing::SigningMutation, FrostCoordinator, Mutation},
+    KeyId,
+};
+use rusqlite::{params, OptionalExtension};
+use std::{
+    collections::BTreeMap,
+    sync::mpsc::{channel, Receiver, Sender},
+};
+use tracing::{event, Level};
+
+/// Identifies a coordinator to the others it syncs with. Made at random the first time the
+/// database is opened.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct CoordinatorId(pub [u8; 32]);
+
+frostsnap_core::impl_display_debug_serialize! {
+    fn to_bytes(id: &CoordinatorId) -> [u8;32] {
+        id.0
+    }
+}
+
+frostsnap_core::impl_fromstr_deserialize! {
+    name => "coordinator id",
+    fn from_bytes(bytes: [u8;32]) -> CoordinatorId {
+        CoordinatorId(bytes)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, bincode::Encode, bincode::Decode)]
+pub struct SyncEntry {
+    pub origin: CoordinatorId,
+    pub seq: u64,
+    /// `None` once the key the mutation was for has been deleted. The entry stays so every origin's
+    /// entries still count up without a gap.
+    pub mutation: Option<Mutation>,
+}
+
+#[derive(Clone, Debug, PartialEq, bincode::Encode, bincode::Decode)]
+pub enum SyncMessage {
+    /// For each origin, the `seq` of the first of its entries we don't have.
+    Have(BTreeMap<CoordinatorId, u64>),
+    Entries(Vec<SyncEntry>),
+}
+
+/// Whether other coordinators get to see `mutation`. See the module docs.
+pub fn is_shared(mutation: &Mutation) -> bool {
+    !matches!(
+        mutation,
+        Mutation::Signing(
+            SigningMutation::NewNonces { .. } | SigningMutation::NewNoncePartition { .. }
+        ) | Mutation::Restoration(_)
+    )
+}
+
+/// Whether a mutation from another coordinator can be applied to ours without conflict.
+fn can_apply(coordinator: &FrostCoordinator, mutation: &Mutation) -> bool {
+    match mutation {
+        Mutation::Keygen(KeyMutation::NewAccessStructure { shared_key, .. }) => coordinator
+            .get_frost_key(frostsnap_core::MasterAppkey::from_xpub_unchecked(shared_key).key_id())
+            .is_some(),
+        Mutation::Keygen(KeyMutation::NewShare {
+            access_structure_ref,
+            ..
+        })
+        | Mutation::Keygen(KeyMutation::DeleteShare {
+            access_structure_ref,
+            ..
+        }) => coordinator
+            .get_access_structure(*access_structure_ref)
+            .is_some(),
+        Mutation::Keygen(KeyMutation::DeleteKey(key_id)) => {
+            coordinator.get_frost_key(*key_id).is_some()
+        }
+        Mutation::Signing(SigningMutation::NewSigningSession(session)) => {
+            coordinator.get_frost_key(session.key_id).is_some()
+                && !session.init.nonces.iter().any(|(device_id, nonces)| {
+                    coordinator.has_nonce_stream(*device_id, nonces.stream_id)
+                })
+        }
+        mutation => is_shared(mutation),
+    }
+}
+
+pub fn migrate(conn: &mut rusqlite::Connection) -> anyhow::Result<()> {
+    const SCHEMA_NAME: &str = "frostsnap_coordinator_sync";
+    const MIGRATIONS: &[&str] = &[
+        // Version 0
+        "CREATE TABLE IF NOT EXISTS fs_sync_identity ( \
+            id BLOB NOT NULL \
+        ); \
+        CREATE TABLE IF NOT EXISTS fs_sync_log ( \
+            position INTEGER PRIMARY KEY AUTOINCREMENT, \
+            origin BLOB NOT NULL, \
+            seq INTEGER NOT NULL, \
+            mutation BLOB, \
+            tied_to_key TEXT, \
+            UNIQUE (origin, seq) \
+        ); \
+        CREATE TABLE IF NOT EXISTS fs_sync_heads ( \
+            origin BLOB PRIMARY KEY, \
+            next_seq INTEGER NOT NULL \
+        ); \
+        CREATE TABLE IF NOT EXISTS fs_sync_held ( \
+            origin BLOB NOT NULL, \
+            seq INTEGER NOT NULL, \
+            PRIMARY KEY (origin, seq) \
+        )",
+    ];
+
+    let db_tx = conn.transaction()?;
+    let backfill =
+        !table_exists(&db_tx, "fs_sync_log")? && table_exists(&db_tx, "fs_coordinator_mutations")?;
+    migrate_schema(&db_tx, SCHEMA_NAME, MIGRATIONS)?;
+    if backfill {
+        // deleted keys' mutations are already gone from here, so there is nothing to redact
+        let mutations = db_tx
+            .prepare("SELECT mutation, tied_to_key FROM fs_coordinator_mutations ORDER BY id")?
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, BincodeWrapper<Mutation>>(0)?.0,
+                    row.get::<_, Option<KeyId>>(1)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()
+            .context("failed to decode an fs_coordinator_mutation")?;
+        for (mutation, tied_to_key) in &mutations {
+            if is_shared(mutation) {
+                let origin = local_id(&db_tx)?;
+                let seq = next_seq(&db_tx, origin)?;
+                append(&db_tx, origin, seq, Some(mutation), *tied_to_key)?;
+            }
+        }
+    }
+    db_tx.commit()?;
+    Ok(())
+}
+
+fn table_exists(conn: &rusqlite::Connection, table: &str) -> rusqlite::Result<bool> {
+    conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name=?1",
+        params![table],
+        |row| row.get::<_, u32>(0),
+    )
+    .map(|count| count > 0)
+}
+
+pub fn local_id(conn: &rusqlite::Connection) -> anyhow::Result<CoordinatorId> {
+    let existing = conn
+        .query_row("SELECT id FROM fs_sync_identity", [], |row| {
+            row.get::<_, [u8; 32]>(0)
+        })
+        .optional()?;
+    match existing {
+        Some(id) => Ok(CoordinatorId(id)),
+        None => {
+            let mut id = [0u8; 32];
+            rand_core::RngCore::fill_bytes(&mut rand::thread_rng(), &mut id);
+            conn.execute("INSERT INTO fs_sync_identity (id) VALUES (?1)", params![id])?;
+            Ok(CoordinatorId(id))
+        }
+    }
+}
+
+/// Log a mutation this coordinator made, if it's one that is shared. `coordinator` is the state
+/// it was made in.
+pub(crate) fn record_local(
+    conn: &rusqlite::Connection,
+    coordinator: &FrostCoordinator,
+    mutation: &Mutation,
+) -> anyhow::Result<()> {
+    if !is_shared(mutation) {
+        return Ok(());
+    }
+    let origin = local_id(conn)?;
+    let seq = next_seq(conn, origin)?;
+    append(
+        conn,
+        origin,
+        seq,
+        Some(mutation),
+        tied_to_key(coordinator, mutation),
+    )
+}
+
+fn next_seq(conn: &rusqlite::Connection, origin: CoordinatorId) -> anyhow::Result<u64> {
+    Ok(conn
+        .query_row(
+            "SELECT next_seq FROM fs_sync_heads WHERE origin=?1",
+            params![origin.0],
+            |row| row.get::<_, u64>(0),
+        )
+        .optional()?
+        .unwrap_or(0))
+}
+
+/// The key whose deletion turns `mutation`'s entry into a tombstone.
+fn tied_to_key(coordinator: &FrostCoordinator, mutation: &Mutation) -> Option<KeyId> {
+    match mutation {
+        // kept so the deletion reaches the coordinators we sync with
+        Mutation::Keygen(KeyMutation::DeleteKey(_)) => None,
+        mutation => mutation.tied_to_key(coordinator),
+    }
+}
+
+fn append(
+    conn: &rusqlite::Connection,
+    origin: CoordinatorId,
+    seq: u64,
+    mutation: Option<&Mutation>,
+    tied_to_key: Option<KeyId>,
+) -> anyhow::Result<()> {
+    // an entry for a key we've already deleted arrives from a coordinator that hadn't heard yet
+    let deleted = match tied_to_key {
+        Some(key_id) => conn.query_row(
+            "SELECT EXISTS (SELECT 1 FROM fs_sync_log WHERE tied_to_key=?1 AND mutation IS NULL)",
+            params![key_id],
+            |row| row.get::<_, bool>(0),
+        )?,
+        None => false,
+    };
+    let mutation = mutation.filter(|_| !deleted);
+    conn.execute(
+        "INSERT INTO fs_sync_log (origin, seq, mutation, tied_to_key) VALUES (?1, ?2, ?3, ?4)",
+        params![origin.0, seq, mutation.map(BincodeWrapper), tied_to_key],
+    )?;
+    conn.execute(
+        "INSERT OR REPLACE INTO fs_sync_heads (origin, next_seq) VALUES (?1, ?2)",
+        params![origin.0, seq + 1],
+    )?;
+    Ok(())
+}
+
+/// Cut the entries for a key that has been deleted down to tombstones.
+pub(crate) fn redact_key(conn: &rusqlite::Connection, key_id: KeyId) -> anyhow::Result<()> {
+    conn.execute(
+        "UPDATE fs_sync_log SET mutation = NULL WHERE tied_to_key=?1",
+        params![key_id],
+    )?;
+    Ok(())
+}
+
+/// How far into each origin's entries we are.
+pub fn have(conn: &rusqlite::Connection) -> anyhow::Result<BTreeMap<CoordinatorId, u64>> {
+    let mut stmt = conn.prepare("SELECT origin, next_seq FROM fs_sync_heads")?;
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            CoordinatorId(row.get::<_, [u8; 32]>(0)?),
+            row.get::<_, u64>(1)?,
+        ))
+    })?;
+    Ok(rows.collect::<Result<_, _>>()?)
+}
+
+/// Entries a coordinator that `has` this much is missing, in the order we logged them.
+pub fn missing_from(
+    conn: &rusqlite::Connection,
+    has: &BTreeMap<CoordinatorId, u64>,
+) -> anyhow::Result<Vec<SyncEntry>> {
+    let mut stmt =
+        conn.prepare("SELECT origin, seq, mutation FROM fs_sync_log ORDER BY position")?;
+    let rows = stmt.query_map([], |row| {
+        Ok(SyncEntry {
+            origin: CoordinatorId(row.get::<_, [u8; 32]>(0)?),
+            seq: row.get(1)?,
+            mutation: row
+                .get::<_, Option<BincodeWrapper<Mutation>>>(2)?
+                .map(|mutation| mutation.0),
+        })
+    })?;
+    let mut entries = vec![];
+    for entry in rows {
+        let entry = entry.context("failed to decode a sync log entry")?;
+        if entry.seq >= has.get(&entry.origin).copied().unwrap_or(0) {
+            entries.push(entry);
+        }
+    }
+    Ok(entries)
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SyncReport {
+    /// New entries applied to our coordinator.
+    pub applied: usize,
+    /// New entries logged but not applied because of a conflict rule.
+    pub dropped: usize,
+    /// New entries logged but waiting for the user, see [`held`].
+    pub held: usize,
+}
+
+/// Whether the user has to accept `mutation` before it's applied. See the module docs.
+fn needs_confirmation(mutation: &Mutation) -> bool {
+    matches!(mutation, Mutation::Keygen(_))
+}
+
+/// Whether `mutation` is held even if it can't be applied yet, since what it builds on may be
+/// held too.
+fn is_addition(mutation: &Mutation) -> bool {
+    matches!(
+        mutation,
+        Mutation::Keygen(
+            KeyMutation::NewKey { .. }
+                | KeyMutation::NewAccessStructure { .. }
+                | KeyMutation::NewShare { .. }
+        )
+    )
+}
+
+/// Log and apply entries from another coordinator. Ones we already have are skipped; a gap in an
+/// origin's entries is an error, and nothing after it is taken.
+///
+/// The entries are applied to a copy of `coordinator`, which only replaces it once they're
+/// written, so a database error never leaves the two disagreeing.
+pub fn receive(
+    conn: &mut rusqlite::Connection,
+    coordinator: &mut FrostCoordinator,
+    entries: Vec<SyncEntry>,
+) -> anyhow::Result<SyncReport> {
+    let db_tx = conn.transaction()?;
+    let local = local_id(&db_tx)?;
+    let mut next = have(&db_tx)?;
+    let mut staged = coordinator.clone();
+    let mut report = SyncReport::default();
+    let mut gap = None;
+
+    for entry in entries {
+        let expected = next.get(&entry.origin).copied().unwrap_or(0);
+        if entry.origin == local || entry.seq < expected {
+            continue;
+        }
+        if entry.seq > expected {
+            gap = Some(anyhow!(
+                "sync entries from {} skip from {} to {}",
+                entry.origin,
+                expected,
+                entry.seq
+            ));
+            break;
+        }
+        let tied_to_key = entry
+            .mutation
+            .as_ref()
+            .and_then(|mutation| tied_to_key(&staged, mutation));
+        append(
+            &db_tx,
+            entry.origin,
+            entry.seq,
+            entry.mutation.as_ref(),
+            tied_to_key,
+        )?;
+        next.insert(entry.origin, entry.seq + 1);
+
+        let mutation = match entry.mutation {
+            Some(mutation) => mutation,
+            None => continue,
+        };
+        let held = needs_confirmation(&mutation);
+        if !can_apply(&staged, &mutation) && !(held && is_addition(&mutation)) {
+            event!(
+                Level::INFO,
+                origin = entry.origin.to_string(),
+                seq = entry.seq,
+                "dropped conflicting sync entry"
+            );
+            report.dropped += 1;
+            continue;
+        }
+        if held {
+            db_tx.execute(
+                "INSERT INTO fs_sync_held (origin, seq) VALUES (?1, ?2)",
+                params![entry.origin.0, entry.seq],
+            )?;
+            report.held += 1;
+            continue;
+        }
+        if let Some(mutation) = staged.apply_mutation(mutation) {
+            store_mutation(&staged, &db_tx, mutation)?;
+            report.applied += 1;
+        }
+    }
+
+    db_tx.commit()?;
+    *coordinator = staged;
+    match gap {
+        Some(gap) => Err(gap),
+        None => Ok(report),
+    }
+}
+
+/// Entries from other coordinators waiting for the user to accept or reject them, in the order
+/// they were logged.
+pub fn held(conn: &rusqlite::Connection) -> anyhow::Result<Vec<SyncEntry>> {
+    let mut stmt = conn.prepare(
+        "SELECT fs_sync_log.origin, fs_sync_log.seq, fs_sync_log.mutation FROM fs_sync_log \
+         JOIN fs_sync_held ON fs_sync_held.origin = fs_sync_log.origin \
+            AND fs_sync_held.seq = fs_sync_log.seq \
+         ORDER BY fs_sync_log.position",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok(SyncEntry {
+            origin: CoordinatorId(row.get::<_, [u8; 32]>(0)?),
+            seq: row.get(1)?,
+            mutation: row
+                .get::<_, Option<BincodeWrapper<Mutation>>>(2)?
+                .map(|mutation| mutation.0),
+        })
+    })?;
+    Ok(rows
+        .collect::<Result<_, _>>()
+        .context("failed to decode a held sync entry")?)
+}
+
+/// Settle a [`held`] entry. If `accept` it's applied as long as it still can be; either way it
+/// stops being held. An addition that builds on one still held can't be accepted before it.
+pub fn resolve_held(
+    conn: &mut rusqlite::Connection,
+    coordinator: &mut FrostCoordinator,
+    origin: CoordinatorId,
+    seq: u64,
+    accept: bool,
+) -> anyhow::Result<()> {
+    let db_tx = conn.transaction()?;
+    let removed = db_tx.execute(
+        "DELETE FROM fs_sync_held WHERE origin=?1 AND seq=?2",
+        params![origin.0, seq],
+    )?;
+    if removed == 0 {
+        return Err(anyhow!("no held sync entry {seq} from {origin}"));
+    }
+    let mut staged = None;
+    // a held entry can have become a tombstone if its key was deleted since
+    let mutation = db_tx
+        .query_row(
+            "SELECT mutation FROM fs_sync_log WHERE origin=?1 AND seq=?2",
+            params![origin.0, seq],
+            |row| row.get::<_, Option<BincodeWrapper<Mutation>>>(0),
+        )
+        .context("failed to read a held sync entry")?
+        .map(|mutation| mutation.0);
+    if let (true, Some(mutation)) = (accept, mutation) {
+        if can_apply(coordinator, &mutation) {
+            let mut coordinator = coordinator.clone();
+            if let Some(mutation) = coordinator.apply_mutation(mutation) {
+                store_mutation(&coordinator, &db_tx, mutation)?;
+            }
+            staged = Some(coordinator);
+        } else if is_addition(&mutation) {
+            return Err(anyhow!(
+                "held sync entry {seq} from {origin} needs one before it accepted first"
+            ));
+        }
+    }
+    db_tx.commit()?;
+    if let Some(staged) = staged {
+        *coordinator = staged;
+    }
+    Ok(())
+}
+
+/// Gets sync messages between two coordinators.
+pub trait SyncTransport {
+    fn send(&mut self, message: SyncMessage) -> anyhow::Result<()>;
+    /// Waits for the other side's next message.
+    fn receive(&mut self) -> anyhow::Result<SyncMessage>;
+}
+
+/// Both ends in one process, for tests.
+pub struct LocalTransport {
+    sender: Sender<SyncMessage>,
+    receiver: Receiver<SyncMessage>,
+}
+
+impl LocalTransport {
+    pub fn pair() -> (Self, Self) {
+        let (a_sender, b_receiver) = channel();
+        let (b_sender, a_receiver) = channel();
+        (
+            Self {
+                sender: a_sender,
+                receiver: a_receiver,
+            },
+            Self {
+                sender: b_sender,
+                receiver: b_receiver,
+            },
+        )
+    }
+}
+
+impl SyncTransport for LocalTransport {
+    fn send(&mut self, message: SyncMessage) -> anyhow::Result<()> {
+        self.sender
+            .send(message)
+            .map_err(|_| anyhow!("the other coordinator hung up"))
+    }
+
+    fn receive(&mut self) -> anyhow::Result<SyncMessage> {
+        self.receiver
+            .recv()
+            .map_err(|_| anyhow!("the other coordinator hung up"))
+    }
+}
+
+/// One round of sync. Both coordinators run this at the same time, each with its end of the
+/// transport, and afterwards each has everything the other had.
+pub fn sync(
+    conn: &mut rusqlite::Connection,
+    coordinator: &mut FrostCoordinator,
+    transport: &mut impl SyncTransport,
+) -> anyhow::Result<SyncReport> {
+    transport.send(SyncMessage::Have(have(conn)?))?;
+    let their_have = match transport.receive()? {
+        SyncMessage::Have(have) => have,
+        SyncMessage::Entries(_) => return Err(anyhow!("expected what they have first")),
+    };
+    transport.send(SyncMessage::Entries(missing_from(conn, &their_have)?))?;
+    match transport.receive()? {
+        SyncMessage::Entries(entries) => receive(conn, coordinator, entries),
+        SyncMessage::Have(_) => Err(anyhow!("expected entries")),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::persist::Persisted;
+    use frostsnap_core::{
+        coordinator::CompleteKey, device::KeyPurpose, schnorr_fun::fun::Point, Ciphertext,
+        MasterAppkey, SymmetricKey,
+    };
+
+    struct Coordinator {
+        conn: rusqlite::Connection,
+        coordinator: Persisted<FrostCoordinator>,
+    }
+
+    impl Coordinator {
+        fn new() -> Self {
+            let mut conn = rusqlite::Connection::open_in_memory().unwrap();
+            let coordinator = Persisted::new(&mut conn, ()).unwrap();
+            Self { conn, coordinator }
+        }
+
+        fn new_key(&mut self, name: &str) {
+            let mut rng = rand::thread_rng();
+            let rootkey = Point::random(&mut rng);
+            self.coordinator
+                .staged_mutate(&mut self.conn, |coordinator| {
+                    coordinator.mutate(Mutation::Keygen(KeyMutation::NewKey {
+                        key_name: name.into(),
+                        purpose: KeyPurpose::Test,
+                        complete_key: CompleteKey {
+                            master_appkey: MasterAppkey::derive_from_rootkey(rootkey),
+                            encrypted_rootkey: Ciphertext::encrypt(
+                                SymmetricKey([7u8; 32]),
+                                &rootkey,
+                                &mut rng,
+                            ),
+                            access_structures: Default::default(),
+                        },
+                    }));
+                    Ok(())
+                })
+                .unwrap();
+        }
+
+        fn delete_key(&mut self, name: &str) {
+            let key_id = self
+                .coordinator
+                .iter_keys()
+                .find(|key| key.key_name == name)
+                .unwrap()
+                .key_id;
+            self.coordinator
+                .staged_mutate(&mut self.conn, |coordinator| {
+                    coordinator.mutate(Mutation::Keygen(KeyMutation::DeleteKey(key_id)));
+                    Ok(())
+                })
+                .unwrap();
+        }
+
+        /// Accept everything held, in the order it was logged.
+        fn accept_held(&mut self) {
+            for entry in held(&self.conn).unwrap() {
+                resolve_held(
+                    &mut self.conn,
+                    self.coordinator.MUTATE_NO_PERSIST(),
+                    entry.origin,
+                    entry.seq,
+                    true,
+                )
+                .unwrap();
+            }
+        }
+
+        fn key_names(&self) -> Vec<String> {
+            let mut names = self
+                .coordinator
+                .iter_keys()
+                .map(|key| key.key_name.clone())
+                .collect::<Vec<_>>();
+            names.sort();
+            names
+        }
+    }
+
+    fn sync_pair(a: &mut Coordinator, b: &mut Coordinator) -> (SyncReport, SyncReport) {
+        let (mut a_end, mut b_end) = LocalTransport::pair();
+        std::thread::scope(|s| {
+            let a_side = s.spawn(|| {
+                sync(&mut a.conn, a.coordinator.MUTATE_NO_PERSIST(), &mut a_end).unwrap()
+            });
+            let b_report =
+                sync(&mut b.conn, b.coordinator.MUTATE_NO_PERSIST(), &mut b_end).unwrap();
+            (a_side.join().unwrap(), b_report)
+        })
+    }
+
+    #[test]
+    fn keys_made_on_either_side_end_up_on_both() {
+        let mut a = Coordinator::new();
+        let mut b = Coordinator::new();
+        a.new_key("laptop key");
+        b.new_key("phone key");
+
+        let (a_report, b_report) = sync_pair(&mut a, &mut b);
+        assert_eq!(a_report.held, 1);
+        assert_eq!(b_report.held, 1);
+        assert_eq!(a.key_names(), vec!["laptop key"]);
+
+        a.accept_held();
+        b.accept_held();
+        assert_eq!(a.key_names(), vec!["laptop key", "phone key"]);
+        assert_eq!(a.key_names(), b.key_names());
+
+        // what was synced is on disk too
+        let reloaded = Persisted::<FrostCoordinator>::new(&mut a.conn, ()).unwrap();
+        assert_eq!(*reloaded, *a.coordinator);
+
+        // nothing new the second time
+        let (a_report, b_report) = sync_pair(&mut a, &mut b);
+        assert_eq!(a_report, SyncReport::default());
+        assert_eq!(b_report, SyncReport::default());
+    }
+
+    #[test]
+    fn entries_pass_through_to_coordinators_that_never_met() {
+        let mut a = Coordinator::new();
+        let mut b = Coordinator::new();
+        let mut c = Coordinator::new();
+        a.new_key("from a");
+
+        sync_pair(&mut a, &mut b);
+        b.new_key("from b");
+        sync_pair(&mut b, &mut c);
+        c.accept_held();
+
+        assert_eq!(c.key_names(), vec!["from a", "from b"]);
+        assert_eq!(
+            have(&c.conn).unwrap(),
+            BTreeMap::from([
+                (local_id(&a.conn).unwrap(), 1),
+                (local_id(&b.conn).unwrap(), 1),
+            ])
+        );
+    }
+
+    #[test]
+    fn a_gap_in_an_origins_entries_is_refused() {
+        let mut a = Coordinator::new();
+        let mut b = Coordinator::new();
+        a.new_key("first");
+        a.new_key("second");
+
+        let mut entries = missing_from(&a.conn, &BTreeMap::new()).unwrap();
+        entries.remove(0);
+        assert!(receive(&mut b.conn, b.coordinator.MUTATE_NO_PERSIST(), entries).is_err());
+        assert!(b.key_names().is_empty());
+    }
+
+    #[test]
+    fn deletions_from_elsewhere_wait_for_the_user() {
+        let mut a = Coordinator::new();
+        let mut b = Coordinator::new();
+        a.new_key("spending");
+        a.new_key("savings");
+        sync_pair(&mut a, &mut b);
+        b.accept_held();
+
+        a.delete_key("spending");
+        a.delete_key("savings");
+        let (_, b_report) = sync_pair(&mut a, &mut b);
+        assert_eq!(b_report.held, 2);
+        assert_eq!(b.key_names(), vec!["savings", "spending"]);
+
+        let waiting = held(&b.conn).unwrap();
+        assert_eq!(waiting.len(), 2);
+        let a_id = local_id(&a.conn).unwrap();
+        resolve_held(
+            &mut b.conn,
+            b.coordinator.MUTATE_NO_PERSIST(),
+            a_id,
+            waiting[0].seq,
+            true,
+        )
+        .unwrap();
+        resolve_held(
+            &mut b.conn,
+            b.coordinator.MUTATE_NO_PERSIST(),
+            a_id,
+            waiting[1].seq,
+            false,
+        )
+        .unwrap();
+        assert_eq!(b.key_names(), vec!["savings"]);
+        assert!(held(&b.conn).unwrap().is_empty());
+
+        let reloaded = Persisted::<FrostCoordinator>::new(&mut b.conn, ()).unwrap();
+        assert_eq!(*reloaded, *b.coordinator);
+    }
+
+    #[test]
+    fn mutations_from_before_the_log_are_logged_as_our_own() {
+        let mut a = Coordinator::new();
+        a.new_key("from before sync");
+        a.conn
+            .execute_batch(
+                "DROP TABLE fs_sync_identity; \
+                 DROP TABLE fs_sync_log; \
+                 DROP TABLE fs_sync_heads; \
+                 DROP TABLE fs_sync_held; \
+                 DELETE FROM bdk_schemas WHERE name = 'frostsnap_coordinator_sync';",
+            )
+            .unwrap();
+
+        migrate(&mut a.conn).unwrap();
+        assert_eq!(
+            have(&a.conn).unwrap(),
+            BTreeMap::from([(local_id(&a.conn).unwrap(), 1)])
+        );
+
+        let mut b = Coordinator::new();
+        sync_pair(&mut a, &mut b);
+        b.accept_held();
+        assert_eq!(b.key_names(), vec!["from before sync"]);
+    }
+
+    #[test]
+    fn a_key_from_elsewhere_waits_for_the_user() {
+        let mut a = Coordinator::new();
+        let mut b = Coordinator::new();
+        a.new_key("planted");
+
+        let (_, b_report) = sync_pair(&mut a, &mut b);
+        assert_eq!(b_report.held, 1);
+        assert!(b.key_names().is_empty());
+
+        let waiting = held(&b.conn).unwrap();
+        resolve_held(
+            &mut b.conn,
+            b.coordinator.MUTATE_NO_PERSIST(),
+            waiting[0].origin,
+            waiting[0].seq,
+            false,
+        )
+        .unwrap();
+        assert!(b.key_names().is_empty());
+        assert!(held(&b.conn).unwrap().is_empty());
+
+        // it isn't offered again
+        let (_, b_report) = sync_pair(&mut a, &mut b);
+        assert_eq!(b_report, SyncReport::default());
+    }
+
+    #[test]
+    fn a_deleted_key_leaves_only_tombstones() {
+        let mut a = Coordinator::new();
+        a.new_key("secret");
+        a.new_key("kept");
+        a.delete_key("secret");
+
+        let entries = missing_from(&a.conn, &BTreeMap::new()).unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(
+            entries[0].mutation, None,
+            "the deleted key is gone from the log"
+        );
+        assert!(matches!(
+            entries[1].mutation,
+            Some(Mutation::Keygen(KeyMutation::NewKey { .. }))
+        ));
+        assert!(matches!(
+            entries[2].mutation,
+            Some(Mutation::Keygen(KeyMutation::DeleteKey(_)))
+        ));
+
+        // a coordinator that never had it gets nothing to hold for it
+        let mut b = Coordinator::new();
+        let (_, b_report) = sync_pair(&mut a, &mut b);
+        assert_eq!(b_report.held, 1);
+        assert_eq!(b_report.dropped, 1);
+        b.accept_held();
+        assert_eq!(b.key_names(), vec!["kept"]);
+        assert_eq!(have(&b.conn).unwrap(), have(&a.conn).unwrap());
+    }
+
+    #[test]
+    fn a_failed_write_leaves_the_coordinator_as_it_was() {
+        let mut a = Coordinator::new();
+        let mut b = Coordinator::new();
+        a.new_key("from a");
+        sync_pair(&mut a, &mut b);
+
+        let waiting = held(&b.conn).unwrap();
+        b.conn
+            .execute_batch("DROP TABLE fs_coordinator_mutations")
+            .unwrap();
+        assert!(resolve_held(
+            &mut b.conn,
+            b.coordinator.MUTATE_NO_PERSIST(),
+            waiting[0].origin,
+            waiting[0].seq,
+            true,
+        )
+        .is_err());
+        assert!(b.key_names().is_empty());
+        assert_eq!(held(&b.conn).unwrap().len(), 1, "and it's still waiting");
+    }
+}
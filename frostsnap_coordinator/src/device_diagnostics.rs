@@ -0,0 +1,264 @@
+//! Asking a device to check itself over, and keeping what it said so a device's health can be
+//! followed across runs.
+use crate::persist::{BincodeWrapper, Persist};
+use crate::{Completion, Sink, UiProtocol};
+use anyhow::Result;
+use bdk_chain::rusqlite_impl::migrate_schema;
+use frostsnap_comms::diagnostics::DiagnosticReport;
+use frostsnap_comms::{CommsMisc, CoordinatorSendBody, CoordinatorSendMessage};
+use frostsnap_core::DeviceId;
+use rusqlite::params;
+use std::borrow::BorrowMut;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DeviceDiagnosticsState {
+    /// The device is reading through its flash. This takes a few seconds.
+    Running,
+    Finished(Box<DiagnosticReport>),
+}
+
+pub struct RunDeviceDiagnostics {
+    target_device: DeviceId,
+    sent_request: bool,
+    finished: bool,
+    aborted: bool,
+    sink: Box<dyn Sink<DeviceDiagnosticsState>>,
+}
+
+impl RunDeviceDiagnostics {
+    pub fn new(target_device: DeviceId, sink: impl Sink<DeviceDiagnosticsState> + 'static) -> Self {
+        Self {
+            target_device,
+            sent_request: false,
+            finished: false,
+            aborted: false,
+            sink: Box::new(sink),
+        }
+    }
+}
+
+impl UiProtocol for RunDeviceDiagnostics {
+    fn cancel(&mut self) {
+        self.aborted = true;
+    }
+
+    fn is_complete(&self) -> Option<Completion> {
+        if self.aborted {
+            Some(Completion::Abort {
+                send_cancel_to_all_devices: false,
+            })
+        } else if self.finished {
+            Some(Completion::Success)
+        } else {
+            None
+        }
+    }
+
+    fn disconnected(&mut self, id: DeviceId) {
+        if id == self.target_device {
+            self.aborted = true;
+        }
+    }
+
+    fn poll(&mut self) -> Vec<CoordinatorSendMessage> {
+        if !self.sent_request {
+            self.sent_request = true;
+            self.sink.send(DeviceDiagnosticsState::Running);
+            vec![CoordinatorSendMessage::to(
+                self.target_device,
+                CoordinatorSendBody::RunDiagnostics,
+            )]
+        } else {
+            vec![]
+        }
+    }
+
+    fn process_comms_message(&mut self, from: DeviceId, message: CommsMisc) -> bool {
+        match message {
+            CommsMisc::DiagnosticReport(report) if from == self.target_device => {
+                self.finished = true;
+                self.sink.send(DeviceDiagnosticsState::Finished(report));
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_mut_any(&mut self) -> &mut dyn std::any::Any {
+        self.borrow_mut()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct StoredDiagnosticReport {
+    /// Unix time in seconds.
+    pub received_at: u64,
+    pub report: DiagnosticReport,
+}
+
+/// Every diagnostic report each device has sent, oldest first.
+#[derive(Default, Debug)]
+pub struct DiagnosticsLog {
+    reports: BTreeMap<DeviceId, Vec<StoredDiagnosticReport>>,
+}
+
+#[derive(Clone, Debug)]
+pub enum DiagnosticsLogMutation {
+    Record {
+        device_id: DeviceId,
+        report: StoredDiagnosticReport,
+    },
+}
+
+impl DiagnosticsLog {
+    pub fn record(
+        &mut self,
+        device_id: DeviceId,
+        received_at: u64,
+        report: DiagnosticReport,
+    ) -> DiagnosticsLogMutation {
+        let report = StoredDiagnosticReport {
+            received_at,
+            report,
+        };
+        self.reports
+            .entry(device_id)
+            .or_default()
+            .push(report.clone());
+        DiagnosticsLogMutation::Record { device_id, report }
+    }
+
+    pub fn reports(&self, device_id: DeviceId) -> &[StoredDiagnosticReport] {
+        self.reports
+            .get(&device_id)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    pub fn latest(&self, device_id: DeviceId) -> Option<&StoredDiagnosticReport> {
+        self.reports(device_id).last()
+    }
+}
+
+impl Persist<rusqlite::Connection> for DiagnosticsLog {
+    type Update = Vec<DiagnosticsLogMutation>;
+    type LoadParams = ();
+
+    fn migrate(conn: &mut rusqlite::Connection) -> Result<()> {
+        const SCHEMA_NAME: &str = "frostsnap_device_diagnostics";
+        const MIGRATIONS: &[&str] = &[
+            // Version 0
+            "CREATE TABLE IF NOT EXISTS fs_device_diagnostics ( \
+                id INTEGER PRIMARY KEY AUTOINCREMENT, \
+                device_id TEXT NOT NULL, \
+                received_at INTEGER NOT NULL, \
+                report BLOB NOT NULL \
+            ) STRICT",
+        ];
+
+        let db_tx = conn.transaction()?;
+        migrate_schema(&db_tx, SCHEMA_NAME, MIGRATIONS)?;
+        db_tx.commit()?;
+        Ok(())
+    }
+
+    fn load(conn: &mut rusqlite::Connection, _: Self::LoadParams) -> Result<Self> {
+        let mut log = DiagnosticsLog::default();
+        let mut stmt = conn.prepare(
+            "SELECT device_id, received_at, report FROM fs_device_diagnostics ORDER BY id",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, BincodeWrapper<DiagnosticReport>>(2)?,
+            ))
+        })?;
+        for row in rows {
+            let (device_id, received_at, BincodeWrapper(report)) = row?;
+            log.record(DeviceId::from_str(&device_id)?, received_at as u64, report);
+        }
+        drop(stmt);
+        Ok(log)
+    }
+
+    fn persist_update(&self, conn: &mut rusqlite::Connection, update: Self::Update) -> Result<()> {
+        let db_tx = conn.transaction()?;
+        for mutation in update {
+            match mutation {
+                DiagnosticsLogMutation::Record { device_id, report } => {
+                    db_tx.execute(
+                        "INSERT INTO fs_device_diagnostics (device_id, received_at, report) \
+                         VALUES (?1, ?2, ?3)",
+                        params![
+                            device_id.to_string(),
+                            report.received_at as i64,
+                            BincodeWrapper(&report.report),
+                        ],
+                    )?;
+                }
+            }
+        }
+        db_tx.commit()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::persist::Persisted;
+    use frostsnap_comms::{
+        diagnostics::{EfuseKeyStatus, NonceSlotUsage, StorageFill},
+        Sha256Digest,
+    };
+
+    fn report(used_bytes: u32) -> DiagnosticReport {
+        DiagnosticReport {
+            firmware_digest: Sha256Digest([1; 32]),
+            partitions: vec![],
+            mutation_log: StorageFill {
+                used_bytes,
+                capacity_bytes: 4096,
+            },
+            nonce_slots: NonceSlotUsage {
+                total: 8,
+                in_use: 1,
+            },
+            efuse_keys: EfuseKeyStatus {
+                share_encryption: Some(2),
+                fixed_entropy: Some(3),
+                ds: Some(4),
+            },
+            touch_controller: Some(0xb5),
+            secure_boot_enabled: true,
+        }
+    }
+
+    #[test]
+    fn reports_survive_reload_in_order() {
+        let mut conn = rusqlite::Connection::open_in_memory().unwrap();
+        let device_id = DeviceId([2; 33]);
+        let mut log = Persisted::<DiagnosticsLog>::new(&mut conn, ()).unwrap();
+        for (received_at, used_bytes) in [(10, 100), (20, 200)] {
+            log.mutate(&mut conn, |log| {
+                Ok((
+                    (),
+                    vec![log.record(device_id, received_at, report(used_bytes))],
+                ))
+            })
+            .unwrap();
+        }
+
+        let reloaded = Persisted::<DiagnosticsLog>::new(&mut conn, ()).unwrap();
+        assert_eq!(reloaded.reports(device_id), log.reports(device_id));
+        assert_eq!(reloaded.latest(device_id).unwrap().received_at, 20);
+        assert!(reloaded.reports(DeviceId([3; 33])).is_empty());
+    }
+}
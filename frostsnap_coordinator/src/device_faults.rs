@@ -0,0 +1,61 @@
+//! Keeps the UI up to date with the coordinator's record of misbehaving devices.
+use crate::Sink;
+use frostsnap_core::{
+    coordinator::{faults::DeviceFault, FrostCoordinator},
+    DeviceId,
+};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DeviceFaultsState {
+    pub devices: Vec<DeviceFaultRecord>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DeviceFaultRecord {
+    pub device_id: DeviceId,
+    pub faults: Vec<DeviceFault>,
+    pub quarantined: bool,
+}
+
+impl DeviceFaultsState {
+    pub fn from_coordinator(coordinator: &FrostCoordinator) -> Self {
+        Self {
+            devices: coordinator
+                .device_faults()
+                .iter()
+                .map(|(device_id, device_faults)| DeviceFaultRecord {
+                    device_id: *device_id,
+                    faults: device_faults.faults.clone(),
+                    quarantined: device_faults.quarantined,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Faults are recorded while the coordinator processes device messages that it then rejects, so
+/// nothing is sent to the user when they happen. Call [`Self::update`] after processing and the
+/// sink hears about it if the record changed.
+pub struct DeviceFaultWatcher {
+    sink: Box<dyn Sink<DeviceFaultsState>>,
+    last_sent: DeviceFaultsState,
+}
+
+impl DeviceFaultWatcher {
+    pub fn new(coordinator: &FrostCoordinator, sink: impl Sink<DeviceFaultsState>) -> Self {
+        let state = DeviceFaultsState::from_coordinator(coordinator);
+        sink.send(state.clone());
+        Self {
+            sink: Box::new(sink),
+            last_sent: state,
+        }
+    }
+
+    pub fn update(&mut self, coordinator: &FrostCoordinator) {
+        let state = DeviceFaultsState::from_coordinator(coordinator);
+        if state != self.last_sent {
+            self.sink.send(state.clone());
+            self.last_sent = state;
+        }
+    }
+}
@@ -1,4 +1,12 @@
-use frostsnap_comms::{Sha256Digest, FIRMWARE_UPGRADE_CHUNK_LEN};
+use frostsnap_comms::firmware_delta::PatchOp;
+use frostsnap_comms::{firmware_compression, Sha256Digest, FIRMWARE_UPGRADE_CHUNK_LEN};
+use frostsnap_core::schnorr_fun::{
+    fun::{marker::EvenY, KeyPair, Point},
+    nonce::NonceGen,
+    Message, Schnorr, Signature,
+};
+use frostsnap_core::sha2::Sha256;
+use std::collections::{BTreeSet, HashMap};
 
 pub use frostsnap_comms::firmware_version::{FirmwareFeatures, VersionNumber, EARLIEST_ACCEPTABLE};
 
@@ -67,6 +75,215 @@ impl FirmwareVersion {
             }
         }
     }
+
+    /// [`Self::check_upgrade_eligibility`] also held to what the release manifest says about this
+    /// firmware (`release`) and the device's (`device_release`).
+    pub fn check_release_upgrade_eligibility(
+        &self,
+        device_digest: &Sha256Digest,
+        release: FirmwareReleaseStatus,
+        device_release: FirmwareReleaseStatus,
+    ) -> FirmwareUpgradeEligibility {
+        let eligibility = self.check_upgrade_eligibility(device_digest);
+        if eligibility != FirmwareUpgradeEligibility::CanUpgrade {
+            return eligibility;
+        }
+
+        match (release, device_release) {
+            (FirmwareReleaseStatus::Unknown, FirmwareReleaseStatus::Official(_)) => {
+                FirmwareUpgradeEligibility::CannotUpgrade {
+                    reason: "The app's firmware is not an official release. \
+                             Cannot upgrade a device running one."
+                        .to_string(),
+                }
+            }
+            (FirmwareReleaseStatus::Official(release), FirmwareReleaseStatus::Official(device))
+                if device.version < release.min_upgrade_from =>
+            {
+                FirmwareUpgradeEligibility::CannotUpgrade {
+                    reason: format!(
+                        "Device firmware v{} is too old to upgrade to v{} directly. \
+                         Upgrade it to v{} first.",
+                        device.version, release.version, release.min_upgrade_from
+                    ),
+                }
+            }
+            _ => eligibility,
+        }
+    }
+}
+
+/// One official firmware release, as listed in a [`ReleaseManifest`].
+#[derive(bincode::Encode, bincode::Decode, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FirmwareRelease {
+    pub version: VersionNumber,
+    /// Body digest of the release's firmware.
+    pub digest: Sha256Digest,
+    /// The oldest release that can upgrade straight to this one.
+    pub min_upgrade_from: VersionNumber,
+    /// SHA256 of the release notes published with it.
+    pub release_notes_hash: Sha256Digest,
+}
+
+/// Every official firmware release, signed with the project's release key. Firmware that isn't
+/// listed might be a perfectly good development build but it's not something that was released.
+///
+/// The file is the magic bytes, a signature over the rest, then the bincode-standard manifest.
+#[derive(bincode::Encode, bincode::Decode, Debug, Clone, Default, PartialEq)]
+pub struct ReleaseManifest {
+    pub releases: Vec<FirmwareRelease>,
+}
+
+const RELEASE_MANIFEST_MAGIC: [u8; 8] = *b"fsmanif0";
+
+impl ReleaseManifest {
+    pub fn release(&self, digest: &Sha256Digest) -> Option<&FirmwareRelease> {
+        self.releases
+            .iter()
+            .find(|release| release.digest == *digest)
+    }
+
+    /// Make the file for the manifest signed with `release_keypair`.
+    pub fn sign<NG: NonceGen>(
+        &self,
+        schnorr: Schnorr<Sha256, NG>,
+        release_keypair: &KeyPair<EvenY>,
+    ) -> Vec<u8> {
+        let manifest_bytes =
+            bincode::encode_to_vec(self, bincode::config::standard()).expect("manifest encodes");
+        let signature = schnorr.sign(
+            release_keypair,
+            Message::new("frostsnap-release-manifest", &manifest_bytes),
+        );
+        let mut file = RELEASE_MANIFEST_MAGIC.to_vec();
+        file.extend(signature.to_bytes());
+        file.extend(manifest_bytes);
+        file
+    }
+
+    /// Read a manifest file, checking it was signed by `release_key`.
+    pub fn verify(bytes: &[u8], release_key: Point<EvenY>) -> Result<Self, ReleaseManifestError> {
+        let rest = bytes
+            .strip_prefix(&RELEASE_MANIFEST_MAGIC[..])
+            .ok_or(ReleaseManifestError::NotAManifest)?;
+        let (signature, manifest_bytes) = rest
+            .split_first_chunk::<64>()
+            .ok_or(ReleaseManifestError::NotAManifest)?;
+        let signature =
+            Signature::from_bytes(*signature).ok_or(ReleaseManifestError::InvalidSignature)?;
+        let message = Message::new("frostsnap-release-manifest", manifest_bytes);
+        if !Schnorr::<Sha256>::verify_only().verify(&release_key, message, &signature) {
+            return Err(ReleaseManifestError::InvalidSignature);
+        }
+        let (manifest, _) = bincode::decode_from_slice(manifest_bytes, bincode::config::standard())
+            .map_err(|_| ReleaseManifestError::InvalidManifest)?;
+        Ok(manifest)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReleaseManifestError {
+    NotAManifest,
+    InvalidSignature,
+    InvalidManifest,
+}
+
+impl std::fmt::Display for ReleaseManifestError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReleaseManifestError::NotAManifest => write!(f, "Not a firmware release manifest"),
+            ReleaseManifestError::InvalidSignature => {
+                write!(
+                    f,
+                    "Firmware release manifest is not signed by the release key"
+                )
+            }
+            ReleaseManifestError::InvalidManifest => {
+                write!(f, "Firmware release manifest is invalid")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ReleaseManifestError {}
+
+/// What the release manifest says about some firmware.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FirmwareReleaseStatus {
+    /// There's no release manifest to check against.
+    Unchecked,
+    Official(FirmwareRelease),
+    /// Not in the release manifest: a development build, or something worse.
+    Unknown,
+}
+
+impl FirmwareReleaseStatus {
+    pub fn of(digest: &Sha256Digest, manifest: Option<&ReleaseManifest>) -> Self {
+        match manifest {
+            Some(manifest) => match manifest.release(digest) {
+                Some(release) => FirmwareReleaseStatus::Official(*release),
+                None => FirmwareReleaseStatus::Unknown,
+            },
+            None => FirmwareReleaseStatus::Unchecked,
+        }
+    }
+
+    pub fn is_unknown(&self) -> bool {
+        matches!(self, FirmwareReleaseStatus::Unknown)
+    }
+}
+
+/// How an upgrade is sent down a chain of devices.
+#[derive(Debug, Clone, Copy)]
+pub enum UpgradeStream {
+    /// The image as it is.
+    Raw,
+    /// The image compressed.
+    Compressed,
+    /// A patch against the firmware all the devices being upgraded are running.
+    Delta(FirmwarePatch),
+}
+
+impl UpgradeStream {
+    /// The best way to upgrade `devices` to `target`. Every device on a chain forwards the whole
+    /// stream whether it's upgrading or not, so they all have to understand it, and one patch has
+    /// to suit every device being upgraded. Falls back to the whole image otherwise, including
+    /// when there's no patch from the firmware they're running.
+    pub fn choose(
+        target: &ValidatedFirmwareBin,
+        patches: &[FirmwarePatch],
+        devices: &[FirmwareVersion],
+    ) -> Self {
+        if !devices
+            .iter()
+            .all(|firmware| firmware.features().compressed_upgrade)
+        {
+            return UpgradeStream::Raw;
+        }
+
+        let bases = devices
+            .iter()
+            .map(|firmware| firmware.digest)
+            .filter(|digest| *digest != target.digest())
+            .collect::<BTreeSet<_>>();
+        let patch = match bases.into_iter().collect::<Vec<_>>()[..] {
+            [base]
+                if devices
+                    .iter()
+                    .all(|firmware| firmware.features().delta_upgrade) =>
+            {
+                patches.iter().find(|patch| {
+                    patch.base_digest() == base && patch.target_digest() == target.digest()
+                })
+            }
+            _ => None,
+        };
+
+        match patch {
+            Some(patch) => UpgradeStream::Delta(*patch),
+            None => UpgradeStream::Compressed,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -264,6 +481,14 @@ impl ValidatedFirmwareBin {
         self.firmware.num_chunks()
     }
 
+    /// The image compressed for [`CoordinatorUpgradeMessage::PrepareUpgradeCompressed`]. This
+    /// compresses the whole image on every call.
+    ///
+    /// [`CoordinatorUpgradeMessage::PrepareUpgradeCompressed`]: frostsnap_comms::CoordinatorUpgradeMessage::PrepareUpgradeCompressed
+    pub fn compressed(&self) -> Vec<u8> {
+        firmware_compression::compress(self.as_bytes())
+    }
+
     pub fn is_signed(&self) -> bool {
         self.firmware_size < self.total_size
     }
@@ -272,3 +497,300 @@ impl ValidatedFirmwareBin {
         self.firmware_version.version
     }
 }
+
+/// A patch that upgrades one particular firmware to another, as the app bundles it. It's the magic
+/// bytes, a header saying which firmware it's between, then the patch compressed ready to send
+/// with [`CoordinatorUpgradeMessage::PrepareUpgradeDelta`].
+///
+/// Nothing here is trusted by the device. A patch that doesn't build the firmware it claims to
+/// just fails the device's digest check.
+///
+/// [`CoordinatorUpgradeMessage::PrepareUpgradeDelta`]: frostsnap_comms::CoordinatorUpgradeMessage::PrepareUpgradeDelta
+#[derive(Clone, Copy)]
+pub struct FirmwarePatch {
+    header: FirmwarePatchHeader,
+    compressed: &'static [u8],
+}
+
+#[derive(bincode::Encode, bincode::Decode, Debug, Clone, Copy, PartialEq)]
+struct FirmwarePatchHeader {
+    /// Body digest of the firmware the patch applies to.
+    base_digest: Sha256Digest,
+    /// Body digest of the firmware it makes.
+    target_digest: Sha256Digest,
+    /// Length of the image it makes.
+    size: u32,
+    /// Length of the patch decompressed.
+    patch_size: u32,
+}
+
+const FIRMWARE_PATCH_MAGIC: [u8; 8] = *b"fspatch0";
+
+/// Matches shorter than this aren't worth a copy op.
+const MIN_MATCH_LEN: usize = 16;
+
+impl std::fmt::Debug for FirmwarePatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FirmwarePatch")
+            .field("header", &self.header)
+            .field("compressed_size", &self.compressed.len())
+            .finish()
+    }
+}
+
+impl FirmwarePatch {
+    /// Make the file for a patch from `base` to `target`.
+    pub fn create(base: &ValidatedFirmwareBin, target: &ValidatedFirmwareBin) -> Vec<u8> {
+        let patch = diff(base.as_bytes(), target.as_bytes());
+        let header = FirmwarePatchHeader {
+            base_digest: base.digest(),
+            target_digest: target.digest(),
+            size: target.size(),
+            patch_size: patch.len() as u32,
+        };
+        let mut file = FIRMWARE_PATCH_MAGIC.to_vec();
+        file.extend(
+            bincode::encode_to_vec(header, bincode::config::standard()).expect("header encodes"),
+        );
+        file.extend(firmware_compression::compress(&patch));
+        file
+    }
+
+    pub fn from_bytes(bytes: &'static [u8]) -> Result<Self, FirmwarePatchError> {
+        let rest = bytes
+            .strip_prefix(&FIRMWARE_PATCH_MAGIC[..])
+            .ok_or(FirmwarePatchError::NotAPatch)?;
+        let (header, header_len) = bincode::decode_from_slice(rest, bincode::config::standard())
+            .map_err(|_| FirmwarePatchError::InvalidHeader)?;
+        Ok(Self {
+            header,
+            compressed: &rest[header_len..],
+        })
+    }
+
+    pub fn base_digest(&self) -> Sha256Digest {
+        self.header.base_digest
+    }
+
+    pub fn target_digest(&self) -> Sha256Digest {
+        self.header.target_digest
+    }
+
+    pub fn size(&self) -> u32 {
+        self.header.size
+    }
+
+    pub fn patch_size(&self) -> u32 {
+        self.header.patch_size
+    }
+
+    /// The patch as it's sent to devices.
+    pub fn compressed(&self) -> &'static [u8] {
+        self.compressed
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FirmwarePatchError {
+    NotAPatch,
+    InvalidHeader,
+}
+
+impl std::fmt::Display for FirmwarePatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FirmwarePatchError::NotAPatch => write!(f, "Not a firmware patch"),
+            FirmwarePatchError::InvalidHeader => write!(f, "Firmware patch header is invalid"),
+        }
+    }
+}
+
+impl std::error::Error for FirmwarePatchError {}
+
+/// Work out a patch that builds `target` from `base`. Every [`MIN_MATCH_LEN`] aligned block of
+/// `base` is indexed and `target` is scanned for them a byte at a time, growing each hit as far
+/// as it goes both ways. That finds everything a firmware release keeps from the last one, even
+/// when it has moved.
+fn diff(base: &[u8], target: &[u8]) -> Vec<u8> {
+    let mut index = HashMap::new();
+    for (i, block) in base.chunks_exact(MIN_MATCH_LEN).enumerate() {
+        index.entry(block).or_insert(i * MIN_MATCH_LEN);
+    }
+
+    let mut patch = vec![];
+    let mut insert_from = 0;
+    let mut t = 0;
+    while t + MIN_MATCH_LEN <= target.len() {
+        let Some(&b) = index.get(&target[t..t + MIN_MATCH_LEN]) else {
+            t += 1;
+            continue;
+        };
+        let forward = target[t..]
+            .iter()
+            .zip(&base[b..])
+            .take_while(|(x, y)| x == y)
+            .count();
+        let back = target[insert_from..t]
+            .iter()
+            .rev()
+            .zip(base[..b].iter().rev())
+            .take_while(|(x, y)| x == y)
+            .count();
+
+        if insert_from < t - back {
+            PatchOp::Insert(&target[insert_from..t - back]).encode(&mut patch);
+        }
+        PatchOp::Copy {
+            offset: (b - back) as u32,
+            len: (back + forward) as u32,
+        }
+        .encode(&mut patch);
+        t += forward;
+        insert_from = t;
+    }
+    if insert_from < target.len() {
+        PatchOp::Insert(&target[insert_from..]).encode(&mut patch);
+    }
+    patch
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use core::convert::Infallible;
+    use frostsnap_comms::firmware_delta::PatchApplier;
+    use frostsnap_comms::firmware_reader::SECTOR_SIZE;
+    use frostsnap_core::schnorr_fun::{self, fun::Scalar};
+
+    fn apply(base: &[u8], patch: &[u8], size: u32) -> Vec<u8> {
+        let mut out = vec![];
+        let mut applier = PatchApplier::new(size);
+        let mut read_base = |offset: u32, buf: &mut [u8]| -> Result<(), Infallible> {
+            buf.copy_from_slice(&base[offset as usize..offset as usize + buf.len()]);
+            Ok(())
+        };
+        let mut write_sector = |_, sector: &[u8; SECTOR_SIZE]| -> Result<(), Infallible> {
+            out.extend_from_slice(sector);
+            Ok(())
+        };
+        applier
+            .push(patch, &mut read_base, &mut write_sector)
+            .unwrap();
+        applier.finish(&mut write_sector).unwrap();
+        out.truncate(size as usize);
+        out
+    }
+
+    fn release(version: VersionNumber, min_upgrade_from: VersionNumber) -> FirmwareRelease {
+        FirmwareRelease {
+            version,
+            digest: Sha256Digest([version.minor; 32]),
+            min_upgrade_from,
+            release_notes_hash: Sha256Digest([0; 32]),
+        }
+    }
+
+    #[test]
+    fn release_manifest_only_verifies_under_the_release_key() {
+        let schnorr = schnorr_fun::new_with_deterministic_nonces::<Sha256>();
+        let keypair = KeyPair::<EvenY>::new_xonly(Scalar::random(&mut rand::thread_rng()));
+        let other_key = KeyPair::<EvenY>::new_xonly(Scalar::random(&mut rand::thread_rng()));
+        let manifest = ReleaseManifest {
+            releases: vec![release(
+                VersionNumber::new(0, 4, 0),
+                VersionNumber::new(0, 3, 0),
+            )],
+        };
+        let mut file = manifest.sign(schnorr, &keypair);
+
+        assert_eq!(
+            ReleaseManifest::verify(&file, keypair.public_key()),
+            Ok(manifest)
+        );
+        assert_eq!(
+            ReleaseManifest::verify(&file, other_key.public_key()),
+            Err(ReleaseManifestError::InvalidSignature)
+        );
+        *file.last_mut().unwrap() ^= 1;
+        assert_eq!(
+            ReleaseManifest::verify(&file, keypair.public_key()),
+            Err(ReleaseManifestError::InvalidSignature)
+        );
+        assert_eq!(
+            ReleaseManifest::verify(b"fspatch0", keypair.public_key()),
+            Err(ReleaseManifestError::NotAManifest)
+        );
+    }
+
+    #[test]
+    fn release_manifest_holds_back_upgrades() {
+        let old = release(VersionNumber::new(0, 2, 0), VersionNumber::new(0, 1, 0));
+        let recent = release(VersionNumber::new(0, 3, 0), VersionNumber::new(0, 1, 0));
+        let target = release(VersionNumber::new(0, 4, 0), VersionNumber::new(0, 3, 0));
+        let manifest = ReleaseManifest {
+            releases: vec![old, recent, target],
+        };
+        // unknown to the hardcoded version table, so it only goes on the manifest
+        let firmware = FirmwareVersion::new(target.digest);
+        let status = |digest| FirmwareReleaseStatus::of(&digest, Some(&manifest));
+
+        assert_eq!(
+            firmware.check_release_upgrade_eligibility(
+                &recent.digest,
+                status(target.digest),
+                status(recent.digest)
+            ),
+            FirmwareUpgradeEligibility::CanUpgrade
+        );
+        assert!(matches!(
+            firmware.check_release_upgrade_eligibility(
+                &old.digest,
+                status(target.digest),
+                status(old.digest)
+            ),
+            FirmwareUpgradeEligibility::CannotUpgrade { .. }
+        ));
+        let dev_build = Sha256Digest([0xde; 32]);
+        assert!(status(dev_build).is_unknown());
+        assert!(matches!(
+            FirmwareVersion::new(dev_build).check_release_upgrade_eligibility(
+                &recent.digest,
+                status(dev_build),
+                status(recent.digest)
+            ),
+            FirmwareUpgradeEligibility::CannotUpgrade { .. }
+        ));
+        assert_eq!(
+            firmware.check_release_upgrade_eligibility(
+                &old.digest,
+                FirmwareReleaseStatus::Unchecked,
+                FirmwareReleaseStatus::Unchecked
+            ),
+            FirmwareUpgradeEligibility::CanUpgrade
+        );
+    }
+
+    #[test]
+    fn diff_builds_target_and_is_small_for_small_changes() {
+        let mut state = 7u32;
+        let base: Vec<u8> = (0..300_000)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                (state >> 16) as u8
+            })
+            .collect();
+        // move things around, change a few bytes and add some new ones
+        let mut target = base[1_000..150_000].to_vec();
+        target.extend_from_slice(&[0xab; 500]);
+        target.extend_from_slice(&base[..1_000]);
+        target.extend_from_slice(&base[150_000..]);
+        target[20_000] ^= 1;
+        target[250_000] ^= 1;
+
+        let patch = diff(&base, &target);
+        assert_eq!(apply(&base, &patch, target.len() as u32), target);
+        assert!(patch.len() < 1_000, "patch was {} bytes", patch.len());
+        assert_eq!(apply(&base, &diff(&base, &[]), 0), Vec::<u8>::new());
+        assert_eq!(apply(&[], &diff(&[], &target), target.len() as u32), target);
+    }
+}
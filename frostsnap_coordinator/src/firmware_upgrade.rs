@@ -1,5 +1,7 @@
 use crate::{
-    Completion, FirmwareUpgradeEligibility, FirmwareVersion, Sink, UiProtocol, ValidatedFirmwareBin,
+    firmware::{FirmwarePatch, FirmwareReleaseStatus, ReleaseManifest, UpgradeStream},
+    Completion, FirmwareUpgradeEligibility, FirmwareVersion, Sink, UiProtocol,
+    ValidatedFirmwareBin,
 };
 
 use frostsnap_comms::{
@@ -7,11 +9,15 @@ use frostsnap_comms::{
 };
 use frostsnap_core::DeviceId;
 use std::collections::{BTreeSet, HashMap};
+use std::sync::{Arc, OnceLock};
 
 pub struct FirmwareUpgradeProtocol {
     state: FirmwareUpgradeConfirmState,
     sent_first_message: bool,
     firmware_bin: ValidatedFirmwareBin,
+    /// `firmware_bin` compressed, shared with the `UsbSerialManager` that streams it
+    compressed_firmware: Arc<OnceLock<Vec<u8>>>,
+    firmware_patches: Vec<FirmwarePatch>,
     devices: HashMap<DeviceId, FirmwareVersion>,
     /// Devices that are already on the target firmware auto-ack without a
     /// user prompt. We still need to wait for these acks before starting the
@@ -26,11 +32,19 @@ impl FirmwareUpgradeProtocol {
         devices: HashMap<DeviceId, FirmwareVersion>,
         need_upgrade: BTreeSet<DeviceId>,
         firmware_bin: ValidatedFirmwareBin,
+        compressed_firmware: Arc<OnceLock<Vec<u8>>>,
+        firmware_patches: Vec<FirmwarePatch>,
+        release_manifest: Option<&ReleaseManifest>,
         sink: impl Sink<FirmwareUpgradeConfirmState> + 'static,
     ) -> Self {
+        let release = FirmwareReleaseStatus::of(&firmware_bin.digest(), release_manifest);
         // Check if any device has incompatible firmware
         let abort_reason = devices.values().find_map(|fw| {
-            match firmware_bin.firmware_version().check_upgrade_eligibility(&fw.digest) {
+            match firmware_bin.firmware_version().check_release_upgrade_eligibility(
+                &fw.digest,
+                release,
+                FirmwareReleaseStatus::of(&fw.digest, release_manifest),
+            ) {
                 FirmwareUpgradeEligibility::CannotUpgrade { reason } => {
                     Some(format!("One of the devices is incompatible with the upgrade. Unplug it to continue or try upgrading the app. Problem: {reason}"))
                 }
@@ -48,6 +62,8 @@ impl FirmwareUpgradeProtocol {
             },
             sent_first_message: false,
             firmware_bin,
+            compressed_firmware,
+            firmware_patches,
             devices,
             passive_acks: BTreeSet::new(),
             sink: Box::new(sink),
@@ -133,9 +149,29 @@ impl UiProtocol for FirmwareUpgradeProtocol {
                     firmware_digest: self.firmware_bin.digest_with_signature(),
                 }
             } else {
-                CoordinatorUpgradeMessage::PrepareUpgrade2 {
-                    size: self.firmware_bin.size(),
-                    firmware_digest: self.firmware_bin.digest(),
+                let devices = self.devices.values().copied().collect::<Vec<_>>();
+                match UpgradeStream::choose(&self.firmware_bin, &self.firmware_patches, &devices) {
+                    UpgradeStream::Raw => CoordinatorUpgradeMessage::PrepareUpgrade2 {
+                        size: self.firmware_bin.size(),
+                        firmware_digest: self.firmware_bin.digest(),
+                    },
+                    UpgradeStream::Compressed => {
+                        let compressed = self
+                            .compressed_firmware
+                            .get_or_init(|| self.firmware_bin.compressed());
+                        CoordinatorUpgradeMessage::PrepareUpgradeCompressed {
+                            size: self.firmware_bin.size(),
+                            compressed_size: compressed.len() as u32,
+                            firmware_digest: self.firmware_bin.digest(),
+                        }
+                    }
+                    UpgradeStream::Delta(patch) => CoordinatorUpgradeMessage::PrepareUpgradeDelta {
+                        size: patch.size(),
+                        base_digest: patch.base_digest(),
+                        patch_size: patch.patch_size(),
+                        compressed_size: patch.compressed().len() as u32,
+                        firmware_digest: self.firmware_bin.digest(),
+                    },
                 }
             };
 
@@ -1,4 +1,6 @@
 use crate::{
+    audit_log::{self, AuditEvent},
+    coordinator_sync,
     frostsnap_core::{
         self,
         coordinator::{ActiveSignSession, FrostCoordinator},
@@ -35,6 +37,8 @@ impl Persist<rusqlite::Connection> for FrostCoordinator {
         let db_tx = conn.transaction()?;
         migrate_schema(&db_tx, SCHEMA_NAME, MIGRATIONS)?;
         db_tx.commit()?;
+        audit_log::migrate(conn)?;
+        coordinator_sync::migrate(conn)?;
         Ok(())
     }
 
@@ -74,35 +78,48 @@ impl Persist<rusqlite::Connection> for FrostCoordinator {
         update: Self::Update,
     ) -> anyhow::Result<()> {
         for mutation in update {
-            match mutation {
-                coordinator::Mutation::Keygen(coordinator::keys::KeyMutation::DeleteKey(
-                    key_id,
-                )) => {
-                    conn.execute(
-                        "DELETE FROM fs_coordinator_mutations WHERE tied_to_key=?1",
-                        params![key_id],
-                    )?;
-                }
-                coordinator::Mutation::Restoration(RestorationMutation::DeleteRestoration {
-                    restoration_id,
-                }) => {
-                    conn.execute(
-                        "DELETE FROM fs_coordinator_mutations WHERE tied_to_restoration=?1",
-                        params![restoration_id],
-                    )?;
-                }
-                mutation => {
-                    conn.execute(
-                        "INSERT INTO fs_coordinator_mutations (tied_to_key, tied_to_restoration, mutation, version) VALUES (?1, ?2, ?3, 0)",
-                        params![mutation.tied_to_key(self), mutation.tied_to_restoration(), BincodeWrapper(mutation)],
-                    )?;
-                }
-            }
+            coordinator_sync::record_local(conn, self, &mutation)?;
+            store_mutation(self, conn, mutation)?;
         }
         Ok(())
     }
 }
 
+/// Write a mutation that has already been applied to `coordinator`, wherever it came from.
+pub(crate) fn store_mutation(
+    coordinator: &FrostCoordinator,
+    conn: &rusqlite::Connection,
+    mutation: coordinator::Mutation,
+) -> anyhow::Result<()> {
+    if let Some(event) = AuditEvent::from_mutation(&mutation, coordinator) {
+        audit_log::append(conn, event)?;
+    }
+    match mutation {
+        coordinator::Mutation::Keygen(coordinator::keys::KeyMutation::DeleteKey(key_id)) => {
+            conn.execute(
+                "DELETE FROM fs_coordinator_mutations WHERE tied_to_key=?1",
+                params![key_id],
+            )?;
+            coordinator_sync::redact_key(conn, key_id)?;
+        }
+        coordinator::Mutation::Restoration(RestorationMutation::DeleteRestoration {
+            restoration_id,
+        }) => {
+            conn.execute(
+                "DELETE FROM fs_coordinator_mutations WHERE tied_to_restoration=?1",
+                params![restoration_id],
+            )?;
+        }
+        mutation => {
+            conn.execute(
+                "INSERT INTO fs_coordinator_mutations (tied_to_key, tied_to_restoration, mutation, version) VALUES (?1, ?2, ?3, 0)",
+                params![mutation.tied_to_key(coordinator), mutation.tied_to_restoration(), BincodeWrapper(mutation)],
+            )?;
+        }
+    }
+    Ok(())
+}
+
 impl TakeStaged<VecDeque<coordinator::Mutation>> for FrostCoordinator {
     fn take_staged_update(&mut self) -> Option<VecDeque<coordinator::Mutation>> {
         let mutations = self.take_staged_mutations();
@@ -1,5 +1,10 @@
+pub mod address_book;
+pub mod audit_log;
 pub mod backup_run;
 pub mod check_backup;
+pub mod coordinator_sync;
+pub mod device_diagnostics;
+pub mod device_faults;
 pub mod display_backup;
 pub mod enter_physical_backup;
 pub mod erase_device;
@@ -7,10 +12,13 @@ pub mod firmware;
 pub mod firmware_upgrade;
 pub mod keygen;
 pub mod nonce_replenish;
+pub mod remote_signing;
 mod serial_port;
 pub mod signing;
+pub mod silent_payment_ecdh;
 mod ui_protocol;
 mod usb_serial_manager;
+pub mod verify_account;
 pub mod verify_address;
 pub mod wait_for_single_device;
 mod wait_for_to_user_message;
@@ -24,7 +32,8 @@ pub use frostsnap_core;
 pub use serial_port::*;
 pub mod settings;
 pub use firmware::{
-    FirmwareBin, FirmwareUpgradeEligibility, FirmwareValidationError, FirmwareVersion,
+    FirmwareBin, FirmwarePatch, FirmwareRelease, FirmwareReleaseStatus,
+    FirmwareUpgradeEligibility, FirmwareValidationError, FirmwareVersion, ReleaseManifest,
     ValidatedFirmwareBin, VersionNumber,
 };
 pub use ui_protocol::*;
@@ -33,6 +42,7 @@ pub mod bitcoin;
 pub use bdk_chain;
 pub mod frostsnap_persist;
 pub mod persist;
+pub mod portable_backup;
 
 pub trait Sink<M>: Send + 'static {
     fn send(&self, state: M);
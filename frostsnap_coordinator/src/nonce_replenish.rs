@@ -3,13 +3,18 @@ use crate::{Completion, Sink, UiProtocol};
 use frostsnap_comms::{CoordinatorSendBody, CoordinatorSendMessage, Destination};
 use frostsnap_core::{
     coordinator::{CoordinatorToUserMessage, NonceReplenishRequest},
-    message::signing::OpenNonceStreams,
+    message::{
+        signing::{CoordinatorSigning, OpenNonceStreams},
+        CoordinatorToDeviceMessage,
+    },
     DeviceId,
 };
 use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
 
 pub struct NonceReplenishProtocol {
     state: NonceReplenishState,
+    /// Sent ahead of everything else. The device answers them without us having to wait.
+    partition_claims: Vec<CoordinatorSendMessage>,
     pending_messages: HashMap<DeviceId, VecDeque<OpenNonceStreams>>,
     awaiting_response: HashSet<DeviceId>,
     completed_streams: u32,
@@ -25,6 +30,19 @@ impl NonceReplenishProtocol {
         let mut pending_messages = HashMap::new();
         let mut total_streams = 0;
 
+        let partition_claims = nonce_request
+            .partition_claims
+            .iter()
+            .map(|(device_id, partition)| CoordinatorSendMessage {
+                target_destinations: Destination::from([*device_id]),
+                message_body: CoordinatorSendBody::Core(CoordinatorToDeviceMessage::Signing(
+                    CoordinatorSigning::ClaimNoncePartition {
+                        partition: *partition,
+                    },
+                )),
+            })
+            .collect();
+
         // Process NonceReplenishRequest into split OpenNonceStream messages
         for (device_id, open_nonce_stream) in nonce_request.into_open_nonce_streams() {
             // split them so we get more fine grained progress
@@ -40,6 +58,7 @@ impl NonceReplenishProtocol {
                 total_streams,
                 abort: false,
             },
+            partition_claims,
             pending_messages,
             awaiting_response: HashSet::new(),
             completed_streams: 0,
@@ -92,7 +111,7 @@ impl UiProtocol for NonceReplenishProtocol {
     }
 
     fn poll(&mut self) -> Vec<CoordinatorSendMessage> {
-        let mut messages = vec![];
+        let mut messages = core::mem::take(&mut self.partition_claims);
 
         for (device_id, queue) in &mut self.pending_messages {
             if !self.awaiting_response.contains(device_id) {
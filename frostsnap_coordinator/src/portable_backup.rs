@@ -0,0 +1,679 @@
+//! The coordinator's database in one passphrase-encrypted file, to move to a new phone.
+//!
+//! Keys can always be restored from device shares, but that loses what only the coordinator
+//! knew: key and device names, which shares have been backed up, settings, PSBTs, signing
+//! sessions, presigned sweeps, device diagnostics, the sync log and the audit log. This copies
+//! the tables that hold those. Wallet history isn't included; it lives in its own database and
+//! comes back from the chain.
+//!
+//! Tables are copied row by row along with their schema version. Restoring creates the tables
+//! with the app's own migrations and only puts a table's rows back if the backup has it at the
+//! same version, so nothing in the file decides what SQL is run. Only the values of columns the
+//! table has are taken from it.
+//!
+//! The file is a header followed by the bincoded tables encrypted with ChaCha20-Poly1305.
+//! The key comes from the passphrase with Argon2id, whose cost is in the header so it can be
+//! raised without breaking old files. The header is authenticated along with the ciphertext.
+use crate::{
+    audit_log,
+    backup_run::BackupState,
+    bitcoin::{psbt::SignSessionPsbt, sweep_vault::SweepVault},
+    coordinator_sync,
+    device_diagnostics::DiagnosticsLog,
+    frostsnap_persist::DeviceNames,
+    persist::Persist,
+    settings::Settings,
+};
+use anyhow::{anyhow, Context as _};
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    ChaCha20Poly1305,
+};
+use frostsnap_core::coordinator::{ActiveSignSession, FrostCoordinator};
+use rusqlite::{params, params_from_iter, types::Value, OptionalExtension};
+
+const MAGIC: [u8; 8] = *b"FSNAPDB\0";
+const FORMAT_VERSION: u8 = 0;
+
+type Migrate = fn(&mut rusqlite::Connection) -> anyhow::Result<()>;
+
+/// The schema each table is migrated under, the table, and the migrations that create it, in the
+/// order they're restored.
+const TABLES: &[(&str, &str, Migrate)] = &[
+    (
+        "frostsnap_coordinator",
+        "fs_coordinator_mutations",
+        <FrostCoordinator as Persist<rusqlite::Connection>>::migrate,
+    ),
+    (
+        "frostsnap_coordinator_sync",
+        "fs_sync_identity",
+        coordinator_sync::migrate,
+    ),
+    (
+        "frostsnap_coordinator_sync",
+        "fs_sync_log",
+        coordinator_sync::migrate,
+    ),
+    (
+        "frostsnap_coordinator_sync",
+        "fs_sync_heads",
+        coordinator_sync::migrate,
+    ),
+    (
+        "frostsnap_coordinator_sync",
+        "fs_sync_held",
+        coordinator_sync::migrate,
+    ),
+    (
+        "frostsnap_active_sign_session",
+        "fs_signing_session_state",
+        <Option<ActiveSignSession> as Persist<rusqlite::Connection>>::migrate,
+    ),
+    (
+        "frostsnap_device_names",
+        "fs_devices",
+        <DeviceNames as Persist<rusqlite::Connection>>::migrate,
+    ),
+    (
+        "frostsnap_backup_state",
+        "backup_runs",
+        <BackupState as Persist<rusqlite::Connection>>::migrate,
+    ),
+    (
+        "frostsnap_settings",
+        "fs_app_global_settings",
+        <Settings as Persist<rusqlite::Connection>>::migrate,
+    ),
+    (
+        "frostsnap_psbt",
+        "fs_psbt",
+        <Option<SignSessionPsbt> as Persist<rusqlite::Connection>>::migrate,
+    ),
+    ("frostsnap_audit_log", "fs_audit_log", audit_log::migrate),
+    (
+        "frostsnap_sweep_vault",
+        "fs_sweep_vault",
+        <SweepVault as Persist<rusqlite::Connection>>::migrate,
+    ),
+    (
+        "frostsnap_device_diagnostics",
+        "fs_device_diagnostics",
+        <DiagnosticsLog as Persist<rusqlite::Connection>>::migrate,
+    ),
+];
+
+/// Argon2id cost. Memory is in KiB.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KdfParams {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        Self {
+            memory_kib: 64 * 1024,
+            iterations: 3,
+            parallelism: 1,
+        }
+    }
+}
+
+impl KdfParams {
+    /// Importing refuses anything costing more than this many times [`KdfParams::default`] in
+    /// any one parameter so a crafted file can't make us allocate or grind without limit.
+    const MAX_COST_MULTIPLE: u32 = 4;
+
+    fn is_too_expensive(&self) -> bool {
+        let max = KdfParams::default();
+        self.memory_kib > max.memory_kib * Self::MAX_COST_MULTIPLE
+            || self.iterations > max.iterations * Self::MAX_COST_MULTIPLE
+            || self.parallelism > max.parallelism * Self::MAX_COST_MULTIPLE
+    }
+
+    fn derive_key(&self, passphrase: &str, salt: &[u8; 16]) -> anyhow::Result<ChaCha20Poly1305> {
+        let params = Params::new(self.memory_kib, self.iterations, self.parallelism, Some(32))
+            .map_err(|e| anyhow!("invalid key derivation parameters: {e}"))?;
+        let mut key = [0u8; 32];
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+            .map_err(|e| anyhow!("key derivation failed: {e}"))?;
+        Ok(ChaCha20Poly1305::new(&key.into()))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Header {
+    version: u8,
+    kdf: KdfParams,
+    salt: [u8; 16],
+    nonce: [u8; 12],
+}
+
+impl Header {
+    const LEN: usize = 8 + 1 + 3 * 4 + 16 + 12;
+
+    fn to_bytes(self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(Self::LEN);
+        bytes.extend_from_slice(&MAGIC);
+        bytes.push(self.version);
+        bytes.extend_from_slice(&self.kdf.memory_kib.to_be_bytes());
+        bytes.extend_from_slice(&self.kdf.iterations.to_be_bytes());
+        bytes.extend_from_slice(&self.kdf.parallelism.to_be_bytes());
+        bytes.extend_from_slice(&self.salt);
+        bytes.extend_from_slice(&self.nonce);
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, PortableBackupError> {
+        if bytes.len() < Self::LEN || bytes[..8] != MAGIC {
+            return Err(PortableBackupError::NotABackup);
+        }
+        let version = bytes[8];
+        if version != FORMAT_VERSION {
+            return Err(PortableBackupError::UnsupportedVersion { version });
+        }
+        let u32_at = |at: usize| u32::from_be_bytes(bytes[at..at + 4].try_into().unwrap());
+        Ok(Self {
+            version,
+            kdf: KdfParams {
+                memory_kib: u32_at(9),
+                iterations: u32_at(13),
+                parallelism: u32_at(17),
+            },
+            salt: bytes[21..37].try_into().unwrap(),
+            nonce: bytes[37..49].try_into().unwrap(),
+        })
+    }
+}
+
+#[derive(bincode::Encode, bincode::Decode)]
+struct TableDump {
+    table: String,
+    schema_version: Option<u32>,
+    columns: Vec<String>,
+    rows: Vec<Vec<SqlValue>>,
+}
+
+#[derive(bincode::Encode, bincode::Decode)]
+enum SqlValue {
+    Null,
+    Integer(i64),
+    Real(f64),
+    Text(String),
+    Blob(Vec<u8>),
+}
+
+impl From<Value> for SqlValue {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::Null => SqlValue::Null,
+            Value::Integer(v) => SqlValue::Integer(v),
+            Value::Real(v) => SqlValue::Real(v),
+            Value::Text(v) => SqlValue::Text(v),
+            Value::Blob(v) => SqlValue::Blob(v),
+        }
+    }
+}
+
+impl From<SqlValue> for Value {
+    fn from(value: SqlValue) -> Self {
+        match value {
+            SqlValue::Null => Value::Null,
+            SqlValue::Integer(v) => Value::Integer(v),
+            SqlValue::Real(v) => Value::Real(v),
+            SqlValue::Text(v) => Value::Text(v),
+            SqlValue::Blob(v) => Value::Blob(v),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PortableBackupError {
+    NotABackup,
+    UnsupportedVersion {
+        version: u8,
+    },
+    KdfTooExpensive,
+    /// Wrong passphrase, or the file has been changed.
+    CannotDecrypt,
+    Malformed,
+    /// Restoring only goes into a database without any of what's being restored.
+    DatabaseNotEmpty {
+        table: String,
+    },
+    /// The backup was made by an app that stores `table` differently to this one.
+    SchemaMismatch {
+        table: String,
+    },
+}
+
+impl core::fmt::Display for PortableBackupError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            PortableBackupError::NotABackup => write!(f, "not a Frostsnap coordinator backup"),
+            PortableBackupError::UnsupportedVersion { version } => {
+                write!(
+                    f,
+                    "backup format version {version} is not supported by this app"
+                )
+            }
+            PortableBackupError::KdfTooExpensive => {
+                write!(
+                    f,
+                    "the backup asks for more work to unlock than we're willing to do"
+                )
+            }
+            PortableBackupError::CannotDecrypt => {
+                write!(f, "wrong passphrase or the backup has been modified")
+            }
+            PortableBackupError::Malformed => write!(f, "the backup's contents are malformed"),
+            PortableBackupError::DatabaseNotEmpty { table } => {
+                write!(f, "the database already has data in {table}")
+            }
+            PortableBackupError::SchemaMismatch { table } => {
+                write!(
+                    f,
+                    "the backup's {table} is from a different version of the app; update both apps and export again"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for PortableBackupError {}
+
+fn table_exists(conn: &rusqlite::Connection, table: &str) -> rusqlite::Result<bool> {
+    conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name=?1",
+        params![table],
+        |row| row.get::<_, u32>(0),
+    )
+    .map(|count| count > 0)
+}
+
+fn schema_version(conn: &rusqlite::Connection, schema_name: &str) -> rusqlite::Result<Option<u32>> {
+    if !table_exists(conn, "bdk_schemas")? {
+        return Ok(None);
+    }
+    conn.query_row(
+        "SELECT version FROM bdk_schemas WHERE name=?1",
+        params![schema_name],
+        |row| row.get::<_, u32>(0),
+    )
+    .optional()
+}
+
+/// Encrypt everything worth keeping in `conn` under `passphrase`.
+pub fn export(
+    conn: &rusqlite::Connection,
+    passphrase: &str,
+    rng: &mut impl rand_core::RngCore,
+) -> anyhow::Result<Vec<u8>> {
+    export_with_params(conn, passphrase, KdfParams::default(), rng)
+}
+
+fn export_with_params(
+    conn: &rusqlite::Connection,
+    passphrase: &str,
+    kdf: KdfParams,
+    rng: &mut impl rand_core::RngCore,
+) -> anyhow::Result<Vec<u8>> {
+    encrypt(&dump_tables(conn)?, passphrase, kdf, rng)
+}
+
+fn dump_tables(conn: &rusqlite::Connection) -> anyhow::Result<Vec<TableDump>> {
+    let mut tables = vec![];
+    for (schema_name, table, _) in TABLES {
+        if !table_exists(conn, table)? {
+            continue;
+        }
+
+        let mut stmt = conn.prepare(&format!("SELECT * FROM {table}"))?;
+        let columns = stmt
+            .column_names()
+            .into_iter()
+            .map(String::from)
+            .collect::<Vec<_>>();
+        let rows = stmt
+            .query_map([], |row| {
+                (0..columns.len())
+                    .map(|i| Ok(SqlValue::from(row.get::<_, Value>(i)?)))
+                    .collect::<rusqlite::Result<Vec<_>>>()
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .with_context(|| format!("failed to read {table}"))?;
+
+        tables.push(TableDump {
+            table: table.to_string(),
+            schema_version: schema_version(conn, schema_name)?,
+            columns,
+            rows,
+        });
+    }
+    Ok(tables)
+}
+
+fn encrypt(
+    tables: &[TableDump],
+    passphrase: &str,
+    kdf: KdfParams,
+    rng: &mut impl rand_core::RngCore,
+) -> anyhow::Result<Vec<u8>> {
+    let mut salt = [0u8; 16];
+    rng.fill_bytes(&mut salt);
+    let mut nonce = [0u8; 12];
+    rng.fill_bytes(&mut nonce);
+    let header = Header {
+        version: FORMAT_VERSION,
+        kdf,
+        salt,
+        nonce,
+    }
+    .to_bytes();
+
+    let plaintext = bincode::encode_to_vec(tables, bincode::config::standard())?;
+    let ciphertext = kdf
+        .derive_key(passphrase, &salt)?
+        .encrypt(
+            &nonce.into(),
+            Payload {
+                msg: &plaintext,
+                aad: &header,
+            },
+        )
+        .expect("encryption doesn't fail");
+
+    Ok([header, ciphertext].concat())
+}
+
+/// Restore a backup made by [`export`] into `conn`, which must not have any of the backed up
+/// tables with rows in them. Load the coordinator from `conn` afterwards, not before.
+pub fn import(
+    conn: &mut rusqlite::Connection,
+    backup: &[u8],
+    passphrase: &str,
+) -> anyhow::Result<()> {
+    let header = Header::from_bytes(backup)?;
+    if header.kdf.is_too_expensive() {
+        return Err(PortableBackupError::KdfTooExpensive.into());
+    }
+    let plaintext = header
+        .kdf
+        .derive_key(passphrase, &header.salt)?
+        .decrypt(
+            &header.nonce.into(),
+            Payload {
+                msg: &backup[Header::LEN..],
+                aad: &backup[..Header::LEN],
+            },
+        )
+        .map_err(|_| PortableBackupError::CannotDecrypt)?;
+    let (tables, _): (Vec<TableDump>, _) =
+        bincode::decode_from_slice(&plaintext, bincode::config::standard())
+            .map_err(|_| PortableBackupError::Malformed)?;
+
+    for (_, _, migrate) in TABLES {
+        migrate(conn)?;
+    }
+
+    let db_tx = conn.transaction()?;
+    for dump in tables {
+        let (schema_name, table) = TABLES
+            .iter()
+            .find(|(_, table, _)| *table == dump.table)
+            .map(|(schema_name, table, _)| (*schema_name, *table))
+            .ok_or(PortableBackupError::Malformed)?;
+        let rows = db_tx.query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |row| {
+            row.get::<_, u64>(0)
+        })?;
+        if rows > 0 {
+            return Err(PortableBackupError::DatabaseNotEmpty {
+                table: table.to_string(),
+            }
+            .into());
+        }
+        if dump.schema_version.is_none()
+            || dump.schema_version != schema_version(&db_tx, schema_name)?
+        {
+            return Err(PortableBackupError::SchemaMismatch {
+                table: table.to_string(),
+            }
+            .into());
+        }
+
+        // The column names that go into the statement are the table's own, never the backup's.
+        let table_columns = db_tx
+            .prepare("SELECT name FROM pragma_table_info(?1)")?
+            .query_map(params![table], |row| row.get::<_, String>(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        let columns = dump
+            .columns
+            .iter()
+            .map(|column| {
+                table_columns
+                    .iter()
+                    .find(|table_column| *table_column == column)
+                    .ok_or(PortableBackupError::Malformed)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let placeholders = (1..=columns.len())
+            .map(|i| format!("?{i}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let columns = columns
+            .into_iter()
+            .map(|column| column.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut stmt = db_tx.prepare(&format!(
+            "INSERT INTO {table} ({columns}) VALUES ({placeholders})"
+        ))?;
+        for row in dump.rows {
+            if row.len() != dump.columns.len() {
+                return Err(PortableBackupError::Malformed.into());
+            }
+            stmt.execute(params_from_iter(row.into_iter().map(Value::from)))
+                .with_context(|| format!("failed to restore a row of {table}"))?;
+        }
+    }
+    db_tx.commit()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{frostsnap_persist::DeviceNames, persist::Persisted, settings::Settings};
+    use frostsnap_core::{
+        coordinator::{keys::KeyMutation, CompleteKey, FrostCoordinator, Mutation},
+        device::KeyPurpose,
+        schnorr_fun::fun::Point,
+        Ciphertext, DeviceId, MasterAppkey, SymmetricKey,
+    };
+
+    const CHEAP: KdfParams = KdfParams {
+        memory_kib: 64,
+        iterations: 1,
+        parallelism: 1,
+    };
+
+    fn populated_db() -> rusqlite::Connection {
+        let mut rng = rand::thread_rng();
+        let mut conn = rusqlite::Connection::open_in_memory().unwrap();
+        let mut coordinator = Persisted::<FrostCoordinator>::new(&mut conn, ()).unwrap();
+        let mut device_names = Persisted::<DeviceNames>::new(&mut conn, ()).unwrap();
+        let mut settings = Persisted::<Settings>::new(&mut conn, ()).unwrap();
+
+        let rootkey = Point::random(&mut rng);
+        coordinator
+            .staged_mutate(&mut conn, |coordinator| {
+                coordinator.mutate(Mutation::Keygen(KeyMutation::NewKey {
+                    key_name: "savings".into(),
+                    purpose: KeyPurpose::Test,
+                    complete_key: CompleteKey {
+                        master_appkey: MasterAppkey::derive_from_rootkey(rootkey),
+                        encrypted_rootkey: Ciphertext::encrypt(
+                            SymmetricKey([7u8; 32]),
+                            &rootkey,
+                            &mut rng,
+                        ),
+                        access_structures: Default::default(),
+                    },
+                }));
+                Ok(())
+            })
+            .unwrap();
+        device_names
+            .staged_mutate(&mut conn, |names| {
+                names.insert(DeviceId([2u8; 33]), "kitchen drawer".into());
+                Ok(())
+            })
+            .unwrap();
+        settings
+            .mutate2(&mut conn, |settings, update| {
+                settings.set_developer_mode(true, update);
+                Ok(())
+            })
+            .unwrap();
+        conn
+    }
+
+    #[test]
+    fn import_restores_what_was_exported() {
+        let mut original = populated_db();
+        let backup =
+            export_with_params(&original, "correct horse", CHEAP, &mut rand::thread_rng()).unwrap();
+
+        let mut restored = rusqlite::Connection::open_in_memory().unwrap();
+        import(&mut restored, &backup, "correct horse").unwrap();
+
+        let original_coordinator = Persisted::<FrostCoordinator>::new(&mut original, ()).unwrap();
+        let restored_coordinator = Persisted::<FrostCoordinator>::new(&mut restored, ()).unwrap();
+        assert_eq!(*restored_coordinator, *original_coordinator);
+        assert_eq!(restored_coordinator.iter_keys().count(), 1);
+
+        let names = Persisted::<DeviceNames>::new(&mut restored, ()).unwrap();
+        assert_eq!(
+            names.get(DeviceId([2u8; 33])).as_deref(),
+            Some("kitchen drawer")
+        );
+        let settings = Persisted::<Settings>::new(&mut restored, ()).unwrap();
+        assert!(settings.developer_mode);
+        assert_eq!(
+            coordinator_sync::local_id(&restored).unwrap(),
+            coordinator_sync::local_id(&original).unwrap()
+        );
+        assert_eq!(
+            coordinator_sync::have(&restored).unwrap(),
+            coordinator_sync::have(&original).unwrap()
+        );
+
+        // it goes in once
+        assert!(import(&mut restored, &backup, "correct horse").is_err());
+    }
+
+    #[test]
+    fn only_the_tables_own_columns_are_written() {
+        let mut restored = rusqlite::Connection::open_in_memory().unwrap();
+        let injected = TableDump {
+            table: "fs_devices".into(),
+            schema_version: Some(0),
+            columns: vec![
+                "id".into(),
+                "name) VALUES (x'00', ''); DROP TABLE fs_app_global_settings; --".into(),
+            ],
+            rows: vec![vec![
+                SqlValue::Blob(vec![2u8; 33]),
+                SqlValue::Text("kitchen drawer".into()),
+            ]],
+        };
+        let backup = encrypt(&[injected], "correct horse", CHEAP, &mut rand::thread_rng()).unwrap();
+        let error = import(&mut restored, &backup, "correct horse").unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<PortableBackupError>(),
+            Some(&PortableBackupError::Malformed)
+        );
+        assert!(table_exists(&restored, "fs_app_global_settings").unwrap());
+    }
+
+    #[test]
+    fn tables_from_another_schema_version_are_refused() {
+        let mut tables = dump_tables(&populated_db()).unwrap();
+        for dump in &mut tables {
+            dump.schema_version = dump.schema_version.map(|version| version + 1);
+        }
+        let backup = encrypt(&tables, "correct horse", CHEAP, &mut rand::thread_rng()).unwrap();
+
+        let mut restored = rusqlite::Connection::open_in_memory().unwrap();
+        let error = import(&mut restored, &backup, "correct horse").unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<PortableBackupError>(),
+            Some(&PortableBackupError::SchemaMismatch {
+                table: "fs_coordinator_mutations".into()
+            })
+        );
+    }
+
+    #[test]
+    fn wrong_passphrase_or_tampering_is_refused() {
+        let original = populated_db();
+        let backup =
+            export_with_params(&original, "correct horse", CHEAP, &mut rand::thread_rng()).unwrap();
+        let mut restored = rusqlite::Connection::open_in_memory().unwrap();
+
+        let error = import(&mut restored, &backup, "battery staple").unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<PortableBackupError>(),
+            Some(&PortableBackupError::CannotDecrypt)
+        );
+
+        // the KDF cost is authenticated too
+        let mut cheaper = backup.clone();
+        cheaper[9..13].copy_from_slice(&32u32.to_be_bytes());
+        let error = import(&mut restored, &cheaper, "correct horse").unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<PortableBackupError>(),
+            Some(&PortableBackupError::CannotDecrypt)
+        );
+
+        let mut future = backup.clone();
+        future[8] = FORMAT_VERSION + 1;
+        let error = import(&mut restored, &future, "correct horse").unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<PortableBackupError>(),
+            Some(&PortableBackupError::UnsupportedVersion {
+                version: FORMAT_VERSION + 1
+            })
+        );
+    }
+
+    #[test]
+    fn expensive_kdf_params_are_refused() {
+        let original = populated_db();
+        let backup =
+            export_with_params(&original, "correct horse", CHEAP, &mut rand::thread_rng()).unwrap();
+        let mut restored = rusqlite::Connection::open_in_memory().unwrap();
+        let default = KdfParams::default();
+        assert!(!default.is_too_expensive());
+
+        // memory, iterations and parallelism in turn
+        for (at, limit) in [
+            (9, default.memory_kib),
+            (13, default.iterations),
+            (17, default.parallelism),
+        ] {
+            let too_much = limit * KdfParams::MAX_COST_MULTIPLE + 1;
+            let mut expensive = backup.clone();
+            expensive[at..at + 4].copy_from_slice(&too_much.to_be_bytes());
+            let error = import(&mut restored, &expensive, "correct horse").unwrap_err();
+            assert_eq!(
+                error.downcast_ref::<PortableBackupError>(),
+                Some(&PortableBackupError::KdfTooExpensive)
+            );
+        }
+    }
+}
@@ -0,0 +1,607 @@
+//! Signing with a device plugged into a different coordinator.
+//!
+//! The coordinator that owns the session exports a device's [`RequestSign`] as an [`Envelope`]
+//! addressed to the coordinator the device is plugged into. That coordinator passes it to the
+//! device with [`RemoteSignRelay`] and seals the device's answer back the same way. The answer
+//! is then an ordinary [`DeviceSigning::SignatureShare`] to the owner, which verifies it like a
+//! share from a local device.
+//!
+//! Each coordinator is known to the other by a [`RemoteSigningKey`]'s public key, exchanged out
+//! of band. An envelope's key comes from a Diffie-Hellman between the sender's key and the
+//! recipient's, so only those two can read it and opening it proves who sealed it. How envelopes
+//! get between them is up to an [`EnvelopeTransport`].
+use crate::{Completion, DeviceMode, UiProtocol};
+use anyhow::Context as _;
+use base64::Engine as _;
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    ChaCha20Poly1305,
+};
+use frostsnap_comms::CoordinatorSendMessage;
+use frostsnap_core::{
+    coordinator::{
+        CoordinatorSend, CoordinatorToUserMessage, CoordinatorToUserSigningMessage,
+        RequestDeviceSign,
+    },
+    message::{signing::DeviceSigning, DeviceToCoordinatorMessage, RequestSign},
+    nonce_stream::NonceStreamSegment,
+    schnorr_fun::{
+        frost::SignatureShare,
+        fun::{g, Point, Scalar, G},
+    },
+    DeviceId, SignSessionId,
+};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+/// A coordinator's identity for remote signing. The public key is what the other coordinator
+/// addresses envelopes to.
+#[derive(Clone)]
+pub struct RemoteSigningKey(Scalar);
+
+impl RemoteSigningKey {
+    pub fn generate(rng: &mut impl rand_core::RngCore) -> Self {
+        Self(Scalar::random(rng))
+    }
+
+    pub fn from_bytes(bytes: [u8; 32]) -> Option<Self> {
+        Scalar::from_bytes_mod_order(bytes).non_zero().map(Self)
+    }
+
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.0.to_bytes()
+    }
+
+    pub fn public_key(&self) -> Point {
+        let secret = &self.0;
+        g!(secret * G).normalize()
+    }
+
+    /// The key for envelopes from `from` to `to`, one of which is us. Binding the direction in
+    /// means a request can't be bounced back at its sender as if it were an answer.
+    fn envelope_key(&self, from: Point, to: Point) -> ChaCha20Poly1305 {
+        let other = if from == self.public_key() { to } else { from };
+        let secret = &self.0;
+        let shared = g!(secret * other).normalize();
+        let key = Sha256::new()
+            .chain_update(b"frostsnap/remote-signing")
+            .chain_update(shared.to_bytes())
+            .chain_update(from.to_bytes())
+            .chain_update(to.to_bytes())
+            .finalize();
+        ChaCha20Poly1305::new(&key)
+    }
+}
+
+/// What one coordinator sends another.
+#[derive(Clone, Debug, bincode::Encode, bincode::Decode, PartialEq)]
+pub enum RemoteSigningMessage {
+    SignRequest {
+        device_id: DeviceId,
+        request_sign: RequestSign,
+    },
+    SignatureShare {
+        session_id: SignSessionId,
+        from: DeviceId,
+        signature_shares: Vec<SignatureShare>,
+        replenish_nonces: Option<NonceStreamSegment>,
+    },
+}
+
+impl RemoteSigningMessage {
+    pub fn sign_request(request: RequestDeviceSign) -> Self {
+        RemoteSigningMessage::SignRequest {
+            device_id: request.device_id,
+            request_sign: request.request_sign,
+        }
+    }
+
+    /// The device's answer as the owning coordinator's `recv_device_message` takes it.
+    pub fn into_device_message(self) -> Option<(DeviceId, DeviceToCoordinatorMessage)> {
+        match self {
+            RemoteSigningMessage::SignatureShare {
+                session_id,
+                from,
+                signature_shares,
+                replenish_nonces,
+            } => Some((
+                from,
+                DeviceToCoordinatorMessage::Signing(DeviceSigning::SignatureShare {
+                    session_id,
+                    signature_shares,
+                    replenish_nonces,
+                }),
+            )),
+            RemoteSigningMessage::SignRequest { .. } => None,
+        }
+    }
+}
+
+/// A [`RemoteSigningMessage`] sealed for one coordinator by another.
+#[derive(Clone, Debug, bincode::Encode, bincode::Decode, PartialEq)]
+pub struct Envelope {
+    pub from: Point,
+    pub to: Point,
+    nonce: [u8; 12],
+    ciphertext: Vec<u8>,
+}
+
+impl Envelope {
+    pub fn seal(
+        message: &RemoteSigningMessage,
+        key: &RemoteSigningKey,
+        to: Point,
+        rng: &mut impl rand_core::RngCore,
+    ) -> Self {
+        let from = key.public_key();
+        let mut nonce = [0u8; 12];
+        rng.fill_bytes(&mut nonce);
+        let plaintext = bincode::encode_to_vec(message, bincode::config::standard())
+            .expect("remote signing messages encode");
+        let ciphertext = key
+            .envelope_key(from, to)
+            .encrypt(
+                &nonce.into(),
+                Payload {
+                    msg: &plaintext,
+                    aad: &Self::aad(from, to),
+                },
+            )
+            .expect("encryption doesn't fail");
+        Self {
+            from,
+            to,
+            nonce,
+            ciphertext,
+        }
+    }
+
+    /// Open an envelope addressed to `key` that must have come from `expected_from`.
+    pub fn open(
+        &self,
+        key: &RemoteSigningKey,
+        expected_from: Point,
+    ) -> Result<RemoteSigningMessage, RemoteSigningError> {
+        if self.to != key.public_key() {
+            return Err(RemoteSigningError::NotForUs);
+        }
+        if self.from != expected_from {
+            return Err(RemoteSigningError::UnexpectedSender { from: self.from });
+        }
+        let plaintext = key
+            .envelope_key(self.from, self.to)
+            .decrypt(
+                &self.nonce.into(),
+                Payload {
+                    msg: &self.ciphertext,
+                    aad: &Self::aad(self.from, self.to),
+                },
+            )
+            .map_err(|_| RemoteSigningError::Unauthentic)?;
+        let (message, _) = bincode::decode_from_slice(&plaintext, bincode::config::standard())
+            .map_err(|_| RemoteSigningError::Malformed)?;
+        Ok(message)
+    }
+
+    fn aad(from: Point, to: Point) -> Vec<u8> {
+        [from.to_bytes(), to.to_bytes()].concat()
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        bincode::encode_to_vec(self, bincode::config::standard()).expect("envelopes encode")
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, RemoteSigningError> {
+        let (envelope, _) = bincode::decode_from_slice(bytes, bincode::config::standard())
+            .map_err(|_| RemoteSigningError::Malformed)?;
+        Ok(envelope)
+    }
+
+    /// Text form, for a QR code or for pasting into a message.
+    pub fn to_text(&self) -> String {
+        base64::engine::general_purpose::STANDARD.encode(self.to_bytes())
+    }
+
+    pub fn from_text(text: &str) -> Result<Self, RemoteSigningError> {
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(text.trim())
+            .map_err(|_| RemoteSigningError::Malformed)?;
+        Self::from_bytes(&bytes)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum RemoteSigningError {
+    NotForUs,
+    UnexpectedSender {
+        from: Point,
+    },
+    /// The envelope wasn't sealed by its sender for us, or was changed on the way.
+    Unauthentic,
+    Malformed,
+}
+
+impl core::fmt::Display for RemoteSigningError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            RemoteSigningError::NotForUs => write!(f, "this envelope is for another coordinator"),
+            RemoteSigningError::UnexpectedSender { from } => {
+                write!(f, "this envelope is from an unknown coordinator {from}")
+            }
+            RemoteSigningError::Unauthentic => {
+                write!(f, "this envelope couldn't be authenticated")
+            }
+            RemoteSigningError::Malformed => write!(f, "this envelope is malformed"),
+        }
+    }
+}
+
+impl std::error::Error for RemoteSigningError {}
+
+/// Gets envelopes between coordinators.
+pub trait EnvelopeTransport {
+    fn send(&mut self, envelope: &Envelope) -> anyhow::Result<()>;
+    /// Takes every envelope waiting for us.
+    fn receive(&mut self) -> anyhow::Result<Vec<Envelope>>;
+}
+
+/// Envelopes as files in a directory, e.g. a shared folder or a USB stick carried between
+/// coordinators.
+pub struct FileTransport {
+    dir: PathBuf,
+    me: Point,
+}
+
+impl FileTransport {
+    const EXTENSION: &'static str = "frostsnap-envelope";
+
+    pub fn new(dir: impl Into<PathBuf>, me: Point) -> Self {
+        Self {
+            dir: dir.into(),
+            me,
+        }
+    }
+}
+
+impl EnvelopeTransport for FileTransport {
+    fn send(&mut self, envelope: &Envelope) -> anyhow::Result<()> {
+        let bytes = envelope.to_bytes();
+        let digest = Sha256::digest(&bytes);
+        let name = format!(
+            "{}-{}.{}",
+            envelope.to,
+            frostsnap_core::hex::encode(&digest[..8]),
+            Self::EXTENSION
+        );
+        std::fs::write(self.dir.join(name), bytes).context("writing envelope")
+    }
+
+    fn receive(&mut self) -> anyhow::Result<Vec<Envelope>> {
+        let prefix = format!("{}-", self.me);
+        let mut envelopes = vec![];
+        for entry in std::fs::read_dir(&self.dir).context("reading envelope directory")? {
+            let path = entry?.path();
+            let ours = path.extension().is_some_and(|ext| ext == Self::EXTENSION)
+                && path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| name.starts_with(&prefix));
+            if !ours {
+                continue;
+            }
+            let envelope = Envelope::from_bytes(&std::fs::read(&path)?)?;
+            std::fs::remove_file(&path)?;
+            envelopes.push(envelope);
+        }
+        Ok(envelopes)
+    }
+}
+
+/// An in-process stand-in for a relay server: mailboxes by recipient, shared between every
+/// [`RelayMailbox`] made from it.
+#[derive(Clone, Default)]
+pub struct LocalRelay {
+    mailboxes: Arc<Mutex<BTreeMap<Point, VecDeque<Envelope>>>>,
+}
+
+impl LocalRelay {
+    pub fn mailbox(&self, me: Point) -> RelayMailbox {
+        RelayMailbox {
+            relay: self.clone(),
+            me,
+        }
+    }
+}
+
+pub struct RelayMailbox {
+    relay: LocalRelay,
+    me: Point,
+}
+
+impl EnvelopeTransport for RelayMailbox {
+    fn send(&mut self, envelope: &Envelope) -> anyhow::Result<()> {
+        self.relay
+            .mailboxes
+            .lock()
+            .unwrap()
+            .entry(envelope.to)
+            .or_default()
+            .push_back(envelope.clone());
+        Ok(())
+    }
+
+    fn receive(&mut self) -> anyhow::Result<Vec<Envelope>> {
+        Ok(self
+            .relay
+            .mailboxes
+            .lock()
+            .unwrap()
+            .remove(&self.me)
+            .map(Vec::from)
+            .unwrap_or_default())
+    }
+}
+
+/// Runs on the coordinator the device is plugged into: passes one remote sign request to the
+/// device once it's connected and seals its answer for the coordinator that asked. Like
+/// signing, the caller makes the request with `FrostCoordinator::relay_sign_request` when it's
+/// told the device is ready and hands it to [`Self::send_request`].
+pub struct RemoteSignRelay {
+    key: RemoteSigningKey,
+    requester: Point,
+    device_id: DeviceId,
+    session_id: SignSessionId,
+    connected_but_need_request: bool,
+    answer: Option<Envelope>,
+    aborted: Option<String>,
+    outbox_to_devices: Vec<CoordinatorSendMessage>,
+    sink: Box<dyn crate::Sink<RemoteSignState>>,
+}
+
+impl RemoteSignRelay {
+    /// `envelope` must be a sign request from `requester`.
+    pub fn new(
+        envelope: &Envelope,
+        key: RemoteSigningKey,
+        requester: Point,
+        sink: impl crate::Sink<RemoteSignState>,
+    ) -> anyhow::Result<(Self, DeviceId, RequestSign)> {
+        let (device_id, request_sign) = match envelope.open(&key, requester)? {
+            RemoteSigningMessage::SignRequest {
+                device_id,
+                request_sign,
+            } => (device_id, request_sign),
+            RemoteSigningMessage::SignatureShare { .. } => {
+                anyhow::bail!("expected a sign request but this envelope is a signature share")
+            }
+        };
+        let relay = Self {
+            key,
+            requester,
+            device_id,
+            session_id: request_sign.group_sign_req.session_id(),
+            connected_but_need_request: false,
+            answer: None,
+            aborted: None,
+            outbox_to_devices: Default::default(),
+            sink: Box::new(sink),
+        };
+        Ok((relay, device_id, request_sign))
+    }
+
+    pub fn send_request(&mut self, request: RequestDeviceSign) {
+        if request.device_id == self.device_id && self.connected_but_need_request {
+            self.connected_but_need_request = false;
+            self.outbox_to_devices.push(
+                CoordinatorSend::from(request)
+                    .try_into()
+                    .expect("sign requests go to devices"),
+            );
+            self.emit_state();
+        }
+    }
+
+    /// The device's answer, sealed for the coordinator that asked.
+    pub fn answer(&self) -> Option<&Envelope> {
+        self.answer.as_ref()
+    }
+
+    pub fn emit_state(&mut self) {
+        let state = RemoteSignState {
+            device_id: self.device_id,
+            session_id: self.session_id,
+            connected_but_need_request: self.connected_but_need_request,
+            answer: self.answer.clone(),
+            aborted: self.aborted.clone(),
+        };
+        self.sink.send(state);
+    }
+}
+
+impl UiProtocol for RemoteSignRelay {
+    fn process_to_user_message(&mut self, message: CoordinatorToUserMessage) -> bool {
+        match message {
+            CoordinatorToUserMessage::Signing(
+                CoordinatorToUserSigningMessage::RelayedSignatureShare {
+                    session_id,
+                    from,
+                    signature_shares,
+                    replenish_nonces,
+                },
+            ) if session_id == self.session_id && from == self.device_id => {
+                if self.answer.is_none() {
+                    let message = RemoteSigningMessage::SignatureShare {
+                        session_id,
+                        from,
+                        signature_shares,
+                        replenish_nonces,
+                    };
+                    self.answer = Some(Envelope::seal(
+                        &message,
+                        &self.key,
+                        self.requester,
+                        &mut rand::thread_rng(),
+                    ));
+                    self.emit_state();
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn connected(&mut self, device_id: DeviceId, state: DeviceMode) {
+        if device_id == self.device_id && self.answer.is_none() && state == DeviceMode::Ready {
+            self.connected_but_need_request = true;
+            self.emit_state();
+        }
+    }
+
+    fn disconnected(&mut self, device_id: DeviceId) {
+        if device_id == self.device_id {
+            self.connected_but_need_request = false;
+            self.emit_state();
+        }
+    }
+
+    fn is_complete(&self) -> Option<Completion> {
+        if self.answer.is_some() {
+            Some(Completion::Success)
+        } else if self.aborted.is_some() {
+            Some(Completion::Abort {
+                send_cancel_to_all_devices: true,
+            })
+        } else {
+            None
+        }
+    }
+
+    fn poll(&mut self) -> Vec<CoordinatorSendMessage> {
+        core::mem::take(&mut self.outbox_to_devices)
+    }
+
+    fn cancel(&mut self) {
+        self.aborted = Some("Remote signing canceled".into());
+        self.emit_state()
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_mut_any(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RemoteSignState {
+    pub device_id: DeviceId,
+    pub session_id: SignSessionId,
+    pub connected_but_need_request: bool,
+    pub answer: Option<Envelope>,
+    pub aborted: Option<String>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn keys() -> (RemoteSigningKey, RemoteSigningKey) {
+        let mut rng = rand::thread_rng();
+        (
+            RemoteSigningKey::generate(&mut rng),
+            RemoteSigningKey::generate(&mut rng),
+        )
+    }
+
+    fn answer() -> RemoteSigningMessage {
+        RemoteSigningMessage::SignatureShare {
+            session_id: SignSessionId([7u8; 32]),
+            from: DeviceId([2u8; 33]),
+            signature_shares: vec![Scalar::from_bytes_mod_order([3u8; 32]).public()],
+            replenish_nonces: None,
+        }
+    }
+
+    #[test]
+    fn an_envelope_opens_only_for_its_recipient_from_its_sender() {
+        let (alice, bob) = keys();
+        let (mallory, _) = keys();
+        let envelope = Envelope::seal(&answer(), &alice, bob.public_key(), &mut rand::thread_rng());
+
+        assert_eq!(envelope.open(&bob, alice.public_key()), Ok(answer()));
+        assert_eq!(
+            envelope.open(&mallory, alice.public_key()),
+            Err(RemoteSigningError::NotForUs)
+        );
+        assert_eq!(
+            envelope.open(&bob, mallory.public_key()),
+            Err(RemoteSigningError::UnexpectedSender {
+                from: alice.public_key()
+            })
+        );
+
+        // Claiming to be alice doesn't help someone who doesn't have her key.
+        let mut forged = Envelope::seal(
+            &answer(),
+            &mallory,
+            bob.public_key(),
+            &mut rand::thread_rng(),
+        );
+        forged.from = alice.public_key();
+        assert_eq!(
+            forged.open(&bob, alice.public_key()),
+            Err(RemoteSigningError::Unauthentic)
+        );
+    }
+
+    #[test]
+    fn an_envelope_cant_be_bounced_back_at_its_sender() {
+        let (alice, bob) = keys();
+        let mut envelope =
+            Envelope::seal(&answer(), &alice, bob.public_key(), &mut rand::thread_rng());
+        core::mem::swap(&mut envelope.from, &mut envelope.to);
+        assert_eq!(
+            envelope.open(&alice, bob.public_key()),
+            Err(RemoteSigningError::Unauthentic)
+        );
+    }
+
+    #[test]
+    fn envelopes_survive_text_and_both_transports() {
+        let (alice, bob) = keys();
+        let envelope = Envelope::seal(&answer(), &alice, bob.public_key(), &mut rand::thread_rng());
+        assert_eq!(
+            Envelope::from_text(&envelope.to_text()),
+            Ok(envelope.clone())
+        );
+
+        let relay = LocalRelay::default();
+        relay.mailbox(alice.public_key()).send(&envelope).unwrap();
+        assert!(relay
+            .mailbox(alice.public_key())
+            .receive()
+            .unwrap()
+            .is_empty());
+        let mut bob_mailbox = relay.mailbox(bob.public_key());
+        assert_eq!(bob_mailbox.receive().unwrap(), vec![envelope.clone()]);
+        assert!(bob_mailbox.receive().unwrap().is_empty());
+
+        let dir = tempfile::tempdir().unwrap();
+        FileTransport::new(dir.path(), alice.public_key())
+            .send(&envelope)
+            .unwrap();
+        let mut bob_files = FileTransport::new(dir.path(), bob.public_key());
+        assert!(FileTransport::new(dir.path(), alice.public_key())
+            .receive()
+            .unwrap()
+            .is_empty());
+        assert_eq!(bob_files.receive().unwrap(), vec![envelope]);
+        assert!(bob_files.receive().unwrap().is_empty());
+    }
+}
@@ -1,9 +1,13 @@
 use crate::{
-    bitcoin::chain_sync::{default_backup_electrum_server, default_electrum_server},
+    bitcoin::{
+        chain_sync::{default_backup_electrum_server, default_electrum_server},
+        spending_policy::SpendingPolicy,
+    },
     persist::Persist,
 };
 use bdk_chain::{bitcoin, rusqlite_impl::migrate_schema};
 use core::str::FromStr;
+use frostsnap_core::KeyId;
 use rusqlite::params;
 use std::collections::BTreeMap;
 use tracing::{event, Level};
@@ -46,6 +50,7 @@ pub struct Settings {
     pub electrum_enabled: BTreeMap<bitcoin::Network, ElectrumEnabled>,
     pub developer_mode: bool,
     pub hide_balance: bool,
+    pub spending_policies: BTreeMap<KeyId, SpendingPolicy>,
 }
 
 impl Settings {
@@ -109,6 +114,20 @@ impl Settings {
         self.mutate(Mutation::SetElectrumEnabled { network, enabled }, mutations)
     }
 
+    pub fn get_spending_policy(&self, key_id: KeyId) -> Option<&SpendingPolicy> {
+        self.spending_policies.get(&key_id)
+    }
+
+    /// `None` removes the key's policy.
+    pub fn set_spending_policy(
+        &mut self,
+        key_id: KeyId,
+        policy: Option<SpendingPolicy>,
+        mutations: &mut Vec<Mutation>,
+    ) {
+        self.mutate(Mutation::SetSpendingPolicy { key_id, policy }, mutations)
+    }
+
     fn mutate(&mut self, mutation: Mutation, mutations: &mut Vec<Mutation>) {
         self.apply_mutation(mutation.clone());
         mutations.push(mutation);
@@ -131,6 +150,14 @@ impl Settings {
             Mutation::SetElectrumEnabled { network, enabled } => {
                 self.electrum_enabled.insert(network, enabled);
             }
+            Mutation::SetSpendingPolicy { key_id, policy } => match policy {
+                Some(policy) => {
+                    self.spending_policies.insert(key_id, policy);
+                }
+                None => {
+                    self.spending_policies.remove(&key_id);
+                }
+            },
         }
     }
 }
@@ -155,6 +182,10 @@ pub enum Mutation {
         network: bitcoin::Network,
         enabled: ElectrumEnabled,
     },
+    SetSpendingPolicy {
+        key_id: KeyId,
+        policy: Option<SpendingPolicy>,
+    },
 }
 
 impl Persist<rusqlite::Connection> for Settings {
@@ -257,6 +288,27 @@ impl Persist<rusqlite::Connection> for Settings {
                             }
                         }
                     }
+                    policy if policy.starts_with("spending_policy_") => {
+                        let key_id = policy.strip_prefix("spending_policy_").unwrap();
+                        match (
+                            KeyId::from_str(key_id),
+                            serde_json::from_str::<SpendingPolicy>(&value),
+                        ) {
+                            (Ok(key_id), Ok(policy)) => Mutation::SetSpendingPolicy {
+                                key_id,
+                                policy: Some(policy),
+                            },
+                            _ => {
+                                event!(
+                                    Level::WARN,
+                                    key = key,
+                                    value = value,
+                                    "invalid spending policy",
+                                );
+                                continue;
+                            }
+                        }
+                    }
                     _ => {
                         event!(
                             Level::WARN,
@@ -332,6 +384,29 @@ impl Persist<rusqlite::Connection> for Settings {
                         params![format!("electrum_enabled_{}", network), enabled.to_string()],
                     )?;
                 }
+                Mutation::SetSpendingPolicy { key_id, policy } => {
+                    event!(
+                        Level::DEBUG,
+                        key_id = key_id.to_string(),
+                        removed = policy.is_none(),
+                        "set spending policy"
+                    );
+                    let key = format!("spending_policy_{}", key_id);
+                    match policy {
+                        Some(policy) => {
+                            conn.execute(
+                                "INSERT OR REPLACE INTO fs_app_global_settings (key, value) VALUES (?1, ?2)",
+                                params![key, serde_json::to_string(&policy)?],
+                            )?;
+                        }
+                        None => {
+                            conn.execute(
+                                "DELETE FROM fs_app_global_settings WHERE key=?1",
+                                params![key],
+                            )?;
+                        }
+                    }
+                }
             }
         }
 
@@ -16,6 +16,8 @@ use crate::{Completion, DeviceMode, UiProtocol};
 pub struct SigningDispatcher {
     pub key_id: KeyId,
     pub session_id: SignSessionId,
+    /// The session this one was started in place of, see [`Self::switch_to_replacement`].
+    pub replaces: Option<SignSessionId>,
     pub finished_signatures: Option<Vec<EncodedSignature>>,
     pub targets: BTreeSet<DeviceId>,
     pub got_signatures: BTreeSet<DeviceId>,
@@ -36,6 +38,7 @@ impl SigningDispatcher {
             targets,
             key_id,
             session_id,
+            replaces: None,
             got_signatures: Default::default(),
             finished_signatures: Default::default(),
             sink: Box::new(sink),
@@ -52,6 +55,7 @@ impl SigningDispatcher {
         Self {
             key_id: active_sign_session.key_id,
             session_id: active_sign_session.session_id(),
+            replaces: None,
             got_signatures: active_sign_session.received_from().collect(),
             targets: active_sign_session.init.nonces.keys().cloned().collect(),
             finished_signatures: None,
@@ -62,6 +66,28 @@ impl SigningDispatcher {
         }
     }
 
+    /// Follow the session the coordinator started in place of ours with
+    /// `FrostCoordinator::substitute_signer`. Every signer has to be asked again, so the
+    /// `connected` ones that are signing in `replacement` need a request straight away.
+    pub fn switch_to_replacement(
+        &mut self,
+        replacement: &ActiveSignSession,
+        connected: impl IntoIterator<Item = DeviceId>,
+    ) {
+        self.replaces = Some(self.session_id);
+        self.session_id = replacement.session_id();
+        self.targets = replacement.init.nonces.keys().cloned().collect();
+        self.got_signatures = replacement.received_from().collect();
+        self.connected_but_need_request = connected
+            .into_iter()
+            .filter(|device_id| {
+                self.targets.contains(device_id) && !self.got_signatures.contains(device_id)
+            })
+            .collect();
+        self.outbox_to_devices.clear();
+        self.emit_state();
+    }
+
     pub fn set_signature_received(&mut self, from: DeviceId) {
         self.got_signatures.insert(from);
     }
@@ -69,6 +95,7 @@ impl SigningDispatcher {
     pub fn emit_state(&mut self) {
         let state = SigningState {
             session_id: self.session_id,
+            replaces: self.replaces,
             got_shares: self.got_signatures.iter().cloned().collect(),
             needed_from: self.targets.iter().cloned().collect(),
             finished_signatures: self.finished_signatures.clone(),
@@ -113,6 +140,8 @@ impl UiProtocol for SigningDispatcher {
                     event!(Level::INFO, "received signatures from all devices");
                     self.emit_state();
                 }
+                CoordinatorToUserSigningMessage::SilentPaymentEcdhShare { .. }
+                | CoordinatorToUserSigningMessage::RelayedSignatureShare { .. } => return false,
             }
             true
         } else {
@@ -168,6 +197,7 @@ impl UiProtocol for SigningDispatcher {
 #[derive(Clone, Debug)]
 pub struct SigningState {
     pub session_id: SignSessionId,
+    pub replaces: Option<SignSessionId>,
     pub got_shares: Vec<DeviceId>,
     pub needed_from: Vec<DeviceId>,
     pub finished_signatures: Option<Vec<EncodedSignature>>,
@@ -0,0 +1,155 @@
+use frostsnap_comms::CoordinatorSendMessage;
+use frostsnap_core::{
+    coordinator::{
+        CoordinatorToUserMessage, CoordinatorToUserSigningMessage, RequestDeviceSilentPaymentEcdh,
+    },
+    silent_payments::{EcdhShare, SilentPaymentAddress, SilentPaymentEcdh},
+    AccessStructureRef, DeviceId,
+};
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{Completion, DeviceMode, UiProtocol};
+
+/// Gathers ECDH shares with a silent payment address's scan key from a threshold of devices, the
+/// round that has to happen before a send paying it can be committed. Like signing, the caller
+/// makes each device's request when it's told the device is ready, since that needs the
+/// coordinator's encryption key.
+pub struct SilentPaymentEcdhDispatcher {
+    access_structure_ref: AccessStructureRef,
+    address: SilentPaymentAddress,
+    threshold: usize,
+    targets: BTreeSet<DeviceId>,
+    shares: BTreeMap<DeviceId, EcdhShare>,
+    connected_but_need_request: BTreeSet<DeviceId>,
+    outbox_to_devices: Vec<CoordinatorSendMessage>,
+    aborted: Option<String>,
+    sink: Box<dyn crate::Sink<SilentPaymentEcdhState>>,
+}
+
+impl SilentPaymentEcdhDispatcher {
+    pub fn new(
+        access_structure_ref: AccessStructureRef,
+        address: SilentPaymentAddress,
+        threshold: usize,
+        targets: BTreeSet<DeviceId>,
+        sink: impl crate::Sink<SilentPaymentEcdhState>,
+    ) -> Self {
+        Self {
+            access_structure_ref,
+            address,
+            threshold,
+            targets,
+            shares: Default::default(),
+            connected_but_need_request: Default::default(),
+            outbox_to_devices: Default::default(),
+            aborted: None,
+            sink: Box::new(sink),
+        }
+    }
+
+    pub fn send_request(&mut self, request: RequestDeviceSilentPaymentEcdh) {
+        if self.connected_but_need_request.remove(&request.device_id) {
+            self.outbox_to_devices.extend(
+                request
+                    .into_iter()
+                    .map(|send| send.try_into().expect("ecdh requests go to devices")),
+            );
+            self.emit_state();
+        }
+    }
+
+    /// The shares once a threshold of devices have contributed.
+    pub fn ecdh(&self) -> Option<SilentPaymentEcdh> {
+        (self.shares.len() >= self.threshold).then(|| SilentPaymentEcdh {
+            scan: self.address.scan,
+            shares: self.shares.values().take(self.threshold).cloned().collect(),
+        })
+    }
+
+    pub fn emit_state(&mut self) {
+        let state = SilentPaymentEcdhState {
+            address: self.address.clone(),
+            got_shares: self.shares.keys().cloned().collect(),
+            needed_from: self.targets.iter().cloned().collect(),
+            connected_but_need_request: self.connected_but_need_request.iter().cloned().collect(),
+            ecdh: self.ecdh(),
+            aborted: self.aborted.clone(),
+        };
+        self.sink.send(state);
+    }
+}
+
+impl UiProtocol for SilentPaymentEcdhDispatcher {
+    fn process_to_user_message(&mut self, message: CoordinatorToUserMessage) -> bool {
+        match message {
+            CoordinatorToUserMessage::Signing(
+                CoordinatorToUserSigningMessage::SilentPaymentEcdhShare {
+                    from,
+                    access_structure_ref,
+                    scan,
+                    share,
+                },
+            ) if access_structure_ref == self.access_structure_ref && scan == self.address.scan => {
+                if self.ecdh().is_none() && self.shares.insert(from, share).is_none() {
+                    self.emit_state();
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn disconnected(&mut self, device_id: DeviceId) {
+        self.connected_but_need_request.remove(&device_id);
+        self.emit_state();
+    }
+
+    fn connected(&mut self, device_id: DeviceId, state: DeviceMode) {
+        if !self.shares.contains_key(&device_id)
+            && self.targets.contains(&device_id)
+            && state == DeviceMode::Ready
+        {
+            self.connected_but_need_request.insert(device_id);
+            self.emit_state();
+        }
+    }
+
+    fn is_complete(&self) -> Option<Completion> {
+        if self.ecdh().is_some() {
+            Some(Completion::Success)
+        } else if self.aborted.is_some() {
+            Some(Completion::Abort {
+                send_cancel_to_all_devices: true,
+            })
+        } else {
+            None
+        }
+    }
+
+    fn poll(&mut self) -> Vec<CoordinatorSendMessage> {
+        core::mem::take(&mut self.outbox_to_devices)
+    }
+
+    fn cancel(&mut self) {
+        self.aborted = Some("Silent payment canceled".into());
+        self.emit_state()
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_mut_any(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SilentPaymentEcdhState {
+    pub address: SilentPaymentAddress,
+    pub got_shares: Vec<DeviceId>,
+    pub needed_from: Vec<DeviceId>,
+    pub connected_but_need_request: Vec<DeviceId>,
+    pub ecdh: Option<SilentPaymentEcdh>,
+    pub aborted: Option<String>,
+}
@@ -9,7 +9,10 @@ const USB_PID: u16 = 4097;
 // device's own DeviceId to bind it. Disabled until that's fixed.
 const DO_GENUINE_CHECK: bool = false;
 
-use crate::firmware::ValidatedFirmwareBin;
+use crate::firmware::{
+    FirmwarePatch, FirmwareReleaseStatus, FirmwareVersion, ReleaseManifest, UpgradeStream,
+    ValidatedFirmwareBin,
+};
 use crate::PortOpenError;
 use crate::{FramedSerialPort, Serial};
 use anyhow::anyhow;
@@ -26,6 +29,7 @@ use frostsnap_core::{DeviceId, Gist};
 use std::collections::BTreeSet;
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::sync::{Arc, OnceLock};
 use std::time::Duration;
 use tracing::{event, span, Level};
 
@@ -57,6 +61,15 @@ pub struct UsbSerialManager {
     outbox_sender: std::sync::mpsc::Sender<CoordinatorSendMessage>,
     /// The firmware binary provided to devices who are doing an upgrade
     firmware_bin: Option<ValidatedFirmwareBin>,
+    /// `firmware_bin` compressed, made the first time a chain can take it. Shared with
+    /// [`FirmwareUpgradeProtocol`] so it can tell devices the size without compressing it again.
+    ///
+    /// [`FirmwareUpgradeProtocol`]: crate::firmware_upgrade::FirmwareUpgradeProtocol
+    compressed_firmware: Arc<OnceLock<Vec<u8>>>,
+    /// Patches to `firmware_bin` from earlier firmware
+    firmware_patches: Vec<FirmwarePatch>,
+    /// The signed list of official firmware releases, already verified
+    release_manifest: Option<ReleaseManifest>,
     /// Genuine certificate public key for verifying device certificates
     genuine_cert_key: Option<Point<EvenY>>,
     /// Ongoing genuine check challenges to devices
@@ -96,6 +109,9 @@ impl UsbSerialManager {
             port_outbox: receiver,
             outbox_sender: sender,
             firmware_bin: None,
+            compressed_firmware: Default::default(),
+            firmware_patches: vec![],
+            release_manifest: None,
             genuine_cert_key: None,
             challenges: Default::default(),
             genuine_devices: Default::default(),
@@ -107,6 +123,17 @@ impl UsbSerialManager {
         self
     }
 
+    pub fn with_firmware_patches(mut self, firmware_patches: Vec<FirmwarePatch>) -> Self {
+        self.firmware_patches = firmware_patches;
+        self
+    }
+
+    /// `release_manifest` must have been verified against the release key.
+    pub fn with_release_manifest(mut self, release_manifest: ReleaseManifest) -> Self {
+        self.release_manifest = Some(release_manifest);
+        self
+    }
+
     pub fn with_genuine_cert_key(mut self, key: Point<EvenY>) -> Self {
         self.genuine_cert_key = Some(key);
         self
@@ -407,6 +434,26 @@ impl UsbSerialManager {
                                         body: AppMessageBody::Misc(CommsMisc::AckUpgradeMode),
                                     }))
                                 }
+                                DeviceSendBody::Misc(CommsMisc::CrashReports(reports)) => {
+                                    for report in &reports {
+                                        event!(
+                                            Level::ERROR,
+                                            from = message.from.to_string(),
+                                            report = report.to_string(),
+                                            "device reported a crash"
+                                        );
+                                    }
+                                    self.outbox_sender
+                                        .send(CoordinatorSendMessage::to(
+                                            message.from,
+                                            CoordinatorSendBody::CrashReportsReceived,
+                                        ))
+                                        .unwrap();
+                                    device_changes.push(DeviceChange::CrashReported {
+                                        id: message.from,
+                                        reports,
+                                    });
+                                }
                                 DeviceSendBody::Misc(inner) => {
                                     device_changes.push(DeviceChange::AppMessage(AppMessage {
                                         from: message.from,
@@ -589,11 +636,20 @@ impl UsbSerialManager {
                     .or_default()
                     .retain(|device_id| *device_id != from);
             }
-            None => device_changes.push(DeviceChange::Connected {
-                id: from,
-                firmware_digest,
-                latest_firmware_digest: self.firmware_bin.map(|firmware_bin| firmware_bin.digest()),
-            }),
+            None => {
+                let latest_firmware_digest =
+                    self.firmware_bin.map(|firmware_bin| firmware_bin.digest());
+                let release_manifest = self.release_manifest.as_ref();
+                device_changes.push(DeviceChange::Connected {
+                    id: from,
+                    firmware_digest,
+                    firmware_release: FirmwareReleaseStatus::of(&firmware_digest, release_manifest),
+                    latest_firmware_digest,
+                    latest_firmware_release: latest_firmware_digest
+                        .map(|digest| FirmwareReleaseStatus::of(&digest, release_manifest))
+                        .unwrap_or(FirmwareReleaseStatus::Unchecked),
+                })
+            }
         }
 
         self.outbox_sender
@@ -602,6 +658,12 @@ impl UsbSerialManager {
                 CoordinatorSendBody::AnnounceAck,
             ))
             .unwrap();
+        self.outbox_sender
+            .send(CoordinatorSendMessage::to(
+                from,
+                CoordinatorSendBody::RequestCrashReports,
+            ))
+            .unwrap();
 
         if DO_GENUINE_CHECK && self.genuine_cert_key.is_some() {
             let challenge = frostsnap_comms::GenuineChallenge::random(&mut rand::thread_rng());
@@ -658,13 +720,43 @@ impl UsbSerialManager {
         self.firmware_bin
     }
 
+    pub fn compressed_firmware(&self) -> Arc<OnceLock<Vec<u8>>> {
+        self.compressed_firmware.clone()
+    }
+
+    pub fn firmware_patches(&self) -> Vec<FirmwarePatch> {
+        self.firmware_patches.clone()
+    }
+
+    pub fn release_manifest(&self) -> Option<ReleaseManifest> {
+        self.release_manifest.clone()
+    }
+
     pub fn run_firmware_upgrade(
         &mut self,
     ) -> anyhow::Result<impl Iterator<Item = anyhow::Result<f32>> + '_> {
         let firmware_bin = self.firmware_bin.ok_or(anyhow!(
             "App wasn't compiled with BUNDLE_FIRMWARE=1 so it can't do firmware upgrades"
         ))?;
-        let n_chunks = firmware_bin.size().div_ceil(FIRMWARE_UPGRADE_CHUNK_LEN);
+        // This has to come to the same answer as `FirmwareUpgradeProtocol` did when it told the
+        // devices what to expect.
+        let upgrade_stream = UpgradeStream::choose(
+            &firmware_bin,
+            &self.firmware_patches,
+            &self
+                .device_ports
+                .values()
+                .map(|device_port| FirmwareVersion::new(device_port.firmware_digest))
+                .collect::<Vec<_>>(),
+        );
+        let stream: &[u8] = match upgrade_stream {
+            UpgradeStream::Raw => firmware_bin.as_bytes(),
+            UpgradeStream::Compressed => self
+                .compressed_firmware
+                .get_or_init(|| firmware_bin.compressed()),
+            UpgradeStream::Delta(patch) => patch.compressed(),
+        };
+        let n_chunks = (stream.len() as u32).div_ceil(FIRMWARE_UPGRADE_CHUNK_LEN);
         let total_chunks = n_chunks * self.ready.len() as u32;
 
         let mut iters = vec![];
@@ -693,9 +785,13 @@ impl UsbSerialManager {
 
             io.wait_for_conch()?;
 
-            event!(Level::INFO, port = port, "starting writing firmware");
-            let mut chunks = firmware_bin
-                .as_bytes()
+            event!(
+                Level::INFO,
+                port = port,
+                stream = format!("{upgrade_stream:?}"),
+                "starting writing firmware"
+            );
+            let mut chunks = stream
                 .chunks(FIRMWARE_UPGRADE_CHUNK_LEN as usize)
                 .enumerate();
 
@@ -829,7 +925,10 @@ pub enum DeviceChange {
     Connected {
         id: DeviceId,
         firmware_digest: Sha256Digest,
+        /// What the release manifest says about `firmware_digest`
+        firmware_release: FirmwareReleaseStatus,
         latest_firmware_digest: Option<Sha256Digest>,
+        latest_firmware_release: FirmwareReleaseStatus,
     },
     NeedsName {
         id: DeviceId,
@@ -850,6 +949,11 @@ pub enum DeviceChange {
         id: DeviceId,
         certificate: frostsnap_comms::genuine_certificate::CertificateBody,
     },
+    /// The device panicked at some point since it last connected.
+    CrashReported {
+        id: DeviceId,
+        reports: Vec<frostsnap_comms::CrashReport>,
+    },
 }
 
 #[derive(Debug, Clone)]
@@ -0,0 +1,108 @@
+use std::{
+    borrow::BorrowMut,
+    collections::{BTreeSet, HashSet},
+};
+
+use frostsnap_comms::{CoordinatorSendBody, CoordinatorSendMessage, Destination};
+use frostsnap_core::{
+    coordinator::VerifyAccount,
+    message::{screen_verify::ScreenVerify, CoordinatorToDeviceMessage},
+    DeviceId,
+};
+
+use crate::{Completion, DeviceMode, Sink, UiProtocol};
+
+#[derive(Clone, Debug, Default)]
+pub struct VerifyAccountProtocolState {
+    pub target_devices: Vec<DeviceId>, // not a set for frb compat
+    pub connected_devices: HashSet<DeviceId>,
+}
+
+/// Has each device show the master fingerprint and xpub of an account until the user is done
+/// comparing them and cancels.
+pub struct VerifyAccountProtocol {
+    state: VerifyAccountProtocolState,
+    message: ScreenVerify,
+    is_complete: Option<Completion>,
+    need_to_send_to: BTreeSet<DeviceId>,
+    sink: Box<dyn Sink<VerifyAccountProtocolState>>,
+}
+
+impl VerifyAccountProtocol {
+    pub fn new(
+        verify_account: VerifyAccount,
+        sink: impl Sink<VerifyAccountProtocolState> + 'static,
+    ) -> Self {
+        Self {
+            state: VerifyAccountProtocolState {
+                target_devices: verify_account.target_devices.into_iter().collect(),
+                connected_devices: Default::default(),
+            },
+            message: ScreenVerify::VerifyAccount {
+                rootkey: verify_account.rootkey,
+                account: verify_account.account,
+            },
+            is_complete: None,
+            need_to_send_to: Default::default(),
+            sink: Box::new(sink),
+        }
+    }
+
+    pub fn emit_state(&self) {
+        self.sink.send(self.state.clone());
+    }
+}
+
+impl UiProtocol for VerifyAccountProtocol {
+    fn cancel(&mut self) {
+        self.is_complete = Some(Completion::Abort {
+            send_cancel_to_all_devices: true,
+        })
+    }
+
+    fn is_complete(&self) -> Option<Completion> {
+        self.is_complete.clone()
+    }
+
+    fn connected(&mut self, id: DeviceId, state: DeviceMode) {
+        if self.state.target_devices.contains(&id) {
+            if state == DeviceMode::Ready {
+                self.need_to_send_to.insert(id);
+            }
+            if self.state.connected_devices.insert(id) {
+                self.emit_state();
+            }
+        }
+    }
+
+    fn disconnected(&mut self, device_id: DeviceId) {
+        self.need_to_send_to.remove(&device_id);
+        if self.state.connected_devices.remove(&device_id) {
+            self.emit_state();
+        }
+    }
+
+    fn poll(&mut self) -> Vec<CoordinatorSendMessage> {
+        let mut messages = vec![];
+        if !self.need_to_send_to.is_empty() {
+            messages.push(CoordinatorSendMessage {
+                target_destinations: Destination::Particular(core::mem::take(
+                    &mut self.need_to_send_to,
+                )),
+                message_body: CoordinatorSendBody::Core(CoordinatorToDeviceMessage::ScreenVerify(
+                    self.message.clone(),
+                )),
+            });
+        }
+
+        messages
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_mut_any(&mut self) -> &mut dyn std::any::Any {
+        self.borrow_mut()
+    }
+}
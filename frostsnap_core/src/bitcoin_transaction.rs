@@ -1,5 +1,5 @@
 use alloc::vec::Vec;
-use alloc::{boxed::Box, collections::BTreeMap};
+use alloc::{boxed::Box, collections::BTreeMap, string::String};
 use bitcoin::{
     consensus::Encodable,
     hashes::{sha256d, Hash},
@@ -10,6 +10,9 @@ use bitcoin::{
 
 use crate::{
     message::EncodedSignature,
+    silent_payments::{
+        SilentPaymentAddress, SilentPaymentEcdh, SilentPaymentError, SilentPaymentOutput,
+    },
     tweak::{AppTweak, BitcoinBip32Path, Keychain},
     MasterAppkey,
 };
@@ -258,6 +261,51 @@ impl TransactionTemplate<Unscoped> {
         });
     }
 
+    /// Pay a silent payment address, deriving the output from the inputs already pushed.
+    ///
+    /// BIP-352 keys the output to every input, so push them all first: an input added after this
+    /// leaves an output the device will refuse. `ecdh` is the devices' contribution for
+    /// `address`'s scan key, see [`crate::silent_payments`].
+    pub fn push_silent_payment_output(
+        &mut self,
+        value: bitcoin::Amount,
+        address: SilentPaymentAddress,
+        ecdh: SilentPaymentEcdh,
+    ) -> Result<(), SilentPaymentError> {
+        let master_appkey = self
+            .inputs
+            .first()
+            .and_then(|input| input.owner.local_owner_key())
+            .ok_or(SilentPaymentError::ForeignInput)?;
+        let k = self
+            .outputs
+            .iter()
+            .filter(|output| match &output.owner {
+                SpkOwner::SilentPayment(other) => other.address.scan == address.scan,
+                _ => false,
+            })
+            .count() as u32;
+        let spk = SilentPaymentOutput::derive_spk(
+            master_appkey,
+            self.inputs
+                .iter()
+                .map(|input| (input.outpoint, input.owner.local_owner())),
+            &address,
+            k,
+            &ecdh,
+        )?;
+        self.outputs.push(Output {
+            owner: SpkOwner::SilentPayment(Box::new(SilentPaymentOutput {
+                address,
+                k,
+                ecdh,
+                spk,
+            })),
+            value: value.to_sat(),
+        });
+        Ok(())
+    }
+
     /// Claim an existing output as `owner`'s, proving the claim against its spk.
     ///
     /// For outputs whose spk came from somewhere other than `owner` — a PSBT, an index
@@ -396,6 +444,52 @@ impl TransactionTemplate<ScopedTo> {
         Ok(tx)
     }
 
+    /// Re-derive every silent payment output from the inputs we are signing and refuse any the
+    /// coordinator got wrong.
+    ///
+    /// This is the check that makes paying one safe: the spk in the template is only the
+    /// coordinator's claim, and what the user approves is the address on screen.
+    pub fn check_silent_payment_outputs(
+        &self,
+        network: bitcoin::Network,
+    ) -> Result<(), SilentPaymentError> {
+        let mut counters = BTreeMap::<[u8; 33], Vec<u32>>::new();
+        for (index, output) in self.outputs.iter().enumerate() {
+            let sp = match &output.owner {
+                SpkOwner::SilentPayment(sp) => sp,
+                _ => continue,
+            };
+            if sp.address.network != network {
+                return Err(SilentPaymentError::InvalidAddress(
+                    "address is for a different network",
+                ));
+            }
+            let expected = SilentPaymentOutput::derive_spk(
+                self.master_appkey(),
+                self.inputs
+                    .iter()
+                    .map(|input| (input.outpoint, input.owner.local_owner())),
+                &sp.address,
+                sp.k,
+                &sp.ecdh,
+            )?;
+            if expected != sp.spk {
+                return Err(SilentPaymentError::OutputMismatch { index });
+            }
+            counters
+                .entry(sp.address.scan.to_bytes())
+                .or_default()
+                .push(sp.k);
+        }
+        for ks in counters.values_mut() {
+            ks.sort_unstable();
+            if !ks.iter().copied().eq(0..ks.len() as u32) {
+                return Err(SilentPaymentError::BadOutputCounter);
+            }
+        }
+        Ok(())
+    }
+
     pub fn iter_our_outputs(&self) -> impl Iterator<Item = (usize, &Output, &LocalSpk)> {
         self.outputs
             .iter()
@@ -427,7 +521,7 @@ impl TransactionTemplate<ScopedTo> {
     pub fn our_net_value(&self) -> i64 {
         let ours = |owner: &SpkOwner, value: u64| match owner {
             SpkOwner::Local(_) => i64::try_from(value).expect("value ridiculously large"),
-            SpkOwner::Foreign(_) => 0,
+            SpkOwner::Foreign(_) | SpkOwner::SilentPayment(_) => 0,
         };
 
         self.outputs
@@ -446,61 +540,143 @@ impl TransactionTemplate<ScopedTo> {
             .iter()
             .filter_map(|output| match &output.owner {
                 SpkOwner::Foreign(spk) => Some((spk.as_script(), output.value)),
-                _ => None,
+                SpkOwner::SilentPayment(sp) => Some((sp.spk.as_script(), output.value)),
+                SpkOwner::Local(_) => None,
             })
     }
 
     pub fn user_prompt(&self, network: bitcoin::Network) -> PromptSignBitcoinTx {
+        Self::prompt_over(&[self], network)
+    }
+
+    /// The prompt for a transaction that spends from several of the signer's keys at once, given
+    /// each key's name and its view of the transaction.
+    ///
+    /// Each view only knows its own key, so an output is shown as ours if any of them derives it.
+    /// The fee warning's total says nothing about which key pays for what, so each key's own
+    /// value at risk is listed too.
+    pub fn joint_user_prompt(
+        keys: &[(&str, &Self)],
+        network: bitcoin::Network,
+    ) -> PromptSignBitcoinTx {
+        let views = keys.iter().map(|(_, view)| *view).collect::<Vec<_>>();
+        let mut prompt = Self::prompt_over(&views, network);
+        if keys.len() > 1 {
+            prompt.key_spends = keys
+                .iter()
+                .map(|(key_name, view)| PromptKeySpend {
+                    key_name: String::from(*key_name),
+                    at_risk: view.our_value_at_risk(),
+                })
+                .collect();
+        }
+        prompt
+    }
+
+    /// What this key puts into the transaction less what comes back to it.
+    pub fn our_value_at_risk(&self) -> bitcoin::Amount {
+        let spent: u64 = self
+            .iter_our_inputs()
+            .map(|(_, input, _)| input.value)
+            .sum();
+        let returned: u64 = self
+            .iter_our_outputs()
+            .map(|(_, output, _)| output.value)
+            .sum();
+        bitcoin::Amount::from_sat(spent.saturating_sub(returned))
+    }
+
+    /// The prompt over `views`, all of the same transaction, with an input or output ours if it
+    /// is any view's.
+    fn prompt_over(views: &[&Self], network: bitcoin::Network) -> PromptSignBitcoinTx {
+        let first = views.first().expect("a prompt needs at least one key");
         let fee = bitcoin::Amount::from_sat(
-            self.fee()
+            first
+                .fee()
                 .expect("transaction validity should have already been checked"),
         );
         // Calculate fee rate in sats/vB
-        let fee_rate_sats_per_vbyte = self.feerate();
+        let fee_rate_sats_per_vbyte = first.feerate();
 
-        let any_foreign = self
-            .outputs
+        let outputs = Self::joint_outputs(views);
+        let any_foreign = outputs
             .iter()
-            .any(|output| matches!(output.owner, SpkOwner::Foreign(_)));
-        let internal_count = self
-            .iter_our_outputs()
-            .filter(|(_, _, local)| {
+            .any(|output| output.owner.local_owner().is_none());
+        let is_change = |output: &Output| {
+            output.local_owner().is_some_and(|local| {
                 local.bip32_path.account_keychain.keychain == Keychain::Internal
             })
-            .count();
+        };
+        let internal_count = outputs.iter().filter(|output| is_change(output)).count();
         // A single change output alongside a foreign recipient is the shape of an
         // ordinary send; disclosing it would train users to skim past their own
         // outputs. Any other local output — or more than one change output — is
         // value returning to us that the signer must be shown.
         let hide_single_change = any_foreign && internal_count == 1;
 
-        let recipients = self
-            .outputs
-            .iter()
-            .filter_map(|output| {
-                let owned = match &output.owner {
-                    SpkOwner::Foreign(_) => None,
-                    SpkOwner::Local(local) => {
-                        if hide_single_change
-                            && local.bip32_path.account_keychain.keychain == Keychain::Internal
-                        {
-                            return None;
-                        }
-                        Some(local.bip32_path)
-                    }
-                };
-                Some(PromptRecipient {
-                    destination: PromptDestination::of(&output.owner.spk(), network),
-                    amount: bitcoin::Amount::from_sat(output.value),
-                    owned,
-                })
-            })
+        let recipients = outputs
+            .into_iter()
+            .filter(|output| !(hide_single_change && is_change(output)))
+            .map(|output| PromptRecipient::of(output, network))
             .collect();
 
         PromptSignBitcoinTx {
             recipients,
             fee,
             fee_rate_sats_per_vbyte,
+            address_book_in_use: false,
+            key_spends: vec![],
+        }
+    }
+
+    /// Each output as whichever view owns it, if any does.
+    fn joint_outputs<'a>(views: &[&'a Self]) -> Vec<&'a Output> {
+        let first = views.first().expect("a prompt needs at least one key");
+        (0..first.outputs.len())
+            .map(|i| {
+                views
+                    .iter()
+                    .map(|view| &view.outputs[i])
+                    .find(|output| output.local_owner().is_some())
+                    .unwrap_or(&first.outputs[i])
+            })
+            .collect()
+    }
+
+    /// Everything [`Self::user_prompt`] leaves out, for a signer who wants to go through the
+    /// transaction in full: every input and every output, change included.
+    pub fn user_prompt_details(&self, network: bitcoin::Network) -> PromptTxDetails {
+        Self::joint_user_prompt_details(&[self], network)
+    }
+
+    /// [`Self::user_prompt_details`] over several keys' views, as [`Self::joint_user_prompt`].
+    pub fn joint_user_prompt_details(
+        views: &[&Self],
+        network: bitcoin::Network,
+    ) -> PromptTxDetails {
+        let first = views.first().expect("a prompt needs at least one key");
+        PromptTxDetails {
+            inputs: (0..first.inputs.len())
+                .map(|i| {
+                    let input = views
+                        .iter()
+                        .map(|view| &view.inputs[i])
+                        .find(|input| input.owner.local_owner().is_some())
+                        .unwrap_or(&first.inputs[i]);
+                    PromptInput {
+                        outpoint: input.outpoint,
+                        amount: bitcoin::Amount::from_sat(input.value),
+                        owned: input.owner.local_owner().map(|local| local.bip32_path),
+                    }
+                })
+                .collect(),
+            outputs: Self::joint_outputs(views)
+                .into_iter()
+                .map(|output| PromptRecipient::of(output, network))
+                .collect(),
+            version: first.version,
+            lock_time: first.lock_time,
+            address_book_in_use: false,
         }
     }
 }
@@ -513,6 +689,9 @@ pub enum PromptDestination {
     /// future segwit version. Says nothing about whether it can be spent — only that we
     /// cannot show it as an address, and a signing screen must not claim more than it knows.
     UnrecognizedScript(ScriptBuf),
+    /// A silent payment, shown as the address the user was given rather than the one-off
+    /// script it became. The script was checked to be the address's before this was built.
+    SilentPayment(SilentPaymentAddress),
 }
 
 impl PromptDestination {
@@ -531,6 +710,50 @@ pub struct PromptRecipient {
     /// `Some` iff the signing wallet itself derives this output's script at that path — the
     /// prompt renders such a recipient as our own.
     pub owned: Option<BitcoinBip32Path>,
+    /// What the device's address book calls this destination, if it's in there.
+    pub label: Option<String>,
+}
+
+impl PromptRecipient {
+    fn of(output: &Output, network: bitcoin::Network) -> Self {
+        let destination = match &output.owner {
+            SpkOwner::SilentPayment(sp) => PromptDestination::SilentPayment(sp.address.clone()),
+            owner => PromptDestination::of(&owner.spk(), network),
+        };
+        PromptRecipient {
+            destination,
+            amount: bitcoin::Amount::from_sat(output.value),
+            owned: output.owner.local_owner().map(|local| local.bip32_path),
+            label: None,
+        }
+    }
+
+    /// Label the recipient if it pays a script in the address books of the keys signing, one book
+    /// per key. A label is only used if every key's book has it, since each book was approved by
+    /// its own key and one key can't vouch for where another's coins go. Outputs back to us are
+    /// already shown as ours and silent payments never pay the same script twice, so neither is
+    /// looked up.
+    fn apply_address_books(&mut self, address_books: &[&BTreeMap<ScriptBuf, String>]) {
+        if self.owned.is_some() {
+            return;
+        }
+        let spk = match &self.destination {
+            PromptDestination::Address(address) => address.script_pubkey(),
+            PromptDestination::UnrecognizedScript(spk) => spk.clone(),
+            PromptDestination::SilentPayment(_) => return,
+        };
+        let mut labels = address_books.iter().map(|book| book.get(&spk));
+        self.label = match labels.next().flatten() {
+            Some(label) if labels.all(|other| other == Some(label)) => Some(label.clone()),
+            _ => None,
+        };
+    }
+
+    /// Whether the recipient leaves the wallet for somewhere the address book doesn't know, while
+    /// there is an address book to know it.
+    fn is_unlisted(&self, address_book_in_use: bool) -> bool {
+        address_book_in_use && self.owned.is_none() && self.label.is_none()
+    }
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -541,9 +764,37 @@ pub struct PromptSignBitcoinTx {
     pub fee: bitcoin::Amount,
     /// Fee rate in sats/vB
     pub fee_rate_sats_per_vbyte: Option<f64>,
+    /// Whether the signing wallet has an address book. Only then is a destination missing from it
+    /// worth warning about.
+    pub address_book_in_use: bool,
+    /// What each key puts at risk, when the transaction spends from more than one. Empty
+    /// otherwise.
+    pub key_spends: Vec<PromptKeySpend>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PromptKeySpend {
+    pub key_name: String,
+    /// What the key puts in less what comes back to it.
+    pub at_risk: bitcoin::Amount,
 }
 
 impl PromptSignBitcoinTx {
+    /// Label the recipients from the address books of the keys signing, one book per key. See
+    /// [`PromptRecipient::apply_address_books`] for which get a label.
+    pub fn apply_address_books(&mut self, address_books: &[&BTreeMap<ScriptBuf, String>]) {
+        self.address_book_in_use = address_books.iter().any(|book| !book.is_empty());
+        for recipient in &mut self.recipients {
+            recipient.apply_address_books(address_books);
+        }
+    }
+
+    /// A recipient that leaves the wallet for somewhere the address book doesn't know, while
+    /// there is an address book to know it.
+    pub fn is_unlisted(&self, recipient: &PromptRecipient) -> bool {
+        recipient.is_unlisted(self.address_book_in_use)
+    }
+
     /// Total value the prompt itemises as moving.
     pub fn value_moved(&self) -> bitcoin::Amount {
         self.recipients.iter().map(|r| r.amount).sum()
@@ -577,6 +828,51 @@ impl PromptSignBitcoinTx {
     }
 }
 
+#[derive(Clone, Debug, PartialEq)]
+pub struct PromptInput {
+    pub outpoint: OutPoint,
+    pub amount: bitcoin::Amount,
+    /// `Some` iff the signing wallet owns this input at that path. A foreign input isn't signed
+    /// here and its amount is only what the coordinator claims.
+    pub owned: Option<BitcoinBip32Path>,
+}
+
+/// The whole transaction, input by input and output by output, as the detailed review shows it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PromptTxDetails {
+    pub inputs: Vec<PromptInput>,
+    /// Every output in transaction order. Unlike [`PromptSignBitcoinTx::recipients`] the change
+    /// of an ordinary send is here too.
+    pub outputs: Vec<PromptRecipient>,
+    pub version: bitcoin::blockdata::transaction::Version,
+    pub lock_time: bitcoin::absolute::LockTime,
+    /// As [`PromptSignBitcoinTx::address_book_in_use`].
+    pub address_book_in_use: bool,
+}
+
+impl PromptTxDetails {
+    /// Label the outputs as [`PromptSignBitcoinTx::apply_address_books`] does the recipients.
+    pub fn apply_address_books(&mut self, address_books: &[&BTreeMap<ScriptBuf, String>]) {
+        self.address_book_in_use = address_books.iter().any(|book| !book.is_empty());
+        for output in &mut self.outputs {
+            output.apply_address_books(address_books);
+        }
+    }
+
+    /// As [`PromptSignBitcoinTx::is_unlisted`].
+    pub fn is_unlisted(&self, output: &PromptRecipient) -> bool {
+        output.is_unlisted(self.address_book_in_use)
+    }
+
+    /// Inputs that aren't the signing wallet's.
+    pub fn foreign_inputs(&self) -> usize {
+        self.inputs
+            .iter()
+            .filter(|input| input.owned.is_none())
+            .count()
+    }
+}
+
 /// A signature was produced for every input of ours; a different count means the list did
 /// not come from this template.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -717,6 +1013,9 @@ impl Output {
 pub enum SpkOwner {
     Foreign(#[bincode(with_serde)] ScriptBuf),
     Local(LocalSpk),
+    /// An output paying a silent payment address. Only ever an output: nothing of ours spends
+    /// one.
+    SilentPayment(Box<SilentPaymentOutput>),
 }
 
 impl SpkOwner {
@@ -724,19 +1023,20 @@ impl SpkOwner {
         match self {
             SpkOwner::Foreign(spk) => spk.clone(),
             SpkOwner::Local(owner) => owner.spk(),
+            SpkOwner::SilentPayment(sp) => sp.spk.clone(),
         }
     }
 
     pub fn local_owner_key(&self) -> Option<MasterAppkey> {
         match self {
-            SpkOwner::Foreign(_) => None,
+            SpkOwner::Foreign(_) | SpkOwner::SilentPayment(_) => None,
             SpkOwner::Local(owner) => Some(owner.master_appkey),
         }
     }
 
     pub fn local_owner(&self) -> Option<&LocalSpk> {
         match self {
-            SpkOwner::Foreign(_) => None,
+            SpkOwner::Foreign(_) | SpkOwner::SilentPayment(_) => None,
             SpkOwner::Local(owner) => Some(owner),
         }
     }
@@ -4,14 +4,39 @@ use alloc::collections::*;
 #[derive(Default, Clone, Debug, PartialEq)]
 pub struct NonceCache {
     by_device: BTreeMap<DeviceId, BTreeMap<NonceStreamId, NonceStreamSegment>>,
+    /// The partition of each device's nonce slots that is ours. Once we have one we only keep
+    /// streams in it so we never sign with a stream another coordinator opened.
+    partitions: BTreeMap<DeviceId, NoncePartition>,
 }
 
 impl NonceCache {
+    pub fn partition(&self, device_id: DeviceId) -> Option<NoncePartition> {
+        self.partitions.get(&device_id).copied()
+    }
+
+    /// Returns whether a change happened or not. Streams on the device outside `partition` are
+    /// forgotten.
+    pub fn set_partition(&mut self, device_id: DeviceId, partition: NoncePartition) -> bool {
+        if self.partitions.insert(device_id, partition) == Some(partition) {
+            return false;
+        }
+        if let Some(streams) = self.by_device.get_mut(&device_id) {
+            streams.retain(|stream_id, _| partition.contains(*stream_id));
+        }
+        true
+    }
+
     pub fn extend_segment(
         &mut self,
         device_id: DeviceId,
         new_segment: NonceStreamSegment,
     ) -> Result<bool, NonceSegmentIncompatible> {
+        if let Some(partition) = self.partition(device_id) {
+            // a reply to a request made before we had the partition
+            if !partition.contains(new_segment.stream_id) {
+                return Ok(false);
+            }
+        }
         let nonce_segments = self.by_device.entry(device_id).or_default();
 
         let segment = nonce_segments
@@ -39,6 +64,12 @@ impl NonceCache {
         }
     }
 
+    pub fn has_stream(&self, device_id: DeviceId, stream_id: NonceStreamId) -> bool {
+        self.by_device
+            .get(&device_id)
+            .is_some_and(|streams| streams.contains_key(&stream_id))
+    }
+
     pub fn new_signing_session(
         &self,
         devices: &BTreeSet<DeviceId>,
@@ -119,23 +150,34 @@ impl NonceCache {
         available
     }
 
+    /// Requests to open streams on `device_id` until there are `min_streams`, including the ones
+    /// we have already. New streams go in `open_in` if there is one. Until the device has granted
+    /// us a partition, the streams we have outside it still count.
     pub fn generate_nonce_stream_opening_requests(
         &self,
         device_id: DeviceId,
+        open_in: Option<NoncePartition>,
         min_streams: usize,
         rng: &mut impl rand_core::RngCore,
     ) -> impl IntoIterator<Item = CoordNonceStreamState> {
         let mut stream_ids = vec![];
+        let granted = self.partition(device_id);
         let streams = self
             .by_device
             .get(&device_id)
             .cloned()
             .unwrap_or_default()
-            .into_iter();
+            .into_iter()
+            .filter(|(stream_id, _)| granted.map_or(true, |granted| granted.contains(*stream_id)))
+            .collect::<Vec<_>>();
         let new_streams_needed = min_streams.saturating_sub(streams.len());
         for _ in 0..new_streams_needed {
+            let stream_id = match open_in {
+                Some(partition) => NonceStreamId::random_in(partition, rng),
+                None => NonceStreamId::random(rng),
+            };
             stream_ids.push(CoordNonceStreamState {
-                stream_id: NonceStreamId::random(rng),
+                stream_id,
                 index: 0,
                 remaining: 0,
             });
@@ -3,7 +3,7 @@ use crate::{
     device::{KeyPurpose, NONCE_BATCH_SIZE},
     map_ext::*,
     message::{signing::OpenNonceStreams, *},
-    nonce_stream::{CoordNonceStreamState, NonceStreamId},
+    nonce_stream::{CoordNonceStreamState, NoncePartition, NonceStreamId},
     symmetric_encryption::{Ciphertext, SymmetricKey},
     tweak::Xpub,
     AccessStructureId, AccessStructureKind, AccessStructureRef, ActionError,
@@ -33,6 +33,7 @@ use std::collections::{HashMap, HashSet};
 use tracing::{event, Level};
 
 mod coordinator_to_user;
+pub mod faults;
 pub mod keys;
 pub mod restoration;
 pub mod signing;
@@ -52,8 +53,18 @@ pub struct FrostCoordinator {
     active_signing_sessions: BTreeMap<SignSessionId, ActiveSignSession>,
     active_sign_session_order: Vec<SignSessionId>,
     finished_signing_sessions: BTreeMap<SignSessionId, FinishedSignSession>,
+    /// Sign requests from other coordinators we've passed to our devices, by the devices we're
+    /// waiting on. Not persisted: the session isn't ours, so losing it only means the remote
+    /// coordinator has to send it again.
+    relayed_sign_requests: BTreeMap<SignSessionId, BTreeSet<DeviceId>>,
+    /// Sessions started by [`Self::substitute_signer`], mapped to the session each replaced.
+    replaced_sign_sessions: BTreeMap<SignSessionId, SignSessionId>,
+    device_faults: BTreeMap<DeviceId, faults::DeviceFaults>,
     restoration: restoration::State,
     pub keygen_fingerprint: schnorr_fun::frost::Fingerprint,
+    /// Tells this coordinator apart from others using the same devices. The nonce partition we
+    /// claim on each device comes from it.
+    pub coordinator_id: [u8; 32],
 }
 
 #[derive(Debug, Clone, bincode::Encode, bincode::Decode, PartialEq)]
@@ -383,16 +394,9 @@ impl FrostCoordinator {
                     .active_signing_sessions
                     .remove(&session_id)
                     .expect("it existed in the order");
-                let n_sigs = session_state.init.group_request.n_signatures();
-                for (device_id, nonce_segment) in &session_state.init.nonces {
-                    if session_state.sent_req_to_device.contains(device_id) {
-                        let consume_to = nonce_segment
-                            .index
-                            .checked_add(n_sigs as _)
-                            .expect("no overflow");
-                        self.nonce_cache
-                            .consume(*device_id, nonce_segment.stream_id, consume_to);
-                    }
+                session_state.consume_sent_nonces(&mut self.nonce_cache);
+                if finished.is_none() {
+                    self.replaced_sign_sessions.remove(&session_id);
                 }
                 if let Some(signatures) = finished {
                     self.finished_signing_sessions.insert(
@@ -407,6 +411,46 @@ impl FrostCoordinator {
             }
             Signing(SigningMutation::ForgetFinishedSignSession { session_id }) => {
                 self.finished_signing_sessions.remove(&session_id);
+                self.replaced_sign_sessions.remove(&session_id);
+            }
+            Signing(SigningMutation::ReplacedSignSession {
+                abandoned,
+                replacement,
+            }) => {
+                if !self.active_signing_sessions.contains_key(&replacement) {
+                    return None;
+                }
+                self.replaced_sign_sessions.insert(replacement, abandoned);
+            }
+            Signing(SigningMutation::NewNoncePartition {
+                device_id,
+                partition,
+            }) => {
+                if !self.nonce_cache.set_partition(device_id, partition) {
+                    return None;
+                }
+            }
+            Fault(faults::FaultMutation::RecordFault { device_id, fault }) => {
+                let device_faults = self.device_faults.entry(device_id).or_default();
+                let kind = core::mem::discriminant(&fault);
+                if device_faults
+                    .faults
+                    .iter()
+                    .any(|recorded| core::mem::discriminant(recorded) == kind)
+                {
+                    return None;
+                }
+                device_faults.faults.push(fault);
+            }
+            Fault(faults::FaultMutation::SetQuarantined {
+                device_id,
+                quarantined,
+            }) => {
+                let device_faults = self.device_faults.entry(device_id).or_default();
+                if device_faults.quarantined == quarantined {
+                    return None;
+                }
+                device_faults.quarantined = quarantined;
             }
             Restoration(inner) => {
                 return self
@@ -604,14 +648,12 @@ impl FrostCoordinator {
             ) => {
                 let mut outgoing = vec![];
                 for new_segment in segments {
-                    self.nonce_cache
-                        .check_can_extend(from, &new_segment)
-                        .map_err(|e| {
-                            Error::coordinator_invalid_message(
-                                message_kind,
-                                format!("couldn't extend nonces: {e}"),
-                            )
-                        })?;
+                    if let Err(e) = self.nonce_cache.check_can_extend(from, &new_segment) {
+                        return Err(Error::coordinator_invalid_message(
+                            message_kind,
+                            format!("couldn't extend nonces: {e}"),
+                        ));
+                    }
 
                     self.mutate(Mutation::Signing(SigningMutation::NewNonces {
                         device_id: from,
@@ -625,6 +667,22 @@ impl FrostCoordinator {
 
                 Ok(outgoing)
             }
+            DeviceToCoordinatorMessage::Signing(
+                crate::message::signing::DeviceSigning::NoncePartitionClaim { partition, granted },
+            ) => {
+                // If it wasn't granted another coordinator is using the partition, so we carry on
+                // with the streams we have. A claim we didn't make is ignored.
+                if granted
+                    && self.nonce_cache.partition(from).is_none()
+                    && partition == NoncePartition::for_coordinator(self.coordinator_id, from)
+                {
+                    self.mutate(Mutation::Signing(SigningMutation::NewNoncePartition {
+                        device_id: from,
+                        partition,
+                    }));
+                }
+                Ok(vec![])
+            }
             DeviceToCoordinatorMessage::KeyGen(keygen::DeviceKeygen::Response(response)) => {
                 let keygen_id = response.keygen_id;
                 let (state, entry) = self.pending_keygens.take_entry(keygen_id);
@@ -643,14 +701,17 @@ impl FrostCoordinator {
                                 "got share from device that was not part of keygen",
                             ))? as u32;
 
-                        state
-                            .input_aggregator
-                            .add_input(
-                                &schnorr_fun::Schnorr::<Sha256>::verify_only(),
-                                certpedpop::Party::Receiver(receiver_idx),
-                                *response.input,
-                            )
-                            .map_err(|e| Error::coordinator_invalid_message(message_kind, e))?;
+                        if let Err(e) = state.input_aggregator.add_input(
+                            &schnorr_fun::Schnorr::<Sha256>::verify_only(),
+                            certpedpop::Party::Receiver(receiver_idx),
+                            *response.input,
+                        ) {
+                            self.record_fault(
+                                from,
+                                faults::DeviceFault::InvalidKeygenInput { keygen_id },
+                            );
+                            return Err(Error::coordinator_invalid_message(message_kind, e));
+                        }
 
                         let mut outgoing =
                             vec![CoordinatorSend::ToUser(CoordinatorToUserMessage::KeyGen {
@@ -719,14 +780,19 @@ impl FrostCoordinator {
                 match state {
                     Some(KeyGenState::WaitingForCertificates(mut state)) => {
                         // Store device output and its certificate
-                        state.certifier
+                        if state.certifier
                             .receive_certificate(from.pubkey(), vrf_cert)
-                            .map_err(|_| {
-                                Error::coordinator_invalid_message(
-                                    message_kind,
-                                    "Invalid VRF proof received",
-                                )
-                            })?;
+                            .is_err()
+                        {
+                            self.record_fault(
+                                from,
+                                faults::DeviceFault::InvalidKeygenCertificate { keygen_id },
+                            );
+                            return Err(Error::coordinator_invalid_message(
+                                message_kind,
+                                "Invalid VRF proof received",
+                            ));
+                        }
 
                         // contributers are the devices plus one coordinator
                         if state.certifier.is_finished() {
@@ -791,6 +857,10 @@ impl FrostCoordinator {
                             SessionHash::from_certified_keygen(&state.certified_keygen);
                         if ack_session_hash != session_hash {
                             entry.insert(KeyGenState::WaitingForAcks(state));
+                            self.record_fault(
+                                from,
+                                faults::DeviceFault::InconsistentKeygenAck { keygen_id },
+                            );
                             return Err(Error::coordinator_invalid_message(
                                 message_kind,
                                 "Device acked wrong keygen session hash",
@@ -871,6 +941,25 @@ impl FrostCoordinator {
                     ref replenish_nonces,
                 },
             ) => {
+                if !self.active_signing_sessions.contains_key(&session_id) {
+                    if let Some(relayed_to) = self.relayed_sign_requests.get_mut(&session_id) {
+                        if relayed_to.remove(&from) {
+                            if relayed_to.is_empty() {
+                                self.relayed_sign_requests.remove(&session_id);
+                            }
+                            return Ok(vec![CoordinatorSend::ToUser(
+                                CoordinatorToUserMessage::Signing(
+                                    CoordinatorToUserSigningMessage::RelayedSignatureShare {
+                                        session_id,
+                                        from,
+                                        signature_shares: signature_shares.clone(),
+                                        replenish_nonces: replenish_nonces.clone(),
+                                    },
+                                ),
+                            )]);
+                        }
+                    }
+                }
                 let active_sign_session = self.active_signing_sessions.get(&session_id).ok_or(
                     Error::coordinator_invalid_message(
                         message_kind,
@@ -895,6 +984,7 @@ impl FrostCoordinator {
                     return Err(Error::coordinator_invalid_message(message_kind, format!("signer did not provide the right number of signature shares. Got {}, expected {}", signature_shares.len(), sessions.len())));
                 }
 
+                let mut invalid_share_under = None;
                 for (session_progress, signature_share) in sessions.iter().zip(signature_shares) {
                     let session = &session_progress.sign_session;
                     let xonly_frost_key = &session_progress.tweaked_frost_key();
@@ -912,16 +1002,22 @@ impl FrostCoordinator {
                         )
                         .is_err()
                     {
-                        return Err(Error::coordinator_invalid_message(
-                            message_kind,
-                            format!(
-                                "Invalid signature share under key {}",
-                                xonly_frost_key.public_key()
-                            ),
-                        ));
+                        invalid_share_under = Some(xonly_frost_key.public_key());
+                        break;
                     }
                 }
 
+                if let Some(public_key) = invalid_share_under {
+                    self.record_fault(
+                        from,
+                        faults::DeviceFault::InvalidSignatureShare { session_id },
+                    );
+                    return Err(Error::coordinator_invalid_message(
+                        message_kind,
+                        format!("Invalid signature share under key {public_key}"),
+                    ));
+                }
+
                 outgoing.push(CoordinatorSend::ToUser(CoordinatorToUserMessage::Signing(
                     CoordinatorToUserSigningMessage::GotShare { session_id, from },
                 )));
@@ -935,10 +1031,18 @@ impl FrostCoordinator {
                 ));
 
                 if let Some(replenish_nonces) = replenish_nonces {
-                    self.mutate(Mutation::Signing(SigningMutation::NewNonces {
-                        device_id: from,
-                        nonce_segment: replenish_nonces.clone(),
-                    }));
+                    if self
+                        .nonce_cache
+                        .check_can_extend(from, replenish_nonces)
+                        .is_ok()
+                    {
+                        self.mutate(Mutation::Signing(SigningMutation::NewNonces {
+                            device_id: from,
+                            nonce_segment: replenish_nonces.clone(),
+                        }));
+                    }
+                    // Otherwise the shares are still good so we keep them. The stream may have
+                    // moved on under another coordinator, so it's no fault of the device.
                 }
 
                 if let Some(signatures) = self.complete_sign_session(session_id) {
@@ -952,9 +1056,76 @@ impl FrostCoordinator {
 
                 Ok(outgoing)
             }
+            DeviceToCoordinatorMessage::Signing(
+                crate::message::signing::DeviceSigning::SilentPaymentEcdhShare {
+                    access_structure_ref,
+                    scan,
+                    ref share,
+                },
+            ) => {
+                let access_structure = self.get_access_structure(access_structure_ref).ok_or(
+                    Error::coordinator_invalid_message(
+                        message_kind,
+                        "got silent payment ECDH share for an access structure we don't have",
+                    ),
+                )?;
+                if access_structure.device_to_share_index.get(&from) != Some(&share.index) {
+                    return Err(Error::coordinator_invalid_message(
+                        message_kind,
+                        "device sent an ECDH share for a share it doesn't hold",
+                    ));
+                }
+                let expected_image = access_structure.app_shared_key.key.share_image(share.index);
+                if expected_image.image != share.image.mark_zero() || !share.verify(scan) {
+                    return Err(Error::coordinator_invalid_message(
+                        message_kind,
+                        "invalid silent payment ECDH share",
+                    ));
+                }
+                Ok(vec![CoordinatorSend::ToUser(
+                    CoordinatorToUserMessage::Signing(
+                        CoordinatorToUserSigningMessage::SilentPaymentEcdhShare {
+                            from,
+                            access_structure_ref,
+                            scan,
+                            share: share.clone(),
+                        },
+                    ),
+                )])
+            }
             DeviceToCoordinatorMessage::Restoration(message) => {
                 self.recv_restoration_message(from, message)
             }
+            DeviceToCoordinatorMessage::AddressBook(address_book::DeviceAddressBook::Updated {
+                key_id,
+                spk,
+                label,
+            }) => {
+                let frost_key =
+                    self.keys
+                        .get(&key_id)
+                        .ok_or(Error::coordinator_invalid_message(
+                            message_kind,
+                            "device updated the address book of a key we don't have",
+                        ))?;
+                if !frost_key
+                    .access_structures()
+                    .any(|accss| accss.device_to_share_index.contains_key(&from))
+                {
+                    return Err(Error::coordinator_invalid_message(
+                        message_kind,
+                        "device updated the address book of a key it isn't part of",
+                    ));
+                }
+                Ok(vec![CoordinatorSend::ToUser(
+                    CoordinatorToUserMessage::AddressBookUpdated {
+                        device_id: from,
+                        key_id,
+                        spk,
+                        label,
+                    },
+                )])
+            }
         }
     }
 
@@ -1073,6 +1244,34 @@ impl FrostCoordinator {
         signing_devices: &BTreeSet<DeviceId>,
         rng: &mut impl rand_core::RngCore,
     ) -> Result<SignSessionId, StartSignError> {
+        let local_session = self.plan_sign_session(
+            &self.nonce_cache,
+            &self.all_used_nonce_streams(),
+            access_structure_ref,
+            sign_task,
+            signing_devices,
+            rng,
+        )?;
+        let session_id = local_session.session_id();
+
+        self.mutate(Mutation::Signing(SigningMutation::NewSigningSession(
+            local_session,
+        )));
+
+        Ok(session_id)
+    }
+
+    /// Choose nonces for `signing_devices` from `nonce_cache`, skipping `used_streams`, and build
+    /// the session without starting it.
+    fn plan_sign_session(
+        &self,
+        nonce_cache: &NonceCache,
+        used_streams: &BTreeSet<NonceStreamId>,
+        access_structure_ref: AccessStructureRef,
+        sign_task: WireSignTask,
+        signing_devices: &BTreeSet<DeviceId>,
+        rng: &mut impl rand_core::RngCore,
+    ) -> Result<ActiveSignSession, StartSignError> {
         let AccessStructureRef {
             key_id,
             access_structure_id,
@@ -1095,6 +1294,9 @@ impl FrostCoordinator {
             if !access_structure.device_to_share_index.contains_key(device) {
                 return Err(StartSignError::DeviceNotPartOfKey { device_id: *device });
             }
+            if self.is_quarantined(*device) {
+                return Err(StartSignError::DeviceQuarantined { device_id: *device });
+            }
         }
 
         let app_shared_key = access_structure.app_shared_key().clone();
@@ -1115,13 +1317,8 @@ impl FrostCoordinator {
         let sign_items = checked_sign_task.sign_items();
         let n_signatures = sign_items.len();
 
-        let nonces_by_device = self
-            .nonce_cache
-            .new_signing_session(
-                signing_devices,
-                n_signatures,
-                &self.all_used_nonce_streams(),
-            )
+        let nonces_by_device = nonce_cache
+            .new_signing_session(signing_devices, n_signatures, used_streams)
             .map_err(StartSignError::NotEnoughNoncesForDevice)?;
 
         let nonces_by_party = nonces_by_device
@@ -1166,8 +1363,6 @@ impl FrostCoordinator {
                 .collect(),
             access_structure_id,
         };
-        let session_id = group_request.session_id();
-
         let device_requests = nonces_by_device
             .into_iter()
             .map(|(device, nonce_segment)| (device, nonce_segment.coord_nonce_state()))
@@ -1178,18 +1373,12 @@ impl FrostCoordinator {
             group_request,
         };
 
-        let local_session = ActiveSignSession {
+        Ok(ActiveSignSession {
             progress: sessions,
-            init: start_sign.clone(),
+            init: start_sign,
             key_id,
             sent_req_to_device: Default::default(),
-        };
-
-        self.mutate(Mutation::Signing(SigningMutation::NewSigningSession(
-            local_session,
-        )));
-
-        Ok(session_id)
+        })
     }
 
     pub fn request_device_sign(
@@ -1242,20 +1431,214 @@ impl FrostCoordinator {
         }
     }
 
+    /// Pass a sign request another coordinator made to `device_id`, a signer that is plugged in
+    /// here rather than there. The request is the other coordinator's business so nothing is
+    /// checked or persisted; the device's answer comes back unverified as
+    /// [`CoordinatorToUserSigningMessage::RelayedSignatureShare`] for the caller to return.
+    pub fn relay_sign_request(
+        &mut self,
+        device_id: DeviceId,
+        request_sign: RequestSign,
+    ) -> RequestDeviceSign {
+        self.relayed_sign_requests
+            .entry(request_sign.group_sign_req.session_id())
+            .or_default()
+            .insert(device_id);
+        RequestDeviceSign {
+            request_sign,
+            device_id,
+        }
+    }
+
+    /// Start a session for each of `keys` over the same `sign_task`, for a task that spends coins
+    /// from more than one of our keys.
+    ///
+    /// The sessions are ordinary ones that complete on their own, but since each is started
+    /// with the others' nonce streams already in use, a device signing for more than one key
+    /// can be sent them all at once with [`Self::request_device_multi_sign`]. If any key can't
+    /// start, the ones before it are cancelled.
+    pub fn start_multi_sign(
+        &mut self,
+        keys: Vec<(AccessStructureRef, BTreeSet<DeviceId>)>,
+        sign_task: WireSignTask,
+        rng: &mut impl rand_core::RngCore,
+    ) -> Result<Vec<SignSessionId>, StartSignError> {
+        let mut session_ids = vec![];
+        for (access_structure_ref, signing_devices) in keys {
+            match self.start_sign(
+                access_structure_ref,
+                sign_task.clone(),
+                &signing_devices,
+                rng,
+            ) {
+                Ok(session_id) => session_ids.push(session_id),
+                Err(e) => {
+                    for session_id in session_ids {
+                        self.cancel_sign_session(session_id);
+                    }
+                    return Err(e);
+                }
+            }
+        }
+        Ok(session_ids)
+    }
+
+    /// Like [`Self::request_device_sign`] but for every one of `session_ids` that `device_id`
+    /// is signing in, so the device asks its user to confirm once. The sessions must be over the
+    /// same task, as they are when started by [`Self::start_multi_sign`], and `encryption_key`
+    /// must open every one of their keys. Nothing is sent or recorded unless all of that holds.
+    pub fn request_device_multi_sign(
+        &mut self,
+        session_ids: &[SignSessionId],
+        device_id: DeviceId,
+        encryption_key: SymmetricKey,
+    ) -> Result<RequestDeviceMultiSign, ActionError> {
+        let mut sign_task = None;
+        let mut signing_in = vec![];
+        for &session_id in session_ids {
+            let session = self
+                .active_signing_sessions
+                .get(&session_id)
+                .ok_or_else(|| {
+                    ActionError::StateInconsistent(format!("no signing session {session_id}"))
+                })?;
+            if !session.init.nonces.contains_key(&device_id) {
+                continue;
+            }
+            let group_sign_req = &session.init.group_request;
+            let task = sign_task.get_or_insert_with(|| group_sign_req.sign_task.clone());
+            if *task != group_sign_req.sign_task {
+                return Err(ActionError::StateInconsistent(
+                    "sessions in a multi-key request must be over the same task".into(),
+                ));
+            }
+            let opens = self.keys.get(&session.key_id).is_some_and(|key| {
+                key.complete_key
+                    .coord_share_decryption_contrib(
+                        group_sign_req.access_structure_id,
+                        device_id,
+                        encryption_key,
+                    )
+                    .is_some()
+            });
+            if !opens {
+                return Err(ActionError::StateInconsistent(format!(
+                    "encryption key doesn't open the key of session {session_id}"
+                )));
+            }
+            signing_in.push(session_id);
+        }
+        let sign_task = sign_task.ok_or_else(|| {
+            ActionError::StateInconsistent(format!(
+                "device {device_id} isn't in any of the signing sessions"
+            ))
+        })?;
+
+        let keys = signing_in
+            .into_iter()
+            .map(|session_id| {
+                let RequestSign {
+                    group_sign_req,
+                    device_sign_req,
+                } = self
+                    .request_device_sign(session_id, device_id, encryption_key)
+                    .request_sign;
+                KeySignReq {
+                    parties: group_sign_req.parties,
+                    agg_nonces: group_sign_req.agg_nonces,
+                    access_structure_id: group_sign_req.access_structure_id,
+                    device_sign_req,
+                }
+            })
+            .collect();
+
+        Ok(RequestDeviceMultiSign {
+            request_multi_sign: RequestMultiSign { sign_task, keys },
+            device_id,
+        })
+    }
+
+    /// Ask `device_id` for its share of the ECDH with `address`'s scan key, so a transaction
+    /// from `access_structure_ref` can pay it. The shares come back as
+    /// [`CoordinatorToUserSigningMessage::SilentPaymentEcdhShare`]; a threshold of them make the
+    /// [`crate::silent_payments::SilentPaymentEcdh`] the template needs.
+    pub fn request_silent_payment_ecdh(
+        &self,
+        access_structure_ref: AccessStructureRef,
+        device_id: DeviceId,
+        address: crate::silent_payments::SilentPaymentAddress,
+        encryption_key: SymmetricKey,
+    ) -> Result<RequestDeviceSilentPaymentEcdh, ActionError> {
+        let complete_key = &self
+            .keys
+            .get(&access_structure_ref.key_id)
+            .ok_or(ActionError::StateInconsistent("no such key".into()))?
+            .complete_key;
+        let share_index = *complete_key
+            .access_structures
+            .get(&access_structure_ref.access_structure_id)
+            .ok_or(ActionError::StateInconsistent(
+                "no such access structure".into(),
+            ))?
+            .device_to_share_index
+            .get(&device_id)
+            .ok_or(ActionError::StateInconsistent(
+                "device is not part of the access structure".into(),
+            ))?;
+        let (rootkey, coord_share_decryption_contrib) = complete_key
+            .coord_share_decryption_contrib(
+                access_structure_ref.access_structure_id,
+                device_id,
+                encryption_key,
+            )
+            .ok_or(ActionError::StateInconsistent(
+                "couldn't decrypt rootkey".into(),
+            ))?;
+
+        Ok(RequestDeviceSilentPaymentEcdh {
+            device_id,
+            request: RequestSilentPaymentEcdh {
+                access_structure_id: access_structure_ref.access_structure_id,
+                share_index,
+                address,
+                rootkey,
+                coord_share_decryption_contrib,
+            },
+        })
+    }
+
+    /// Streams to open so each of `devices` has `desired_nonce_streams`. Devices that
+    /// `can_partition` and haven't granted us a partition yet are asked for one, and new streams
+    /// on them are opened in it. Until it's granted the streams we have keep being used.
     pub fn maybe_request_nonce_replenishment(
         &self,
         devices: &BTreeSet<DeviceId>,
         desired_nonce_streams: usize,
+        can_partition: impl Fn(DeviceId) -> bool,
         rng: &mut impl rand_core::RngCore,
     ) -> NonceReplenishRequest {
+        let mut partition_claims = BTreeMap::default();
         let replenish_requests = devices
             .iter()
             .map(|device_id| {
+                let open_in = match self.nonce_cache.partition(*device_id) {
+                    Some(granted) => Some(granted),
+                    None if can_partition(*device_id) => {
+                        // The claim goes out before the streams are opened so the device has set
+                        // the partition aside by the time it gets to them.
+                        let pending =
+                            NoncePartition::for_coordinator(self.coordinator_id, *device_id);
+                        partition_claims.insert(*device_id, pending);
+                        Some(pending)
+                    }
+                    None => None,
+                };
                 (
                     *device_id,
                     self.nonce_cache
                         .generate_nonce_stream_opening_requests(
                             *device_id,
+                            open_in,
                             desired_nonce_streams,
                             rng,
                         )
@@ -1265,7 +1648,10 @@ impl FrostCoordinator {
             })
             .collect();
 
-        NonceReplenishRequest { replenish_requests }
+        NonceReplenishRequest {
+            replenish_requests,
+            partition_claims,
+        }
     }
 
     pub fn verify_address(
@@ -1298,11 +1684,129 @@ impl FrostCoordinator {
         })
     }
 
+    /// Ask every device with a share of `key_id` to show the master fingerprint and xpub of
+    /// `account`, so the user can check the descriptor a watch-only wallet was given really is
+    /// this key's.
+    pub fn verify_account(
+        &self,
+        key_id: KeyId,
+        account: crate::tweak::BitcoinAccount,
+        encryption_key: SymmetricKey,
+    ) -> Result<VerifyAccount, ActionError> {
+        let frost_key = self
+            .get_frost_key(key_id)
+            .ok_or(ActionError::StateInconsistent("no such frost key".into()))?;
+        if frost_key.purpose.bitcoin_network().is_none() {
+            return Err(ActionError::StateInconsistent(
+                "only wallets have accounts".into(),
+            ));
+        }
+        let rootkey = frost_key
+            .complete_key
+            .encrypted_rootkey
+            .decrypt(encryption_key)
+            .ok_or(ActionError::StateInconsistent(
+                "couldn't decrypt rootkey".into(),
+            ))?;
+
+        let target_devices = frost_key
+            .access_structures()
+            .flat_map(|accss| {
+                accss
+                    .device_to_share_index
+                    .keys()
+                    .cloned()
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+
+        Ok(VerifyAccount {
+            rootkey,
+            account,
+            target_devices,
+        })
+    }
+
+    /// Ask the devices in `access_structure_ref` to label `spk` in their address book, or to drop
+    /// it if `label` is `None`. A new label must first have been approved by signing a
+    /// [`WireSignTask::AddressBookEntry`] for it with the key, since the devices only take an entry
+    /// that comes with the key's signature. Each device also asks its user first, and reports the
+    /// change with [`CoordinatorToUserMessage::AddressBookUpdated`].
+    pub fn update_address_book(
+        &self,
+        access_structure_ref: AccessStructureRef,
+        spk: bitcoin::ScriptBuf,
+        label: Option<String>,
+    ) -> Result<UpdateAddressBook, ActionError> {
+        let frost_key = self
+            .get_frost_key(access_structure_ref.key_id)
+            .ok_or(ActionError::StateInconsistent("no such frost key".into()))?;
+        if frost_key.purpose.bitcoin_network().is_none() {
+            return Err(ActionError::StateInconsistent(
+                "only wallets have an address book".into(),
+            ));
+        }
+        let access_structure = frost_key
+            .get_access_structure(access_structure_ref.access_structure_id)
+            .ok_or(ActionError::StateInconsistent(
+                "no such access structure".into(),
+            ))?;
+        let key_id = access_structure_ref.key_id;
+        let message = match label {
+            Some(label) => {
+                crate::device::address_book::check_label(&label)
+                    .map_err(|e| ActionError::StateInconsistent(e.into()))?;
+                let approval = WireSignTask::AddressBookEntry {
+                    label: label.clone(),
+                    spk: spk.clone(),
+                };
+                // finished sessions only hold signatures that have been verified
+                let signature = self
+                    .finished_signing_sessions
+                    .values()
+                    .filter(|session| {
+                        session.key_id == key_id && session.init.group_request.sign_task == approval
+                    })
+                    .find_map(|session| session.signatures.first().copied())
+                    .ok_or(ActionError::StateInconsistent(
+                        "approve the entry by signing it with the key first".into(),
+                    ))?;
+                address_book::CoordinatorAddressBook::AddEntry {
+                    master_appkey: frost_key.complete_key.master_appkey,
+                    label,
+                    spk,
+                    signature,
+                }
+            }
+            None => address_book::CoordinatorAddressBook::RemoveEntry { key_id, spk },
+        };
+
+        Ok(UpdateAddressBook {
+            message,
+            target_devices: access_structure
+                .device_to_share_index
+                .keys()
+                .cloned()
+                .collect(),
+        })
+    }
+
     pub fn nonces_available(&self, device_id: DeviceId) -> BTreeMap<NonceStreamId, u32> {
         self.nonce_cache
             .nonces_available(device_id, &self.all_used_nonce_streams())
     }
 
+    /// The partition of `device_id`'s nonce slots we open streams in, once the device has granted
+    /// us one.
+    pub fn nonce_partition(&self, device_id: DeviceId) -> Option<NoncePartition> {
+        self.nonce_cache.partition(device_id)
+    }
+
+    /// Whether `stream_id` is one of ours: opened by us on `device_id`, with its nonces cached here.
+    pub fn has_nonce_stream(&self, device_id: DeviceId, stream_id: NonceStreamId) -> bool {
+        self.nonce_cache.has_stream(device_id, stream_id)
+    }
+
     pub fn get_access_structure(
         &self,
         access_structure_ref: AccessStructureRef,
@@ -1473,6 +1977,157 @@ impl FrostCoordinator {
         }))
     }
 
+    /// Swap `failed` out of an active session for `substitute`, another device in the same
+    /// access structure, when `failed` has been lost or refused to sign.
+    ///
+    /// The signer set is baked into the session's aggregate nonces, so the shares already
+    /// collected can't be carried over. Instead the session is abandoned and a replacement over
+    /// the same task is started with fresh nonces for every signer; the devices that had
+    /// already signed have to be asked again. Nothing changes unless the replacement can be
+    /// started. [`Self::sign_session_replaces`] links the replacement back to the abandoned
+    /// session.
+    pub fn substitute_signer(
+        &mut self,
+        session_id: SignSessionId,
+        failed: DeviceId,
+        substitute: DeviceId,
+        rng: &mut impl rand_core::RngCore,
+    ) -> Result<SignSessionId, SubstituteSignerError> {
+        let abandoned = self
+            .active_signing_sessions
+            .get(&session_id)
+            .ok_or(SubstituteSignerError::NoSuchSession)?;
+        if !abandoned.init.nonces.contains_key(&failed) {
+            return Err(SubstituteSignerError::NotASigner { device_id: failed });
+        }
+        if abandoned.has_received_from(failed) {
+            return Err(SubstituteSignerError::AlreadySigned { device_id: failed });
+        }
+        if abandoned.init.nonces.contains_key(&substitute) {
+            return Err(SubstituteSignerError::AlreadySigning {
+                device_id: substitute,
+            });
+        }
+
+        // Plan against the nonces as they'll be once the abandoned session is closed.
+        let mut nonce_cache = self.nonce_cache.clone();
+        abandoned.consume_sent_nonces(&mut nonce_cache);
+        let abandoned_streams = abandoned
+            .init
+            .nonces
+            .values()
+            .map(|nonces| nonces.stream_id)
+            .collect::<BTreeSet<_>>();
+        let used_streams = self
+            .all_used_nonce_streams()
+            .difference(&abandoned_streams)
+            .copied()
+            .collect();
+        let signing_devices = abandoned
+            .init
+            .nonces
+            .keys()
+            .copied()
+            .filter(|device_id| *device_id != failed)
+            .chain(core::iter::once(substitute))
+            .collect();
+
+        let replacement = self
+            .plan_sign_session(
+                &nonce_cache,
+                &used_streams,
+                abandoned.access_structure_ref(),
+                abandoned.init.group_request.sign_task.clone(),
+                &signing_devices,
+                rng,
+            )
+            .map_err(SubstituteSignerError::StartSign)?;
+        let replacement_id = replacement.session_id();
+
+        self.mutate(Mutation::Signing(SigningMutation::CloseSignSession {
+            session_id,
+            finished: None,
+        }));
+        self.mutate(Mutation::Signing(SigningMutation::NewSigningSession(
+            replacement,
+        )));
+        self.mutate(Mutation::Signing(SigningMutation::ReplacedSignSession {
+            abandoned: session_id,
+            replacement: replacement_id,
+        }));
+
+        Ok(replacement_id)
+    }
+
+    /// The devices that could take `failed`'s place in `session_id` with
+    /// [`Self::substitute_signer`]: those in the session's access structure that aren't already
+    /// signing and have a stream with enough nonces.
+    pub fn eligible_substitutes(
+        &self,
+        session_id: SignSessionId,
+        failed: DeviceId,
+    ) -> BTreeSet<DeviceId> {
+        let Some(session) = self.active_signing_sessions.get(&session_id) else {
+            return Default::default();
+        };
+        let Some(access_structure) = self.get_access_structure(session.access_structure_ref())
+        else {
+            return Default::default();
+        };
+        let n_signatures = session.init.group_request.n_signatures() as u32;
+        let used_streams = self.all_used_nonce_streams();
+        access_structure
+            .devices()
+            .filter(|device_id| *device_id != failed)
+            .filter(|device_id| !session.init.nonces.contains_key(device_id))
+            .filter(|device_id| !self.is_quarantined(*device_id))
+            .filter(|device_id| {
+                self.nonce_cache
+                    .nonces_available(*device_id, &used_streams)
+                    .values()
+                    .any(|&available| available >= n_signatures)
+            })
+            .collect()
+    }
+
+    /// The session `session_id` was started in place of by [`Self::substitute_signer`], if any.
+    pub fn sign_session_replaces(&self, session_id: SignSessionId) -> Option<SignSessionId> {
+        self.replaced_sign_sessions.get(&session_id).copied()
+    }
+
+    fn record_fault(&mut self, device_id: DeviceId, fault: faults::DeviceFault) {
+        event!(
+            Level::WARN,
+            device_id = device_id.to_string(),
+            "recording fault: {fault}"
+        );
+        self.mutate(Mutation::Fault(faults::FaultMutation::RecordFault {
+            device_id,
+            fault,
+        }));
+    }
+
+    /// Every device that has misbehaved or been quarantined.
+    pub fn device_faults(&self) -> &BTreeMap<DeviceId, faults::DeviceFaults> {
+        &self.device_faults
+    }
+
+    pub fn is_quarantined(&self, device_id: DeviceId) -> bool {
+        self.device_faults
+            .get(&device_id)
+            .map(|device_faults| device_faults.quarantined)
+            .unwrap_or(false)
+    }
+
+    /// Stop `device_id` being chosen to sign until it is released again. Its faults stay on
+    /// record either way.
+    pub fn set_device_quarantined(&mut self, device_id: DeviceId, quarantined: bool) {
+        self.mutate(Mutation::Fault(faults::FaultMutation::SetQuarantined {
+            device_id,
+            quarantined,
+        }));
+    }
+
     pub fn forget_finished_sign_session(
         &mut self,
         session_id: SignSessionId,
@@ -1684,6 +2339,21 @@ impl ActiveSignSession {
     pub fn session_id(&self) -> SignSessionId {
         self.init.group_request.session_id()
     }
+
+    /// Devices we've sent the request to may have used their nonces, so closing the session
+    /// has to move their streams past them whether or not they answered.
+    fn consume_sent_nonces(&self, nonce_cache: &mut NonceCache) {
+        let n_sigs = self.init.group_request.n_signatures();
+        for (device_id, nonce_segment) in &self.init.nonces {
+            if self.sent_req_to_device.contains(device_id) {
+                let consume_to = nonce_segment
+                    .index
+                    .checked_add(n_sigs as _)
+                    .expect("no overflow");
+                nonce_cache.consume(*device_id, nonce_segment.stream_id, consume_to);
+            }
+        }
+    }
 }
 
 #[derive(Clone, Debug, bincode::Encode, bincode::Decode, PartialEq)]
@@ -1847,6 +2517,7 @@ pub enum StartSignError {
     SignTask(SignTaskError),
     NoSuchAccessStructure,
     CouldntDecryptRootKey,
+    DeviceQuarantined { device_id: DeviceId },
 }
 
 impl fmt::Display for StartSignError {
@@ -1883,6 +2554,9 @@ impl fmt::Display for StartSignError {
                 "the access structure you wanted to sign with did not exist"
             ),
             StartSignError::CouldntDecryptRootKey => write!(f, "the decryption key did not"),
+            StartSignError::DeviceQuarantined { device_id } => {
+                write!(f, "device {device_id} is quarantined")
+            }
         }
     }
 }
@@ -1890,6 +2564,43 @@ impl fmt::Display for StartSignError {
 #[cfg(feature = "std")]
 impl std::error::Error for StartSignError {}
 
+#[derive(Debug, Clone)]
+pub enum SubstituteSignerError {
+    NoSuchSession,
+    NotASigner { device_id: DeviceId },
+    AlreadySigned { device_id: DeviceId },
+    AlreadySigning { device_id: DeviceId },
+    StartSign(StartSignError),
+}
+
+impl fmt::Display for SubstituteSignerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SubstituteSignerError::NoSuchSession => {
+                write!(f, "the signing session is not active")
+            }
+            SubstituteSignerError::NotASigner { device_id } => {
+                write!(f, "device {device_id} is not signing in this session")
+            }
+            SubstituteSignerError::AlreadySigned { device_id } => {
+                write!(
+                    f,
+                    "device {device_id} has already signed so does not need replacing"
+                )
+            }
+            SubstituteSignerError::AlreadySigning { device_id } => {
+                write!(f, "device {device_id} is already signing in this session")
+            }
+            SubstituteSignerError::StartSign(error) => {
+                write!(f, "couldn't start the replacement session: {error}")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SubstituteSignerError {}
+
 #[derive(Debug, Clone)]
 pub enum DeleteShareError {
     DeviceInActiveSignSession,
@@ -1920,6 +2631,8 @@ pub enum Mutation {
     Signing(signing::SigningMutation),
     #[delegate_kind]
     Restoration(restoration::RestorationMutation),
+    #[delegate_kind]
+    Fault(faults::FaultMutation),
 }
 
 impl Mutation {
@@ -1942,6 +2655,8 @@ impl Mutation {
             Mutation::Keygen(keys::KeyMutation::DeleteKey(key_id)) => *key_id,
             Mutation::Signing(inner) => inner.tied_to_key(coord)?,
             Mutation::Restoration(inner) => inner.tied_to_key()?,
+            // a device's record outlives the keys it misbehaved with
+            Mutation::Fault(_) => return None,
         })
     }
 
@@ -1971,6 +2686,9 @@ pub enum CoordinatorSend {
 
 pub struct NonceReplenishRequest {
     pub replenish_requests: BTreeMap<DeviceId, Vec<CoordNonceStreamState>>,
+    /// Devices we're still waiting to grant us a nonce partition. These have to be sent before the
+    /// streams are opened.
+    pub partition_claims: BTreeMap<DeviceId, NoncePartition>,
 }
 
 impl NonceReplenishRequest {
@@ -2000,14 +2718,23 @@ impl IntoIterator for NonceReplenishRequest {
     type Item = CoordinatorSend;
     type IntoIter = std::vec::IntoIter<CoordinatorSend>;
     fn into_iter(self) -> Self::IntoIter {
-        self.replenish_requests
+        let claims = self
+            .partition_claims
+            .into_iter()
+            .map(|(device_id, partition)| CoordinatorSend::ToDevice {
+                message: CoordinatorToDeviceMessage::Signing(
+                    crate::message::signing::CoordinatorSigning::ClaimNoncePartition { partition },
+                ),
+                destinations: [device_id].into(),
+            });
+        let opens = self
+            .replenish_requests
             .into_iter()
             .map(|(device_id, streams)| CoordinatorSend::ToDevice {
                 message: OpenNonceStreams { streams }.into(),
                 destinations: [device_id].into(),
-            })
-            .collect::<Vec<_>>()
-            .into_iter()
+            });
+        claims.chain(opens).collect::<Vec<_>>().into_iter()
     }
 }
 
@@ -2035,6 +2762,48 @@ impl IntoIterator for VerifyAddress {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct VerifyAccount {
+    pub rootkey: Point,
+    pub account: crate::tweak::BitcoinAccount,
+    pub target_devices: BTreeSet<DeviceId>,
+}
+
+impl IntoIterator for VerifyAccount {
+    type Item = CoordinatorSend;
+    type IntoIter = core::iter::Once<CoordinatorSend>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        core::iter::once(CoordinatorSend::ToDevice {
+            message: CoordinatorToDeviceMessage::ScreenVerify(
+                crate::message::screen_verify::ScreenVerify::VerifyAccount {
+                    rootkey: self.rootkey,
+                    account: self.account,
+                },
+            ),
+            destinations: self.target_devices,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UpdateAddressBook {
+    pub message: address_book::CoordinatorAddressBook,
+    pub target_devices: BTreeSet<DeviceId>,
+}
+
+impl IntoIterator for UpdateAddressBook {
+    type Item = CoordinatorSend;
+    type IntoIter = core::iter::Once<CoordinatorSend>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        core::iter::once(CoordinatorSend::ToDevice {
+            message: CoordinatorToDeviceMessage::AddressBook(self.message),
+            destinations: self.target_devices,
+        })
+    }
+}
+
 #[derive(Debug, Clone, bincode::Encode, bincode::Decode, PartialEq)]
 pub struct StartSign {
     pub nonces: BTreeMap<DeviceId, CoordNonceStreamState>,
@@ -2070,6 +2839,50 @@ impl IntoIterator for RequestDeviceSign {
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestDeviceMultiSign {
+    pub request_multi_sign: RequestMultiSign,
+    pub device_id: DeviceId,
+}
+
+impl IntoIterator for RequestDeviceMultiSign {
+    type Item = CoordinatorSend;
+    type IntoIter = core::iter::Once<CoordinatorSend>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        core::iter::once(CoordinatorSend::ToDevice {
+            message: CoordinatorToDeviceMessage::Signing(
+                crate::message::signing::CoordinatorSigning::RequestMultiSign(Box::new(
+                    self.request_multi_sign,
+                )),
+            ),
+            destinations: [self.device_id].into(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestDeviceSilentPaymentEcdh {
+    pub request: RequestSilentPaymentEcdh,
+    pub device_id: DeviceId,
+}
+
+impl IntoIterator for RequestDeviceSilentPaymentEcdh {
+    type Item = CoordinatorSend;
+    type IntoIter = core::iter::Once<CoordinatorSend>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        core::iter::once(CoordinatorSend::ToDevice {
+            message: CoordinatorToDeviceMessage::Signing(
+                crate::message::signing::CoordinatorSigning::RequestSilentPaymentEcdh(Box::new(
+                    self.request,
+                )),
+            ),
+            destinations: [self.device_id].into(),
+        })
+    }
+}
+
 #[derive(Clone, Debug, bincode::Encode, bincode::Decode)]
 pub struct SendBeginKeygen(pub keygen::Begin);
 
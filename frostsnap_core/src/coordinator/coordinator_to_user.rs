@@ -12,6 +12,14 @@ pub enum CoordinatorToUserMessage {
     ReplenishedNonces {
         device_id: DeviceId,
     },
+    /// The user of `device_id` confirmed a change to `key_id`'s address book. `label` is `None`
+    /// if `spk` was removed.
+    AddressBookUpdated {
+        device_id: DeviceId,
+        key_id: KeyId,
+        spk: bitcoin::ScriptBuf,
+        label: Option<String>,
+    },
 }
 
 impl Gist for CoordinatorToUserMessage {
@@ -30,6 +38,22 @@ pub enum CoordinatorToUserSigningMessage {
         session_id: SignSessionId,
         signatures: Vec<EncodedSignature>,
     },
+    /// A verified share of the ECDH with a silent payment recipient, already checked against the
+    /// device's share image.
+    SilentPaymentEcdhShare {
+        from: DeviceId,
+        access_structure_ref: AccessStructureRef,
+        scan: Point,
+        share: crate::silent_payments::EcdhShare,
+    },
+    /// A device answered a request passed on with [`FrostCoordinator::relay_sign_request`]. This
+    /// is the device's message as it was sent, to go back to the coordinator whose session it is.
+    RelayedSignatureShare {
+        session_id: SignSessionId,
+        from: DeviceId,
+        signature_shares: Vec<SignatureShare>,
+        replenish_nonces: Option<crate::nonce_stream::NonceStreamSegment>,
+    },
 }
 
 #[derive(Clone, Debug)]
@@ -0,0 +1,66 @@
+//! What devices have done wrong.
+//!
+//! When a device sends something the coordinator has to reject, the rejection alone is forgotten
+//! as soon as the error is logged. Faults that could only come from a broken or malicious device
+//! are recorded here as mutations so they survive restarts, and outlive the key or session they
+//! happened in. Only the first fault of each kind is kept, so a device repeating itself can't
+//! grow the record without bound. A device with faults can be quarantined so it isn't chosen to
+//! sign until it is released.
+use crate::{DeviceId, KeygenId, SignSessionId};
+use alloc::vec::Vec;
+use frostsnap_macros::Kind as KindDerive;
+
+#[derive(Clone, Debug, bincode::Encode, bincode::Decode, PartialEq, KindDerive)]
+pub enum FaultMutation {
+    RecordFault {
+        device_id: DeviceId,
+        fault: DeviceFault,
+    },
+    SetQuarantined {
+        device_id: DeviceId,
+        quarantined: bool,
+    },
+}
+
+/// Something a device sent that no honest device would.
+///
+/// Nonces that don't follow on from the ones we hold aren't here: an honest device that another
+/// coordinator has been using sends exactly that.
+#[derive(Clone, Copy, Debug, bincode::Encode, bincode::Decode, PartialEq, Eq)]
+pub enum DeviceFault {
+    /// A signature share that doesn't verify against the device's verification share.
+    InvalidSignatureShare { session_id: SignSessionId },
+    /// A keygen input the aggregator refused.
+    InvalidKeygenInput { keygen_id: KeygenId },
+    /// A keygen certificate that doesn't verify under the device's key.
+    InvalidKeygenCertificate { keygen_id: KeygenId },
+    /// An ack of a keygen transcript other than the one everyone certified.
+    InconsistentKeygenAck { keygen_id: KeygenId },
+}
+
+impl core::fmt::Display for DeviceFault {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            DeviceFault::InvalidSignatureShare { session_id } => {
+                write!(f, "invalid signature share in signing session {session_id}")
+            }
+            DeviceFault::InvalidKeygenInput { keygen_id } => {
+                write!(f, "invalid input to keygen {keygen_id}")
+            }
+            DeviceFault::InvalidKeygenCertificate { keygen_id } => {
+                write!(f, "invalid certificate for keygen {keygen_id}")
+            }
+            DeviceFault::InconsistentKeygenAck { keygen_id } => {
+                write!(f, "acked a different transcript for keygen {keygen_id}")
+            }
+        }
+    }
+}
+
+/// A device's record: the first fault of each kind in the order they happened, and whether it's
+/// been quarantined.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DeviceFaults {
+    pub faults: Vec<DeviceFault>,
+    pub quarantined: bool,
+}
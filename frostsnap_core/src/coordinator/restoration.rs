@@ -205,6 +205,8 @@ pub struct State {
     tmp_waiting_consolidate: BTreeSet<PendingConsolidation>,
 
     tmp_waiting_save: BTreeMap<(DeviceId, ShareImage), (RestorationId, HeldShare2)>,
+    /// Shares being moved from one device to another, keyed by the device receiving them.
+    tmp_share_migrations: BTreeMap<DeviceId, ShareMigration>,
 }
 
 impl State {
@@ -359,11 +361,15 @@ impl State {
 
         self.tmp_waiting_consolidate
             .retain(|consolidation| consolidation.access_structure_ref.key_id != key_id);
+
+        self.tmp_share_migrations
+            .retain(|_, migration| migration.access_structure_ref.key_id != key_id);
     }
 
     pub fn clear_tmp_data(&mut self) {
         self.tmp_waiting_consolidate.clear();
         self.tmp_waiting_save.clear();
+        self.tmp_share_migrations.clear();
     }
 }
 
@@ -878,6 +884,14 @@ impl FrostCoordinator {
                             },
                         ),
                     )])
+                } else if self
+                    .restoration
+                    .tmp_share_migrations
+                    .get(&from)
+                    .is_some_and(|migration| migration.share_image() == share_image)
+                {
+                    // the share is consolidated right after so there's nothing to tell the user yet
+                    Ok(vec![])
                 } else {
                     Err(Error::coordinator_invalid_message(
                         message.kind(),
@@ -909,6 +923,29 @@ impl FrostCoordinator {
                         device_id: from,
                         share_index,
                     }));
+
+                    let migration = self.restoration.tmp_share_migrations.get(&from);
+                    if let Some(migration) = migration.filter(|migration| {
+                        migration.access_structure_ref == access_structure_ref
+                            && migration.share_index == share_index
+                    }) {
+                        let old_device = migration.from;
+                        self.restoration.tmp_share_migrations.remove(&from);
+                        // The new device has taken over so the old one is no longer a signer.
+                        self.mutate(Mutation::Keygen(keys::KeyMutation::DeleteShare {
+                            access_structure_ref,
+                            device_id: old_device,
+                        }));
+                        return Ok(vec![CoordinatorSend::ToUser(
+                            ToUserRestoration::ShareMigrated {
+                                from: old_device,
+                                to: from,
+                                access_structure_ref,
+                                share_index,
+                            }
+                            .into(),
+                        )]);
+                    }
                 } else if self
                     .restoration
                     .pending_physical_consolidations
@@ -955,7 +992,194 @@ impl FrostCoordinator {
                 }
                 .into(),
             )]),
+            DeviceRestoration::ShareExported(ref exported) => {
+                let migration = self
+                    .restoration
+                    .tmp_share_migrations
+                    .get(&exported.to)
+                    .filter(|migration| {
+                        migration.from == from
+                            && migration.access_structure_ref == exported.access_structure_ref
+                            && migration.share_index == exported.share_index
+                    })
+                    .ok_or_else(|| {
+                        Error::coordinator_invalid_message(
+                            message.kind(),
+                            "not waiting for that share to be exported",
+                        )
+                    })?;
+
+                Ok(vec![
+                    CoordinatorSend::ToDevice {
+                        message: CoordinatorToDeviceMessage::Restoration(
+                            CoordinatorRestoration::ImportShare(Box::new(ImportShare {
+                                from,
+                                access_structure_ref: migration.access_structure_ref,
+                                share_index: migration.share_index,
+                                encrypted_share: exported.encrypted_share,
+                            })),
+                        ),
+                        destinations: [migration.to].into(),
+                    },
+                    CoordinatorSend::ToUser(
+                        ToUserRestoration::ShareExported {
+                            from,
+                            to: migration.to,
+                            access_structure_ref: migration.access_structure_ref,
+                        }
+                        .into(),
+                    ),
+                ])
+            }
+            DeviceRestoration::ShareImported(share_image) => {
+                let migration = self
+                    .restoration
+                    .tmp_share_migrations
+                    .get(&from)
+                    .filter(|migration| migration.share_image() == share_image)
+                    .ok_or_else(|| {
+                        Error::coordinator_invalid_message(
+                            message.kind(),
+                            "not waiting for that share to be imported",
+                        )
+                    })?
+                    .clone();
+
+                self.restoration
+                    .tmp_waiting_consolidate
+                    .insert(PendingConsolidation {
+                        device_id: from,
+                        access_structure_ref: migration.access_structure_ref,
+                        share_index: migration.share_index,
+                    });
+
+                let save = CoordinatorRestoration::SavePhysicalBackup2(Box::new(HeldShare2 {
+                    access_structure_ref: Some(migration.access_structure_ref),
+                    share_image,
+                    threshold: Some(migration.root_shared_key.threshold() as u16),
+                    key_name: Some(migration.key_name.clone()),
+                    purpose: Some(migration.purpose),
+                    needs_consolidation: true,
+                }));
+                let consolidate = TellDeviceConsolidateBackup {
+                    device_id: from,
+                    share_index: migration.share_index,
+                    root_shared_key: migration.root_shared_key,
+                    key_name: migration.key_name,
+                    purpose: migration.purpose,
+                };
+
+                Ok(core::iter::once(CoordinatorSend::ToDevice {
+                    message: CoordinatorToDeviceMessage::Restoration(save),
+                    destinations: [from].into(),
+                })
+                .chain(consolidate)
+                .collect())
+            }
+            DeviceRestoration::ShareImportAccepted(share_image) => {
+                let migration = self
+                    .restoration
+                    .tmp_share_migrations
+                    .get(&from)
+                    .filter(|migration| migration.share_image() == share_image)
+                    .ok_or_else(|| {
+                        Error::coordinator_invalid_message(
+                            message.kind(),
+                            "not waiting for that share to be accepted",
+                        )
+                    })?;
+
+                Ok(vec![CoordinatorSend::ToDevice {
+                    message: CoordinatorToDeviceMessage::Restoration(
+                        CoordinatorRestoration::ExportShare(Box::new(ExportShare {
+                            to: from,
+                            coord_share_decryption_contrib: migration
+                                .coord_share_decryption_contrib,
+                            share_index: migration.share_index,
+                            root_shared_key: migration.root_shared_key.clone(),
+                        })),
+                    ),
+                    destinations: [migration.from].into(),
+                }])
+            }
+        }
+    }
+
+    /// Start moving `from`'s share in `access_structure_ref` to the device `to`, e.g. because
+    /// `from` is being replaced. `to` is asked first and shows a code derived from the secret only
+    /// the two devices share. Only once its user accepts is `from` asked to export the share. It
+    /// shows the same code and encrypts the share so that only `to` can read it if its user
+    /// confirms the screens match. Once `to` has consolidated the share it replaces `from` as a
+    /// signer. `from` still has the share afterwards and should be wiped.
+    pub fn start_share_migration(
+        &mut self,
+        from: DeviceId,
+        to: DeviceId,
+        access_structure_ref: AccessStructureRef,
+        encryption_key: SymmetricKey,
+    ) -> Result<Vec<CoordinatorSend>, ActionError> {
+        let AccessStructureRef {
+            key_id,
+            access_structure_id,
+        } = access_structure_ref;
+        let key_data = self
+            .keys
+            .get(&key_id)
+            .ok_or(ActionError::StateInconsistent("no such key".into()))?;
+        let complete_key = &key_data.complete_key;
+        let access_structure = complete_key
+            .access_structures
+            .get(&access_structure_id)
+            .ok_or(ActionError::StateInconsistent(
+                "no such access structure".into(),
+            ))?;
+        if from == to {
+            return Err(ActionError::StateInconsistent(
+                "can't move a share to the device it's on".into(),
+            ));
+        }
+        let share_index = *access_structure.device_to_share_index.get(&from).ok_or(
+            ActionError::StateInconsistent("device does not have share in key".into()),
+        )?;
+        if access_structure.device_to_share_index.contains_key(&to) {
+            return Err(ActionError::StateInconsistent(
+                "the receiving device already has a share in key".into(),
+            ));
         }
+        let root_shared_key = complete_key
+            .root_shared_key(access_structure_id, encryption_key)
+            .ok_or(ActionError::StateInconsistent(
+                "couldn't decrypt root key".into(),
+            ))?;
+        let (_, coord_share_decryption_contrib) = complete_key
+            .coord_share_decryption_contrib(access_structure_id, from, encryption_key)
+            .ok_or(ActionError::StateInconsistent(
+                "couldn't decrypt root key".into(),
+            ))?;
+
+        let migration = ShareMigration {
+            from,
+            to,
+            access_structure_ref,
+            share_index,
+            root_shared_key: root_shared_key.clone(),
+            key_name: key_data.key_name.clone(),
+            purpose: key_data.purpose,
+            coord_share_decryption_contrib,
+        };
+        self.restoration.tmp_share_migrations.insert(to, migration);
+
+        Ok(vec![CoordinatorSend::ToDevice {
+            message: CoordinatorToDeviceMessage::Restoration(CoordinatorRestoration::ExpectShare(
+                Box::new(ExpectShare {
+                    from,
+                    share_index,
+                    root_shared_key,
+                    key_name: key_data.key_name.clone(),
+                }),
+            )),
+            destinations: [to].into(),
+        }])
     }
 
     /// The distinct wallets this device has physical backups waiting to be consolidated into.
@@ -1228,6 +1452,19 @@ pub enum ToUserRestoration {
         access_structure_ref: AccessStructureRef,
         share_index: ShareIndex,
     },
+    /// `from` has encrypted its share to `to`, which takes it over once its user has confirmed.
+    ShareExported {
+        from: DeviceId,
+        to: DeviceId,
+        access_structure_ref: AccessStructureRef,
+    },
+    /// `to` has taken over `from`'s share and replaced it as a signer.
+    ShareMigrated {
+        from: DeviceId,
+        to: DeviceId,
+        access_structure_ref: AccessStructureRef,
+        share_index: ShareIndex,
+    },
 }
 
 impl From<ToUserRestoration> for CoordinatorToUserMessage {
@@ -1245,6 +1482,24 @@ pub struct PendingConsolidation {
     pub share_index: ShareIndex,
 }
 
+#[derive(Clone, Debug, PartialEq)]
+struct ShareMigration {
+    from: DeviceId,
+    to: DeviceId,
+    access_structure_ref: AccessStructureRef,
+    share_index: ShareIndex,
+    root_shared_key: SharedKey,
+    key_name: String,
+    purpose: KeyPurpose,
+    coord_share_decryption_contrib: CoordShareDecryptionContrib,
+}
+
+impl ShareMigration {
+    fn share_image(&self) -> ShareImage {
+        self.root_shared_key.share_image(self.share_index)
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct PhysicalBackupPhase {
     pub backup: EnteredPhysicalBackup,
@@ -1,4 +1,7 @@
-use crate::{nonce_stream::NonceStreamSegment, DeviceId, KeyId, Kind, SignSessionId};
+use crate::{
+    nonce_stream::{NoncePartition, NonceStreamSegment},
+    DeviceId, KeyId, Kind, SignSessionId,
+};
 use alloc::vec::Vec;
 use frostsnap_macros::Kind as KindDerive;
 use schnorr_fun::frost::SignatureShare;
@@ -28,21 +31,34 @@ pub enum SigningMutation {
     ForgetFinishedSignSession {
         session_id: SignSessionId,
     },
+    /// `replacement` was started in place of `abandoned` after a signer dropped out.
+    ReplacedSignSession {
+        abandoned: SignSessionId,
+        replacement: SignSessionId,
+    },
+    /// `device_id` set aside `partition` of its nonce slots for us. From now on we only open and
+    /// use streams in it.
+    NewNoncePartition {
+        device_id: DeviceId,
+        partition: NoncePartition,
+    },
 }
 
 impl SigningMutation {
     pub fn tied_to_key(&self, coord: &FrostCoordinator) -> Option<KeyId> {
         match self {
-            SigningMutation::NewNonces { .. } => None,
+            SigningMutation::NewNonces { .. } | SigningMutation::NewNoncePartition { .. } => None,
             SigningMutation::NewSigningSession(active_sign_session) => {
                 Some(active_sign_session.key_id)
             }
             SigningMutation::SentSignReq { session_id, .. }
             | SigningMutation::GotSignatureSharesFromDevice { session_id, .. }
             | SigningMutation::CloseSignSession { session_id, .. }
-            | SigningMutation::ForgetFinishedSignSession { session_id } => {
-                Some(coord.get_sign_session(*session_id)?.key_id())
-            }
+            | SigningMutation::ForgetFinishedSignSession { session_id }
+            | SigningMutation::ReplacedSignSession {
+                replacement: session_id,
+                ..
+            } => Some(coord.get_sign_session(*session_id)?.key_id()),
         }
     }
 }
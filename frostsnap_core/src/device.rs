@@ -1,11 +1,12 @@
 use crate::device_nonces::{self, AbSlots, MemoryNonceSlot, NonceStreamSlot};
-use crate::nonce_stream::CoordNonceStreamState;
+use crate::nonce_stream::{CoordNonceStreamState, NoncePartition};
+use crate::silent_payments::{EcdhShare, SilentPaymentAddress};
 use crate::symmetric_encryption::{Ciphertext, SymmetricKey};
 use crate::tweak::{self, Xpub};
 use crate::{
     bitcoin_transaction, message::*, AccessStructureId, AccessStructureKind, AccessStructureRef,
     ActionError, CheckedSignTask, CoordShareDecryptionContrib, Error, KeyId, KeygenId, Kind,
-    MessageResult, RestorationId, SessionHash, ShareImage,
+    MessageResult, RestorationId, SessionHash, ShareImage, SignTask,
 };
 use crate::{DeviceId, SignSessionId};
 use alloc::boxed::Box;
@@ -18,6 +19,7 @@ use alloc::{
 use schnorr_fun::frost::chilldkg::certpedpop::{self};
 use schnorr_fun::frost::{Fingerprint, PairedSecretShare, SecretShare, ShareIndex, SharedKey};
 
+pub mod address_book;
 pub mod keys;
 use schnorr_fun::fun::KeyPair;
 use schnorr_fun::{frost, fun::prelude::*};
@@ -39,6 +41,7 @@ pub struct FrostSigner<S = MemoryNonceSlot> {
     mutations: VecDeque<Mutation>,
     keygen: keygen::State,
     restoration: restoration::State,
+    address_book: address_book::State,
     pub keygen_fingerprint: Fingerprint,
     pub nonce_batch_size: u32,
 }
@@ -139,17 +142,96 @@ impl EncryptedSecretShare {
     }
 }
 
+/// What the user is asked to confirm before signing: one session per key the coordinator asked
+/// this device to sign with, all over the same task.
 #[derive(Clone, Debug, PartialEq)]
 pub struct SignPhase1 {
+    keys: Vec<KeySignPhase>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct KeySignPhase {
+    key_name: String,
     group_sign_req: GroupSignReq<CheckedSignTask>,
     device_sign_req: DeviceSignReq,
     encrypted_secret_share: EncryptedSecretShare,
-    pub session_id: SignSessionId,
+    session_id: SignSessionId,
+    /// The key's address book as it was when the request came in.
+    address_book: BTreeMap<bitcoin::ScriptBuf, String>,
 }
 
 impl SignPhase1 {
+    /// The task as seen by the first key. With several keys the others' inputs and outputs show
+    /// up here as foreign, so a transaction is shown from [`Self::bitcoin_prompt`] instead.
     pub fn sign_task(&self) -> &CheckedSignTask {
-        &self.group_sign_req.sign_task
+        &self.keys[0].group_sign_req.sign_task
+    }
+
+    /// What to show the user for a bitcoin transaction, and the details for a full review, with
+    /// every key's view merged: an input or output is ours if any of the keys owns it, and with
+    /// more than one key each one's value at risk is listed. Both are labelled from the keys'
+    /// address books. `None` if the task isn't a transaction.
+    pub fn bitcoin_prompt(
+        &self,
+    ) -> Option<(
+        bitcoin_transaction::PromptSignBitcoinTx,
+        bitcoin_transaction::PromptTxDetails,
+    )> {
+        let mut network = None;
+        let views = self
+            .keys
+            .iter()
+            .map(|key| match &key.group_sign_req.sign_task.inner {
+                SignTask::BitcoinTransaction {
+                    tx_template,
+                    network: task_network,
+                } => {
+                    network = Some(*task_network);
+                    Some((key.key_name.as_str(), tx_template))
+                }
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()?;
+        let network = network?;
+        let mut prompt =
+            bitcoin_transaction::TransactionTemplate::joint_user_prompt(&views, network);
+        let address_books = self.address_books().collect::<Vec<_>>();
+        prompt.apply_address_books(&address_books);
+        let mut details = bitcoin_transaction::TransactionTemplate::joint_user_prompt_details(
+            &views.iter().map(|(_, view)| *view).collect::<Vec<_>>(),
+            network,
+        );
+        details.apply_address_books(&address_books);
+        Some((prompt, details))
+    }
+
+    pub fn sign_tasks(&self) -> impl Iterator<Item = &CheckedSignTask> + '_ {
+        self.keys.iter().map(|key| &key.group_sign_req.sign_task)
+    }
+
+    pub fn session_ids(&self) -> impl Iterator<Item = SignSessionId> + '_ {
+        self.keys.iter().map(|key| key.session_id)
+    }
+
+    /// Labels for the outputs the user may recognise, one book for each key being signed with.
+    pub fn address_books(
+        &self,
+    ) -> impl Iterator<Item = &BTreeMap<bitcoin::ScriptBuf, String>> + '_ {
+        self.keys.iter().map(|key| &key.address_book)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SilentPaymentEcdhPhase {
+    access_structure_ref: AccessStructureRef,
+    request: RequestSilentPaymentEcdh,
+    encrypted_secret_share: EncryptedSecretShare,
+}
+
+impl SilentPaymentEcdhPhase {
+    /// Who the user is agreeing to pay. This is what they must recognise before confirming.
+    pub fn address(&self) -> &SilentPaymentAddress {
+        &self.request.address
     }
 }
 
@@ -162,6 +244,7 @@ impl<S: NonceStreamSlot + core::fmt::Debug> FrostSigner<S> {
             mutations: Default::default(),
             keygen: Default::default(),
             restoration: Default::default(),
+            address_book: Default::default(),
             keygen_fingerprint: Fingerprint::FROST_V0,
             nonce_batch_size: NONCE_BATCH_SIZE,
         }
@@ -237,6 +320,12 @@ impl<S: NonceStreamSlot + core::fmt::Debug> FrostSigner<S> {
                     .apply_mutation_restoration(restoration_mutation)
                     .map(Mutation::Restoration);
             }
+            AddressBook(address_book_mutation) => {
+                return self
+                    .address_book
+                    .apply_mutation_address_book(address_book_mutation)
+                    .map(Mutation::AddressBook);
+            }
         }
 
         Some(mutation)
@@ -278,11 +367,29 @@ impl<S: NonceStreamSlot + core::fmt::Debug> FrostSigner<S> {
                     .streams
                     .iter()
                     .partition(|stream| self.nonce_slots.get(stream.stream_id).is_some());
+                let mut quotas = BTreeMap::<NoncePartition, usize>::new();
+                for stream in &open_nonce_stream.streams {
+                    let partition = stream.stream_id.partition();
+                    if !quotas.contains_key(&partition) {
+                        let quota = self.nonce_slots.partition_quota(partition);
+                        quotas.insert(partition, quota);
+                    }
+                }
                 let ordered_streams = existing
                     .into_iter()
                     .chain::<Vec<CoordNonceStreamState>>(new)
-                    // If we take more than the total available we risk overwriting slots
-                    .take(self.nonce_slots.total_slots());
+                    // If we take more than the partition's share we risk overwriting slots
+                    .filter(|stream| {
+                        let quota = quotas
+                            .get_mut(&stream.stream_id.partition())
+                            .expect("inserted above");
+                        *quota = match quota.checked_sub(1) {
+                            Some(quota) => quota,
+                            None => return false,
+                        };
+                        true
+                    })
+                    .collect::<Vec<_>>();
                 for coord_stream_state in ordered_streams {
                     let slot = self
                         .nonce_slots
@@ -309,73 +416,88 @@ impl<S: NonceStreamSlot + core::fmt::Debug> FrostSigner<S> {
                     ))])
                 }
             }
+            Signing(signing::CoordinatorSigning::ClaimNoncePartition { partition }) => {
+                let granted = !self.nonce_slots.partition_in_use(partition);
+                Ok(vec![DeviceSend::ToCoordinator(Box::new(
+                    DeviceToCoordinatorMessage::Signing(
+                        signing::DeviceSigning::NoncePartitionClaim { partition, granted },
+                    ),
+                ))])
+            }
             KeyGen(keygen_msg) => self.recv_keygen_message(keygen_msg, &message, rng),
             Signing(signing::CoordinatorSigning::RequestSign(request_sign)) => {
-                let self::RequestSign {
-                    group_sign_req,
-                    device_sign_req,
-                } = *request_sign;
-                let session_id = group_sign_req.session_id();
-                let key_id = KeyId::from_rootkey(device_sign_req.rootkey);
-                let key_data = self
-                    .keys
-                    .get(&key_id)
-                    .ok_or_else(|| {
-                        Error::signer_invalid_message(
-                            &message,
-                            format!("device doesn't have key for {key_id}"),
-                        )
-                    })?
-                    .clone();
-
-                let group_sign_req = group_sign_req
-                    .check(device_sign_req.rootkey, key_data.purpose)
-                    .map_err(|e| Error::signer_invalid_message(&message, e))?;
-
-                let GroupSignReq {
-                    parties,
-                    access_structure_id,
-                    ..
-                } = &group_sign_req;
-                let coord_req_nonces = device_sign_req.nonces;
-
-                let access_structure_data = key_data.access_structures.get(access_structure_id)
-                    .ok_or_else(|| {
-                        Error::signer_invalid_message(&message, format!("this device is not part of that access structure: {access_structure_id}"))
-                    })?.clone();
-                let (_, encrypted_secret_share) = parties
+                let phase = SignPhase1 {
+                    keys: vec![self.key_sign_phase(*request_sign, &message)?],
+                };
+                Ok(vec![DeviceSend::ToUser(Box::new(
+                    DeviceToUserMessage::SignatureRequest {
+                        phase: Box::new(phase),
+                    },
+                ))])
+            }
+            Signing(signing::CoordinatorSigning::RequestMultiSign(request_multi_sign)) => {
+                let keys = request_multi_sign
+                    .requests()
+                    .map(|request_sign| self.key_sign_phase(request_sign, &message))
+                    .collect::<Result<Vec<_>, _>>()?;
+                if keys.is_empty() {
+                    return Err(Error::signer_invalid_message(
+                        &message,
+                        "asked to sign with no keys",
+                    ));
+                }
+                // Each session consumes nonces from the start of its stream. Two of them sharing
+                // one would have the second refused only after the first was signed.
+                let streams = keys
                     .iter()
-                    .find_map(|party| Some((*party, *access_structure_data.shares.get(party)?)))
+                    .map(|key| key.device_sign_req.nonces.stream_id)
+                    .collect::<BTreeSet<_>>();
+                if streams.len() != keys.len() {
+                    return Err(Error::signer_invalid_message(
+                        &message,
+                        "two keys were asked to sign from the same nonce stream",
+                    ));
+                }
+                Ok(vec![DeviceSend::ToUser(Box::new(
+                    DeviceToUserMessage::SignatureRequest {
+                        phase: Box::new(SignPhase1 { keys }),
+                    },
+                ))])
+            }
+            Signing(signing::CoordinatorSigning::RequestSilentPaymentEcdh(request)) => {
+                let key_id = KeyId::from_rootkey(request.rootkey);
+                let key_data = self.keys.get(&key_id).ok_or_else(|| {
+                    Error::signer_invalid_message(
+                        &message,
+                        format!("device doesn't have key for {key_id}"),
+                    )
+                })?;
+                if key_data.purpose != KeyPurpose::Bitcoin(request.address.network) {
+                    return Err(Error::signer_invalid_message(
+                        &message,
+                        "silent payment address is not for this key's network",
+                    ));
+                }
+                let encrypted_secret_share = *key_data
+                    .access_structures
+                    .get(&request.access_structure_id)
+                    .and_then(|access_structure| access_structure.shares.get(&request.share_index))
                     .ok_or_else(|| {
                         Error::signer_invalid_message(
                             &message,
-                            "device doesn't have any of the shares requested",
+                            "device doesn't have the share requested",
                         )
                     })?;
-
-                // Just verify the nonce stream exists but don't check availability
-                // The signing logic will handle cached signatures naturally
-                let _nonce_slot = self
-                    .nonce_slots
-                    .get(coord_req_nonces.stream_id)
-                    .and_then(|slot| slot.read_slot())
-                    .ok_or(Error::signer_invalid_message(
-                        &message,
-                        format!(
-                            "device did not have that nonce stream id {}",
-                            coord_req_nonces.stream_id
-                        ),
-                    ))?;
-
-                // Removed are_nonces_available check - let the signing system handle it
-                let phase = SignPhase1 {
-                    group_sign_req,
-                    device_sign_req,
+                let phase = SilentPaymentEcdhPhase {
+                    access_structure_ref: AccessStructureRef {
+                        key_id,
+                        access_structure_id: request.access_structure_id,
+                    },
+                    request: *request,
                     encrypted_secret_share,
-                    session_id,
                 };
                 Ok(vec![DeviceSend::ToUser(Box::new(
-                    DeviceToUserMessage::SignatureRequest {
+                    DeviceToUserMessage::SilentPaymentEcdh {
                         phase: Box::new(phase),
                     },
                 ))])
@@ -417,52 +539,196 @@ impl<S: NonceStreamSlot + core::fmt::Debug> FrostSigner<S> {
                     },
                 ))])
             }
+            ScreenVerify(screen_verify::ScreenVerify::VerifyAccount { rootkey, account }) => {
+                let key_id = KeyId::from_rootkey(rootkey);
+                let key_data = self.keys.get(&key_id).ok_or_else(|| {
+                    Error::signer_invalid_message(
+                        &message,
+                        format!("device doesn't have key for {key_id}"),
+                    )
+                })?;
+                let network = key_data.purpose.bitcoin_network().ok_or_else(|| {
+                    Error::signer_invalid_message(&message, "key is not a bitcoin wallet")
+                })?;
+
+                // Derived the same way as the coordinator's wallet descriptor
+                let bitcoin_app_xpub = crate::MasterAppkey::derive_from_rootkey(rootkey)
+                    .derive_appkey(tweak::AppTweakKind::Bitcoin);
+                let account_xpub = bitcoin_app_xpub
+                    .derive_bip32(account.path_segments_from_bitcoin_appkey())
+                    .to_bitcoin_xpub_with_lies(network.into());
+
+                Ok(vec![DeviceSend::ToUser(Box::new(
+                    DeviceToUserMessage::VerifyAccount {
+                        key_name: key_data.key_name.clone(),
+                        account,
+                        master_fingerprint: bitcoin_app_xpub.fingerprint(),
+                        account_xpub,
+                    },
+                ))])
+            }
             Restoration(message) => self.recv_restoration_message(message, rng),
+            AddressBook(message) => self.recv_address_book_message(message),
         }
     }
 
+    /// Checks one key's signing request, finding the share this device signs it with.
+    fn key_sign_phase(
+        &self,
+        request_sign: RequestSign,
+        message: &CoordinatorToDeviceMessage,
+    ) -> MessageResult<KeySignPhase> {
+        let self::RequestSign {
+            group_sign_req,
+            device_sign_req,
+        } = request_sign;
+        let session_id = group_sign_req.session_id();
+        let key_id = KeyId::from_rootkey(device_sign_req.rootkey);
+        let key_data = self.keys.get(&key_id).ok_or_else(|| {
+            Error::signer_invalid_message(message, format!("device doesn't have key for {key_id}"))
+        })?;
+
+        let group_sign_req = group_sign_req
+            .check(device_sign_req.rootkey, key_data.purpose)
+            .map_err(|e| Error::signer_invalid_message(message, e))?;
+
+        let GroupSignReq {
+            parties,
+            access_structure_id,
+            ..
+        } = &group_sign_req;
+        let coord_req_nonces = device_sign_req.nonces;
+
+        let access_structure_data = key_data
+            .access_structures
+            .get(access_structure_id)
+            .ok_or_else(|| {
+                Error::signer_invalid_message(
+                    message,
+                    format!(
+                        "this device is not part of that access structure: {access_structure_id}"
+                    ),
+                )
+            })?;
+        let (_, encrypted_secret_share) = parties
+            .iter()
+            .find_map(|party| Some((*party, *access_structure_data.shares.get(party)?)))
+            .ok_or_else(|| {
+                Error::signer_invalid_message(
+                    message,
+                    "device doesn't have any of the shares requested",
+                )
+            })?;
+
+        // Just verify the nonce stream exists but don't check availability
+        // The signing logic will handle cached signatures naturally
+        let _nonce_slot = self
+            .nonce_slots
+            .get(coord_req_nonces.stream_id)
+            .and_then(|slot| slot.read_slot())
+            .ok_or(Error::signer_invalid_message(
+                message,
+                format!(
+                    "device did not have that nonce stream id {}",
+                    coord_req_nonces.stream_id
+                ),
+            ))?;
+
+        // Removed are_nonces_available check - let the signing system handle it
+        Ok(KeySignPhase {
+            key_name: key_data.key_name.clone(),
+            group_sign_req,
+            device_sign_req,
+            encrypted_secret_share,
+            session_id,
+            address_book: self
+                .address_book
+                .entries(key_id)
+                .map(|(spk, label)| (spk.to_owned(), label.into()))
+                .collect(),
+        })
+    }
+
     pub fn sign_ack(
         &mut self,
         phase: SignPhase1,
         symm_keygen: &mut impl DeviceSecretDerivation,
     ) -> Result<Vec<DeviceSend>, ActionError> {
-        let SignPhase1 {
+        // Every key is checked before any nonces are used so a key failing can't throw away the
+        // signature shares of the keys signed before it.
+        let mut ready = vec![];
+        for key in phase.keys {
+            let secret_share = self.key_sign_check(&key, symm_keygen)?;
+            ready.push((key, secret_share));
+        }
+
+        let mut sends = vec![];
+        for (key, secret_share) in ready {
+            sends.extend(self.key_sign_ack(key, secret_share, symm_keygen)?);
+        }
+        Ok(sends)
+    }
+
+    /// Decrypts the share `phase` signs with and checks its nonces are still there, without
+    /// using any.
+    fn key_sign_check(
+        &mut self,
+        phase: &KeySignPhase,
+        symm_keygen: &mut impl DeviceSecretDerivation,
+    ) -> Result<Scalar<Secret, Zero>, ActionError> {
+        let access_structure_ref = AccessStructureRef {
+            key_id: KeyId::from_rootkey(phase.device_sign_req.rootkey),
+            access_structure_id: phase.group_sign_req.access_structure_id,
+        };
+        let symmetric_key = symm_keygen.get_share_encryption_key(
+            access_structure_ref,
+            phase.encrypted_secret_share.share_image.index,
+            phase.device_sign_req.coord_share_decryption_contrib,
+        );
+        let secret_share = phase
+            .encrypted_secret_share
+            .ciphertext
+            .decrypt(symmetric_key)
+            .ok_or_else(|| {
+                ActionError::StateInconsistent("couldn't decrypt secret share".into())
+            })?;
+        self.nonce_slots
+            .check_can_sign(
+                phase.session_id,
+                phase.device_sign_req.nonces,
+                phase.group_sign_req.n_signatures() as u32,
+            )
+            .map_err(|e| ActionError::StateInconsistent(e.to_string()))?;
+        Ok(secret_share)
+    }
+
+    fn key_sign_ack(
+        &mut self,
+        phase: KeySignPhase,
+        secret_share: Scalar<Secret, Zero>,
+        symm_keygen: &mut impl DeviceSecretDerivation,
+    ) -> Result<Vec<DeviceSend>, ActionError> {
+        let KeySignPhase {
             group_sign_req:
                 GroupSignReq {
                     parties,
                     agg_nonces,
                     sign_task,
-                    access_structure_id,
+                    ..
                 },
             device_sign_req:
                 DeviceSignReq {
                     nonces: coord_nonce_state,
                     rootkey,
-                    coord_share_decryption_contrib,
+                    ..
                 },
             encrypted_secret_share,
             session_id,
+            ..
         } = phase;
 
         let sign_items = sign_task.sign_items();
-        let key_id = KeyId::from_rootkey(rootkey);
         let my_party_index = encrypted_secret_share.share_image.index;
-        let access_structure_ref = AccessStructureRef {
-            key_id,
-            access_structure_id,
-        };
-        let symmetric_key = symm_keygen.get_share_encryption_key(
-            access_structure_ref,
-            my_party_index,
-            coord_share_decryption_contrib,
-        );
-
-        let secret_share = encrypted_secret_share
-            .ciphertext
-            .decrypt(symmetric_key)
-            .ok_or_else(|| {
-                ActionError::StateInconsistent("couldn't decrypt secret share".into())
-            })?;
         let root_paired_secret_share = Xpub::from_rootkey(PairedSecretShare::new_unchecked(
             SecretShare {
                 index: my_party_index,
@@ -520,6 +786,53 @@ impl<S: NonceStreamSlot + core::fmt::Debug> FrostSigner<S> {
         ))])
     }
 
+    /// Answer a [`SilentPaymentEcdhPhase`] the user has confirmed.
+    pub fn silent_payment_ecdh_ack(
+        &mut self,
+        phase: SilentPaymentEcdhPhase,
+        symm_keygen: &mut impl DeviceSecretDerivation,
+    ) -> Result<Vec<DeviceSend>, ActionError> {
+        let SilentPaymentEcdhPhase {
+            access_structure_ref,
+            request,
+            encrypted_secret_share,
+        } = phase;
+        let symmetric_key = symm_keygen.get_share_encryption_key(
+            access_structure_ref,
+            request.share_index,
+            request.coord_share_decryption_contrib,
+        );
+        let secret_share = encrypted_secret_share
+            .ciphertext
+            .decrypt(symmetric_key)
+            .ok_or_else(|| {
+                ActionError::StateInconsistent("couldn't decrypt secret share".into())
+            })?;
+        let app_paired_secret_share = Xpub::from_rootkey(PairedSecretShare::new_unchecked(
+            SecretShare {
+                index: request.share_index,
+                share: secret_share,
+            },
+            request.rootkey,
+        ))
+        .rootkey_to_master_appkey();
+        let app_secret_share = app_paired_secret_share.key.secret_share();
+        let share = EcdhShare::new(
+            app_secret_share.index,
+            &app_secret_share.share,
+            request.address.scan,
+        )
+        .ok_or_else(|| ActionError::StateInconsistent("secret share is zero".into()))?;
+
+        Ok(vec![DeviceSend::ToCoordinator(Box::new(
+            DeviceToCoordinatorMessage::Signing(signing::DeviceSigning::SilentPaymentEcdhShare {
+                access_structure_ref,
+                scan: request.address.scan,
+                share,
+            }),
+        ))])
+    }
+
     fn save_complete_share(&mut self, phase: KeyGenPhase4) {
         self.mutate(Mutation::Keygen(keys::KeyMutation::NewKey {
             key_id: phase.access_structure_ref.key_id,
@@ -604,6 +917,8 @@ pub enum Mutation {
     Keygen(keys::KeyMutation),
     #[delegate_kind]
     Restoration(restoration::RestorationMutation),
+    #[delegate_kind]
+    AddressBook(address_book::AddressBookMutation),
 }
 
 pub trait DeviceSecretDerivation {
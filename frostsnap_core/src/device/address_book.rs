@@ -0,0 +1,237 @@
+//! Labels for addresses a wallet pays often, so the sign prompt can say who an output goes to
+//! rather than only where. Entries only get here once the wallet has signed them and the user has
+//! confirmed them on the device.
+use super::*;
+use crate::message::address_book::{self, CoordinatorAddressBook, DeviceAddressBook};
+use crate::{tweak::AppTweak, SignItem};
+use alloc::fmt::Debug;
+use bitcoin::{Script, ScriptBuf};
+use frostsnap_macros::Kind as KindDerive;
+
+/// Long enough for "Kraken deposit", short enough that "To: " and the label fit across the
+/// screen.
+pub const MAX_LABEL_LEN: usize = 20;
+/// How many entries the device keeps for a single wallet.
+pub const MAX_ENTRIES_PER_KEY: usize = 32;
+
+/// Whether `label` can be shown on the device. Only printable ASCII, since the device fonts don't
+/// have much else and a label is no use if it can be made to look like another one.
+pub fn check_label(label: &str) -> Result<(), &'static str> {
+    if label.trim().is_empty() {
+        return Err("address book label is empty");
+    }
+    if label.len() > MAX_LABEL_LEN {
+        return Err("address book label is too long");
+    }
+    if !label.chars().all(|c| c.is_ascii_graphic() || c == ' ') {
+        return Err("address book label has characters the device can't show");
+    }
+    Ok(())
+}
+
+#[derive(Clone, Debug, bincode::Encode, bincode::Decode, PartialEq, KindDerive)]
+pub enum AddressBookMutation {
+    Add {
+        key_id: KeyId,
+        label: String,
+        #[bincode(with_serde)]
+        spk: ScriptBuf,
+    },
+    Remove {
+        key_id: KeyId,
+        #[bincode(with_serde)]
+        spk: ScriptBuf,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct State {
+    entries: BTreeMap<KeyId, BTreeMap<ScriptBuf, String>>,
+}
+
+impl State {
+    pub fn apply_mutation_address_book(
+        &mut self,
+        mutation: AddressBookMutation,
+    ) -> Option<AddressBookMutation> {
+        match &mutation {
+            AddressBookMutation::Add { key_id, label, spk } => {
+                self.entries
+                    .entry(*key_id)
+                    .or_default()
+                    .insert(spk.clone(), label.clone());
+            }
+            AddressBookMutation::Remove { key_id, spk } => {
+                let entries = self.entries.get_mut(key_id)?;
+                entries.remove(spk)?;
+                if entries.is_empty() {
+                    self.entries.remove(key_id);
+                }
+            }
+        }
+        Some(mutation)
+    }
+
+    pub fn entries(&self, key_id: KeyId) -> impl Iterator<Item = (&Script, &str)> + '_ {
+        self.entries
+            .get(&key_id)
+            .into_iter()
+            .flatten()
+            .map(|(spk, label)| (spk.as_script(), label.as_str()))
+    }
+
+    pub fn label(&self, key_id: KeyId, spk: &Script) -> Option<&str> {
+        self.entries.get(&key_id)?.get(spk).map(String::as_str)
+    }
+}
+
+/// A change to a wallet's address book waiting on the user.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AddressBookPhase {
+    key_id: KeyId,
+    key_name: String,
+    address: bitcoin::Address,
+    /// What the entry will be labelled, or `None` if it's being removed.
+    label: Option<String>,
+    /// The label being removed or replaced.
+    old_label: Option<String>,
+}
+
+impl AddressBookPhase {
+    pub fn key_name(&self) -> &str {
+        &self.key_name
+    }
+
+    pub fn address(&self) -> &bitcoin::Address {
+        &self.address
+    }
+
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    pub fn old_label(&self) -> Option<&str> {
+        self.old_label.as_deref()
+    }
+}
+
+impl<S: Debug + NonceStreamSlot> FrostSigner<S> {
+    pub fn recv_address_book_message(
+        &mut self,
+        message: CoordinatorAddressBook,
+    ) -> MessageResult<Vec<DeviceSend>> {
+        let (key_id, spk) = match &message {
+            CoordinatorAddressBook::AddEntry {
+                master_appkey, spk, ..
+            } => (master_appkey.key_id(), spk),
+            CoordinatorAddressBook::RemoveEntry { key_id, spk } => (*key_id, spk),
+        };
+        let key_data = self.keys.get(&key_id).ok_or_else(|| {
+            Error::signer_invalid_message(&message, format!("device doesn't have key for {key_id}"))
+        })?;
+        let network = key_data.purpose.bitcoin_network().ok_or_else(|| {
+            Error::signer_invalid_message(&message, "only wallets have an address book")
+        })?;
+        // The user has to be able to check what they're labelling
+        let address = bitcoin::Address::from_script(spk, network).map_err(|_| {
+            Error::signer_invalid_message(&message, "script has no address to show")
+        })?;
+        let old_label = self.address_book.label(key_id, spk).map(String::from);
+
+        let label = match &message {
+            CoordinatorAddressBook::AddEntry {
+                master_appkey,
+                label,
+                signature,
+                ..
+            } => {
+                check_label(label).map_err(|e| Error::signer_invalid_message(&message, e))?;
+                // Our own user confirming isn't enough: a threshold of the wallet must have
+                // approved the entry, or one device could vouch for an address to all the others
+                let approval = SignItem {
+                    message: address_book::entry_message(label, spk),
+                    app_tweak: AppTweak::AddressBook,
+                };
+                let approved = signature.into_decoded().is_some_and(|signature| {
+                    approval.verify_final_signature(
+                        &schnorr_fun::Schnorr::<Sha256>::verify_only(),
+                        *master_appkey,
+                        &signature,
+                    )
+                });
+                if !approved {
+                    return Err(Error::signer_invalid_message(
+                        &message,
+                        "address book entry isn't signed by the wallet",
+                    ));
+                }
+                if old_label.is_none()
+                    && self.address_book.entries(key_id).count() >= MAX_ENTRIES_PER_KEY
+                {
+                    return Err(Error::signer_invalid_message(
+                        &message,
+                        "the address book for this wallet is full",
+                    ));
+                }
+                Some(label.clone())
+            }
+            CoordinatorAddressBook::RemoveEntry { .. } => {
+                if old_label.is_none() {
+                    return Err(Error::signer_invalid_message(
+                        &message,
+                        "that address isn't in the address book",
+                    ));
+                }
+                None
+            }
+        };
+
+        let phase = AddressBookPhase {
+            key_id,
+            key_name: key_data.key_name.clone(),
+            address,
+            label,
+            old_label,
+        };
+        Ok(vec![DeviceSend::ToUser(Box::new(
+            DeviceToUserMessage::AddressBook {
+                phase: Box::new(phase),
+            },
+        ))])
+    }
+
+    /// Make the change the user has confirmed.
+    pub fn address_book_ack(&mut self, phase: AddressBookPhase) -> Vec<DeviceSend> {
+        let AddressBookPhase {
+            key_id,
+            address,
+            label,
+            ..
+        } = phase;
+        let spk = address.script_pubkey();
+        self.mutate(Mutation::AddressBook(match &label {
+            Some(label) => AddressBookMutation::Add {
+                key_id,
+                label: label.clone(),
+                spk: spk.clone(),
+            },
+            None => AddressBookMutation::Remove {
+                key_id,
+                spk: spk.clone(),
+            },
+        }));
+
+        vec![DeviceSend::ToCoordinator(Box::new(
+            DeviceToCoordinatorMessage::AddressBook(DeviceAddressBook::Updated {
+                key_id,
+                spk,
+                label,
+            }),
+        ))]
+    }
+
+    /// The labels this device has for wallet `key_id`.
+    pub fn address_book(&self, key_id: KeyId) -> impl Iterator<Item = (&Script, &str)> + '_ {
+        self.address_book.entries(key_id)
+    }
+}
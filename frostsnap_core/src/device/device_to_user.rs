@@ -1,6 +1,6 @@
 use crate::device_nonces::NonceJobBatch;
-use bitcoin::{address::NetworkChecked, Address};
-use tweak::BitcoinBip32Path;
+use bitcoin::{address::NetworkChecked, bip32, Address};
+use tweak::{BitcoinAccount, BitcoinBip32Path};
 
 use super::*;
 /// Messages to the user often to ask them to confirm things. Often confirmations contain what we
@@ -17,10 +17,23 @@ pub enum DeviceToUserMessage {
     SignatureRequest {
         phase: Box<SignPhase1>,
     },
+    SilentPaymentEcdh {
+        phase: Box<SilentPaymentEcdhPhase>,
+    },
     VerifyAddress {
         address: Address<NetworkChecked>,
         bip32_path: BitcoinBip32Path,
     },
+    VerifyAccount {
+        key_name: String,
+        account: BitcoinAccount,
+        /// The fingerprint in the key origin of the wallet's descriptor.
+        master_fingerprint: bip32::Fingerprint,
+        account_xpub: bip32::Xpub,
+    },
     Restoration(Box<restoration::ToUserRestoration>),
+    AddressBook {
+        phase: Box<address_book::AddressBookPhase>,
+    },
     NonceJobs(NonceJobBatch),
 }
@@ -1,15 +1,27 @@
-use crate::message::HeldShare2;
+use crate::message::{ExportedShare, HeldShare2};
 use crate::EnterPhysicalId;
 use frost_backup::ShareBackup;
 use schnorr_fun::frost::SharedKey;
 
 use super::*;
 use alloc::fmt::Debug;
+use sha2::Digest;
 
 #[derive(Clone, Debug, PartialEq, Default)]
 pub struct State {
     tmp_loaded_backups: BTreeMap<ShareImage, ShareBackup>,
     saved_backups: BTreeMap<ShareImage, SavedBackup2>,
+    /// Shares we've been told to expect, keyed by the device sending them.
+    tmp_share_imports: BTreeMap<DeviceId, PendingShareImport>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct PendingShareImport {
+    access_structure_ref: AccessStructureRef,
+    share_index: ShareIndex,
+    root_shared_key: SharedKey,
+    accepted: bool,
+    share_backup: Option<ShareBackup>,
 }
 
 impl State {
@@ -38,6 +50,7 @@ impl State {
 
     pub fn clear_tmp_data(&mut self) {
         self.tmp_loaded_backups.clear();
+        self.tmp_share_imports.clear();
     }
 
     pub fn remove_backups_with_share_image(&mut self, share_image: ShareImage) {
@@ -322,6 +335,179 @@ impl<S: Debug + NonceStreamSlot> FrostSigner<S> {
                 ))])
             }
 
+            CoordinatorRestoration::ExportShare(export) => {
+                let access_structure_ref =
+                    AccessStructureRef::from_root_shared_key(&export.root_shared_key);
+                if export.to == self.device_id() {
+                    return Err(Error::signer_invalid_message(
+                        &message,
+                        "can't export a share to ourselves",
+                    ));
+                }
+                let key_name = self
+                    .keys
+                    .get(&access_structure_ref.key_id)
+                    .ok_or_else(|| {
+                        Error::signer_invalid_message(
+                            &message,
+                            "signer doesn't have a share for this key",
+                        )
+                    })?
+                    .key_name
+                    .clone();
+                let encrypted_secret_share = self
+                    .get_encrypted_share(access_structure_ref, export.share_index)
+                    .ok_or_else(|| {
+                        Error::signer_invalid_message(
+                            &message,
+                            "this device doesn't have that share",
+                        )
+                    })?
+                    .ciphertext;
+                let code = share_migration_code(
+                    &self.keypair,
+                    export.to,
+                    self.device_id(),
+                    export.to,
+                    access_structure_ref,
+                    export.share_index,
+                )
+                .ok_or_else(|| {
+                    Error::signer_invalid_message(&message, "the receiving device id is invalid")
+                })?;
+                let phase = ShareExportPhase {
+                    to: export.to,
+                    code,
+                    share: BackupDisplayPhase {
+                        access_structure_ref,
+                        share_index: export.share_index,
+                        encrypted_secret_share,
+                        coord_share_decryption_contrib: export.coord_share_decryption_contrib,
+                        key_name: key_name.clone(),
+                        root_shared_key: export.root_shared_key.clone(),
+                    },
+                };
+                Ok(vec![DeviceSend::ToUser(Box::new(
+                    DeviceToUserMessage::Restoration(Box::new(ExportShare {
+                        key_name,
+                        to: export.to,
+                        phase,
+                    })),
+                ))])
+            }
+            CoordinatorRestoration::ExpectShare(expect) => {
+                let access_structure_ref =
+                    AccessStructureRef::from_root_shared_key(&expect.root_shared_key);
+                if expect.from == self.device_id() {
+                    return Err(Error::signer_invalid_message(
+                        &message,
+                        "can't take over a share from ourselves",
+                    ));
+                }
+                if self
+                    .get_encrypted_share(access_structure_ref, expect.share_index)
+                    .is_some()
+                {
+                    return Err(Error::signer_invalid_message(
+                        &message,
+                        "this device already has that share",
+                    ));
+                }
+                // Worked out from our own key so a coordinator that swapped the devices can't make
+                // the two screens agree.
+                let code = share_migration_code(
+                    &self.keypair,
+                    expect.from,
+                    expect.from,
+                    self.device_id(),
+                    access_structure_ref,
+                    expect.share_index,
+                )
+                .ok_or_else(|| {
+                    Error::signer_invalid_message(&message, "the sending device id is invalid")
+                })?;
+                self.restoration.tmp_share_imports.insert(
+                    expect.from,
+                    PendingShareImport {
+                        access_structure_ref,
+                        share_index: expect.share_index,
+                        root_shared_key: expect.root_shared_key.clone(),
+                        accepted: false,
+                        share_backup: None,
+                    },
+                );
+                Ok(vec![DeviceSend::ToUser(Box::new(
+                    DeviceToUserMessage::Restoration(Box::new(ImportShare {
+                        key_name: expect.key_name.clone(),
+                        from: expect.from,
+                        phase: ShareImportPhase {
+                            from: expect.from,
+                            share_index: expect.share_index,
+                            code,
+                        },
+                    })),
+                ))])
+            }
+            CoordinatorRestoration::ImportShare(import) => {
+                let pending = self
+                    .restoration
+                    .tmp_share_imports
+                    .get(&import.from)
+                    .filter(|pending| {
+                        pending.accepted
+                            && pending.access_structure_ref == import.access_structure_ref
+                            && pending.share_index == import.share_index
+                    })
+                    .ok_or_else(|| {
+                        Error::signer_invalid_message(
+                            &message,
+                            "not expecting that share from that device",
+                        )
+                    })?;
+                let encryption_key = share_migration_key(
+                    &self.keypair,
+                    import.from,
+                    import.from,
+                    self.device_id(),
+                    import.access_structure_ref,
+                    import.share_index,
+                )
+                .ok_or_else(|| {
+                    Error::signer_invalid_message(&message, "the sending device id is invalid")
+                })?;
+                let share = import
+                    .encrypted_share
+                    .decrypt(encryption_key)
+                    .ok_or_else(|| {
+                        Error::signer_invalid_message(
+                            &message,
+                            "couldn't decrypt the share sent to us",
+                        )
+                    })?;
+                let secret_share = SecretShare {
+                    index: import.share_index,
+                    share,
+                };
+                if secret_share.share_image()
+                    != pending.root_shared_key.share_image(import.share_index)
+                {
+                    return Err(Error::signer_invalid_message(
+                        &message,
+                        "the share sent to us doesn't belong to the key",
+                    ));
+                }
+                let share_backup = ShareBackup::from_secret_share_and_shared_key(
+                    secret_share,
+                    &pending.root_shared_key,
+                );
+                let pending = self
+                    .restoration
+                    .tmp_share_imports
+                    .get_mut(&import.from)
+                    .expect("checked above");
+                pending.share_backup = Some(share_backup);
+                Ok(self.maybe_finish_share_import(import.from))
+            }
             CoordinatorRestoration::RequestHeldShares => {
                 let held_shares = self.held_shares().collect();
                 let send = Some(DeviceSend::ToCoordinator(Box::new(
@@ -359,6 +545,86 @@ impl<S: Debug + NonceStreamSlot> FrostSigner<S> {
         ret
     }
 
+    /// Encrypt the share the user has agreed to hand over to [`ShareExportPhase::to`]. Only that
+    /// device can decrypt it; the coordinator just passes it along.
+    pub fn export_share_ack(
+        &mut self,
+        phase: ShareExportPhase,
+        symm_keygen: &mut impl DeviceSecretDerivation,
+        rng: &mut impl rand_core::RngCore,
+    ) -> Result<Vec<DeviceSend>, ActionError> {
+        let ShareExportPhase { to, share, .. } = phase;
+        let secret_share = share.decrypt_secret_share(symm_keygen)?;
+        let encryption_key = share_migration_key(
+            &self.keypair,
+            to,
+            self.device_id(),
+            to,
+            share.access_structure_ref,
+            share.share_index,
+        )
+        .ok_or(ActionError::StateInconsistent(
+            "the receiving device id is invalid".into(),
+        ))?;
+        let encrypted_share = Ciphertext::encrypt(encryption_key, &secret_share.share, rng);
+
+        Ok(vec![DeviceSend::ToCoordinator(Box::new(
+            DeviceToCoordinatorMessage::Restoration(DeviceRestoration::ShareExported(Box::new(
+                ExportedShare {
+                    to,
+                    access_structure_ref: share.access_structure_ref,
+                    share_index: share.share_index,
+                    encrypted_share,
+                },
+            ))),
+        ))])
+    }
+
+    /// The user has agreed to take over the share. Only now is the sending device asked to export
+    /// it, and it shows the same code so the user can check it's talking to us. Once the share
+    /// arrives it's saved and consolidated just like a physical backup that was entered by hand.
+    pub fn import_share_ack(&mut self, phase: ShareImportPhase) -> Vec<DeviceSend> {
+        let share_image = match self.restoration.tmp_share_imports.get_mut(&phase.from) {
+            Some(pending) => {
+                pending.accepted = true;
+                pending.root_shared_key.share_image(pending.share_index)
+            }
+            None => return vec![],
+        };
+        let mut sends = vec![DeviceSend::ToCoordinator(Box::new(
+            DeviceToCoordinatorMessage::Restoration(DeviceRestoration::ShareImportAccepted(
+                share_image,
+            )),
+        ))];
+        sends.extend(self.maybe_finish_share_import(phase.from));
+        sends
+    }
+
+    fn maybe_finish_share_import(&mut self, from: DeviceId) -> Vec<DeviceSend> {
+        let ready = self
+            .restoration
+            .tmp_share_imports
+            .get(&from)
+            .is_some_and(|pending| pending.accepted && pending.share_backup.is_some());
+        if !ready {
+            return vec![];
+        }
+        let share_backup = self
+            .restoration
+            .tmp_share_imports
+            .remove(&from)
+            .and_then(|pending| pending.share_backup)
+            .expect("checked above");
+        let share_image = share_backup.share_image();
+        self.restoration
+            .tmp_loaded_backups
+            .insert(share_image, share_backup);
+
+        vec![DeviceSend::ToCoordinator(Box::new(
+            DeviceToCoordinatorMessage::Restoration(DeviceRestoration::ShareImported(share_image)),
+        ))]
+    }
+
     pub fn held_shares(&self) -> impl Iterator<Item = HeldShare2> + '_ {
         // Iterator over shares from keys with master access structures
         let keys_iter = self.keys.iter().flat_map(move |(key_id, key_data)| {
@@ -474,6 +740,66 @@ pub enum ToUserRestoration {
         access_structure_ref: AccessStructureRef,
         phase: BackupDisplayPhase,
     },
+    /// The user must agree to hand this device's share over to `to`.
+    ExportShare {
+        key_name: String,
+        to: DeviceId,
+        phase: ShareExportPhase,
+    },
+    /// The user must agree to this device taking over the share `from` holds.
+    ImportShare {
+        key_name: String,
+        from: DeviceId,
+        phase: ShareImportPhase,
+    },
+}
+
+#[derive(Clone, Debug)]
+pub struct ShareExportPhase {
+    pub to: DeviceId,
+    /// Must match the code [`ShareImportPhase::code`] shows on the receiving device.
+    pub code: ShareMigrationCode,
+    share: BackupDisplayPhase,
+}
+
+impl ShareExportPhase {
+    pub fn share_index(&self) -> ShareIndex {
+        self.share.share_index
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ShareImportPhase {
+    from: DeviceId,
+    share_index: ShareIndex,
+    /// Must match the code [`ShareExportPhase::code`] shows on the sending device.
+    pub code: ShareMigrationCode,
+}
+
+impl ShareImportPhase {
+    pub fn share_index(&self) -> ShareIndex {
+        self.share_index
+    }
+}
+
+/// What the user compares across the two devices in a share migration. Each device derives it
+/// from its own keypair and the id of the device it was told it's talking to, so the codes only
+/// match if the coordinator told both devices the truth about each other.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ShareMigrationCode(pub [u8; 20]);
+
+impl core::fmt::Display for ShareMigrationCode {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        for (i, chunk) in self.0.chunks(4).enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            for byte in chunk {
+                write!(f, "{byte:02x}")?;
+            }
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -497,6 +823,17 @@ impl BackupDisplayPhase {
         &self,
         symm_keygen: &mut impl DeviceSecretDerivation,
     ) -> Result<ShareBackup, ActionError> {
+        let secret = self.decrypt_secret_share(symm_keygen)?;
+        Ok(ShareBackup::from_secret_share_and_shared_key(
+            secret,
+            &self.root_shared_key,
+        ))
+    }
+
+    fn decrypt_secret_share(
+        &self,
+        symm_keygen: &mut impl DeviceSecretDerivation,
+    ) -> Result<SecretShare, ActionError> {
         let encryption_key = symm_keygen.get_share_encryption_key(
             self.access_structure_ref,
             self.share_index,
@@ -505,17 +842,85 @@ impl BackupDisplayPhase {
         let secret_share = self.encrypted_secret_share.decrypt(encryption_key).ok_or(
             ActionError::StateInconsistent("could not decrypt secret share".into()),
         )?;
-        let secret = SecretShare {
+        Ok(SecretShare {
             index: self.share_index,
             share: secret_share,
-        };
-        Ok(ShareBackup::from_secret_share_and_shared_key(
-            secret,
-            &self.root_shared_key,
-        ))
+        })
     }
 }
 
+/// The key a share moving from `from` to `to` is encrypted under: the Diffie-Hellman secret of
+/// the two devices' keypairs, so either device can derive it from the other's [`DeviceId`] and the
+/// coordinator in the middle can't. `None` if `peer` isn't a valid point, since falling back to
+/// some default point would make the key public.
+fn share_migration_key(
+    keypair: &KeyPair,
+    peer: DeviceId,
+    from: DeviceId,
+    to: DeviceId,
+    access_structure_ref: AccessStructureRef,
+    share_index: ShareIndex,
+) -> Option<SymmetricKey> {
+    let hash = share_migration_hash(
+        b"frostsnap/share-migration",
+        keypair,
+        peer,
+        from,
+        to,
+        access_structure_ref,
+        share_index,
+    )?;
+    Some(SymmetricKey(hash))
+}
+
+/// Hashes the same transcript as [`share_migration_key`] under its own tag so showing it gives
+/// nothing about the key away.
+fn share_migration_code(
+    keypair: &KeyPair,
+    peer: DeviceId,
+    from: DeviceId,
+    to: DeviceId,
+    access_structure_ref: AccessStructureRef,
+    share_index: ShareIndex,
+) -> Option<ShareMigrationCode> {
+    let hash = share_migration_hash(
+        b"frostsnap/share-migration-code",
+        keypair,
+        peer,
+        from,
+        to,
+        access_structure_ref,
+        share_index,
+    )?;
+    let mut code = [0u8; 20];
+    code.copy_from_slice(&hash[..20]);
+    Some(ShareMigrationCode(code))
+}
+
+fn share_migration_hash(
+    tag: &[u8],
+    keypair: &KeyPair,
+    peer: DeviceId,
+    from: DeviceId,
+    to: DeviceId,
+    access_structure_ref: AccessStructureRef,
+    share_index: ShareIndex,
+) -> Option<[u8; 32]> {
+    let peer = Point::<Normal, Public, NonZero>::from_bytes(peer.0)?;
+    let secret = keypair.secret_key();
+    let shared_secret = g!(secret * peer).normalize();
+    let hash = Sha256::default()
+        .chain_update(tag)
+        .chain_update(shared_secret.to_bytes())
+        .chain_update(from.0)
+        .chain_update(to.0)
+        .chain_update(access_structure_ref.key_id.0)
+        .chain_update(access_structure_ref.access_structure_id.0)
+        .chain_update(share_index.to_bytes())
+        .finalize();
+    Some(hash.into())
+}
+
 #[derive(Clone, Debug)]
 pub struct EnterBackupPhase {
     pub enter_physical_id: EnterPhysicalId,
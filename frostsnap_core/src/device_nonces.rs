@@ -1,9 +1,9 @@
 use crate::{
     device::DeviceSecretDerivation,
-    nonce_stream::{CoordNonceStreamState, NonceStreamId, NonceStreamSegment},
+    nonce_stream::{CoordNonceStreamState, NoncePartition, NonceStreamId, NonceStreamSegment},
     SignSessionId, Versioned,
 };
-use alloc::vec::Vec;
+use alloc::{collections::BTreeMap, vec::Vec};
 use chacha20::{
     cipher::{KeyIvInit, StreamCipher},
     ChaCha20,
@@ -356,48 +356,115 @@ impl<S: NonceStreamSlot> AbSlots<S> {
         Ok(out)
     }
 
+    /// Checks [`Self::sign_guaranteeing_nonces_destroyed`] could sign `n` items for `session_id`
+    /// without using any nonces.
+    pub fn check_can_sign(
+        &mut self,
+        session_id: SignSessionId,
+        coord_nonce_state: CoordNonceStreamState,
+        n: u32,
+    ) -> Result<(), NoncesUnavailable> {
+        let slot_value = self
+            .get(coord_nonce_state.stream_id)
+            .and_then(|slot| slot.read_slot())
+            .ok_or(NoncesUnavailable::Overflow)?; // Using Overflow as a placeholder for "stream not found"
+        match &slot_value.signing_state {
+            Some(SigningState {
+                session_id: saved_session_id,
+                ..
+            }) if *saved_session_id == session_id => Ok(()),
+            _ => slot_value.are_nonces_available(coord_nonce_state.index, n),
+        }
+    }
+
     fn increment_last_used(&mut self) -> u32 {
         self.last_used += 1;
         self.last_used
     }
 
     pub fn get_or_create(&mut self, stream_id: NonceStreamId, rng: &mut impl RngCore) -> &mut S {
-        // the algorithm is to find the first empty slot or to choose the one with the lowest `last_used`
-        let mut i = 0;
-        let mut lowest_last_used = u32::MAX;
-        let mut idx_lowest_last_used = 0;
+        // the algorithm is to find the first empty slot or to evict the least recently used slot.
+        // The eviction comes from the stream's own partition once it has its fair share of the
+        // slots, otherwise from the partition with the most slots so coordinators can't push each
+        // other's streams out.
         let last_used = self.increment_last_used();
-        let found = loop {
-            if i >= self.slots.len() {
-                break None;
-            }
-            let ab_slot = &mut self.slots[i];
-            let value = ab_slot.read_slot();
-            match value {
+        let mut occupied: Vec<(usize, NoncePartition, u32)> = Vec::with_capacity(self.slots.len());
+        for (i, ab_slot) in self.slots.iter_mut().enumerate() {
+            match ab_slot.read_slot() {
                 Some(value) => {
                     if value.nonce_stream_id == stream_id {
-                        break Some(i);
-                    } else if value.last_used < lowest_last_used {
-                        idx_lowest_last_used = i;
-                        lowest_last_used = value.last_used;
+                        return &mut self.slots[i];
                     }
+                    occupied.push((i, value.nonce_stream_id.partition(), value.last_used));
                 }
                 None => {
                     ab_slot.initialize(stream_id, last_used, rng);
-                    break Some(i);
+                    return &mut self.slots[i];
                 }
             }
-            i += 1;
+        }
+
+        let partition = stream_id.partition();
+        let mut counts = BTreeMap::<NoncePartition, usize>::new();
+        for (_, slot_partition, _) in &occupied {
+            *counts.entry(*slot_partition).or_default() += 1;
+        }
+        let ours = counts.get(&partition).copied().unwrap_or(0);
+        let evict_from = if ours >= Self::fair_share(self.slots.len(), &counts, partition) {
+            partition
+        } else {
+            // ties go to whichever holds the least recently used slot
+            counts
+                .iter()
+                .filter(|(slot_partition, _)| **slot_partition != partition)
+                .map(|(slot_partition, count)| {
+                    let oldest = occupied
+                        .iter()
+                        .filter(|(_, p, _)| p == slot_partition)
+                        .map(|(_, _, last_used)| *last_used)
+                        .min()
+                        .unwrap_or(u32::MAX);
+                    (*count, core::cmp::Reverse(oldest), *slot_partition)
+                })
+                .max()
+                .map(|(_, _, slot_partition)| slot_partition)
+                .unwrap_or(partition)
         };
 
-        match found {
-            Some(i) => &mut self.slots[i],
-            None => {
-                let ab_slot = &mut self.slots[idx_lowest_last_used];
-                ab_slot.initialize(stream_id, last_used, rng);
-                ab_slot
-            }
+        let (idx_lowest_last_used, _, _) = occupied
+            .iter()
+            .filter(|(_, slot_partition, _)| *slot_partition == evict_from)
+            .min_by_key(|(_, _, last_used)| *last_used)
+            .copied()
+            .expect("the partition we evict from holds a slot");
+        let ab_slot = &mut self.slots[idx_lowest_last_used];
+        ab_slot.initialize(stream_id, last_used, rng);
+        ab_slot
+    }
+
+    /// How many slots streams in `partition` can hold without taking them from another partition
+    /// that is within its own share.
+    pub fn partition_quota(&mut self, partition: NoncePartition) -> usize {
+        let mut counts = BTreeMap::<NoncePartition, usize>::new();
+        for stream_id in self.all_stream_ids() {
+            *counts.entry(stream_id.partition()).or_default() += 1;
         }
+        Self::fair_share(self.slots.len(), &counts, partition)
+    }
+
+    fn fair_share(
+        total_slots: usize,
+        counts: &BTreeMap<NoncePartition, usize>,
+        partition: NoncePartition,
+    ) -> usize {
+        let partitions = counts.len() + usize::from(!counts.contains_key(&partition));
+        (total_slots / partitions).max(1)
+    }
+
+    /// Whether any slot holds a stream in `partition`.
+    pub fn partition_in_use(&mut self, partition: NoncePartition) -> bool {
+        self.all_stream_ids()
+            .any(|stream_id| partition.contains(stream_id))
     }
 
     pub fn get(&mut self, stream_id: NonceStreamId) -> Option<&mut S> {
@@ -12,6 +12,7 @@ mod master_appkey;
 pub mod message;
 pub mod nonce_stream;
 pub mod nostr;
+pub mod silent_payments;
 pub mod tweak;
 
 use core::ops::RangeBounds;
@@ -33,6 +34,7 @@ pub mod coordinator;
 pub mod device;
 pub use schnorr_fun;
 pub mod bitcoin_transaction;
+pub mod proof_of_reserves;
 /// Reading a PSBT needs std and tracing, and no device ever does it.
 #[cfg(feature = "coordinator")]
 pub mod psbt;
@@ -1,5 +1,6 @@
 use crate::device::KeyPurpose;
 use crate::nonce_stream::CoordNonceStreamState;
+use crate::symmetric_encryption::Ciphertext;
 use crate::{
     AccessStructureId, AccessStructureRef, CheckedSignTask, CoordShareDecryptionContrib, Gist,
     KeygenId, MasterAppkey, SessionHash, ShareImage, SignSessionId, SignTaskError, Vec,
@@ -20,6 +21,7 @@ use schnorr_fun::Signature;
 use sha2::digest::Update;
 use sha2::Digest;
 
+pub mod address_book;
 pub mod keygen;
 pub mod screen_verify;
 pub mod signing;
@@ -41,6 +43,8 @@ pub enum CoordinatorToDeviceMessage {
     Restoration(CoordinatorRestoration),
     #[delegate_kind]
     ScreenVerify(screen_verify::ScreenVerify),
+    #[delegate_kind]
+    AddressBook(address_book::CoordinatorAddressBook),
 }
 
 #[derive(Clone, Debug, bincode::Encode, bincode::Decode, Kind)]
@@ -73,6 +77,48 @@ pub enum CoordinatorRestoration {
         share_index: ShareIndex,
         root_shared_key: SharedKey,
     },
+    /// Encrypt our share to another device so it can take over from us. Only sent once the
+    /// receiving device's user has accepted the [`ExpectShare`].
+    ///
+    /// [`ExpectShare`]: CoordinatorRestoration::ExpectShare
+    ExportShare(Box<ExportShare>),
+    /// Get ready to take over a share that another device will be asked to [`ExportShare`] to us
+    /// once our user accepts.
+    ///
+    /// [`ExportShare`]: CoordinatorRestoration::ExportShare
+    ExpectShare(Box<ExpectShare>),
+    /// The share another device encrypted to us after [`ExpectShare`].
+    ///
+    /// [`ExpectShare`]: CoordinatorRestoration::ExpectShare
+    ImportShare(Box<ImportShare>),
+}
+
+#[derive(Clone, Debug, bincode::Encode, bincode::Decode, PartialEq)]
+pub struct ExportShare {
+    /// The device the share is being moved to.
+    pub to: DeviceId,
+    pub coord_share_decryption_contrib: CoordShareDecryptionContrib,
+    pub share_index: ShareIndex,
+    pub root_shared_key: SharedKey,
+}
+
+#[derive(Clone, Debug, bincode::Encode, bincode::Decode, PartialEq)]
+pub struct ExpectShare {
+    /// The device the share is being moved from.
+    pub from: DeviceId,
+    pub share_index: ShareIndex,
+    pub root_shared_key: SharedKey,
+    pub key_name: String,
+}
+
+#[derive(Clone, Debug, bincode::Encode, bincode::Decode, PartialEq)]
+pub struct ImportShare {
+    /// The device the share is being moved from.
+    pub from: DeviceId,
+    pub access_structure_ref: AccessStructureRef,
+    pub share_index: ShareIndex,
+    /// The secret share encrypted under the key only `from` and the receiving device can derive.
+    pub encrypted_share: Ciphertext<32, Scalar<Secret, Zero>>,
 }
 
 #[derive(Clone, Debug, bincode::Encode, bincode::Decode, PartialEq)]
@@ -132,6 +178,8 @@ pub enum DeviceToCoordinatorMessage {
     Signing(signing::DeviceSigning),
     #[delegate_kind]
     Restoration(DeviceRestoration),
+    #[delegate_kind]
+    AddressBook(address_book::DeviceAddressBook),
 }
 
 #[derive(Clone, Debug, bincode::Encode, bincode::Decode, Kind)]
@@ -144,6 +192,23 @@ pub enum DeviceRestoration {
     },
     HeldShares(Vec<HeldShare>),
     HeldShares2(Vec<HeldShare2>),
+    ShareExported(Box<ExportedShare>),
+    /// The share from an [`ImportShare`] was accepted and is waiting to be saved like a physical
+    /// backup that was just entered.
+    ShareImported(ShareImage),
+    /// The user accepted the [`ExpectShare`] for this share so the sending device can be asked to
+    /// export it.
+    ///
+    /// [`ExpectShare`]: CoordinatorRestoration::ExpectShare
+    ShareImportAccepted(ShareImage),
+}
+
+#[derive(Clone, Debug, bincode::Encode, bincode::Decode, PartialEq)]
+pub struct ExportedShare {
+    pub to: DeviceId,
+    pub access_structure_ref: AccessStructureRef,
+    pub share_index: ShareIndex,
+    pub encrypted_share: Ciphertext<32, Scalar<Secret, Zero>>,
 }
 
 #[derive(Clone, Debug, bincode::Encode, bincode::Decode, PartialEq)]
@@ -237,6 +302,54 @@ pub struct DeviceSignReq {
     pub coord_share_decryption_contrib: CoordShareDecryptionContrib,
 }
 
+/// Signing requests for several of our keys over one task, e.g. a transaction spending coins from
+/// more than one wallet. A device holding shares in more than one of them signs everything after
+/// a single confirmation.
+///
+/// Each key is still its own session, with its own nonce stream, so this is only a way of
+/// delivering the [`RequestSign`]s together with the task sent once. The device sees in
+/// [`Self::requests`] exactly what it would have been sent one at a time.
+#[derive(Debug, Clone, bincode::Encode, bincode::Decode, PartialEq)]
+pub struct RequestMultiSign {
+    pub sign_task: WireSignTask,
+    pub keys: Vec<KeySignReq>,
+}
+
+/// One key's part of a [`RequestMultiSign`]: a [`RequestSign`] without the task.
+#[derive(Debug, Clone, bincode::Encode, bincode::Decode, PartialEq)]
+pub struct KeySignReq {
+    pub parties: BTreeSet<ShareIndex>,
+    pub agg_nonces: Vec<binonce::Nonce<Zero>>,
+    pub access_structure_id: AccessStructureId,
+    pub device_sign_req: DeviceSignReq,
+}
+
+impl RequestMultiSign {
+    pub fn requests(&self) -> impl Iterator<Item = RequestSign> + '_ {
+        self.keys.iter().map(|key| RequestSign {
+            group_sign_req: GroupSignReq {
+                parties: key.parties.clone(),
+                agg_nonces: key.agg_nonces.clone(),
+                sign_task: self.sign_task.clone(),
+                access_structure_id: key.access_structure_id,
+            },
+            device_sign_req: key.device_sign_req.clone(),
+        })
+    }
+}
+
+/// Ask a device for its share of the ECDH with a silent payment recipient's scan key. See
+/// [`crate::silent_payments`] for why this is a round of its own.
+#[derive(Debug, Clone, bincode::Encode, bincode::Decode, PartialEq)]
+pub struct RequestSilentPaymentEcdh {
+    pub access_structure_id: AccessStructureId,
+    pub share_index: ShareIndex,
+    pub address: crate::silent_payments::SilentPaymentAddress,
+    /// Semi secret, as in [`DeviceSignReq`].
+    pub rootkey: Point,
+    pub coord_share_decryption_contrib: CoordShareDecryptionContrib,
+}
+
 #[derive(Clone, Debug, bincode::Encode, bincode::Decode)]
 pub struct KeyGenAck {
     pub ack_session_hash: SessionHash,
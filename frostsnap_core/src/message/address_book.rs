@@ -0,0 +1,50 @@
+use super::EncodedSignature;
+use crate::{KeyId, Kind, MasterAppkey};
+use alloc::{string::String, vec::Vec};
+use bitcoin::{Script, ScriptBuf};
+use frostsnap_macros::Kind as KindDerive;
+
+/// Changes to the address book a device keeps for a wallet. The device shows each one to the user
+/// and only makes it once they've confirmed it. Adding an entry also needs the wallet's signature
+/// over it, so a threshold of devices must have approved it by signing a
+/// [`WireSignTask::AddressBookEntry`](crate::WireSignTask::AddressBookEntry) first. Removing one
+/// can only make the sign prompt more cautious so the device's own user is enough.
+#[derive(Clone, Debug, bincode::Encode, bincode::Decode, KindDerive)]
+pub enum CoordinatorAddressBook {
+    /// Label payments to `spk`, replacing any label it already has.
+    AddEntry {
+        master_appkey: MasterAppkey,
+        label: String,
+        #[bincode(with_serde)]
+        spk: ScriptBuf,
+        /// The wallet's signature over [`entry_message`].
+        signature: EncodedSignature,
+    },
+    RemoveEntry {
+        key_id: KeyId,
+        #[bincode(with_serde)]
+        spk: ScriptBuf,
+    },
+}
+
+#[derive(Clone, Debug, bincode::Encode, bincode::Decode, KindDerive)]
+pub enum DeviceAddressBook {
+    /// The user confirmed the change and the device has saved it. `label` is `None` when the entry
+    /// was removed.
+    Updated {
+        key_id: KeyId,
+        #[bincode(with_serde)]
+        spk: ScriptBuf,
+        label: Option<String>,
+    },
+}
+
+/// What the wallet signs to approve labelling `spk` with `label`.
+pub fn entry_message(label: &str, spk: &Script) -> Vec<u8> {
+    let mut message = Vec::with_capacity(1 + label.len() + spk.len());
+    // labels are at most `MAX_LABEL_LEN` long so the length fits in a byte
+    message.push(label.len() as u8);
+    message.extend_from_slice(label.as_bytes());
+    message.extend_from_slice(spk.as_bytes());
+    message
+}
@@ -1,5 +1,9 @@
-use crate::{tweak::NormalIndex, Kind, MasterAppkey};
+use crate::{
+    tweak::{BitcoinAccount, NormalIndex},
+    Kind, MasterAppkey,
+};
 use frostsnap_macros::Kind as KindDerive;
+use schnorr_fun::fun::Point;
 
 /// Screen verification messages (for verifying addresses on device screens)
 #[derive(Clone, Debug, bincode::Encode, bincode::Decode, KindDerive)]
@@ -8,4 +12,11 @@ pub enum ScreenVerify {
         master_appkey: MasterAppkey,
         derivation_index: NormalIndex,
     },
+    /// Show the master fingerprint and xpub of `account` so the user can check them against the
+    /// descriptor another wallet is watching. The rootkey rather than the master appkey is sent so
+    /// the device checks the key against the one it holds a share of.
+    VerifyAccount {
+        rootkey: Point,
+        account: BitcoinAccount,
+    },
 }
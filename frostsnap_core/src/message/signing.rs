@@ -1,10 +1,11 @@
 use crate::{
-    nonce_stream::{CoordNonceStreamState, NonceStreamSegment},
-    Kind, SignSessionId,
+    nonce_stream::{CoordNonceStreamState, NoncePartition, NonceStreamSegment},
+    silent_payments::EcdhShare,
+    AccessStructureRef, Kind, SignSessionId,
 };
 use alloc::{boxed::Box, vec::Vec};
 use frostsnap_macros::Kind as KindDerive;
-use schnorr_fun::frost::SignatureShare;
+use schnorr_fun::{frost::SignatureShare, fun::Point};
 
 /// A request to open one or more nonce streams
 #[derive(Clone, Debug, bincode::Encode, bincode::Decode)]
@@ -29,6 +30,13 @@ impl OpenNonceStreams {
 pub enum CoordinatorSigning {
     RequestSign(Box<super::RequestSign>),
     OpenNonceStreams(OpenNonceStreams),
+    RequestSilentPaymentEcdh(Box<super::RequestSilentPaymentEcdh>),
+    RequestMultiSign(Box<super::RequestMultiSign>),
+    /// Ask the device to set aside a partition of its nonce slots for us. Sent before we open
+    /// streams on a device for the first time.
+    ClaimNoncePartition {
+        partition: NoncePartition,
+    },
 }
 
 /// Device to coordinator signing messages  
@@ -42,6 +50,17 @@ pub enum DeviceSigning {
         signature_shares: Vec<SignatureShare>,
         replenish_nonces: Option<NonceStreamSegment>,
     },
+    SilentPaymentEcdhShare {
+        access_structure_ref: AccessStructureRef,
+        scan: Point,
+        share: EcdhShare,
+    },
+    NoncePartitionClaim {
+        partition: NoncePartition,
+        /// False if the device already has streams in the partition, i.e. another coordinator
+        /// picked the same one.
+        granted: bool,
+    },
 }
 
 impl From<DeviceSigning> for super::DeviceToCoordinatorMessage {
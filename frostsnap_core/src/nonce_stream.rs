@@ -164,6 +164,58 @@ impl NonceStreamId {
         rng.fill_bytes(&mut bytes);
         NonceStreamId(bytes)
     }
+
+    /// A random stream id that falls in `partition`.
+    pub fn random_in(partition: NoncePartition, rng: &mut impl rand_core::RngCore) -> Self {
+        let mut stream_id = Self::random(rng);
+        stream_id.0[..4].copy_from_slice(&partition.0);
+        stream_id
+    }
+
+    pub fn partition(&self) -> NoncePartition {
+        NoncePartition(self.0[..4].try_into().expect("correct length"))
+    }
+}
+
+/// The share of a device's nonce slots that belongs to one coordinator. A stream's partition is
+/// the first four bytes of its id, so the device doesn't have to remember who opened what: it just
+/// makes sure that opening a stream in one partition doesn't evict streams of another partition
+/// that is within its fair share of the slots.
+///
+/// Streams opened before partitions existed have random prefixes so each ends up in a partition of
+/// its own.
+#[derive(Clone, Copy, PartialEq, PartialOrd, Ord, Eq, Hash)]
+pub struct NoncePartition(pub [u8; 4]);
+
+impl NoncePartition {
+    /// The partition `coordinator_id` claims on `device_id`. It's the same every time, so a claim
+    /// that hasn't been granted yet is just made again.
+    pub fn for_coordinator(coordinator_id: [u8; 32], device_id: crate::DeviceId) -> Self {
+        use sha2::Digest;
+        let hash = sha2::Sha256::new()
+            .chain_update(b"frostsnap/nonce-partition")
+            .chain_update(coordinator_id)
+            .chain_update(device_id.0)
+            .finalize();
+        NoncePartition(hash[..4].try_into().expect("correct length"))
+    }
+
+    pub fn contains(&self, stream_id: NonceStreamId) -> bool {
+        stream_id.partition() == *self
+    }
+}
+
+crate::impl_display_debug_serialize! {
+    fn to_bytes(partition: &NoncePartition) -> [u8;4] {
+        partition.0
+    }
+}
+
+crate::impl_fromstr_deserialize! {
+    name => "nonce partition",
+    fn from_bytes(bytes: [u8;4]) -> NoncePartition {
+        NoncePartition(bytes)
+    }
 }
 
 crate::impl_display_debug_serialize! {
@@ -0,0 +1,109 @@
+//! [BIP-127] proofs of reserves.
+//!
+//! A proof is a transaction spending every coin being proven plus one extra input, the
+//! commitment, placed first. The commitment's prevout txid is the hash of the message being
+//! committed to, so no transaction has it and the proof can never be mined. Every other input
+//! still carries an ordinary `SIGHASH_ALL` signature, which commits to the commitment input too,
+//! so none of them can be lifted into a transaction that spends.
+//!
+//! [BIP-127]: https://github.com/bitcoin/bips/blob/master/bip-0127.mediawiki
+use crate::bitcoin_transaction::{PushInput, SpkOwner, TransactionTemplate};
+use alloc::vec::Vec;
+use bitcoin::{
+    hashes::{sha256d, Hash},
+    Amount, OutPoint, ScriptBuf, TxOut, Txid,
+};
+
+/// Prepended to the message before it is hashed into the commitment's txid.
+pub const MESSAGE_PREFIX: &str = "Proof-of-Reserves: ";
+
+/// The prevout the commitment input spends: `sha256d(MESSAGE_PREFIX || message):0`.
+pub fn commitment_outpoint(message: &str) -> OutPoint {
+    let mut preimage = Vec::with_capacity(MESSAGE_PREFIX.len() + message.len());
+    preimage.extend_from_slice(MESSAGE_PREFIX.as_bytes());
+    preimage.extend_from_slice(message.as_bytes());
+    OutPoint {
+        txid: Txid::from_raw_hash(sha256d::Hash::hash(&preimage)),
+        vout: 0,
+    }
+}
+
+/// What the commitment's prevout is taken to be. It doesn't exist, but a taproot sighash commits
+/// to every prevout's amount and script so the signer and the verifier have to agree on one.
+/// Zero sats to `OP_TRUE`, as other implementations use.
+pub fn commitment_txout() -> TxOut {
+    TxOut {
+        value: Amount::ZERO,
+        script_pubkey: ScriptBuf::from_bytes(alloc::vec![0x51]),
+    }
+}
+
+/// The proof's single output. `OP_RETURN` so that even a proof that somehow became valid would
+/// pay nobody.
+pub fn proof_output_script() -> ScriptBuf {
+    ScriptBuf::from_bytes(alloc::vec![0x6a])
+}
+
+/// Start a proof of reserves for `message`: a template holding only the commitment input. Push
+/// the coins being proven after it and finish with [`push_proof_output`].
+pub fn proof_template(message: &str) -> TransactionTemplate {
+    let mut template = TransactionTemplate::new();
+    let txout = commitment_txout();
+    template.push_foreign_input(PushInput::spend_outpoint(
+        &txout,
+        commitment_outpoint(message),
+    ));
+    template
+}
+
+/// Pay everything the proof's inputs hold to its unspendable output, so the proof claims no fee.
+pub fn push_proof_output(template: &mut TransactionTemplate) {
+    let value = template
+        .inputs()
+        .iter()
+        .map(|input| input.txout().value)
+        .sum::<Amount>();
+    template.push_foreign_output(TxOut {
+        value,
+        script_pubkey: proof_output_script(),
+    });
+}
+
+/// Whether the template opens with the commitment to `message`, i.e. whether it's a proof that
+/// can never be valid on chain.
+pub fn commits_to<S>(template: &TransactionTemplate<S>, message: &str) -> bool {
+    match template.inputs().first() {
+        Some(input) => {
+            input.outpoint() == commitment_outpoint(message)
+                && input.txout() == commitment_txout()
+                && matches!(input.owner(), SpkOwner::Foreign(_))
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn commitment_txid_is_the_hash_of_the_prefixed_message() {
+        let outpoint = commitment_outpoint("Frostsnap audit 2026");
+        assert_eq!(
+            outpoint.txid.to_raw_hash(),
+            sha256d::Hash::hash(b"Proof-of-Reserves: Frostsnap audit 2026")
+        );
+        assert_eq!(outpoint.vout, 0);
+        assert_ne!(outpoint, commitment_outpoint("Frostsnap audit 2027"));
+    }
+
+    #[test]
+    fn a_proof_commits_only_to_its_own_message() {
+        let mut template = proof_template("one");
+        push_proof_output(&mut template);
+        assert!(commits_to(&template, "one"));
+        assert!(!commits_to(&template, "two"));
+        assert!(!commits_to(&TransactionTemplate::new(), "one"));
+        assert_eq!(template.fee(), Some(0));
+    }
+}
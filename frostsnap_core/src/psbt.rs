@@ -4,7 +4,7 @@ use crate::{
         TransactionTemplate,
     },
     message::EncodedSignature,
-    tweak::{AppTweakKind, BitcoinBip32Path},
+    tweak::{AppTweakKind, BitcoinBip32Path, DerivationPathExt, TweakableKey},
     MasterAppkey,
 };
 use alloc::{
@@ -154,6 +154,58 @@ impl TransactionTemplate {
     }
 }
 
+impl<S> TransactionTemplate<S> {
+    /// The unsigned PSBT for this template, annotated so that [`TransactionTemplate::from_psbt`]
+    /// reads back the same ownership.
+    ///
+    /// Every input carries its `witness_utxo`; inputs and outputs of ours also carry the
+    /// untweaked internal key and its origin under the bitcoin appkey. Foreign scripts get no
+    /// annotation since we have nothing true to say about them.
+    pub fn to_psbt(&self) -> Psbt {
+        let mut psbt = Psbt::from_unsigned_tx(self.to_rust_bitcoin_tx())
+            .expect("templates build transactions without script_sigs or witnesses");
+
+        for (psbt_input, input) in psbt.inputs.iter_mut().zip(self.inputs()) {
+            psbt_input.witness_utxo = Some(input.txout());
+            if let Some(local) = input.owner().local_owner() {
+                let (internal_key, key_source) = key_origin(local);
+                psbt_input.tap_internal_key = Some(internal_key);
+                psbt_input
+                    .tap_key_origins
+                    .insert(internal_key, (vec![], key_source));
+            }
+        }
+
+        for (psbt_output, output) in psbt.outputs.iter_mut().zip(self.outputs()) {
+            if let Some(local) = output.local_owner() {
+                let (internal_key, key_source) = key_origin(local);
+                psbt_output.tap_internal_key = Some(internal_key);
+                psbt_output
+                    .tap_key_origins
+                    .insert(internal_key, (vec![], key_source));
+            }
+        }
+
+        psbt
+    }
+}
+
+/// The internal key a [`LocalSpk`] tweaks into its script, and where it comes from.
+fn key_origin(local: &LocalSpk) -> (XOnlyPublicKey, bip32::KeySource) {
+    let bitcoin_appkey = local.master_appkey.derive_appkey(AppTweakKind::Bitcoin);
+    let internal_key = bitcoin_appkey
+        .derive_bip32(local.bip32_path.path_segments_from_bitcoin_appkey())
+        .into_key()
+        .to_libsecp_xonly();
+    let derivation_path = bip32::DerivationPath::from_normal_path_segments(
+        local.bip32_path.path_segments_from_bitcoin_appkey(),
+    );
+    (
+        internal_key,
+        (bitcoin_appkey.fingerprint(), derivation_path),
+    )
+}
+
 /// Only a template narrowed to one key can say which input a signature belongs to.
 impl TransactionTemplate<ScopedTo> {
     /// Writes each signature onto the PSBT input it was produced for.
@@ -184,6 +236,27 @@ impl TransactionTemplate<ScopedTo> {
     }
 }
 
+impl TransactionTemplate {
+    /// Writes the signatures from every key that signed this transaction onto the PSBT, for a
+    /// transaction spending from several of our keys at once.
+    ///
+    /// Each key's signatures are in the order of its own session, so they go through the template
+    /// as that key sees it. Any key's mismatch fails the whole attachment rather than leaving the
+    /// PSBT partly signed.
+    pub fn attach_all_signatures_to_psbt(
+        &self,
+        signatures: &[(MasterAppkey, Vec<EncodedSignature>)],
+        psbt: &Psbt,
+    ) -> Result<Psbt, AttachSignaturesError> {
+        signatures
+            .iter()
+            .try_fold(psbt.clone(), |psbt, (master_appkey, signatures)| {
+                self.as_seen_by(*master_appkey)
+                    .attach_signatures_to_psbt(signatures, &psbt)
+            })
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum AttachSignaturesError {
     CountMismatch(SignatureCountMismatch),
@@ -1140,4 +1213,57 @@ mod test {
             .attach_signatures_to_psbt(&[signature(1), signature(2)], &psbt)
             .is_err());
     }
+
+    #[test]
+    fn a_template_reads_back_from_its_own_psbt() {
+        let key = our_key();
+        let mut template = TransactionTemplate::new();
+        template
+            .push_owned_input(
+                PushInput::spend_outpoint(
+                    &txout_of(key, BitcoinBip32Path::external(idx(3)), 80_000),
+                    OutPoint {
+                        txid: Txid::from_byte_array([1u8; 32]),
+                        vout: 0,
+                    },
+                ),
+                LocalSpk {
+                    master_appkey: key,
+                    bip32_path: BitcoinBip32Path::external(idx(3)),
+                },
+            )
+            .unwrap();
+        template.push_foreign_input(PushInput::spend_outpoint(
+            &foreign_txout(20_000),
+            OutPoint {
+                txid: Txid::from_byte_array([2u8; 32]),
+                vout: 1,
+            },
+        ));
+        template.push_foreign_output(foreign_txout(60_000));
+        template.push_owned_output(
+            Amount::from_sat(39_000),
+            LocalSpk {
+                master_appkey: key,
+                bip32_path: BitcoinBip32Path::internal(idx(0)),
+            },
+        );
+
+        let psbt = template.to_psbt();
+        let (internal_key, (fingerprint, path)) = psbt.inputs[0]
+            .tap_key_origins
+            .iter()
+            .map(|(k, (_, source))| (*k, source.clone()))
+            .next()
+            .unwrap();
+        assert_eq!(psbt.inputs[0].tap_internal_key, Some(internal_key));
+        assert_eq!(fingerprint, fingerprint_of(key));
+        assert_eq!(path, derivation_of(BitcoinBip32Path::external(idx(3))));
+        assert!(psbt.inputs[1].tap_key_origins.is_empty());
+
+        assert_eq!(
+            TransactionTemplate::from_psbt(&psbt, &[key]).unwrap(),
+            template
+        );
+    }
 }
@@ -1,6 +1,7 @@
 use crate::{
     bitcoin_transaction,
-    device::KeyPurpose,
+    device::{address_book, KeyPurpose},
+    message, proof_of_reserves,
     tweak::{AppTweak, BitcoinAccount, BitcoinAccountKeychain, Keychain, NormalIndex},
     MasterAppkey,
 };
@@ -24,6 +25,21 @@ pub enum WireSignTask {
         event: Box<crate::nostr::UnsignedEvent>,
     },
     BitcoinTransaction(bitcoin_transaction::TransactionTemplate),
+    /// A [BIP-127](crate::proof_of_reserves) proof that the key holds the coins the transaction
+    /// spends. Signed like a transaction but shown as what it is, and only once it's clear it
+    /// can never be mined.
+    ProofOfReserves {
+        message: String,
+        tx_template: bitcoin_transaction::TransactionTemplate,
+    },
+    /// Approving an entry for the wallet's [address book](crate::message::address_book). Once a
+    /// threshold have signed it the coordinator hands the signature to each device, which only
+    /// takes the entry with it.
+    AddressBookEntry {
+        label: String,
+        #[bincode(with_serde)]
+        spk: bitcoin::ScriptBuf,
+    },
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -38,10 +54,21 @@ pub enum SignTask {
         tx_template: bitcoin_transaction::TransactionTemplate<bitcoin_transaction::ScopedTo>,
         network: bitcoin::Network,
     },
+    ProofOfReserves {
+        message: String,
+        tx_template: bitcoin_transaction::TransactionTemplate<bitcoin_transaction::ScopedTo>,
+        network: bitcoin::Network,
+    },
+    AddressBookEntry {
+        label: String,
+        spk: bitcoin::ScriptBuf,
+        network: bitcoin::Network,
+    },
 }
 
 #[derive(Debug, Clone, PartialEq)]
-/// A sign task bound to a single key. We only support signing tasks with single keys for now.
+/// A sign task bound to a single key. A task spending from several of our keys is checked once
+/// per key, each in its own session (see [`crate::message::RequestMultiSign`]).
 pub struct CheckedSignTask {
     /// The appkey it the task was checked against. Indicates that for example, the Bitcoin
     /// transaction was signing inputs whose public key was derived from this.
@@ -131,6 +158,13 @@ impl WireSignTask {
                     }
                 }
 
+                // Every other foreign output is just a script the user is shown as an address. A
+                // silent payment's script is derived from our inputs, so here we can check that it
+                // really is the address the user will be shown.
+                tx_template
+                    .check_silent_payment_outputs(network)
+                    .map_err(SignTaskError::SilentPayment)?;
+
                 if !tx_template.has_any_inputs_to_sign() {
                     return Err(SignTaskError::NothingToSign);
                 }
@@ -144,6 +178,47 @@ impl WireSignTask {
                     network,
                 }
             }
+            WireSignTask::ProofOfReserves {
+                message,
+                tx_template,
+            } => {
+                let network = match purpose {
+                    KeyPurpose::Bitcoin(network) => network,
+                    _ => return Err(SignTaskError::WrongPurpose),
+                };
+                // The commitment input is the only thing standing between this and a spend the
+                // user was told isn't one, so without it there's nothing to show.
+                if !proof_of_reserves::commits_to(&tx_template, &message) {
+                    return Err(SignTaskError::ProofOfReservesCouldBeMined);
+                }
+                let tx_template = tx_template.as_seen_by(master_appkey);
+                if !tx_template.has_any_inputs_to_sign() {
+                    return Err(SignTaskError::NothingToSign);
+                }
+                SignTask::ProofOfReserves {
+                    message,
+                    tx_template,
+                    network,
+                }
+            }
+            WireSignTask::AddressBookEntry { label, spk } => {
+                let network = match purpose {
+                    KeyPurpose::Bitcoin(network) => network,
+                    _ => return Err(SignTaskError::WrongPurpose),
+                };
+                address_book::check_label(&label).map_err(SignTaskError::AddressBookEntry)?;
+                // The signers have to be able to check what they're labelling
+                if bitcoin::Address::from_script(&spk, network).is_err() {
+                    return Err(SignTaskError::AddressBookEntry(
+                        "script has no address to show",
+                    ));
+                }
+                SignTask::AddressBookEntry {
+                    label,
+                    spk,
+                    network,
+                }
+            }
         };
         Ok(CheckedSignTask {
             master_appkey,
@@ -177,13 +252,18 @@ impl CheckedSignTask {
                 message: event.hash_bytes.clone(),
                 app_tweak: AppTweak::Nostr,
             }],
-            SignTask::BitcoinTransaction { tx_template, .. } => tx_template
+            SignTask::BitcoinTransaction { tx_template, .. }
+            | SignTask::ProofOfReserves { tx_template, .. } => tx_template
                 .iter_our_input_sighashes()
                 .map(|(owner, sighash)| SignItem {
                     message: sighash.as_raw_hash().to_byte_array().to_vec(),
                     app_tweak: AppTweak::Bitcoin(owner.bip32_path),
                 })
                 .collect(),
+            SignTask::AddressBookEntry { label, spk, .. } => vec![SignItem {
+                message: message::address_book::entry_message(label, spk),
+                app_tweak: AppTweak::AddressBook,
+            }],
         }
     }
 }
@@ -211,6 +291,7 @@ impl SignItem {
             AppTweak::TestMessage => Message::new("frostsnap-test", &self.message[..]),
             AppTweak::Bitcoin(_) => Message::raw(&self.message[..]),
             AppTweak::Nostr => Message::raw(&self.message[..]),
+            AppTweak::AddressBook => Message::new("frostsnap-address-book", &self.message[..]),
         }
     }
 }
@@ -222,6 +303,9 @@ pub enum SignTaskError {
     WrongPurpose,
     InvalidBitcoinTransaction,
     NothingToSign,
+    SilentPayment(crate::silent_payments::SilentPaymentError),
+    ProofOfReservesCouldBeMined,
+    AddressBookEntry(&'static str),
 }
 
 impl core::fmt::Display for SignTaskError {
@@ -241,6 +325,12 @@ impl core::fmt::Display for SignTaskError {
             SignTaskError::NothingToSign => {
                 write!(f, "Transaction has no inputs that belong to this wallet")
             }
+            SignTaskError::SilentPayment(e) => write!(f, "{e}"),
+            SignTaskError::ProofOfReservesCouldBeMined => write!(
+                f,
+                "proof of reserves doesn't start with its commitment input so it could be mined"
+            ),
+            SignTaskError::AddressBookEntry(e) => write!(f, "{e}"),
             SignTaskError::WrongPurpose => {
                 write!(
                     f,
@@ -329,6 +419,7 @@ mod test {
                 destination: PromptDestination::Address(their_address),
                 amount: Amount::from_sat(90_000),
                 owned: None,
+                label: None,
             }],
             "their output is disclosed at their address, and not as ours"
         );
@@ -433,6 +524,7 @@ mod test {
                 destination: PromptDestination::UnrecognizedScript(bare_multisig),
                 amount: Amount::from_sat(90_000),
                 owned: None,
+                label: None,
             }],
             "carried as a script we cannot show, with its real amount"
         );
@@ -494,6 +586,46 @@ mod test {
         assert_eq!(checked.master_appkey, signing);
     }
 
+    /// A proof of reserves is shown as "not a spend", so one that's missing its commitment
+    /// input, and so could be mined, must never get as far as the screen.
+    #[test]
+    fn a_proof_of_reserves_that_could_be_mined_is_refused() {
+        let signing = signing_key();
+        let coin = LocalSpk {
+            master_appkey: signing,
+            bip32_path: BitcoinBip32Path::external(NormalIndex::ZERO),
+        };
+        let proof = |commit_to: Option<&str>| {
+            let mut tx = match commit_to {
+                Some(message) => crate::proof_of_reserves::proof_template(message),
+                None => TransactionTemplate::new(),
+            };
+            tx.push_imaginary_owned_input(coin.clone(), Amount::from_sat(100_000));
+            crate::proof_of_reserves::push_proof_output(&mut tx);
+            WireSignTask::ProofOfReserves {
+                message: "audit".into(),
+                tx_template: tx,
+            }
+            .check(signing, KeyPurpose::Bitcoin(Network::Bitcoin))
+        };
+
+        assert!(matches!(
+            proof(Some("audit")).map(|checked| checked.inner),
+            Ok(SignTask::ProofOfReserves { .. })
+        ));
+        assert!(matches!(
+            proof(None),
+            Err(SignTaskError::ProofOfReservesCouldBeMined)
+        ));
+        assert!(
+            matches!(
+                proof(Some("a different audit")),
+                Err(SignTaskError::ProofOfReservesCouldBeMined)
+            ),
+            "a commitment to another message is no commitment to this one"
+        );
+    }
+
     /// One test for the whole temporary policy: it is three lines of check and will be replaced,
     /// so it gets its boundaries pinned once rather than a case each.
     #[test]
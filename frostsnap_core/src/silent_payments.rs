@@ -0,0 +1,610 @@
+//! Paying [BIP-352] silent payment addresses.
+//!
+//! A silent payment output is keyed by an ECDH between the *sum of the input secret keys* and the
+//! recipient's scan key. Nobody holds our input secret keys: the coordinator has only public keys
+//! and each device has only a share. So the spend happens in two rounds. First, before the
+//! transaction is built, a threshold of devices each contribute `m_j·B_scan` for their share `m_j`
+//! of the master appkey, with a proof that it is the same `m_j` as their public share image.
+//! Those contributions travel inside the [`TransactionTemplate`] itself and every input key is
+//! a linear function of the master appkey, so the output key can be derived from them and the
+//! input paths alone.
+//!
+//! That last point is what makes the output checkable. A device signing the transaction does not
+//! have to believe the coordinator about which key the recipient gets; it re-derives the output
+//! from the shares, the inputs it is about to sign and the address it shows the user, and refuses
+//! if they disagree. A coordinator that swaps the output key for its own can't produce shares
+//! that interpolate to our master appkey and still land on its key.
+//!
+//! The first round needs the user's confirmation on each device. Every key this wallet derives is
+//! the master appkey plus a public tweak, so a device that answered `m_j·P` for any `P` would be
+//! an ECDH oracle for all of them — nostr included.
+//!
+//! [BIP-352]: https://github.com/bitcoin/bips/blob/master/bip-0352.mediawiki
+//! [`TransactionTemplate`]: crate::bitcoin_transaction::TransactionTemplate
+use crate::{
+    bitcoin_transaction::LocalSpk,
+    tweak::{AppTweak, TweakableKey, Xpub},
+    MasterAppkey,
+};
+use alloc::vec::Vec;
+use bitcoin::{
+    bech32::{
+        primitives::{decode::CheckedHrpstring, encode::Encoder, iter::ByteIterExt},
+        Bech32m, Fe32, Hrp,
+    },
+    hashes::Hash,
+    key::TweakedPublicKey,
+    OutPoint, ScriptBuf,
+};
+use schnorr_fun::{
+    frost::ShareIndex,
+    fun::{hash::HashAdd, prelude::*},
+};
+use sha2::{digest::FixedOutput, Digest, Sha256};
+
+/// A recipient's `sp1...` (or `tsp1...` off mainnet) address: a scan key to do ECDH against and
+/// a spend key the output is tweaked from.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, bincode::Encode, bincode::Decode)]
+pub struct SilentPaymentAddress {
+    #[bincode(with_serde)]
+    pub network: bitcoin::Network,
+    pub scan: Point,
+    pub spend: Point,
+}
+
+impl SilentPaymentAddress {
+    const KEYS_LEN: usize = 66;
+
+    pub fn parse(address: &str, network: bitcoin::Network) -> Result<Self, SilentPaymentError> {
+        let mut checked = CheckedHrpstring::new::<Bech32m>(address)
+            .map_err(|_| SilentPaymentError::InvalidAddress("not a bech32m string"))?;
+        if checked.hrp() != Self::hrp(network) {
+            return Err(SilentPaymentError::InvalidAddress(
+                "address is for a different network",
+            ));
+        }
+        let version = checked
+            .remove_witness_version()
+            .ok_or(SilentPaymentError::InvalidAddress("missing version"))?;
+        let bytes = checked.byte_iter().collect::<Vec<u8>>();
+        // Versions up to 30 promise to start with the two keys so today's senders can pay them.
+        let keys = match version.to_u8() {
+            0 if bytes.len() == Self::KEYS_LEN => &bytes[..],
+            1..=30 if bytes.len() >= Self::KEYS_LEN => &bytes[..Self::KEYS_LEN],
+            0..=30 => return Err(SilentPaymentError::InvalidAddress("wrong length")),
+            _ => return Err(SilentPaymentError::InvalidAddress("unsupported version")),
+        };
+        let scan = Point::from_slice(&keys[..33])
+            .ok_or(SilentPaymentError::InvalidAddress("invalid scan key"))?;
+        let spend = Point::from_slice(&keys[33..])
+            .ok_or(SilentPaymentError::InvalidAddress("invalid spend key"))?;
+        Ok(Self {
+            network,
+            scan,
+            spend,
+        })
+    }
+
+    fn hrp(network: bitcoin::Network) -> Hrp {
+        match network {
+            bitcoin::Network::Bitcoin => Hrp::parse_unchecked("sp"),
+            _ => Hrp::parse_unchecked("tsp"),
+        }
+    }
+}
+
+impl core::fmt::Display for SilentPaymentAddress {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let mut keys = [0u8; Self::KEYS_LEN];
+        keys[..33].copy_from_slice(&self.scan.to_bytes());
+        keys[33..].copy_from_slice(&self.spend.to_bytes());
+        let hrp = Self::hrp(self.network);
+        for c in Encoder::<_, Bech32m>::new(keys.iter().copied().bytes_to_fes(), &hrp)
+            .with_witness_version(Fe32::Q)
+            .chars()
+        {
+            write!(f, "{c}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A Chaum-Pedersen proof that `image = x·G` and `ecdh = x·base` share the same `x`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, bincode::Encode, bincode::Decode)]
+pub struct DleqProof {
+    challenge: Scalar<Public, Zero>,
+    response: Scalar<Public, Zero>,
+}
+
+impl DleqProof {
+    fn prove(secret: &Scalar<Secret, Zero>, image: Point, base: Point, ecdh: Point) -> Self {
+        // Deterministic so the device needs no randomness to answer. Binding the base means two
+        // requests for different recipients never share a nonce.
+        let nonce = Scalar::<Secret, Zero>::from_bytes_mod_order(
+            crate::prefix_hash("SP_DLEQ_NONCE")
+                .add(secret.to_bytes())
+                .add(base)
+                .finalize_fixed()
+                .into(),
+        );
+        let nonce_g = g!(nonce * G).normalize();
+        let nonce_base = g!(nonce * base).normalize();
+        let challenge = Self::challenge(image, base, ecdh, nonce_g, nonce_base);
+        Self {
+            challenge,
+            response: s!(nonce + challenge * secret).public(),
+        }
+    }
+
+    fn verify(&self, image: Point, base: Point, ecdh: Point) -> bool {
+        let (challenge, response) = (self.challenge, self.response);
+        let nonce_g = g!(response * G - challenge * image).normalize();
+        let nonce_base = g!(response * base - challenge * ecdh).normalize();
+        Self::challenge(image, base, ecdh, nonce_g, nonce_base) == challenge
+    }
+
+    fn challenge(
+        image: Point,
+        base: Point,
+        ecdh: Point,
+        nonce_g: Point<Normal, Public, Zero>,
+        nonce_base: Point<Normal, Public, Zero>,
+    ) -> Scalar<Public, Zero> {
+        Scalar::from_bytes_mod_order(
+            crate::prefix_hash("SP_DLEQ")
+                .add(image)
+                .add(base)
+                .add(ecdh)
+                .add(nonce_g)
+                .add(nonce_base)
+                .finalize_fixed()
+                .into(),
+        )
+    }
+}
+
+/// One device's contribution to the ECDH with a recipient's scan key: `m_j·B_scan` for its share
+/// `m_j` of the master appkey, proven against the share's public image.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, bincode::Encode, bincode::Decode)]
+pub struct EcdhShare {
+    pub index: ShareIndex,
+    /// `m_j·G`. The coordinator checks this against the access structure it already knows; the
+    /// device signing later checks only that the images interpolate to the master appkey.
+    pub image: Point,
+    pub ecdh: Point,
+    pub proof: DleqProof,
+}
+
+impl EcdhShare {
+    pub fn new(index: ShareIndex, secret: &Scalar<Secret, Zero>, scan: Point) -> Option<Self> {
+        let image = g!(secret * G).normalize().non_zero()?;
+        let ecdh = g!(secret * scan).normalize().non_zero()?;
+        Some(Self {
+            index,
+            image,
+            ecdh,
+            proof: DleqProof::prove(secret, image, scan, ecdh),
+        })
+    }
+
+    pub fn verify(&self, scan: Point) -> bool {
+        self.proof.verify(self.image, scan, self.ecdh)
+    }
+}
+
+/// Enough [`EcdhShare`]s to recover `m·B_scan` for the master appkey `m`.
+///
+/// There is no threshold to check against: shares whose images interpolate to the master appkey
+/// pin down `m`, and the proofs tie each `ecdh` to its image, so the interpolated `ecdh` is
+/// `m·B_scan` whoever supplied them.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, bincode::Encode, bincode::Decode)]
+pub struct SilentPaymentEcdh {
+    pub scan: Point,
+    pub shares: Vec<EcdhShare>,
+}
+
+impl SilentPaymentEcdh {
+    /// `m·B_scan` for `master_appkey`, if the shares really are of it.
+    pub fn master_appkey_ecdh(
+        &self,
+        master_appkey: MasterAppkey,
+    ) -> Result<Point, SilentPaymentError> {
+        let invalid = SilentPaymentError::InvalidEcdh;
+        if self.shares.is_empty() {
+            return Err(invalid);
+        }
+        for (i, share) in self.shares.iter().enumerate() {
+            let duplicate = self.shares[..i]
+                .iter()
+                .any(|other| other.index == share.index);
+            if duplicate || !share.verify(self.scan) {
+                return Err(invalid);
+            }
+        }
+        let images = self
+            .shares
+            .iter()
+            .map(|share| (share.index, share.image))
+            .collect::<Vec<_>>();
+        if interpolate_at_zero(&images) != master_appkey.to_xpub().key.mark_zero() {
+            return Err(invalid);
+        }
+        let ecdhs = self
+            .shares
+            .iter()
+            .map(|share| (share.index, share.ecdh))
+            .collect::<Vec<_>>();
+        interpolate_at_zero(&ecdhs).non_zero().ok_or(invalid)
+    }
+}
+
+fn interpolate_at_zero(points: &[(ShareIndex, Point)]) -> Point<Normal, Public, Zero> {
+    points.iter().fold(Point::zero(), |acc, &(index, point)| {
+        let lagrange = points.iter().filter(|(other, _)| *other != index).fold(
+            s!(1).public(),
+            |lagrange, &(other, _)| {
+                let denominator = s!(other - index)
+                    .non_zero()
+                    .expect("indices were checked distinct")
+                    .invert();
+                s!(lagrange * other * denominator).public()
+            },
+        );
+        g!(acc + lagrange * point).normalize()
+    })
+}
+
+/// A silent payment output as it travels in a template: who it pays, which output to them it is,
+/// the ECDH it was derived with and the script that derivation produced.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, bincode::Encode, bincode::Decode)]
+pub struct SilentPaymentOutput {
+    pub address: SilentPaymentAddress,
+    /// BIP-352's `k`: the position of this output among those paying the same scan key.
+    pub k: u32,
+    pub ecdh: SilentPaymentEcdh,
+    #[bincode(with_serde)]
+    pub spk: ScriptBuf,
+}
+
+impl SilentPaymentOutput {
+    /// The script these inputs pay this address, as the `k`th output to its scan key.
+    ///
+    /// `inputs` is every input of the transaction; each must be ours under `master_appkey`,
+    /// since BIP-352 keys the output to all of them and we can only account for our own.
+    pub fn derive_spk<'a>(
+        master_appkey: MasterAppkey,
+        inputs: impl IntoIterator<Item = (OutPoint, Option<&'a LocalSpk>)>,
+        address: &SilentPaymentAddress,
+        k: u32,
+        ecdh: &SilentPaymentEcdh,
+    ) -> Result<ScriptBuf, SilentPaymentError> {
+        if ecdh.scan != address.scan {
+            return Err(SilentPaymentError::InvalidEcdh);
+        }
+        let master_ecdh = ecdh.master_appkey_ecdh(master_appkey)?;
+
+        // Each input's secret key is ±m plus a public tweak, so the sum of them is too, and `a·B`
+        // follows from `m·B` without anyone learning `a`.
+        let one = s!(1).public();
+        let mut coefficient = Scalar::<Secret, Zero>::zero().public();
+        let mut tweak = Scalar::<Secret, Zero>::zero().public();
+        let mut input_keys = Point::<Normal, Public, Zero>::zero();
+        let mut smallest_outpoint: Option<[u8; 36]> = None;
+        let mut any_inputs = false;
+        for (outpoint, owner) in inputs {
+            let owner = match owner {
+                Some(owner) if owner.master_appkey == master_appkey => owner,
+                _ => return Err(SilentPaymentError::ForeignInput),
+            };
+            any_inputs = true;
+            let key = AppTweak::Bitcoin(owner.bip32_path)
+                .derive_xonly_key(&LinearKey::master_appkey(master_appkey));
+            coefficient = if key.negated {
+                s!(coefficient - one).public()
+            } else {
+                s!(coefficient + one).public()
+            };
+            tweak = s!(tweak + key.tweak).public();
+            input_keys = g!(input_keys + key.point).normalize();
+
+            let mut serialized = [0u8; 36];
+            serialized[..32].copy_from_slice(&outpoint.txid.to_byte_array());
+            serialized[32..].copy_from_slice(&outpoint.vout.to_le_bytes());
+            if smallest_outpoint.map_or(true, |smallest| serialized < smallest) {
+                smallest_outpoint = Some(serialized);
+            }
+        }
+        let (smallest_outpoint, input_keys) = match (smallest_outpoint, input_keys.non_zero()) {
+            (Some(outpoint), Some(keys)) if any_inputs => (outpoint, keys),
+            _ => return Err(SilentPaymentError::Degenerate),
+        };
+
+        let input_hash = Scalar::<Public, Zero>::from_bytes_mod_order(
+            tagged_hash("BIP0352/Inputs")
+                .chain_update(smallest_outpoint)
+                .chain_update(input_keys.to_bytes())
+                .finalize_fixed()
+                .into(),
+        );
+        let input_ecdh = g!(coefficient * master_ecdh + tweak * address.scan).normalize();
+        let shared_secret = g!(input_hash * input_ecdh)
+            .normalize()
+            .non_zero()
+            .ok_or(SilentPaymentError::Degenerate)?;
+        let output_tweak = Scalar::<Public, Zero>::from_bytes_mod_order(
+            tagged_hash("BIP0352/SharedSecret")
+                .chain_update(shared_secret.to_bytes())
+                .chain_update(k.to_be_bytes())
+                .finalize_fixed()
+                .into(),
+        );
+        let (output_key, _) = g!(address.spend + output_tweak * G)
+            .normalize()
+            .non_zero()
+            .ok_or(SilentPaymentError::Degenerate)?
+            .into_point_with_even_y();
+        Ok(ScriptBuf::new_p2tr_tweaked(
+            TweakedPublicKey::dangerous_assume_tweaked(output_key.into()),
+        ))
+    }
+}
+
+fn tagged_hash(tag: &str) -> Sha256 {
+    let tag = Sha256::digest(tag.as_bytes());
+    Sha256::default().chain_update(tag).chain_update(tag)
+}
+
+/// A public key kept together with how its secret key relates to the master appkey's: the secret
+/// is `±m + tweak`. Deriving it the way a secret share would be derived gives the relation for any
+/// of our keys without the secret.
+#[derive(Clone, Debug)]
+struct LinearKey {
+    point: Point,
+    negated: bool,
+    tweak: Scalar<Public, Zero>,
+}
+
+impl LinearKey {
+    fn master_appkey(master_appkey: MasterAppkey) -> Xpub<Self> {
+        let xpub = master_appkey.to_xpub();
+        Xpub::new(
+            LinearKey {
+                point: xpub.key,
+                negated: false,
+                tweak: Scalar::<Secret, Zero>::zero().public(),
+            },
+            xpub.chaincode,
+        )
+    }
+}
+
+impl TweakableKey for LinearKey {
+    type XOnly = LinearKey;
+
+    fn to_key(&self) -> Point {
+        self.point
+    }
+
+    fn tweak(self, tweak: Scalar<Public, Zero>) -> Self {
+        LinearKey {
+            point: g!(self.point + tweak * G)
+                .normalize()
+                .non_zero()
+                .expect("computationally unreachable"),
+            negated: self.negated,
+            tweak: s!(self.tweak + tweak).public(),
+        }
+    }
+
+    fn into_xonly_with_tweak(self, tweak: Scalar<Public>) -> Self::XOnly {
+        self.into_xonly().tweak(tweak.mark_zero()).into_xonly()
+    }
+
+    fn into_xonly(self) -> Self::XOnly {
+        if self.point.is_y_even() {
+            return self;
+        }
+        LinearKey {
+            point: -self.point,
+            negated: !self.negated,
+            tweak: -self.tweak,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SilentPaymentError {
+    InvalidAddress(&'static str),
+    /// An input isn't ours, so its key can't be accounted for in the ECDH.
+    ForeignInput,
+    /// The ECDH shares don't prove `m·B_scan` for this key and recipient.
+    InvalidEcdh,
+    /// The output script isn't the one the inputs and ECDH derive.
+    OutputMismatch {
+        index: usize,
+    },
+    /// The `k`s paying one scan key aren't `0..n`. A repeated `k` pays the same key twice and a
+    /// gap leaves an output the recipient will never scan for.
+    BadOutputCounter,
+    /// A point at infinity in the derivation, which BIP-352 says to give up on.
+    Degenerate,
+}
+
+impl core::fmt::Display for SilentPaymentError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            SilentPaymentError::InvalidAddress(reason) => {
+                write!(f, "invalid silent payment address: {reason}")
+            }
+            SilentPaymentError::ForeignInput => write!(
+                f,
+                "silent payments can only be sent from transactions whose inputs are all ours"
+            ),
+            SilentPaymentError::InvalidEcdh => {
+                write!(f, "silent payment ECDH shares are not valid for this key")
+            }
+            SilentPaymentError::OutputMismatch { index } => write!(
+                f,
+                "output {index} does not pay the silent payment address it claims to"
+            ),
+            SilentPaymentError::BadOutputCounter => write!(
+                f,
+                "silent payment outputs to one recipient must be numbered from zero without gaps"
+            ),
+            SilentPaymentError::Degenerate => {
+                write!(f, "silent payment derivation reached the point at infinity")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SilentPaymentError {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tweak::{BitcoinBip32Path, NormalIndex};
+    use bitcoin::Txid;
+
+    #[test]
+    fn address_round_trips() {
+        let address = SilentPaymentAddress {
+            network: bitcoin::Network::Bitcoin,
+            scan: g!(7 * G).normalize(),
+            spend: g!(11 * G).normalize(),
+        };
+        let encoded = address.to_string();
+        assert!(encoded.starts_with("sp1q"));
+        assert_eq!(
+            SilentPaymentAddress::parse(&encoded, bitcoin::Network::Bitcoin),
+            Ok(address.clone())
+        );
+        assert_eq!(
+            SilentPaymentAddress::parse(&encoded, bitcoin::Network::Signet),
+            Err(SilentPaymentError::InvalidAddress(
+                "address is for a different network"
+            ))
+        );
+    }
+
+    #[test]
+    fn parses_the_bip_test_vector_address() {
+        let address = SilentPaymentAddress::parse(
+            "sp1qqgste7k9hx0qftg6qmwlkqtwuy6cycyavzmzj85c6qdfhjdpdjtdgqjuexzk6murw56suy3e0rd2cgqvycxttddwsvgxe2usfpxumr70xc9pkqwv",
+            bitcoin::Network::Bitcoin,
+        )
+        .unwrap();
+        assert_eq!(
+            address.to_string(),
+            "sp1qqgste7k9hx0qftg6qmwlkqtwuy6cycyavzmzj85c6qdfhjdpdjtdgqjuexzk6murw56suy3e0rd2cgqvycxttddwsvgxe2usfpxumr70xc9pkqwv"
+        );
+    }
+
+    /// Threshold ECDH must land on exactly what a single signer with the whole key would
+    /// compute, or the recipient scans for the wrong output.
+    #[test]
+    fn threshold_ecdh_matches_single_signer_derivation() {
+        let mut rng = rand::thread_rng();
+        // A 2-of-3 sharing of the master appkey `m` by hand: `f(x) = m + c·x`.
+        let secret = Scalar::<Secret, NonZero>::random(&mut rng);
+        let coefficient = Scalar::<Secret, NonZero>::random(&mut rng);
+        let master_appkey =
+            MasterAppkey::from_xpub_unchecked(&Xpub::new(g!(secret * G).normalize(), [42; 32]));
+
+        let scan_secret = Scalar::<Secret, NonZero>::random(&mut rng);
+        let address = SilentPaymentAddress {
+            network: bitcoin::Network::Bitcoin,
+            scan: g!(scan_secret * G).normalize(),
+            spend: g!(13 * G).normalize(),
+        };
+
+        let shares = [s!(1).public(), s!(3).public()]
+            .into_iter()
+            .map(|index| {
+                let share = s!(secret + coefficient * index);
+                EcdhShare::new(index, &share, address.scan).unwrap()
+            })
+            .collect::<Vec<_>>();
+        let ecdh = SilentPaymentEcdh {
+            scan: address.scan,
+            shares,
+        };
+
+        let owners = [
+            BitcoinBip32Path::external(NormalIndex::ZERO),
+            BitcoinBip32Path::internal(NormalIndex::new(4).unwrap()),
+        ]
+        .map(|bip32_path| LocalSpk {
+            master_appkey,
+            bip32_path,
+        });
+        let outpoints = [
+            OutPoint::new(Txid::from_byte_array([2; 32]), 1),
+            OutPoint::new(Txid::from_byte_array([1; 32]), 7),
+        ];
+        let inputs = || outpoints.iter().copied().zip(owners.iter().map(Some));
+
+        let derived =
+            SilentPaymentOutput::derive_spk(master_appkey, inputs(), &address, 0, &ecdh).unwrap();
+
+        // The recipient's side: `input_hash·b_scan·A`, from public input keys alone.
+        let input_keys = owners
+            .iter()
+            .map(|owner| {
+                AppTweak::Bitcoin(owner.bip32_path).derive_xonly_key(&master_appkey.to_xpub())
+            })
+            .fold(Point::<Normal, Public, Zero>::zero(), |acc, key| {
+                g!(acc + key).normalize()
+            })
+            .non_zero()
+            .unwrap();
+        let mut smallest = [0u8; 36];
+        smallest[..32].copy_from_slice(&[1; 32]);
+        smallest[32..].copy_from_slice(&7u32.to_le_bytes());
+        let input_hash = Scalar::<Public, Zero>::from_bytes_mod_order(
+            tagged_hash("BIP0352/Inputs")
+                .chain_update(smallest)
+                .chain_update(input_keys.to_bytes())
+                .finalize_fixed()
+                .into(),
+        );
+        let scan_tweak = s!(input_hash * scan_secret);
+        let shared = g!(scan_tweak * input_keys).normalize();
+        let t_0 = Scalar::<Public, Zero>::from_bytes_mod_order(
+            tagged_hash("BIP0352/SharedSecret")
+                .chain_update(shared.to_bytes())
+                .chain_update(0u32.to_be_bytes())
+                .finalize_fixed()
+                .into(),
+        );
+        let (expected, _) = g!(address.spend + t_0 * G)
+            .normalize()
+            .non_zero()
+            .unwrap()
+            .into_point_with_even_y();
+        assert_eq!(
+            derived,
+            ScriptBuf::new_p2tr_tweaked(TweakedPublicKey::dangerous_assume_tweaked(
+                expected.into()
+            ))
+        );
+
+        let other_key = MasterAppkey::derive_from_rootkey(g!(5 * G).normalize());
+        assert_eq!(
+            ecdh.master_appkey_ecdh(other_key),
+            Err(SilentPaymentError::InvalidEcdh),
+            "shares of one key must not pass as another's"
+        );
+    }
+
+    #[test]
+    fn a_share_with_the_wrong_ecdh_is_rejected() {
+        let secret = Scalar::<Secret, Zero>::from_bytes_mod_order([9; 32]);
+        let scan = g!(3 * G).normalize();
+        let mut share = EcdhShare::new(s!(1).public(), &secret, scan).unwrap();
+        assert!(share.verify(scan));
+        share.ecdh = g!(4 * G).normalize();
+        assert!(!share.verify(scan));
+    }
+}
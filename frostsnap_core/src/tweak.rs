@@ -134,6 +134,8 @@ pub enum AppTweak {
     TestMessage,
     Bitcoin(BitcoinBip32Path),
     Nostr,
+    /// Approving an entry in a wallet's address book. See [`crate::message::address_book`].
+    AddressBook,
 }
 
 #[derive(
@@ -269,6 +271,7 @@ impl AppTweak {
             AppTweak::Bitcoin { .. } => AppTweakKind::Bitcoin,
             AppTweak::Nostr => AppTweakKind::Nostr,
             AppTweak::TestMessage => AppTweakKind::TestMessage,
+            AppTweak::AddressBook => AppTweakKind::AddressBook,
         }
     }
 
@@ -293,6 +296,7 @@ impl AppTweak {
             }
             AppTweak::Nostr => appkey.into_key().into_xonly(),
             AppTweak::TestMessage => appkey.into_key().into_xonly(),
+            AppTweak::AddressBook => appkey.into_key().into_xonly(),
         }
     }
 }
@@ -302,6 +306,7 @@ pub enum AppTweakKind {
     Bitcoin = 0,
     TestMessage = 1,
     Nostr = 2,
+    AddressBook = 3,
 }
 
 impl AppTweakKind {
@@ -0,0 +1,390 @@
+use bitcoin::{Amount, ScriptBuf, TxOut};
+use common::TEST_ENCRYPTION_KEY;
+use frostsnap_core::bitcoin_transaction::{LocalSpk, TransactionTemplate};
+use frostsnap_core::device::KeyPurpose;
+use frostsnap_core::message::{
+    address_book::CoordinatorAddressBook, CoordinatorToDeviceMessage, EncodedSignature,
+};
+use frostsnap_core::schnorr_fun::fun::{g, G};
+use frostsnap_core::tweak::{BitcoinBip32Path, NormalIndex};
+use frostsnap_core::{
+    AccessStructureRef, DeviceId, KeyId, MasterAppkey, SignSessionId, SignTask, WireSignTask,
+};
+use rand_chacha::rand_core::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use std::collections::BTreeSet;
+
+mod common;
+mod env;
+use crate::common::Run;
+use crate::env::TestEnv;
+
+const NETWORK: bitcoin::Network = bitcoin::Network::Bitcoin;
+
+fn kraken_spk() -> ScriptBuf {
+    ScriptBuf::from_hex("0014751e76e8199196d454941c45d1b3a323f1433bd6").unwrap()
+}
+
+fn stranger_spk() -> ScriptBuf {
+    ScriptBuf::from_hex("5120a62baa9e7c1aeda63492f2129cc8226a39db1bc05a9c11e45a61cb751a11061d")
+        .unwrap()
+}
+
+struct Setup {
+    run: Run,
+    env: TestEnv,
+    rng: ChaCha20Rng,
+    access_structure_ref: AccessStructureRef,
+    master_appkey: MasterAppkey,
+}
+
+fn setup() -> Setup {
+    let mut rng = ChaCha20Rng::from_seed([48u8; 32]);
+    let mut env = TestEnv::default();
+    let run = Run::start_after_keygen_and_nonces(
+        3,
+        2,
+        &mut env,
+        &mut rng,
+        1,
+        KeyPurpose::Bitcoin(NETWORK),
+    );
+    let access_structure_ref = run
+        .coordinator
+        .iter_access_structures()
+        .next()
+        .unwrap()
+        .access_structure_ref();
+    let master_appkey = run
+        .coordinator
+        .get_frost_key(access_structure_ref.key_id)
+        .unwrap()
+        .complete_key
+        .master_appkey;
+    Setup {
+        run,
+        env,
+        rng,
+        access_structure_ref,
+        master_appkey,
+    }
+}
+
+impl Setup {
+    fn key_id(&self) -> KeyId {
+        self.access_structure_ref.key_id
+    }
+
+    fn signers(&self) -> BTreeSet<DeviceId> {
+        self.run.device_set().into_iter().take(2).collect()
+    }
+
+    /// Have a threshold of devices sign the entry, as the coordinator needs before it can send it.
+    fn approve(&mut self, spk: ScriptBuf, label: &str) -> EncodedSignature {
+        let signers = self.signers();
+        let session_id = self.sign_task(
+            WireSignTask::AddressBookEntry {
+                label: label.into(),
+                spk,
+            },
+            &signers,
+        );
+        EncodedSignature::new(self.env.signatures[&session_id][0])
+    }
+
+    fn update(&mut self, spk: ScriptBuf, label: Option<&str>) {
+        if let Some(label) = label {
+            self.approve(spk.clone(), label);
+        }
+        self.env.address_book_updated.clear();
+        let update = self
+            .run
+            .coordinator
+            .update_address_book(self.access_structure_ref, spk, label.map(String::from))
+            .unwrap();
+        self.run.extend(update);
+        self.run
+            .run_until_finished(&mut self.env, &mut self.rng)
+            .unwrap();
+        assert_eq!(self.env.address_book_updated, self.run.device_set());
+    }
+
+    fn sign(&mut self, signers: &BTreeSet<DeviceId>) {
+        let mut tx_template = TransactionTemplate::new();
+        tx_template.push_imaginary_owned_input(
+            LocalSpk {
+                master_appkey: self.master_appkey,
+                bip32_path: BitcoinBip32Path::external(NormalIndex::new(0).unwrap()),
+            },
+            Amount::from_sat(100_000),
+        );
+        tx_template.push_foreign_output(TxOut {
+            value: Amount::from_sat(40_000),
+            script_pubkey: kraken_spk(),
+        });
+        tx_template.push_foreign_output(TxOut {
+            value: Amount::from_sat(20_000),
+            script_pubkey: stranger_spk(),
+        });
+        tx_template.push_owned_output(
+            Amount::from_sat(39_000),
+            LocalSpk {
+                master_appkey: self.master_appkey,
+                bip32_path: BitcoinBip32Path::internal(NormalIndex::new(0).unwrap()),
+            },
+        );
+
+        self.sign_task(WireSignTask::BitcoinTransaction(tx_template), signers);
+    }
+
+    fn sign_task(&mut self, task: WireSignTask, signers: &BTreeSet<DeviceId>) -> SignSessionId {
+        let session_id = self
+            .run
+            .coordinator
+            .start_sign(self.access_structure_ref, task, signers, &mut self.rng)
+            .unwrap();
+        for &device_id in signers {
+            let sign_req = self.run.coordinator.request_device_sign(
+                session_id,
+                device_id,
+                TEST_ENCRYPTION_KEY,
+            );
+            self.run.extend(sign_req);
+        }
+        self.run
+            .run_until_finished(&mut self.env, &mut self.rng)
+            .unwrap();
+        session_id
+    }
+}
+
+#[test]
+fn confirmed_entries_label_the_sign_prompt() {
+    let mut setup = setup();
+    setup.update(kraken_spk(), Some("Kraken deposit"));
+
+    for device_id in setup.run.device_set() {
+        let phase = &setup.env.address_book_prompts[&device_id];
+        assert_eq!(phase.label(), Some("Kraken deposit"));
+        assert_eq!(phase.old_label(), None);
+        assert_eq!(phase.address().script_pubkey(), kraken_spk());
+    }
+
+    let signers = setup.signers();
+    setup.sign(&signers);
+
+    for device_id in &signers {
+        let address_books = &setup.env.sign_address_books[device_id];
+        let mut prompt = match &setup.env.sign_tasks[device_id].inner {
+            SignTask::BitcoinTransaction { tx_template, .. } => tx_template.user_prompt(NETWORK),
+            _ => unreachable!(),
+        };
+        prompt.apply_address_books(&address_books.iter().collect::<Vec<_>>());
+
+        assert_eq!(prompt.recipients.len(), 2);
+        let (kraken, stranger) = (&prompt.recipients[0], &prompt.recipients[1]);
+        assert_eq!(kraken.label.as_deref(), Some("Kraken deposit"));
+        assert!(!prompt.is_unlisted(kraken));
+        assert_eq!(stranger.label, None);
+        assert!(
+            prompt.is_unlisted(stranger),
+            "a destination missing from the address book is called out"
+        );
+
+        // and the same in the full review, where the change is listed too
+        let details = &setup.env.sign_prompt_details[device_id];
+        assert_eq!(details.outputs.len(), 3);
+        let (kraken, stranger, change) = (
+            &details.outputs[0],
+            &details.outputs[1],
+            &details.outputs[2],
+        );
+        assert_eq!(kraken.label.as_deref(), Some("Kraken deposit"));
+        assert!(!details.is_unlisted(kraken));
+        assert!(details.is_unlisted(stranger));
+        assert!(change.owned.is_some() && !details.is_unlisted(change));
+    }
+}
+
+#[test]
+fn relabelling_and_removing_an_entry() {
+    let mut setup = setup();
+    setup.update(kraken_spk(), Some("Kraken deposit"));
+    setup.update(kraken_spk(), Some("Kraken"));
+
+    let key_id = setup.key_id();
+    for device_id in setup.run.device_set() {
+        let phase = &setup.env.address_book_prompts[&device_id];
+        assert_eq!(phase.old_label(), Some("Kraken deposit"));
+        assert_eq!(
+            setup
+                .run
+                .device(device_id)
+                .address_book(key_id)
+                .collect::<Vec<_>>(),
+            vec![(kraken_spk().as_script(), "Kraken")]
+        );
+    }
+
+    setup.update(kraken_spk(), None);
+    for device_id in setup.run.device_set() {
+        assert_eq!(setup.env.address_book_prompts[&device_id].label(), None);
+        assert_eq!(setup.run.device(device_id).address_book(key_id).count(), 0);
+    }
+
+    // With the book empty again nothing is flagged as unlisted
+    let signers = setup.signers();
+    setup.sign(&signers);
+    for device_id in &signers {
+        assert!(setup.env.sign_address_books[device_id]
+            .iter()
+            .all(|book| book.is_empty()));
+    }
+}
+
+#[test]
+fn bad_entries_are_refused() {
+    let mut setup = setup();
+    let key_id = setup.key_id();
+
+    for label in ["", "   ", "Kräken", "a label far too long to show"] {
+        assert!(
+            setup
+                .run
+                .coordinator
+                .update_address_book(setup.access_structure_ref, kraken_spk(), Some(label.into()))
+                .is_err(),
+            "{label:?} should be refused"
+        );
+    }
+
+    let master_appkey = setup.master_appkey;
+    let signature = setup.approve(kraken_spk(), "Kraken");
+    let device_id = setup.run.device_vec()[0];
+    let bad_messages = [
+        // a coordinator that skips its own check is still refused by the device
+        CoordinatorAddressBook::AddEntry {
+            master_appkey,
+            label: "Kraken\ndeposit".into(),
+            spk: kraken_spk(),
+            signature,
+        },
+        CoordinatorAddressBook::AddEntry {
+            master_appkey: MasterAppkey::derive_from_rootkey(g!(3 * G).normalize()),
+            label: "Kraken".into(),
+            spk: kraken_spk(),
+            signature,
+        },
+        // nothing the user could check
+        CoordinatorAddressBook::AddEntry {
+            master_appkey,
+            label: "Kraken".into(),
+            spk: ScriptBuf::from_hex("6a0401020304").unwrap(),
+            signature,
+        },
+        CoordinatorAddressBook::RemoveEntry {
+            key_id,
+            spk: kraken_spk(),
+        },
+    ];
+    for message in bad_messages {
+        assert!(setup
+            .run
+            .device(device_id)
+            .recv_coordinator_message(
+                CoordinatorToDeviceMessage::AddressBook(message),
+                &mut setup.rng
+            )
+            .is_err());
+    }
+    assert_eq!(setup.run.device(device_id).address_book(key_id).count(), 0);
+}
+
+#[test]
+fn entries_need_the_wallets_signature() {
+    let mut setup = setup();
+    let key_id = setup.key_id();
+
+    assert!(
+        setup
+            .run
+            .coordinator
+            .update_address_book(
+                setup.access_structure_ref,
+                kraken_spk(),
+                Some("Kraken".into())
+            )
+            .is_err(),
+        "an entry the wallet hasn't signed can't be sent"
+    );
+
+    let master_appkey = setup.master_appkey;
+    let signature = setup.approve(kraken_spk(), "Kraken");
+    let test_signature = {
+        let signers = setup.signers();
+        let session_id = setup.sign_task(
+            WireSignTask::Test {
+                message: "Kraken".into(),
+            },
+            &signers,
+        );
+        EncodedSignature::new(setup.env.signatures[&session_id][0])
+    };
+
+    let device_id = setup.run.device_vec()[0];
+    let forged = [
+        // one device's user confirming something isn't the wallet approving it
+        CoordinatorAddressBook::AddEntry {
+            master_appkey,
+            label: "Kraken".into(),
+            spk: kraken_spk(),
+            signature: EncodedSignature([0u8; 64]),
+        },
+        // the approval is for a label, not for the address
+        CoordinatorAddressBook::AddEntry {
+            master_appkey,
+            label: "Kraken deposit".into(),
+            spk: kraken_spk(),
+            signature,
+        },
+        CoordinatorAddressBook::AddEntry {
+            master_appkey,
+            label: "Kraken".into(),
+            spk: stranger_spk(),
+            signature,
+        },
+        // nor does anything else the wallet signed count
+        CoordinatorAddressBook::AddEntry {
+            master_appkey,
+            label: "Kraken".into(),
+            spk: kraken_spk(),
+            signature: test_signature,
+        },
+    ];
+    for message in forged {
+        assert!(setup
+            .run
+            .device(device_id)
+            .recv_coordinator_message(
+                CoordinatorToDeviceMessage::AddressBook(message),
+                &mut setup.rng
+            )
+            .is_err());
+    }
+
+    // the real approval goes on to the user, and only their confirmation saves it
+    assert!(setup
+        .run
+        .device(device_id)
+        .recv_coordinator_message(
+            CoordinatorToDeviceMessage::AddressBook(CoordinatorAddressBook::AddEntry {
+                master_appkey,
+                label: "Kraken".into(),
+                spk: kraken_spk(),
+                signature,
+            }),
+            &mut setup.rng
+        )
+        .is_ok());
+    assert_eq!(setup.run.device(device_id).address_book(key_id).count(), 0);
+}
@@ -167,7 +167,8 @@ pub trait Env {
                     _ => { /* do nothing */ }
                 };
             }
-            DeviceToUserMessage::VerifyAddress { .. } => {
+            DeviceToUserMessage::VerifyAddress { .. }
+            | DeviceToUserMessage::VerifyAccount { .. } => {
                 // we dont actually confirm on the device
             }
             _ => { /* do nothing */ }
@@ -277,6 +278,7 @@ impl Run {
         run.extend(run.coordinator.maybe_request_nonce_replenishment(
             &run.device_set(),
             n_nonce_streams,
+            |_| true,
             rng,
         ));
         run.run_until_finished(env, rng).unwrap();
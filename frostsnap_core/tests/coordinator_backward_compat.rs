@@ -2,6 +2,7 @@
 mod common;
 
 use frostsnap_core::coordinator::{
+    faults::{DeviceFault, FaultMutation},
     keys::KeyMutation,
     restoration::{PendingConsolidation, RestorationMutation},
     signing::SigningMutation,
@@ -10,7 +11,9 @@ use frostsnap_core::coordinator::{
 use frostsnap_core::device::KeyPurpose;
 use frostsnap_core::message::HeldShare2;
 use frostsnap_core::message::{EncodedSignature, GroupSignReq};
-use frostsnap_core::nonce_stream::{CoordNonceStreamState, NonceStreamId, NonceStreamSegment};
+use frostsnap_core::nonce_stream::{
+    CoordNonceStreamState, NoncePartition, NonceStreamId, NonceStreamSegment,
+};
 use frostsnap_core::tweak::AppTweak;
 use frostsnap_core::tweak::Xpub;
 use frostsnap_core::{
@@ -300,6 +303,25 @@ fn test_all_coordinator_mutations() {
         Mutation::Signing(SigningMutation::ForgetFinishedSignSession {
             session_id: SignSessionId([12u8; 32]),
         }),
+        Mutation::Signing(SigningMutation::ReplacedSignSession {
+            abandoned: SignSessionId([12u8; 32]),
+            replacement: SignSessionId([13u8; 32]),
+        }),
+        Mutation::Signing(SigningMutation::NewNoncePartition {
+            device_id: DeviceId([6u8; 33]),
+            partition: NoncePartition([8u8; 4]),
+        }),
+        // Fault mutations
+        Mutation::Fault(FaultMutation::RecordFault {
+            device_id: DeviceId([7u8; 33]),
+            fault: DeviceFault::InvalidSignatureShare {
+                session_id: SignSessionId([12u8; 32]),
+            },
+        }),
+        Mutation::Fault(FaultMutation::SetQuarantined {
+            device_id: DeviceId([7u8; 33]),
+            quarantined: true,
+        }),
     ];
 
     // Test each mutation
@@ -416,6 +438,30 @@ fn test_all_coordinator_mutations() {
                     "01050c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c"
                 );
             }
+            Mutation::Signing(SigningMutation::ReplacedSignSession { .. }) => {
+                assert_bincode_hex_eq!(
+                    mutation,
+                    "01060c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0d0d0d0d0d0d0d0d0d0d0d0d0d0d0d0d0d0d0d0d0d0d0d0d0d0d0d0d0d0d0d0d"
+                );
+            }
+            Mutation::Signing(SigningMutation::NewNoncePartition { .. }) => {
+                assert_bincode_hex_eq!(
+                    mutation,
+                    "010706060606060606060606060606060606060606060606060606060606060606060608080808"
+                );
+            }
+            Mutation::Fault(FaultMutation::RecordFault { .. }) => {
+                assert_bincode_hex_eq!(
+                    mutation,
+                    "0300070707070707070707070707070707070707070707070707070707070707070707000c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c"
+                );
+            }
+            Mutation::Fault(FaultMutation::SetQuarantined { .. }) => {
+                assert_bincode_hex_eq!(
+                    mutation,
+                    "030107070707070707070707070707070707070707070707070707070707070707070701"
+                );
+            }
             Mutation::Restoration(RestorationMutation::NewRestoration2 { .. }) => {
                 assert_bincode_hex_eq!(
                     mutation,
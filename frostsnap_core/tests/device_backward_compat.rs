@@ -3,7 +3,8 @@ mod common;
 
 use frost_backup::ShareBackup;
 use frostsnap_core::device::{
-    restoration::*, EncryptedSecretShare, KeyPurpose, Mutation, SaveShareMutation,
+    address_book::AddressBookMutation, restoration::*, EncryptedSecretShare, KeyPurpose, Mutation,
+    SaveShareMutation,
 };
 use frostsnap_core::{AccessStructureId, AccessStructureKind, Kind};
 use schnorr_fun::frost::{SecretShare, ShareImage, SharedKey};
@@ -42,6 +43,10 @@ fn test_all_device_mutations() {
         ciphertext: Ciphertext::encrypt(test_key, &test_secret_share, &mut rng),
     };
 
+    let mut taproot_spk = vec![0x51, 0x20];
+    taproot_spk.extend([0xaa; 32]);
+    let taproot_spk = bitcoin::ScriptBuf::from_bytes(taproot_spk);
+
     // Create all mutation variants we want to test
     let mutations = vec![
         // Keygen mutations
@@ -83,6 +88,16 @@ fn test_all_device_mutations() {
             key_name: Some("test_key".to_string()),
         })),
         Mutation::Restoration(RestorationMutation::_UnSave(share_image)),
+        // Address book mutations
+        Mutation::AddressBook(AddressBookMutation::Add {
+            key_id: frostsnap_core::KeyId([1u8; 32]),
+            label: "kraken".to_string(),
+            spk: taproot_spk.clone(),
+        }),
+        Mutation::AddressBook(AddressBookMutation::Remove {
+            key_id: frostsnap_core::KeyId([1u8; 32]),
+            spk: taproot_spk,
+        }),
     ];
 
     // Test each mutation
@@ -126,6 +141,18 @@ fn test_all_device_mutations() {
                     "000201010101010101010101010101010101010101010101010101010101010101010202020202020202020202020202020202020202020202020202020202020202000000000000000000000000000000000000000000000000000000000000000102fe8d1eb1bcb3432b1db5833ff5f2226d9cb5e65cee430558c18ed3a3c86ce1afb1dde6fd8607b05ecd33fcdf96eaef828be8955ad2af175f7b4f231e83dac8a2a89393d068530505297b93b9dc5b740d59a1ebee4d9a5924acda8cca"
                 );
             }
+            Mutation::AddressBook(AddressBookMutation::Add { .. }) => {
+                assert_bincode_hex_eq!(
+                    mutation,
+                    "02000101010101010101010101010101010101010101010101010101010101010101066b72616b656e225120aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa"
+                );
+            }
+            Mutation::AddressBook(AddressBookMutation::Remove { .. }) => {
+                assert_bincode_hex_eq!(
+                    mutation,
+                    "02010101010101010101010101010101010101010101010101010101010101010101225120aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa"
+                );
+            }
         }
     }
 }
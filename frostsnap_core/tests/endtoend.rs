@@ -1,7 +1,10 @@
 use common::TEST_ENCRYPTION_KEY;
 use frostsnap_core::bitcoin_transaction::{LocalSpk, TransactionTemplate};
 use frostsnap_core::device::KeyPurpose;
-use frostsnap_core::tweak::{BitcoinBip32Path, NormalIndex};
+use frostsnap_core::message::{screen_verify::ScreenVerify, CoordinatorToDeviceMessage};
+use frostsnap_core::tweak::{
+    AccountKind, AppTweakKind, BitcoinAccount, BitcoinBip32Path, NormalIndex,
+};
 use frostsnap_core::EnterPhysicalId;
 use frostsnap_core::{MasterAppkey, WireSignTask};
 use rand::seq::IteratorRandom;
@@ -241,6 +244,58 @@ fn test_verify_address() {
     assert_eq!(env.verification_requests.len(), 3);
 }
 
+#[test]
+fn test_verify_account() {
+    let mut env = TestEnv::default();
+    let mut test_rng = ChaCha20Rng::from_seed([50u8; 32]);
+    let network = bitcoin::Network::Bitcoin;
+
+    let mut run =
+        Run::start_after_keygen(3, 2, &mut env, &mut test_rng, KeyPurpose::Bitcoin(network));
+
+    let key_data = run.coordinator.iter_keys().next().unwrap().clone();
+    let account = BitcoinAccount {
+        kind: AccountKind::Segwitv1,
+        index: NormalIndex::new(1).unwrap(),
+    };
+
+    let verify_request = run
+        .coordinator
+        .verify_account(key_data.key_id, account, TEST_ENCRYPTION_KEY)
+        .unwrap();
+    run.extend(verify_request);
+    run.run_until_finished(&mut env, &mut test_rng).unwrap();
+
+    // what the coordinator puts in the wallet's descriptor
+    let bitcoin_app_xpub = key_data
+        .complete_key
+        .master_appkey
+        .derive_appkey(AppTweakKind::Bitcoin);
+    let expected_xpub = bitcoin_app_xpub
+        .derive_bip32(account.path_segments_from_bitcoin_appkey())
+        .to_bitcoin_xpub_with_lies(network.into());
+
+    assert_eq!(env.account_verifications.len(), 3);
+    for (fingerprint, xpub) in env.account_verifications.values() {
+        assert_eq!(*fingerprint, bitcoin_app_xpub.fingerprint());
+        assert_eq!(*xpub, expected_xpub);
+    }
+
+    // a device won't show an xpub for a key it doesn't hold a share of
+    let device_id = run.device_vec()[0];
+    let other_rootkey = g!(7 * G).normalize();
+    assert!(run
+        .device(device_id)
+        .recv_coordinator_message(
+            CoordinatorToDeviceMessage::ScreenVerify(ScreenVerify::VerifyAccount {
+                rootkey: other_rootkey,
+                account,
+            }),
+            &mut test_rng,
+        )
+        .is_err());
+}
+
 #[test]
 fn when_we_abandon_a_sign_request_we_should_be_able_to_start_a_new_one() {
     let mut test_rng = ChaCha20Rng::from_seed([42u8; 32]);
@@ -794,6 +849,7 @@ fn nonces_available_should_heal_itself_when_outcome_of_sign_request_is_ambigious
     run.extend(run.coordinator.maybe_request_nonce_replenishment(
         &BTreeSet::from([device_id]),
         1,
+        |_| true,
         &mut test_rng,
     ));
     run.run_until_finished(&mut env, &mut test_rng).unwrap();
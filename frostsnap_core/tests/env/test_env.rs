@@ -1,8 +1,10 @@
 use crate::common::{Env, Run, TestDeviceKeyGen, TEST_ENCRYPTION_KEY};
-use bitcoin::Address;
+use bitcoin::{bip32, Address};
+use frostsnap_core::bitcoin_transaction::{PromptSignBitcoinTx, PromptTxDetails};
 use frostsnap_core::coordinator::restoration::RecoverShare;
-use frostsnap_core::device::{self, DeviceToUserMessage};
+use frostsnap_core::device::{self, restoration::ShareMigrationCode, DeviceToUserMessage};
 use frostsnap_core::message::{self, DeviceSend, DeviceToCoordinatorMessage, EncodedSignature};
+use frostsnap_core::silent_payments::EcdhShare;
 use frostsnap_core::tweak::BitcoinBip32Path;
 use frostsnap_core::{
     coordinator::{
@@ -37,9 +39,34 @@ pub struct TestEnv {
     // signing
     pub received_signing_shares: BTreeMap<SignSessionId, BTreeSet<DeviceId>>,
     pub sign_tasks: BTreeMap<DeviceId, CheckedSignTask>,
+    /// How many times each device asked its user to confirm signing.
+    pub sign_confirmations: BTreeMap<DeviceId, usize>,
+    /// The transaction prompt each device last showed its user.
+    pub sign_prompts: BTreeMap<DeviceId, PromptSignBitcoinTx>,
+    /// The details for a full review that went with it.
+    pub sign_prompt_details: BTreeMap<DeviceId, PromptTxDetails>,
+    /// The address books, one per key, each device labelled its last sign prompt with.
+    pub sign_address_books: BTreeMap<DeviceId, Vec<BTreeMap<bitcoin::ScriptBuf, String>>>,
     pub signatures: BTreeMap<SignSessionId, Vec<Signature>>,
+    pub silent_payment_ecdh_shares: BTreeMap<DeviceId, EcdhShare>,
 
     pub verification_requests: BTreeMap<DeviceId, (Address, BitcoinBip32Path)>,
+    /// The master fingerprint and account xpub each device showed.
+    pub account_verifications: BTreeMap<DeviceId, (bip32::Fingerprint, bip32::Xpub)>,
+
+    // address book
+    /// The address book changes each device asked its user to confirm.
+    pub address_book_prompts: BTreeMap<DeviceId, device::address_book::AddressBookPhase>,
+    /// Devices the coordinator heard made an address book change.
+    pub address_book_updated: BTreeSet<DeviceId>,
+
+    // share migration
+    /// Devices that confirmed handing their share over, and to whom.
+    pub share_exports_confirmed: BTreeMap<DeviceId, DeviceId>,
+    /// Devices that confirmed taking over a share, and from whom.
+    pub share_imports_confirmed: BTreeMap<DeviceId, DeviceId>,
+    /// The code each device showed while confirming.
+    pub share_migration_codes: BTreeMap<DeviceId, ShareMigrationCode>,
 
     // Explicit mapping of which backup each device should enter
     pub backup_to_enter: BTreeMap<DeviceId, frost_backup::ShareBackup>,
@@ -120,6 +147,10 @@ impl Env for TestEnv {
                             .map(Option::unwrap),
                     );
                 }
+                CoordinatorToUserSigningMessage::SilentPaymentEcdhShare { from, share, .. } => {
+                    self.silent_payment_ecdh_shares.insert(from, share);
+                }
+                CoordinatorToUserSigningMessage::RelayedSignatureShare { .. } => {}
             },
             CoordinatorToUserMessage::Restoration(msg) => {
                 use frostsnap_core::coordinator::restoration::ToUserRestoration::*;
@@ -202,6 +233,9 @@ impl Env for TestEnv {
             CoordinatorToUserMessage::ReplenishedNonces { device_id } => {
                 self.received_nonce_replenishes.insert(device_id);
             }
+            CoordinatorToUserMessage::AddressBookUpdated { device_id, .. } => {
+                self.address_book_updated.insert(device_id);
+            }
         }
     }
 
@@ -224,12 +258,26 @@ impl Env for TestEnv {
             }
             DeviceToUserMessage::SignatureRequest { phase } => {
                 self.sign_tasks.insert(from, phase.sign_task().clone());
+                if let Some((prompt, details)) = phase.bitcoin_prompt() {
+                    self.sign_prompts.insert(from, prompt);
+                    self.sign_prompt_details.insert(from, details);
+                }
+                *self.sign_confirmations.entry(from).or_default() += 1;
+                self.sign_address_books
+                    .insert(from, phase.address_books().cloned().collect());
                 let sign_ack = run
                     .device(from)
                     .sign_ack(*phase, &mut TestDeviceKeyGen)
                     .unwrap();
                 run.extend_from_device(from, sign_ack);
             }
+            DeviceToUserMessage::SilentPaymentEcdh { phase } => {
+                let ack = run
+                    .device(from)
+                    .silent_payment_ecdh_ack(*phase, &mut TestDeviceKeyGen)
+                    .unwrap();
+                run.extend_from_device(from, ack);
+            }
             DeviceToUserMessage::Restoration(restoration) => {
                 use device::restoration::ToUserRestoration::*;
                 match *restoration {
@@ -264,6 +312,25 @@ impl Env for TestEnv {
                     }
                     BackupSaved { .. } => { /* informational */ }
                     CheckBackup { .. } => { /* not tested here */ }
+                    ExportShare { to, phase, .. } => {
+                        self.share_exports_confirmed.insert(from, to);
+                        self.share_migration_codes.insert(from, phase.code);
+                        let ack = run
+                            .device(from)
+                            .export_share_ack(phase, &mut TestDeviceKeyGen, rng)
+                            .unwrap();
+                        run.extend_from_device(from, ack);
+                    }
+                    ImportShare {
+                        from: exporter,
+                        phase,
+                        ..
+                    } => {
+                        self.share_imports_confirmed.insert(from, exporter);
+                        self.share_migration_codes.insert(from, phase.code);
+                        let ack = run.device(from).import_share_ack(phase);
+                        run.extend_from_device(from, ack);
+                    }
                 }
             }
             DeviceToUserMessage::VerifyAddress {
@@ -273,6 +340,19 @@ impl Env for TestEnv {
                 self.verification_requests
                     .insert(from, (address, bip32_path));
             }
+            DeviceToUserMessage::VerifyAccount {
+                master_fingerprint,
+                account_xpub,
+                ..
+            } => {
+                self.account_verifications
+                    .insert(from, (master_fingerprint, account_xpub));
+            }
+            DeviceToUserMessage::AddressBook { phase } => {
+                self.address_book_prompts.insert(from, (*phase).clone());
+                let ack = run.device(from).address_book_ack(*phase);
+                run.extend_from_device(from, ack);
+            }
             DeviceToUserMessage::NonceJobs(mut batch) => {
                 // Run the batch to completion and send a single response
                 batch.run_until_finished(&mut TestDeviceKeyGen);
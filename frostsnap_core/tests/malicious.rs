@@ -1,13 +1,16 @@
 //! Tests for a malicious actions. A malicious coordinator, a malicious device or both.
 use common::TEST_ENCRYPTION_KEY;
 use env::TestEnv;
-use frostsnap_core::coordinator::{BeginKeygen, CoordinatorSend};
+use frostsnap_core::coordinator::{
+    faults::{DeviceFault, FaultMutation},
+    BeginKeygen, CoordinatorSend, Mutation, StartSignError,
+};
 use frostsnap_core::device::KeyPurpose;
 use frostsnap_core::message::{
-    keygen::DeviceKeygen, CoordinatorToDeviceMessage, DeviceSend, DeviceToCoordinatorMessage,
-    Keygen,
+    keygen::DeviceKeygen, signing::DeviceSigning, CoordinatorToDeviceMessage, DeviceSend,
+    DeviceToCoordinatorMessage, Keygen,
 };
-use frostsnap_core::WireSignTask;
+use frostsnap_core::{SignSessionId, WireSignTask};
 use rand_chacha::rand_core::SeedableRng;
 use rand_chacha::ChaCha20Rng;
 
@@ -286,3 +289,107 @@ fn malicious_consolidation_wrong_root_shared_key() {
         error_msg
     );
 }
+
+/// A device whose signature share doesn't verify is rejected and the fault is kept on its record,
+/// where it survives a restart and can be used to keep the device out of signing.
+#[test]
+fn invalid_signature_share_is_recorded_against_device() {
+    use schnorr_fun::fun::Scalar;
+
+    let mut test_rng = ChaCha20Rng::from_seed([45u8; 32]);
+    let mut env = TestEnv::default();
+    let mut run =
+        Run::start_after_keygen_and_nonces(3, 2, &mut env, &mut test_rng, 1, KeyPurpose::Test);
+    let access_structure_ref = run
+        .coordinator
+        .iter_access_structures()
+        .next()
+        .unwrap()
+        .access_structure_ref();
+    let devices = run.device_set().into_iter().collect::<Vec<_>>();
+    let (honest, malicious) = (devices[0], devices[1]);
+    let signers = [honest, malicious].into_iter().collect();
+
+    let session_id = run
+        .coordinator
+        .start_sign(
+            access_structure_ref,
+            WireSignTask::Test {
+                message: "don't trust, verify".into(),
+            },
+            &signers,
+            &mut test_rng,
+        )
+        .unwrap();
+    for &device_id in &signers {
+        let request =
+            run.coordinator
+                .request_device_sign(session_id, device_id, TEST_ENCRYPTION_KEY);
+        run.extend(request);
+    }
+
+    let result = run.run_until(&mut env, &mut test_rng, move |run| {
+        for send in run.message_queue.iter_mut() {
+            if let Send::DeviceToCoordinator {
+                from,
+                message:
+                    DeviceToCoordinatorMessage::Signing(DeviceSigning::SignatureShare {
+                        signature_shares,
+                        ..
+                    }),
+            } = send
+            {
+                if *from == malicious {
+                    for share in signature_shares.iter_mut() {
+                        *share = Scalar::from_bytes_mod_order([3u8; 32]).public();
+                    }
+                }
+            }
+        }
+        run.message_queue.is_empty()
+    });
+    assert!(result.is_err());
+
+    let device_faults = run.coordinator.device_faults();
+    assert!(!device_faults.contains_key(&honest));
+    assert_eq!(
+        device_faults[&malicious].faults,
+        vec![DeviceFault::InvalidSignatureShare { session_id }]
+    );
+
+    // Replaying the persisted mutations into a fresh coordinator gets the same record back.
+    run.check_mutations();
+    assert_eq!(
+        run.start_coordinator.device_faults(),
+        run.coordinator.device_faults()
+    );
+
+    // Another fault of the same kind adds nothing to persist.
+    assert_eq!(
+        run.coordinator
+            .apply_mutation(Mutation::Fault(FaultMutation::RecordFault {
+                device_id: malicious,
+                fault: DeviceFault::InvalidSignatureShare {
+                    session_id: SignSessionId([1u8; 32]),
+                },
+            })),
+        None
+    );
+
+    run.coordinator.set_device_quarantined(malicious, true);
+    assert!(matches!(
+        run.coordinator.start_sign(
+            access_structure_ref,
+            WireSignTask::Test {
+                message: "try again".into(),
+            },
+            &signers,
+            &mut test_rng,
+        ),
+        Err(StartSignError::DeviceQuarantined { device_id }) if device_id == malicious
+    ));
+    assert_eq!(
+        run.coordinator.eligible_substitutes(session_id, malicious),
+        [devices[2]].into_iter().collect()
+    );
+}
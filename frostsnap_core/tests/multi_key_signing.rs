@@ -0,0 +1,296 @@
+use common::{TestDeviceKeyGen, TEST_ENCRYPTION_KEY};
+use frostsnap_core::bitcoin_transaction::{LocalSpk, TransactionTemplate};
+use frostsnap_core::coordinator::BeginKeygen;
+use frostsnap_core::device::{DeviceToUserMessage, KeyPurpose};
+use frostsnap_core::message::{
+    signing::CoordinatorSigning, CoordinatorToDeviceMessage, DeviceSend,
+};
+use frostsnap_core::tweak::{BitcoinBip32Path, NormalIndex};
+use frostsnap_core::{
+    AccessStructureRef, CoordShareDecryptionContrib, DeviceId, MasterAppkey, WireSignTask,
+};
+use rand_chacha::rand_core::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use schnorr_fun::Schnorr;
+use std::collections::BTreeSet;
+
+mod common;
+mod env;
+use crate::common::Run;
+use crate::env::TestEnv;
+
+const NETWORK: bitcoin::Network = bitcoin::Network::Bitcoin;
+
+struct Setup {
+    run: Run,
+    rng: ChaCha20Rng,
+    keys: [(AccessStructureRef, MasterAppkey); 2],
+    devices: Vec<DeviceId>,
+}
+
+/// Two wallets over the same three devices, each device with two nonce streams so it can sign
+/// for both keys at once.
+fn setup() -> Setup {
+    let mut rng = ChaCha20Rng::from_seed([30u8; 32]);
+    let mut env = TestEnv::default();
+    let mut run = Run::start_after_keygen_and_nonces(
+        3,
+        2,
+        &mut env,
+        &mut rng,
+        2,
+        KeyPurpose::Bitcoin(NETWORK),
+    );
+    let begin = BeginKeygen::new(
+        run.device_set().into_iter().collect(),
+        2,
+        "second wallet".to_string(),
+        KeyPurpose::Bitcoin(NETWORK),
+        &mut rng,
+    );
+    let keygen_init = run.coordinator.begin_keygen(begin, &mut rng).unwrap();
+    run.extend(keygen_init);
+    run.run_until_finished(&mut TestEnv::default(), &mut rng)
+        .unwrap();
+
+    let keys = run
+        .coordinator
+        .iter_keys()
+        .map(|key| {
+            (
+                key.access_structures()
+                    .next()
+                    .unwrap()
+                    .access_structure_ref(),
+                key.complete_key.master_appkey,
+            )
+        })
+        .collect::<Vec<_>>();
+    let devices = run.device_set().into_iter().collect();
+    Setup {
+        run,
+        rng,
+        keys: keys.try_into().unwrap(),
+        devices,
+    }
+}
+
+fn idx(n: u32) -> NormalIndex {
+    NormalIndex::new(n).unwrap()
+}
+
+/// Spends a coin from each key, with change back to the first.
+fn template(keys: &[(AccessStructureRef, MasterAppkey); 2]) -> TransactionTemplate {
+    let mut tx_template = TransactionTemplate::new();
+    for (i, (_, master_appkey)) in keys.iter().enumerate() {
+        tx_template.push_imaginary_owned_input(
+            LocalSpk {
+                master_appkey: *master_appkey,
+                bip32_path: BitcoinBip32Path::external(idx(i as u32)),
+            },
+            bitcoin::Amount::from_sat(40_000),
+        );
+    }
+    tx_template.push_foreign_output(bitcoin::TxOut {
+        value: bitcoin::Amount::from_sat(60_000),
+        script_pubkey: bitcoin::ScriptBuf::from_bytes(
+            [&[0x51u8, 0x20][..], &[7u8; 32][..]].concat(),
+        ),
+    });
+    tx_template.push_owned_output(
+        bitcoin::Amount::from_sat(19_000),
+        LocalSpk {
+            master_appkey: keys[0].1,
+            bip32_path: BitcoinBip32Path::internal(idx(0)),
+        },
+    );
+    tx_template
+}
+
+#[test]
+fn a_device_in_both_keys_signs_both_after_one_confirmation() {
+    let Setup {
+        mut run,
+        mut rng,
+        keys,
+        devices,
+    } = setup();
+    let tx_template = template(&keys);
+    let task = WireSignTask::BitcoinTransaction(tx_template.clone());
+
+    // The middle device signs for both keys, the others for one each.
+    let signers = [
+        BTreeSet::from([devices[0], devices[1]]),
+        BTreeSet::from([devices[1], devices[2]]),
+    ];
+    let session_ids = run
+        .coordinator
+        .start_multi_sign(
+            vec![
+                (keys[0].0, signers[0].clone()),
+                (keys[1].0, signers[1].clone()),
+            ],
+            task.clone(),
+            &mut rng,
+        )
+        .unwrap();
+    assert_eq!(session_ids.len(), 2);
+
+    for &device_id in &devices {
+        let request = run
+            .coordinator
+            .request_device_multi_sign(&session_ids, device_id, TEST_ENCRYPTION_KEY)
+            .unwrap();
+        run.extend(request);
+    }
+    let mut env = TestEnv::default();
+    run.run_until_finished(&mut env, &mut rng).unwrap();
+
+    for &device_id in &devices {
+        assert_eq!(env.sign_confirmations.get(&device_id), Some(&1));
+    }
+
+    // The device in both keys sees each key's own spend, and the first key's change as change
+    // rather than a payment out.
+    let joint = &env.sign_prompts[&devices[1]];
+    assert_eq!(
+        joint
+            .key_spends
+            .iter()
+            .map(|spend| spend.at_risk.to_sat())
+            .collect::<Vec<_>>(),
+        vec![21_000, 40_000]
+    );
+    assert_eq!(joint.recipients.len(), 1);
+    assert_eq!(
+        joint.foreign_value(),
+        Some(bitcoin::Amount::from_sat(60_000))
+    );
+    assert!(env.sign_prompts[&devices[0]].key_spends.is_empty());
+
+    let schnorr = Schnorr::<sha2::Sha256>::verify_only();
+    for ((_, master_appkey), session_id) in keys.iter().zip(&session_ids) {
+        let checked_task = task
+            .clone()
+            .check(*master_appkey, KeyPurpose::Bitcoin(NETWORK))
+            .unwrap();
+        assert!(checked_task.verify_final_signatures(&schnorr, &env.signatures[session_id]));
+    }
+
+    let signatures = keys
+        .iter()
+        .zip(&session_ids)
+        .map(|((_, master_appkey), session_id)| {
+            let signatures = run
+                .coordinator
+                .finished_signing_sessions()
+                .get(session_id)
+                .expect("session finished")
+                .signatures
+                .clone();
+            (*master_appkey, signatures)
+        })
+        .collect::<Vec<_>>();
+    let psbt = tx_template
+        .attach_all_signatures_to_psbt(&signatures, &tx_template.to_psbt())
+        .unwrap();
+    assert!(psbt.inputs.iter().all(|input| input.tap_key_sig.is_some()));
+}
+
+/// Each key's session must consume from its own nonce stream, otherwise the second would only be
+/// refused once the first had already been signed.
+#[test]
+fn a_multi_key_request_sharing_a_nonce_stream_is_refused() {
+    let Setup {
+        mut run,
+        mut rng,
+        keys,
+        devices,
+    } = setup();
+    let task = WireSignTask::BitcoinTransaction(template(&keys));
+    let signers = BTreeSet::from([devices[0], devices[1]]);
+    let session_ids = run
+        .coordinator
+        .start_multi_sign(
+            vec![(keys[0].0, signers.clone()), (keys[1].0, signers)],
+            task,
+            &mut rng,
+        )
+        .unwrap();
+
+    let mut request = run
+        .coordinator
+        .request_device_multi_sign(&session_ids, devices[0], TEST_ENCRYPTION_KEY)
+        .unwrap()
+        .request_multi_sign;
+    request.keys[1].device_sign_req.nonces = request.keys[0].device_sign_req.nonces;
+
+    let message = CoordinatorToDeviceMessage::Signing(CoordinatorSigning::RequestMultiSign(
+        Box::new(request),
+    ));
+    assert!(run
+        .device(devices[0])
+        .recv_coordinator_message(message, &mut rng)
+        .is_err());
+}
+
+/// A key that can't be signed for must be found before any key's nonces are used up, otherwise the
+/// signature shares already made for the keys before it would be lost.
+#[test]
+fn a_key_failing_at_confirmation_uses_no_nonces() {
+    let Setup {
+        mut run,
+        mut rng,
+        keys,
+        devices,
+    } = setup();
+    let task = WireSignTask::BitcoinTransaction(template(&keys));
+    let signers = BTreeSet::from([devices[0], devices[1]]);
+    let session_ids = run
+        .coordinator
+        .start_multi_sign(
+            vec![(keys[0].0, signers.clone()), (keys[1].0, signers)],
+            task,
+            &mut rng,
+        )
+        .unwrap();
+
+    let mut request = run
+        .coordinator
+        .request_device_multi_sign(&session_ids, devices[0], TEST_ENCRYPTION_KEY)
+        .unwrap()
+        .request_multi_sign;
+    let first_key_nonces = request.keys[0].device_sign_req.nonces;
+    // the device can only find out it can't decrypt its share once the user has confirmed
+    request.keys[1]
+        .device_sign_req
+        .coord_share_decryption_contrib = CoordShareDecryptionContrib::from_bytes([9u8; 32]);
+
+    let message = CoordinatorToDeviceMessage::Signing(CoordinatorSigning::RequestMultiSign(
+        Box::new(request),
+    ));
+    let phase = run
+        .device(devices[0])
+        .recv_coordinator_message(message, &mut rng)
+        .unwrap()
+        .into_iter()
+        .find_map(|send| match send {
+            DeviceSend::ToUser(message) => match *message {
+                DeviceToUserMessage::SignatureRequest { phase } => Some(phase),
+                _ => None,
+            },
+            _ => None,
+        })
+        .expect("device asks the user");
+    assert!(run
+        .device(devices[0])
+        .sign_ack(*phase, &mut TestDeviceKeyGen)
+        .is_err());
+
+    // had the first key been signed for, its stream would have moved past these nonces
+    assert!(run
+        .device(devices[0])
+        .nonce_slots()
+        .check_can_sign(session_ids[1], first_key_nonces, 1)
+        .is_ok());
+}
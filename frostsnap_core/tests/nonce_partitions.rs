@@ -0,0 +1,140 @@
+use frostsnap_core::coordinator::FrostCoordinator;
+use frostsnap_core::device::KeyPurpose;
+use frostsnap_core::message::{
+    signing::{CoordinatorSigning, DeviceSigning},
+    CoordinatorToDeviceMessage, DeviceSend, DeviceToCoordinatorMessage,
+};
+use frostsnap_core::nonce_stream::{NoncePartition, NonceStreamId};
+use rand_chacha::rand_core::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use std::collections::BTreeSet;
+
+mod common;
+mod env;
+use crate::common::{Run, TEST_FINGERPRINT};
+use crate::env::TestEnv;
+
+/// Swap the coordinator talking to the devices, returning the one that was.
+fn switch_coordinator(run: &mut Run, to: FrostCoordinator) -> FrostCoordinator {
+    run.check_mutations();
+    run.start_coordinator = to.clone();
+    core::mem::replace(&mut run.coordinator, to)
+}
+
+fn streams_in(
+    run: &mut Run,
+    device_id: frostsnap_core::DeviceId,
+    partition: NoncePartition,
+) -> BTreeSet<NonceStreamId> {
+    run.device(device_id)
+        .nonce_slots()
+        .all_stream_ids()
+        .filter(|stream_id| partition.contains(*stream_id))
+        .collect()
+}
+
+/// The devices have 8 slots, all full of the first coordinator's streams. A second coordinator
+/// gets half of them, and when the first replenishes again it keeps to the half it has left.
+#[test]
+fn a_second_coordinator_gets_its_own_share_of_nonce_slots() {
+    let mut rng = ChaCha20Rng::from_seed([38u8; 32]);
+    let mut env = TestEnv::default();
+    let mut run = Run::start_after_keygen_and_nonces(2, 2, &mut env, &mut rng, 8, KeyPurpose::Test);
+    let device_id = *run.device_set().first().unwrap();
+    let first_partition = run.coordinator.nonce_partition(device_id).unwrap();
+    assert_eq!(streams_in(&mut run, device_id, first_partition).len(), 8);
+
+    let mut second = FrostCoordinator::new();
+    second.keygen_fingerprint = TEST_FINGERPRINT;
+    second.coordinator_id = [2u8; 32];
+    let first = switch_coordinator(&mut run, second);
+    run.extend(run.coordinator.maybe_request_nonce_replenishment(
+        &run.device_set(),
+        8,
+        |_| true,
+        &mut rng,
+    ));
+    run.run_until_finished(&mut env, &mut rng).unwrap();
+
+    let second_partition = run.coordinator.nonce_partition(device_id).unwrap();
+    assert_ne!(second_partition, first_partition);
+    let second_streams = streams_in(&mut run, device_id, second_partition);
+    assert_eq!(second_streams.len(), 4);
+    assert!(run
+        .coordinator
+        .nonces_available(device_id)
+        .keys()
+        .all(|stream_id| second_partition.contains(*stream_id)));
+
+    switch_coordinator(&mut run, first);
+    run.extend(run.coordinator.maybe_request_nonce_replenishment(
+        &run.device_set(),
+        8,
+        |_| true,
+        &mut rng,
+    ));
+    run.run_until_finished(&mut env, &mut rng).unwrap();
+
+    assert_eq!(
+        streams_in(&mut run, device_id, second_partition),
+        second_streams
+    );
+    assert_eq!(streams_in(&mut run, device_id, first_partition).len(), 4);
+}
+
+/// A device won't hand out a partition that already has streams in it.
+#[test]
+fn a_partition_in_use_is_not_granted_again() {
+    let mut rng = ChaCha20Rng::from_seed([39u8; 32]);
+    let mut env = TestEnv::default();
+    let mut run = Run::start_after_keygen_and_nonces(1, 1, &mut env, &mut rng, 2, KeyPurpose::Test);
+    let device_id = *run.device_set().first().unwrap();
+    let taken = run.coordinator.nonce_partition(device_id).unwrap();
+
+    for (partition, expected) in [(taken, false), (NoncePartition([0xff; 4]), true)] {
+        let reply = run
+            .device(device_id)
+            .recv_coordinator_message(
+                CoordinatorToDeviceMessage::Signing(CoordinatorSigning::ClaimNoncePartition {
+                    partition,
+                }),
+                &mut rng,
+            )
+            .unwrap();
+        match &reply[..] {
+            [DeviceSend::ToCoordinator(message)] => match message.as_ref() {
+                DeviceToCoordinatorMessage::Signing(DeviceSigning::NoncePartitionClaim {
+                    partition: claimed,
+                    granted,
+                }) => {
+                    assert_eq!(*claimed, partition);
+                    assert_eq!(*granted, expected);
+                }
+                message => panic!("unexpected reply {message:?}"),
+            },
+            reply => panic!("unexpected reply {reply:?}"),
+        }
+    }
+}
+
+/// Firmware that can't set aside partitions isn't asked to, and its streams are used as before.
+#[test]
+fn devices_without_partitions_keep_their_streams() {
+    let mut rng = ChaCha20Rng::from_seed([40u8; 32]);
+    let mut env = TestEnv::default();
+    let mut run = Run::start_after_keygen(1, 1, &mut env, &mut rng, KeyPurpose::Test);
+    let device_id = *run.device_set().first().unwrap();
+
+    let request = run.coordinator.maybe_request_nonce_replenishment(
+        &run.device_set(),
+        2,
+        |_| false,
+        &mut rng,
+    );
+    assert!(request.partition_claims.is_empty());
+    run.extend(request);
+    run.run_until_finished(&mut env, &mut rng).unwrap();
+
+    assert_eq!(run.coordinator.nonce_partition(device_id), None);
+    assert_eq!(run.coordinator.nonces_available(device_id).len(), 2);
+}
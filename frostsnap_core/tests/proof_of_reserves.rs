@@ -0,0 +1,133 @@
+use common::TEST_ENCRYPTION_KEY;
+use frostsnap_core::bitcoin_transaction::{LocalSpk, TransactionTemplate};
+use frostsnap_core::device::KeyPurpose;
+use frostsnap_core::proof_of_reserves;
+use frostsnap_core::tweak::{BitcoinBip32Path, NormalIndex};
+use frostsnap_core::{SignTask, WireSignTask};
+use rand_chacha::rand_core::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use schnorr_fun::Schnorr;
+
+mod common;
+mod env;
+use crate::common::Run;
+use crate::env::TestEnv;
+
+const MESSAGE: &str = "reserves for the 2026 audit";
+
+fn proof(master_appkey: frostsnap_core::MasterAppkey, commit: bool) -> WireSignTask {
+    let mut tx_template = if commit {
+        proof_of_reserves::proof_template(MESSAGE)
+    } else {
+        TransactionTemplate::new()
+    };
+    for (index, value) in [(3, 60_000), (9, 15_000)] {
+        tx_template.push_imaginary_owned_input(
+            LocalSpk {
+                master_appkey,
+                bip32_path: BitcoinBip32Path::external(NormalIndex::new(index).unwrap()),
+            },
+            bitcoin::Amount::from_sat(value),
+        );
+    }
+    proof_of_reserves::push_proof_output(&mut tx_template);
+    WireSignTask::ProofOfReserves {
+        message: MESSAGE.into(),
+        tx_template,
+    }
+}
+
+#[test]
+fn a_proof_of_reserves_is_signed_as_a_proof() {
+    let schnorr = Schnorr::<sha2::Sha256>::verify_only();
+    let mut rng = ChaCha20Rng::from_seed([40u8; 32]);
+    let mut env = TestEnv::default();
+    let mut run = Run::start_after_keygen_and_nonces(
+        2,
+        2,
+        &mut env,
+        &mut rng,
+        1,
+        KeyPurpose::Bitcoin(bitcoin::Network::Bitcoin),
+    );
+    let device_set = run.device_set();
+    let access_structure_ref = run
+        .coordinator
+        .iter_access_structures()
+        .next()
+        .unwrap()
+        .access_structure_ref();
+    let master_appkey = run
+        .coordinator
+        .get_frost_key(access_structure_ref.key_id)
+        .unwrap()
+        .complete_key
+        .master_appkey;
+
+    let task = proof(master_appkey, true);
+    let checked_task = task
+        .clone()
+        .check(
+            master_appkey,
+            KeyPurpose::Bitcoin(bitcoin::Network::Bitcoin),
+        )
+        .unwrap();
+    let session_id = run
+        .coordinator
+        .start_sign(access_structure_ref, task, &device_set, &mut rng)
+        .unwrap();
+    for &device_id in &device_set {
+        let sign_req =
+            run.coordinator
+                .request_device_sign(session_id, device_id, TEST_ENCRYPTION_KEY);
+        run.extend(sign_req);
+    }
+    run.run_until_finished(&mut env, &mut rng).unwrap();
+
+    for device_id in &device_set {
+        assert!(matches!(
+            env.sign_tasks.get(device_id).map(|task| &task.inner),
+            Some(SignTask::ProofOfReserves { message, .. }) if message == MESSAGE
+        ));
+    }
+    let signatures = env.signatures.get(&session_id).unwrap();
+    assert_eq!(signatures.len(), 2, "one per coin, none for the commitment");
+    assert!(checked_task.verify_final_signatures(&schnorr, signatures));
+}
+
+#[test]
+fn a_proof_of_reserves_without_its_commitment_is_not_signed() {
+    let mut rng = ChaCha20Rng::from_seed([41u8; 32]);
+    let mut env = TestEnv::default();
+    let mut run = Run::start_after_keygen_and_nonces(
+        1,
+        1,
+        &mut env,
+        &mut rng,
+        1,
+        KeyPurpose::Bitcoin(bitcoin::Network::Bitcoin),
+    );
+    let device_set = run.device_set();
+    let access_structure_ref = run
+        .coordinator
+        .iter_access_structures()
+        .next()
+        .unwrap()
+        .access_structure_ref();
+    let master_appkey = run
+        .coordinator
+        .get_frost_key(access_structure_ref.key_id)
+        .unwrap()
+        .complete_key
+        .master_appkey;
+
+    assert!(run
+        .coordinator
+        .start_sign(
+            access_structure_ref,
+            proof(master_appkey, false),
+            &device_set,
+            &mut rng
+        )
+        .is_err());
+}
@@ -653,6 +653,8 @@ impl Env for ProptestEnv {
                 CoordinatorToUserSigningMessage::Signed { session_id, .. } => {
                     self.finished_signatures.insert(session_id);
                 }
+                CoordinatorToUserSigningMessage::SilentPaymentEcdhShare { .. }
+                | CoordinatorToUserSigningMessage::RelayedSignatureShare { .. } => {}
             },
             _ => { /* nothing needs doing */ }
         }
@@ -676,17 +678,24 @@ impl Env for ProptestEnv {
                 pending.insert(from, *phase);
             }
             SignatureRequest { phase } => {
+                let session_id = phase.session_ids().next().expect("signing with a key");
                 self.sign_reqs
-                    .entry(phase.session_id)
+                    .entry(session_id)
                     .or_default()
                     .insert(from, *phase);
             }
             Restoration(_msg) => {
                 // TODO: proptest restoration
             }
-            VerifyAddress { .. } => {
+            VerifyAddress { .. } | VerifyAccount { .. } => {
                 // we dont actually confirm on the device
             }
+            SilentPaymentEcdh { .. } => {
+                // silent payments aren't part of the happy path
+            }
+            AddressBook { .. } => {
+                // the address book isn't part of the happy path
+            }
             NonceJobs(mut batch) => {
                 // Run the batch to completion and send a single response
                 batch.run_until_finished(&mut TestDeviceKeyGen);
@@ -772,6 +781,7 @@ impl StateMachineTest for HappyPathTest {
                 let messages = run.coordinator.maybe_request_nonce_replenishment(
                     &BTreeSet::from([device_id]),
                     ref_state.n_desired_nonce_streams_coord,
+                    |_| true,
                     rng,
                 );
                 run.extend(messages);
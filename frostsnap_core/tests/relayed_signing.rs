@@ -0,0 +1,156 @@
+use common::{TestDeviceKeyGen, TEST_ENCRYPTION_KEY};
+use frostsnap_core::coordinator::{
+    CoordinatorSend, CoordinatorToUserMessage, CoordinatorToUserSigningMessage, FrostCoordinator,
+};
+use frostsnap_core::device::{DeviceToUserMessage, KeyPurpose};
+use frostsnap_core::message::{
+    signing::{CoordinatorSigning, DeviceSigning},
+    CoordinatorToDeviceMessage, DeviceSend, DeviceToCoordinatorMessage,
+};
+use frostsnap_core::WireSignTask;
+use rand_chacha::rand_core::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use std::collections::BTreeSet;
+
+mod common;
+mod env;
+use crate::common::Run;
+use crate::env::TestEnv;
+
+/// A signer plugged into a second coordinator that knows nothing of the key or the session
+/// still contributes to the first coordinator's session, with its share verified there.
+#[test]
+fn a_device_on_another_coordinator_signs_through_a_relay() {
+    let mut rng = ChaCha20Rng::from_seed([31u8; 32]);
+    let mut env = TestEnv::default();
+    let mut run = Run::start_after_keygen_and_nonces(2, 2, &mut env, &mut rng, 1, KeyPurpose::Test);
+    let access_structure_ref = run
+        .coordinator
+        .iter_access_structures()
+        .next()
+        .unwrap()
+        .access_structure_ref();
+    let devices = run.device_set().into_iter().collect::<Vec<_>>();
+    let (local, remote) = (devices[0], devices[1]);
+    let mut relay = FrostCoordinator::new();
+
+    let session_id = run
+        .coordinator
+        .start_sign(
+            access_structure_ref,
+            WireSignTask::Test {
+                message: "signed from two places".into(),
+            },
+            &BTreeSet::from([local, remote]),
+            &mut rng,
+        )
+        .unwrap();
+    let local_request = run
+        .coordinator
+        .request_device_sign(session_id, local, TEST_ENCRYPTION_KEY);
+    run.extend(local_request);
+    run.run_until_finished(&mut env, &mut rng).unwrap();
+    assert!(!env.signatures.contains_key(&session_id));
+
+    let request_sign = run
+        .coordinator
+        .request_device_sign(session_id, remote, TEST_ENCRYPTION_KEY)
+        .request_sign;
+    let relayed = relay.relay_sign_request(remote, request_sign);
+    let message = CoordinatorToDeviceMessage::Signing(CoordinatorSigning::RequestSign(Box::new(
+        relayed.request_sign,
+    )));
+    let sends = run
+        .device(remote)
+        .recv_coordinator_message(message, &mut rng)
+        .unwrap();
+    let phase = match sends.into_iter().next() {
+        Some(DeviceSend::ToUser(to_user)) => match *to_user {
+            DeviceToUserMessage::SignatureRequest { phase } => phase,
+            other => panic!("unexpected {other:?}"),
+        },
+        other => panic!("unexpected {other:?}"),
+    };
+    let answer = run
+        .device(remote)
+        .sign_ack(*phase, &mut TestDeviceKeyGen)
+        .unwrap();
+    let device_message = match answer.into_iter().next() {
+        Some(DeviceSend::ToCoordinator(message)) => *message,
+        other => panic!("unexpected {other:?}"),
+    };
+
+    let returned = match relay
+        .recv_device_message(remote, device_message)
+        .unwrap()
+        .into_iter()
+        .next()
+    {
+        Some(CoordinatorSend::ToUser(CoordinatorToUserMessage::Signing(
+            CoordinatorToUserSigningMessage::RelayedSignatureShare {
+                session_id: relayed_session_id,
+                from,
+                signature_shares,
+                replenish_nonces,
+            },
+        ))) => {
+            assert_eq!(relayed_session_id, session_id);
+            assert_eq!(from, remote);
+            DeviceToCoordinatorMessage::Signing(DeviceSigning::SignatureShare {
+                session_id,
+                signature_shares,
+                replenish_nonces,
+            })
+        }
+        other => panic!("unexpected {other:?}"),
+    };
+
+    let sends = run
+        .coordinator
+        .recv_device_message(remote, returned)
+        .unwrap();
+    run.extend(sends);
+    run.run_until_finished(&mut env, &mut rng).unwrap();
+    assert!(env.signatures.contains_key(&session_id));
+}
+
+/// Relaying is for requests we were asked to pass on. A share for a session that is neither ours
+/// nor relayed is still refused.
+#[test]
+fn a_relay_only_accepts_answers_from_the_device_it_asked() {
+    let mut rng = ChaCha20Rng::from_seed([32u8; 32]);
+    let mut env = TestEnv::default();
+    let mut run = Run::start_after_keygen_and_nonces(2, 2, &mut env, &mut rng, 1, KeyPurpose::Test);
+    let access_structure_ref = run
+        .coordinator
+        .iter_access_structures()
+        .next()
+        .unwrap()
+        .access_structure_ref();
+    let devices = run.device_set().into_iter().collect::<Vec<_>>();
+    let mut relay = FrostCoordinator::new();
+
+    let session_id = run
+        .coordinator
+        .start_sign(
+            access_structure_ref,
+            WireSignTask::Test {
+                message: "relayed".into(),
+            },
+            &devices.iter().copied().collect(),
+            &mut rng,
+        )
+        .unwrap();
+    let request_sign = run
+        .coordinator
+        .request_device_sign(session_id, devices[0], TEST_ENCRYPTION_KEY)
+        .request_sign;
+    relay.relay_sign_request(devices[0], request_sign);
+
+    let impostor = DeviceToCoordinatorMessage::Signing(DeviceSigning::SignatureShare {
+        session_id,
+        signature_shares: vec![],
+        replenish_nonces: None,
+    });
+    assert!(relay.recv_device_message(devices[1], impostor).is_err());
+}
@@ -0,0 +1,239 @@
+use common::{Send, TEST_ENCRYPTION_KEY};
+use frostsnap_core::coordinator::CoordinatorSend;
+use frostsnap_core::device::{restoration::ToUserRestoration, DeviceToUserMessage, KeyPurpose};
+use frostsnap_core::message::{CoordinatorRestoration, CoordinatorToDeviceMessage, DeviceSend};
+use frostsnap_core::WireSignTask;
+use rand_chacha::rand_core::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use schnorr_fun::Schnorr;
+use std::collections::BTreeSet;
+
+mod common;
+mod env;
+use crate::common::Run;
+use crate::env::TestEnv;
+
+#[test]
+fn migrated_share_replaces_the_old_device_as_a_signer() {
+    let schnorr = Schnorr::<sha2::Sha256>::verify_only();
+    let mut rng = ChaCha20Rng::from_seed([41u8; 32]);
+    let mut env = TestEnv::default();
+    let mut run = Run::start_after_keygen_and_nonces(3, 2, &mut env, &mut rng, 2, KeyPurpose::Test);
+    let devices = run.device_vec();
+    let old_device = devices[2];
+    let access_structure_ref = run
+        .coordinator
+        .iter_access_structures()
+        .next()
+        .unwrap()
+        .access_structure_ref();
+    let share_index = run
+        .coordinator
+        .get_access_structure(access_structure_ref)
+        .unwrap()
+        .device_to_share_indicies()[&old_device];
+
+    let new_device = run.new_device(&mut rng);
+    let messages = run
+        .coordinator
+        .start_share_migration(
+            old_device,
+            new_device,
+            access_structure_ref,
+            TEST_ENCRYPTION_KEY,
+        )
+        .unwrap();
+    run.extend(messages);
+    run.run_until_finished(&mut env, &mut rng).unwrap();
+
+    assert_eq!(
+        env.share_exports_confirmed.get(&old_device),
+        Some(&new_device)
+    );
+    assert_eq!(
+        env.share_imports_confirmed.get(&new_device),
+        Some(&old_device)
+    );
+    assert_eq!(
+        env.share_migration_codes.get(&old_device),
+        env.share_migration_codes.get(&new_device)
+    );
+
+    let access_structure = run
+        .coordinator
+        .get_access_structure(access_structure_ref)
+        .unwrap();
+    assert_eq!(
+        access_structure.device_to_share_indicies().get(&new_device),
+        Some(&share_index)
+    );
+    assert!(!access_structure
+        .devices()
+        .any(|device| device == old_device));
+
+    let key_data = run.coordinator.iter_keys().next().unwrap().clone();
+    let task = WireSignTask::Test {
+        message: "signed by the replacement".into(),
+    };
+    let checked_task = task
+        .clone()
+        .check(key_data.complete_key.master_appkey, KeyPurpose::Test)
+        .unwrap();
+    let signing_set = BTreeSet::from_iter([devices[0], new_device]);
+    let nonces =
+        run.coordinator
+            .maybe_request_nonce_replenishment(&signing_set, 2, |_| true, &mut rng);
+    run.extend(nonces);
+    run.run_until_finished(&mut env, &mut rng).unwrap();
+    let session_id = run
+        .coordinator
+        .start_sign(access_structure_ref, task, &signing_set, &mut rng)
+        .unwrap();
+    for &device_id in &signing_set {
+        let sign_req =
+            run.coordinator
+                .request_device_sign(session_id, device_id, TEST_ENCRYPTION_KEY);
+        run.extend(sign_req);
+    }
+    run.run_until_finished(&mut env, &mut rng).unwrap();
+    assert!(
+        checked_task.verify_final_signatures(&schnorr, env.signatures.get(&session_id).unwrap())
+    );
+}
+
+#[test]
+fn only_the_receiving_device_can_read_a_migrated_share() {
+    let mut rng = ChaCha20Rng::from_seed([42u8; 32]);
+    let mut env = TestEnv::default();
+    let mut run = Run::start_after_keygen_and_nonces(2, 2, &mut env, &mut rng, 1, KeyPurpose::Test);
+    let devices = run.device_vec();
+    let access_structure_ref = run
+        .coordinator
+        .iter_access_structures()
+        .next()
+        .unwrap()
+        .access_structure_ref();
+
+    let new_device = run.new_device(&mut rng);
+    let eavesdropper = run.new_device(&mut rng);
+    let messages = run
+        .coordinator
+        .start_share_migration(
+            devices[0],
+            new_device,
+            access_structure_ref,
+            TEST_ENCRYPTION_KEY,
+        )
+        .unwrap();
+    run.extend(messages);
+    run.run_until_finished(&mut env, &mut rng).unwrap();
+
+    let relayed = |is_wanted: fn(&CoordinatorRestoration) -> bool| {
+        run.transcript
+            .iter()
+            .find_map(|send| match send {
+                Send::CoordinatorToDevice {
+                    message: message @ CoordinatorToDeviceMessage::Restoration(restoration),
+                    ..
+                } if is_wanted(restoration) => Some(message.clone()),
+                _ => None,
+            })
+            .expect("coordinator sent the message")
+    };
+    let expect = relayed(|message| matches!(message, CoordinatorRestoration::ExpectShare(_)));
+    let import = relayed(|message| matches!(message, CoordinatorRestoration::ImportShare(_)));
+
+    // the eavesdropper can be told to expect the share but its code won't match the sender's
+    let shown = run
+        .device(eavesdropper)
+        .recv_coordinator_message(expect, &mut rng)
+        .unwrap();
+    let phase = shown
+        .into_iter()
+        .find_map(|send| match send {
+            DeviceSend::ToUser(message) => match *message {
+                DeviceToUserMessage::Restoration(restoration) => match *restoration {
+                    ToUserRestoration::ImportShare { phase, .. } => Some(phase),
+                    _ => None,
+                },
+                _ => None,
+            },
+            _ => None,
+        })
+        .expect("eavesdropper shows a code");
+    assert_ne!(
+        Some(&phase.code),
+        env.share_migration_codes.get(&devices[0])
+    );
+
+    // even if its user accepts it can't read the share
+    let _ = run.device(eavesdropper).import_share_ack(phase);
+    assert!(run
+        .device(eavesdropper)
+        .recv_coordinator_message(import, &mut rng)
+        .is_err());
+}
+
+#[test]
+fn share_is_only_exported_once_the_receiving_device_accepts() {
+    let mut rng = ChaCha20Rng::from_seed([44u8; 32]);
+    let mut env = TestEnv::default();
+    let mut run = Run::start_after_keygen_and_nonces(2, 2, &mut env, &mut rng, 1, KeyPurpose::Test);
+    let devices = run.device_vec();
+    let access_structure_ref = run
+        .coordinator
+        .iter_access_structures()
+        .next()
+        .unwrap()
+        .access_structure_ref();
+
+    let new_device = run.new_device(&mut rng);
+    let messages = run
+        .coordinator
+        .start_share_migration(
+            devices[0],
+            new_device,
+            access_structure_ref,
+            TEST_ENCRYPTION_KEY,
+        )
+        .unwrap();
+
+    assert!(messages.iter().all(|send| matches!(
+        send,
+        CoordinatorSend::ToDevice {
+            message: CoordinatorToDeviceMessage::Restoration(CoordinatorRestoration::ExpectShare(_)),
+            destinations,
+        } if destinations == &BTreeSet::from_iter([new_device])
+    )));
+
+    run.extend(messages);
+    run.run_until_finished(&mut env, &mut rng).unwrap();
+    assert_eq!(
+        env.share_exports_confirmed.get(&devices[0]),
+        Some(&new_device)
+    );
+}
+
+#[test]
+fn cannot_migrate_a_share_to_a_device_already_in_the_wallet() {
+    let mut rng = ChaCha20Rng::from_seed([43u8; 32]);
+    let mut env = TestEnv::default();
+    let mut run = Run::start_after_keygen_and_nonces(2, 2, &mut env, &mut rng, 1, KeyPurpose::Test);
+    let devices = run.device_vec();
+    let access_structure_ref = run
+        .coordinator
+        .iter_access_structures()
+        .next()
+        .unwrap()
+        .access_structure_ref();
+
+    assert!(run
+        .coordinator
+        .start_share_migration(
+            devices[0],
+            devices[1],
+            access_structure_ref,
+            TEST_ENCRYPTION_KEY,
+        )
+        .is_err());
+}
@@ -0,0 +1,138 @@
+use common::TEST_ENCRYPTION_KEY;
+use frostsnap_core::coordinator::SubstituteSignerError;
+use frostsnap_core::device::KeyPurpose;
+use frostsnap_core::WireSignTask;
+use rand_chacha::rand_core::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use std::collections::BTreeSet;
+
+mod common;
+mod env;
+use crate::common::Run;
+use crate::env::TestEnv;
+
+/// One signer answers, the other never does. Swapping in the third device abandons the session
+/// and the replacement completes with the device that had signed asked again.
+#[test]
+fn a_failed_signer_is_replaced_by_another_device() {
+    let mut rng = ChaCha20Rng::from_seed([32u8; 32]);
+    let mut env = TestEnv::default();
+    let mut run = Run::start_after_keygen_and_nonces(3, 2, &mut env, &mut rng, 1, KeyPurpose::Test);
+    let access_structure_ref = run
+        .coordinator
+        .iter_access_structures()
+        .next()
+        .unwrap()
+        .access_structure_ref();
+    let devices = run.device_set().into_iter().collect::<Vec<_>>();
+    let (signer, failed, substitute) = (devices[0], devices[1], devices[2]);
+
+    let session_id = run
+        .coordinator
+        .start_sign(
+            access_structure_ref,
+            WireSignTask::Test {
+                message: "one of us went missing".into(),
+            },
+            &BTreeSet::from([signer, failed]),
+            &mut rng,
+        )
+        .unwrap();
+    for device_id in [signer, failed] {
+        let request =
+            run.coordinator
+                .request_device_sign(session_id, device_id, TEST_ENCRYPTION_KEY);
+        // the failed device never hears about it
+        if device_id == signer {
+            run.extend(request);
+        }
+    }
+    run.run_until_finished(&mut env, &mut rng).unwrap();
+    assert!(!env.signatures.contains_key(&session_id));
+
+    assert_eq!(
+        run.coordinator.eligible_substitutes(session_id, failed),
+        BTreeSet::from([substitute])
+    );
+    let replacement_id = run
+        .coordinator
+        .substitute_signer(session_id, failed, substitute, &mut rng)
+        .unwrap();
+
+    assert!(run.coordinator.get_sign_session(session_id).is_none());
+    assert_eq!(
+        run.coordinator.sign_session_replaces(replacement_id),
+        Some(session_id)
+    );
+    let replacement = run.coordinator.active_signing_sessions_by_ssid()[&replacement_id].clone();
+    assert_eq!(
+        replacement
+            .init
+            .nonces
+            .keys()
+            .copied()
+            .collect::<BTreeSet<_>>(),
+        BTreeSet::from([signer, substitute])
+    );
+    assert_eq!(replacement.received_from().count(), 0);
+
+    for device_id in [signer, substitute] {
+        let request =
+            run.coordinator
+                .request_device_sign(replacement_id, device_id, TEST_ENCRYPTION_KEY);
+        run.extend(request);
+    }
+    run.run_until_finished(&mut env, &mut rng).unwrap();
+    assert!(env.signatures.contains_key(&replacement_id));
+    assert_eq!(
+        run.coordinator.sign_session_replaces(replacement_id),
+        Some(session_id)
+    );
+}
+
+/// Only a signer that hasn't answered can be swapped out, and nothing changes when it's refused.
+#[test]
+fn a_signer_that_already_signed_is_not_replaced() {
+    let mut rng = ChaCha20Rng::from_seed([33u8; 32]);
+    let mut env = TestEnv::default();
+    let mut run = Run::start_after_keygen_and_nonces(3, 2, &mut env, &mut rng, 1, KeyPurpose::Test);
+    let access_structure_ref = run
+        .coordinator
+        .iter_access_structures()
+        .next()
+        .unwrap()
+        .access_structure_ref();
+    let devices = run.device_set().into_iter().collect::<Vec<_>>();
+
+    let session_id = run
+        .coordinator
+        .start_sign(
+            access_structure_ref,
+            WireSignTask::Test {
+                message: "already done".into(),
+            },
+            &BTreeSet::from([devices[0], devices[1]]),
+            &mut rng,
+        )
+        .unwrap();
+    let request = run
+        .coordinator
+        .request_device_sign(session_id, devices[0], TEST_ENCRYPTION_KEY);
+    run.extend(request);
+    run.run_until_finished(&mut env, &mut rng).unwrap();
+
+    assert!(matches!(
+        run.coordinator
+            .substitute_signer(session_id, devices[0], devices[2], &mut rng),
+        Err(SubstituteSignerError::AlreadySigned { .. })
+    ));
+    assert!(matches!(
+        run.coordinator
+            .substitute_signer(session_id, devices[1], devices[0], &mut rng),
+        Err(SubstituteSignerError::AlreadySigning { .. })
+    ));
+    assert!(run
+        .coordinator
+        .active_signing_sessions_by_ssid()
+        .contains_key(&session_id));
+}
@@ -0,0 +1,248 @@
+use common::TEST_ENCRYPTION_KEY;
+use frostsnap_core::bitcoin_transaction::{LocalSpk, PromptDestination, TransactionTemplate};
+use frostsnap_core::device::KeyPurpose;
+use frostsnap_core::silent_payments::{
+    SilentPaymentAddress, SilentPaymentEcdh, SilentPaymentError,
+};
+use frostsnap_core::tweak::{BitcoinBip32Path, NormalIndex};
+use frostsnap_core::{bincode, AccessStructureRef, MasterAppkey, SignTaskError, WireSignTask};
+use rand_chacha::rand_core::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use schnorr_fun::{fun::prelude::*, Schnorr};
+use std::collections::BTreeSet;
+
+mod common;
+mod env;
+use crate::common::Run;
+use crate::env::TestEnv;
+
+const NETWORK: bitcoin::Network = bitcoin::Network::Bitcoin;
+
+struct Setup {
+    run: Run,
+    env: TestEnv,
+    rng: ChaCha20Rng,
+    access_structure_ref: AccessStructureRef,
+    master_appkey: MasterAppkey,
+    address: SilentPaymentAddress,
+}
+
+fn setup() -> Setup {
+    let mut rng = ChaCha20Rng::from_seed([52u8; 32]);
+    let mut env = TestEnv::default();
+    let run = Run::start_after_keygen_and_nonces(
+        3,
+        2,
+        &mut env,
+        &mut rng,
+        1,
+        KeyPurpose::Bitcoin(NETWORK),
+    );
+    let access_structure_ref = run
+        .coordinator
+        .iter_access_structures()
+        .next()
+        .unwrap()
+        .access_structure_ref();
+    let master_appkey = run
+        .coordinator
+        .get_frost_key(access_structure_ref.key_id)
+        .unwrap()
+        .complete_key
+        .master_appkey;
+    let address = SilentPaymentAddress {
+        network: NETWORK,
+        scan: Point::random(&mut rng),
+        spend: Point::random(&mut rng),
+    };
+    Setup {
+        run,
+        env,
+        rng,
+        access_structure_ref,
+        master_appkey,
+        address,
+    }
+}
+
+impl Setup {
+    fn ecdh_from(&mut self, devices: &BTreeSet<frostsnap_core::DeviceId>) -> SilentPaymentEcdh {
+        self.env.silent_payment_ecdh_shares.clear();
+        for &device_id in devices {
+            let request = self
+                .run
+                .coordinator
+                .request_silent_payment_ecdh(
+                    self.access_structure_ref,
+                    device_id,
+                    self.address.clone(),
+                    TEST_ENCRYPTION_KEY,
+                )
+                .unwrap();
+            self.run.extend(request);
+        }
+        self.run
+            .run_until_finished(&mut self.env, &mut self.rng)
+            .unwrap();
+        SilentPaymentEcdh {
+            scan: self.address.scan,
+            shares: self
+                .env
+                .silent_payment_ecdh_shares
+                .values()
+                .cloned()
+                .collect(),
+        }
+    }
+
+    fn template(&self, ecdh: SilentPaymentEcdh) -> TransactionTemplate {
+        let mut tx_template = TransactionTemplate::new();
+        for (path, value) in [
+            (
+                BitcoinBip32Path::external(NormalIndex::new(3).unwrap()),
+                60_000,
+            ),
+            (
+                BitcoinBip32Path::internal(NormalIndex::new(9).unwrap()),
+                25_000,
+            ),
+        ] {
+            tx_template.push_imaginary_owned_input(
+                LocalSpk {
+                    master_appkey: self.master_appkey,
+                    bip32_path: path,
+                },
+                bitcoin::Amount::from_sat(value),
+            );
+        }
+        tx_template
+            .push_silent_payment_output(
+                bitcoin::Amount::from_sat(50_000),
+                self.address.clone(),
+                ecdh,
+            )
+            .unwrap();
+        tx_template.push_owned_output(
+            bitcoin::Amount::from_sat(34_000),
+            LocalSpk {
+                master_appkey: self.master_appkey,
+                bip32_path: BitcoinBip32Path::internal(NormalIndex::new(10).unwrap()),
+            },
+        );
+        tx_template
+    }
+}
+
+#[test]
+fn a_threshold_of_devices_can_pay_a_silent_payment_address() {
+    let mut setup = setup();
+    let signers = setup
+        .run
+        .device_set()
+        .into_iter()
+        .take(2)
+        .collect::<BTreeSet<_>>();
+    let ecdh = setup.ecdh_from(&signers);
+    assert_eq!(ecdh.shares.len(), 2);
+
+    let task = WireSignTask::BitcoinTransaction(setup.template(ecdh));
+    let checked_task = task
+        .clone()
+        .check(setup.master_appkey, KeyPurpose::Bitcoin(NETWORK))
+        .expect("an honestly derived output passes the device's check");
+    match &checked_task.inner {
+        frostsnap_core::SignTask::BitcoinTransaction { tx_template, .. } => {
+            let prompt = tx_template.user_prompt(NETWORK);
+            assert_eq!(prompt.recipients.len(), 1);
+            assert_eq!(
+                prompt.recipients[0].destination,
+                PromptDestination::SilentPayment(setup.address.clone()),
+                "the user is shown the address they were given, not the one-off script"
+            );
+        }
+        _ => unreachable!(),
+    }
+
+    let session_id = setup
+        .run
+        .coordinator
+        .start_sign(setup.access_structure_ref, task, &signers, &mut setup.rng)
+        .unwrap();
+    for &device_id in &signers {
+        let sign_req =
+            setup
+                .run
+                .coordinator
+                .request_device_sign(session_id, device_id, TEST_ENCRYPTION_KEY);
+        setup.run.extend(sign_req);
+    }
+    setup
+        .run
+        .run_until_finished(&mut setup.env, &mut setup.rng)
+        .unwrap();
+    assert!(checked_task.verify_final_signatures(
+        &Schnorr::<sha2::Sha256>::verify_only(),
+        setup.env.signatures.get(&session_id).unwrap()
+    ));
+}
+
+/// ECDH shares from a different pair of devices must land on the same output, or which devices
+/// happened to be plugged in would decide where the money goes.
+#[test]
+fn any_threshold_of_devices_derives_the_same_output() {
+    let mut setup = setup();
+    let devices = setup.run.device_set().into_iter().collect::<Vec<_>>();
+    let first = setup.ecdh_from(&devices[..2].iter().copied().collect());
+    let second = setup.ecdh_from(&devices[1..].iter().copied().collect());
+    assert_ne!(first, second);
+
+    let first = setup.template(first);
+    let second = setup.template(second);
+    assert_eq!(
+        first.outputs()[0].txout().script_pubkey,
+        second.outputs()[0].txout().script_pubkey
+    );
+}
+
+/// A coordinator that keeps the address the user is shown but swaps the output's script for its
+/// own is caught by the device re-deriving it.
+#[test]
+fn a_redirected_silent_payment_is_refused_by_the_device() {
+    let mut setup = setup();
+    let signers = setup
+        .run
+        .device_set()
+        .into_iter()
+        .take(2)
+        .collect::<BTreeSet<_>>();
+    let ecdh = setup.ecdh_from(&signers);
+    let task = WireSignTask::BitcoinTransaction(setup.template(ecdh));
+
+    let honest_spk = match &task {
+        WireSignTask::BitcoinTransaction(tx_template) => {
+            tx_template.outputs()[0].txout().script_pubkey
+        }
+        _ => unreachable!(),
+    };
+    let (attacker_key, _) = Point::random(&mut setup.rng).into_point_with_even_y();
+    let attacker_spk = bitcoin::ScriptBuf::new_p2tr_tweaked(
+        bitcoin::key::TweakedPublicKey::dangerous_assume_tweaked(attacker_key.into()),
+    );
+
+    // Edit the script where it sits in the message the device receives.
+    let config = bincode::config::standard();
+    let mut bytes = bincode::encode_to_vec(&task, config).unwrap();
+    let at = bytes
+        .windows(honest_spk.len())
+        .position(|window| window == honest_spk.as_bytes())
+        .expect("the script is in the encoded task");
+    bytes[at..at + attacker_spk.len()].copy_from_slice(attacker_spk.as_bytes());
+    let (tampered, _): (WireSignTask, _) = bincode::decode_from_slice(&bytes, config).unwrap();
+
+    assert!(matches!(
+        tampered.check(setup.master_appkey, KeyPurpose::Bitcoin(NETWORK)),
+        Err(SignTaskError::SilentPayment(
+            SilentPaymentError::OutputMismatch { index: 0 }
+        ))
+    ));
+}
@@ -1,5 +1,7 @@
-use bitcoin::{Amount, ScriptBuf, TxOut};
-use frostsnap_core::bitcoin_transaction::{LocalSpk, PromptSignBitcoinTx, TransactionTemplate};
+use bitcoin::{hashes::Hash, Amount, OutPoint, ScriptBuf, TxOut, Txid};
+use frostsnap_core::bitcoin_transaction::{
+    LocalSpk, PromptSignBitcoinTx, PushInput, TransactionTemplate,
+};
 use frostsnap_core::tweak::{BitcoinBip32Path, NormalIndex};
 use frostsnap_core::MasterAppkey;
 use schnorr_fun::fun::G;
@@ -171,3 +173,63 @@ fn two_change_outputs_with_a_foreign_recipient_are_disclosed() {
     assert_eq!(prompt.value_moved(), Amount::from_sat(99_000));
     assert_eq!(prompt.value_at_risk(), Amount::from_sat(50_000));
 }
+
+#[test]
+fn details_show_every_input_and_output_including_change() {
+    let mut template = template_with_input(100_000);
+    let foreign_txout = TxOut {
+        value: Amount::from_sat(30_000),
+        script_pubkey: ScriptBuf::from_hex("0014751e76e8199196d454941c45d1b3a323f1433bd6").unwrap(),
+    };
+    let foreign_outpoint = OutPoint {
+        txid: Txid::from_byte_array([7u8; 32]),
+        vout: 1,
+    };
+    template.push_foreign_input(PushInput::spend_outpoint(&foreign_txout, foreign_outpoint));
+    push_foreign(&mut template, 120_000);
+    push_change(&mut template, 9_000, 0);
+
+    let seen_by_us = template.as_seen_by(MasterAppkey::derive_from_rootkey(G.normalize()));
+    assert_eq!(
+        seen_by_us
+            .user_prompt(bitcoin::Network::Bitcoin)
+            .recipients
+            .len(),
+        1,
+        "the summary hides the change"
+    );
+
+    let details = seen_by_us.user_prompt_details(bitcoin::Network::Bitcoin);
+    assert_eq!(
+        details
+            .inputs
+            .iter()
+            .map(|input| (input.amount.to_sat(), input.owned))
+            .collect::<Vec<_>>(),
+        vec![
+            (
+                100_000,
+                Some(BitcoinBip32Path::external(NormalIndex::new(0).unwrap()))
+            ),
+            (30_000, None),
+        ]
+    );
+    assert_eq!(details.inputs[1].outpoint, foreign_outpoint);
+    assert_eq!(details.foreign_inputs(), 1);
+    assert_eq!(
+        details
+            .outputs
+            .iter()
+            .map(|output| (output.amount.to_sat(), output.owned))
+            .collect::<Vec<_>>(),
+        vec![
+            (120_000, None),
+            (
+                9_000,
+                Some(BitcoinBip32Path::internal(NormalIndex::new(0).unwrap()))
+            ),
+        ]
+    );
+    assert_eq!(details.version, bitcoin::transaction::Version::TWO);
+    assert_eq!(details.lock_time, bitcoin::absolute::LockTime::ZERO);
+}
@@ -43,6 +43,16 @@ impl<'a, S: NorFlash> NorFlashLog<'a, S> {
         Ok(())
     }
 
+    /// Bytes taken by the entries up to where the log was last read or written to. Only the whole
+    /// log if [`Self::seek_iter`] has been run to the end since it was opened.
+    pub fn used_bytes(&self) -> u32 {
+        self.word_pos * WORD_SIZE
+    }
+
+    pub fn capacity_bytes(&self) -> u32 {
+        self.flash.size()
+    }
+
     pub fn seek_iter<I: bincode::Decode<()>>(
         &mut self,
     ) -> impl Iterator<Item = Result<I, bincode::error::DecodeError>> + use<'_, 'a, S, I> {
@@ -166,6 +166,11 @@ impl<'a, S: NorFlash> FlashPartition<'a, S> {
         self.set_offset(offset);
     }
 
+    /// The sector of the underlying flash the partition starts at.
+    pub fn offset_sector(&self) -> u32 {
+        self.offset_sector
+    }
+
     pub fn n_sectors(&self) -> u32 {
         self.n_sectors
     }
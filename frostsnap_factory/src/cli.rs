@@ -31,6 +31,11 @@ pub enum Commands {
         /// Optional batch note (e.g., "testing devices", "for Company X")
         #[arg(short = 'n', long)]
         batch_note: Option<String>,
+        /// Public key (PEM) of a backup Secure Boot key whose digest gets burned alongside the
+        /// bootloader's, so devices still boot firmware signed by it once the bootloader's key is
+        /// revoked. Can be given twice.
+        #[arg(long = "backup-secure-boot-key")]
+        backup_secure_boot_keys: Vec<PathBuf>,
     },
     /// Generate an RSA-3072 signing key for ESP32 Secure Boot v2
     GenSecureBootKey {
@@ -38,7 +43,7 @@ pub enum Commands {
         #[arg(short, long)]
         output: PathBuf,
     },
-    /// Generate a Schnorr keypair for genuine certificate signing
+    /// Generate a Schnorr keypair for genuine certificate or release manifest signing
     GenGenuineCertKey {
         /// Output directory for key files (writes secret_key.hex and public_key.hex)
         #[arg(short, long)]
@@ -52,9 +57,10 @@ pub enum Commands {
         /// Output path for signed firmware binary
         #[arg(short, long)]
         output: PathBuf,
-        /// Path to RSA-3072 secure boot key (PEM)
-        #[arg(short, long)]
-        key: PathBuf,
+        /// Path to RSA-3072 secure boot key (PEM). Give it up to three times to sign with several
+        /// keys; devices boot the image if any one of them is burned into their eFuses.
+        #[arg(short, long, required = true)]
+        key: Vec<PathBuf>,
     },
     /// Verify a signed firmware or bootloader binary
     VerifyFirmware {
@@ -66,6 +72,44 @@ pub enum Commands {
         #[arg(long)]
         require_known_version: bool,
     },
+    /// Sign an instruction for devices to revoke a compromised secure boot key
+    SignKeyRevocation {
+        /// The key to revoke (PEM, public or private)
+        #[arg(short, long)]
+        revoke: PathBuf,
+        /// RSA-3072 secure boot key (PEM) to sign the instruction with. Must be another key
+        /// burned into the devices.
+        #[arg(short, long)]
+        key: PathBuf,
+        /// Output path for the signed instruction
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    /// Make a patch upgrading devices running one firmware to another, for the app to bundle
+    MakeFirmwarePatch {
+        /// Firmware the devices are running
+        #[arg(short, long)]
+        base: PathBuf,
+        /// Firmware to upgrade them to
+        #[arg(short, long)]
+        target: PathBuf,
+        /// Output path for the patch
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    /// Sign the list of official firmware releases for the app to bundle
+    SignReleaseManifest {
+        /// One release per line: `<version> <firmware digest> <min upgrade from version>
+        /// <release notes path>`. Lines starting with `#` are ignored.
+        #[arg(short, long)]
+        releases: PathBuf,
+        /// Environment (dev or prod) — signs with frostsnap_factory/release/{env}/secret_key.hex
+        #[arg(long)]
+        env: String,
+        /// Output path for the signed manifest
+        #[arg(short, long)]
+        output: PathBuf,
+    },
     /// Provision a single device (no database required)
     Provision {
         /// Case color
@@ -73,6 +117,11 @@ pub enum Commands {
         /// Environment (dev or prod) — determines key paths
         #[arg(long)]
         env: String,
+        /// Public key (PEM) of a backup Secure Boot key whose digest gets burned alongside the
+        /// bootloader's, so devices still boot firmware signed by it once the bootloader's key is
+        /// revoked. Can be given twice.
+        #[arg(long = "backup-secure-boot-key")]
+        backup_secure_boot_keys: Vec<PathBuf>,
     },
     /// Verify a connected device's genuine certificate
     GenuineCheck,
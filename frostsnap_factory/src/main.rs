@@ -30,6 +30,8 @@ pub struct FactoryState<D: db::FactoryDatabase> {
     pub genuine_keypair: KeyPair<EvenY>,
     pub db: D,
     pub batch_note: Option<String>,
+    /// Burned into each device alongside the digest the bootloader burns.
+    pub backup_secure_boot_digests: Vec<[u8; 32]>,
 }
 
 impl<D: db::FactoryDatabase> FactoryState<D> {
@@ -53,6 +55,7 @@ impl<D: db::FactoryDatabase> FactoryState<D> {
             genuine_keypair,
             db,
             batch_note,
+            backup_secure_boot_digests: vec![],
         }
     }
 
@@ -133,6 +136,13 @@ impl<D: db::FactoryDatabase> FactoryState<D> {
 
 fn load_genuine_keypair(
     path: &std::path::Path,
+) -> Result<KeyPair<EvenY>, Box<dyn std::error::Error>> {
+    load_schnorr_keypair(path, "genuine certificate")
+}
+
+fn load_schnorr_keypair(
+    path: &std::path::Path,
+    what: &str,
 ) -> Result<KeyPair<EvenY>, Box<dyn std::error::Error>> {
     let hex_content = std::fs::read_to_string(path)?;
     let hex_content = hex_content.trim();
@@ -166,7 +176,7 @@ fn load_genuine_keypair(
     }
 
     eprintln!(
-        "Loaded genuine certificate public key: {}",
+        "Loaded {what} public key: {}",
         hex::encode(&genuine_keypair.public_key().to_xonly_bytes())
     );
 
@@ -184,6 +194,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             env: env_name,
             db_connection_url,
             batch_note,
+            backup_secure_boot_keys,
         } => {
             let secret_path = format!("frostsnap_factory/genuine/{env_name}/secret_key.hex");
             let genuine_keypair = load_genuine_keypair(std::path::Path::new(&secret_path))?;
@@ -212,6 +223,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 db,
                 batch_note,
             );
+            factory_state.backup_secure_boot_digests =
+                load_backup_secure_boot_digests(&backup_secure_boot_keys)?;
 
             println!("Starting factory batch:");
             println!("Color: {color}, Quantity: {quantity}, Operator: {operator}");
@@ -268,9 +281,13 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             );
         }
         cli::Commands::SignFirmware { input, output, key } => {
-            let pem = std::fs::read(&key)?;
+            let pems = key
+                .iter()
+                .map(std::fs::read)
+                .collect::<Result<Vec<_>, _>>()?;
+            let pems = pems.iter().map(Vec::as_slice).collect::<Vec<_>>();
             let firmware = std::fs::read(&input)?;
-            let signed = secure_boot::sign_firmware(&firmware, &pem, &mut rand::thread_rng())?;
+            let signed = secure_boot::sign_firmware(&firmware, &pems, &mut rand::thread_rng())?;
             std::fs::write(&output, &signed)?;
             println!(
                 "Signed {} bytes -> {} bytes written to {}",
@@ -282,6 +299,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         cli::Commands::Provision {
             color,
             env: env_name,
+            backup_secure_boot_keys,
         } => {
             let secret_path = format!("frostsnap_factory/genuine/{env_name}/secret_key.hex");
             let genuine_keypair = load_genuine_keypair(std::path::Path::new(&secret_path))?;
@@ -294,6 +312,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 db::DevDatabase::new(99999),
                 None,
             );
+            factory_state.backup_secure_boot_digests =
+                load_backup_secure_boot_digests(&backup_secure_boot_keys)?;
             println!("Provisioning single device (color: {color})");
             process::run_with_state(&mut factory_state);
         }
@@ -322,7 +342,18 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 }
             };
 
-            let signer = classify_firmware_signer(&verified.key_digest)?;
+            let signers = verified
+                .key_digests()
+                .map(|key_digest| Ok((*key_digest, classify_firmware_signer(key_digest)?)))
+                .collect::<Result<Vec<_>, Box<dyn std::error::Error>>>()?;
+            // Devices boot it if any one of the keys is burned into them
+            let signer = [
+                frostsnap_secure_boot::Signer::Prod,
+                frostsnap_secure_boot::Signer::Dev,
+            ]
+            .into_iter()
+            .find(|pinned| signers.iter().any(|(_, signer)| signer == pinned))
+            .unwrap_or(frostsnap_secure_boot::Signer::Unknown);
 
             use sha2::{Digest, Sha256};
             let bytes: &'static [u8] = Box::leak(signed.into_boxed_slice());
@@ -355,7 +386,14 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 }
                 None => println!("  Firmware body:     unparseable (reporting signer only)"),
             }
-            println!("  Signer key digest: {}", hex::encode(&verified.key_digest));
+            for (key_digest, signer) in &signers {
+                let which = match signer {
+                    frostsnap_secure_boot::Signer::Prod => "Frostsnap PROD key",
+                    frostsnap_secure_boot::Signer::Dev => "Frostsnap DEV key",
+                    frostsnap_secure_boot::Signer::Unknown => "not a pinned key",
+                };
+                println!("  Signer key digest: {} ({which})", hex::encode(key_digest));
+            }
             match version {
                 Some(version) => println!("  Version: v{} (known release)", version),
                 None => println!("  Version: unknown (not a tagged release)"),
@@ -369,15 +407,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     println!("✅ Verified — signed by Frostsnap DEV key (not production!)");
                 }
                 frostsnap_secure_boot::Signer::Unknown => {
-                    eprintln!(
-                        "⚠️  SIGNED BY UNKNOWN KEY (digest {}) — not a Frostsnap factory key",
-                        hex::encode(&verified.key_digest)
-                    );
-                    return Err(format!(
-                        "unknown signer key {} — not a Frostsnap factory key",
-                        hex::encode(&verified.key_digest)
-                    )
-                    .into());
+                    eprintln!("⚠️  SIGNED BY UNKNOWN KEY — not a Frostsnap factory key");
+                    return Err("unknown signer key — not a Frostsnap factory key".into());
                 }
             }
             if require_known_version && version.is_none() {
@@ -393,6 +424,89 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 .into());
             }
         }
+        cli::Commands::SignKeyRevocation {
+            revoke,
+            key,
+            output,
+        } => {
+            let key_digest = load_secure_boot_key_digest(&revoke)?;
+            let digest = frostsnap_comms::secure_boot::revocation_digest(&key_digest);
+            let signature_block =
+                secure_boot::sign_digest(&std::fs::read(&key)?, &digest, &mut rand::thread_rng())?;
+            let signer = secure_boot::verify_signature_block(&signature_block, &digest)?;
+            if signer.key_digest == key_digest {
+                return Err("a key can't sign its own revocation".into());
+            }
+
+            let revocation = frostsnap_comms::secure_boot::SecureBootKeyRevocation {
+                key_digest,
+                signature_block: signature_block.to_vec(),
+            };
+            std::fs::write(
+                &output,
+                bincode::encode_to_vec(&revocation, bincode::config::standard())
+                    .map_err(|e| e.to_string())?,
+            )?;
+
+            println!("{}", output.display());
+            println!("  Revokes key digest:  {}", hex::encode(&key_digest));
+            println!(
+                "  Signed by key digest: {}",
+                hex::encode(&signer.key_digest)
+            );
+        }
+        cli::Commands::MakeFirmwarePatch {
+            base,
+            target,
+            output,
+        } => {
+            let load = |path: &std::path::Path| -> Result<_, Box<dyn std::error::Error>> {
+                let bytes: &'static [u8] = Box::leak(std::fs::read(path)?.into_boxed_slice());
+                Ok(frostsnap_coordinator::FirmwareBin::new(bytes)
+                    .validate()
+                    .map_err(|e| format!("{}: {e}", path.display()))?)
+            };
+            let base_firmware = load(&base)?;
+            let target_firmware = load(&target)?;
+            let patch =
+                frostsnap_coordinator::FirmwarePatch::create(&base_firmware, &target_firmware);
+            std::fs::write(&output, &patch)?;
+
+            println!("{}", output.display());
+            println!("  Base firmware digest:   {}", base_firmware.digest());
+            println!("  Target firmware digest: {}", target_firmware.digest());
+            println!(
+                "  Size: {} bytes ({:.1}% of the {} byte target)",
+                patch.len(),
+                patch.len() as f64 * 100.0 / target_firmware.size() as f64,
+                target_firmware.size()
+            );
+        }
+        cli::Commands::SignReleaseManifest {
+            releases,
+            env: env_name,
+            output,
+        } => {
+            if env_name != "dev" && env_name != "prod" {
+                return Err(format!("--env must be dev or prod, got {env_name}").into());
+            }
+            let secret_path = format!("frostsnap_factory/release/{env_name}/secret_key.hex");
+            let release_keypair =
+                load_schnorr_keypair(std::path::Path::new(&secret_path), "release manifest")?;
+            let manifest = read_release_list(&releases)?;
+            let schnorr = frostsnap_core::schnorr_fun::new_with_deterministic_nonces::<
+                frostsnap_core::sha2::Sha256,
+            >();
+            std::fs::write(&output, manifest.sign(schnorr, &release_keypair))?;
+
+            println!("{}", output.display());
+            for release in &manifest.releases {
+                println!(
+                    "  v{} {} (upgrades from v{} and later)",
+                    release.version, release.digest, release.min_upgrade_from
+                );
+            }
+        }
         cli::Commands::GenuineCheck => {
             let known_keys = load_known_genuine_keys();
             if known_keys.is_empty() {
@@ -415,6 +529,87 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+/// Read the release list [`cli::Commands::SignReleaseManifest`] takes. Release notes paths are
+/// relative to the list.
+fn read_release_list(
+    path: &std::path::Path,
+) -> Result<frostsnap_coordinator::ReleaseManifest, Box<dyn std::error::Error>> {
+    use frostsnap_coordinator::{FirmwareRelease, VersionNumber};
+    use sha2::{Digest, Sha256};
+
+    fn version(s: &str) -> Result<VersionNumber, Box<dyn std::error::Error>> {
+        let parts = s
+            .trim_start_matches('v')
+            .split('.')
+            .map(str::parse)
+            .collect::<Result<Vec<u8>, _>>()
+            .map_err(|e| format!("bad version {s}: {e}"))?;
+        match parts[..] {
+            [major, minor, patch] => Ok(VersionNumber::new(major, minor, patch)),
+            _ => Err(format!("bad version {s}: expected major.minor.patch").into()),
+        }
+    }
+
+    let dir = path.parent().unwrap_or(std::path::Path::new("."));
+    let mut releases = vec![];
+    for (i, line) in std::fs::read_to_string(path)?.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields = line.split_whitespace().collect::<Vec<_>>();
+        let [release_version, digest, min_upgrade_from, notes] = fields[..] else {
+            return Err(format!("{}:{}: expected 4 fields", path.display(), i + 1).into());
+        };
+        let digest: [u8; 32] = hex::decode(digest)?
+            .try_into()
+            .map_err(|_| format!("{}:{}: digest must be 32 bytes", path.display(), i + 1))?;
+        let notes = std::fs::read(dir.join(notes))?;
+        releases.push(FirmwareRelease {
+            version: version(release_version)?,
+            digest: Sha256Digest(digest),
+            min_upgrade_from: version(min_upgrade_from)?,
+            release_notes_hash: Sha256Digest(Sha256::digest(&notes).into()),
+        });
+    }
+
+    Ok(frostsnap_coordinator::ReleaseManifest { releases })
+}
+
+/// The eFuse key digest of a secure boot key, from a PEM file holding either half of it.
+fn load_secure_boot_key_digest(
+    path: &std::path::Path,
+) -> Result<[u8; 32], Box<dyn std::error::Error>> {
+    let pem = std::fs::read(path).map_err(|e| format!("read {}: {e}", path.display()))?;
+    let public_key = secure_boot::secure_boot_pubkey_from_pem(&pem)
+        .map_err(|e| format!("parse {}: {e}", path.display()))?;
+    Ok(secure_boot::compute_key_digest(&public_key))
+}
+
+fn load_backup_secure_boot_digests(
+    paths: &[std::path::PathBuf],
+) -> Result<Vec<[u8; 32]>, Box<dyn std::error::Error>> {
+    // The bootloader's digest takes one of the three slots
+    if paths.len() > secure_boot::MAX_SIGNATURE_BLOCKS - 1 {
+        return Err(format!(
+            "at most {} backup secure boot keys fit on a device",
+            secure_boot::MAX_SIGNATURE_BLOCKS - 1
+        )
+        .into());
+    }
+    let digests = paths
+        .iter()
+        .map(|path| load_secure_boot_key_digest(path))
+        .collect::<Result<Vec<_>, _>>()?;
+    for digest in &digests {
+        eprintln!(
+            "Burning backup secure boot key digest: {}",
+            hex::encode(digest)
+        );
+    }
+    Ok(digests)
+}
+
 /// Classify a verified firmware's signer against the prod/dev keys committed at
 /// `frostsnap_factory/bootloader/{env}/secure-boot-pubkey.pem`. Reads the same files
 /// the desktop `build.rs` uses, so build.rs and this CLI can't drift apart on
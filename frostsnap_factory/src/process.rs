@@ -55,6 +55,10 @@ enum ConnectionState {
         ds_public_key: RsaPublicKey,
         provisioned_serial: String,
     },
+    SettingSecureBootDigests {
+        ds_public_key: RsaPublicKey,
+        provisioned_serial: String,
+    },
     FactoryDone {
         serial: String,
     },
@@ -307,6 +311,41 @@ fn process_factory_connection<D: crate::db::FactoryDatabase>(
         } => {
             match connection.port.try_read_message() {
                 Ok(Some(ReceiveSerial::Message(DeviceFactorySend::ReceivedDsKey))) => {
+                    match connection.port.raw_send(ReceiveSerial::Message(
+                        FactorySend::SetSecureBootDigests(
+                            factory_state.backup_secure_boot_digests.clone(),
+                        ),
+                    )) {
+                        Ok(_) => {
+                            connection.state = ConnectionState::SettingSecureBootDigests {
+                                ds_public_key: ds_public_key.clone(),
+                                provisioned_serial: provisioned_serial.clone(),
+                            };
+                        }
+                        Err(e) => {
+                            return FactoryResult::Failed(
+                                Some(provisioned_serial.to_string()),
+                                format!("Secure boot digests send failed: {}", e),
+                            );
+                        }
+                    }
+                }
+                Ok(_) => {} // Keep waiting
+                Err(e) => {
+                    return FactoryResult::Failed(
+                        Some(provisioned_serial.to_string()),
+                        format!("Read error: {}", e),
+                    );
+                }
+            }
+            FactoryResult::Continue
+        }
+        ConnectionState::SettingSecureBootDigests {
+            ds_public_key,
+            provisioned_serial,
+        } => {
+            match connection.port.try_read_message() {
+                Ok(Some(ReceiveSerial::Message(DeviceFactorySend::ReceivedSecureBootDigests))) => {
                     let rsa_der_bytes = ds_public_key.to_pkcs1_der().unwrap().to_vec();
                     let schnorr = schnorr_fun::new_with_synthetic_nonces::<Sha256, ThreadRng>();
 
@@ -22,14 +22,38 @@ const SIGNATURE_BLOCK_MAGIC: [u8; 4] = [0xE7, 0x02, 0x00, 0x00];
 const CRC: Crc<u32> = Crc::<u32>::new(&crc::CRC_32_ISO_HDLC);
 const RSA_KEY_BYTES: usize = 384;
 
+/// Length of one signature block. The blocks sit back to back at the start of the final sector of
+/// a signed image, one per signing key.
+pub const SIGNATURE_BLOCK_LEN: usize = 1216;
+/// The ESP32-C3 has three eFuse key digest slots, so the ROM reads at most three signature blocks.
+pub const MAX_SIGNATURE_BLOCKS: usize = 3;
+
+/// Sign `firmware` with each of `pem_keys` (at most [`MAX_SIGNATURE_BLOCKS`]).
+///
+/// A device boots the image if any one of the signatures is by a key whose digest it has burned
+/// and not revoked, so signing with a backup key as well keeps devices booting after the primary
+/// key is revoked.
 pub fn sign_firmware<R: RngCore + CryptoRng>(
     firmware: &[u8],
-    pem_key: &[u8],
+    pem_keys: &[&[u8]],
     rng: &mut R,
 ) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
-    let pem_str = std::str::from_utf8(pem_key)?;
-    let private_key = RsaPrivateKey::from_pkcs8_pem(pem_str)
-        .or_else(|_| RsaPrivateKey::from_pkcs1_pem(pem_str))?;
+    if pem_keys.is_empty() || pem_keys.len() > MAX_SIGNATURE_BLOCKS {
+        return Err(format!(
+            "need between 1 and {MAX_SIGNATURE_BLOCKS} secure boot keys, got {}",
+            pem_keys.len()
+        )
+        .into());
+    }
+    let private_keys = pem_keys
+        .iter()
+        .map(|pem| private_key_from_pem(pem))
+        .collect::<Result<Vec<_>, _>>()?;
+    for (i, key) in private_keys.iter().enumerate() {
+        if private_keys[..i].contains(key) {
+            return Err("the same secure boot key was given more than once".into());
+        }
+    }
 
     // Pad firmware to sector boundary with 0xFF
     let padded_len = firmware.len().div_ceil(SECTOR_SIZE) * SECTOR_SIZE;
@@ -39,47 +63,86 @@ pub fn sign_firmware<R: RngCore + CryptoRng>(
 
     let image_digest: [u8; 32] = Sha256::digest(&signed).into();
 
-    let sig_block = build_signature_block(&private_key, &image_digest, rng)?;
-    signed.extend_from_slice(&sig_block);
+    let mut sig_sector = [0xFFu8; SECTOR_SIZE];
+    for (private_key, slot) in private_keys
+        .iter()
+        .zip(sig_sector.chunks_exact_mut(SIGNATURE_BLOCK_LEN))
+    {
+        slot.copy_from_slice(&build_signature_block(private_key, &image_digest, rng)?);
+    }
+    signed.extend_from_slice(&sig_sector);
 
     Ok(signed)
 }
 
+/// Sign an arbitrary digest with a secure boot key, producing a lone signature block in the same
+/// format as the ones appended to firmware. Devices can check these with the RSA hardware and
+/// key digests they already use for Secure Boot, which is what makes them useful for signing
+/// instructions to the device (e.g. revoking a key).
+pub fn sign_digest<R: RngCore + CryptoRng>(
+    pem_key: &[u8],
+    digest: &[u8; 32],
+    rng: &mut R,
+) -> Result<[u8; SIGNATURE_BLOCK_LEN], Box<dyn std::error::Error>> {
+    build_signature_block(&private_key_from_pem(pem_key)?, digest, rng)
+}
+
+fn private_key_from_pem(pem: &[u8]) -> Result<RsaPrivateKey, Box<dyn std::error::Error>> {
+    let pem_str = std::str::from_utf8(pem)?;
+    Ok(RsaPrivateKey::from_pkcs8_pem(pem_str)
+        .or_else(|_| RsaPrivateKey::from_pkcs1_pem(pem_str))?)
+}
+
 /// Result of a successful Secure Boot v2 verification.
 ///
-/// A `Verified` only proves the image is *self-consistently* signed by
-/// `public_key` — it does NOT say *who* `public_key` belongs to. Use
-/// [`classify_signer`] on `key_digest` to learn whether the signer is the
+/// A `Verified` only proves the image is *self-consistently* signed by each
+/// of `signers` — it does NOT say *who* those keys belong to. Use
+/// [`classify_signer`] on a signer's `key_digest` to learn whether it is the
 /// pinned Frostsnap prod or dev key.
 #[derive(Debug)]
 pub struct Verified {
+    /// One entry per signature block, in the order they appear in the image.
+    /// Never empty.
+    pub signers: Vec<SignatureKey>,
+    /// SHA-256 of the signed firmware body (everything before the signature sector).
+    pub image_digest: [u8; 32],
+}
+
+/// A key whose signature block checked out.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignatureKey {
     /// The RSA public key embedded in (and validated against) the signature block.
     pub public_key: RsaPublicKey,
     /// SHA-256 over the signature block's public-key fields (`block[36..812]`) —
     /// the ESP32 Secure Boot v2 "key digest" burned into device eFuses.
     /// Identifies *which* key signed the image; pass it to [`classify_signer`].
     pub key_digest: [u8; 32],
-    /// SHA-256 of the signed firmware body (everything before the signature block).
-    pub image_digest: [u8; 32],
 }
 
 impl Verified {
-    /// Whether this image is signed by `expected`, compared via the ESP32
-    /// Secure Boot v2 eFuse key digest — the device's own trust anchor, which
-    /// covers the Montgomery constants too, not just the RSA modulus/exponent.
-    /// [`verify_firmware`] has already proven the signature; this answers *who*.
+    /// Whether this image is signed by `expected` (among any others), compared via
+    /// the ESP32 Secure Boot v2 eFuse key digest — the device's own trust anchor,
+    /// which covers the Montgomery constants too, not just the RSA modulus/exponent.
+    /// [`verify_firmware`] has already proven the signatures; this answers *who*.
     pub fn signed_by(&self, expected: &RsaPublicKey) -> bool {
-        self.key_digest == compute_key_digest(expected)
+        let expected = compute_key_digest(expected);
+        self.key_digests().any(|key_digest| *key_digest == expected)
+    }
+
+    pub fn key_digests(&self) -> impl Iterator<Item = &[u8; 32]> {
+        self.signers.iter().map(|signer| &signer.key_digest)
     }
 }
 
-/// Verify the Secure Boot v2 signature block on a signed firmware image.
+/// Verify the Secure Boot v2 signature blocks on a signed firmware image.
 ///
-/// Checks magic + CRC32 + image SHA-256 + Montgomery constants + RSA-PSS
-/// signature, all against the modulus embedded in the signature block. A
-/// successful return only proves the image is *self-consistently* signed —
-/// pair it with [`classify_signer`] (or a direct `verified.public_key == expected`
-/// check) to learn *who* signed it.
+/// Every block present (up to [`MAX_SIGNATURE_BLOCKS`]) must pass: magic +
+/// CRC32 + image SHA-256 + Montgomery constants + RSA-PSS signature, all against
+/// the modulus embedded in that block. A device only needs one of them to match a
+/// digest in its eFuses, but a block that doesn't check out means the image has
+/// been tampered with. A successful return only proves the image is
+/// *self-consistently* signed — pair it with [`classify_signer`] (or
+/// [`Verified::signed_by`]) to learn *who* signed it.
 pub fn verify_firmware(signed_firmware: &[u8]) -> Result<Verified, VerifyError> {
     if signed_firmware.len() < SECTOR_SIZE * 2 {
         return Err(VerifyError::TooSmall);
@@ -88,10 +151,34 @@ pub fn verify_firmware(signed_firmware: &[u8]) -> Result<Verified, VerifyError>
         return Err(VerifyError::NotSectorAligned);
     }
 
-    let sig_block_offset = signed_firmware.len() - SECTOR_SIZE;
-    let block = &signed_firmware[sig_block_offset..];
-    let firmware = &signed_firmware[..sig_block_offset];
+    let sig_sector_offset = signed_firmware.len() - SECTOR_SIZE;
+    let sig_sector = &signed_firmware[sig_sector_offset..];
+    let firmware = &signed_firmware[..sig_sector_offset];
+
+    if sig_sector[0..4] != SIGNATURE_BLOCK_MAGIC {
+        return Err(VerifyError::BadMagic);
+    }
+
+    let image_digest: [u8; 32] = Sha256::digest(firmware).into();
+    let signers = sig_sector
+        .chunks_exact(SIGNATURE_BLOCK_LEN)
+        .take(MAX_SIGNATURE_BLOCKS)
+        .take_while(|block| block[0..4] == SIGNATURE_BLOCK_MAGIC)
+        .map(|block| verify_signature_block(block.try_into().unwrap(), &image_digest))
+        .collect::<Result<Vec<_>, _>>()?;
 
+    Ok(Verified {
+        signers,
+        image_digest,
+    })
+}
+
+/// Verify a single signature block over `digest` — one of the blocks of a signed image, or one
+/// made by [`sign_digest`].
+pub fn verify_signature_block(
+    block: &[u8; SIGNATURE_BLOCK_LEN],
+    digest: &[u8; 32],
+) -> Result<SignatureKey, VerifyError> {
     if block[0..4] != SIGNATURE_BLOCK_MAGIC {
         return Err(VerifyError::BadMagic);
     }
@@ -102,8 +189,7 @@ pub fn verify_firmware(signed_firmware: &[u8]) -> Result<Verified, VerifyError>
         return Err(VerifyError::CrcMismatch);
     }
 
-    let image_digest: [u8; 32] = Sha256::digest(firmware).into();
-    if block[4..36] != image_digest {
+    if block[4..36] != *digest {
         return Err(VerifyError::DigestMismatch);
     }
 
@@ -139,7 +225,7 @@ pub fn verify_firmware(signed_firmware: &[u8]) -> Result<Verified, VerifyError>
 
     let verifying_key = VerifyingKey::<Sha256>::new(public_key.clone());
     verifying_key
-        .verify_prehash(&image_digest, &signature)
+        .verify_prehash(digest, &signature)
         .map_err(|e| VerifyError::SignatureInvalid(e.to_string()))?;
 
     // The eFuse key digest is SHA-256 over the public-key fields as they sit
@@ -147,10 +233,9 @@ pub fn verify_firmware(signed_firmware: &[u8]) -> Result<Verified, VerifyError>
     // already proved these are consistent with `public_key`.
     let key_digest: [u8; 32] = Sha256::digest(&block[36..812]).into();
 
-    Ok(Verified {
+    Ok(SignatureKey {
         public_key,
         key_digest,
-        image_digest,
     })
 }
 
@@ -276,9 +361,9 @@ pub fn compute_key_digest(public_key: &RsaPublicKey) -> [u8; 32] {
 
 fn build_signature_block<R: RngCore + CryptoRng>(
     private_key: &RsaPrivateKey,
-    image_digest: &[u8; 32],
+    digest: &[u8; 32],
     rng: &mut R,
-) -> Result<[u8; SECTOR_SIZE], Box<dyn std::error::Error>> {
+) -> Result<[u8; SIGNATURE_BLOCK_LEN], Box<dyn std::error::Error>> {
     let public_key = private_key.to_public_key();
     let n = public_key.n();
     let e = public_key.e();
@@ -294,16 +379,16 @@ fn build_signature_block<R: RngCore + CryptoRng>(
     let m_prime_le = m_prime.to_le_bytes();
 
     let signing_key = SigningKey::<Sha256>::new(private_key.clone());
-    let signature = signing_key.sign_prehash_with_rng(rng, image_digest)?;
+    let signature = signing_key.sign_prehash_with_rng(rng, digest)?;
     let sig_bytes = signature.to_vec();
 
     let mut sig_le = [0u8; RSA_KEY_BYTES];
     sig_le.copy_from_slice(&sig_bytes);
     sig_le.reverse();
 
-    let mut block = [0u8; SECTOR_SIZE];
+    let mut block = [0u8; SIGNATURE_BLOCK_LEN];
     block[0..4].copy_from_slice(&SIGNATURE_BLOCK_MAGIC);
-    block[4..36].copy_from_slice(image_digest);
+    block[4..36].copy_from_slice(digest);
     block[36..420].copy_from_slice(&modulus_le);
     block[420..424].copy_from_slice(&exponent_le);
     block[424..808].copy_from_slice(&r_le);
@@ -313,8 +398,6 @@ fn build_signature_block<R: RngCore + CryptoRng>(
     let crc_val = CRC.checksum(&block[0..1196]);
     block[1196..1200].copy_from_slice(&crc_val.to_le_bytes());
 
-    block[1216..].fill(0xFF);
-
     Ok(block)
 }
 
@@ -356,7 +439,7 @@ mod tests {
     #[test]
     fn sign_and_verify_aligned() {
         let firmware = vec![0xABu8; 4096 * 4];
-        let signed = sign_firmware(&firmware, &TEST_KEY_PEM, &mut rand::thread_rng()).unwrap();
+        let signed = sign_firmware(&firmware, &[&TEST_KEY_PEM], &mut rand::thread_rng()).unwrap();
         assert_eq!(signed.len(), 4096 * 5);
         verify_firmware(&signed).unwrap();
     }
@@ -364,7 +447,7 @@ mod tests {
     #[test]
     fn sign_and_verify_unaligned() {
         let firmware = vec![0xCDu8; 5000];
-        let signed = sign_firmware(&firmware, &TEST_KEY_PEM, &mut rand::thread_rng()).unwrap();
+        let signed = sign_firmware(&firmware, &[&TEST_KEY_PEM], &mut rand::thread_rng()).unwrap();
         assert_eq!(signed.len(), 12288);
         assert!(signed[5000..8192].iter().all(|&b| b == 0xFF));
         verify_firmware(&signed).unwrap();
@@ -389,10 +472,10 @@ mod tests {
     #[test]
     fn compute_key_digest_matches_verify_output() {
         let firmware = vec![0xABu8; 4096 * 4];
-        let signed = sign_firmware(&firmware, &TEST_KEY_PEM, &mut rand::thread_rng()).unwrap();
+        let signed = sign_firmware(&firmware, &[&TEST_KEY_PEM], &mut rand::thread_rng()).unwrap();
         let verified = verify_firmware(&signed).unwrap();
-        let recomputed = compute_key_digest(&verified.public_key);
-        assert_eq!(verified.key_digest, recomputed);
+        let signer = &verified.signers[0];
+        assert_eq!(signer.key_digest, compute_key_digest(&signer.public_key));
     }
 
     #[test]
@@ -419,8 +502,12 @@ mod tests {
 
     #[test]
     fn roundtrip_against_espsecure_input() {
-        let signed =
-            sign_firmware(ESPSECURE_FIRMWARE, ESPSECURE_KEY, &mut rand::thread_rng()).unwrap();
+        let signed = sign_firmware(
+            ESPSECURE_FIRMWARE,
+            &[ESPSECURE_KEY],
+            &mut rand::thread_rng(),
+        )
+        .unwrap();
         assert_eq!(signed.len(), ESPSECURE_SIGNED.len(), "output size mismatch");
 
         let our_block = &signed[signed.len() - SECTOR_SIZE..];
@@ -433,4 +520,68 @@ mod tests {
 
         verify_firmware(&signed).unwrap();
     }
+
+    #[test]
+    fn sign_and_verify_with_several_keys() {
+        let firmware = vec![0x5Au8; 6000];
+        let keys: [&[u8]; 2] = [&TEST_KEY_PEM, ESPSECURE_KEY];
+        let signed = sign_firmware(&firmware, &keys, &mut rand::thread_rng()).unwrap();
+        assert_eq!(signed.len(), 4096 * 3);
+
+        let sig_sector = &signed[signed.len() - SECTOR_SIZE..];
+        assert_eq!(
+            &sig_sector[SIGNATURE_BLOCK_LEN..SIGNATURE_BLOCK_LEN + 4],
+            &SIGNATURE_BLOCK_MAGIC
+        );
+        assert!(
+            sig_sector[2 * SIGNATURE_BLOCK_LEN..]
+                .iter()
+                .all(|&b| b == 0xFF)
+        );
+
+        let verified = verify_firmware(&signed).unwrap();
+        assert_eq!(verified.signers.len(), 2);
+        for (signer, pem) in verified.signers.iter().zip(keys) {
+            let public_key = secure_boot_pubkey_from_pem(pem).unwrap();
+            assert_eq!(signer.public_key, public_key);
+            assert!(verified.signed_by(&public_key));
+        }
+    }
+
+    #[test]
+    fn every_signature_block_must_verify() {
+        let firmware = vec![0x5Au8; 4096];
+        let keys: [&[u8]; 2] = [&TEST_KEY_PEM, ESPSECURE_KEY];
+        let mut signed = sign_firmware(&firmware, &keys, &mut rand::thread_rng()).unwrap();
+        let second_block = signed.len() - SECTOR_SIZE + SIGNATURE_BLOCK_LEN;
+        signed[second_block + 900] ^= 1;
+        assert!(matches!(
+            verify_firmware(&signed),
+            Err(VerifyError::CrcMismatch)
+        ));
+    }
+
+    #[test]
+    fn signing_key_count_is_limited() {
+        let firmware = vec![0x5Au8; 4096];
+        let mut rng = rand::thread_rng();
+        assert!(sign_firmware(&firmware, &[], &mut rng).is_err());
+        assert!(sign_firmware(&firmware, &[ESPSECURE_KEY, ESPSECURE_KEY], &mut rng).is_err());
+        assert!(sign_firmware(&firmware, &[ESPSECURE_KEY; 4], &mut rng).is_err());
+    }
+
+    #[test]
+    fn signed_digest_only_verifies_for_that_digest() {
+        let digest = [7u8; 32];
+        let block = sign_digest(ESPSECURE_KEY, &digest, &mut rand::thread_rng()).unwrap();
+        let signer = verify_signature_block(&block, &digest).unwrap();
+        assert_eq!(
+            signer.public_key,
+            secure_boot_pubkey_from_pem(ESPSECURE_KEY).unwrap()
+        );
+        assert!(matches!(
+            verify_signature_block(&block, &[8u8; 32]),
+            Err(VerifyError::DigestMismatch)
+        ));
+    }
 }
@@ -0,0 +1,107 @@
+use crate::{
+    gray4_style::Gray4TextStyle, page_slider::PageSlider, palette::PALETTE, prelude::*,
+    string_ext::StringWrap, Padding, QrCode, LEGACY_FONT_SMALL,
+};
+use alloc::{boxed::Box, format, string::ToString};
+use embedded_graphics::{geometry::Size, pixelcolor::Rgb565, text::Alignment};
+use frostsnap_core::tweak::BitcoinAccount;
+use frostsnap_fonts::{Gray4Font, NOTO_SANS_18_LIGHT, NOTO_SANS_18_MEDIUM};
+use u8g2_fonts::U8g2TextStyle;
+
+const FONT_HEADER: &Gray4Font = &NOTO_SANS_18_LIGHT;
+const FONT_FINGERPRINT: &Gray4Font = &NOTO_SANS_18_MEDIUM;
+
+/// The master fingerprint and xpub of an account written out.
+#[derive(Clone, frostsnap_macros::Widget)]
+pub struct AccountXpubPage {
+    #[widget_delegate]
+    center: Center<
+        Column<(
+            Text<Gray4TextStyle>,
+            Text<Gray4TextStyle>,
+            Container<Padding<Text<U8g2TextStyle<Rgb565>>>>,
+        )>,
+    >,
+}
+
+impl AccountXpubPage {
+    fn new(
+        key_name: &str,
+        account: BitcoinAccount,
+        master_fingerprint: &bitcoin::bip32::Fingerprint,
+        account_xpub: &str,
+    ) -> Self {
+        let title = Text::new(
+            format!("{key_name}\nAccount #{}", account.index.to_u32()),
+            Gray4TextStyle::new(FONT_HEADER, PALETTE.text_secondary),
+        )
+        .with_alignment(Alignment::Center);
+        let fingerprint = Text::new(
+            format!("Fingerprint {master_fingerprint}"),
+            Gray4TextStyle::new(FONT_FINGERPRINT, PALETTE.primary),
+        );
+
+        let wrapped_xpub = StringWrap::from_str(account_xpub, 23);
+        let xpub_text = Text::new(
+            wrapped_xpub.as_str(),
+            U8g2TextStyle::new(LEGACY_FONT_SMALL, PALETTE.on_surface),
+        )
+        .with_alignment(Alignment::Center);
+        let xpub_container = Container::new(Padding::all(8, xpub_text))
+            .with_border(PALETTE.outline, 2)
+            .with_fill(PALETTE.surface)
+            .with_corner_radius(Size::new(8, 8));
+
+        let mut column = Column::new((title, fingerprint, xpub_container))
+            .with_main_axis_alignment(MainAxisAlignment::Center)
+            .with_cross_axis_alignment(CrossAxisAlignment::Center);
+        column.set_uniform_gap(12);
+
+        Self {
+            center: Center::new(column),
+        }
+    }
+}
+
+/// The account xpub as a QR code, for comparing against another wallet with a camera.
+#[derive(Clone, frostsnap_macros::Widget)]
+pub struct AccountQrPage {
+    #[widget_delegate]
+    center: Center<Padding<QrCode>>,
+}
+
+impl AccountQrPage {
+    fn new(account_xpub: &str) -> Self {
+        let qr_code = QrCode::new(account_xpub).expect("an xpub always fits");
+        Self {
+            center: Center::new(Padding::all(8, qr_code)),
+        }
+    }
+}
+
+/// Shows the master fingerprint and xpub of an account so the user can check them against the
+/// descriptor another wallet is watching. Swipe up for the xpub as a QR code.
+#[derive(frostsnap_macros::Widget)]
+pub struct AccountXpubDisplay {
+    #[widget_delegate]
+    page_slider: Box<PageSlider<(AccountXpubPage, AccountQrPage)>>,
+}
+
+impl AccountXpubDisplay {
+    pub fn new(
+        key_name: &str,
+        account: BitcoinAccount,
+        master_fingerprint: bitcoin::bip32::Fingerprint,
+        account_xpub: &bitcoin::bip32::Xpub,
+    ) -> Self {
+        let account_xpub = account_xpub.to_string();
+        let pages = (
+            AccountXpubPage::new(key_name, account, &master_fingerprint, &account_xpub),
+            AccountQrPage::new(&account_xpub),
+        );
+        let mut page_slider = Box::new(PageSlider::new(pages));
+        page_slider.enable_swipe_up_chevron();
+
+        Self { page_slider }
+    }
+}
@@ -0,0 +1,71 @@
+use crate::{
+    palette::PALETTE, prelude::*, string_ext::StringWrap, HoldToConfirm, Padding, FONT_MED,
+};
+use crate::{DefaultTextStyle, HOLD_TO_CONFIRM_TIME_SHORT_MS, LEGACY_FONT_SMALL};
+use alloc::{format, string::String};
+use embedded_graphics::pixelcolor::Rgb565;
+use embedded_graphics::{geometry::Size, text::Alignment};
+use u8g2_fonts::U8g2TextStyle;
+
+/// Hold to confirm widget for adding, relabelling or removing an address book entry. Once an
+/// address is in the book the sign prompt shows its label, so the user checks the address here.
+#[derive(frostsnap_macros::Widget)]
+pub struct AddressBookConfirm {
+    #[widget_delegate]
+    hold_to_confirm:
+        HoldToConfirm<Column<(Text, Text, Container<Padding<Text<U8g2TextStyle<Rgb565>>>>)>>,
+}
+
+impl AddressBookConfirm {
+    /// `label` is `None` when the entry is being removed. `old_label` is the label the address has
+    /// now, if any.
+    pub fn new(address: &bitcoin::Address, label: Option<&str>, old_label: Option<&str>) -> Self {
+        let (title, label_line): (&str, String) = match (label, old_label) {
+            (Some(label), None) => ("Add to\naddress book?", label.into()),
+            (Some(label), Some(old_label)) => {
+                ("Relabel address?", format!("{old_label}\nto {label}"))
+            }
+            (None, old_label) => (
+                "Remove from\naddress book?",
+                old_label.unwrap_or_default().into(),
+            ),
+        };
+
+        let title = Text::new(
+            title,
+            DefaultTextStyle::new(FONT_MED, PALETTE.on_background),
+        )
+        .with_alignment(Alignment::Center);
+        let label_text = Text::new(label_line, DefaultTextStyle::new(FONT_MED, PALETTE.primary))
+            .with_alignment(Alignment::Center);
+
+        let wrapped_address = StringWrap::from_str(&address.to_string(), 23);
+        let address_text = Text::new(
+            wrapped_address.as_str(),
+            U8g2TextStyle::new(LEGACY_FONT_SMALL, PALETTE.on_surface),
+        )
+        .with_alignment(Alignment::Center);
+
+        let address_with_padding = Padding::all(8, address_text);
+        let address_container = Container::new(address_with_padding)
+            .with_border(PALETTE.outline, 2)
+            .with_fill(PALETTE.surface)
+            .with_corner_radius(Size::new(8, 8))
+            .with_expanded();
+
+        let content = Column::new((title, label_text, address_container))
+            .with_main_axis_alignment(MainAxisAlignment::SpaceEvenly);
+
+        let hold_to_confirm = HoldToConfirm::new(HOLD_TO_CONFIRM_TIME_SHORT_MS, content);
+
+        Self { hold_to_confirm }
+    }
+
+    pub fn is_confirmed(&self) -> bool {
+        self.hold_to_confirm.is_confirmed()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.hold_to_confirm.is_finished()
+    }
+}
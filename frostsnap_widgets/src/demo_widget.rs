@@ -470,11 +470,16 @@ macro_rules! demo_widget {
                     destination: PromptDestination::Address(address),
                     amount: bitcoin::Amount::from_sat(sats),
                     owned: None,
+                    label: None,
                 };
                 let prompt = PromptSignBitcoinTx {
                     recipients: $crate::alloc::vec![
                         recipient(p2tr_address.clone(), 100_000),
-                        recipient(p2wsh_address, 200_000),
+                        // In the wallet's address book
+                        PromptRecipient {
+                            label: Some("Kraken deposit".into()),
+                            ..recipient(p2wsh_address, 200_000)
+                        },
                         recipient(p2wpkh_address, 300_000),
                         recipient(p2sh_address, 400_000),
                         recipient(p2pkh_address, 500_000),
@@ -486,10 +491,13 @@ macro_rules! demo_widget {
                             owned: Some(frostsnap_core::tweak::BitcoinBip32Path::internal(
                                 frostsnap_core::tweak::NormalIndex::new(3).unwrap(),
                             )),
+                            label: None,
                         },
                     ],
                     fee: bitcoin::Amount::from_sat(90_000), // >5% of the value moved, so the warning page shows
                     fee_rate_sats_per_vbyte: Some(12.5), // Example: 12.5 sats/vB fee rate
+                    address_book_in_use: true,
+                    key_spends: $crate::alloc::vec![],
                 };
 
                 // Create the sign prompt widget
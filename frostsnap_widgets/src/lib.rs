@@ -15,6 +15,8 @@ pub mod compressed_point;
 pub mod palette;
 
 // Widget modules
+pub mod account_xpub;
+pub mod address_book;
 pub mod address_display;
 pub mod animation_speed;
 pub mod backup;
@@ -54,11 +56,13 @@ pub mod gray4_style;
 pub mod keygen_check;
 pub mod layout;
 pub mod prelude;
+pub mod qr_code;
 pub mod screen_test;
 pub mod scroll_bar;
 pub mod share_index;
 pub mod sign_message;
 pub mod sign_prompt;
+pub mod silent_payment;
 pub mod slide_in_transition;
 pub mod standby;
 pub mod string_ext;
@@ -79,6 +83,7 @@ pub use page_slider::PageSlider;
 pub use share_index::ShareIndexWidget;
 pub use sign_message::SignMessageConfirm;
 pub use sign_prompt::SignTxPrompt;
+pub use silent_payment::SilentPaymentEcdhConfirm;
 pub use super_draw_target::SuperDrawTarget;
 pub use widget_color::{ColorInterpolate, WidgetColor};
 pub use widget_list::*;
@@ -87,6 +92,8 @@ pub use widget_list::*;
 pub use tinybmp;
 
 // Re-export all widget items
+pub use account_xpub::AccountXpubDisplay;
+pub use address_book::AddressBookConfirm;
 pub use address_display::{AddressDisplay, AddressWithIndex};
 pub use backup::*;
 pub use checkmark::*;
@@ -115,6 +122,7 @@ pub use icons::IconWidget;
 pub use keygen_check::*;
 pub use progress::{ProgressBar, ProgressIndicator};
 pub use progress_bars::*;
+pub use qr_code::QrCode;
 pub use screen_test::ScreenTest;
 pub use scroll_bar::*;
 pub use slide_in_transition::*;
@@ -0,0 +1,117 @@
+use crate::{super_draw_target::SuperDrawTarget, DynWidget, Instant, Widget};
+use alloc::{vec, vec::Vec};
+use embedded_graphics::{
+    draw_target::DrawTarget,
+    geometry::{Point, Size},
+    pixelcolor::{Rgb565, RgbColor},
+    primitives::Rectangle,
+};
+use qrcodegen_no_heap::{QrCodeEcc, Version};
+
+pub use qrcodegen_no_heap::DataTooLong;
+
+/// Modules of light border scanners need around the code.
+const QUIET_ZONE: u32 = 2;
+
+/// Anything bigger has modules too small to scan off the screen.
+const MAX_VERSION: u8 = 10;
+
+/// A QR code drawn dark on light, scaled up to the largest whole number of pixels per module that
+/// fits its constraints.
+#[derive(Clone)]
+pub struct QrCode {
+    /// Modules per side, not counting the quiet zone.
+    size: u32,
+    /// Row by row, `true` for dark.
+    modules: Vec<bool>,
+    scale: u32,
+    needs_redraw: bool,
+}
+
+impl QrCode {
+    pub fn new(text: &str) -> Result<Self, DataTooLong> {
+        let max_version = Version::new(MAX_VERSION);
+        let mut outbuffer = vec![0u8; max_version.buffer_len()];
+        let mut tempbuffer = vec![0u8; max_version.buffer_len()];
+        let qr = qrcodegen_no_heap::QrCode::encode_text(
+            text,
+            &mut tempbuffer,
+            &mut outbuffer,
+            QrCodeEcc::Low,
+            Version::MIN,
+            max_version,
+            None,
+            true,
+        )?;
+
+        let size = qr.size();
+        let modules = (0..size)
+            .flat_map(|y| (0..size).map(move |x| (x, y)))
+            .map(|(x, y)| qr.get_module(x, y))
+            .collect();
+
+        Ok(Self {
+            size: size as u32,
+            modules,
+            scale: 1,
+            needs_redraw: true,
+        })
+    }
+
+    fn side_in_modules(&self) -> u32 {
+        self.size + 2 * QUIET_ZONE
+    }
+}
+
+impl DynWidget for QrCode {
+    fn set_constraints(&mut self, max_size: Size) {
+        let side = max_size.width.min(max_size.height);
+        self.scale = (side / self.side_in_modules()).max(1);
+    }
+
+    fn sizing(&self) -> crate::Sizing {
+        let side = self.side_in_modules() * self.scale;
+        Size::new(side, side).into()
+    }
+
+    fn force_full_redraw(&mut self) {
+        self.needs_redraw = true;
+    }
+}
+
+impl Widget for QrCode {
+    type Color = Rgb565;
+
+    fn draw<D>(
+        &mut self,
+        target: &mut SuperDrawTarget<D, Self::Color>,
+        _current_time: Instant,
+    ) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Self::Color>,
+    {
+        if !self.needs_redraw {
+            return Ok(());
+        }
+
+        let side = self.side_in_modules() * self.scale;
+        target.fill_solid(
+            &Rectangle::new(Point::zero(), Size::new(side, side)),
+            Rgb565::WHITE,
+        )?;
+
+        let module_size = Size::new(self.scale, self.scale);
+        let offset = (QUIET_ZONE * self.scale) as i32;
+        for (i, _) in self.modules.iter().enumerate().filter(|(_, dark)| **dark) {
+            let (x, y) = (i as u32 % self.size, i as u32 / self.size);
+            let top_left = Point::new(
+                offset + (x * self.scale) as i32,
+                offset + (y * self.scale) as i32,
+            );
+            target.fill_solid(&Rectangle::new(top_left, module_size), Rgb565::BLACK)?;
+        }
+
+        self.needs_redraw = false;
+        Ok(())
+    }
+}
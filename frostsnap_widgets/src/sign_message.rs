@@ -16,8 +16,14 @@ pub struct SignMessageConfirm {
 
 impl SignMessageConfirm {
     pub fn new(message: String) -> Self {
+        Self::with_title("Sign message?", message)
+    }
+
+    /// For signing something that isn't a plain message but is best shown as one, e.g. a proof
+    /// of reserves.
+    pub fn with_title(title: &'static str, message: String) -> Self {
         let title = Text::new(
-            "Sign message?",
+            title,
             DefaultTextStyle::new(FONT_MED, PALETTE.on_background),
         )
         .with_alignment(Alignment::Center);
@@ -6,22 +6,32 @@ use crate::{
     page_slider::PageSlider,
     palette::PALETTE,
     prelude::*,
+    string_ext::StringWrap,
     widget_list::{WidgetList, WidgetListItem},
-    GrayToAlpha, HoldToConfirm, Image,
+    GrayToAlpha, HoldToConfirm, Image, LEGACY_FONT_SMALL,
 };
-use alloc::{boxed::Box, format, string::ToString};
+use alloc::{boxed::Box, format, rc::Rc, string::ToString};
+use bitcoin::absolute::LockTime;
+use core::cell::Cell;
 use embedded_graphics::{
-    geometry::Size,
+    draw_target::DrawTarget,
+    geometry::{Point, Size},
     pixelcolor::{Gray8, Rgb565},
+    text::Alignment,
 };
 use frostsnap_core::{
-    bitcoin_transaction::{PromptDestination, PromptSignBitcoinTx},
+    bitcoin_transaction::{
+        PromptDestination, PromptInput, PromptKeySpend, PromptRecipient, PromptSignBitcoinTx,
+        PromptTxDetails,
+    },
+    silent_payments::SilentPaymentAddress,
     tweak::BitcoinBip32Path,
 };
 use frostsnap_fonts::{
     Gray4Font, NOTO_SANS_17_REGULAR, NOTO_SANS_18_LIGHT, NOTO_SANS_18_MEDIUM, NOTO_SANS_24_BOLD,
 };
 use tinybmp::Bmp;
+use u8g2_fonts::U8g2TextStyle;
 
 const FONT_PAGE_HEADER: &Gray4Font = &NOTO_SANS_18_LIGHT;
 const FONT_CONFIRM_TITLE: &Gray4Font = &NOTO_SANS_18_MEDIUM;
@@ -35,12 +45,16 @@ const HIGH_FEE_PERCENTAGE_THRESHOLD: u64 = 5;
 const HOLD_TO_SIGN_TIME_MS: u32 = 3000;
 
 const FONT_TO_SELF_FOOTNOTE: &Gray4Font = &NOTO_SANS_17_REGULAR;
+const FONT_ADDRESS_BOOK_LABEL: &Gray4Font = &NOTO_SANS_18_MEDIUM;
 
 /// Widget list that generates sign prompt pages
 #[derive(Clone)]
 pub struct SignPromptPageList {
     prompt: PromptSignBitcoinTx,
-    total_pages: usize,
+    /// When set the signer is offered every input and output before signing.
+    details: Option<PromptTxDetails>,
+    /// Set by the [`ReviewDetailsPage`] once the signer asks to see the details.
+    show_details: Rc<Cell<bool>>,
     rand_seed: u32,
 }
 
@@ -112,34 +126,50 @@ pub struct AddressPage {
 }
 
 impl AddressPage {
+    /// `label` is the address book's name for the address. `unlisted` marks one the address book
+    /// doesn't know while the wallet has one, which is worth a second look.
     #[inline(never)]
     pub fn new_with_seed(
         index: usize,
         address: &bitcoin::Address,
         rand_seed: u32,
         owned: Option<&BitcoinBip32Path>,
+        label: Option<&str>,
+        unlisted: bool,
     ) -> Self {
-        let title = Text::new(
-            format!("To Address #{}", index + 1),
-            Gray4TextStyle::new(FONT_PAGE_HEADER, PALETTE.text_secondary),
-        );
+        let title = match label {
+            Some(label) => Text::new(
+                format!("To: {}", label),
+                Gray4TextStyle::new(FONT_ADDRESS_BOOK_LABEL, PALETTE.primary),
+            ),
+            None => Text::new(
+                format!("To Address #{}", index + 1),
+                Gray4TextStyle::new(FONT_PAGE_HEADER, PALETTE.text_secondary),
+            ),
+        };
         // Names the keychain rather than printing its number: this is a threshold
         // wallet, and a bare "1/3" reads as one-of-three signers — a claim about the
         // access structure, not about where the money went.
-        let footnote = owned.map(|path| {
-            Text::new(
+        let footnote = match owned {
+            Some(path) => Some(Text::new(
                 path.label(),
                 Gray4TextStyle::new(FONT_TO_SELF_FOOTNOTE, PALETTE.text_secondary),
-            )
-        });
+            )),
+            None if unlisted => Some(Text::new(
+                "Not in address book".to_string(),
+                Gray4TextStyle::new(FONT_CAUTION_NOTE, PALETTE.warning),
+            )),
+            None => None,
+        };
 
+        let footnote_shown = footnote.is_some();
         let mixed_seed = rand_seed.wrapping_add((index as u32).wrapping_mul(0x9e3779b9));
         let address_display = AddressDisplay::new_with_seed(address.clone(), mixed_seed);
 
         let mut column = Column::new((title, address_display, footnote))
             .with_main_axis_alignment(MainAxisAlignment::Start);
         column.set_gap(0, 10);
-        column.set_gap(1, if owned.is_some() { 8 } else { 0 });
+        column.set_gap(1, if footnote_shown { 8 } else { 0 });
         let padded = Padding::only(column).bottom(40).build();
 
         Self {
@@ -182,6 +212,38 @@ impl UnrecognizedScriptPage {
     }
 }
 
+/// A silent payment address is what the user was given and can compare; the script it pays is
+/// derived for this transaction alone and would mean nothing to them. The device has re-derived
+/// that script from this address before the prompt is shown.
+#[derive(frostsnap_macros::Widget)]
+pub struct SilentPaymentPage {
+    #[widget_delegate]
+    center: Center<Column<(Text<Gray4TextStyle>, Text<U8g2TextStyle<Rgb565>>)>>,
+}
+
+impl SilentPaymentPage {
+    #[inline(never)]
+    pub fn new(index: usize, address: &SilentPaymentAddress) -> Self {
+        let title = Text::new(
+            format!("Silent Payment #{}", index + 1),
+            Gray4TextStyle::new(FONT_PAGE_HEADER, PALETTE.text_secondary),
+        );
+        let wrapped = StringWrap::from_str(&address.to_string(), 23);
+        let body = Text::new(
+            wrapped.as_str(),
+            U8g2TextStyle::new(LEGACY_FONT_SMALL, PALETTE.on_background),
+        )
+        .with_alignment(Alignment::Center);
+        let mut column = Column::new((title, body))
+            .with_main_axis_alignment(MainAxisAlignment::Center)
+            .with_cross_axis_alignment(CrossAxisAlignment::Center);
+        column.set_uniform_gap(10);
+        Self {
+            center: Center::new(column),
+        }
+    }
+}
+
 /// Page widget for displaying network fee
 #[derive(frostsnap_macros::Widget)]
 pub struct FeePage {
@@ -228,6 +290,46 @@ impl FeePage {
     }
 }
 
+/// Page widget for what one of several keys puts at risk
+#[derive(frostsnap_macros::Widget)]
+pub struct KeySpendPage {
+    #[widget_delegate]
+    center: Center<
+        Column<(
+            Text<Gray4TextStyle>,
+            BitcoinAmountDisplay,
+            Text<Gray4TextStyle>,
+        )>,
+    >,
+}
+
+impl KeySpendPage {
+    #[inline(never)]
+    fn new(key_spend: &PromptKeySpend) -> Self {
+        let title = Text::new(
+            format!("Spent from\n{}", key_spend.key_name),
+            Gray4TextStyle::new(FONT_PAGE_HEADER, PALETTE.text_secondary),
+        )
+        .with_alignment(Alignment::Center);
+
+        let amount = BitcoinAmountDisplay::new(key_spend.at_risk.to_sat());
+
+        let btc_text = Text::new(
+            "BTC".to_string(),
+            Gray4TextStyle::new(FONT_PAGE_HEADER, PALETTE.text_secondary),
+        );
+
+        let mut column = Column::new((title, amount, btc_text))
+            .with_main_axis_alignment(MainAxisAlignment::Center)
+            .with_cross_axis_alignment(CrossAxisAlignment::Center);
+        column.set_uniform_gap(10);
+
+        Self {
+            center: Center::new(column),
+        }
+    }
+}
+
 const WARNING_ICON_DATA: &[u8] = include_bytes!("../assets/warning-icon-24x24.bmp");
 
 /// Page widget for high fee warning
@@ -363,29 +465,342 @@ impl ConfirmationPage {
     }
 }
 
+/// Offers the signer every input and output before they sign. Tapping it adds a page for each
+/// ahead of the hold to sign page; swiping on skips them.
+pub struct ReviewDetailsPage {
+    center: Center<
+        Column<(
+            Text<Gray4TextStyle>,
+            Text<Gray4TextStyle>,
+            Option<Text<Gray4TextStyle>>,
+            Container<Padding<Text<Gray4TextStyle>>>,
+        )>,
+    >,
+    show_details: Rc<Cell<bool>>,
+    pressed: bool,
+}
+
+impl ReviewDetailsPage {
+    #[inline(never)]
+    fn new(details: &PromptTxDetails, show_details: Rc<Cell<bool>>) -> Self {
+        let title = Text::new(
+            "Transaction Details".to_string(),
+            Gray4TextStyle::new(FONT_PAGE_HEADER, PALETTE.text_secondary),
+        );
+        let summary = Text::new(
+            format!(
+                "{} {}, {} {}",
+                details.inputs.len(),
+                if details.inputs.len() == 1 {
+                    "input"
+                } else {
+                    "inputs"
+                },
+                details.outputs.len(),
+                if details.outputs.len() == 1 {
+                    "output"
+                } else {
+                    "outputs"
+                },
+            ),
+            Gray4TextStyle::new(FONT_CONFIRM_TEXT, PALETTE.on_background),
+        );
+        let foreign_inputs = details.foreign_inputs();
+        let foreign_note = (foreign_inputs > 0).then(|| {
+            Text::new(
+                format!("{foreign_inputs} not from this wallet"),
+                Gray4TextStyle::new(FONT_CAUTION_NOTE, PALETTE.warning),
+            )
+        });
+        let button_text = Text::new(
+            "Show details".to_string(),
+            Gray4TextStyle::new(FONT_CONFIRM_TITLE, PALETTE.on_surface),
+        );
+        let button = Container::new(Padding::symmetric(20, 10, button_text))
+            .with_border(PALETTE.outline, 2)
+            .with_fill(PALETTE.surface)
+            .with_corner_radius(Size::new(8, 8));
+
+        let mut column = Column::new((title, summary, foreign_note, button))
+            .with_main_axis_alignment(MainAxisAlignment::Center)
+            .with_cross_axis_alignment(CrossAxisAlignment::Center);
+        column.set_gap(0, 10);
+        column.set_gap(1, if foreign_inputs > 0 { 8 } else { 0 });
+        column.set_gap(2, 20);
+
+        let mut page = Self {
+            center: Center::new(column),
+            show_details,
+            pressed: false,
+        };
+        if page.show_details.get() {
+            page.mark_shown();
+        }
+        page
+    }
+
+    fn mark_shown(&mut self) {
+        let button = &mut self.center.child.children.3;
+        button.set_fill(PALETTE.primary_container);
+        button.child.child.set_character_style(Gray4TextStyle::new(
+            FONT_CONFIRM_TITLE,
+            PALETTE.on_primary_container,
+        ));
+    }
+}
+
+impl DynWidget for ReviewDetailsPage {
+    fn set_constraints(&mut self, max_size: Size) {
+        self.center.set_constraints(max_size);
+    }
+
+    fn sizing(&self) -> crate::Sizing {
+        self.center.sizing()
+    }
+
+    fn handle_touch(
+        &mut self,
+        _point: Point,
+        _current_time: crate::Instant,
+        is_release: bool,
+    ) -> Option<crate::KeyTouch> {
+        // Only a press that started here counts, so the release at the end of a swipe onto this
+        // page doesn't turn the details on.
+        if !is_release {
+            self.pressed = true;
+        } else if core::mem::take(&mut self.pressed) && !self.show_details.get() {
+            self.show_details.set(true);
+            self.mark_shown();
+        }
+        None
+    }
+
+    fn force_full_redraw(&mut self) {
+        self.center.force_full_redraw();
+    }
+}
+
+impl Widget for ReviewDetailsPage {
+    type Color = Rgb565;
+
+    fn draw<D>(
+        &mut self,
+        target: &mut SuperDrawTarget<D, Self::Color>,
+        current_time: crate::Instant,
+    ) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Self::Color>,
+    {
+        self.center.draw(target, current_time)
+    }
+}
+
+/// One input of the transaction in the detailed review.
+#[derive(frostsnap_macros::Widget)]
+pub struct InputPage {
+    #[widget_delegate]
+    center: Center<
+        Column<(
+            Text<Gray4TextStyle>,
+            BitcoinAmountDisplay,
+            Text<Gray4TextStyle>,
+            Text<U8g2TextStyle<Rgb565>>,
+        )>,
+    >,
+}
+
+impl InputPage {
+    #[inline(never)]
+    fn new(index: usize, count: usize, input: &PromptInput) -> Self {
+        let title = Text::new(
+            format!("Input {} of {}", index + 1, count),
+            Gray4TextStyle::new(FONT_PAGE_HEADER, PALETTE.text_secondary),
+        );
+        let amount_display = BitcoinAmountDisplay::new(input.amount.to_sat());
+        // A foreign input isn't signed here, and its amount is only the coordinator's word.
+        let owner = match &input.owned {
+            Some(path) => Text::new(
+                path.label(),
+                Gray4TextStyle::new(FONT_TO_SELF_FOOTNOTE, PALETTE.text_secondary),
+            ),
+            None => Text::new(
+                "Not from this wallet".to_string(),
+                Gray4TextStyle::new(FONT_CAUTION_NOTE, PALETTE.warning),
+            ),
+        };
+        let wrapped_outpoint = StringWrap::from_str(&input.outpoint.to_string(), 23);
+        let outpoint = Text::new(
+            wrapped_outpoint.as_str(),
+            U8g2TextStyle::new(LEGACY_FONT_SMALL, PALETTE.text_secondary),
+        )
+        .with_alignment(Alignment::Center);
+
+        let mut column = Column::new((title, amount_display, owner, outpoint))
+            .with_main_axis_alignment(MainAxisAlignment::Center)
+            .with_cross_axis_alignment(CrossAxisAlignment::Center);
+        column.set_gap(0, 10);
+        column.set_gap(1, 8);
+        column.set_gap(2, 12);
+
+        Self {
+            center: Center::new(column),
+        }
+    }
+}
+
+/// One output of the transaction in the detailed review, change included.
+#[derive(frostsnap_macros::Widget)]
+pub struct OutputPage {
+    #[widget_delegate]
+    center: Center<
+        Column<(
+            Text<Gray4TextStyle>,
+            BitcoinAmountDisplay,
+            Option<Text<Gray4TextStyle>>,
+            Text<U8g2TextStyle<Rgb565>>,
+        )>,
+    >,
+}
+
+impl OutputPage {
+    /// `unlisted` as for [`AddressPage::new_with_seed`].
+    #[inline(never)]
+    fn new(index: usize, count: usize, output: &PromptRecipient, unlisted: bool) -> Self {
+        let title = Text::new(
+            format!("Output {} of {}", index + 1, count),
+            Gray4TextStyle::new(FONT_PAGE_HEADER, PALETTE.text_secondary),
+        );
+        let amount_display = BitcoinAmountDisplay::new(output.amount.to_sat());
+        let owner = match (&output.owned, &output.label) {
+            (Some(path), _) => Some(Text::new(
+                path.label(),
+                Gray4TextStyle::new(FONT_TO_SELF_FOOTNOTE, PALETTE.text_secondary),
+            )),
+            (None, Some(label)) => Some(Text::new(
+                format!("To: {}", label),
+                Gray4TextStyle::new(FONT_ADDRESS_BOOK_LABEL, PALETTE.primary),
+            )),
+            (None, None) if unlisted => Some(Text::new(
+                "Not in address book".to_string(),
+                Gray4TextStyle::new(FONT_CAUTION_NOTE, PALETTE.warning),
+            )),
+            (None, None) => None,
+        };
+        let owner_shown = owner.is_some();
+        let destination = match &output.destination {
+            PromptDestination::Address(address) => address.to_string(),
+            PromptDestination::UnrecognizedScript(_) => "Unrecognized script".to_string(),
+            PromptDestination::SilentPayment(address) => address.to_string(),
+        };
+        let wrapped_destination = StringWrap::from_str(&destination, 23);
+        let destination = Text::new(
+            wrapped_destination.as_str(),
+            U8g2TextStyle::new(LEGACY_FONT_SMALL, PALETTE.on_background),
+        )
+        .with_alignment(Alignment::Center);
+
+        let mut column = Column::new((title, amount_display, owner, destination))
+            .with_main_axis_alignment(MainAxisAlignment::Center)
+            .with_cross_axis_alignment(CrossAxisAlignment::Center);
+        column.set_gap(0, 10);
+        column.set_gap(1, if owner_shown { 8 } else { 0 });
+        column.set_gap(2, 12);
+
+        Self {
+            center: Center::new(column),
+        }
+    }
+}
+
+/// The transaction's version and locktime, the last of the detailed review.
+#[derive(frostsnap_macros::Widget)]
+pub struct TxFieldsPage {
+    #[widget_delegate]
+    center: Center<
+        Column<(
+            Text<Gray4TextStyle>,
+            Text<Gray4TextStyle>,
+            Text<Gray4TextStyle>,
+        )>,
+    >,
+}
+
+impl TxFieldsPage {
+    #[inline(never)]
+    fn new(details: &PromptTxDetails) -> Self {
+        let title = Text::new(
+            "Transaction".to_string(),
+            Gray4TextStyle::new(FONT_PAGE_HEADER, PALETTE.text_secondary),
+        );
+        let version = Text::new(
+            format!("Version {}", details.version.0),
+            Gray4TextStyle::new(FONT_CONFIRM_TEXT, PALETTE.on_background),
+        );
+        let lock_time = match details.lock_time {
+            LockTime::Blocks(height) if height.to_consensus_u32() == 0 => "No locktime".to_string(),
+            LockTime::Blocks(height) => format!("Locked until block {}", height),
+            LockTime::Seconds(time) => format!("Locked until time {}", time),
+        };
+        let lock_time = Text::new(
+            lock_time,
+            Gray4TextStyle::new(FONT_CONFIRM_TEXT, PALETTE.on_background),
+        );
+
+        let mut column = Column::new((title, version, lock_time))
+            .with_main_axis_alignment(MainAxisAlignment::Center)
+            .with_cross_axis_alignment(CrossAxisAlignment::Center);
+        column.set_uniform_gap(10);
+
+        Self {
+            center: Center::new(column),
+        }
+    }
+}
+
 type SignPromptPage = AnyOf<(
     AmountPage,
     AddressPage,
     UnrecognizedScriptPage,
+    SilentPaymentPage,
+    KeySpendPage,
     FeePage,
     WarningPage,
+    ReviewDetailsPage,
+    InputPage,
+    OutputPage,
+    TxFieldsPage,
     ConfirmationPage,
 )>;
 
 impl SignPromptPageList {
     pub fn new_with_seed(prompt: PromptSignBitcoinTx, rand_seed: u32) -> Self {
-        let num_recipients = prompt.recipients.len();
-        let has_warning = Self::has_high_fee(&prompt);
-
-        let total_pages = num_recipients * 2 + has_warning as usize + 2;
+        Self::new_with_details(prompt, None, rand_seed)
+    }
 
+    pub fn new_with_details(
+        prompt: PromptSignBitcoinTx,
+        details: Option<PromptTxDetails>,
+        rand_seed: u32,
+    ) -> Self {
         Self {
             prompt,
-            total_pages,
+            details,
+            show_details: Rc::new(Cell::new(false)),
             rand_seed,
         }
     }
 
+    /// The number of detail pages currently in the sequence, including the page offering them.
+    fn detail_pages(&self) -> usize {
+        match &self.details {
+            Some(details) if self.show_details.get() => {
+                1 + details.inputs.len() + details.outputs.len() + 1
+            }
+            Some(_) => 1,
+            None => 0,
+        }
+    }
+
     fn has_high_fee(prompt: &PromptSignBitcoinTx) -> bool {
         let fee_sats = prompt.fee.to_sat();
 
@@ -402,11 +817,17 @@ impl WidgetList for SignPromptPageList {
     type Widget = SignPromptPage;
 
     fn len(&self) -> usize {
-        self.total_pages
+        let has_warning = Self::has_high_fee(&self.prompt);
+        self.prompt.recipients.len() * 2
+            + self.prompt.key_spends.len()
+            + has_warning as usize
+            + 1
+            + self.detail_pages()
+            + 1
     }
 
     fn get(&self, index: usize) -> Option<WidgetListItem<SignPromptPage>> {
-        if index >= self.total_pages {
+        if index >= self.len() {
             return None;
         }
 
@@ -414,12 +835,16 @@ impl WidgetList for SignPromptPageList {
         let recipient_pages = num_recipients * 2;
         let has_warning = Self::has_high_fee(&self.prompt);
 
+        let key_spend_pages = recipient_pages + self.prompt.key_spends.len();
+
         let warning_page = if has_warning {
-            Some(recipient_pages)
+            Some(key_spend_pages)
         } else {
             None
         };
-        let fee_page = recipient_pages + has_warning as usize;
+        let fee_page = key_spend_pages + has_warning as usize;
+        let details_start = fee_page + 1;
+        let confirm_page = details_start + self.detail_pages();
 
         let (page, use_fb) = if index < recipient_pages {
             let recipient_idx = index / 2;
@@ -443,6 +868,8 @@ impl WidgetList for SignPromptPageList {
                             address,
                             self.rand_seed,
                             recipient.owned.as_ref(),
+                            recipient.label.as_deref(),
+                            self.prompt.is_unlisted(recipient),
                         )),
                         true,
                     ),
@@ -450,8 +877,19 @@ impl WidgetList for SignPromptPageList {
                         SignPromptPage::new(UnrecognizedScriptPage::new(recipient_idx)),
                         true,
                     ),
+                    PromptDestination::SilentPayment(address) => (
+                        SignPromptPage::new(SilentPaymentPage::new(recipient_idx, address)),
+                        true,
+                    ),
                 }
             }
+        } else if index < key_spend_pages {
+            (
+                SignPromptPage::new(KeySpendPage::new(
+                    &self.prompt.key_spends[index - recipient_pages],
+                )),
+                false,
+            )
         } else if Some(index) == warning_page {
             (
                 SignPromptPage::new(WarningPage::new(
@@ -468,6 +906,34 @@ impl WidgetList for SignPromptPageList {
                 )),
                 false,
             )
+        } else if index < confirm_page {
+            // only reachable when there are details to show
+            let details = self.details.as_ref()?;
+            let detail_idx = index - details_start;
+            let num_inputs = details.inputs.len();
+            let num_outputs = details.outputs.len();
+            let page = if detail_idx == 0 {
+                SignPromptPage::new(ReviewDetailsPage::new(details, self.show_details.clone()))
+            } else if detail_idx <= num_inputs {
+                let input_idx = detail_idx - 1;
+                SignPromptPage::new(InputPage::new(
+                    input_idx,
+                    num_inputs,
+                    &details.inputs[input_idx],
+                ))
+            } else if detail_idx <= num_inputs + num_outputs {
+                let output_idx = detail_idx - 1 - num_inputs;
+                let output = &details.outputs[output_idx];
+                SignPromptPage::new(OutputPage::new(
+                    output_idx,
+                    num_outputs,
+                    output,
+                    details.is_unlisted(output),
+                ))
+            } else {
+                SignPromptPage::new(TxFieldsPage::new(details))
+            };
+            (page, false)
         } else {
             (SignPromptPage::new(ConfirmationPage::new()), false)
         };
@@ -483,7 +949,7 @@ impl WidgetList for SignPromptPageList {
         if from_index == 0 {
             return false;
         }
-        if from_index == self.total_pages - 1 {
+        if from_index == self.len() - 1 {
             if let Some(confirmation_page) = current_widget.downcast_ref::<ConfirmationPage>() {
                 return !confirmation_page.is_confirmed();
             }
@@ -505,7 +971,17 @@ impl SignTxPrompt {
     }
 
     pub fn new_with_seed(prompt: PromptSignBitcoinTx, rand_seed: u32) -> Self {
-        let page_list = SignPromptPageList::new_with_seed(prompt, rand_seed);
+        Self::new_with_details(prompt, None, rand_seed)
+    }
+
+    /// Like [`SignTxPrompt::new_with_seed`] but also offers the signer a page for every input and
+    /// output, plus the version and locktime, before the hold to sign page.
+    pub fn new_with_details(
+        prompt: PromptSignBitcoinTx,
+        details: Option<PromptTxDetails>,
+        rand_seed: u32,
+    ) -> Self {
+        let page_list = SignPromptPageList::new_with_details(prompt, details, rand_seed);
         let mut page_slider = Box::new(PageSlider::new(page_list));
         page_slider.set_on_page_ready(|page| {
             if let Some(confirmation_page) = page.downcast_mut::<ConfirmationPage>() {
@@ -543,7 +1019,6 @@ mod test {
     use super::*;
     use crate::WidgetList;
     use core::str::FromStr;
-    use frostsnap_core::bitcoin_transaction::PromptRecipient;
 
     fn address() -> bitcoin::Address {
         bitcoin::Address::from_str("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4")
@@ -559,10 +1034,13 @@ mod test {
                     destination,
                     amount: bitcoin::Amount::from_sat(10_000),
                     owned: None,
+                    label: None,
                 })
                 .collect(),
             fee: bitcoin::Amount::from_sat(100),
             fee_rate_sats_per_vbyte: Some(1.0),
+            address_book_in_use: false,
+            key_spends: alloc::vec![],
         }
     }
 
@@ -594,4 +1072,45 @@ mod test {
             "the second output still has its destination page"
         );
     }
+
+    #[test]
+    fn details_pages_are_only_shown_once_asked_for() {
+        let prompt = prompt_of(alloc::vec![PromptDestination::Address(address())]);
+        let details = PromptTxDetails {
+            inputs: alloc::vec![PromptInput {
+                outpoint: bitcoin::OutPoint::null(),
+                amount: bitcoin::Amount::from_sat(10_100),
+                owned: None,
+            }],
+            outputs: prompt.recipients.clone(),
+            version: bitcoin::transaction::Version::TWO,
+            lock_time: LockTime::ZERO,
+            address_book_in_use: false,
+        };
+        let list = SignPromptPageList::new_with_details(prompt, Some(details), 0);
+        // amount, address, fee, review offer, hold to sign
+        assert_eq!(list.len(), 5);
+
+        let offer_index = 3;
+        let mut offer = list.get(offer_index).unwrap().widget;
+        let offer_page = offer.downcast_mut::<ReviewDetailsPage>().unwrap();
+        offer_page.handle_touch(Point::zero(), crate::Instant::from_millis(0), true);
+        assert_eq!(
+            list.len(),
+            5,
+            "a release without a press is the end of a swipe"
+        );
+
+        offer_page.handle_touch(Point::zero(), crate::Instant::from_millis(0), false);
+        offer_page.handle_touch(Point::zero(), crate::Instant::from_millis(10), true);
+        // plus one input, one output and the version and locktime
+        assert_eq!(list.len(), 8);
+        let pages = (offer_index + 1..list.len())
+            .map(|index| list.get(index).unwrap().widget)
+            .collect::<alloc::vec::Vec<_>>();
+        assert!(pages[0].downcast_ref::<InputPage>().is_some());
+        assert!(pages[1].downcast_ref::<OutputPage>().is_some());
+        assert!(pages[2].downcast_ref::<TxFieldsPage>().is_some());
+        assert!(pages[3].downcast_ref::<ConfirmationPage>().is_some());
+    }
 }
@@ -0,0 +1,55 @@
+use crate::{
+    palette::PALETTE, prelude::*, string_ext::StringWrap, HoldToConfirm, Padding, FONT_MED,
+};
+use crate::{DefaultTextStyle, HOLD_TO_CONFIRM_TIME_SHORT_MS, LEGACY_FONT_SMALL};
+use embedded_graphics::pixelcolor::Rgb565;
+use embedded_graphics::{geometry::Size, text::Alignment};
+use frostsnap_core::silent_payments::SilentPaymentAddress;
+use u8g2_fonts::U8g2TextStyle;
+
+/// Hold to confirm widget for contributing to a silent payment. Nothing is spent here, but the
+/// device is doing ECDH with a key the coordinator chose, so the user says which address it's for.
+#[derive(frostsnap_macros::Widget)]
+pub struct SilentPaymentEcdhConfirm {
+    #[widget_delegate]
+    hold_to_confirm: HoldToConfirm<Column<(Text, Container<Padding<Text<U8g2TextStyle<Rgb565>>>>)>>,
+}
+
+impl SilentPaymentEcdhConfirm {
+    pub fn new(address: &SilentPaymentAddress) -> Self {
+        let title = Text::new(
+            "Pay silent payment?",
+            DefaultTextStyle::new(FONT_MED, PALETTE.on_background),
+        )
+        .with_alignment(Alignment::Center);
+
+        let wrapped_address = StringWrap::from_str(&address.to_string(), 23);
+        let address_text = Text::new(
+            wrapped_address.as_str(),
+            U8g2TextStyle::new(LEGACY_FONT_SMALL, PALETTE.on_surface),
+        )
+        .with_alignment(Alignment::Center);
+
+        let address_with_padding = Padding::all(8, address_text);
+        let address_container = Container::new(address_with_padding)
+            .with_border(PALETTE.outline, 2)
+            .with_fill(PALETTE.surface)
+            .with_corner_radius(Size::new(8, 8))
+            .with_expanded();
+
+        let content = Column::new((title, address_container))
+            .with_main_axis_alignment(MainAxisAlignment::SpaceEvenly);
+
+        let hold_to_confirm = HoldToConfirm::new(HOLD_TO_CONFIRM_TIME_SHORT_MS, content);
+
+        Self { hold_to_confirm }
+    }
+
+    pub fn is_confirmed(&self) -> bool {
+        self.hold_to_confirm.is_confirmed()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.hold_to_confirm.is_finished()
+    }
+}
@@ -245,7 +245,9 @@ class _DeviceDetailsState extends State<DeviceDetails> {
                     vertical: 2,
                   ),
                   child: Text(
-                    device.firmware.versionName(),
+                    device.firmwareRelease.isUnknown()
+                        ? '${device.firmware.versionName()} (unofficial)'
+                        : device.firmware.versionName(),
                     style: TextStyle(
                       fontSize: 12,
                       color: theme.colorScheme.onPrimaryContainer,
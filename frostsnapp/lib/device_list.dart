@@ -82,6 +82,11 @@ class _DeviceListPageState extends State<DeviceListPage> {
           mainAxisSize: MainAxisSize.min,
           spacing: 8,
           children: [
+            if (device.firmwareRelease.isUnknown())
+              Tooltip(
+                message: 'This firmware is not an official release',
+                child: Icon(Icons.gpp_maybe, color: theme.colorScheme.error),
+              ),
             upgradeEligibility.when(
               upToDate: () => SizedBox.shrink(),
               canUpgrade: () => Icon(
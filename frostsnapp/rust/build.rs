@@ -5,7 +5,11 @@ use std::path::Path;
 fn main() {
     println!("cargo::rustc-check-cfg=cfg(bundle_firmware)");
     println!("cargo::rustc-check-cfg=cfg(genuine_cert_key)");
+    println!("cargo::rustc-check-cfg=cfg(bundle_firmware_patches)");
+    println!("cargo::rustc-check-cfg=cfg(bundle_release_manifest)");
     println!("cargo:rerun-if-env-changed=BUNDLE_FIRMWARE");
+    println!("cargo:rerun-if-env-changed=BUNDLE_FIRMWARE_PATCHES");
+    println!("cargo:rerun-if-env-changed=BUNDLE_RELEASE_MANIFEST");
     println!("cargo:rerun-if-env-changed=FROSTSNAP_ENV");
 
     let out_dir = env::var("OUT_DIR").unwrap();
@@ -32,6 +36,49 @@ fn main() {
         None => {}
     }
 
+    // Patches from earlier releases to the bundled firmware, made with
+    // `frostsnap_factory make-firmware-patch`. Every `*.patch` in the directory is
+    // bundled. Devices without a patch for their firmware are sent the whole image.
+    if let Some(dir) = env::var("BUNDLE_FIRMWARE_PATCHES")
+        .ok()
+        .filter(|v| !v.is_empty())
+    {
+        bundle_firmware_patches(&dir, &out_dir);
+    }
+
+    // Signed release manifest, made with `frostsnap_factory sign-release-manifest`. It's
+    // verified at startup against the release key for FROSTSNAP_ENV, which is bundled with it.
+    // Release keys live outside the repo, so without one for the env the manifest is left out and
+    // the app just can't tell official releases apart.
+    if let Some(path) = env::var("BUNDLE_RELEASE_MANIFEST")
+        .ok()
+        .filter(|v| !v.is_empty())
+    {
+        let env_name = frostsnap_env
+            .as_deref()
+            .expect("BUNDLE_RELEASE_MANIFEST needs FROSTSNAP_ENV to pick the release key");
+        let key_path = format!("../../frostsnap_factory/release/{env_name}/public_key.hex");
+        println!("cargo:rerun-if-changed={key_path}");
+        if Path::new(&key_path).exists() {
+            copy_to_out(
+                &path,
+                &out_dir,
+                "release_manifest.bin",
+                "bundle_release_manifest",
+            );
+            copy_to_out(
+                &key_path,
+                &out_dir,
+                "release_manifest_key.hex",
+                "bundle_release_manifest",
+            );
+        } else {
+            println!(
+                "cargo:warning=not bundling the release manifest: there's no release key at {key_path}"
+            );
+        }
+    }
+
     // Genuine certificate key — derived from FROSTSNAP_ENV
     if let Some(env_name) = &frostsnap_env {
         let key_path = format!("../../frostsnap_factory/genuine/{env_name}/public_key.hex");
@@ -58,6 +105,33 @@ fn copy_to_out(source: &str, out_dir: &str, dest_name: &str, cfg_flag: &str) {
         .unwrap_or_else(|e| panic!("Failed to copy {dest_name} to OUT_DIR: {e}"));
 }
 
+fn bundle_firmware_patches(dir: &str, out_dir: &str) {
+    println!("cargo:rerun-if-changed={dir}");
+    println!("cargo:rustc-cfg=bundle_firmware_patches");
+
+    let mut patches = fs::read_dir(dir)
+        .unwrap_or_else(|e| panic!("read firmware patch dir {dir}: {e}"))
+        .map(|entry| entry.expect("read firmware patch dir entry").path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "patch"))
+        .collect::<Vec<_>>();
+    patches.sort();
+
+    let mut includes = String::from("&[\n");
+    for (i, source_path) in patches.iter().enumerate() {
+        println!("cargo:rerun-if-changed={}", source_path.display());
+        let dest_path = Path::new(out_dir).join(format!("firmware_{i}.patch"));
+        fs::copy(source_path, &dest_path)
+            .unwrap_or_else(|e| panic!("Failed to copy {} to OUT_DIR: {e}", source_path.display()));
+        includes += &format!(
+            "    include_bytes!({:?}),\n",
+            dest_path.display().to_string()
+        );
+    }
+    includes += "]\n";
+    fs::write(Path::new(out_dir).join("firmware_patches.rs"), includes)
+        .unwrap_or_else(|e| panic!("Failed to write firmware_patches.rs to OUT_DIR: {e}"));
+}
+
 fn verify_signed_firmware(source: &str, env_name: &str) {
     if env_name != "dev" && env_name != "prod" {
         panic!(
@@ -8,6 +8,7 @@ use flutter_rust_bridge::frb; // or, for example, easy_ext's;
 use frostsnap_coordinator::bitcoin::chain_sync::{
     default_backup_electrum_server, default_electrum_server, SUPPORTED_NETWORKS,
 };
+use frostsnap_coordinator::bitcoin::wallet;
 pub use frostsnap_coordinator::bitcoin::wallet::ConfirmationTime;
 pub use frostsnap_coordinator::frostsnap_core::{self, MasterAppkey};
 use frostsnap_core::bitcoin_transaction::{ScopedTo, TransactionTemplate};
@@ -245,26 +246,6 @@ impl Transaction {
         })
     }
 
-    /// The fee, from prevouts and a transaction that need not be built yet.
-    ///
-    /// Free-standing so a constructor can compute the feerate before there is a `Self` to ask.
-    /// `None` when any input's prevout is absent, which is what makes the fee unknowable.
-    fn fee_from(
-        prevouts: &HashMap<bitcoin::OutPoint, bitcoin::TxOut>,
-        tx: &RTransaction,
-    ) -> Option<u64> {
-        let inputs: u64 = tx
-            .input
-            .iter()
-            .map(|txin| {
-                prevouts
-                    .get(&txin.previous_output)
-                    .map(|o| o.value.to_sat())
-            })
-            .sum::<Option<u64>>()?;
-        Some(inputs.saturating_sub(tx.output.iter().map(|o| o.value.to_sat()).sum()))
-    }
-
     /// Computes the sum of all inputs, or only those whose previous output script pubkey is in
     /// `filter`, if provided. The result is `None` if any input is missing a previous output.
     fn _sum_inputs(&self, filter: Option<&HashMap<bitcoin::ScriptBuf, u32>>) -> Option<u64> {
@@ -353,22 +334,14 @@ impl Transaction {
     /// Returns `None` if any owned input is missing a previous output.
     #[frb(sync, type_64bit_int)]
     pub fn balance_delta(&self) -> Option<i64> {
-        let owned_inputs_sum: i64 = self
-            ._sum_inputs(Some(&self.is_mine))?
-            .try_into()
-            .expect("net spent value must convert to i64");
-        let owned_outputs_sum: i64 = self
-            ._sum_outputs(Some(&self.is_mine))
-            .try_into()
-            .expect("net created value must convert to i64");
-        Some(owned_outputs_sum.saturating_sub(owned_inputs_sum))
+        wallet::net_value(&self.inner, &self.prevouts, &self.is_mine)
     }
 
     /// Computes the transaction fee as the difference between total input and output value.
     /// Returns `None` if any input is missing a previous output.
     #[frb(sync, type_64bit_int)]
     pub fn fee(&self) -> Option<u64> {
-        Self::fee_from(&self.prevouts, &self.inner)
+        wallet::fee(&self.inner, &self.prevouts)
     }
 
     #[frb(sync, type_64bit_int)]
@@ -482,7 +455,7 @@ impl From<frostsnap_coordinator::bitcoin::wallet::Transaction> for Transaction {
         // In the canonical graph means broadcast or seen on chain, so `inner` carries real
         // witnesses and its own size is the signed size.
         let feerate =
-            Self::fee_from(&value.prevouts, &inner).map(|fee| fee as f64 / inner.vsize() as f64);
+            wallet::fee(&inner, &value.prevouts).map(|fee| fee as f64 / inner.vsize() as f64);
         Self {
             inner,
             txid: value.txid.to_string(),
@@ -292,4 +292,20 @@ impl Coordinator {
     pub fn get_device_name(&self, id: DeviceId) -> Option<String> {
         self.0.get_device_name(id)
     }
+
+    /// The audit log as JSON, oldest entry first.
+    pub fn export_audit_log(&self) -> Result<String> {
+        self.0.export_audit_log()
+    }
+
+    /// Errors if any entry in the audit log has been edited, removed or reordered.
+    pub fn verify_audit_log(&self) -> Result<()> {
+        self.0.verify_audit_log()
+    }
+
+    /// Everything the app knows besides the wallet history, encrypted under `passphrase`.
+    /// Restored with `Api::restore_coordinator_backup`.
+    pub fn export_backup(&self, passphrase: String) -> Result<Vec<u8>> {
+        self.0.export_backup(&passphrase)
+    }
 }
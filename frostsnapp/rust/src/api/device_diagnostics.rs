@@ -0,0 +1,91 @@
+use super::coordinator::Coordinator;
+use crate::{frb_generated::StreamSink, sink_wrap::SinkWrap};
+use flutter_rust_bridge::frb;
+pub use frostsnap_coordinator::device_diagnostics::{
+    DeviceDiagnosticsState, StoredDiagnosticReport,
+};
+pub use frostsnap_coordinator::frostsnap_comms::diagnostics::{
+    DiagnosticReport, EfuseKeyStatus, NonceSlotUsage, PartitionCheck, StorageFill,
+};
+use frostsnap_coordinator::frostsnap_comms::Sha256Digest;
+use frostsnap_core::DeviceId;
+
+#[frb(mirror(DeviceDiagnosticsState), non_opaque)]
+pub enum _DeviceDiagnosticsState {
+    Running,
+    Finished(Box<DiagnosticReport>),
+}
+
+#[frb(mirror(StoredDiagnosticReport), unignore)]
+pub struct _StoredDiagnosticReport {
+    pub received_at: u64,
+    pub report: DiagnosticReport,
+}
+
+#[frb(mirror(DiagnosticReport), unignore)]
+pub struct _DiagnosticReport {
+    pub firmware_digest: Sha256Digest,
+    pub partitions: Vec<PartitionCheck>,
+    pub mutation_log: StorageFill,
+    pub nonce_slots: NonceSlotUsage,
+    pub efuse_keys: EfuseKeyStatus,
+    pub touch_controller: Option<u8>,
+    pub secure_boot_enabled: bool,
+}
+
+#[frb(external)]
+impl DiagnosticReport {
+    #[frb(sync)]
+    pub fn problems(&self) -> Vec<String> {}
+}
+
+#[frb(mirror(PartitionCheck), unignore)]
+pub struct _PartitionCheck {
+    pub name: String,
+    pub size: u32,
+    pub crc32: Option<u32>,
+    pub unreadable_sectors: u32,
+}
+
+#[frb(mirror(StorageFill), unignore)]
+pub struct _StorageFill {
+    pub used_bytes: u32,
+    pub capacity_bytes: u32,
+}
+
+#[frb(external)]
+impl StorageFill {
+    #[frb(sync)]
+    pub fn percent_used(&self) -> u32 {}
+}
+
+#[frb(mirror(NonceSlotUsage), unignore)]
+pub struct _NonceSlotUsage {
+    pub total: u32,
+    pub in_use: u32,
+}
+
+#[frb(mirror(EfuseKeyStatus), unignore)]
+pub struct _EfuseKeyStatus {
+    pub share_encryption: Option<u8>,
+    pub fixed_entropy: Option<u8>,
+    pub ds: Option<u8>,
+}
+
+impl Coordinator {
+    /// Have the device check its flash, storage and hardware. Nothing on the device is changed.
+    /// The report is stored as it arrives so it also shows up in [`Self::device_diagnostics`].
+    pub fn run_device_diagnostics(
+        &self,
+        device_id: DeviceId,
+        sink: StreamSink<DeviceDiagnosticsState>,
+    ) {
+        self.0.run_device_diagnostics(device_id, SinkWrap(sink));
+    }
+
+    /// Every diagnostic report the device has sent, oldest first.
+    #[frb(sync)]
+    pub fn device_diagnostics(&self, device_id: DeviceId) -> Vec<StoredDiagnosticReport> {
+        self.0.device_diagnostics(device_id)
+    }
+}
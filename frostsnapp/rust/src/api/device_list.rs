@@ -1,17 +1,59 @@
-pub use crate::api::firmware::{FirmwareUpgradeEligibility, FirmwareVersion};
+pub use crate::api::firmware::{
+    FirmwareReleaseStatus, FirmwareUpgradeEligibility, FirmwareVersion,
+};
 use anyhow::Result;
 use flutter_rust_bridge::frb;
+use frostsnap_coordinator::device_faults::{DeviceFaultRecord, DeviceFaultsState};
+use frostsnap_coordinator::frostsnap_comms::{CrashReport, Sha256Digest};
 use frostsnap_coordinator::DeviceMode;
+use frostsnap_core::coordinator::faults::DeviceFault;
 use frostsnap_core::{AccessStructureRef, DeviceId};
 
 use crate::{frb_generated::StreamSink, sink_wrap::SinkWrap};
 
+#[frb(mirror(DeviceFaultsState), unignore)]
+pub struct _DeviceFaultsState {
+    pub devices: Vec<DeviceFaultRecord>,
+}
+
+#[frb(mirror(DeviceFaultRecord), unignore)]
+pub struct _DeviceFaultRecord {
+    pub device_id: DeviceId,
+    pub faults: Vec<DeviceFault>,
+    pub quarantined: bool,
+}
+
+#[frb(mirror(DeviceFault), opaque)]
+pub struct _DeviceFault {}
+
+#[frb(external)]
+impl DeviceFault {
+    #[frb(sync)]
+    pub fn to_string(&self) -> String {}
+}
+
+#[frb(mirror(CrashReport), unignore)]
+pub struct _CrashReport {
+    pub message: String,
+    pub location: Option<String>,
+    pub firmware_digest: Sha256Digest,
+    pub uptime_ms: u64,
+    pub last_workflow: String,
+}
+
+#[frb(external)]
+impl CrashReport {
+    #[frb(sync)]
+    pub fn to_string(&self) -> String {}
+}
+
 #[derive(Clone, Debug)]
 pub enum DeviceListChangeKind {
     Added,
     Removed,
     Named,
     RecoveryMode,
+    CrashReported,
 }
 
 #[derive(Clone, Debug)]
@@ -58,9 +100,16 @@ pub enum RecoveryMode {
 pub struct ConnectedDevice {
     pub name: Option<String>,
     pub firmware: FirmwareVersion,
+    /// What the signed release manifest says about `firmware`. Unknown firmware is worth
+    /// pointing out.
+    pub firmware_release: FirmwareReleaseStatus,
     pub latest_firmware: Option<FirmwareVersion>,
+    pub latest_firmware_release: FirmwareReleaseStatus,
     pub id: DeviceId,
     pub recovery_mode: RecoveryMode,
+    /// Panics the device reported when it connected. They're cleared off the device once
+    /// reported so this is the only place they're kept.
+    pub crash_reports: Vec<CrashReport>,
 }
 
 impl ConnectedDevice {
@@ -101,7 +150,11 @@ impl ConnectedDevice {
             };
         };
 
-        latest_firmware.check_upgrade_eligibility(&self.firmware.digest)
+        latest_firmware.check_release_upgrade_eligibility(
+            &self.firmware.digest,
+            self.latest_firmware_release,
+            self.firmware_release,
+        )
     }
 
     #[frb(ignore)]
@@ -139,6 +192,18 @@ impl super::coordinator::Coordinator {
         Ok(())
     }
 
+    /// Misbehaving devices, sent again whenever a fault is recorded or a device is quarantined
+    /// or released.
+    pub fn sub_device_faults(&self, sink: StreamSink<DeviceFaultsState>) -> Result<()> {
+        self.0.sub_device_faults(SinkWrap(sink));
+        Ok(())
+    }
+
+    /// Keep a device out of signing (or let it back in). Its faults stay on record.
+    pub fn set_device_quarantined(&self, id: DeviceId, quarantined: bool) -> Result<()> {
+        self.0.set_device_quarantined(id, quarantined)
+    }
+
     #[frb(sync)]
     pub fn get_connected_device(&self, id: DeviceId) -> Option<ConnectedDevice> {
         self.0.get_connected_device(id)
@@ -56,6 +56,27 @@ pub struct _FirmwareVersion {
     pub version: Option<VersionNumber>,
 }
 
+#[frb(mirror(FirmwareRelease))]
+pub struct _FirmwareRelease {
+    pub version: VersionNumber,
+    pub digest: Sha256Digest,
+    pub min_upgrade_from: VersionNumber,
+    pub release_notes_hash: Sha256Digest,
+}
+
+#[frb(mirror(FirmwareReleaseStatus))]
+pub enum _FirmwareReleaseStatus {
+    Unchecked,
+    Official(FirmwareRelease),
+    Unknown,
+}
+
+#[frb(external)]
+impl FirmwareReleaseStatus {
+    #[frb(sync)]
+    pub fn is_unknown(&self) -> bool {}
+}
+
 #[frb(mirror(FirmwareUpgradeEligibility))]
 pub enum _FirmwareUpgradeEligibility {
     UpToDate,
@@ -6,7 +6,10 @@ use crate::{
     frb_generated::{RustAutoOpaque, StreamSink},
 };
 use anyhow::{Context as _, Result};
-use frostsnap_coordinator::{DesktopSerial, UsbSerialManager, ValidatedFirmwareBin};
+use frostsnap_coordinator::{
+    portable_backup, DesktopSerial, FirmwarePatch, ReleaseManifest, UsbSerialManager,
+    ValidatedFirmwareBin,
+};
 use frostsnap_core::schnorr_fun::fun::{marker::EvenY, Point};
 use std::{
     path::PathBuf,
@@ -73,7 +76,12 @@ impl super::Api {
         let ffi_serial = FfiSerial::default();
         let mut usb_manager = UsbSerialManager::new(Box::new(ffi_serial.clone()));
         if let Some(firmware) = crate::FIRMWARE.map(ValidatedFirmwareBin::new).transpose()? {
-            usb_manager = usb_manager.with_firmware_bin(firmware);
+            usb_manager = usb_manager
+                .with_firmware_bin(firmware)
+                .with_firmware_patches(load_firmware_patches()?);
+        }
+        if let Some(release_manifest) = load_release_manifest()? {
+            usb_manager = usb_manager.with_release_manifest(release_manifest);
         }
         if let Some(key) = load_genuine_cert_key() {
             usb_manager = usb_manager.with_genuine_cert_key(key);
@@ -87,13 +95,54 @@ impl super::Api {
         let app_dir = PathBuf::from_str(&app_dir)?;
         let mut usb_manager = UsbSerialManager::new(Box::new(DesktopSerial));
         if let Some(firmware) = crate::FIRMWARE.map(ValidatedFirmwareBin::new).transpose()? {
-            usb_manager = usb_manager.with_firmware_bin(firmware);
+            usb_manager = usb_manager
+                .with_firmware_bin(firmware)
+                .with_firmware_patches(load_firmware_patches()?);
+        }
+        if let Some(release_manifest) = load_release_manifest()? {
+            usb_manager = usb_manager.with_release_manifest(release_manifest);
         }
         if let Some(key) = load_genuine_cert_key() {
             usb_manager = usb_manager.with_genuine_cert_key(key);
         }
         load_internal(app_dir, usb_manager)
     }
+
+    /// Put a backup from `Coordinator::export_backup` into the app's database. Call it before
+    /// loading, on an install without keys.
+    pub fn restore_coordinator_backup(
+        &self,
+        app_dir: String,
+        backup: Vec<u8>,
+        passphrase: String,
+    ) -> Result<()> {
+        let db_file = PathBuf::from_str(&app_dir)?.join("frostsnap.sqlite");
+        let mut db = rusqlite::Connection::open(&db_file)
+            .with_context(|| format!("failed to open database at {}", db_file.display()))?;
+        portable_backup::import(&mut db, &backup, &passphrase)
+    }
+}
+
+fn load_firmware_patches() -> Result<Vec<FirmwarePatch>> {
+    crate::FIRMWARE_PATCHES
+        .iter()
+        .map(|bytes| FirmwarePatch::from_bytes(bytes).context("bundled firmware patch"))
+        .collect()
+}
+
+fn load_release_manifest() -> Result<Option<ReleaseManifest>> {
+    let Some((manifest, key_hex)) = crate::RELEASE_MANIFEST else {
+        return Ok(None);
+    };
+    let key_bytes: [u8; 32] = frostsnap_core::hex::decode(key_hex.trim())
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .context("bundled release manifest key is not 32 hex bytes")?;
+    let key = Point::<EvenY>::from_xonly_bytes(key_bytes)
+        .context("bundled release manifest key is not a valid point")?;
+    let release_manifest =
+        ReleaseManifest::verify(manifest, key).context("bundled release manifest")?;
+    Ok(Some(release_manifest))
 }
 
 #[cfg(genuine_cert_key)]
@@ -129,10 +178,11 @@ fn load_internal(
     })?;
     let db = Arc::new(Mutex::new(db));
 
-    let coordinator = FfiCoordinator::new(db.clone(), usb_serial_manager)?;
+    let settings = RustAutoOpaque::new(Settings::new(db.clone(), app_dir)?);
+    let coordinator = FfiCoordinator::new(db.clone(), usb_serial_manager, settings.clone())?;
     let coordinator = Coordinator(coordinator);
     let app_state = AppCtx {
-        settings: RustAutoOpaque::new(Settings::new(db.clone(), app_dir)?),
+        settings,
         psbt_manager: RustAutoOpaque::new(PsbtManager::new(db.clone())),
     };
     println!("loaded db");
@@ -3,6 +3,7 @@ pub mod bitcoin;
 pub mod broadcast;
 pub mod camera;
 pub mod coordinator;
+pub mod device_diagnostics;
 pub mod device_list;
 pub mod firmware;
 pub mod init;
@@ -11,6 +12,7 @@ pub mod log;
 pub mod name;
 pub mod nonce_replenish;
 pub mod port;
+pub mod proof_of_reserves;
 pub mod psbt_manager;
 pub mod qr;
 pub mod recovery;
@@ -18,6 +20,7 @@ pub mod send;
 pub mod settings;
 pub mod signing;
 pub mod super_wallet;
+pub mod sweep_vault;
 pub mod transaction;
 
 use flutter_rust_bridge::frb;
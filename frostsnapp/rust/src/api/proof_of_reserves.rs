@@ -0,0 +1,105 @@
+//! BIP-127 proofs of reserves as Dart drives them: build one with
+//! [`SuperWallet::build_proof_of_reserves`], sign it with
+//! [`Coordinator::start_signing_proof_of_reserves`] — the devices show it as a proof, not a spend
+//! — and hand the auditor [`ProofOfReserves::complete`]'s transaction along with the message.
+
+use super::bitcoin::{Psbt, RTransaction};
+use super::coordinator::Coordinator;
+use super::signing::SigningState;
+use super::super_wallet::SuperWallet;
+use crate::{frb_generated::StreamSink, sink_wrap::SinkWrap};
+use anyhow::Result;
+use flutter_rust_bridge::frb;
+use frostsnap_coordinator::bitcoin::proof_of_reserves as coord_por;
+use frostsnap_core::{
+    bitcoin_transaction::TransactionTemplate, message::EncodedSignature, AccessStructureRef,
+    DeviceId, MasterAppkey,
+};
+
+/// An unsigned proof that a key holds every coin the wallet knows of.
+#[frb(opaque)]
+#[derive(Clone, Debug)]
+pub struct ProofOfReserves {
+    message: String,
+    template_tx: TransactionTemplate,
+    master_appkey: MasterAppkey,
+}
+
+impl ProofOfReserves {
+    #[frb(sync)]
+    pub fn message(&self) -> String {
+        self.message.clone()
+    }
+
+    /// How many of the key's coins the proof covers.
+    #[frb(sync)]
+    pub fn coin_count(&self) -> u32 {
+        self.template_tx
+            .as_seen_by(self.master_appkey)
+            .iter_our_inputs()
+            .count() as u32
+    }
+
+    #[frb(sync, type_64bit_int)]
+    pub fn value(&self) -> u64 {
+        self.template_tx
+            .as_seen_by(self.master_appkey)
+            .iter_our_inputs()
+            .map(|(_, input, _)| input.txout().value.to_sat())
+            .sum()
+    }
+
+    /// The unsigned proof, for auditors that take a PSBT.
+    #[frb(sync)]
+    pub fn psbt(&self) -> Psbt {
+        self.template_tx.to_psbt()
+    }
+
+    /// The signed proof. It can't be broadcast; it's for [`coord_por::verify`] on the auditor's
+    /// side.
+    #[frb(sync)]
+    pub fn complete(&self, signatures: Vec<EncodedSignature>) -> Result<RTransaction> {
+        Ok(self
+            .template_tx
+            .as_seen_by(self.master_appkey)
+            .to_signed_rust_bitcoin_tx(&signatures)?)
+    }
+}
+
+impl SuperWallet {
+    #[frb(sync)]
+    pub fn build_proof_of_reserves(
+        &self,
+        master_appkey: MasterAppkey,
+        message: String,
+    ) -> Result<ProofOfReserves> {
+        let template_tx = self
+            .inner
+            .lock()
+            .unwrap()
+            .build_proof_of_reserves(master_appkey, &message)?;
+        Ok(ProofOfReserves {
+            message,
+            template_tx,
+            master_appkey,
+        })
+    }
+}
+
+impl Coordinator {
+    pub fn start_signing_proof_of_reserves(
+        &self,
+        access_structure_ref: AccessStructureRef,
+        proof: &ProofOfReserves,
+        devices: Vec<DeviceId>,
+        sink: StreamSink<SigningState>,
+    ) -> Result<()> {
+        self.0.start_signing(
+            access_structure_ref,
+            devices.into_iter().collect(),
+            coord_por::proof_of_reserves_sign_task(&proof.message, proof.template_tx.clone()),
+            SinkWrap(sink),
+        )?;
+        Ok(())
+    }
+}
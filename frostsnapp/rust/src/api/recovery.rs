@@ -231,6 +231,20 @@ impl super::coordinator::Coordinator {
         Ok(())
     }
 
+    /// Move the share on `from` to `to`, e.g. to replace a damaged device. Returns whether it
+    /// finished.
+    pub fn migrate_share(
+        &self,
+        from: DeviceId,
+        to: DeviceId,
+        access_structure_ref: AccessStructureRef,
+        encryption_key: SymmetricKey,
+        device_name: String,
+    ) -> anyhow::Result<bool> {
+        self.0
+            .migrate_share(from, to, access_structure_ref, encryption_key, device_name)
+    }
+
     pub fn exit_recovery_mode(
         &self,
         device_id: DeviceId,
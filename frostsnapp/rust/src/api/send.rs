@@ -3,7 +3,7 @@
 //! numbers, and signing passes it back through [`SuperWallet::commit_send`]. The plan is a pure
 //! value — holding, dropping, or rebuilding one costs the wallet nothing.
 
-use bitcoin::OutPoint;
+use bitcoin::{Address, OutPoint};
 use flutter_rust_bridge::frb;
 use frostsnap_coordinator::bitcoin::send as coord_send;
 use frostsnap_coordinator::frostsnap_core::tweak::BitcoinAccount;
@@ -65,6 +65,23 @@ impl SuperWallet {
         )?))
     }
 
+    /// Plan moving every coin of the key to `destination` with no change, for storing as a
+    /// pre-signed sweep (see [`super::sweep_vault`]). Commit through [`Self::commit_send`] like
+    /// any send plan.
+    #[frb(sync)]
+    pub fn plan_sweep(
+        &self,
+        master_appkey: frostsnap_core::MasterAppkey,
+        destination: RustAutoOpaque<Address>,
+        feerate: f32,
+    ) -> anyhow::Result<SendPlan> {
+        Ok(SendPlan(self.inner.lock().unwrap().plan_sweep(
+            master_appkey,
+            &destination.blocking_read(),
+            feerate,
+        )?))
+    }
+
     /// The coins a future restore could miss and that are worth moving at `feerate` — the input
     /// set the nudge's remedy consolidates.
     #[frb(sync)]
@@ -6,14 +6,20 @@ use frostsnap_coordinator::bitcoin::chain_sync::{ChainClient, ElectrumConfig, SU
 pub use frostsnap_coordinator::bitcoin::chain_sync::{
     ChainStatus, ChainStatusState, ConnectionResult,
 };
+use frostsnap_coordinator::bitcoin::spending_policy::{past_spends, SpendingPolicyCheck};
+pub use frostsnap_coordinator::bitcoin::spending_policy::{LargeSpendRule, SpendingPolicy};
 pub use frostsnap_coordinator::bitcoin::tofu::verifier::UntrustedCertificate;
 use frostsnap_coordinator::persist::Persisted;
 pub use frostsnap_coordinator::settings::ElectrumEnabled;
 use frostsnap_coordinator::settings::Settings as RSettings;
+use frostsnap_core::coordinator::CoordFrostKey;
+use frostsnap_core::KeyId;
 use std::collections::HashMap;
 use std::path::PathBuf;
+use std::str::FromStr;
 use std::sync::{Arc, Mutex};
 use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use crate::frb_generated::StreamSink;
 use crate::sink_wrap::SinkWrap;
@@ -312,6 +318,65 @@ impl Settings {
         Ok(())
     }
 
+    /// `None` removes the key's policy.
+    pub fn set_spending_policy(
+        &mut self,
+        key_id: KeyId,
+        policy: Option<SpendingPolicy>,
+    ) -> Result<()> {
+        if let Some(policy) = &policy {
+            for address in policy.allowed_destinations.iter().flatten() {
+                bitcoin::Address::from_str(address)
+                    .map_err(|e| anyhow!("invalid allowed destination {address}: {e}"))?;
+            }
+        }
+        let mut db = self.db.lock().unwrap();
+        self.settings.mutate2(&mut *db, |settings, update| {
+            settings.set_spending_policy(key_id, policy, update);
+            Ok(())
+        })?;
+        Ok(())
+    }
+
+    #[frb(sync)]
+    pub fn spending_policy(&self, key_id: KeyId) -> Option<SpendingPolicy> {
+        self.settings.get_spending_policy(key_id).cloned()
+    }
+
+    /// Everything needed to check a spend from `frost_key` against its policy, or `None` if it
+    /// has no policy or isn't a bitcoin key. Errors if it has a policy but its wallet isn't
+    /// loaded, since without the history the limits can't be enforced.
+    pub(crate) fn spending_policy_check(
+        &self,
+        frost_key: &CoordFrostKey,
+    ) -> Result<Option<SpendingPolicyCheck>> {
+        let Some(policy) = self.settings.get_spending_policy(frost_key.key_id) else {
+            return Ok(None);
+        };
+        let Some(network) = frost_key.purpose.bitcoin_network() else {
+            return Ok(None);
+        };
+        let super_wallet = self.loaded_wallets.get(&network).ok_or_else(|| {
+            anyhow!("the {network} wallet must be loaded to check the spending policy")
+        })?;
+        let transactions = super_wallet
+            .inner
+            .lock()
+            .unwrap()
+            .list_transactions(frost_key.complete_key.master_appkey);
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|since| since.as_secs())
+            .unwrap_or(0);
+        Ok(Some(SpendingPolicyCheck {
+            policy: policy.clone(),
+            network,
+            history: past_spends(&transactions, now),
+            history_txids: transactions.iter().map(|tx| tx.txid).collect(),
+            now,
+        }))
+    }
+
     pub fn connect_to(&self, network: BitcoinNetwork, use_backup: bool) -> Result<()> {
         let chain_api = self
             .chain_clients
@@ -417,3 +482,18 @@ pub enum _ElectrumEnabled {
     PrimaryOnly,
     None,
 }
+
+#[frb(mirror(SpendingPolicy))]
+pub struct _SpendingPolicy {
+    pub daily_limit: Option<u64>,
+    pub weekly_limit: Option<u64>,
+    pub allowed_destinations: Option<Vec<String>>,
+    pub max_feerate: Option<f64>,
+    pub large_spend: Option<LargeSpendRule>,
+}
+
+#[frb(mirror(LargeSpendRule))]
+pub struct _LargeSpendRule {
+    pub above: u64,
+    pub min_signers: u16,
+}
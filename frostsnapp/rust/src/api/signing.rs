@@ -1,6 +1,6 @@
 use super::super_wallet::SuperWallet;
 use super::{
-    bitcoin::{Psbt, RTransaction, Transaction, TxOutInfo},
+    bitcoin::{Address, Psbt, RTransaction, Transaction, TxOutInfo},
     coordinator::Coordinator,
 };
 use crate::{frb_generated::StreamSink, sink_wrap::SinkWrap};
@@ -40,6 +40,7 @@ impl UnbroadcastedTx {
 #[frb(mirror(SigningState), unignore)]
 pub struct _SigningState {
     pub session_id: SignSessionId,
+    pub replaces: Option<SignSessionId>,
     pub got_shares: Vec<DeviceId>,
     pub needed_from: Vec<DeviceId>,
     pub finished_signatures: Option<Vec<EncodedSignature>>,
@@ -70,6 +71,7 @@ impl ActiveSignSessionExt for ActiveSignSession {
         let got_shares = self.received_from();
         let state = SigningState {
             session_id,
+            replaces: None,
             got_shares: got_shares.into_iter().collect(),
             needed_from: session_init.nonces.keys().copied().collect(),
             finished_signatures: None,
@@ -121,6 +123,11 @@ impl UnsignedTx {
             })
     }
 
+    #[frb(ignore)]
+    pub(crate) fn template_tx(&self) -> &TransactionTemplate {
+        &self.template_tx
+    }
+
     /// This transaction as its own key sees it. Cheap enough for the handful of UI-rate
     /// reads below, and it keeps the sendable form the only one stored.
     #[frb(ignore)]
@@ -284,11 +291,57 @@ impl Coordinator {
         Ok(())
     }
 
+    /// Have `devices` approve labelling `address` with `label` in the wallet's address book. Once
+    /// the session finishes the entry can be sent to the devices with `update_address_book`.
+    pub fn start_signing_address_book_entry(
+        &self,
+        access_structure_ref: AccessStructureRef,
+        address: &Address,
+        label: String,
+        devices: Vec<DeviceId>,
+        sink: StreamSink<SigningState>,
+    ) -> Result<()> {
+        self.0.start_signing(
+            access_structure_ref,
+            devices.into_iter().collect(),
+            WireSignTask::AddressBookEntry {
+                label,
+                spk: address.script_pubkey(),
+            },
+            SinkWrap(sink),
+        )?;
+        Ok(())
+    }
+
     #[frb(sync)]
     pub fn nonces_available(&self, id: DeviceId) -> u32 {
         self.0.nonces_available(id)
     }
 
+    /// Swap a signer that was lost or refused for another device that can sign, `substitute`
+    /// or the first eligible one. Returns the id of the session that replaces `session_id`.
+    pub fn substitute_signer(
+        &self,
+        session_id: SignSessionId,
+        failed: DeviceId,
+        substitute: Option<DeviceId>,
+    ) -> Result<SignSessionId> {
+        self.0.substitute_signer(session_id, failed, substitute)
+    }
+
+    #[frb(sync)]
+    pub fn eligible_substitutes(
+        &self,
+        session_id: SignSessionId,
+        failed: DeviceId,
+    ) -> Vec<DeviceId> {
+        self.0
+            .inner()
+            .eligible_substitutes(session_id, failed)
+            .into_iter()
+            .collect()
+    }
+
     pub fn try_restore_signing_session(
         &self,
         session_id: SignSessionId,
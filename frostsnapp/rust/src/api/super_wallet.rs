@@ -10,13 +10,15 @@ use bitcoin::Transaction as RTransaction;
 use bitcoin::Txid;
 pub use bitcoin::{Address, Network as BitcoinNetwork, Psbt};
 use flutter_rust_bridge::frb;
+pub use frostsnap_coordinator::address_book::AddressBookUpdateState;
 pub use frostsnap_coordinator::bitcoin::wallet::AddressInfo;
 pub use frostsnap_coordinator::bitcoin::wallet::PsbtValidationError;
 pub use frostsnap_coordinator::bitcoin::{chain_sync::ChainClient, wallet::CoordSuperWallet};
+pub use frostsnap_coordinator::verify_account::VerifyAccountProtocolState;
 pub use frostsnap_coordinator::verify_address::VerifyAddressProtocolState;
 
 use frostsnap_core::bitcoin_transaction::TransactionTemplate;
-use frostsnap_core::{DeviceId, KeyId, MasterAppkey};
+use frostsnap_core::{AccessStructureRef, DeviceId, KeyId, MasterAppkey, SymmetricKey};
 use std::collections::HashSet;
 use std::str::FromStr;
 use std::sync::atomic::AtomicBool;
@@ -339,12 +341,26 @@ pub struct _AddressInfo {
     pub derivation_path: Vec<u32>,
 }
 
+#[frb(mirror(AddressBookUpdateState), unignore)]
+pub struct _AddressBookUpdateState {
+    pub target_devices: Vec<DeviceId>,
+    pub connected_devices: std::collections::HashSet<DeviceId>,
+    pub confirmed_by: Vec<DeviceId>,
+    pub aborted: Option<String>,
+}
+
 #[frb(mirror(VerifyAddressProtocolState), unignore)]
 pub struct _VerifyAddressProtocolState {
     pub target_devices: Vec<DeviceId>,
     pub connected_devices: std::collections::HashSet<DeviceId>,
 }
 
+#[frb(mirror(VerifyAccountProtocolState), unignore)]
+pub struct _VerifyAccountProtocolState {
+    pub target_devices: Vec<DeviceId>,
+    pub connected_devices: std::collections::HashSet<DeviceId>,
+}
+
 impl super::coordinator::Coordinator {
     pub fn verify_address(
         &self,
@@ -356,4 +372,28 @@ impl super::coordinator::Coordinator {
             .verify_address(key_id, address_index, SinkWrap(sink))?;
         Ok(())
     }
+
+    pub fn verify_account(
+        &self,
+        key_id: KeyId,
+        account_index: u32,
+        encryption_key: SymmetricKey,
+        sink: StreamSink<VerifyAccountProtocolState>,
+    ) -> Result<()> {
+        self.0
+            .verify_account(key_id, account_index, encryption_key, SinkWrap(sink))?;
+        Ok(())
+    }
+
+    pub fn update_address_book(
+        &self,
+        access_structure_ref: AccessStructureRef,
+        address: &Address,
+        label: Option<String>,
+        sink: StreamSink<AddressBookUpdateState>,
+    ) -> Result<()> {
+        self.0
+            .update_address_book(access_structure_ref, address, label, SinkWrap(sink))?;
+        Ok(())
+    }
 }
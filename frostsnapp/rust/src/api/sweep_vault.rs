@@ -0,0 +1,119 @@
+//! Pre-signed emergency sweeps as Dart drives them: plan one per feerate with
+//! [`SuperWallet::plan_sweep`], sign each through the normal signing flow, then hand the
+//! signatures to [`SuperWallet::store_presigned_sweep`]. Broadcasting one later needs no devices.
+
+use super::signing::UnsignedTx;
+use super::super_wallet::SuperWallet;
+use anyhow::Result;
+use bitcoin::Txid;
+use flutter_rust_bridge::frb;
+use frostsnap_coordinator::bitcoin::sweep_vault::SweepStatus;
+use frostsnap_coordinator::frostsnap_core::{
+    message::EncodedSignature, MasterAppkey, SymmetricKey,
+};
+use std::str::FromStr;
+
+#[derive(Clone, Debug)]
+pub struct PresignedSweepInfo {
+    pub txid: String,
+    /// sats per vbyte
+    pub feerate: f64,
+    /// `None` if the script has no address form, which a sweep we planned never has.
+    pub destination: Option<String>,
+    pub input_count: u32,
+    pub status: PresignedSweepStatus,
+}
+
+/// See [`SweepStatus`].
+#[derive(Clone, Debug)]
+pub enum PresignedSweepStatus {
+    Ready,
+    LeavesBehind { value: u64 },
+    Broadcast,
+    Confirmed,
+    Replaces { txids: Vec<String> },
+    Stale,
+}
+
+impl From<SweepStatus> for PresignedSweepStatus {
+    fn from(status: SweepStatus) -> Self {
+        match status {
+            SweepStatus::Ready => Self::Ready,
+            SweepStatus::LeavesBehind { value } => Self::LeavesBehind { value },
+            SweepStatus::Broadcast => Self::Broadcast,
+            SweepStatus::Confirmed => Self::Confirmed,
+            SweepStatus::Replaces { txids } => Self::Replaces {
+                txids: txids.iter().map(ToString::to_string).collect(),
+            },
+            SweepStatus::Stale => Self::Stale,
+        }
+    }
+}
+
+impl SuperWallet {
+    /// Keep the signed sweep so it can be broadcast later. Fails if the wallet's coins changed
+    /// while it was being signed; plan and sign it again then.
+    pub fn store_presigned_sweep(
+        &self,
+        unsigned_tx: &UnsignedTx,
+        signatures: Vec<EncodedSignature>,
+        encryption_key: SymmetricKey,
+    ) -> Result<String> {
+        let txid = self.inner.lock().unwrap().store_presigned_sweep(
+            unsigned_tx.master_appkey,
+            unsigned_tx.template_tx(),
+            &signatures,
+            encryption_key,
+            &mut rand::thread_rng(),
+        )?;
+        Ok(txid.to_string())
+    }
+
+    /// Every stored sweep of the key with where it stands, cheapest first. Sweeps are only ever
+    /// removed by the user, so ones that are stale or leave coins behind are listed too.
+    #[frb(sync)]
+    pub fn presigned_sweeps(&self, master_appkey: MasterAppkey) -> Vec<PresignedSweepInfo> {
+        self.inner
+            .lock()
+            .unwrap()
+            .presigned_sweeps(master_appkey)
+            .into_iter()
+            .map(|(sweep, status)| PresignedSweepInfo {
+                txid: sweep.txid.to_string(),
+                feerate: sweep.feerate,
+                destination: bitcoin::Address::from_script(&sweep.destination, self.network)
+                    .ok()
+                    .map(|address| address.to_string()),
+                input_count: sweep.spends.len() as u32,
+                status: status.into(),
+            })
+            .collect()
+    }
+
+    pub fn broadcast_presigned_sweep(
+        &self,
+        master_appkey: MasterAppkey,
+        txid: String,
+        encryption_key: SymmetricKey,
+    ) -> Result<()> {
+        let txid = Txid::from_str(&txid)?;
+        let tx = self
+            .inner
+            .lock()
+            .unwrap()
+            .presigned_sweep_tx(txid, encryption_key)?;
+        self.broadcast_tx(master_appkey, tx)
+    }
+
+    pub fn discard_presigned_sweep(&self, txid: String) -> Result<()> {
+        let txid = Txid::from_str(&txid)?;
+        self.inner.lock().unwrap().discard_presigned_sweep(txid)
+    }
+
+    pub fn discard_presigned_sweeps(&self, master_appkey: MasterAppkey) -> Result<()> {
+        self.inner
+            .lock()
+            .unwrap()
+            .discard_presigned_sweeps(master_appkey)
+    }
+}
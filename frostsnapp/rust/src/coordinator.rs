@@ -3,30 +3,42 @@ use crate::api;
 use crate::api::backup_run::{BackupDevice, BackupRun};
 use crate::api::coordinator::KeyState;
 use crate::api::device_list::DeviceListUpdate;
+use crate::api::settings::Settings;
 use crate::device_list::DeviceList;
-use crate::frb_generated::StreamSink;
+use crate::frb_generated::{RustAutoOpaque, StreamSink};
 use anyhow::{anyhow, Result};
+use frostsnap_coordinator::address_book::{AddressBookUpdateState, UpdateAddressBookProtocol};
+use frostsnap_coordinator::audit_log::{self, AuditEvent};
 use frostsnap_coordinator::backup_run::BackupState;
+use frostsnap_coordinator::bitcoin::spending_policy::SpendingPolicyCheck;
 use frostsnap_coordinator::check_backup::{CheckBackupProtocol, CheckBackupState};
+use frostsnap_coordinator::coordinator_sync;
+use frostsnap_coordinator::device_diagnostics::{
+    DeviceDiagnosticsState, DiagnosticsLog, RunDeviceDiagnostics, StoredDiagnosticReport,
+};
+use frostsnap_coordinator::device_faults::{DeviceFaultWatcher, DeviceFaultsState};
 use frostsnap_coordinator::enter_physical_backup::{EnterPhysicalBackup, EnterPhysicalBackupState};
 use frostsnap_coordinator::erase_device::{EraseDevice, EraseDeviceState};
 use frostsnap_coordinator::firmware_upgrade::{
     FirmwareUpgradeConfirmState, FirmwareUpgradeProtocol,
 };
 use frostsnap_coordinator::frostsnap_comms::{
-    CoordinatorSendBody, CoordinatorSendMessage, Destination, Sha256Digest,
+    CommsMisc, CoordinatorSendBody, CoordinatorSendMessage, Destination, Sha256Digest,
 };
 use frostsnap_coordinator::frostsnap_persist::DeviceNames;
 use frostsnap_coordinator::nonce_replenish::NonceReplenishState;
 use frostsnap_coordinator::persist::Persisted;
+use frostsnap_coordinator::portable_backup;
 use frostsnap_coordinator::signing::SigningState;
+use frostsnap_coordinator::verify_account::{VerifyAccountProtocol, VerifyAccountProtocolState};
 use frostsnap_coordinator::verify_address::{VerifyAddressProtocol, VerifyAddressProtocolState};
 use frostsnap_coordinator::wait_for_single_device::{
     WaitForSingleDevice, WaitForSingleDeviceState,
 };
 use frostsnap_coordinator::{
-    AppMessageBody, DeviceChange, DeviceMode, FirmwareVersion, Sink, UiProtocol, UiStack,
-    UsbSender, UsbSerialManager, ValidatedFirmwareBin, WaitForToUserMessage,
+    AppMessageBody, DeviceChange, DeviceMode, FirmwarePatch, FirmwareVersion, ReleaseManifest,
+    Sink, UiProtocol, UiStack, UsbSender, UsbSerialManager, ValidatedFirmwareBin,
+    WaitForToUserMessage,
 };
 use frostsnap_core::coordinator::restoration::{
     PhysicalBackupPhase, RecoverShare, RestorationState, ToUserRestoration,
@@ -43,7 +55,7 @@ use frostsnap_core::{
 use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
 use std::ops::DerefMut;
 use std::str::FromStr;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, OnceLock};
 use std::thread::JoinHandle;
 use std::time::Duration;
 use tracing::{event, Level};
@@ -57,16 +69,22 @@ pub struct FfiCoordinator {
     ui_stack: Arc<Mutex<UiStack>>,
     pub(crate) usb_sender: UsbSender,
     firmware_bin: Option<ValidatedFirmwareBin>,
+    compressed_firmware: Arc<OnceLock<Vec<u8>>>,
+    firmware_patches: Vec<FirmwarePatch>,
+    release_manifest: Option<ReleaseManifest>,
     firmware_upgrade_progress: Arc<Mutex<Option<Box<dyn Sink<f32>>>>>,
     device_list: Arc<Mutex<DeviceList>>,
     device_list_stream: Arc<Mutex<Option<Box<dyn Sink<DeviceListUpdate>>>>>,
+    device_fault_watcher: Arc<Mutex<Option<DeviceFaultWatcher>>>,
     // // persisted things
     pub(crate) db: Arc<Mutex<rusqlite::Connection>>,
+    settings: RustAutoOpaque<Settings>,
     device_names: Arc<Mutex<Persisted<DeviceNames>>>,
     pub(crate) coordinator: Arc<Mutex<Persisted<FrostCoordinator>>>,
     // backup management
     pub(crate) backup_state: Arc<Mutex<Persisted<BackupState>>>,
     pub(crate) backup_run_streams: Arc<Mutex<BTreeMap<KeyId, StreamSink<BackupRun>>>>,
+    diagnostics_log: Arc<Mutex<Persisted<DiagnosticsLog>>>,
 }
 
 type Signal = Box<dyn Sink<()>>;
@@ -75,18 +93,25 @@ impl FfiCoordinator {
     pub fn new(
         db: Arc<Mutex<rusqlite::Connection>>,
         usb_manager: UsbSerialManager,
+        settings: RustAutoOpaque<Settings>,
     ) -> anyhow::Result<Self> {
         let mut db_ = db.lock().unwrap();
 
         event!(Level::DEBUG, "loading core coordinator");
-        let coordinator = Persisted::<FrostCoordinator>::new(&mut db_, ())?;
+        let mut coordinator = Persisted::<FrostCoordinator>::new(&mut db_, ())?;
+        coordinator.MUTATE_NO_PERSIST().coordinator_id = coordinator_sync::local_id(&db_)?.0;
         event!(Level::DEBUG, "loading device names");
         let device_names = Persisted::<DeviceNames>::new(&mut db_, ())?;
         event!(Level::DEBUG, "loading backup state");
         let backup_state = Persisted::<BackupState>::new(&mut db_, ())?;
+        event!(Level::DEBUG, "loading device diagnostics");
+        let diagnostics_log = Persisted::<DiagnosticsLog>::new(&mut db_, ())?;
 
         let usb_sender = usb_manager.usb_sender();
         let firmware_bin = usb_manager.upgrade_bin();
+        let compressed_firmware = usb_manager.compressed_firmware();
+        let firmware_patches = usb_manager.firmware_patches();
+        let release_manifest = usb_manager.release_manifest();
 
         let usb_manager = Mutex::new(Some(usb_manager));
         drop(db_);
@@ -100,13 +125,19 @@ impl FfiCoordinator {
             firmware_upgrade_progress: Default::default(),
             device_list: Default::default(),
             device_list_stream: Default::default(),
+            device_fault_watcher: Default::default(),
             usb_sender,
             firmware_bin,
+            compressed_firmware,
+            firmware_patches,
+            release_manifest,
             db,
+            settings,
             coordinator: Arc::new(Mutex::new(coordinator)),
             device_names: Arc::new(Mutex::new(device_names)),
             backup_state: Arc::new(Mutex::new(backup_state)),
             backup_run_streams: Default::default(),
+            diagnostics_log: Arc::new(Mutex::new(diagnostics_log)),
         })
     }
 
@@ -134,6 +165,9 @@ impl FfiCoordinator {
         let firmware_upgrade_progress = self.firmware_upgrade_progress.clone();
         let device_list = self.device_list.clone();
         let device_list_stream = self.device_list_stream.clone();
+        let device_fault_watcher = self.device_fault_watcher.clone();
+        let diagnostics_log = self.diagnostics_log.clone();
+        let firmware_digest = self.firmware_bin.map(|firmware_bin| firmware_bin.digest());
 
         let handle = std::thread::spawn(move || {
             loop {
@@ -166,6 +200,19 @@ impl FfiCoordinator {
                     }
 
                     *firmware_upgrade_progress_loop = None;
+                    if let Some(firmware_digest) = firmware_digest {
+                        let event = AuditEvent::FirmwareUpgrade {
+                            firmware_digest,
+                            error: error.as_ref().err().map(|e| e.to_string()),
+                        };
+                        if let Err(e) = audit_log::append(&db_loop.lock().unwrap(), event) {
+                            event!(
+                                Level::ERROR,
+                                error = e.to_string(),
+                                "failed to audit firmware upgrade"
+                            );
+                        }
+                    }
                     match error {
                         Ok(_) => {
                             event!(Level::INFO, "firmware upgrade completed")
@@ -310,11 +357,38 @@ impl FfiCoordinator {
                             }
                         }
                         AppMessageBody::Misc(comms_misc) => {
+                            if let CommsMisc::DiagnosticReport(report) = &comms_misc {
+                                let received_at = std::time::SystemTime::now()
+                                    .duration_since(std::time::UNIX_EPOCH)
+                                    .expect("system time is after the epoch")
+                                    .as_secs();
+                                let result =
+                                    diagnostics_log.lock().unwrap().mutate(&mut *db, |log| {
+                                        let mutation = log.record(
+                                            app_message.from,
+                                            received_at,
+                                            (**report).clone(),
+                                        );
+                                        Ok(((), vec![mutation]))
+                                    });
+                                if let Err(e) = result {
+                                    event!(
+                                        Level::ERROR,
+                                        from = app_message.from.to_string(),
+                                        error = e.to_string(),
+                                        "failed to store device diagnostic report"
+                                    );
+                                }
+                            }
                             ui_stack.process_comms_message(app_message.from, comms_misc);
                         }
                     }
                 }
 
+                if let Some(device_fault_watcher) = &mut *device_fault_watcher.lock().unwrap() {
+                    device_fault_watcher.update(&coordinator);
+                }
+
                 drop(coordinator);
                 drop(db);
 
@@ -430,11 +504,27 @@ impl FfiCoordinator {
     }
 
     pub fn nonce_replenish_request(&self, devices: BTreeSet<DeviceId>) -> NonceReplenishRequest {
+        let can_partition = {
+            let device_list = self.device_list.lock().unwrap();
+            devices
+                .iter()
+                .copied()
+                .filter(|device_id| {
+                    device_list
+                        .get_device(*device_id)
+                        .is_some_and(|device| device.firmware.features().nonce_partitions)
+                })
+                .collect::<BTreeSet<_>>()
+        };
         self.coordinator
             .lock()
             .unwrap()
-            .MUTATE_NO_PERSIST()
-            .maybe_request_nonce_replenishment(&devices, N_NONCE_STREAMS, &mut rand::thread_rng())
+            .maybe_request_nonce_replenishment(
+                &devices,
+                N_NONCE_STREAMS,
+                |device_id| can_partition.contains(&device_id),
+                &mut rand::thread_rng(),
+            )
     }
 
     pub fn replenish_nonces(
@@ -462,7 +552,16 @@ impl FfiCoordinator {
         task: WireSignTask,
         sink: impl Sink<SigningState>,
     ) -> anyhow::Result<()> {
+        let spending_policy = self.spending_policy_check(access_structure_ref.key_id)?;
         let mut coordinator = self.coordinator.lock().unwrap();
+        if let Some(spending_policy) = &spending_policy {
+            spending_policy.check(
+                &coordinator,
+                access_structure_ref.key_id,
+                &task,
+                devices.len(),
+            )?;
+        }
         let session_id =
             coordinator.staged_mutate(&mut self.db.lock().unwrap(), |coordinator| {
                 Ok(coordinator.start_sign(
@@ -492,6 +591,23 @@ impl FfiCoordinator {
         Ok(())
     }
 
+    /// The key's spending policy and wallet history, if it has a policy. Takes the settings and
+    /// wallet locks, so call it before locking the coordinator.
+    fn spending_policy_check(&self, key_id: KeyId) -> anyhow::Result<Option<SpendingPolicyCheck>> {
+        let Some(frost_key) = self
+            .coordinator
+            .lock()
+            .unwrap()
+            .get_frost_key(key_id)
+            .cloned()
+        else {
+            return Ok(None);
+        };
+        self.settings
+            .blocking_read()
+            .spending_policy_check(&frost_key)
+    }
+
     pub fn request_device_sign(
         &self,
         device_id: DeviceId,
@@ -519,6 +635,55 @@ impl FfiCoordinator {
         Ok(())
     }
 
+    /// Replace `failed` in the running signing session with `substitute`, or with the first
+    /// eligible device if none is given. The running dispatcher follows the replacement session.
+    pub fn substitute_signer(
+        &self,
+        session_id: SignSessionId,
+        failed: DeviceId,
+        substitute: Option<DeviceId>,
+    ) -> anyhow::Result<SignSessionId> {
+        let mut ui_stack = self.ui_stack.lock().unwrap();
+        let signing = ui_stack
+            .get_mut::<frostsnap_coordinator::signing::SigningDispatcher>()
+            .ok_or(anyhow!("UI was not in signing state"))?;
+
+        let mut db = self.db.lock().unwrap();
+        let mut coordinator = self.coordinator.lock().unwrap();
+        let substitute = match substitute {
+            Some(substitute) => substitute,
+            None => coordinator
+                .eligible_substitutes(session_id, failed)
+                .into_iter()
+                .next()
+                .ok_or(anyhow!("no other device can take this signer's place"))?,
+        };
+        let replacement_id = coordinator.staged_mutate(&mut *db, |coordinator| {
+            Ok(coordinator.substitute_signer(
+                session_id,
+                failed,
+                substitute,
+                &mut rand::thread_rng(),
+            )?)
+        })?;
+        let replacement = coordinator
+            .active_signing_sessions_by_ssid()
+            .get(&replacement_id)
+            .expect("replacement was just started");
+
+        let connected = self
+            .device_list
+            .lock()
+            .unwrap()
+            .devices()
+            .into_iter()
+            .filter(|device| device.device_mode() == DeviceMode::Ready)
+            .map(|device| device.id);
+        signing.switch_to_replacement(replacement, connected);
+
+        Ok(replacement_id)
+    }
+
     pub fn try_restore_signing_session(
         &self,
         session_id: SignSessionId,
@@ -545,6 +710,7 @@ impl FfiCoordinator {
                 active_sign_session,
                 sink,
             );
+        dispatcher.replaces = coordinator.sign_session_replaces(session_id);
         dispatcher.emit_state();
         self.start_protocol(dispatcher);
         Ok(())
@@ -574,8 +740,15 @@ impl FfiCoordinator {
                 .map(|device| device.id)
                 .collect();
 
-            let ui_protocol =
-                FirmwareUpgradeProtocol::new(devices, need_upgrade, firmware_bin, sink);
+            let ui_protocol = FirmwareUpgradeProtocol::new(
+                devices,
+                need_upgrade,
+                firmware_bin,
+                self.compressed_firmware.clone(),
+                self.firmware_patches.clone(),
+                self.release_manifest.as_ref(),
+                sink,
+            );
             ui_protocol.emit_state();
             ui_protocol
         };
@@ -761,12 +934,26 @@ impl FfiCoordinator {
         address_index: u32,
         stream: impl Sink<VerifyAddressProtocolState>,
     ) -> anyhow::Result<()> {
+        let db = self.db.lock().unwrap();
         let coordinator = self.coordinator.lock().unwrap();
 
         let address_index = frostsnap_core::tweak::NormalIndex::new(address_index)
             .ok_or_else(|| anyhow!("address index {address_index} is not a normal bip32 child"))?;
 
         let verify_address_messages = coordinator.verify_address(key_id, address_index)?;
+        audit_log::append(
+            &db,
+            AuditEvent::AddressShown {
+                key_id,
+                address_index: address_index.into(),
+                devices: verify_address_messages
+                    .target_devices
+                    .iter()
+                    .copied()
+                    .collect(),
+            },
+        )?;
+        drop(db);
 
         let ui_protocol = VerifyAddressProtocol::new(verify_address_messages.clone(), stream);
 
@@ -776,6 +963,81 @@ impl FfiCoordinator {
         Ok(())
     }
 
+    /// Have the devices of `key_id` show the master fingerprint and xpub of the account at
+    /// `account_index`, to compare with the wallet's descriptor.
+    pub fn verify_account(
+        &self,
+        key_id: KeyId,
+        account_index: u32,
+        encryption_key: SymmetricKey,
+        stream: impl Sink<VerifyAccountProtocolState>,
+    ) -> anyhow::Result<()> {
+        let db = self.db.lock().unwrap();
+        let coordinator = self.coordinator.lock().unwrap();
+
+        let account = frostsnap_core::tweak::BitcoinAccount {
+            kind: frostsnap_core::tweak::AccountKind::Segwitv1,
+            index: frostsnap_core::tweak::NormalIndex::new(account_index).ok_or_else(|| {
+                anyhow!("account index {account_index} is not a normal bip32 child")
+            })?,
+        };
+
+        let verify_account = coordinator.verify_account(key_id, account, encryption_key)?;
+        audit_log::append(
+            &db,
+            AuditEvent::AccountXpubShown {
+                key_id,
+                account_index,
+                devices: verify_account.target_devices.iter().copied().collect(),
+            },
+        )?;
+        drop(db);
+
+        let ui_protocol = VerifyAccountProtocol::new(verify_account, stream);
+
+        ui_protocol.emit_state();
+        self.start_protocol(ui_protocol);
+
+        Ok(())
+    }
+
+    /// Ask the devices of `access_structure_ref` to label `address` in their address book, or to
+    /// remove it if `label` is `None`. A new label has to have been approved with
+    /// `start_signing_address_book_entry` first.
+    pub fn update_address_book(
+        &self,
+        access_structure_ref: AccessStructureRef,
+        address: &bitcoin::Address,
+        label: Option<String>,
+        stream: impl Sink<AddressBookUpdateState>,
+    ) -> anyhow::Result<()> {
+        let db = self.db.lock().unwrap();
+        let coordinator = self.coordinator.lock().unwrap();
+
+        let update = coordinator.update_address_book(
+            access_structure_ref,
+            address.script_pubkey(),
+            label.clone(),
+        )?;
+        audit_log::append(
+            &db,
+            AuditEvent::AddressBookUpdateRequested {
+                key_id: access_structure_ref.key_id,
+                address: address.to_string(),
+                label,
+                devices: update.target_devices.iter().copied().collect(),
+            },
+        )?;
+        drop(db);
+
+        let ui_protocol = UpdateAddressBookProtocol::new(update, stream);
+
+        ui_protocol.emit_state();
+        self.start_protocol(ui_protocol);
+
+        Ok(())
+    }
+
     pub fn key_state(&self) -> api::coordinator::KeyState {
         key_state(&self.coordinator.lock().unwrap())
     }
@@ -971,6 +1233,42 @@ impl FfiCoordinator {
         device_list_stream.replace(Box::new(new_stream));
     }
 
+    pub fn sub_device_faults(&self, new_stream: impl Sink<DeviceFaultsState>) {
+        let coordinator = self.coordinator.lock().unwrap();
+        let watcher = DeviceFaultWatcher::new(&coordinator, new_stream);
+        self.device_fault_watcher.lock().unwrap().replace(watcher);
+    }
+
+    pub fn export_audit_log(&self) -> Result<String> {
+        audit_log::export_json(&self.db.lock().unwrap())
+    }
+
+    pub fn export_backup(&self, passphrase: &str) -> Result<Vec<u8>> {
+        portable_backup::export(
+            &self.db.lock().unwrap(),
+            passphrase,
+            &mut rand::thread_rng(),
+        )
+    }
+
+    pub fn verify_audit_log(&self) -> Result<()> {
+        let entries = audit_log::export(&self.db.lock().unwrap())?;
+        Ok(audit_log::verify(&entries)?)
+    }
+
+    pub fn set_device_quarantined(&self, id: DeviceId, quarantined: bool) -> Result<()> {
+        let mut db = self.db.lock().unwrap();
+        let mut coordinator = self.coordinator.lock().unwrap();
+        coordinator.staged_mutate(&mut *db, |coordinator| {
+            coordinator.set_device_quarantined(id, quarantined);
+            Ok(())
+        })?;
+        if let Some(device_fault_watcher) = &mut *self.device_fault_watcher.lock().unwrap() {
+            device_fault_watcher.update(&coordinator);
+        }
+        Ok(())
+    }
+
     pub fn device_at_index(&self, index: usize) -> Option<api::device_list::ConnectedDevice> {
         self.device_list.lock().unwrap().device_at_index(index)
     }
@@ -988,6 +1286,15 @@ impl FfiCoordinator {
         self.start_protocol(ui_protocol);
     }
 
+    /// Have the device check itself over. The coordinator loop stores the report when it arrives.
+    pub fn run_device_diagnostics(&self, id: DeviceId, sink: impl Sink<DeviceDiagnosticsState>) {
+        self.start_protocol(RunDeviceDiagnostics::new(id, sink));
+    }
+
+    pub fn device_diagnostics(&self, id: DeviceId) -> Vec<StoredDiagnosticReport> {
+        self.diagnostics_log.lock().unwrap().reports(id).to_vec()
+    }
+
     pub fn erase_all_devices(&self) {
         self.usb_sender.erase_all()
     }
@@ -1121,6 +1428,26 @@ impl FfiCoordinator {
             .map(|d| d.firmware)
             .ok_or_else(|| anyhow!("device not connected"))?;
 
+        // the protocol's state is sent from the device loop after it has let go of the db
+        let db = self.db.clone();
+        let sink = sink.inspect(move |state: &CheckBackupState| {
+            if let Some(valid) = state.backup_manually_entered_valid {
+                let event = AuditEvent::BackupChecked {
+                    key_id: access_structure_ref.key_id,
+                    access_structure_id: access_structure_ref.access_structure_id,
+                    device_id,
+                    valid,
+                };
+                if let Err(e) = audit_log::append(&db.lock().unwrap(), event) {
+                    event!(
+                        Level::ERROR,
+                        error = e.to_string(),
+                        "failed to audit backup check"
+                    );
+                }
+            }
+        });
+
         let proto = CheckBackupProtocol::new(
             self.coordinator.lock().unwrap().MUTATE_NO_PERSIST(),
             device_id,
@@ -1232,6 +1559,53 @@ impl FfiCoordinator {
         Ok(())
     }
 
+    /// Move `from`'s share to the device `to` so `to` can replace it. Both devices ask their user
+    /// to confirm and the share only ever passes through here encrypted to `to`.
+    // XXX: Cannot be called during another UI protocol
+    pub fn migrate_share(
+        &self,
+        from: DeviceId,
+        to: DeviceId,
+        access_structure_ref: AccessStructureRef,
+        encryption_key: SymmetricKey,
+        device_name: String,
+    ) -> Result<bool> {
+        let msgs = {
+            let mut coordinator = self.coordinator.lock().unwrap();
+            coordinator.MUTATE_NO_PERSIST().start_share_migration(
+                from,
+                to,
+                access_structure_ref,
+                encryption_key,
+            )?
+        };
+
+        self.usb_sender.send_from_core(msgs);
+
+        {
+            let mut db = self.db.lock().unwrap();
+            self.commit_device_name_locally(&mut db, to, &device_name);
+        }
+
+        let success = self.block_for_to_user_message([from, to], move |to_user| {
+            matches!(
+                to_user,
+                CoordinatorToUserMessage::Restoration(ToUserRestoration::ShareMigrated {
+                    from: got_from,
+                    to: got_to,
+                    access_structure_ref: got_ref,
+                    ..
+                }) if got_from == from && got_to == to && got_ref == access_structure_ref
+            )
+        });
+
+        if success {
+            self.emit_key_state();
+        }
+
+        Ok(success)
+    }
+
     fn block_for_to_user_message(
         &self,
         devices: impl IntoIterator<Item = DeviceId>,
@@ -85,7 +85,9 @@ impl DeviceList {
             DeviceChange::Connected {
                 id,
                 firmware_digest,
+                firmware_release,
                 latest_firmware_digest,
+                latest_firmware_release,
             } => {
                 use frostsnap_coordinator::FirmwareVersion;
 
@@ -93,10 +95,13 @@ impl DeviceList {
                     id,
                     api::ConnectedDevice {
                         firmware: FirmwareVersion::new(firmware_digest),
+                        firmware_release,
                         latest_firmware: latest_firmware_digest.map(FirmwareVersion::new),
+                        latest_firmware_release,
                         name: None,
                         id,
                         recovery_mode: api::RecoveryMode::Off,
+                        crash_reports: vec![],
                     },
                 );
             }
@@ -155,6 +160,19 @@ impl DeviceList {
                     })
                 }
             }
+            DeviceChange::CrashReported { id, reports } => {
+                let index = self.index_of(id);
+                if let Some(connected) = self.connected.get_mut(&id) {
+                    connected.crash_reports.extend(reports);
+                    if let Some(index) = index {
+                        self.outbox.push(api::DeviceListChange {
+                            kind: api::DeviceListChangeKind::CrashReported,
+                            index: index as u32,
+                            device: connected.clone(),
+                        });
+                    }
+                }
+            }
             DeviceChange::AppMessage(_) => { /* not relevant */ }
             DeviceChange::GenuineDevice { .. } => { /* not displayed in app yet */ }
         }
@@ -20,6 +20,25 @@ pub const FIRMWARE: Option<FirmwareBin> = Some(FirmwareBin::new(include_bytes!(c
     "/firmware.bin"
 ))));
 
+/// Patches from earlier firmware to [`FIRMWARE`]. See `BUNDLE_FIRMWARE_PATCHES` in `build.rs`.
+#[cfg(not(bundle_firmware_patches))]
+pub const FIRMWARE_PATCHES: &[&[u8]] = &[];
+
+#[cfg(bundle_firmware_patches)]
+pub const FIRMWARE_PATCHES: &[&[u8]] =
+    include!(concat!(env!("OUT_DIR"), "/firmware_patches.rs"));
+
+/// The signed list of official firmware releases and the key it has to be signed with. See
+/// `BUNDLE_RELEASE_MANIFEST` in `build.rs`.
+#[cfg(bundle_release_manifest)]
+pub const RELEASE_MANIFEST: Option<(&[u8], &str)> = Some((
+    include_bytes!(concat!(env!("OUT_DIR"), "/release_manifest.bin")),
+    include_str!(concat!(env!("OUT_DIR"), "/release_manifest_key.hex")),
+));
+
+#[cfg(not(bundle_release_manifest))]
+pub const RELEASE_MANIFEST: Option<(&[u8], &str)> = None;
+
 #[allow(unused)]
 /// meant to be replaced by something that's actually secure from the phone's secure element.
 const TEMP_KEY: SymmetricKey = SymmetricKey([42u8; 32]);
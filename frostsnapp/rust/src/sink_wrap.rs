@@ -3,13 +3,16 @@ use crate::{
     frb_generated::StreamSink,
 };
 use frostsnap_coordinator::{
+    address_book::AddressBookUpdateState,
     // bitcoin::chain_sync::ChainStatus,
     bitcoin::chain_sync::ChainStatus,
+    device_diagnostics::DeviceDiagnosticsState,
     erase_device::EraseDeviceState,
     firmware_upgrade::FirmwareUpgradeConfirmState,
     keygen::KeyGenState,
     nonce_replenish::NonceReplenishState,
     signing::SigningState,
+    verify_account::VerifyAccountProtocolState,
     verify_address::VerifyAddressProtocolState,
 };
 
@@ -30,6 +33,8 @@ macro_rules! bridge_sink {
 bridge_sink!(KeyGenState);
 bridge_sink!(FirmwareUpgradeConfirmState);
 bridge_sink!(VerifyAddressProtocolState);
+bridge_sink!(VerifyAccountProtocolState);
+bridge_sink!(AddressBookUpdateState);
 bridge_sink!(SigningState);
 bridge_sink!(bool);
 bridge_sink!(f32);
@@ -43,4 +48,5 @@ bridge_sink!(crate::api::backup_run::DisplayBackupState);
 bridge_sink!(crate::api::recovery::EnterPhysicalBackupState);
 bridge_sink!(crate::api::recovery::WaitForSingleDeviceState);
 bridge_sink!(EraseDeviceState);
+bridge_sink!(DeviceDiagnosticsState);
 bridge_sink!(crate::api::recovery::CheckBackupState);